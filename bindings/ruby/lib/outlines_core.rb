@@ -0,0 +1,3 @@
+# frozen_string_literal: true
+
+require_relative "outlines_core/outlines_core_ruby"
@@ -0,0 +1,248 @@
+//! Ruby bindings of `outlines_core`, exposing vocabularies, indexes, guides and the
+//! generation of regular expressions from JSON schemas, e.g. to compute token masks in
+//! Ruby-based serving or agent frameworks.
+//!
+//! The interface follows the Python bindings with Ruby naming: `Index#allowed_tokens`
+//! instead of `get_allowed_tokens`, `Guide#finished?` instead of `is_finished`, and errors
+//! raise `OutlinesCore::Error`.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use magnus::scan_args::scan_args;
+use magnus::value::Lazy;
+use magnus::{function, method, prelude::*, wrap, Error, ExceptionClass, RModule, Ruby, Value};
+use outlines_core::prelude::*;
+use outlines_core::{json_schema, mask};
+
+/// `OutlinesCore::Error`, raised by every failure of the library.
+static ERROR: Lazy<ExceptionClass> = Lazy::new(|ruby| {
+    ruby.define_module("OutlinesCore")
+        .and_then(|module| module.define_error("Error", ruby.exception_standard_error()))
+        .expect("Failed to define OutlinesCore::Error")
+});
+
+fn to_ruby_error(ruby: &Ruby, error: impl std::fmt::Display) -> Error {
+    Error::new(ruby.get_inner(&ERROR), error.to_string())
+}
+
+fn sorted(mut token_ids: Vec<TokenId>) -> Vec<u32> {
+    token_ids.sort_unstable();
+    token_ids.into_iter().map(|id| id.as_u32()).collect()
+}
+
+#[wrap(class = "OutlinesCore::Vocabulary", free_immediately, size)]
+struct RbVocabulary(Vocabulary);
+
+impl RbVocabulary {
+    /// Creates a vocabulary from eos token id and a hash of tokens to token ids.
+    fn new(ruby: &Ruby, eos_token_id: u32, map: HashMap<String, Vec<u32>>) -> Result<Self, Error> {
+        let map: HashMap<String, Vec<TokenId>> = map
+            .into_iter()
+            .map(|(token, ids)| (token, ids.into_iter().map(TokenId::new).collect()))
+            .collect();
+        Vocabulary::try_from((TokenId::new(eos_token_id), map))
+            .map(Self)
+            .map_err(|e| to_ruby_error(ruby, e))
+    }
+
+    /// Creates the vocabulary of a pre-trained model, at an optional revision.
+    fn from_pretrained(ruby: &Ruby, args: &[Value]) -> Result<Self, Error> {
+        let args = scan_args::<(String,), (Option<String>,), (), (), (), ()>(args)?;
+        let (model,) = args.required;
+        let (revision,) = args.optional;
+        let mut params = FromPretrainedParameters::default();
+        if let Some(revision) = revision {
+            params.revision = revision;
+        }
+        Vocabulary::from_pretrained(&model, Some(params))
+            .map(Self)
+            .map_err(|e| to_ruby_error(ruby, e))
+    }
+
+    fn eos_token_id(&self) -> u32 {
+        self.0.eos_token_id().as_u32()
+    }
+
+    fn get(&self, token: String) -> Option<Vec<u32>> {
+        self.0
+            .token_ids(token.as_bytes())
+            .map(|ids| ids.iter().map(|id| id.as_u32()).collect())
+    }
+
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    fn inspect(&self) -> String {
+        self.0.to_string()
+    }
+}
+
+#[wrap(class = "OutlinesCore::Index", free_immediately, size)]
+struct RbIndex(Arc<Index>);
+
+impl RbIndex {
+    /// Creates an index from a regex and vocabulary.
+    fn new(ruby: &Ruby, regex: String, vocabulary: &RbVocabulary) -> Result<Self, Error> {
+        Index::new(&regex, &vocabulary.0)
+            .map(|index| Self(Arc::new(index)))
+            .map_err(|e| to_ruby_error(ruby, e))
+    }
+
+    fn initial_state(&self) -> u32 {
+        self.0.initial_state().as_u32()
+    }
+
+    /// Returns allowed tokens in this state, sorted, or nil for an unknown state.
+    fn allowed_tokens(&self, state: u32) -> Option<Vec<u32>> {
+        self.0.allowed_tokens(&StateId::new(state)).map(sorted)
+    }
+
+    fn next_state(&self, state: u32, token_id: u32) -> Option<u32> {
+        self.0
+            .next_state(&StateId::new(state), &TokenId::new(token_id))
+            .map(|state| state.as_u32())
+    }
+
+    fn is_final_state(&self, state: u32) -> bool {
+        self.0.is_final_state(&StateId::new(state))
+    }
+
+    fn final_states(&self) -> Vec<u32> {
+        let mut states: Vec<u32> = self.0.final_states().iter().map(|s| s.as_u32()).collect();
+        states.sort_unstable();
+        states
+    }
+
+    fn vocab_size(&self) -> usize {
+        self.0.vocab_size()
+    }
+
+    fn inspect(&self) -> String {
+        self.0.to_string()
+    }
+}
+
+#[wrap(class = "OutlinesCore::Guide", free_immediately, size)]
+struct RbGuide(RefCell<Guide>);
+
+impl RbGuide {
+    /// Creates a guide walking through the index, with an optional `max_rollback`.
+    fn new(args: &[Value]) -> Result<Self, Error> {
+        let args = scan_args::<(&RbIndex,), (Option<usize>,), (), (), (), ()>(args)?;
+        let (index,) = args.required;
+        let (max_rollback,) = args.optional;
+        let guide = Guide::new(index.0.clone(), max_rollback.unwrap_or(32));
+        Ok(Self(RefCell::new(guide)))
+    }
+
+    fn state(&self) -> u32 {
+        self.0.borrow().state().as_u32()
+    }
+
+    /// Gets the allowed tokens for the current state, sorted.
+    fn tokens(ruby: &Ruby, rb_self: &Self) -> Result<Vec<u32>, Error> {
+        let tokens = rb_self.0.borrow().allowed_tokens();
+        tokens.map(sorted).map_err(|e| to_ruby_error(ruby, e))
+    }
+
+    /// Gets the allowed tokens for the current state as a bitmask of 32-bit words.
+    fn mask(ruby: &Ruby, rb_self: &Self) -> Result<Vec<u32>, Error> {
+        let guide = rb_self.0.borrow();
+        let tokens = guide.allowed_tokens().map_err(|e| to_ruby_error(ruby, e))?;
+        let mut words = vec![0; mask::mask_len(guide.index().vocab_size())];
+        mask::set_tokens(&mut words, &tokens);
+        Ok(words)
+    }
+
+    /// Moves to the next state by the token id and returns the allowed tokens there.
+    fn advance(ruby: &Ruby, rb_self: &Self, token_id: u32) -> Result<Vec<u32>, Error> {
+        let mut guide = rb_self.0.borrow_mut();
+        guide
+            .advance(TokenId::new(token_id))
+            .and_then(|_| guide.allowed_tokens())
+            .map(sorted)
+            .map_err(|e| to_ruby_error(ruby, e))
+    }
+
+    fn rollback_state(ruby: &Ruby, rb_self: &Self, n: usize) -> Result<(), Error> {
+        let rollback = rb_self.0.borrow_mut().rollback_state(n);
+        rollback.map_err(|e| to_ruby_error(ruby, e))
+    }
+
+    fn is_finished(&self) -> bool {
+        self.0.borrow().is_finished()
+    }
+
+    fn reset(&self) {
+        self.0.borrow_mut().reset()
+    }
+
+    fn inspect(&self) -> String {
+        let guide = self.0.borrow();
+        format!(
+            "Guide object with the state={} and {}",
+            guide.state(),
+            guide.index()
+        )
+    }
+}
+
+/// Generates a regular expression from a JSON schema string, with an optional whitespace
+/// pattern and maximum recursion depth.
+fn build_regex_from_schema(ruby: &Ruby, args: &[Value]) -> Result<String, Error> {
+    let args = scan_args::<(String,), (Option<String>, Option<usize>), (), (), (), ()>(args)?;
+    let (schema,) = args.required;
+    let (whitespace_pattern, max_recursion_depth) = args.optional;
+    json_schema::regex_from_str(
+        &schema,
+        whitespace_pattern.as_deref(),
+        max_recursion_depth.or(Some(3)),
+    )
+    .map_err(|e| to_ruby_error(ruby, e))
+}
+
+#[magnus::init]
+fn init(ruby: &Ruby) -> Result<(), Error> {
+    let module: RModule = ruby.define_module("OutlinesCore")?;
+    Lazy::force(&ERROR, ruby);
+    module.define_module_function(
+        "build_regex_from_schema",
+        function!(build_regex_from_schema, -1),
+    )?;
+
+    let class = module.define_class("Vocabulary", ruby.class_object())?;
+    class.define_singleton_method("new", function!(RbVocabulary::new, 2))?;
+    class.define_singleton_method(
+        "from_pretrained",
+        function!(RbVocabulary::from_pretrained, -1),
+    )?;
+    class.define_method("eos_token_id", method!(RbVocabulary::eos_token_id, 0))?;
+    class.define_method("[]", method!(RbVocabulary::get, 1))?;
+    class.define_method("size", method!(RbVocabulary::len, 0))?;
+    class.define_method("inspect", method!(RbVocabulary::inspect, 0))?;
+
+    let class = module.define_class("Index", ruby.class_object())?;
+    class.define_singleton_method("new", function!(RbIndex::new, 2))?;
+    class.define_method("initial_state", method!(RbIndex::initial_state, 0))?;
+    class.define_method("allowed_tokens", method!(RbIndex::allowed_tokens, 1))?;
+    class.define_method("next_state", method!(RbIndex::next_state, 2))?;
+    class.define_method("final_state?", method!(RbIndex::is_final_state, 1))?;
+    class.define_method("final_states", method!(RbIndex::final_states, 0))?;
+    class.define_method("vocab_size", method!(RbIndex::vocab_size, 0))?;
+    class.define_method("inspect", method!(RbIndex::inspect, 0))?;
+
+    let class = module.define_class("Guide", ruby.class_object())?;
+    class.define_singleton_method("new", function!(RbGuide::new, -1))?;
+    class.define_method("state", method!(RbGuide::state, 0))?;
+    class.define_method("tokens", method!(RbGuide::tokens, 0))?;
+    class.define_method("mask", method!(RbGuide::mask, 0))?;
+    class.define_method("advance", method!(RbGuide::advance, 1))?;
+    class.define_method("rollback_state", method!(RbGuide::rollback_state, 1))?;
+    class.define_method("finished?", method!(RbGuide::is_finished, 0))?;
+    class.define_method("reset", method!(RbGuide::reset, 0))?;
+    class.define_method("inspect", method!(RbGuide::inspect, 0))?;
+    Ok(())
+}
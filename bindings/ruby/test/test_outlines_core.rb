@@ -0,0 +1,44 @@
+# frozen_string_literal: true
+
+require "json"
+require "minitest/autorun"
+require "outlines_core"
+
+class TestOutlinesCore < Minitest::Test
+  def setup
+    @vocabulary = OutlinesCore::Vocabulary.new(3, { "1" => [1], "2" => [2] })
+    @index = OutlinesCore::Index.new("[1-9]", @vocabulary)
+  end
+
+  def test_vocabulary
+    assert_equal 3, @vocabulary.eos_token_id
+    assert_equal [1], @vocabulary["1"]
+    assert_nil @vocabulary["3"]
+  end
+
+  def test_index
+    state = @index.initial_state
+    assert_equal [1, 2], @index.allowed_tokens(state)
+    next_state = @index.next_state(state, 2)
+    assert @index.final_state?(next_state)
+    assert_equal [next_state], @index.final_states
+    assert_nil @index.allowed_tokens(1000)
+  end
+
+  def test_guide
+    guide = OutlinesCore::Guide.new(@index)
+    assert_equal [1, 2], guide.tokens
+    assert_equal [0b110], guide.mask
+    assert_equal [3], guide.advance(1)
+    assert guide.finished?
+    guide.rollback_state(1)
+    assert_equal [1, 2], guide.tokens
+    assert_raises(OutlinesCore::Error) { guide.advance(3) }
+  end
+
+  def test_build_regex_from_schema
+    schema = { type: "integer" }.to_json
+    assert_equal "(-)?(0|[1-9][0-9]*)", OutlinesCore.build_regex_from_schema(schema)
+    assert_raises(OutlinesCore::Error) { OutlinesCore.build_regex_from_schema("{") }
+  end
+end
@@ -2,8 +2,17 @@ import sys
 
 # kernels is not reexported as it should remain an optional dependency
 from . import _json_schema as json_schema
-from .outlines_core import Guide, Index, Vocabulary
+from . import _regex as regex
+from .outlines_core import (
+    Guide,
+    Index,
+    Proposal,
+    Vocabulary,
+    capabilities,
+    mask_to_list,
+)
 
-# Register json_schema in sys.modules so "from outlines_core.json_schema
+# Register json_schema and regex in sys.modules so "from outlines_core.json_schema
 # import ..." works
 sys.modules["outlines_core.json_schema"] = json_schema
+sys.modules["outlines_core.regex"] = regex
@@ -15,6 +15,12 @@ URI = _json_schema.URI
 UUID = _json_schema.UUID
 WHITESPACE = _json_schema.WHITESPACE
 build_regex_from_schema = _json_schema.build_regex_from_schema
+build_jsonl_regex_from_schema = _json_schema.build_jsonl_regex_from_schema
+build_regex_from_schemas = _json_schema.build_regex_from_schemas
+build_regex_from_schema_with_report = _json_schema.build_regex_from_schema_with_report
+describe_regex = _json_schema.describe_regex
+partial_instance = _json_schema.partial_instance
+repair = _json_schema.repair
 
 __all__ = [
     "BOOLEAN",
@@ -31,4 +37,10 @@ __all__ = [
     "UUID",
     "WHITESPACE",
     "build_regex_from_schema",
+    "build_jsonl_regex_from_schema",
+    "build_regex_from_schemas",
+    "build_regex_from_schema_with_report",
+    "describe_regex",
+    "partial_instance",
+    "repair",
 ]
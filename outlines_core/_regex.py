@@ -0,0 +1,10 @@
+# Re-export from Rust extension
+from .outlines_core import regex as _regex
+
+build_contains_regex = _regex.build_contains_regex
+sample = _regex.sample
+
+__all__ = [
+    "build_contains_regex",
+    "sample",
+]
@@ -96,15 +96,14 @@ def fill_next_token_bitmask(guide: Guide, mask: np.ndarray) -> None:
     Arguments:
         guide (Guide): An instance of the `Guide` class that provides the current guidance state.
         mask (np.ndarray): A 2D tensor of type `torch.int32` where the bitmask will be written.
-                             The tensor must be contiguous, have a single batch dimension
-                             (shape[0] == 1), and reside on the CPU.
+                             The tensor must have a single batch dimension (shape[0] == 1)
+                             and reside on the CPU, it doesn't need to be contiguous.
 
     Raises:
         ValueError: If any of the following conditions are not met:
                     - `mask.dtype` is not `torch.int32`
                     - `mask` is not a 2D tensor
                     - `mask` does not have a single batch dimension (shape[0] != 1)
-                    - `mask` is not contiguous in memory
                     - `mask` is not on the CPU device
 
     Returns:
@@ -122,9 +121,14 @@ def fill_next_token_bitmask(guide: Guide, mask: np.ndarray) -> None:
         raise ValueError(
             f"Invalid batch size: Batch mask writes are not supported. Expected shape[0] == 1, but got shape {mask.shape}."
         )
-    elif not mask.flags["C_CONTIGUOUS"]:
+    elif mask.strides[1] <= 0 or mask.strides[1] % mask.itemsize != 0:
         raise ValueError(
-            "Mask array must be contiguous in memory. Use `np.ascontiguousarray(mask)`."
+            "Mask array elements must be evenly spaced in memory. Use `np.ascontiguousarray(mask)`."
         )
 
-    return guide.write_mask_into(mask.ctypes.data, mask.size, mask.itemsize)
+    return guide.write_mask_into(
+        mask.ctypes.data,
+        mask.size,
+        mask.itemsize,
+        stride=mask.strides[1] // mask.itemsize,
+    )
@@ -140,7 +140,7 @@ def fill_next_token_bitmask(guide: Guide, mask: torch.Tensor) -> None:
         raise ValueError(
             f"Invalid batch size: Batch mask writes are not supported. Expected shape[0] == 1, but got shape {mask.shape}."
         )
-    elif not mask.is_contiguous():
+    elif mask.stride(1) != 1:
         raise ValueError(
             "Mask array must be contiguous in memory. Use `mask.contiguous()` to fix it."
         )
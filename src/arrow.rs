@@ -0,0 +1,152 @@
+//! Exports the token-level automaton of an [`Index`] as Arrow record batches.
+//!
+//! Two tables are produced:
+//! - transitions: one row per `(state, token_id) -> next_state` transition,
+//!   see [`transitions_schema`].
+//! - states: one row per state with its flags and allowed tokens mask, see [`states_schema`].
+//!
+//! Record batches could then be written to Parquet or IPC files, or queried directly
+//! in notebooks and warehouse pipelines.
+
+use std::sync::Arc;
+
+use arrow_array::builder::{ListBuilder, UInt32Builder};
+use arrow_array::{ArrayRef, BooleanArray, RecordBatch, UInt32Array};
+use arrow_schema::{DataType, Field, Schema, SchemaRef};
+
+use crate::index::Index;
+use crate::prelude::*;
+use crate::Result;
+
+/// Schema of the transitions table: `state`, `token_id`, `next_state`.
+pub fn transitions_schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new("state", DataType::UInt32, false),
+        Field::new("token_id", DataType::UInt32, false),
+        Field::new("next_state", DataType::UInt32, false),
+    ]))
+}
+
+/// Schema of the states table: `state`, `is_initial`, `is_final`, `allowed_tokens`.
+pub fn states_schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new("state", DataType::UInt32, false),
+        Field::new("is_initial", DataType::Boolean, false),
+        Field::new("is_final", DataType::Boolean, false),
+        Field::new(
+            "allowed_tokens",
+            DataType::List(Arc::new(Field::new_list_field(DataType::UInt32, true))),
+            false,
+        ),
+    ]))
+}
+
+/// Exports all transitions of the index, sorted by state and token id.
+pub fn transitions_record_batch(index: &Index) -> Result<RecordBatch> {
+    let mut rows: Vec<(StateId, TokenId, StateId)> = index
+        .transitions()
+        .iter()
+        .flat_map(|(&state, map)| map.iter().map(move |(&token, &next)| (state, token, next)))
+        .collect();
+    rows.sort_unstable();
+
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(UInt32Array::from_iter_values(
+            rows.iter().map(|r| r.0.as_u32()),
+        )),
+        Arc::new(UInt32Array::from_iter_values(
+            rows.iter().map(|r| r.1.as_u32()),
+        )),
+        Arc::new(UInt32Array::from_iter_values(
+            rows.iter().map(|r| r.2.as_u32()),
+        )),
+    ];
+    Ok(RecordBatch::try_new(transitions_schema(), columns)?)
+}
+
+/// Exports all states of the index, sorted by state id, with their allowed tokens.
+pub fn states_record_batch(index: &Index) -> Result<RecordBatch> {
+    let mut states: Vec<StateId> = index
+        .transitions()
+        .keys()
+        .chain(index.final_states().iter())
+        .chain(std::iter::once(&index.initial_state()))
+        .copied()
+        .collect();
+    states.sort_unstable();
+    states.dedup();
+
+    let mut allowed_tokens = ListBuilder::new(UInt32Builder::new());
+    for state in &states {
+        let mut tokens = index.allowed_tokens(state).unwrap_or_default();
+        tokens.sort_unstable();
+        for token in tokens {
+            allowed_tokens.values().append_value(token.as_u32());
+        }
+        allowed_tokens.append(true);
+    }
+
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(UInt32Array::from_iter_values(
+            states.iter().map(|s| s.as_u32()),
+        )),
+        Arc::new(BooleanArray::from_iter(
+            states.iter().map(|s| Some(*s == index.initial_state())),
+        )),
+        Arc::new(BooleanArray::from_iter(
+            states.iter().map(|s| Some(index.is_final_state(s))),
+        )),
+        Arc::new(allowed_tokens.finish()),
+    ];
+    Ok(RecordBatch::try_new(states_schema(), columns)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow_array::cast::AsArray;
+    use arrow_array::types::UInt32Type;
+
+    use super::*;
+
+    #[test]
+    fn export_index_to_record_batches() {
+        let regex = "0|[1-9][0-9]*";
+        let mut vocabulary = Vocabulary::new(4);
+        for (token, token_id) in [("blah", 0), ("1a", 1), ("2", 2), ("0", 3)] {
+            vocabulary
+                .try_insert(token, token_id as u32)
+                .expect("Insert failed");
+        }
+        let index = Index::new(regex, &vocabulary).expect("Index failed");
+
+        let transitions = transitions_record_batch(&index).expect("Export failed");
+        let expected: usize = index.transitions().values().map(|m| m.len()).sum();
+        assert_eq!(transitions.num_rows(), expected);
+        assert_eq!(transitions.schema(), transitions_schema());
+
+        let states = states_record_batch(&index).expect("Export failed");
+        assert_eq!(states.num_rows(), 4);
+        let ids = states.column(0).as_primitive::<UInt32Type>();
+        let is_final = states.column(2).as_boolean();
+        for (i, state) in ids.values().iter().enumerate() {
+            assert_eq!(
+                is_final.value(i),
+                index.is_final_state(&StateId::new(*state))
+            );
+        }
+        let allowed = states.column(3).as_list::<i32>();
+        let initial = ids
+            .values()
+            .iter()
+            .position(|s| index.initial_state() == *s)
+            .expect("Initial state missing");
+        let initial_tokens = allowed.value(initial);
+        assert_eq!(
+            initial_tokens
+                .as_primitive::<UInt32Type>()
+                .values()
+                .to_vec(),
+            vec![2, 3]
+        );
+    }
+}
@@ -0,0 +1,251 @@
+//! Compilation of many JSON schemas against the same vocabulary.
+//!
+//! [`BatchCompiler`] prepares the vocabulary tokens once and shares them between the
+//! compilations of all schemas, which run in parallel threads.
+//!
+//! ```rust
+//! # use outlines_core::Error;
+//! use outlines_core::batch::BatchCompiler;
+//! use outlines_core::prelude::*;
+//!
+//! # fn main() -> Result<(), Error> {
+//! let mut vocabulary = Vocabulary::new(0);
+//! for (token, token_id) in [("1", 1), ("2", 2), ("\"", 3), ("a", 4)] {
+//!     vocabulary.try_insert(token, token_id)?;
+//! }
+//!
+//! let schemas = [r#"{"type": "integer"}"#, r#"{"const": "a"}"#];
+//! let output = BatchCompiler::new().compile_all(&schemas, &vocabulary);
+//! assert_eq!(output.report.compiled, 2);
+//! for index in output.indexes {
+//!     let index = index?;
+//!     assert!(!index.final_states().is_empty());
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::index::{Index, ResourceLimits, TokenTable};
+use crate::json_schema;
+use crate::vocabulary::Vocabulary;
+use crate::Result;
+
+/// Compiled index of a schema with its compilation time.
+type Compiled = (Result<Index>, Duration);
+
+/// Compiles JSON schemas into indexes, sharing the vocabulary preparation between them.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BatchCompiler {
+    whitespace_pattern: Option<String>,
+    max_recursion_depth: Option<usize>,
+    threads: Option<usize>,
+    limits: ResourceLimits,
+}
+
+/// Results of [`BatchCompiler::compile_all`].
+#[derive(Debug)]
+pub struct BatchOutput {
+    /// Compiled indexes, in the order of the schemas.
+    pub indexes: Vec<Result<Index>>,
+    /// Statistics of the whole batch.
+    pub report: BatchReport,
+}
+
+/// Statistics of a batch compilation.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BatchReport {
+    /// Time spent preparing the vocabulary, shared by all schemas.
+    pub preparation_time: Duration,
+    /// Compilation time of each schema, in the order of the schemas.
+    pub compilation_times: Vec<Duration>,
+    /// Wall-clock time of the whole batch, including the preparation.
+    pub total_time: Duration,
+    /// Number of successfully compiled schemas.
+    pub compiled: usize,
+    /// Number of schemas failed to compile.
+    pub failed: usize,
+    /// Total number of states with transitions over compiled indexes.
+    pub states: usize,
+    /// Total number of transitions over compiled indexes.
+    pub transitions: usize,
+}
+
+impl BatchCompiler {
+    /// Creates a compiler with default options of [`json_schema::regex_from_str`], running
+    /// as many threads as the available parallelism.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the whitespace pattern used by all schemas.
+    pub fn with_whitespace_pattern(self, whitespace_pattern: impl Into<String>) -> Self {
+        Self {
+            whitespace_pattern: Some(whitespace_pattern.into()),
+            ..self
+        }
+    }
+
+    /// Sets the maximum recursion depth used by all schemas.
+    pub fn with_max_recursion_depth(self, max_recursion_depth: usize) -> Self {
+        Self {
+            max_recursion_depth: Some(max_recursion_depth),
+            ..self
+        }
+    }
+
+    /// Sets the number of compilation threads, at least one is used.
+    pub fn with_threads(self, threads: usize) -> Self {
+        Self {
+            threads: Some(threads.max(1)),
+            ..self
+        }
+    }
+
+    /// Sets the limits of the resources of each compilation, e.g. of untrusted schemas, see
+    /// [`Index::with_limits`].
+    pub fn with_limits(self, limits: ResourceLimits) -> Self {
+        Self { limits, ..self }
+    }
+
+    /// Compiles each schema into an index of the vocabulary.
+    ///
+    /// Failure of one schema doesn't affect the others.
+    pub fn compile_all<S: AsRef<str> + Sync>(
+        &self,
+        schemas: &[S],
+        vocabulary: &Vocabulary,
+    ) -> BatchOutput {
+        let start = Instant::now();
+        let table = TokenTable::new(vocabulary);
+        let preparation_time = start.elapsed();
+
+        let results: Vec<Mutex<Option<Compiled>>> =
+            schemas.iter().map(|_| Mutex::new(None)).collect();
+        let next = AtomicUsize::new(0);
+        let threads = self
+            .threads
+            .unwrap_or_else(|| std::thread::available_parallelism().map_or(1, NonZeroUsize::get))
+            .min(schemas.len());
+        std::thread::scope(|scope| {
+            for _ in 0..threads {
+                scope.spawn(|| loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(schema) = schemas.get(i) else {
+                        break;
+                    };
+                    let start = Instant::now();
+                    let index = self.compile(schema.as_ref(), &table);
+                    *results[i].lock().unwrap_or_else(|e| e.into_inner()) =
+                        Some((index, start.elapsed()));
+                });
+            }
+        });
+
+        let mut report = BatchReport {
+            preparation_time,
+            ..Default::default()
+        };
+        let mut indexes = Vec::with_capacity(schemas.len());
+        for result in results {
+            let (index, time) = result
+                .into_inner()
+                .unwrap_or_else(|e| e.into_inner())
+                .expect("Every schema is compiled");
+            match &index {
+                Ok(index) => {
+                    report.compiled += 1;
+                    report.states += index.num_states();
+                    report.transitions += index.num_transitions();
+                }
+                Err(_) => report.failed += 1,
+            }
+            report.compilation_times.push(time);
+            indexes.push(index);
+        }
+        report.total_time = start.elapsed();
+        BatchOutput { indexes, report }
+    }
+
+    fn compile(&self, schema: &str, table: &TokenTable) -> Result<Index> {
+        let regex = json_schema::regex_from_str(
+            schema,
+            self.whitespace_pattern.as_deref(),
+            self.max_recursion_depth,
+        )?;
+        Index::with_token_table(&regex, table, &self.limits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Error;
+
+    #[test]
+    fn compile_all_schemas() {
+        let mut vocabulary = Vocabulary::new(0);
+        for (token, token_id) in [("1", 1), ("2", 2), ("\"", 3), ("a", 4), ("b", 5)] {
+            vocabulary
+                .try_insert(token, token_id)
+                .expect("Insert failed");
+        }
+        let schemas = [
+            r#"{"type": "integer"}"#,
+            r#"{"const": "c"}"#,
+            "not a schema",
+            r#"{"enum": ["a", "ba"]}"#,
+        ];
+
+        let output = BatchCompiler::new()
+            .with_threads(2)
+            .compile_all(&schemas, &vocabulary);
+        assert_eq!(output.indexes.len(), 4);
+        assert_eq!(output.report.compiled, 2);
+        assert_eq!(output.report.failed, 2);
+        assert_eq!(output.report.compilation_times.len(), 4);
+        assert!(matches!(
+            output.indexes[1],
+            Err(Error::IncompatibleVocabulary { .. })
+        ));
+        assert!(matches!(output.indexes[2], Err(Error::SerdeJsonError(_))));
+
+        for (i, schema) in [(0, schemas[0]), (3, schemas[3])] {
+            let regex = json_schema::regex_from_str(schema, None, None).expect("Regex failed");
+            let expected = Index::new(&regex, &vocabulary).expect("Index failed");
+            let compiled = output.indexes[i].as_ref().expect("Index failed");
+            assert_eq!(compiled, &expected);
+        }
+
+        let limits = ResourceLimits::default().with_max_states(3);
+        let output = BatchCompiler::new()
+            .with_limits(limits)
+            .compile_all(&[schemas[3], schemas[0]], &vocabulary);
+        assert!(matches!(
+            output.indexes[0],
+            Err(Error::ResourceLimitExceeded { .. })
+        ));
+        assert!(output.indexes[1].is_ok());
+    }
+
+    #[test]
+    fn report_counted_transitions_without_building_them() {
+        let vocabulary = Vocabulary::fixture();
+        let schemas = [r#"{"type": "string", "maxLength": 2000}"#];
+        let output = BatchCompiler::new().compile_all(&schemas, &vocabulary);
+        let index = output.indexes[0].as_ref().expect("Index failed");
+        assert!(index.footprint().counted > 0);
+        assert_eq!(index.footprint().caches, 0);
+
+        let transitions = index.transitions();
+        assert_eq!(output.report.states, transitions.len());
+        assert_eq!(
+            output.report.transitions,
+            transitions.values().map(|t| t.len()).sum::<usize>()
+        );
+    }
+}
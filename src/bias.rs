@@ -0,0 +1,183 @@
+//! Biases of transitions, guiding the generation softly besides the masks of allowed tokens.
+//!
+//! [`TransitionBiases`] attaches `f32` biases to transitions of an [`Index`], which are
+//! exported for each state as a sparse list of `(token, bias)` pairs, to be added to the
+//! logits of the allowed tokens, e.g. to penalize whitespace tokens:
+//!
+//! ```rust
+//! # use outlines_core::Error;
+//! use outlines_core::bias::TransitionBiases;
+//! use outlines_core::prelude::*;
+//!
+//! # fn main() -> Result<(), Error> {
+//! let mut vocabulary = Vocabulary::new(3);
+//! for (token, token_id) in [("1", 1), (" ", 2)] {
+//!     vocabulary.try_insert(token, token_id)?;
+//! }
+//! let index = Index::new("1 ?1", &vocabulary)?;
+//!
+//! let space = TokenId::new(2);
+//! let biases = TransitionBiases::for_tokens(&index, &[(space, -1.5)]);
+//! let state = index
+//!     .next_state(&index.initial_state(), &TokenId::new(1))
+//!     .expect("No next state");
+//! assert_eq!(biases.biases(&state), &[(space, -1.5)]);
+//! assert!(biases.biases(&index.initial_state()).is_empty());
+//! # Ok(())
+//! # }
+//! ```
+
+use bincode::{Decode, Encode};
+use rustc_hash::FxHashMap as HashMap;
+
+use crate::index::Index;
+use crate::prelude::*;
+
+/// Sparse biases of the transitions of an index, by state.
+#[derive(Clone, Debug, Default, PartialEq, Encode, Decode)]
+pub struct TransitionBiases {
+    /// Biases of the tokens leaving each state, sorted by token id, without zeros.
+    biases: HashMap<StateId, Vec<(TokenId, f32)>>,
+}
+
+impl TransitionBiases {
+    /// Creates empty biases.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Computes the bias of every transition of the index with `bias(state, token_id, next)`.
+    ///
+    /// Transitions of the end-of-sequence and stop tokens are included, zero biases are
+    /// skipped.
+    pub fn from_fn<F>(index: &Index, mut bias: F) -> Self
+    where
+        F: FnMut(StateId, TokenId, StateId) -> f32,
+    {
+        let mut biases = Self::new();
+        for (&state, transitions) in index.transitions() {
+            for (&token_id, &next) in transitions {
+                biases.insert(state, token_id, bias(state, token_id, next));
+            }
+        }
+        biases
+    }
+
+    /// Biases the tokens in every state they are allowed in, e.g. to penalize whitespace
+    /// tokens.
+    pub fn for_tokens(index: &Index, token_biases: &[(TokenId, f32)]) -> Self {
+        let token_biases: HashMap<TokenId, f32> = token_biases.iter().copied().collect();
+        Self::from_fn(index, |_, token_id, _| {
+            token_biases.get(&token_id).copied().unwrap_or_default()
+        })
+    }
+
+    /// Biases the transitions getting closer to a final state, see
+    /// [`Index::distance_to_final`], to prefer shorter strings.
+    pub fn toward_final(index: &Index, bias: f32) -> Self {
+        Self::from_fn(index, |state, _, next| {
+            match (
+                index.distance_to_final(&state),
+                index.distance_to_final(&next),
+            ) {
+                (Some(from), Some(to)) if to < from => bias,
+                _ => 0.0,
+            }
+        })
+    }
+
+    /// Adds `bias` to the bias of the transition from `state` with the token.
+    pub fn insert(&mut self, state: StateId, token_id: TokenId, bias: f32) {
+        if bias == 0.0 {
+            return;
+        }
+        let biases = self.biases.entry(state).or_default();
+        match biases.binary_search_by_key(&token_id, |&(token_id, _)| token_id) {
+            Ok(i) => biases[i].1 += bias,
+            Err(i) => biases.insert(i, (token_id, bias)),
+        }
+    }
+
+    /// Adds the biases of `other` to these ones.
+    pub fn merge(&mut self, other: &TransitionBiases) {
+        for (&state, biases) in &other.biases {
+            for &(token_id, bias) in biases {
+                self.insert(state, token_id, bias);
+            }
+        }
+    }
+
+    /// Returns the biases of the tokens leaving the state, sorted by token id.
+    pub fn biases(&self, state: &StateId) -> &[(TokenId, f32)] {
+        self.biases
+            .get(state)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Returns the number of biased transitions.
+    pub fn len(&self) -> usize {
+        self.biases.values().map(Vec::len).sum()
+    }
+
+    /// Checks if no transition is biased.
+    pub fn is_empty(&self) -> bool {
+        self.biases.values().all(Vec::is_empty)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn index() -> Index {
+        let mut vocabulary = Vocabulary::new(4);
+        for (token, token_id) in [("a", 1), ("b", 2), ("ab", 3)] {
+            vocabulary
+                .try_insert(token, token_id)
+                .expect("Insert failed");
+        }
+        Index::new("a+b", &vocabulary).expect("Index failed")
+    }
+
+    fn token_biases<const N: usize>(biases: [(u32, f32); N]) -> [(TokenId, f32); N] {
+        biases.map(|(token_id, bias)| (TokenId::new(token_id), bias))
+    }
+
+    #[test]
+    fn transition_biases() {
+        let index = index();
+        let initial_state = index.initial_state();
+        let state = index
+            .next_state(&initial_state, &TokenId::new(1))
+            .expect("No next state");
+
+        let biases = TransitionBiases::for_tokens(&index, &token_biases([(1, -1.0), (3, 0.5)]));
+        assert_eq!(
+            biases.biases(&initial_state),
+            &token_biases([(1, -1.0), (3, 0.5)])
+        );
+        assert_eq!(biases.biases(&state), &token_biases([(1, -1.0), (3, 0.5)]));
+        assert_eq!(biases.len(), 4);
+
+        // Only `b` and `ab` lead to the final state.
+        let biases = TransitionBiases::toward_final(&index, 2.0);
+        assert_eq!(biases.biases(&initial_state), &token_biases([(3, 2.0)]));
+        assert_eq!(biases.biases(&state), &token_biases([(2, 2.0), (3, 2.0)]));
+
+        let mut merged = TransitionBiases::for_tokens(&index, &token_biases([(1, -1.0), (3, 0.5)]));
+        merged.merge(&biases);
+        assert_eq!(
+            merged.biases(&state),
+            &token_biases([(1, -1.0), (2, 2.0), (3, 2.5)])
+        );
+
+        let mut biases = TransitionBiases::new();
+        assert!(biases.is_empty());
+        biases.insert(state, TokenId::new(2), 1.0);
+        biases.insert(state, TokenId::new(1), 0.0);
+        biases.insert(state, TokenId::new(2), 0.5);
+        assert_eq!(biases.biases(&state), &token_biases([(2, 1.5)]));
+        assert!(biases.biases(&StateId::new(u32::MAX)).is_empty());
+    }
+}
@@ -0,0 +1,205 @@
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::process::ExitCode;
+
+use outlines_core::prelude::*;
+use regex::Regex;
+
+fn print_help() {
+    println!("Offline compilation of structured generation artifacts\n");
+    println!("Usage:");
+    println!(
+        "  outlines-core compile-schema [--schema schema.json] [--whitespace-pattern PATTERN]"
+    );
+    println!("  outlines-core build-index --model MODEL (--schema schema.json | --regex REGEX) --out index.bin");
+    println!("  outlines-core codegen --model MODEL (--schema schema.json | --regex REGEX) --name NAME --out table.rs");
+    println!("  outlines-core inspect index.bin");
+    println!("  outlines-core validate --schema schema.json [--input input.json]");
+    println!("  outlines-core --help\n");
+    println!("Commands:");
+    println!("  compile-schema  Converts a JSON Schema to a regular expression.");
+    println!("  build-index     Builds the index of a JSON Schema or a regex for the model's vocabulary.");
+    println!("  codegen         Generates a Rust module of the index's transition table.");
+    println!("  inspect         Prints the summary of a serialized index.");
+    println!("  validate        Checks whether the input matches the JSON Schema.\n");
+    println!("Description:");
+    println!("  Schema and input are read from stdin when the corresponding path is omitted.");
+    println!("  When both are omitted, stdin holds the schema followed by the input.");
+}
+
+/// Parsed `--name value` options and positional arguments of a command.
+struct Args {
+    options: Vec<(String, String)>,
+    positional: Vec<String>,
+}
+
+impl Args {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut options = Vec::new();
+        let mut positional = Vec::new();
+        while let Some(arg) = args.next() {
+            match arg.strip_prefix("--") {
+                Some(name) => match args.next() {
+                    Some(value) => options.push((name.to_string(), value)),
+                    None => return Err(format!("Missing value of --{name}")),
+                },
+                None => positional.push(arg),
+            }
+        }
+        Ok(Self {
+            options,
+            positional,
+        })
+    }
+
+    fn get(&self, name: &str) -> Option<&str> {
+        self.options
+            .iter()
+            .rev()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    fn require(&self, name: &str) -> Result<&str, String> {
+        self.get(name)
+            .ok_or(format!("Missing required option --{name}"))
+    }
+}
+
+/// Reads the file at `path` or stdin if it's not provided.
+fn read_input(path: Option<&str>) -> Result<String, String> {
+    match path {
+        Some(path) => std::fs::read_to_string(path).map_err(|e| format!("{path}: {e}")),
+        None => std::io::read_to_string(std::io::stdin()).map_err(|e| e.to_string()),
+    }
+}
+
+/// Splits the schema, the first JSON document of `text`, from the input following it.
+fn split_schema(text: &str) -> Result<(&str, &str), String> {
+    let mut documents = serde_json::Deserializer::from_str(text).into_iter::<serde_json::Value>();
+    match documents.next() {
+        Some(Ok(_)) => Ok(text.split_at(documents.byte_offset())),
+        Some(Err(e)) => Err(format!("Invalid schema: {e}")),
+        None => Err("Missing schema".to_string()),
+    }
+}
+
+fn schema_regex(args: &Args) -> Result<String, String> {
+    regex_from_schema(args, &read_input(args.get("schema"))?)
+}
+
+fn regex_from_schema(args: &Args, schema: &str) -> Result<String, String> {
+    let max_recursion_depth = args
+        .get("max-recursion-depth")
+        .map(|depth| depth.parse::<usize>().map_err(|e| e.to_string()))
+        .transpose()?;
+    json_schema::regex_from_str(schema, args.get("whitespace-pattern"), max_recursion_depth)
+        .map_err(|e| e.to_string())
+}
+
+fn compile_schema(args: &Args) -> Result<(), String> {
+    println!("{}", schema_regex(args)?);
+    Ok(())
+}
+
+/// Builds the index of `--regex`, or of the schema, for the vocabulary of `--model`.
+fn model_index(args: &Args) -> Result<Index, String> {
+    let model = args.require("model")?;
+    let regex = match args.get("regex") {
+        Some(regex) => regex.to_string(),
+        None => schema_regex(args)?,
+    };
+
+    let vocabulary = Vocabulary::from_pretrained(model, None).map_err(|e| e.to_string())?;
+    Index::new(&regex, &vocabulary).map_err(|e| e.to_string())
+}
+
+fn build_index(args: &Args) -> Result<(), String> {
+    let out = args.require("out")?;
+    let index = model_index(args)?;
+
+    let file = File::create(out).map_err(|e| format!("{out}: {e}"))?;
+    let bytes = index
+        .write_to(BufWriter::new(file), |_| {})
+        .map_err(|e| e.to_string())?;
+    println!("Index written to {out} ({bytes} bytes)");
+    Ok(())
+}
+
+fn codegen(args: &Args) -> Result<(), String> {
+    let name = args.require("name")?;
+    let out = args.require("out")?;
+    let index = model_index(args)?;
+
+    let source = index.to_rust_source(name).map_err(|e| e.to_string())?;
+    std::fs::write(out, &source).map_err(|e| format!("{out}: {e}"))?;
+    println!("Module {name} written to {out} ({} bytes)", source.len());
+    Ok(())
+}
+
+fn inspect(args: &Args) -> Result<(), String> {
+    let Some(path) = args.positional.first() else {
+        return Err("Missing index path".to_string());
+    };
+    let file = File::open(path).map_err(|e| format!("{path}: {e}"))?;
+    let index = Index::read_from(BufReader::new(file), |_| {}).map_err(|e| e.to_string())?;
+
+    println!("Fingerprint: {:016x}", index.fingerprint());
+    println!("Vocabulary size: {}", index.vocab_size());
+    println!("Initial state: {}", index.initial_state());
+    println!("States: {}", index.num_states());
+    println!("Final states: {}", index.final_states().len());
+    println!("Transitions: {}", index.num_transitions());
+    Ok(())
+}
+
+fn validate(args: &Args) -> Result<(), String> {
+    // Stdin is read once, even when it holds both the schema and the input.
+    let (regex, input) = match (args.get("schema"), args.get("input")) {
+        (None, None) => {
+            let text = read_input(None)?;
+            let (schema, input) = split_schema(&text)?;
+            (
+                regex_from_schema(args, schema)?,
+                input.trim_start().to_string(),
+            )
+        }
+        (schema, input) => (
+            regex_from_schema(args, &read_input(schema)?)?,
+            read_input(input)?,
+        ),
+    };
+    let regex = Regex::new(&format!("^(?:{regex})$")).map_err(|e| e.to_string())?;
+    if regex.is_match(input.trim_end_matches('\n')) {
+        println!("Valid");
+        Ok(())
+    } else {
+        Err("Input doesn't match the schema".to_string())
+    }
+}
+
+fn main() -> ExitCode {
+    let mut args = std::env::args().skip(1);
+    let command = args.next();
+    if matches!(command.as_deref(), None | Some("--help") | Some("help")) {
+        print_help();
+        return ExitCode::SUCCESS;
+    }
+
+    let result = Args::parse(args).and_then(|args| match command.as_deref() {
+        Some("compile-schema") => compile_schema(&args),
+        Some("build-index") => build_index(&args),
+        Some("codegen") => codegen(&args),
+        Some("inspect") => inspect(&args),
+        Some("validate") => validate(&args),
+        Some(command) => Err(format!("Unknown command: {command}, see --help")),
+        None => unreachable!(),
+    });
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
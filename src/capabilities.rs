@@ -0,0 +1,74 @@
+//! Reports the optional features, which the library was compiled with.
+//!
+//! Downstream code may check them at runtime, e.g. to fall back to a vocabulary built from
+//! local files, instead of failing on a missing function.
+
+use serde::{Deserialize, Serialize};
+
+/// Optional features of the library, see [`capabilities`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Capabilities {
+    /// Vocabularies are loaded from the Hugging Face Hub, `hugginface-hub` feature.
+    pub hub: bool,
+    /// Tokens of vocabularies are processed in parallel, unless `TOKENIZERS_PARALLELISM`
+    /// disables it. Always compiled in.
+    pub parallel: bool,
+    /// Generated values are validated against the complete JSON schema, `schema-validation`
+    /// feature.
+    pub schema_validation: bool,
+    /// Indexes are exported as Arrow record batches, `arrow` feature.
+    pub arrow: bool,
+    /// Logits of Candle tensors are masked, `candle` feature.
+    pub candle: bool,
+    /// Logits of llama.cpp samplers are masked, `llama-cpp` feature.
+    pub llama_cpp: bool,
+    /// Metrics are reported to a recorder, `metrics` feature.
+    pub metrics: bool,
+    /// JSON schemas are generated from protobuf messages, `protobuf` feature.
+    pub protobuf: bool,
+    /// The library is loaded as a Python module, `python-bindings` feature.
+    pub python_bindings: bool,
+}
+
+/// Returns the optional features, which the library was compiled with.
+///
+/// # Example
+///
+/// ```rust
+/// use outlines_core::capabilities;
+///
+/// if !capabilities().hub {
+///     println!("Vocabularies have to be built from local files");
+/// }
+/// ```
+pub fn capabilities() -> Capabilities {
+    Capabilities {
+        hub: cfg!(feature = "hugginface-hub"),
+        parallel: true,
+        schema_validation: cfg!(feature = "schema-validation"),
+        arrow: cfg!(feature = "arrow"),
+        candle: cfg!(feature = "candle"),
+        llama_cpp: cfg!(feature = "llama-cpp"),
+        metrics: cfg!(feature = "metrics"),
+        protobuf: cfg!(feature = "protobuf"),
+        python_bindings: cfg!(feature = "python-bindings"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compiled_features() {
+        let capabilities = capabilities();
+        assert_eq!(capabilities.hub, cfg!(feature = "hugginface-hub"));
+        assert!(capabilities.parallel);
+
+        let json = serde_json::to_value(capabilities).expect("Serialization failed");
+        assert_eq!(
+            json["schema_validation"],
+            cfg!(feature = "schema-validation")
+        );
+    }
+}
@@ -0,0 +1,518 @@
+//! Experimental builder of byte-level regular expressions for a subset of CBOR (RFC 8949).
+//!
+//! Generated patterns match bytes, which aren't valid UTF-8, so they only make sense with
+//! vocabularies able to produce any byte, e.g. byte-fallback ones. The
+//! [`Index`](crate::index::Index) is built as for any other regex:
+//!
+//! ```rust
+//! # use outlines_core::Error;
+//! use outlines_core::cbor::CborItem;
+//!
+//! # fn main() -> Result<(), Error> {
+//! // A map with an identifier and a list of up to 4 tags
+//! let regex = CborItem::map()
+//!     .with_field("id", CborItem::Integer { min: 0, max: 1 << 20 })
+//!     .with_field(
+//!         "tags",
+//!         CborItem::array(CborItem::Text { min_len: 1, max_len: 8 }, 0, 4),
+//!     )
+//!     .to_regex()?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Only the shortest, definite-length encodings are matched: integers, booleans, null, text and
+//! byte strings, arrays and maps with text keys in a fixed order. Since the length of strings
+//! and arrays is written before their content, each length is a separate alternative, so
+//! lengths are bounded by [`MAX_LENGTH`]. Text strings are restricted to ASCII characters,
+//! which keeps their length in bytes equal to their length in characters.
+
+use std::fmt::Write;
+
+use crate::{Error, Result};
+
+/// Maximum length of strings and arrays.
+pub const MAX_LENGTH: usize = 256;
+
+/// Major types of the initial byte of CBOR data items.
+const UNSIGNED: u8 = 0;
+const NEGATIVE: u8 = 1;
+const BYTES: u8 = 2;
+const TEXT: u8 = 3;
+const ARRAY: u8 = 4;
+const MAP: u8 = 5;
+
+/// Simple values.
+const FALSE: u8 = 0xF4;
+const TRUE: u8 = 0xF5;
+const NULL: u8 = 0xF6;
+
+/// Schema of a CBOR data item.
+#[derive(Clone, Debug, PartialEq)]
+pub enum CborItem {
+    /// An integer within the inclusive bounds.
+    Integer { min: i64, max: i64 },
+    /// `true` or `false`.
+    Bool,
+    /// `null`.
+    Null,
+    /// An ASCII text string with a bounded number of characters.
+    Text { min_len: usize, max_len: usize },
+    /// A byte string with a bounded number of bytes.
+    Bytes { min_len: usize, max_len: usize },
+    /// An array of items of the same schema, with a bounded number of items.
+    Array {
+        items: Box<CborItem>,
+        min_items: usize,
+        max_items: usize,
+    },
+    /// A map of text keys, in the order of the fields.
+    Map(Vec<(String, CborItem)>),
+}
+
+impl CborItem {
+    /// Creates an array of `min_items` to `max_items` items matching `items`.
+    pub fn array(items: CborItem, min_items: usize, max_items: usize) -> Self {
+        Self::Array {
+            items: Box::new(items),
+            min_items,
+            max_items,
+        }
+    }
+
+    /// Creates a map without fields.
+    pub fn map() -> Self {
+        Self::Map(Vec::new())
+    }
+
+    /// Appends a field to the map, other items are left unchanged.
+    pub fn with_field(mut self, key: impl Into<String>, value: CborItem) -> Self {
+        if let Self::Map(fields) = &mut self {
+            fields.push((key.into(), value));
+        }
+        self
+    }
+
+    /// Generates the regular expression of the encoded item.
+    #[allow(clippy::wrong_self_convention)]
+    pub fn to_regex(&self) -> Result<String> {
+        let mut regex = String::from("(?s-u:");
+        self.write_regex(&mut regex)?;
+        regex.push(')');
+        Ok(regex)
+    }
+
+    fn write_regex(&self, regex: &mut String) -> Result<()> {
+        match self {
+            Self::Integer { min, max } => {
+                if min > max {
+                    return Err(Error::MaxBoundError);
+                }
+                let mut alternatives = Vec::new();
+                // Negative integers `n` are encoded as `-1 - n`, in the reversed order.
+                if *min < 0 {
+                    let low = (-1 - (*max).min(-1)) as u64;
+                    let high = (-1 - *min) as u64;
+                    alternatives.extend(argument_regexes(NEGATIVE, low, high));
+                }
+                if *max >= 0 {
+                    alternatives.extend(argument_regexes(
+                        UNSIGNED,
+                        (*min).max(0) as u64,
+                        *max as u64,
+                    ));
+                }
+                write_alternatives(regex, &alternatives);
+            }
+            Self::Bool => write!(regex, "[{}{}]", byte(FALSE), byte(TRUE)).unwrap(),
+            Self::Null => regex.push_str(&byte(NULL)),
+            Self::Text { min_len, max_len } => {
+                write_lengths(regex, TEXT, *min_len, *max_len, |len| {
+                    format!(r"[\x00-\x7F]{{{len}}}")
+                })?;
+            }
+            Self::Bytes { min_len, max_len } => {
+                write_lengths(regex, BYTES, *min_len, *max_len, |len| {
+                    format!(".{{{len}}}")
+                })?;
+            }
+            Self::Array {
+                items,
+                min_items,
+                max_items,
+            } => {
+                let mut item = String::new();
+                items.write_regex(&mut item)?;
+                write_lengths(regex, ARRAY, *min_items, *max_items, |len| {
+                    format!("(?:{item}){{{len}}}")
+                })?;
+            }
+            Self::Map(fields) => {
+                regex.push_str(&header(MAP, fields.len() as u64));
+                for (key, value) in fields {
+                    regex.push_str(&header(TEXT, key.len() as u64));
+                    for b in key.bytes() {
+                        regex.push_str(&byte(b));
+                    }
+                    regex.push_str("(?:");
+                    value.write_regex(regex)?;
+                    regex.push(')');
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Writes one alternative per length, made of the header and of the content of that length.
+fn write_lengths(
+    regex: &mut String,
+    major: u8,
+    min_len: usize,
+    max_len: usize,
+    content: impl Fn(usize) -> String,
+) -> Result<()> {
+    if min_len > max_len {
+        return Err(Error::MaxBoundError);
+    }
+    if max_len > MAX_LENGTH {
+        return Err(Error::CborLengthTooLarge {
+            max_len,
+            limit: MAX_LENGTH,
+        });
+    }
+    let alternatives: Vec<String> = (min_len..=max_len)
+        .map(|len| header(major, len as u64) + &content(len))
+        .collect();
+    write_alternatives(regex, &alternatives);
+    Ok(())
+}
+
+fn write_alternatives(regex: &mut String, alternatives: &[String]) {
+    match alternatives {
+        [single] => regex.push_str(single),
+        alternatives => write!(regex, "(?:{})", alternatives.join("|")).unwrap(),
+    }
+}
+
+/// Escapes a byte, to be matched in a pattern without Unicode mode.
+fn byte(b: u8) -> String {
+    format!(r"\x{b:02X}")
+}
+
+/// Regex of the header of an item with the major type and the argument, in the shortest form.
+fn header(major: u8, argument: u64) -> String {
+    encode_header(major, argument)
+        .into_iter()
+        .map(byte)
+        .collect()
+}
+
+/// Encodes the header of an item with the major type and the argument, in the shortest form.
+fn encode_header(major: u8, argument: u64) -> Vec<u8> {
+    let major = major << 5;
+    match argument {
+        0..=23 => vec![major | argument as u8],
+        24..=0xFF => vec![major | 24, argument as u8],
+        0x100..=0xFFFF => [&[major | 25][..], &(argument as u16).to_be_bytes()].concat(),
+        0x1_0000..=0xFFFF_FFFF => [&[major | 26][..], &(argument as u32).to_be_bytes()].concat(),
+        _ => [&[major | 27][..], &argument.to_be_bytes()].concat(),
+    }
+}
+
+/// Regexes of the headers with the major type and the arguments within the inclusive bounds,
+/// one for each width of the argument.
+fn argument_regexes(major: u8, low: u64, high: u64) -> Vec<String> {
+    // Inclusive bounds of arguments by width: inline, then 1, 2, 4 and 8 bytes.
+    const WIDTHS: [(u64, u64, usize); 5] = [
+        (0, 23, 0),
+        (24, 0xFF, 1),
+        (0x100, 0xFFFF, 2),
+        (0x1_0000, 0xFFFF_FFFF, 4),
+        (0x1_0000_0000, u64::MAX, 8),
+    ];
+    let mut regexes = Vec::new();
+    for (additional, (first, last, width)) in (23u8..).zip(WIDTHS) {
+        let (low, high) = (low.max(first), high.min(last));
+        if low > high {
+            continue;
+        }
+        let initial = major << 5;
+        if width == 0 {
+            regexes.push(byte_range(initial | low as u8, initial | high as u8));
+        } else {
+            let low = &low.to_be_bytes()[8 - width..];
+            let high = &high.to_be_bytes()[8 - width..];
+            regexes.push(format!(
+                "{}{}",
+                byte(initial | additional),
+                big_endian_range(low, high)
+            ));
+        }
+    }
+    regexes
+}
+
+fn byte_range(low: u8, high: u8) -> String {
+    if low == high {
+        byte(low)
+    } else {
+        format!("[{}-{}]", byte(low), byte(high))
+    }
+}
+
+/// Regex of the big-endian numbers of the same width within the inclusive bounds.
+fn big_endian_range(low: &[u8], high: &[u8]) -> String {
+    let (first_low, first_high) = (low[0], high[0]);
+    let (rest_low, rest_high) = (&low[1..], &high[1..]);
+    if rest_low.is_empty() {
+        return byte_range(first_low, first_high);
+    }
+    if first_low == first_high {
+        return format!(
+            "{}{}",
+            byte(first_low),
+            big_endian_range(rest_low, rest_high)
+        );
+    }
+
+    let any = format!(".{{{}}}", rest_low.len());
+    // The first bytes, which any rest follows.
+    let full_low = rest_low.iter().all(|b| *b == 0x00);
+    let full_high = rest_high.iter().all(|b| *b == 0xFF);
+    let middle_low = if full_low { first_low } else { first_low + 1 };
+    let middle_high = if full_high {
+        first_high
+    } else {
+        first_high - 1
+    };
+
+    let mut alternatives = Vec::new();
+    if !full_low {
+        let max = vec![0xFF; rest_low.len()];
+        alternatives.push(format!(
+            "{}{}",
+            byte(first_low),
+            big_endian_range(rest_low, &max)
+        ));
+    }
+    if middle_low <= middle_high {
+        alternatives.push(format!("{}{any}", byte_range(middle_low, middle_high)));
+    }
+    if !full_high {
+        let min = vec![0x00; rest_high.len()];
+        alternatives.push(format!(
+            "{}{}",
+            byte(first_high),
+            big_endian_range(&min, rest_high)
+        ));
+    }
+    match alternatives.as_slice() {
+        [single] => single.clone(),
+        alternatives => format!("(?:{})", alternatives.join("|")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use regex::bytes::Regex;
+
+    use super::*;
+    use crate::index::Index;
+    use crate::primitives::TokenId;
+    use crate::vocabulary::Vocabulary;
+
+    fn compile(item: &CborItem) -> Regex {
+        let regex = item.to_regex().expect("To regex failed");
+        Regex::new(&format!("^{regex}$")).expect("Regex failed")
+    }
+
+    fn integer(value: i64) -> Vec<u8> {
+        if value < 0 {
+            encode_header(NEGATIVE, (-1 - value) as u64)
+        } else {
+            encode_header(UNSIGNED, value as u64)
+        }
+    }
+
+    fn text(value: &str) -> Vec<u8> {
+        [
+            encode_header(TEXT, value.len() as u64),
+            value.as_bytes().to_vec(),
+        ]
+        .concat()
+    }
+
+    #[test]
+    fn integers() {
+        let item = CborItem::Integer {
+            min: -70_000,
+            max: 5_000_000_000,
+        };
+        let regex = compile(&item);
+        for value in [
+            -70_000,
+            -65_537,
+            -256,
+            -25,
+            -24,
+            -1,
+            0,
+            23,
+            24,
+            255,
+            256,
+            4096,
+            65_535,
+            65_536,
+            5_000_000_000,
+        ] {
+            assert!(regex.is_match(&integer(value)), "{value} should match");
+        }
+        for value in [-70_001, 5_000_000_001, i64::MIN, i64::MAX] {
+            assert!(!regex.is_match(&integer(value)), "{value} shouldn't match");
+        }
+        // Non-shortest encodings of 5 and -1
+        assert!(!regex.is_match(&[0x18, 0x05]));
+        assert!(!regex.is_match(&[0x39, 0x00, 0x00]));
+
+        let regex = compile(&CborItem::Integer {
+            min: 300,
+            max: 70_000,
+        });
+        for value in [300, 511, 512, 65_535, 65_536, 70_000] {
+            assert!(regex.is_match(&integer(value)), "{value} should match");
+        }
+        for value in [0, 299, 70_001, -300] {
+            assert!(!regex.is_match(&integer(value)), "{value} shouldn't match");
+        }
+
+        let item = CborItem::Integer { min: 1, max: 0 };
+        assert!(matches!(item.to_regex(), Err(Error::MaxBoundError)));
+    }
+
+    #[test]
+    fn strings_and_arrays() {
+        let regex = compile(&CborItem::Text {
+            min_len: 1,
+            max_len: 30,
+        });
+        assert!(regex.is_match(&text("a")));
+        assert!(regex.is_match(&text(&"b".repeat(30))));
+        assert!(!regex.is_match(&text("")));
+        assert!(!regex.is_match(&text(&"b".repeat(31))));
+        assert!(!regex.is_match(&text("é")));
+        // Length doesn't match the content
+        assert!(!regex.is_match(b"\x62a"));
+
+        let regex = compile(&CborItem::Bytes {
+            min_len: 0,
+            max_len: 2,
+        });
+        for value in [&[0x40][..], &[0x41, 0xFF], &[0x42, 0x00, 0x80]] {
+            assert!(regex.is_match(value));
+        }
+        assert!(!regex.is_match(&[0x43, 0x00, 0x00, 0x00]));
+
+        let regex = compile(&CborItem::array(CborItem::Bool, 0, 25));
+        assert!(regex.is_match(&[0x80]));
+        assert!(regex.is_match(&[0x82, 0xF4, 0xF5]));
+        let long = [&[0x98, 25][..], &[0xF5; 25]].concat();
+        assert!(regex.is_match(&long));
+        assert!(!regex.is_match(&[0x82, 0xF4]));
+        assert!(!regex.is_match(&[0x81, 0xF6]));
+
+        let item = CborItem::Bytes {
+            min_len: 0,
+            max_len: MAX_LENGTH + 1,
+        };
+        assert!(matches!(
+            item.to_regex(),
+            Err(Error::CborLengthTooLarge { .. })
+        ));
+    }
+
+    #[test]
+    fn maps() {
+        let item = CborItem::map()
+            .with_field("id", CborItem::Integer { min: 0, max: 1000 })
+            .with_field("ok", CborItem::Bool)
+            .with_field("note", CborItem::Null);
+        let regex = compile(&item);
+        let value = [
+            vec![0xA3],
+            text("id"),
+            integer(512),
+            text("ok"),
+            vec![TRUE],
+            text("note"),
+            vec![NULL],
+        ]
+        .concat();
+        assert!(regex.is_match(&value));
+        // Fields out of order
+        let swapped = [
+            vec![0xA3],
+            text("ok"),
+            vec![TRUE],
+            text("id"),
+            integer(512),
+            text("note"),
+            vec![NULL],
+        ]
+        .concat();
+        assert!(!regex.is_match(&swapped));
+    }
+
+    #[test]
+    fn index_with_byte_tokens() {
+        let item = CborItem::map()
+            .with_field("id", CborItem::Integer { min: 0, max: 1000 })
+            .with_field(
+                "tag",
+                CborItem::Bytes {
+                    min_len: 0,
+                    max_len: 4,
+                },
+            );
+        let regex = item.to_regex().expect("To regex failed");
+
+        // Byte-fallback tokens for every byte, and a few merged ones.
+        let eos_token_id = 300;
+        let mut vocabulary = Vocabulary::new(eos_token_id);
+        for b in 0..=255u8 {
+            vocabulary
+                .try_insert(vec![b], b as u32)
+                .expect("Insert failed");
+        }
+        vocabulary
+            .try_insert(text("id"), 256)
+            .expect("Insert failed");
+        vocabulary
+            .try_insert(vec![0x19, 0x02], 257)
+            .expect("Insert failed");
+        let index = Index::new(&regex, &vocabulary).expect("Index failed");
+
+        let value = [
+            vec![0xA2],
+            text("id"),
+            integer(700),
+            text("tag"),
+            vec![0x42, 0xFF, 0x00],
+        ]
+        .concat();
+        let mut state = index.initial_state();
+        let mut tokens: Vec<u32> = vec![0xA2, 256, 257, 0xBC];
+        tokens.extend(value[7..].iter().map(|b| *b as u32));
+        for token_id in tokens.into_iter().map(TokenId::new) {
+            state = index
+                .next_state(&state, &token_id)
+                .unwrap_or_else(|| panic!("Token {token_id} isn't allowed"));
+        }
+        assert!(index.is_final_state(&state));
+
+        // Map with three fields isn't allowed
+        let initial = index.initial_state();
+        assert!(index.next_state(&initial, &TokenId::new(0xA3)).is_none());
+    }
+}
@@ -0,0 +1,365 @@
+//! Composition of regular expressions at the automaton level, for constraints which can't be
+//! combined by the syntax of regular expressions, e.g. a pattern and a length bound.
+//!
+//! The DFA of the pattern is walked one character at a time, so that its product with the
+//! length counter is acyclic and is written back as a regular expression without repetitions.
+
+use regex_automata::dfa::dense::{Builder, Config, DFA};
+use regex_automata::dfa::{Automaton, StartKind};
+use regex_automata::util::primitives::StateID as AutomataStateId;
+use regex_automata::util::start;
+use regex_automata::{Anchored, MatchKind};
+use regex_syntax::utf8::Utf8Sequences;
+use rustc_hash::{FxHashMap as HashMap, FxHashSet as HashSet};
+
+use crate::Result;
+
+/// Maximum length of a composed regular expression, longer compositions are given up.
+pub(crate) const MAX_COMPOSED_LEN: usize = 1 << 16;
+
+/// Transitions of a DFA state by ranges of characters, in increasing order.
+type CharTransitions = Vec<(char, char, AutomataStateId)>;
+
+/// Result of a composition of regular expressions.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Composition {
+    /// Regular expression of the composition.
+    Regex(String),
+    /// No string satisfies all the constraints.
+    Empty,
+    /// The composition isn't expressible, or is longer than [`MAX_COMPOSED_LEN`].
+    Unsupported,
+}
+
+/// Generates a regular expression of the strings fully matching `pattern`, which have from
+/// `min_len` to `max_len` characters.
+///
+/// The pattern itself is returned, if its strings already have such lengths. Without
+/// `max_len`, the composition is supported in this case only.
+pub(crate) fn bounded_length(
+    pattern: &str,
+    min_len: usize,
+    max_len: Option<usize>,
+) -> Result<Composition> {
+    let dfa = Builder::new()
+        .configure(
+            Config::new()
+                .match_kind(MatchKind::All)
+                .start_kind(StartKind::Anchored),
+        )
+        .build(pattern)
+        .map_err(Box::new)?;
+    let Ok(start) = dfa.start_state(&start::Config::new().anchored(Anchored::Yes)) else {
+        return Ok(Composition::Unsupported);
+    };
+    let Some(automaton) = CharAutomaton::new(&dfa, start) else {
+        return Ok(Composition::Unsupported);
+    };
+
+    // Layers of the product with the counter of characters, in increasing order.
+    let max_len = match max_len {
+        Some(max_len) => max_len,
+        None if automaton.min_len().is_some_and(|len| len >= min_len) => {
+            return Ok(Composition::Regex(pattern.to_string()))
+        }
+        None => return Ok(Composition::Unsupported),
+    };
+    let mut layers: Vec<Vec<AutomataStateId>> = vec![vec![start]];
+    let mut binding = false;
+    for len in 0..=max_len {
+        // Every character takes at least a byte of the regular expression.
+        if len > MAX_COMPOSED_LEN {
+            return Ok(Composition::Unsupported);
+        }
+        let mut next: Vec<AutomataStateId> = Vec::new();
+        for &state in &layers[len] {
+            binding |= len < min_len && automaton.accepts(state);
+            for &(_, _, target) in &automaton.transitions[&state] {
+                if len == max_len {
+                    binding = true;
+                } else if !next.contains(&target) {
+                    next.push(target);
+                }
+            }
+        }
+        if next.is_empty() {
+            break;
+        }
+        layers.push(next);
+    }
+    if !binding {
+        return Ok(Composition::Regex(pattern.to_string()));
+    }
+
+    // Regular expressions of the suffixes from each state of a layer, from the last layer.
+    let mut suffixes: HashMap<AutomataStateId, String> = HashMap::default();
+    for (len, layer) in layers.iter().enumerate().rev() {
+        let mut current = HashMap::default();
+        for &state in layer {
+            // Ranges of characters by the states they lead to.
+            let mut branches: Vec<(String, AutomataStateId)> = Vec::new();
+            for &(lo, hi, target) in &automaton.transitions[&state] {
+                if !suffixes.contains_key(&target) {
+                    continue;
+                }
+                match branches.iter_mut().find(|(_, t)| *t == target) {
+                    Some((class, _)) => class.push_str(&char_range(lo, hi)),
+                    None => branches.push((char_range(lo, hi), target)),
+                }
+            }
+            let accepts = len >= min_len && automaton.accepts(state);
+            if branches.is_empty() && !accepts {
+                continue;
+            }
+            let branches: Vec<String> = branches
+                .into_iter()
+                .map(|(class, target)| format!("[{class}]{}", suffixes[&target]))
+                .collect();
+            let regex = match (branches.as_slice(), accepts) {
+                ([], _) => String::new(),
+                ([branch], false) => branch.clone(),
+                (branches, false) => format!("(?:{})", branches.join("|")),
+                (branches, true) => format!("(?:{})?", branches.join("|")),
+            };
+            if regex.len() > MAX_COMPOSED_LEN {
+                return Ok(Composition::Unsupported);
+            }
+            current.insert(state, regex);
+        }
+        suffixes = current;
+    }
+    Ok(match suffixes.remove(&start) {
+        Some(regex) => Composition::Regex(regex),
+        None => Composition::Empty,
+    })
+}
+
+/// Character-level view of the states of a DFA, which are reachable and lead to a match.
+struct CharAutomaton<'a> {
+    dfa: &'a DFA<Vec<u32>>,
+    start: AutomataStateId,
+    transitions: HashMap<AutomataStateId, CharTransitions>,
+}
+
+impl<'a> CharAutomaton<'a> {
+    /// Explores the states reachable from `start`, `None` if the DFA gives up on some of them,
+    /// e.g. on Unicode word boundaries.
+    fn new(dfa: &'a DFA<Vec<u32>>, start: AutomataStateId) -> Option<Self> {
+        let mut transitions: HashMap<AutomataStateId, CharTransitions> = HashMap::default();
+        let mut queue = vec![start];
+        while let Some(state) = queue.pop() {
+            if transitions.contains_key(&state) {
+                continue;
+            }
+            let mut ranges = Vec::new();
+            for sequence in Utf8Sequences::new('\0', char::MAX) {
+                let bytes: Vec<(u8, u8)> = sequence
+                    .as_slice()
+                    .iter()
+                    .map(|r| (r.start, r.end))
+                    .collect();
+                walk(dfa, state, &bytes, &mut Vec::new(), &mut ranges)?;
+            }
+            ranges.sort_unstable();
+            let mut merged: CharTransitions = Vec::with_capacity(ranges.len());
+            for (lo, hi, target) in ranges {
+                match merged.last_mut() {
+                    Some((_, last_hi, last_target))
+                        if *last_target == target && (*last_hi as u32) + 1 == lo as u32 =>
+                    {
+                        *last_hi = hi
+                    }
+                    _ => merged.push((lo, hi, target)),
+                }
+            }
+            queue.extend(merged.iter().map(|&(_, _, target)| target));
+            transitions.insert(state, merged);
+        }
+
+        // Only the states leading to a match are kept.
+        let mut live: HashSet<AutomataStateId> = HashSet::default();
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (&state, ranges) in &transitions {
+                if !live.contains(&state)
+                    && (is_accepting(dfa, state) || ranges.iter().any(|(_, _, t)| live.contains(t)))
+                {
+                    live.insert(state);
+                    changed = true;
+                }
+            }
+        }
+        for ranges in transitions.values_mut() {
+            ranges.retain(|(_, _, target)| live.contains(target));
+        }
+        Some(Self {
+            dfa,
+            start,
+            transitions,
+        })
+    }
+
+    fn accepts(&self, state: AutomataStateId) -> bool {
+        is_accepting(self.dfa, state)
+    }
+
+    /// Minimal number of characters of a match, `None` if nothing matches.
+    fn min_len(&self) -> Option<usize> {
+        let mut layer = vec![self.start];
+        let mut seen: HashSet<AutomataStateId> = layer.iter().copied().collect();
+        for len in 0.. {
+            if layer.iter().any(|&state| self.accepts(state)) {
+                return Some(len);
+            }
+            layer = layer
+                .iter()
+                .flat_map(|state| self.transitions[state].iter().map(|&(_, _, t)| t))
+                .filter(|&target| seen.insert(target))
+                .collect();
+            if layer.is_empty() {
+                return None;
+            }
+        }
+        None
+    }
+}
+
+/// Whether the DFA matches, once the string ends in the state.
+fn is_accepting(dfa: &DFA<Vec<u32>>, state: AutomataStateId) -> bool {
+    dfa.is_match_state(dfa.next_eoi_state(state))
+}
+
+/// Walks the DFA through the byte ranges of an UTF-8 sequence, splitting them by the byte
+/// classes of the DFA, and collects the ranges of characters by the reached states. `None`
+/// if the DFA gives up.
+fn walk(
+    dfa: &DFA<Vec<u32>>,
+    state: AutomataStateId,
+    bytes: &[(u8, u8)],
+    prefix: &mut Vec<(u8, u8)>,
+    ranges: &mut Vec<(char, char, AutomataStateId)>,
+) -> Option<()> {
+    let Some((&(start, end), rest)) = bytes.split_first() else {
+        ranges.extend(
+            char_ranges(prefix)
+                .into_iter()
+                .map(|(lo, hi)| (lo, hi, state)),
+        );
+        return Some(());
+    };
+    let classes = dfa.byte_classes();
+    let mut lo = start;
+    loop {
+        let mut hi = lo;
+        while hi < end && classes.get(hi + 1) == classes.get(lo) {
+            hi += 1;
+        }
+        let next = dfa.next_state(state, lo);
+        if dfa.is_quit_state(next) {
+            return None;
+        }
+        if !dfa.is_dead_state(next) {
+            prefix.push((lo, hi));
+            walk(dfa, next, rest, prefix, ranges)?;
+            prefix.pop();
+        }
+        if hi == end {
+            return Some(());
+        }
+        lo = hi + 1;
+    }
+}
+
+/// Ranges of the characters encoded by the product of byte ranges.
+fn char_ranges(bytes: &[(u8, u8)]) -> Vec<(char, char)> {
+    // The characters are contiguous, if the ranges after the first wide one are complete.
+    let wide = bytes.iter().position(|(lo, hi)| lo != hi);
+    let contiguous = wide.is_none_or(|i| bytes[i + 1..].iter().all(|&r| r == (0x80, 0xBF)));
+    if contiguous {
+        let decode = |bytes: Vec<u8>| std::str::from_utf8(&bytes).ok()?.chars().next();
+        let lo = decode(bytes.iter().map(|r| r.0).collect());
+        let hi = decode(bytes.iter().map(|r| r.1).collect());
+        return lo.zip(hi).into_iter().collect();
+    }
+    let i = wide.unwrap_or_default();
+    let (lo, hi) = bytes[i];
+    (lo..=hi)
+        .flat_map(|byte| {
+            let mut split = bytes.to_vec();
+            split[i] = (byte, byte);
+            char_ranges(&split)
+        })
+        .collect()
+}
+
+/// Range of characters within a class of a regular expression.
+fn char_range(lo: char, hi: char) -> String {
+    if lo == hi {
+        format!(r"\x{{{:X}}}", lo as u32)
+    } else {
+        format!(r"\x{{{:X}}}-\x{{{:X}}}", lo as u32, hi as u32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use regex::Regex;
+
+    use super::*;
+
+    fn bounded(pattern: &str, min_len: usize, max_len: usize) -> Regex {
+        match bounded_length(pattern, min_len, Some(max_len)).expect("Composition failed") {
+            Composition::Regex(regex) => {
+                Regex::new(&format!("^(?:{regex})$")).expect("Regex failed")
+            }
+            composition => panic!("Not composed: {composition:?}"),
+        }
+    }
+
+    #[test]
+    fn intersect_pattern_with_length() {
+        let re = bounded("[a-z]+@[a-z]+", 5, 7);
+        for (value, expected) in [
+            ("a@b", false),
+            ("ab@cd", true),
+            ("abc@def", true),
+            ("abcd@efg", false),
+            ("abcdefg", false),
+        ] {
+            assert_eq!(re.is_match(value), expected, "{value}");
+        }
+
+        let re = bounded("(é|ab)*", 0, 3);
+        for (value, expected) in [("", true), ("éé", true), ("abé", true), ("ééab", false)] {
+            assert_eq!(re.is_match(value), expected, "{value}");
+        }
+    }
+
+    #[test]
+    fn pattern_within_bounds() {
+        for (pattern, min_len, max_len, expected) in [
+            (
+                r"\d{5}",
+                1,
+                Some(5),
+                Composition::Regex(r"\d{5}".to_string()),
+            ),
+            ("ab+", 2, None, Composition::Regex("ab+".to_string())),
+            ("a+", 2, None, Composition::Unsupported),
+            ("abc", 0, Some(2), Composition::Empty),
+        ] {
+            let composition =
+                bounded_length(pattern, min_len, max_len).expect("Composition failed");
+            assert_eq!(composition, expected, "{pattern}");
+        }
+    }
+
+    #[test]
+    fn oversized_composition() {
+        assert_eq!(
+            bounded_length("(a|b)*c(a|b)*d(a|b)*", 0, Some(2000)).expect("Composition failed"),
+            Composition::Unsupported
+        );
+    }
+}
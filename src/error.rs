@@ -2,6 +2,8 @@
 
 use thiserror::Error;
 
+use crate::primitives::{StateId, TokenId};
+
 pub type Result<T, E = crate::Error> = std::result::Result<T, E>;
 
 #[derive(Error, Debug)]
@@ -9,8 +11,33 @@ pub enum Error {
     // Index Errors
     #[error("Failed to build DFA {0}")]
     IndexDfaError(#[from] Box<regex_automata::dfa::dense::BuildError>),
+    #[error("Failed to build NFA {0}")]
+    IndexNfaError(#[from] Box<regex_automata::nfa::thompson::BuildError>),
     #[error("Index failed since anchored universal start state doesn't exist")]
     DfaHasNoStartState,
+    #[error("Failed to encode Index: {0}")]
+    IndexEncodeError(#[from] bincode::error::EncodeError),
+    #[error("Failed to decode Index: {0}")]
+    IndexDecodeError(#[from] bincode::error::DecodeError),
+    #[error("Index stream error: {0}")]
+    IndexStreamError(String),
+    #[error("Index construction was cancelled after exploring {states_explored} states")]
+    IndexBuildCancelled { states_explored: usize },
+    #[error("Index construction exceeded the limit of {limit} {resource}")]
+    ResourceLimitExceeded { resource: &'static str, limit: u64 },
+    #[error("Every token sequence of at most {max_tokens} tokens is accepted by the index, so its complement is empty")]
+    EmptyComplement { max_tokens: usize },
+    #[error("Invalid name of generated module '{0}', expected a Rust identifier")]
+    InvalidModuleName(String),
+    #[cfg(feature = "arrow")]
+    #[error(transparent)]
+    ArrowError(#[from] arrow_schema::ArrowError),
+    #[cfg(feature = "candle")]
+    #[error(transparent)]
+    CandleError(#[from] candle_core::Error),
+    #[cfg(feature = "protobuf")]
+    #[error(transparent)]
+    ProtobufDecodeError(#[from] prost::DecodeError),
     // Vocabulary Errors
     #[error("EOS token should not be inserted into Vocabulary")]
     EOSTokenDisallowed,
@@ -28,6 +55,12 @@ pub enum Error {
     ByteProcessorFailed,
     #[error("Token processing failed for byte fallback level processor")]
     ByteFallbackProcessorFailed,
+    #[error("Special token {token} not found in the tokenizer of {model}")]
+    SpecialTokenNotFound { model: String, token: String },
+    #[error("Token id {0} is a text token and can't be a stop token")]
+    StopTokenIsText(TokenId),
+    #[error("Vocabulary cache error: {0}")]
+    VocabularyCacheError(String),
     // Json Schema errors
     #[error("serde json error")]
     SerdeJsonError(#[from] serde_json::Error),
@@ -43,6 +76,8 @@ pub enum Error {
     OneOfMustBeAnArray,
     #[error("'prefixItems' must be an array")]
     PrefixItemsMustBeAnArray,
+    #[error("Invalid regular expression: {0}")]
+    InvalidRegex(Box<str>),
     #[error("Unsupported data type in enum: {0}")]
     UnsupportedEnumDataType(Box<serde_json::Value>),
     #[error("'enum' must be an array")]
@@ -69,10 +104,95 @@ pub enum Error {
     InvalidRefecencePath(Box<str>),
     #[error("Ref recusion limit reached: {0}")]
     RefRecursionLimitReached(usize),
+    #[error("Keyword '{keyword}' at '{path}' is not supported and would be ignored")]
+    UnsupportedKeyword { path: String, keyword: String },
+    #[error(
+        "Construct '{construct}' of the pattern at '{path}' is not supported and would be dropped"
+    )]
+    UnsupportedPatternConstruct { path: String, construct: String },
+    #[error("Keyword 'multipleOf' at '{path}' only supports powers of ten, e.g. 100 or 0.01, found {multiple}")]
+    UnsupportedMultipleOf {
+        path: String,
+        multiple: Box<serde_json::Value>,
+    },
+    #[error("No value of '{keyword}' at '{path}' satisfies the sibling keywords")]
+    NoValidEnumValue { path: String, keyword: String },
+    #[error("No value satisfies the keywords '{keyword}' combined at '{path}'")]
+    UnsatisfiableSchema { path: String, keyword: String },
+    #[error("No value satisfies the schema")]
+    NoValidInstance,
+    #[error("Invalid JSON Schema: {0}")]
+    InvalidJsonSchema(String),
+    #[error("Regular expression of {len} bytes exceeds the limit of {limit} bytes at '{path_of_largest_node}'")]
+    RegexTooLarge {
+        len: usize,
+        limit: usize,
+        path_of_largest_node: String,
+    },
+    #[error("No schema to compile into a union")]
+    EmptySchemaUnion,
+    #[error("Schemas {first} and {second} share the discriminator '{name}'")]
+    DuplicateSchemaName {
+        name: String,
+        first: usize,
+        second: usize,
+    },
+    // Guide errors
+    #[error("No allowed tokens available for the state {0}")]
+    NoAllowedTokens(StateId),
+    #[error("No next state found for the current state: {state} with token ID: {token_id}")]
+    NoNextState { state: StateId, token_id: TokenId },
+    #[error("Cannot roll back {steps} step(s): only {available} states stored (max_rollback = {max_rollback}). You must advance through at least {steps} state(s) before rolling back {steps} step(s).")]
+    RollbackUnavailable {
+        steps: usize,
+        available: usize,
+        max_rollback: usize,
+    },
+    #[error("State {0} is not found in the Index")]
+    StateNotFound(StateId),
+    #[error(
+        "At least {required} tokens are needed to finish, more than the budget of {max_tokens}"
+    )]
+    TokenBudgetExceeded { max_tokens: usize, required: usize },
+    #[error("Index fingerprint mismatch: expected {expected}, found {found}")]
+    IndexFingerprintMismatch { expected: u64, found: u64 },
+    #[error("Index doesn't match the vocabulary: {0}")]
+    VocabularyMismatch(String),
+    #[error("Vocabulary doesn't extend the previous one: {0}")]
+    VocabularyNotExtended(String),
+    #[error("Index must be rebuilt: {0}")]
+    IndexRebuildRequired(String),
+    #[error("Guide is not finished in the state {0}")]
+    GuideNotFinished(StateId),
+    #[error("Guide has no JSON Schema to validate the generated value against")]
+    GuideWithoutSchema,
+    #[error("Guide doesn't record the consumed tokens, see `with_history`")]
+    TokenHistoryDisabled,
+    #[error("Only {recorded} of the {consumed} consumed tokens are known to the guide")]
+    IncompleteTokenHistory { consumed: usize, recorded: usize },
+    #[error("Token id {0} is not in the vocabulary")]
+    UnknownTokenId(TokenId),
+    #[error("Proposal of the token {token_id} from the state {state} is outdated, the guide moved since")]
+    StaleProposal { token_id: TokenId, state: StateId },
+    // Protobuf errors
+    #[error("Protobuf type {0} is not found in the descriptor set")]
+    ProtobufTypeNotFound(String),
+    // TypeScript errors
+    #[error("Invalid TypeScript type at offset {offset}: {message}")]
+    TypeScriptSyntaxError { offset: usize, message: String },
+    // Markdown errors
+    #[error("Markdown table must have at least one column")]
+    MarkdownTableWithoutColumns,
+    // CBOR errors
+    #[error("Length {max_len} of a CBOR item exceeds the limit of {limit}")]
+    CborLengthTooLarge { max_len: usize, limit: usize },
+    // Text errors
+    #[error("Text must contain at least one of the substrings, but none is given")]
+    ContainsWithoutSubstrings,
     #[error("The vocabulary provided is incompatible with the regex '{regex}'. Found no transitions from state {error_state}, missing tokens corresponding to at least one of the following characters: {missing_tokens:?}. This may be due to an encoding issue in your vocabulary.")]
     IncompatibleVocabulary {
         regex: String,
-        error_state: u32,
+        error_state: StateId,
         missing_tokens: Vec<String>,
     },
 }
@@ -81,6 +201,14 @@ impl Error {
     pub fn is_recursion_limit(&self) -> bool {
         matches!(self, Self::RefRecursionLimitReached(_))
     }
+
+    /// Whether the schema, or one of its subschemas, allows no value at all.
+    pub fn is_unsatisfiable(&self) -> bool {
+        matches!(
+            self,
+            Self::UnsatisfiableSchema { .. } | Self::NoValidEnumValue { .. }
+        )
+    }
 }
 
 #[cfg(feature = "python-bindings")]
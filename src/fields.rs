@@ -0,0 +1,348 @@
+//! Paths of the JSON fields generated in the states of an index, e.g. to stream the output
+//! field by field or to sample each field with its own parameters.
+//!
+//! [`FieldPaths`] names the field of each state by the keys of the objects, which the state
+//! is nested in, joined by dots, with `[]` for the items of arrays, e.g. `address.city` or
+//! `users[].name`. The field of a state is found by walking the tokens of the first path
+//! reaching it, so the item of an array isn't told apart from the others:
+//!
+//! ```rust
+//! # use outlines_core::Error;
+//! use std::sync::Arc;
+//!
+//! use outlines_core::fields::FieldPaths;
+//! use outlines_core::prelude::*;
+//!
+//! # fn main() -> Result<(), Error> {
+//! let vocabulary = Vocabulary::fixture();
+//! let schema = r#"{"type": "object", "properties": {"tags": {"type": "array", "items": {"type": "string"}}}, "required": ["tags"]}"#;
+//! let regex = json_schema::regex_from_str(schema, Some(""), None)?;
+//! let index = Index::new(&regex, &vocabulary)?;
+//!
+//! let paths = Arc::new(FieldPaths::new(&index, &vocabulary));
+//! let mut guide = Guide::new(Arc::new(index), 32).with_field_paths(paths);
+//! assert_eq!(guide.current_path(), None);
+//! for token in ["{\"", "t", "a", "g", "s", "\":", "[\"", "x"] {
+//!     guide.advance(vocabulary.token_ids(token).expect("No token ids")[0])?;
+//! }
+//! assert_eq!(guide.current_path().as_deref(), Some("tags[]"));
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::{BTreeMap, VecDeque};
+
+use bincode::{Decode, Encode};
+use rustc_hash::FxHashMap as HashMap;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::index::Index;
+use crate::prelude::*;
+use crate::Result;
+
+/// Paths of the JSON fields generated in the states of an index, see the [module](self)
+/// documentation.
+#[derive(Clone, Debug, Default, PartialEq, Encode, Decode)]
+pub struct FieldPaths {
+    /// Distinct paths, in the order of their first state.
+    paths: Vec<String>,
+    /// Position of the path of each state in `paths`, states outside of fields are omitted.
+    states: HashMap<StateId, u32>,
+}
+
+impl FieldPaths {
+    /// Finds the field of each state of the index, reached with the tokens of the
+    /// vocabulary. States of a text, which isn't JSON, are outside of fields.
+    pub fn new(index: &Index, vocabulary: &Vocabulary) -> Self {
+        let tokens: HashMap<TokenId, &Token> = vocabulary
+            .tokens()
+            .iter()
+            .flat_map(|(token, ids)| ids.iter().map(move |&id| (id, token)))
+            .collect();
+
+        let mut paths = Self::default();
+        let mut positions: HashMap<String, u32> = HashMap::default();
+        let mut scanners: HashMap<StateId, PathScanner> = HashMap::default();
+        scanners.insert(index.initial_state(), PathScanner::default());
+        let mut queue = VecDeque::from([index.initial_state()]);
+        while let Some(state) = queue.pop_front() {
+            let scanner = scanners[&state].clone();
+            if let Some(path) = scanner.path() {
+                let next_position = positions.len() as u32;
+                let position = *positions.entry(path).or_insert_with_key(|path| {
+                    paths.paths.push(path.clone());
+                    next_position
+                });
+                paths.states.insert(state, position);
+            }
+
+            let Some(transitions) = index.transitions().get(&state) else {
+                continue;
+            };
+            // Walked in order of token ids, so that the paths don't depend on hashing.
+            let mut transitions: Vec<(&TokenId, &StateId)> = transitions.iter().collect();
+            transitions.sort_unstable();
+            for (token_id, next) in transitions {
+                if scanners.contains_key(next) {
+                    continue;
+                }
+                let Some(token) = tokens.get(token_id) else {
+                    continue;
+                };
+                let mut scanner = scanner.clone();
+                token.iter().for_each(|&byte| scanner.feed(byte));
+                scanners.insert(*next, scanner);
+                queue.push_back(*next);
+            }
+        }
+        paths
+    }
+
+    /// Returns the path of the field generated in the state, if any.
+    pub fn path(&self, state: &StateId) -> Option<&str> {
+        let position = *self.states.get(state)?;
+        Some(&self.paths[position as usize])
+    }
+
+    /// Returns the distinct paths, in the order they're first reached.
+    pub fn paths(&self) -> &[String] {
+        &self.paths
+    }
+
+    /// Maps the states of the fields with sampling hints, declared by the JSON schema of the
+    /// index, to their paths, see [`json_schema::field_hints`].
+    pub fn sampling_hints(&self, schema: &Value) -> Result<SamplingHints> {
+        let mut hints = json_schema::field_hints(schema)?;
+        hints.retain(|path, _| self.paths.contains(path));
+        let states = self
+            .states
+            .iter()
+            .map(|(&state, &position)| (state, &self.paths[position as usize]))
+            .filter(|(_, path)| hints.contains_key(*path))
+            .map(|(state, path)| (state, path.clone()))
+            .collect();
+        Ok(SamplingHints { states, hints })
+    }
+}
+
+/// Sampling hints of the fields generated in the states of an index, see
+/// [`FieldPaths::sampling_hints`].
+///
+/// Serializable, so that engines in other processes or languages apply them, while the crate
+/// stays agnostic of the inference.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SamplingHints {
+    /// Path of the field of each state, states of fields without hints are omitted.
+    pub states: BTreeMap<StateId, String>,
+    /// Hints of each field path, by their keyword without the `x-outlines-` prefix.
+    pub hints: BTreeMap<String, Map<String, Value>>,
+}
+
+impl SamplingHints {
+    /// Returns the hints of the field generated in the state, if any.
+    pub fn get(&self, state: &StateId) -> Option<&Map<String, Value>> {
+        self.hints.get(self.states.get(state)?)
+    }
+}
+
+/// Container, which a prefix of a JSON value is nested in.
+#[derive(Clone, Debug)]
+enum Frame {
+    /// Object, with the key of the current field once it's complete, and whether its value
+    /// is started.
+    Object {
+        key: Option<String>,
+        in_value: bool,
+    },
+    Array,
+}
+
+/// Tracks the containers of a prefix of a JSON value, byte by byte.
+#[derive(Clone, Debug, Default)]
+struct PathScanner {
+    stack: Vec<Frame>,
+    in_string: bool,
+    escaped: bool,
+    /// Bytes of the key being read, escapes aren't decoded.
+    key: Option<Vec<u8>>,
+}
+
+impl PathScanner {
+    fn feed(&mut self, byte: u8) {
+        if self.in_string {
+            if self.escaped {
+                self.escaped = false;
+            } else if byte == b'\\' {
+                self.escaped = true;
+                return;
+            } else if byte == b'"' {
+                self.in_string = false;
+                if let (Some(key), Some(Frame::Object { key: current, .. })) =
+                    (self.key.take(), self.stack.last_mut())
+                {
+                    *current = Some(String::from_utf8_lossy(&key).into_owned());
+                }
+                return;
+            }
+            if let Some(key) = &mut self.key {
+                key.push(byte);
+            }
+            return;
+        }
+        match byte {
+            b'"' => {
+                self.in_string = true;
+                if let Some(Frame::Object {
+                    in_value: false, ..
+                }) = self.stack.last()
+                {
+                    self.key = Some(Vec::new());
+                }
+            }
+            b':' => {
+                if let Some(Frame::Object { in_value, .. }) = self.stack.last_mut() {
+                    *in_value = true;
+                }
+            }
+            b',' => {
+                if let Some(Frame::Object { key, in_value }) = self.stack.last_mut() {
+                    *key = None;
+                    *in_value = false;
+                }
+            }
+            b'{' => self.stack.push(Frame::Object {
+                key: None,
+                in_value: false,
+            }),
+            b'[' => self.stack.push(Frame::Array),
+            b'}' | b']' => {
+                self.stack.pop();
+            }
+            _ => {}
+        }
+    }
+
+    /// Path of the innermost field, which the value is generated for.
+    fn path(&self) -> Option<String> {
+        let mut path = String::new();
+        for frame in &self.stack {
+            match frame {
+                Frame::Object {
+                    key: Some(key),
+                    in_value: true,
+                } => {
+                    if !path.is_empty() {
+                        path.push('.');
+                    }
+                    path.push_str(key);
+                }
+                Frame::Object { .. } => break,
+                Frame::Array => path.push_str("[]"),
+            }
+        }
+        (!path.is_empty()).then_some(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scanned_path(text: &str) -> Option<String> {
+        let mut scanner = PathScanner::default();
+        text.bytes().for_each(|byte| scanner.feed(byte));
+        scanner.path()
+    }
+
+    #[test]
+    fn scanned_paths() {
+        for (text, expected) in [
+            ("", None),
+            ("{", None),
+            (r#"{"na"#, None),
+            (r#"{"name""#, None),
+            (r#"{"name":"#, Some("name")),
+            (r#"{"name": "a,b:{"#, Some("name")),
+            (r#"{"name": "a\"}", "#, None),
+            (r#"{"a": {"b": [1, {"c": tr"#, Some("a.b[].c")),
+            (r#"{"a": {"b": [1, {"c": true}"#, Some("a.b[]")),
+            (r#"{"a": {"b": []}, "d": 1"#, Some("d")),
+            ("[[1], [", Some("[][]")),
+            (r#"{"a": {}}"#, None),
+        ] {
+            assert_eq!(scanned_path(text).as_deref(), expected, "{text}");
+        }
+    }
+
+    #[test]
+    fn paths_of_index_states() {
+        let vocabulary = Vocabulary::fixture();
+        let schema = r#"{
+            "type": "object",
+            "properties": {
+                "name": {"type": "string"},
+                "address": {
+                    "type": "object",
+                    "properties": {"city": {"type": "string"}},
+                    "required": ["city"]
+                }
+            },
+            "required": ["name", "address"]
+        }"#;
+        let regex = json_schema::regex_from_str(schema, Some(""), None).expect("Regex failed");
+        let index = Index::new(&regex, &vocabulary).expect("Index failed");
+        let paths = FieldPaths::new(&index, &vocabulary);
+        assert_eq!(paths.paths(), &["name", "address", "address.city"]);
+
+        let mut state = index.initial_state();
+        assert_eq!(paths.path(&state), None);
+        let text = r#"{"name":"x","address":{"city":"y"}}"#;
+        let mut expected = Vec::new();
+        for byte in text.bytes() {
+            let token_id = vocabulary.token_ids([byte]).expect("No token ids")[0];
+            state = index.next_state(&state, &token_id).expect("No next state");
+            expected.push(paths.path(&state).map(str::to_string));
+        }
+        assert_eq!(expected[8], Some("name".to_string()));
+        assert_eq!(expected[22], Some("address".to_string()));
+        assert_eq!(expected[31], Some("address.city".to_string()));
+        assert_eq!(expected.last(), Some(&None));
+
+        let schema: Value = serde_json::from_str(schema).expect("Invalid schema");
+        let mut schema_with_hints = schema.clone();
+        schema_with_hints["properties"]["address"]["x-outlines-temperature"] = 0.5.into();
+        // Hints of the root apply to all the fields.
+        schema_with_hints["x-outlines-top_k"] = 10.into();
+        assert_eq!(
+            paths.sampling_hints(&schema).expect("Hints failed"),
+            SamplingHints::default()
+        );
+        let hints = paths
+            .sampling_hints(&schema_with_hints)
+            .expect("Hints failed");
+        assert_eq!(
+            hints.hints.keys().collect::<Vec<_>>(),
+            ["address", "address.city", "name"]
+        );
+        assert_eq!(hints.get(&index.initial_state()), None);
+        let city = hints
+            .states
+            .iter()
+            .find(|(_, path)| *path == "address.city")
+            .map(|(state, _)| state)
+            .expect("No state of the city");
+        assert_eq!(
+            hints.get(city).and_then(|hints| hints.get("top_k")),
+            Some(&Value::from(10))
+        );
+        assert_eq!(
+            hints.get(city).and_then(|hints| hints.get("temperature")),
+            Some(&Value::from(0.5))
+        );
+
+        // A text, which isn't JSON, has no fields.
+        let index = Index::new("[a-z]+", &vocabulary).expect("Index failed");
+        assert!(FieldPaths::new(&index, &vocabulary).paths().is_empty());
+    }
+}
@@ -0,0 +1,1591 @@
+//! Guiding generation through the states of an `Index`, one token at a time.
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, OnceLock};
+
+use bincode::de::Decoder;
+use bincode::enc::Encoder;
+use bincode::error::{DecodeError, EncodeError};
+use bincode::{Decode, Encode};
+use rustc_hash::{FxHashMap as HashMap, FxHashSet as HashSet};
+use serde::{Deserialize, Serialize};
+
+use crate::bias::TransitionBiases;
+use crate::fields::FieldPaths;
+use crate::index::Index;
+#[cfg(feature = "schema-validation")]
+use crate::json_schema::{self, SchemaMismatch};
+use crate::mask;
+use crate::prelude::*;
+use crate::{Error, Result};
+
+/// `Guide` keeps track of the current state of generation within an [`Index`].
+///
+/// ## Example
+///
+/// ```rust
+/// # use outlines_core::Error;
+/// use std::sync::Arc;
+///
+/// use outlines_core::guide::FinishPolicy;
+/// use outlines_core::prelude::*;
+///
+/// # fn main() -> Result<(), Error> {
+/// let mut vocabulary = Vocabulary::new(3);
+/// for (token, token_id) in [("1", 1), ("2", 2)] {
+///     vocabulary.try_insert(token, token_id)?;
+/// }
+/// let index = Index::new("[1-9]", &vocabulary)?;
+///
+/// let mut guide = Guide::new(Arc::new(index), 32);
+/// assert_eq!(guide.allowed_tokens()?.len(), 2);
+///
+/// guide.advance(TokenId::new(1))?;
+/// assert!(guide.is_finished());
+/// assert_eq!(guide.allowed_tokens()?, vec![3]);
+///
+/// guide.rollback_state(1)?;
+/// assert!(!guide.is_finished());
+///
+/// // Only finished once the end-of-sequence token is consumed.
+/// let mut guide = guide.with_finish_policy(FinishPolicy::OnEos);
+/// guide.advance(TokenId::new(2))?;
+/// assert!(!guide.is_finished());
+/// guide.advance(TokenId::new(3))?;
+/// assert!(guide.is_finished());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq, Encode, Decode)]
+pub struct Guide {
+    /// The index guiding the generation.
+    index: Arc<Index>,
+    /// The current state.
+    state: StateId,
+    /// Previous states, kept to roll back the guide.
+    state_cache: VecDeque<StateId>,
+    /// Maximum number of states kept in `state_cache`.
+    max_rollback: usize,
+    /// Number of tokens consumed since the initial state.
+    consumed_tokens: usize,
+    /// When the guide is finished.
+    finish_policy: FinishPolicy,
+    /// Whether the end-of-sequence token was consumed, which ends the generation.
+    eos_consumed: bool,
+    /// Maximal number of consumed tokens, see [`Guide::with_max_tokens`].
+    max_tokens: Option<usize>,
+    /// Tokens consumed since the initial state, if recorded, see [`Guide::with_history`].
+    token_ids: Option<Vec<TokenId>>,
+    /// Maximal number of violating tokens, see [`Guide::with_max_violations`].
+    max_violations: usize,
+    /// Positions of the consumed violating tokens, in order.
+    violations: Vec<usize>,
+    /// Biases of the transitions, see [`Guide::with_biases`].
+    biases: Option<Arc<TransitionBiases>>,
+    /// Paths of the JSON fields of the states, see [`Guide::with_field_paths`].
+    field_paths: Option<Arc<FieldPaths>>,
+    /// How the consumed tokens were picked, if recorded, see [`Guide::with_token_events`].
+    events: Option<Vec<TokenEvent>>,
+    /// Masks of the recently visited states, see [`Guide::with_mask_cache`].
+    mask_cache: Option<MaskCache>,
+    /// JSON Schema of the generated value, see [`Guide::with_json_schema`].
+    #[cfg(feature = "schema-validation")]
+    schema: Option<Arc<str>>,
+}
+
+/// When a [`Guide`] is finished, since engines disagree on when to stop the generation.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, Encode, Decode)]
+#[serde(rename_all = "snake_case")]
+pub enum FinishPolicy {
+    /// Finished as soon as the end-of-sequence token is allowed, even if the generation
+    /// could continue.
+    #[default]
+    OnAccept,
+    /// Finished only once the end-of-sequence token is consumed by [`Guide::advance`].
+    OnEos,
+}
+
+/// Consumed token and how it was picked, see [`Guide::with_token_events`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, Encode, Decode)]
+pub struct TokenEvent {
+    /// The consumed token.
+    pub token_id: TokenId,
+    /// How the token was picked.
+    #[serde(flatten)]
+    pub kind: TokenEventKind,
+}
+
+/// Kind of a [`TokenEvent`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, Encode, Decode)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TokenEventKind {
+    /// The token was the only allowed one.
+    Forced,
+    /// The token was chosen among `options` allowed tokens.
+    Chosen { options: usize },
+    /// The end-of-sequence token, or a stop token.
+    Eos,
+    /// The token wasn't allowed and was tolerated as a violation.
+    Violation,
+}
+
+/// Allowed tokens of a state, as a list and as a bitmask, see [`Guide::state_mask`].
+#[derive(Debug)]
+pub struct StateMask {
+    tokens: Vec<TokenId>,
+    vocab_size: usize,
+    /// Bitmask of the tokens, packed on the first use.
+    words: OnceLock<Vec<u32>>,
+}
+
+impl StateMask {
+    fn new(tokens: Vec<TokenId>, vocab_size: usize) -> Self {
+        Self {
+            tokens,
+            vocab_size,
+            words: OnceLock::new(),
+        }
+    }
+
+    /// Returns the allowed tokens, as [`Guide::allowed_tokens`].
+    pub fn tokens(&self) -> &[TokenId] {
+        &self.tokens
+    }
+
+    /// Returns the bitmask of the allowed tokens, of [`mask::mask_len`] words.
+    pub fn words(&self) -> &[u32] {
+        self.words.get_or_init(|| {
+            let mut words = vec![0; mask::mask_len(self.vocab_size)];
+            mask::set_tokens(&mut words, &self.tokens);
+            words
+        })
+    }
+
+    /// Returns one flag per token of the vocabulary, set for the allowed tokens, e.g. for
+    /// frameworks masking logits with booleans rather than packed words.
+    pub fn to_bool_vec(&self) -> Vec<bool> {
+        let mut flags = vec![false; self.vocab_size];
+        for token_id in &self.tokens {
+            if let Some(flag) = flags.get_mut(token_id.as_usize()) {
+                *flag = true;
+            }
+        }
+        flags
+    }
+}
+
+/// Least recently used masks by state, shared by the clones of a guide.
+///
+/// Not serialized, nor compared, since it's derived from the index, only its capacity is.
+#[derive(Clone, Debug)]
+struct MaskCache {
+    capacity: usize,
+    entries: Arc<Mutex<MaskCacheEntries>>,
+}
+
+#[derive(Debug, Default)]
+struct MaskCacheEntries {
+    /// Masks by state, with the clock of their last use.
+    masks: HashMap<StateId, (Arc<StateMask>, u64)>,
+    clock: u64,
+}
+
+impl MaskCache {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: Arc::default(),
+        }
+    }
+
+    /// Returns the mask of the state, computing it on a miss.
+    fn get_or_insert(
+        &self,
+        state: StateId,
+        compute: impl FnOnce() -> Result<StateMask>,
+    ) -> Result<Arc<StateMask>> {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.clock += 1;
+        let clock = entries.clock;
+        if let Some((mask, last_used)) = entries.masks.get_mut(&state) {
+            *last_used = clock;
+            return Ok(mask.clone());
+        }
+        let mask = Arc::new(compute()?);
+        if entries.masks.len() >= self.capacity {
+            let oldest = entries
+                .masks
+                .iter()
+                .min_by_key(|(_, (_, last_used))| *last_used)
+                .map(|(&state, _)| state);
+            if let Some(oldest) = oldest {
+                entries.masks.remove(&oldest);
+            }
+        }
+        entries.masks.insert(state, (mask.clone(), clock));
+        Ok(mask)
+    }
+}
+
+impl PartialEq for MaskCache {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl Encode for MaskCache {
+    fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), EncodeError> {
+        self.capacity.encode(encoder)
+    }
+}
+
+impl<Context> Decode<Context> for MaskCache {
+    fn decode<D: Decoder<Context = Context>>(decoder: &mut D) -> Result<Self, DecodeError> {
+        Ok(Self::new(usize::decode(decoder)?))
+    }
+}
+
+bincode::impl_borrow_decode!(MaskCache);
+
+/// Serializable state of a [`Guide`], allowing to resume generation in another process
+/// with the same [`Index`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Encode, Decode)]
+pub struct GuideSnapshot {
+    /// The current state of the guide.
+    pub state: StateId,
+    /// Number of tokens consumed since the initial state.
+    pub consumed_tokens: usize,
+    /// Maximum number of states kept for rollback.
+    pub max_rollback: usize,
+    /// Fingerprint of the index the guide was advancing through.
+    pub index_fingerprint: u64,
+    /// When the guide is finished.
+    #[serde(default)]
+    pub finish_policy: FinishPolicy,
+    /// Whether the end-of-sequence token was consumed.
+    #[serde(default)]
+    pub eos_consumed: bool,
+    /// Maximal number of consumed tokens, if any.
+    #[serde(default)]
+    pub max_tokens: Option<usize>,
+    /// Tokens consumed since the initial state, if recorded.
+    #[serde(default)]
+    pub token_ids: Option<Vec<TokenId>>,
+    /// Maximal number of violating tokens.
+    #[serde(default)]
+    pub max_violations: usize,
+    /// Positions of the consumed violating tokens.
+    #[serde(default)]
+    pub violations: Vec<usize>,
+    /// How the consumed tokens were picked, if recorded.
+    #[serde(default)]
+    pub events: Option<Vec<TokenEvent>>,
+}
+
+/// Consequences of consuming a token, computed by [`Guide::propose`] without consuming it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Proposal {
+    /// The index of the guide the proposal was made by.
+    index: Arc<Index>,
+    /// The state of the guide the proposal was made by.
+    state: StateId,
+    /// Number of tokens consumed by the guide the proposal was made by.
+    consumed_tokens: usize,
+    token_id: TokenId,
+    next_state: StateId,
+    violates: bool,
+    allowed_tokens: Vec<TokenId>,
+}
+
+impl Proposal {
+    /// Returns the proposed token.
+    pub fn token_id(&self) -> TokenId {
+        self.token_id
+    }
+
+    /// Returns the state, which consuming the token leads to.
+    pub fn next_state(&self) -> StateId {
+        self.next_state
+    }
+
+    /// Whether the token would be consumed as a violation, see [`Guide::with_max_violations`].
+    pub fn is_violation(&self) -> bool {
+        self.violates
+    }
+
+    /// Lists the tokens allowed once the token is consumed, as [`Guide::allowed_tokens`].
+    pub fn allowed_tokens(&self) -> &[TokenId] {
+        &self.allowed_tokens
+    }
+
+    /// Whether the end-of-sequence token is allowed once the token is consumed.
+    pub fn allows_eos(&self) -> bool {
+        self.allowed_tokens.contains(&self.index.eos_token_id())
+    }
+
+    /// Consumes the proposed token by the guide, as [`Guide::advance`] does, and returns the
+    /// next state.
+    ///
+    /// Fails if the guide isn't the one the proposal was made by, or if it moved since.
+    pub fn commit(self, guide: &mut Guide) -> Result<StateId> {
+        if !Arc::ptr_eq(&self.index, &guide.index)
+            || self.state != guide.state
+            || self.consumed_tokens != guide.consumed_tokens
+        {
+            return Err(Error::StaleProposal {
+                token_id: self.token_id,
+                state: self.state,
+            });
+        }
+        guide.advance(self.token_id)
+    }
+}
+
+impl Guide {
+    /// Creates a guide starting from the initial state of the index, which keeps
+    /// up to `max_rollback` previous states.
+    pub fn new(index: Arc<Index>, max_rollback: usize) -> Self {
+        Self {
+            state: index.initial_state(),
+            index,
+            state_cache: VecDeque::with_capacity(max_rollback),
+            max_rollback,
+            consumed_tokens: 0,
+            finish_policy: FinishPolicy::default(),
+            eos_consumed: false,
+            max_tokens: None,
+            token_ids: None,
+            max_violations: 0,
+            violations: Vec::new(),
+            biases: None,
+            field_paths: None,
+            events: None,
+            mask_cache: None,
+            #[cfg(feature = "schema-validation")]
+            schema: None,
+        }
+    }
+
+    /// Creates a guide starting from a state of the index, e.g. reached by previously
+    /// generated output, which keeps up to `max_rollback` previous states.
+    ///
+    /// Tokens are consumed, and budgeted by [`Guide::with_max_tokens`], from that state, so
+    /// the generated text excludes the previous output, while [`Guide::reset`] moves back to
+    /// the initial state of the index. Fails if the state isn't in the index.
+    pub fn from_state(index: Arc<Index>, state: StateId, max_rollback: usize) -> Result<Self> {
+        if !index.contains_state(&state) {
+            return Err(Error::StateNotFound(state));
+        }
+        let mut guide = Self::new(index, max_rollback);
+        guide.state = state;
+        Ok(guide)
+    }
+
+    /// Sets when the guide is finished, see [`FinishPolicy`].
+    pub fn with_finish_policy(self, finish_policy: FinishPolicy) -> Self {
+        Self {
+            finish_policy,
+            ..self
+        }
+    }
+
+    /// Limits the generation to `max_tokens` tokens, the end-of-sequence and stop tokens aside.
+    ///
+    /// A token is allowed only if a final state can still be reached within the budget, see
+    /// [`Index::distance_to_final`], so that all the open strings, arrays or objects are
+    /// closed in time, instead of being truncated.
+    pub fn with_max_tokens(self, max_tokens: usize) -> Self {
+        Self {
+            max_tokens: Some(max_tokens),
+            ..self
+        }
+    }
+
+    /// Tolerates up to `max_violations` tokens, which the index doesn't allow, for comparing
+    /// strictly and softly constrained decoding.
+    ///
+    /// A violating token is consumed without moving, so that the generation re-synchronizes
+    /// with the constraint from the last valid state. The end-of-sequence and stop tokens are
+    /// never tolerated, nor tokens exceeding the budget of [`Guide::with_max_tokens`].
+    /// [`Guide::allowed_tokens`] still lists the tokens of the index only.
+    pub fn with_max_violations(self, max_violations: usize) -> Self {
+        Self {
+            max_violations,
+            ..self
+        }
+    }
+
+    /// Attaches biases to the transitions of the index, see [`Guide::token_biases`].
+    pub fn with_biases(self, biases: Arc<TransitionBiases>) -> Self {
+        Self {
+            biases: Some(biases),
+            ..self
+        }
+    }
+
+    /// Attaches the paths of the JSON fields of the index states, see [`Guide::current_path`].
+    pub fn with_field_paths(self, field_paths: Arc<FieldPaths>) -> Self {
+        Self {
+            field_paths: Some(field_paths),
+            ..self
+        }
+    }
+
+    /// Records how each consumed token was picked: forced as the only allowed token, chosen
+    /// among several ones, the end-of-sequence token or a violation, see
+    /// [`Guide::token_events`], e.g. to measure how constraining a schema is.
+    ///
+    /// The allowed tokens are counted on each [`Guide::advance`], which costs as much as
+    /// listing them.
+    pub fn with_token_events(self) -> Self {
+        Self {
+            events: Some(Vec::with_capacity(self.consumed_tokens)),
+            ..self
+        }
+    }
+
+    /// Records the consumed tokens, or stops recording them, so that the generated output
+    /// can be decoded by [`Guide::generated_bytes`].
+    ///
+    /// The history grows with each consumed token, so it's off by default. Tokens consumed
+    /// before it's enabled are unknown to the guide.
+    pub fn with_history(self, enabled: bool) -> Self {
+        Self {
+            token_ids: enabled.then(|| self.token_ids.unwrap_or_default()),
+            ..self
+        }
+    }
+
+    /// Caches the masks of up to `capacity` recently visited states, e.g. inside string
+    /// bodies, which are revisited at almost every step, see [`Guide::state_mask`].
+    ///
+    /// The cache is shared by the clones of the guide. Masks aren't cached with a budget of
+    /// tokens, since they depend on the number of consumed tokens then.
+    pub fn with_mask_cache(self, capacity: usize) -> Self {
+        Self {
+            mask_cache: (capacity > 0).then(|| MaskCache::new(capacity)),
+            ..self
+        }
+    }
+
+    /// Sets the JSON Schema, which the generated value is validated against by
+    /// [`Guide::finish_and_validate`].
+    ///
+    /// The consumed tokens are recorded from then on, as with [`Guide::with_history`].
+    #[cfg(feature = "schema-validation")]
+    pub fn with_json_schema(self, schema: impl Into<Arc<str>>) -> Self {
+        Self {
+            schema: Some(schema.into()),
+            ..self.with_history(true)
+        }
+    }
+
+    /// Resumes a guide from a snapshot taken with [`Guide::export_state`].
+    ///
+    /// Fails if the snapshot was taken with a different index.
+    pub fn resume(index: Arc<Index>, snapshot: &GuideSnapshot) -> Result<Self> {
+        if index.fingerprint() != snapshot.index_fingerprint {
+            return Err(Error::IndexFingerprintMismatch {
+                expected: snapshot.index_fingerprint,
+                found: index.fingerprint(),
+            });
+        }
+        if !index.contains_state(&snapshot.state) {
+            return Err(Error::StateNotFound(snapshot.state));
+        }
+        let mut guide =
+            Self::new(index, snapshot.max_rollback).with_finish_policy(snapshot.finish_policy);
+        guide.max_tokens = snapshot.max_tokens;
+        guide.state = snapshot.state;
+        guide.consumed_tokens = snapshot.consumed_tokens;
+        guide.token_ids = snapshot.token_ids.clone();
+        guide.max_violations = snapshot.max_violations;
+        guide.violations = snapshot.violations.clone();
+        guide.events = snapshot.events.clone();
+        guide.eos_consumed = snapshot.eos_consumed && guide.index.is_accepting(&guide.state);
+        Ok(guide)
+    }
+
+    /// Exports the current state of the guide.
+    ///
+    /// Previous states kept for rollback are not exported.
+    pub fn export_state(&self) -> GuideSnapshot {
+        GuideSnapshot {
+            state: self.state,
+            consumed_tokens: self.consumed_tokens,
+            max_rollback: self.max_rollback,
+            index_fingerprint: self.index.fingerprint(),
+            finish_policy: self.finish_policy,
+            eos_consumed: self.eos_consumed,
+            max_tokens: self.max_tokens,
+            token_ids: self.token_ids.clone(),
+            max_violations: self.max_violations,
+            violations: self.violations.clone(),
+            events: self.events.clone(),
+        }
+    }
+
+    /// Returns the index of the guide.
+    pub fn index(&self) -> &Arc<Index> {
+        &self.index
+    }
+
+    /// Returns the current state.
+    pub fn state(&self) -> StateId {
+        self.state
+    }
+
+    /// Returns the number of tokens consumed since the initial state.
+    pub fn consumed_tokens(&self) -> usize {
+        self.consumed_tokens
+    }
+
+    /// Returns when the guide is finished.
+    pub fn finish_policy(&self) -> FinishPolicy {
+        self.finish_policy
+    }
+
+    /// Returns the number of tokens left in the budget, if the guide has one.
+    pub fn remaining_tokens(&self) -> Option<usize> {
+        self.max_tokens
+            .map(|max_tokens| max_tokens.saturating_sub(self.consumed_tokens))
+    }
+
+    /// Returns the positions of the violating tokens consumed since the initial state, see
+    /// [`Guide::with_max_violations`].
+    pub fn violations(&self) -> &[usize] {
+        &self.violations
+    }
+
+    /// Returns how the tokens consumed since the initial state were picked, in order, if
+    /// recorded, see [`Guide::with_token_events`].
+    pub fn token_events(&self) -> Option<&[TokenEvent]> {
+        self.events.as_deref()
+    }
+
+    /// Returns the number of violating tokens which are still tolerated.
+    pub fn remaining_violations(&self) -> usize {
+        self.max_violations.saturating_sub(self.violations.len())
+    }
+
+    /// Returns the minimal number of tokens needed to reach a final state from the current
+    /// state, without the end-of-sequence token.
+    pub fn min_tokens_to_finish(&self) -> Option<usize> {
+        self.index.distance_to_final(&self.state)
+    }
+
+    /// Lists allowed tokens for the current state.
+    ///
+    /// Once the end-of-sequence token is consumed, it's the only allowed token. Fails if the
+    /// budget of tokens is too small to reach a final state.
+    pub fn allowed_tokens(&self) -> Result<Vec<TokenId>> {
+        #[cfg(feature = "metrics")]
+        crate::metrics::increment(crate::metrics::MASKS_SERVED);
+        self.allowed_tokens_at(self.state, self.eos_consumed, self.consumed_tokens)
+    }
+
+    /// Returns the allowed tokens for the current state, as a list and as a bitmask, from
+    /// the cache of [`Guide::with_mask_cache`], if any.
+    pub fn state_mask(&self) -> Result<Arc<StateMask>> {
+        #[cfg(feature = "metrics")]
+        crate::metrics::increment(crate::metrics::MASKS_SERVED);
+        let compute = || {
+            let tokens =
+                self.allowed_tokens_at(self.state, self.eos_consumed, self.consumed_tokens)?;
+            Ok(StateMask::new(tokens, self.index.vocab_size()))
+        };
+        match &self.mask_cache {
+            Some(cache) if self.max_tokens.is_none() && !self.eos_consumed => {
+                cache.get_or_insert(self.state, compute)
+            }
+            _ => compute().map(Arc::new),
+        }
+    }
+
+    /// Whether masks are cached, see [`Guide::with_mask_cache`].
+    pub fn has_mask_cache(&self) -> bool {
+        self.mask_cache.is_some()
+    }
+
+    /// Lists allowed tokens for the state, reached after `consumed` tokens.
+    fn allowed_tokens_at(
+        &self,
+        state: StateId,
+        eos_consumed: bool,
+        consumed: usize,
+    ) -> Result<Vec<TokenId>> {
+        if eos_consumed {
+            return Ok(vec![self.index.eos_token_id()]);
+        }
+        if let Some(max_tokens) = self.max_tokens {
+            let required = self
+                .index
+                .distance_to_final(&state)
+                .ok_or(Error::NoAllowedTokens(state))?
+                + consumed;
+            if required > max_tokens {
+                return Err(Error::TokenBudgetExceeded {
+                    max_tokens,
+                    required,
+                });
+            }
+            return self
+                .index
+                .allowed_tokens_iter(&state)
+                .map(|tokens| {
+                    tokens
+                        .filter(|token_id| self.is_allowed_at(state, false, consumed, token_id))
+                        .copied()
+                        .collect()
+                })
+                .ok_or(Error::NoAllowedTokens(state));
+        }
+        // Since guide advances only through the states offered by the index, it means
+        // `None` here shouldn't happen and it's an issue at index creation step.
+        self.index
+            .allowed_tokens(&state)
+            .ok_or(Error::NoAllowedTokens(state))
+    }
+
+    /// Lists the biases of the allowed tokens for the current state, sorted by token id, to
+    /// be added to their logits besides the mask, see [`Guide::with_biases`].
+    pub fn token_biases(&self) -> Vec<(TokenId, f32)> {
+        let Some(biases) = &self.biases else {
+            return Vec::new();
+        };
+        biases
+            .biases(&self.state)
+            .iter()
+            .filter(|(token_id, _)| {
+                self.is_allowed_at(
+                    self.state,
+                    self.eos_consumed,
+                    self.consumed_tokens,
+                    token_id,
+                )
+            })
+            .copied()
+            .collect()
+    }
+
+    /// Returns the path of the JSON field being generated, e.g. `address.city`, if the paths
+    /// are attached with [`Guide::with_field_paths`].
+    pub fn current_path(&self) -> Option<String> {
+        self.field_paths
+            .as_ref()?
+            .path(&self.state)
+            .map(str::to_string)
+    }
+
+    /// Iterates over allowed tokens for the current state, without collecting them.
+    pub fn allowed_tokens_iter(&self) -> Option<impl Iterator<Item = &TokenId>> {
+        let tokens = self.index.allowed_tokens_iter(&self.state)?;
+        Some(tokens.filter(move |token_id| {
+            self.is_allowed_at(
+                self.state,
+                self.eos_consumed,
+                self.consumed_tokens,
+                token_id,
+            )
+        }))
+    }
+
+    /// Checks if a token of the index, leaving the state reached after `consumed` tokens, is
+    /// allowed.
+    fn is_allowed_at(
+        &self,
+        state: StateId,
+        eos_consumed: bool,
+        consumed: usize,
+        token_id: &TokenId,
+    ) -> bool {
+        if eos_consumed {
+            return *token_id == self.index.eos_token_id();
+        }
+        self.max_tokens.is_none()
+            || self.index.is_stop_token(token_id)
+            || self
+                .index
+                .next_state(&state, token_id)
+                .is_some_and(|next| self.fits_budget(consumed, next))
+    }
+
+    /// Computes the state and the allowed tokens, which consuming the token would lead to,
+    /// without consuming it, e.g. to prefer the end-of-sequence token once it's allowed.
+    ///
+    /// The proposal is consumed by [`Proposal::commit`], as long as the guide doesn't move
+    /// in between. Fails if [`Guide::advance`] would fail with the token.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// # use outlines_core::Error;
+    /// use std::sync::Arc;
+    ///
+    /// use outlines_core::prelude::*;
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// let mut vocabulary = Vocabulary::new(3);
+    /// for (token, token_id) in [("1", 1), ("2", 2)] {
+    ///     vocabulary.try_insert(token, token_id)?;
+    /// }
+    /// let index = Index::new("[1-9]{1,2}", &vocabulary)?;
+    /// let mut guide = Guide::new(Arc::new(index), 32);
+    ///
+    /// let proposal = guide.propose(TokenId::new(1))?;
+    /// assert!(proposal.allowed_tokens().contains(&TokenId::new(3)));
+    /// assert_eq!(guide.consumed_tokens(), 0);
+    ///
+    /// proposal.commit(&mut guide)?;
+    /// assert_eq!(guide.consumed_tokens(), 1);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn propose(&self, token_id: TokenId) -> Result<Proposal> {
+        let is_eos = self.index.is_stop_token(&token_id);
+        // Once consumed, the end-of-sequence token is ignored, as by `Guide::advance`.
+        let (next_state, violates, consumed) = if self.eos_consumed && is_eos {
+            (self.state, false, self.consumed_tokens)
+        } else {
+            let (next_state, violates) = self
+                .step(
+                    self.state,
+                    self.eos_consumed,
+                    self.consumed_tokens,
+                    self.violations.len(),
+                    token_id,
+                )
+                .ok_or(Error::NoNextState {
+                    state: self.state,
+                    token_id,
+                })?;
+            (next_state, violates, self.consumed_tokens + 1)
+        };
+        let allowed_tokens =
+            self.allowed_tokens_at(next_state, self.eos_consumed || is_eos, consumed)?;
+        Ok(Proposal {
+            index: self.index.clone(),
+            state: self.state,
+            consumed_tokens: self.consumed_tokens,
+            token_id,
+            next_state,
+            violates,
+            allowed_tokens,
+        })
+    }
+
+    /// Moves to the next state provided by the token id and returns it.
+    ///
+    /// With [`FinishPolicy::OnEos`], the end-of-sequence token, or a stop token, is consumed
+    /// without moving, if the state is accepting, then no other token is allowed, while the
+    /// end-of-sequence and stop tokens are ignored.
+    ///
+    /// A token which isn't allowed is consumed as a violation without moving, while the
+    /// guide tolerates violations, see [`Guide::with_max_violations`].
+    pub fn advance(&mut self, token_id: TokenId) -> Result<StateId> {
+        let is_eos = self.index.is_stop_token(&token_id);
+        if self.eos_consumed && is_eos {
+            return Ok(self.state);
+        }
+        let options = self
+            .events
+            .is_some()
+            .then(|| self.allowed_tokens_iter().map_or(0, Iterator::count));
+        match self.step(
+            self.state,
+            self.eos_consumed,
+            self.consumed_tokens,
+            self.violations.len(),
+            token_id,
+        ) {
+            Some((new_state, violates)) => {
+                if violates {
+                    self.violations.push(self.consumed_tokens);
+                }
+                if let (Some(events), Some(options)) = (&mut self.events, options) {
+                    let kind = match options {
+                        _ if is_eos => TokenEventKind::Eos,
+                        _ if violates => TokenEventKind::Violation,
+                        0 | 1 => TokenEventKind::Forced,
+                        options => TokenEventKind::Chosen { options },
+                    };
+                    events.push(TokenEvent { token_id, kind });
+                }
+                if self.max_rollback > 0 {
+                    // Free up space in state_cache if needed.
+                    if self.state_cache.len() == self.max_rollback {
+                        self.state_cache.pop_front();
+                    }
+                    self.state_cache.push_back(self.state);
+                }
+                self.state = new_state;
+                self.consumed_tokens += 1;
+                if let Some(token_ids) = &mut self.token_ids {
+                    token_ids.push(token_id);
+                }
+                self.eos_consumed = is_eos;
+                Ok(new_state)
+            }
+            None => {
+                #[cfg(feature = "metrics")]
+                crate::metrics::increment(crate::metrics::REJECTED_TOKENS);
+                Err(Error::NoNextState {
+                    state: self.state,
+                    token_id,
+                })
+            }
+        }
+    }
+
+    /// Returns the number of rollback steps available.
+    pub fn allowed_rollback(&self) -> usize {
+        self.state_cache.len()
+    }
+
+    /// Rolls back the guide `n` tokens (states).
+    ///
+    /// Fails if `n` is greater than the number of stored prior states.
+    pub fn rollback_state(&mut self, n: usize) -> Result<()> {
+        if n > self.allowed_rollback() {
+            return Err(Error::RollbackUnavailable {
+                steps: n,
+                available: self.allowed_rollback(),
+                max_rollback: self.max_rollback,
+            });
+        }
+        for _ in 0..n {
+            // Length is checked above.
+            if let Some(state) = self.state_cache.pop_back() {
+                self.state = state;
+                self.consumed_tokens = self.consumed_tokens.saturating_sub(1);
+                if let Some(token_ids) = &mut self.token_ids {
+                    token_ids.pop();
+                }
+                // The end-of-sequence token is always the last consumed one.
+                self.eos_consumed = false;
+            }
+        }
+        while self
+            .violations
+            .last()
+            .is_some_and(|&position| position >= self.consumed_tokens)
+        {
+            self.violations.pop();
+        }
+        if let Some(events) = &mut self.events {
+            events.truncate(self.consumed_tokens);
+        }
+        Ok(())
+    }
+
+    /// Checks whether the sequence of tokens is accepted from the current state.
+    pub fn accepts_tokens(&self, token_ids: &[TokenId]) -> bool {
+        let mut state = self.state;
+        let mut eos_consumed = self.eos_consumed;
+        let mut consumed = self.consumed_tokens;
+        let mut violations = self.violations.len();
+        for &token_id in token_ids {
+            let is_eos = self.index.is_stop_token(&token_id);
+            if eos_consumed && is_eos {
+                continue;
+            }
+            match self.step(state, eos_consumed, consumed, violations, token_id) {
+                Some((s, violates)) => {
+                    state = s;
+                    violations += usize::from(violates);
+                }
+                None => return false,
+            }
+            eos_consumed = is_eos;
+            consumed += 1;
+        }
+        true
+    }
+
+    /// Checks if the guide is finished according to its [`FinishPolicy`].
+    pub fn is_finished(&self) -> bool {
+        match self.finish_policy {
+            FinishPolicy::OnAccept => self.index.is_accepting(&self.state),
+            FinishPolicy::OnEos => self.eos_consumed,
+        }
+    }
+
+    /// Returns the state reached with the token, after `consumed` tokens. The end-of-sequence
+    /// token keeps an accepting state, if it's consumed by the policy.
+    fn next_state(
+        &self,
+        state: StateId,
+        eos_consumed: bool,
+        consumed: usize,
+        token_id: TokenId,
+    ) -> Option<StateId> {
+        if eos_consumed {
+            None
+        } else if self.index.is_stop_token(&token_id) {
+            let consumed = self.finish_policy == FinishPolicy::OnEos;
+            (consumed && self.index.is_accepting(&state)).then_some(state)
+        } else {
+            self.index
+                .next_state(&state, &token_id)
+                .filter(|&next| self.fits_budget(consumed, next))
+        }
+    }
+
+    /// Returns the state reached with the token, like [`Guide::next_state`], or the same state
+    /// if the token is tolerated as one more violation after `violations` of them, with
+    /// whether it's a violation.
+    fn step(
+        &self,
+        state: StateId,
+        eos_consumed: bool,
+        consumed: usize,
+        violations: usize,
+        token_id: TokenId,
+    ) -> Option<(StateId, bool)> {
+        if let Some(next) = self.next_state(state, eos_consumed, consumed, token_id) {
+            return Some((next, false));
+        }
+        let tolerated = !eos_consumed
+            && violations < self.max_violations
+            && !self.index.is_stop_token(&token_id)
+            && self.fits_budget(consumed, state);
+        tolerated.then_some((state, true))
+    }
+
+    /// Checks if a final state can be reached from `next` within the budget, once the token
+    /// leading to it is consumed after `consumed` tokens.
+    fn fits_budget(&self, consumed: usize, next: StateId) -> bool {
+        let Some(max_tokens) = self.max_tokens else {
+            return true;
+        };
+        let remaining = max_tokens.saturating_sub(consumed);
+        self.index
+            .distance_to_final(&next)
+            .is_some_and(|distance| distance < remaining)
+    }
+
+    /// Moves back to the initial state, forgetting previous states.
+    pub fn reset(&mut self) {
+        self.state = self.index.initial_state();
+        self.state_cache.clear();
+        self.consumed_tokens = 0;
+        self.eos_consumed = false;
+        if let Some(token_ids) = &mut self.token_ids {
+            token_ids.clear();
+        }
+        self.violations.clear();
+        if let Some(events) = &mut self.events {
+            events.clear();
+        }
+    }
+
+    /// Returns the bytes generated since the initial state, decoded from the consumed tokens
+    /// with the vocabulary of the index. The end-of-sequence and stop tokens are skipped.
+    ///
+    /// Fails if the guide doesn't record its history, see [`Guide::with_history`], or if some
+    /// tokens are unknown: consumed before the history was enabled or before resuming from a
+    /// snapshot without them, or missing from the vocabulary.
+    pub fn generated_bytes(&self, vocabulary: &Vocabulary) -> Result<Vec<u8>> {
+        let history = self.token_ids.as_ref().ok_or(Error::TokenHistoryDisabled)?;
+        if history.len() != self.consumed_tokens {
+            return Err(Error::IncompleteTokenHistory {
+                consumed: self.consumed_tokens,
+                recorded: history.len(),
+            });
+        }
+        let token_ids: HashSet<TokenId> = history.iter().copied().collect();
+        let mut tokens: HashMap<TokenId, &[u8]> = HashMap::default();
+        for (token, ids) in vocabulary.tokens() {
+            for &token_id in ids.iter().filter(|id| token_ids.contains(id)) {
+                tokens.insert(token_id, token);
+            }
+        }
+        let mut bytes = Vec::new();
+        for &token_id in history {
+            if self.index.is_stop_token(&token_id) {
+                continue;
+            }
+            let token = tokens
+                .get(&token_id)
+                .ok_or(Error::UnknownTokenId(token_id))?;
+            bytes.extend_from_slice(token);
+        }
+        Ok(bytes)
+    }
+
+    /// Returns the text generated since the initial state, see [`Guide::generated_bytes`].
+    ///
+    /// Invalid UTF-8 sequences, e.g. a character split between tokens of an unfinished
+    /// generation, are replaced with `U+FFFD`.
+    pub fn text(&self, vocabulary: &Vocabulary) -> Result<String> {
+        let bytes = self.generated_bytes(vocabulary)?;
+        Ok(String::from_utf8_lossy(&bytes).into_owned())
+    }
+
+    /// Validates the value generated by the finished guide against its JSON Schema, see
+    /// [`Guide::with_json_schema`], and returns its mismatches, if any.
+    ///
+    /// The generated regular expression approximates some keywords of the schema, this check
+    /// reports the values, which it permits while the schema doesn't.
+    #[cfg(feature = "schema-validation")]
+    pub fn finish_and_validate(&self, vocabulary: &Vocabulary) -> Result<Vec<SchemaMismatch>> {
+        let schema = self.schema.as_deref().ok_or(Error::GuideWithoutSchema)?;
+        if !self.is_finished() {
+            return Err(Error::GuideNotFinished(self.state));
+        }
+        json_schema::validate(schema, &self.generated_bytes(vocabulary)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn index() -> Arc<Index> {
+        let mut vocabulary = Vocabulary::new(4);
+        for (token, token_id) in [("a", 1), ("b", 2), ("z", 3)] {
+            vocabulary
+                .try_insert(token, token_id)
+                .expect("Insert failed");
+        }
+        Arc::new(Index::new("z[ab]z", &vocabulary).expect("Index failed"))
+    }
+
+    #[test]
+    fn advance_and_rollback() {
+        let mut guide = Guide::new(index(), 2);
+        let initial_state = guide.state();
+        assert_eq!(guide.allowed_tokens().expect("No allowed tokens"), vec![3]);
+
+        for token_id in [3, 1, 3].map(TokenId::new) {
+            guide.advance(token_id).expect("Advance failed");
+        }
+        assert!(guide.is_finished());
+        assert_eq!(guide.consumed_tokens(), 3);
+        assert_eq!(guide.allowed_rollback(), 2);
+
+        match guide.advance(TokenId::new(3)) {
+            Err(Error::NoNextState { token_id, .. }) if token_id == 3 => {}
+            _ => unreachable!(),
+        }
+        match guide.rollback_state(3) {
+            Err(Error::RollbackUnavailable { available: 2, .. }) => {}
+            _ => unreachable!(),
+        }
+
+        guide.rollback_state(2).expect("Rollback failed");
+        assert_eq!(guide.consumed_tokens(), 1);
+        assert!(guide.accepts_tokens(&[2, 3].map(TokenId::new)));
+        assert!(!guide.accepts_tokens(&[3].map(TokenId::new)));
+
+        guide.reset();
+        assert_eq!(guide.state(), initial_state);
+        assert_eq!(guide.allowed_rollback(), 0);
+    }
+
+    #[test]
+    fn finish_policies() {
+        let mut vocabulary = Vocabulary::new(4);
+        for (token, token_id) in [("a", 1), ("z", 3)] {
+            vocabulary
+                .try_insert(token, token_id)
+                .expect("Insert failed");
+        }
+        let index = Arc::new(Index::new("z+", &vocabulary).expect("Index failed"));
+
+        let mut guide = Guide::new(index.clone(), 4);
+        guide.advance(TokenId::new(3)).expect("Advance failed");
+        assert!(guide.is_finished());
+        assert!(guide.advance(TokenId::new(4)).is_err());
+
+        let mut guide = Guide::new(index, 4).with_finish_policy(FinishPolicy::OnEos);
+        guide.advance(TokenId::new(3)).expect("Advance failed");
+        assert!(!guide.is_finished());
+        assert!(guide.accepts_tokens(&[3, 4, 4].map(TokenId::new)));
+        assert!(!guide.accepts_tokens(&[4, 3].map(TokenId::new)));
+
+        let state = guide.advance(TokenId::new(4)).expect("Advance failed");
+        assert!(guide.is_finished());
+        assert_eq!(guide.consumed_tokens(), 2);
+        assert_eq!(guide.allowed_tokens().expect("No allowed tokens"), vec![4]);
+        assert_eq!(
+            guide.advance(TokenId::new(4)).expect("Advance failed"),
+            state
+        );
+        assert!(matches!(
+            guide.advance(TokenId::new(3)),
+            Err(Error::NoNextState { token_id, .. }) if token_id == 3
+        ));
+
+        let snapshot = guide.export_state();
+        let resumed = Guide::resume(guide.index().clone(), &snapshot).expect("Resume failed");
+        assert_eq!(resumed.finish_policy(), FinishPolicy::OnEos);
+        assert!(resumed.is_finished());
+
+        guide.rollback_state(1).expect("Rollback failed");
+        assert!(!guide.is_finished());
+        guide.advance(TokenId::new(3)).expect("Advance failed");
+
+        guide.reset();
+        assert!(matches!(
+            guide.advance(TokenId::new(4)),
+            Err(Error::NoNextState { token_id, .. }) if token_id == 4
+        ));
+    }
+
+    #[test]
+    fn stop_tokens() {
+        let mut vocabulary = Vocabulary::new(4);
+        for (token, token_id) in [("a", 1), ("z", 3)] {
+            vocabulary
+                .try_insert(token, token_id)
+                .expect("Insert failed");
+        }
+        vocabulary.try_insert_stop_token(5).expect("Insert failed");
+        let index = Arc::new(Index::new("z+", &vocabulary).expect("Index failed"));
+
+        let mut guide = Guide::new(index, 4).with_finish_policy(FinishPolicy::OnEos);
+        assert!(!guide.accepts_tokens(&[5].map(TokenId::new)));
+        guide.advance(TokenId::new(3)).expect("Advance failed");
+        assert!(guide.accepts_tokens(&[5, 4].map(TokenId::new)));
+        assert!(!guide.accepts_tokens(&[5, 3].map(TokenId::new)));
+
+        let state = guide.advance(TokenId::new(5)).expect("Advance failed");
+        assert!(guide.is_finished());
+        assert_eq!(
+            guide.advance(TokenId::new(5)).expect("Advance failed"),
+            state
+        );
+        assert_eq!(guide.allowed_tokens().expect("No allowed tokens"), vec![4]);
+    }
+
+    #[test]
+    fn token_budget() {
+        let mut vocabulary = Vocabulary::new(4);
+        for (token, token_id) in [("\"", 1), ("a", 2), ("a\"", 3)] {
+            vocabulary
+                .try_insert(token, token_id)
+                .expect("Insert failed");
+        }
+        let index = Arc::new(Index::new("\"a*\"", &vocabulary).expect("Index failed"));
+        assert_eq!(index.distance_to_final(&index.initial_state()), Some(2));
+
+        let mut guide = Guide::new(index.clone(), 4).with_max_tokens(3);
+        assert_eq!(guide.remaining_tokens(), Some(3));
+        assert_eq!(guide.min_tokens_to_finish(), Some(2));
+        assert!(guide.accepts_tokens(&[1, 2, 1].map(TokenId::new)));
+        assert!(!guide.accepts_tokens(&[1, 2, 2, 1].map(TokenId::new)));
+
+        guide.advance(TokenId::new(1)).expect("Advance failed");
+        let mut allowed = guide.allowed_tokens().expect("No allowed tokens");
+        allowed.sort();
+        assert_eq!(allowed, vec![1, 2, 3]);
+
+        // The last token must close the string.
+        guide.advance(TokenId::new(2)).expect("Advance failed");
+        let mut allowed = guide.allowed_tokens().expect("No allowed tokens");
+        allowed.sort();
+        assert_eq!(allowed, vec![1, 3]);
+        assert_eq!(
+            guide.allowed_tokens_iter().map(|tokens| tokens.count()),
+            Some(2)
+        );
+        assert!(matches!(
+            guide.advance(TokenId::new(2)),
+            Err(Error::NoNextState { token_id, .. }) if token_id == 2
+        ));
+
+        let snapshot = guide.export_state();
+        let resumed = Guide::resume(index.clone(), &snapshot).expect("Resume failed");
+        assert_eq!(resumed.remaining_tokens(), Some(1));
+
+        guide.advance(TokenId::new(3)).expect("Advance failed");
+        assert!(guide.is_finished());
+        assert_eq!(guide.remaining_tokens(), Some(0));
+        assert_eq!(guide.allowed_tokens().expect("No allowed tokens"), vec![4]);
+
+        let guide = Guide::new(index, 4).with_max_tokens(1);
+        assert!(matches!(
+            guide.allowed_tokens(),
+            Err(Error::TokenBudgetExceeded {
+                max_tokens: 1,
+                required: 2
+            })
+        ));
+    }
+
+    #[test]
+    fn violation_budget() {
+        let mut guide = Guide::new(index(), 8).with_max_violations(2);
+        assert!(guide.accepts_tokens(&[3, 3, 1, 1, 3].map(TokenId::new)));
+        assert!(!guide.accepts_tokens(&[1, 1, 1, 3].map(TokenId::new)));
+        assert!(!guide.accepts_tokens(&[4].map(TokenId::new)));
+
+        // Violating tokens keep the state, then the generation goes on from it.
+        let state = guide.advance(TokenId::new(3)).expect("Advance failed");
+        assert_eq!(
+            guide.advance(TokenId::new(3)).expect("Advance failed"),
+            state
+        );
+        assert_eq!(guide.allowed_tokens().expect("No allowed tokens").len(), 2);
+        guide.advance(TokenId::new(1)).expect("Advance failed");
+        assert_eq!(
+            guide.advance(TokenId::new(2)).expect("Advance failed"),
+            guide.state()
+        );
+        assert_eq!(guide.violations(), &[1, 3]);
+        assert_eq!(guide.remaining_violations(), 0);
+        assert!(matches!(
+            guide.advance(TokenId::new(2)),
+            Err(Error::NoNextState { token_id, .. }) if token_id == 2
+        ));
+
+        let snapshot = guide.export_state();
+        let resumed = Guide::resume(guide.index().clone(), &snapshot).expect("Resume failed");
+        assert_eq!(resumed.violations(), &[1, 3]);
+
+        guide.rollback_state(1).expect("Rollback failed");
+        assert_eq!(guide.violations(), &[1]);
+        assert_eq!(guide.remaining_violations(), 1);
+        guide.advance(TokenId::new(3)).expect("Advance failed");
+        assert!(guide.is_finished());
+
+        guide.reset();
+        assert!(guide.violations().is_empty());
+        assert!(!Guide::new(index(), 8).accepts_tokens(&[3, 3].map(TokenId::new)));
+    }
+
+    #[test]
+    fn token_events() {
+        let mut vocabulary = Vocabulary::new(4);
+        for (token, token_id) in [("a", 1), ("b", 2), ("z", 3)] {
+            vocabulary
+                .try_insert(token, token_id)
+                .expect("Insert failed");
+        }
+        let index = Arc::new(Index::new("z[ab]z", &vocabulary).expect("Index failed"));
+        let mut guide = Guide::new(index.clone(), 8)
+            .with_finish_policy(FinishPolicy::OnEos)
+            .with_max_violations(1)
+            .with_token_events();
+        for token_id in [3, 3, 2, 3, 4].map(TokenId::new) {
+            guide.advance(token_id).expect("Advance failed");
+        }
+        let kind = |token_id, kind| TokenEvent {
+            token_id: TokenId::new(token_id),
+            kind,
+        };
+        let events = [
+            kind(3, TokenEventKind::Forced),
+            kind(3, TokenEventKind::Violation),
+            kind(2, TokenEventKind::Chosen { options: 2 }),
+            kind(3, TokenEventKind::Forced),
+            kind(4, TokenEventKind::Eos),
+        ];
+        assert_eq!(guide.token_events(), Some(&events[..]));
+        assert_eq!(
+            serde_json::to_value(events[2]).expect("Serialization failed"),
+            serde_json::json!({"token_id": 2, "kind": "chosen", "options": 2})
+        );
+
+        let snapshot = guide.export_state();
+        let resumed = Guide::resume(index.clone(), &snapshot).expect("Resume failed");
+        assert_eq!(resumed.token_events(), Some(&events[..]));
+
+        guide.rollback_state(2).expect("Rollback failed");
+        assert_eq!(guide.token_events(), Some(&events[..3]));
+        guide.reset();
+        assert_eq!(guide.token_events(), Some(&[][..]));
+        assert_eq!(Guide::new(index, 8).token_events(), None);
+    }
+
+    #[test]
+    fn from_state() {
+        let index = index();
+        let mut guide = Guide::new(index.clone(), 8);
+        guide.advance(TokenId::new(3)).expect("Advance failed");
+        guide.advance(TokenId::new(1)).expect("Advance failed");
+
+        let mut resumed = Guide::from_state(index.clone(), guide.state(), 8)
+            .expect("From state failed")
+            .with_max_tokens(1);
+        assert_eq!(resumed.state(), guide.state());
+        assert_eq!(resumed.consumed_tokens(), 0);
+        assert_eq!(
+            resumed.allowed_tokens().expect("No allowed tokens"),
+            guide.allowed_tokens().expect("No allowed tokens")
+        );
+        resumed.advance(TokenId::new(3)).expect("Advance failed");
+        assert!(resumed.is_finished());
+        assert_eq!(resumed.remaining_tokens(), Some(0));
+        resumed.reset();
+        assert_eq!(resumed.state(), index.initial_state());
+
+        assert!(matches!(
+            Guide::from_state(index, StateId::new(1000), 8),
+            Err(Error::StateNotFound(state)) if state == 1000
+        ));
+    }
+
+    #[test]
+    fn mask_cache() {
+        let index = index();
+        let mut guide = Guide::new(index.clone(), 8).with_mask_cache(1);
+        assert!(guide.has_mask_cache());
+
+        let initial = guide.state_mask().expect("No mask");
+        assert_eq!(initial.tokens(), [3]);
+        assert_eq!(initial.words(), [0b1000]);
+        let flags = initial.to_bool_vec();
+        assert_eq!(flags.len(), index.vocab_size());
+        assert_eq!(flags[..4], [false, false, false, true]);
+        assert_eq!(flags.iter().filter(|&&flag| flag).count(), 1);
+        assert!(Arc::ptr_eq(
+            &initial,
+            &guide.clone().state_mask().expect("No mask")
+        ));
+
+        guide.advance(TokenId::new(3)).expect("Advance failed");
+        let mask = guide.state_mask().expect("No mask");
+        let mut tokens = mask.tokens().to_vec();
+        tokens.sort_unstable();
+        assert_eq!(tokens, [1, 2]);
+        assert_eq!(mask.words(), [0b110]);
+        // The mask of the initial state is evicted.
+        guide.reset();
+        assert!(!Arc::ptr_eq(
+            &initial,
+            &guide.state_mask().expect("No mask")
+        ));
+
+        // Masks depend on the consumed tokens with a budget.
+        let guide = Guide::new(index, 8).with_mask_cache(4).with_max_tokens(8);
+        assert!(!Arc::ptr_eq(
+            &guide.state_mask().expect("No mask"),
+            &guide.state_mask().expect("No mask")
+        ));
+        assert!(!Guide::new(guide.index().clone(), 8)
+            .with_mask_cache(0)
+            .has_mask_cache());
+    }
+
+    #[test]
+    fn propose_and_commit() {
+        let index = index();
+        let mut guide = Guide::new(index.clone(), 8).with_max_violations(1);
+        let initial = guide.clone();
+
+        let proposal = guide.propose(TokenId::new(3)).expect("Propose failed");
+        assert_eq!(guide, initial);
+        let mut advanced = guide.clone();
+        advanced.advance(TokenId::new(3)).expect("Advance failed");
+        assert_eq!(proposal.token_id(), 3);
+        assert_eq!(proposal.next_state(), advanced.state());
+        assert_eq!(
+            proposal.allowed_tokens(),
+            advanced.allowed_tokens().expect("No allowed tokens")
+        );
+        assert!(!proposal.allows_eos());
+        assert!(!proposal.is_violation());
+
+        assert_eq!(
+            proposal.clone().commit(&mut guide).expect("Commit failed"),
+            advanced.state()
+        );
+        assert_eq!(guide, advanced);
+        // The guide moved since the proposal.
+        assert!(matches!(
+            proposal.commit(&mut guide),
+            Err(Error::StaleProposal { token_id, .. }) if token_id == 3
+        ));
+        // The proposal was made by a guide in another state.
+        let proposal = guide.propose(TokenId::new(1)).expect("Propose failed");
+        assert!(matches!(
+            proposal.commit(&mut Guide::new(index, 8)),
+            Err(Error::StaleProposal { .. })
+        ));
+
+        let violation = initial.propose(TokenId::new(1)).expect("Propose failed");
+        assert!(violation.is_violation());
+        assert_eq!(violation.next_state(), initial.state());
+        assert!(Guide::new(initial.index().clone(), 8)
+            .propose(TokenId::new(1))
+            .is_err());
+
+        guide.advance(TokenId::new(1)).expect("Advance failed");
+        assert!(guide
+            .propose(TokenId::new(3))
+            .expect("Propose failed")
+            .allows_eos());
+    }
+
+    #[test]
+    fn token_biases() {
+        let index = index();
+        let biases = TransitionBiases::for_tokens(
+            &index,
+            &[(1, -0.5), (3, 1.0), (4, 2.0)].map(|(id, bias)| (TokenId::new(id), bias)),
+        );
+        let mut guide = Guide::new(index.clone(), 4).with_biases(Arc::new(biases));
+        assert_eq!(guide.token_biases(), vec![(TokenId::new(3), 1.0)]);
+        guide.advance(TokenId::new(3)).expect("Advance failed");
+        assert_eq!(guide.token_biases(), vec![(TokenId::new(1), -0.5)]);
+        guide.advance(TokenId::new(2)).expect("Advance failed");
+        guide.advance(TokenId::new(3)).expect("Advance failed");
+        assert_eq!(guide.token_biases(), vec![(TokenId::new(4), 2.0)]);
+
+        assert!(Guide::new(index, 4).token_biases().is_empty());
+    }
+
+    #[test]
+    fn export_and_resume() {
+        let index = index();
+        let mut guide = Guide::new(index.clone(), 8);
+        guide.advance(TokenId::new(3)).expect("Advance failed");
+        guide.advance(TokenId::new(2)).expect("Advance failed");
+
+        let snapshot = guide.export_state();
+        let json = serde_json::to_string(&snapshot).expect("Serialization failed");
+        let snapshot: GuideSnapshot = serde_json::from_str(&json).expect("Deserialization failed");
+
+        let mut resumed = Guide::resume(index.clone(), &snapshot).expect("Resume failed");
+        assert_eq!(resumed.state(), guide.state());
+        assert_eq!(resumed.consumed_tokens(), 2);
+        resumed.advance(TokenId::new(3)).expect("Advance failed");
+        assert!(resumed.is_finished());
+
+        let mut vocabulary = Vocabulary::new(4);
+        vocabulary.try_insert("z", 3).expect("Insert failed");
+        let other = Arc::new(Index::new("z+", &vocabulary).expect("Index failed"));
+        assert!(matches!(
+            Guide::resume(other, &snapshot),
+            Err(Error::IndexFingerprintMismatch { .. })
+        ));
+
+        let unknown = GuideSnapshot {
+            state: StateId::new(u32::MAX),
+            ..snapshot
+        };
+        assert!(matches!(
+            Guide::resume(index, &unknown),
+            Err(Error::StateNotFound(state)) if state == u32::MAX
+        ));
+    }
+
+    fn json_guide(schema: &str) -> (Guide, Vocabulary) {
+        let mut vocabulary = Vocabulary::new(7);
+        for (token, token_id) in [("[", 1), ("]", 2), (",", 3), ("1", 4), ("2", 5), ("2]", 6)] {
+            vocabulary
+                .try_insert(token, token_id)
+                .expect("Insert failed");
+        }
+        let regex = json_schema::regex_from_str(schema, Some(""), None).expect("Regex failed");
+        let index = Index::new(&regex, &vocabulary).expect("Index failed");
+        let guide = Guide::new(Arc::new(index), 8).with_finish_policy(FinishPolicy::OnEos);
+        (guide, vocabulary)
+    }
+
+    #[test]
+    fn current_path() {
+        let (guide, vocabulary) = json_guide(r#"{"type": "array", "items": {"enum": [1, 2]}}"#);
+        let paths = Arc::new(FieldPaths::new(guide.index(), &vocabulary));
+        let mut guide = guide.with_field_paths(paths);
+        assert_eq!(guide.current_path(), None);
+        guide.advance(TokenId::new(1)).expect("Advance failed");
+        assert_eq!(guide.current_path().as_deref(), Some("[]"));
+        guide.advance(TokenId::new(6)).expect("Advance failed");
+        assert_eq!(guide.current_path(), None);
+        guide.rollback_state(1).expect("Rollback failed");
+        assert_eq!(guide.current_path().as_deref(), Some("[]"));
+    }
+
+    #[test]
+    fn generated_bytes() {
+        let (guide, vocabulary) = json_guide(r#"{"type": "array", "items": {"enum": [1, 2]}}"#);
+        assert!(matches!(
+            guide.generated_bytes(&vocabulary),
+            Err(Error::TokenHistoryDisabled)
+        ));
+
+        let mut guide = guide.with_history(true);
+        for token_id in [1, 4, 3, 6, 7].map(TokenId::new) {
+            guide.advance(token_id).expect("Advance failed");
+        }
+        assert_eq!(
+            guide.generated_bytes(&vocabulary).expect("Decode failed"),
+            b"[1,2]"
+        );
+
+        guide.rollback_state(2).expect("Rollback failed");
+        assert_eq!(
+            guide.generated_bytes(&vocabulary).expect("Decode failed"),
+            b"[1,"
+        );
+        assert_eq!(guide.text(&vocabulary).expect("Decode failed"), "[1,");
+
+        // Tokens consumed before the snapshot are kept by it.
+        let mut snapshot = guide.export_state();
+        let resumed = Guide::resume(guide.index().clone(), &snapshot).expect("Resume failed");
+        assert_eq!(
+            resumed.generated_bytes(&vocabulary).expect("Decode failed"),
+            b"[1,"
+        );
+
+        snapshot.token_ids = Some(Vec::new());
+        let resumed = Guide::resume(guide.index().clone(), &snapshot).expect("Resume failed");
+        assert!(matches!(
+            resumed.generated_bytes(&vocabulary),
+            Err(Error::IncompleteTokenHistory {
+                consumed: 3,
+                recorded: 0
+            })
+        ));
+
+        let mut other = Vocabulary::new(7);
+        other.try_insert("[", 1).expect("Insert failed");
+        assert!(matches!(
+            guide.generated_bytes(&other),
+            Err(Error::UnknownTokenId(token_id)) if token_id == 4
+        ));
+    }
+
+    #[cfg(feature = "schema-validation")]
+    #[test]
+    fn finish_and_validate() {
+        // Unique items are not enforced by the regular expression.
+        let schema = r#"{"type": "array", "items": {"enum": [1, 2]}, "uniqueItems": true}"#;
+        let (guide, vocabulary) = json_guide(schema);
+        assert!(matches!(
+            guide.finish_and_validate(&vocabulary),
+            Err(Error::GuideWithoutSchema)
+        ));
+
+        let mut guide = guide.with_json_schema(schema);
+        guide.advance(TokenId::new(1)).expect("Advance failed");
+        assert!(matches!(
+            guide.finish_and_validate(&vocabulary),
+            Err(Error::GuideNotFinished(_))
+        ));
+
+        for token_id in [4, 3, 6, 7].map(TokenId::new) {
+            guide.advance(token_id).expect("Advance failed");
+        }
+        assert_eq!(
+            guide
+                .finish_and_validate(&vocabulary)
+                .expect("Validation failed"),
+            vec![]
+        );
+
+        guide.reset();
+        for token_id in [1, 4, 3, 4, 2, 7].map(TokenId::new) {
+            guide.advance(token_id).expect("Advance failed");
+        }
+        let mismatches = guide
+            .finish_and_validate(&vocabulary)
+            .expect("Validation failed");
+        assert_eq!(mismatches.len(), 1);
+        assert_eq!(mismatches[0].instance_path, "");
+        assert_eq!(mismatches[0].schema_path, "/uniqueItems");
+    }
+}
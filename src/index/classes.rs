@@ -0,0 +1,92 @@
+//! Byte classes of the automaton an index is built from.
+
+use bincode::{Decode, Encode};
+use regex_automata::nfa::thompson::NFA;
+use regex_automata::util::alphabet;
+
+use crate::Result;
+
+/// Partition of the 256 byte values into classes, which the automaton of the regular
+/// expression never distinguishes: bytes of the same class always lead to the same state.
+///
+/// Classes are numbered from 0 in the order of their smallest byte, so that they could be
+/// used by external tools, e.g. to render the automaton with one edge per class.
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode)]
+pub struct ByteClasses([u8; 256]);
+
+impl ByteClasses {
+    /// Computes the byte classes of the regular expression.
+    pub(crate) fn from_regex(regex: &str) -> Result<Self> {
+        let nfa = NFA::new(regex).map_err(Box::new)?;
+        Ok(Self::from_automaton(nfa.byte_classes()))
+    }
+
+    pub(crate) fn from_automaton(classes: &alphabet::ByteClasses) -> Self {
+        let mut map = [0; 256];
+        for byte in 0..=255u8 {
+            map[byte as usize] = classes.get(byte);
+        }
+        Self(map)
+    }
+
+    /// Puts every byte into its own class, when the classes of the automaton are unknown.
+    pub(crate) fn singletons() -> Self {
+        Self(std::array::from_fn(|byte| byte as u8))
+    }
+
+    /// Returns the class of the byte.
+    pub fn class_of(&self, byte: u8) -> u8 {
+        self.0[byte as usize]
+    }
+
+    /// Returns the number of classes.
+    pub fn num_classes(&self) -> usize {
+        self.0.iter().max().map_or(0, |&class| class as usize + 1)
+    }
+
+    /// Lists the bytes of the class, in ascending order.
+    pub fn bytes(&self, class: u8) -> impl Iterator<Item = u8> + '_ {
+        (0..=255u8).filter(move |&byte| self.class_of(byte) == class)
+    }
+
+    /// Returns the representative byte of each class, i.e. its smallest one, by class.
+    pub fn representatives(&self) -> Vec<u8> {
+        let mut representatives = vec![None; self.num_classes()];
+        for byte in (0..=255u8).rev() {
+            representatives[self.class_of(byte) as usize] = Some(byte);
+        }
+        representatives.into_iter().flatten().collect()
+    }
+
+    /// Returns the class of each byte value, indexed by the byte.
+    pub fn as_slice(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn byte_classes_of_regex() {
+        let classes = ByteClasses::from_regex("[a-c]x").expect("Byte classes failed");
+        assert_eq!(classes.class_of(b'a'), classes.class_of(b'c'));
+        assert_ne!(classes.class_of(b'a'), classes.class_of(b'x'));
+        assert_ne!(classes.class_of(b'c'), classes.class_of(b'd'));
+        assert_eq!(
+            classes.bytes(classes.class_of(b'b')).collect::<Vec<_>>(),
+            b"abc"
+        );
+
+        let representatives = classes.representatives();
+        assert_eq!(representatives.len(), classes.num_classes());
+        assert_eq!(representatives[0], 0);
+        for (class, &byte) in representatives.iter().enumerate() {
+            assert_eq!(classes.class_of(byte) as usize, class);
+        }
+        assert!(representatives.contains(&b'a') && representatives.contains(&b'x'));
+
+        assert_eq!(ByteClasses::singletons().num_classes(), 256);
+    }
+}
@@ -0,0 +1,285 @@
+//! Generation of Rust source with the static transition table of an index.
+
+use std::fmt::{Display, Write};
+
+use super::Index;
+use crate::prelude::*;
+use crate::{Error, Result};
+
+/// Number of values written on each line of the generated arrays.
+const VALUES_PER_LINE: usize = 16;
+
+/// Strict and reserved keywords of every edition, which name the generated module as raw
+/// identifiers, e.g. `r#try`.
+const KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "do", "dyn",
+    "else", "enum", "extern", "false", "final", "fn", "for", "gen", "if", "impl", "in", "let",
+    "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref", "return",
+    "static", "struct", "trait", "true", "try", "type", "typeof", "unsafe", "unsized", "use",
+    "virtual", "where", "while", "yield",
+];
+
+/// Keywords of paths, which can't be raw identifiers.
+const PATH_KEYWORDS: &[&str] = &["crate", "self", "Self", "super"];
+
+/// Lookups of the generated module, which only depend on `core`.
+const LOOKUPS: &str = "
+    fn position(state: u32) -> Option<usize> {
+        STATES.binary_search(&state).ok()
+    }
+
+    /// Checks if the state is final.
+    pub fn is_final_state(state: u32) -> bool {
+        FINAL_STATES.binary_search(&state).is_ok()
+    }
+
+    /// Checks if the token ends the generation.
+    pub fn is_stop_token(token_id: u32) -> bool {
+        token_id == EOS_TOKEN_ID || STOP_TOKEN_IDS.contains(&token_id)
+    }
+
+    /// Returns the sorted allowed tokens of the state, empty if it's not found.
+    pub fn allowed_tokens(state: u32) -> &'static [u32] {
+        match position(state) {
+            Some(i) => &TOKENS[OFFSETS[i] as usize..OFFSETS[i + 1] as usize],
+            None => &[],
+        }
+    }
+
+    /// Returns the state following the token, or `None` if the token isn't allowed or ends
+    /// the generation.
+    pub fn next_state(state: u32, token_id: u32) -> Option<u32> {
+        if is_stop_token(token_id) {
+            return None;
+        }
+        let i = position(state)?;
+        let start = OFFSETS[i] as usize;
+        let tokens = &TOKENS[start..OFFSETS[i + 1] as usize];
+        let j = tokens.binary_search(&token_id).ok()?;
+        Some(NEXT_STATES[start + j])
+    }
+";
+
+impl Index {
+    /// Generates Rust source of a module `name` holding the transition table of the index in
+    /// static sorted arrays, with lookups by binary search.
+    ///
+    /// The module only depends on `core`, so that the constraint could be linked into a
+    /// binary, e.g. with `include!`, without compiling the index at runtime:
+    /// `is_final_state`, `is_stop_token`, `allowed_tokens` and `next_state` behave like the
+    /// methods of the index. `INITIAL_STATE`, `EOS_TOKEN_ID`, `VOCAB_SIZE` and `FINGERPRINT`
+    /// are constants of the module.
+    ///
+    /// Keywords name the module as raw identifiers, e.g. `r#try`, except `crate`, `self`,
+    /// `Self` and `super`, which are rejected like other names that aren't identifiers.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use outlines_core::prelude::*;
+    ///
+    /// let vocabulary = Vocabulary::fixture();
+    /// let index = Index::new("[0-9]{2}", &vocabulary).expect("Index failed");
+    /// let source = index.to_rust_source("two_digits").expect("Codegen failed");
+    /// assert!(source.contains("pub mod two_digits {"));
+    /// ```
+    pub fn to_rust_source(&self, name: &str) -> Result<String> {
+        let mut chars = name.chars();
+        let identifier = chars
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+            && name != "_"
+            && !PATH_KEYWORDS.contains(&name);
+        if !identifier {
+            return Err(Error::InvalidModuleName(name.to_string()));
+        }
+        let module = if KEYWORDS.contains(&name) {
+            format!("r#{name}")
+        } else {
+            name.to_string()
+        };
+
+        let transitions = self.transitions();
+        let mut states: Vec<StateId> = transitions.keys().copied().collect();
+        states.sort_unstable();
+        let mut offsets = Vec::with_capacity(states.len() + 1);
+        let mut tokens = Vec::new();
+        let mut next_states = Vec::new();
+        for state in &states {
+            offsets.push(tokens.len() as u32);
+            let mut targets: Vec<(TokenId, StateId)> = transitions[state]
+                .iter()
+                .map(|(token_id, next)| (*token_id, *next))
+                .collect();
+            targets.sort_unstable();
+            for (token_id, next) in targets {
+                tokens.push(token_id);
+                next_states.push(next);
+            }
+        }
+        offsets.push(tokens.len() as u32);
+        let mut final_states: Vec<StateId> = self.final_states.iter().copied().collect();
+        final_states.sort_unstable();
+        let mut stop_token_ids = self.stop_token_ids.clone();
+        stop_token_ids.sort_unstable();
+
+        let mut source = format!(
+            "// Generated by outlines-core, do not edit.
+
+/// Transition table of an index.
+#[rustfmt::skip]
+pub mod {module} {{
+    /// Fingerprint of the index.
+    pub const FINGERPRINT: u64 = 0x{:016x};
+    pub const INITIAL_STATE: u32 = {};
+    pub const EOS_TOKEN_ID: u32 = {};
+    pub const VOCAB_SIZE: usize = {};
+",
+            self.fingerprint, self.initial_state, self.eos_token_id, self.vocab_size
+        );
+        write_array(
+            &mut source,
+            "Sorted stop tokens, besides the end-of-sequence one.",
+            "STOP_TOKEN_IDS",
+            &stop_token_ids,
+        );
+        write_array(
+            &mut source,
+            "Sorted final states.",
+            "FINAL_STATES",
+            &final_states,
+        );
+        write_array(
+            &mut source,
+            "Sorted states with transitions.",
+            "STATES",
+            &states,
+        );
+        write_array(
+            &mut source,
+            "Start of the transitions of each state of `STATES` in `TOKENS` and `NEXT_STATES`, then their end.",
+            "OFFSETS",
+            &offsets,
+        );
+        write_array(
+            &mut source,
+            "Allowed tokens of each state, sorted.",
+            "TOKENS",
+            &tokens,
+        );
+        write_array(
+            &mut source,
+            "State following each token of `TOKENS`.",
+            "NEXT_STATES",
+            &next_states,
+        );
+        source.push_str(LOOKUPS);
+        source.push_str("}\n");
+        Ok(source)
+    }
+}
+
+/// Writes a documented static array of the values.
+fn write_array(source: &mut String, doc: &str, name: &str, values: &[impl Display]) {
+    // Writing into a `String` doesn't fail.
+    let _ = writeln!(source, "\n    /// {doc}");
+    let _ = write!(source, "    pub static {name}: [u32; {}] = [", values.len());
+    for (i, value) in values.iter().enumerate() {
+        if i % VALUES_PER_LINE == 0 {
+            source.push_str("\n        ");
+        } else {
+            source.push(' ');
+        }
+        let _ = write!(source, "{value},");
+    }
+    if !values.is_empty() {
+        source.push_str("\n    ");
+    }
+    source.push_str("];\n");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    include!("codegen/digits.rs");
+    include!("codegen/try.rs");
+
+    /// Asserts that the included module answers like the index.
+    macro_rules! assert_module {
+        ($module:ident, $index:expr) => {
+            assert_eq!($module::FINGERPRINT, $index.fingerprint());
+            assert_eq!($index.initial_state(), $module::INITIAL_STATE);
+            assert_eq!($index.eos_token_id(), $module::EOS_TOKEN_ID);
+            assert_eq!($module::VOCAB_SIZE, $index.vocab_size());
+            for state in [0, 24, 40, 48, 56].map(StateId::new) {
+                let raw_state = state.as_u32();
+                assert_eq!(
+                    $module::is_final_state(raw_state),
+                    $index.is_final_state(&state)
+                );
+                let mut allowed = $index.allowed_tokens(&state).unwrap_or_default();
+                allowed.sort_unstable();
+                assert_eq!(allowed, $module::allowed_tokens(raw_state));
+                for token_id in (0..5).map(TokenId::new) {
+                    assert_eq!(
+                        $module::next_state(raw_state, token_id.as_u32()).map(StateId::new),
+                        $index.next_state(&state, &token_id),
+                        "{state} {token_id}"
+                    );
+                    assert_eq!(
+                        $module::is_stop_token(token_id.as_u32()),
+                        $index.is_stop_token(&token_id)
+                    );
+                }
+            }
+        };
+    }
+
+    fn digits_index() -> Index {
+        let mut vocabulary = Vocabulary::new(4);
+        for (token, token_id) in [("blah", 0), ("1a", 1), ("2", 2), ("0", 3)] {
+            vocabulary
+                .try_insert(token, token_id as u32)
+                .expect("Insert failed");
+        }
+        Index::new("0|[1-9][0-9]*", &vocabulary).expect("Index failed")
+    }
+
+    #[test]
+    fn generated_source() {
+        let index = digits_index();
+        let source = index.to_rust_source("digits").expect("Codegen failed");
+        assert_eq!(source, include_str!("codegen/digits.rs"));
+
+        assert_module!(digits, index);
+    }
+
+    #[test]
+    fn reserved_module_name() {
+        let index = digits_index();
+        let source = index.to_rust_source("try").expect("Codegen failed");
+        assert_eq!(source, include_str!("codegen/try.rs"));
+
+        // The module is named by a raw identifier and compiles.
+        assert_module!(r#try, index);
+
+        for name in ["gen", "type", "yield"] {
+            let source = index.to_rust_source(name).expect("Codegen failed");
+            assert!(source.contains(&format!("pub mod r#{name} {{")), "{name}");
+        }
+    }
+
+    #[test]
+    fn invalid_module_names() {
+        let index = digits_index();
+        for name in ["", "_", "1st", "two-digits", "self", "digits::inner"] {
+            assert!(
+                matches!(index.to_rust_source(name), Err(Error::InvalidModuleName(n)) if n == name),
+                "{name}"
+            );
+        }
+        assert!(index.to_rust_source("_digits2").is_ok());
+    }
+}
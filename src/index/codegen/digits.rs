@@ -0,0 +1,74 @@
+// Generated by outlines-core, do not edit.
+
+/// Transition table of an index.
+#[rustfmt::skip]
+pub mod digits {
+    /// Fingerprint of the index.
+    pub const FINGERPRINT: u64 = 0xbbc5dee3ca34173d;
+    pub const INITIAL_STATE: u32 = 40;
+    pub const EOS_TOKEN_ID: u32 = 4;
+    pub const VOCAB_SIZE: usize = 5;
+
+    /// Sorted stop tokens, besides the end-of-sequence one.
+    pub static STOP_TOKEN_IDS: [u32; 0] = [];
+
+    /// Sorted final states.
+    pub static FINAL_STATES: [u32; 3] = [
+        24, 48, 56,
+    ];
+
+    /// Sorted states with transitions.
+    pub static STATES: [u32; 4] = [
+        24, 40, 48, 56,
+    ];
+
+    /// Start of the transitions of each state of `STATES` in `TOKENS` and `NEXT_STATES`, then their end.
+    pub static OFFSETS: [u32; 5] = [
+        0, 3, 5, 6, 9,
+    ];
+
+    /// Allowed tokens of each state, sorted.
+    pub static TOKENS: [u32; 9] = [
+        2, 3, 4, 2, 3, 4, 2, 3, 4,
+    ];
+
+    /// State following each token of `TOKENS`.
+    pub static NEXT_STATES: [u32; 9] = [
+        24, 24, 24, 56, 48, 48, 24, 24, 56,
+    ];
+
+    fn position(state: u32) -> Option<usize> {
+        STATES.binary_search(&state).ok()
+    }
+
+    /// Checks if the state is final.
+    pub fn is_final_state(state: u32) -> bool {
+        FINAL_STATES.binary_search(&state).is_ok()
+    }
+
+    /// Checks if the token ends the generation.
+    pub fn is_stop_token(token_id: u32) -> bool {
+        token_id == EOS_TOKEN_ID || STOP_TOKEN_IDS.contains(&token_id)
+    }
+
+    /// Returns the sorted allowed tokens of the state, empty if it's not found.
+    pub fn allowed_tokens(state: u32) -> &'static [u32] {
+        match position(state) {
+            Some(i) => &TOKENS[OFFSETS[i] as usize..OFFSETS[i + 1] as usize],
+            None => &[],
+        }
+    }
+
+    /// Returns the state following the token, or `None` if the token isn't allowed or ends
+    /// the generation.
+    pub fn next_state(state: u32, token_id: u32) -> Option<u32> {
+        if is_stop_token(token_id) {
+            return None;
+        }
+        let i = position(state)?;
+        let start = OFFSETS[i] as usize;
+        let tokens = &TOKENS[start..OFFSETS[i + 1] as usize];
+        let j = tokens.binary_search(&token_id).ok()?;
+        Some(NEXT_STATES[start + j])
+    }
+}
@@ -0,0 +1,1135 @@
+//! Counter-augmented transitions of bounded repetitions, e.g. `"[a-z]{0,4096}"` or `.{1,500}`.
+//!
+//! The DFA of a bounded repetition unrolls its body once per repetition, and almost every
+//! token of the vocabulary is checked against each of those states, so that long strings or
+//! arrays with many items explode into millions of transitions. Instead, the regular expression
+//! is split into a prefix, the repeated body and a suffix, each with its own DFA, and a state
+//! of the repetition is encoded by the state within the body together with the number of
+//! started repetitions. Walks of tokens from a body state don't depend on that number, so that
+//! they are computed once per body state and ordered by the number of repetitions they start,
+//! then allowed tokens of a state are a prefix of them.
+//!
+//! The split is only used when it's unambiguous: neither the prefix nor the body continue
+//! after a match, the body doesn't match an empty string and the first bytes of the body and of
+//! the suffix are disjoint. Otherwise, the DFA of the whole regular expression is explored.
+
+use std::collections::hash_map::Keys;
+use std::iter;
+use std::mem::size_of;
+use std::sync::OnceLock;
+
+use bincode::de::{Decode, Decoder};
+use bincode::enc::{Encode, Encoder};
+use bincode::error::{DecodeError, EncodeError};
+use regex_automata::dfa::dense::{Builder, DFA};
+use regex_automata::dfa::Automaton;
+use regex_automata::nfa::thompson::Compiler;
+use regex_automata::util::primitives::StateID as AutomataStateId;
+use regex_automata::{Anchored, MatchKind};
+use regex_syntax::hir::{Dot, Hir, HirKind};
+use rustc_hash::{FxHashMap as HashMap, FxHashSet as HashSet};
+
+use super::limits::ResourceUsage;
+use super::TokenTable;
+use crate::prelude::*;
+use crate::{Error, Result};
+
+/// Smallest upper bound of a repetition, which is counted instead of being unrolled.
+///
+/// Repetitions of any character, which make the whole regular expression, are always counted.
+const COUNTED_REPETITION_THRESHOLD: u32 = 64;
+
+/// Part of the regular expression with the index of a state within it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, bincode::Encode, bincode::Decode)]
+enum Target {
+    Prefix(u32),
+    /// The first body state is the boundary between repetitions.
+    Body(u32),
+    Suffix(u32),
+}
+
+/// Transitions of a regular expression with a counted repetition.
+///
+/// Decoding validates the automaton, so that malformed data fails instead of panicking on
+/// its first use.
+#[derive(Clone, Debug, PartialEq, bincode::Encode)]
+pub(super) struct CountedAutomaton {
+    min: u32,
+    max: u32,
+    /// The end-of-sequence token followed by the stop tokens, allowed in final states.
+    terminators: Vec<TokenId>,
+    /// Numbers of states of each part, states are numbered with prefix states first, then
+    /// suffix states, then body states repeated for each number of started repetitions.
+    prefix_states: u32,
+    suffix_states: u32,
+    body_states: u32,
+    initial_state: StateId,
+    /// Whether the suffix matches an empty string, so that the input may end on a boundary.
+    suffix_nullable: bool,
+    /// Explicit transitions of prefix and suffix states.
+    transitions: HashMap<StateId, HashMap<TokenId, StateId>>,
+    /// Tokens grouped by the body state they are walked from.
+    groups: Vec<TokenGroup>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, bincode::Encode, bincode::Decode)]
+struct TokenGroup {
+    /// Tokens staying within the repetition, ordered by the number of repetitions they start.
+    tokens: Vec<TokenId>,
+    /// Number of repetitions started by each of `tokens`.
+    started: Vec<u32>,
+    /// Tokens leaving the repetition into the suffix, with the number of repetitions they start.
+    exits: Vec<(TokenId, u32)>,
+    /// Started repetitions and the target of each token, ordered by token id.
+    walks: Vec<(TokenId, u32, Target)>,
+}
+
+impl TokenGroup {
+    /// Returns an estimate of the heap memory held by the group, in bytes.
+    fn memory_usage(&self) -> usize {
+        size_of::<TokenGroup>()
+            + self.tokens.capacity() * size_of::<TokenId>()
+            + self.started.capacity() * size_of::<u32>()
+            + self.exits.capacity() * size_of::<(TokenId, u32)>()
+            + self.walks.capacity() * size_of::<(TokenId, u32, Target)>()
+    }
+}
+
+/// Returns an estimate of the heap memory held by the explicit transitions of a state, in bytes.
+fn map_memory_usage(map: &HashMap<TokenId, StateId>) -> usize {
+    size_of::<(StateId, HashMap<TokenId, StateId>)>()
+        + map.capacity() * (size_of::<(TokenId, StateId)>() + 1)
+}
+
+impl CountedAutomaton {
+    /// Builds the automaton, if the regular expression has a repetition worth counting, which
+    /// splits it unambiguously, and every state, which isn't final, has allowed tokens.
+    ///
+    /// Otherwise the DFA has to be explored, e.g. to report an incompatible vocabulary. The
+    /// automata and the transitions are accounted to `usage` while they are built, so that a
+    /// construction exceeding its limits stops early.
+    pub(super) fn new(
+        regex: &str,
+        table: &TokenTable,
+        usage: &mut ResourceUsage,
+    ) -> Result<Option<Self>> {
+        let Some((prefix, body, min, max, suffix)) = split(regex) else {
+            return Ok(None);
+        };
+        let prefix = match prefix.kind() {
+            HirKind::Empty => None,
+            _ => match Part::new(&prefix, MatchKind::All, usage)? {
+                Some(prefix) => Some(prefix),
+                None => return Ok(None),
+            },
+        };
+        let (Some(body), Some(suffix)) = (
+            Part::new(&body, MatchKind::All, usage)?,
+            // Matches of the suffix are matches of the whole expression.
+            Part::new(&suffix, MatchKind::LeftmostFirst, usage)?,
+        ) else {
+            return Ok(None);
+        };
+        let parts = Parts {
+            prefix,
+            body,
+            suffix,
+        };
+        if !parts.is_unambiguous() {
+            return Ok(None);
+        }
+
+        // Matches of the prefix and of the body lead to the boundary, they aren't states.
+        let ids = |part: &Part, skip_matches: bool| -> HashMap<AutomataStateId, u32> {
+            let states = part.states.iter().copied();
+            let states = states.filter(|&state| !(skip_matches && part.is_match(state)));
+            states.zip(0..).collect()
+        };
+        let prefix_ids = parts
+            .prefix
+            .as_ref()
+            .map_or_else(HashMap::default, |prefix| ids(prefix, true));
+        // The first body state is the boundary, the start of the body DFA is also a state
+        // within a repetition, if the body loops back to it, e.g. `a*b`.
+        let body = &parts.body;
+        let reentered = body
+            .states
+            .iter()
+            .any(|&state| (0..=255).any(|byte| body.next(state, byte) == Some(body.start)));
+        let body_ids: HashMap<AutomataStateId, u32> = body
+            .states
+            .iter()
+            .copied()
+            .filter(|&state| !body.is_match(state) && (state != body.start || reentered))
+            .zip(1..)
+            .collect();
+        let suffix_ids = ids(&parts.suffix, false);
+        let target = |position: Position| match position {
+            Position::Prefix(state) => Target::Prefix(prefix_ids[&state]),
+            Position::Boundary => Target::Body(0),
+            Position::Body(state) => Target::Body(body_ids[&state]),
+            Position::Suffix(state) => Target::Suffix(suffix_ids[&state]),
+        };
+
+        let (prefix_states, suffix_states, body_states) = (
+            prefix_ids.len() as u32,
+            suffix_ids.len() as u32,
+            body_ids.len() as u32 + 1,
+        );
+        let states_total =
+            (prefix_states + suffix_states) as u64 + (max as u64 + 1) * body_states as u64;
+        if states_total > u32::MAX as u64 {
+            return Ok(None);
+        }
+        let mut automaton = Self {
+            min,
+            max,
+            terminators: iter::once(table.eos_token_id)
+                .chain(table.stop_token_ids.iter().copied())
+                .collect(),
+            prefix_states,
+            suffix_states,
+            body_states,
+            initial_state: StateId::default(),
+            suffix_nullable: parts.suffix.is_match(parts.suffix.start),
+            transitions: HashMap::default(),
+            groups: vec![TokenGroup::default(); body_states as usize],
+        };
+        automaton.initial_state = match &parts.prefix {
+            Some(prefix) => automaton.state_id(target(Position::Prefix(prefix.start)), 0),
+            None => automaton.state_id(Target::Body(0), 0),
+        };
+
+        let tokens = || table.tokens.iter();
+        let explicit = prefix_ids
+            .iter()
+            .map(|(&state, &i)| (Position::Prefix(state), Target::Prefix(i)))
+            .chain(
+                suffix_ids
+                    .iter()
+                    .map(|(&state, &i)| (Position::Suffix(state), Target::Suffix(i))),
+            );
+        for (position, from) in explicit {
+            let state = automaton.state_id(from, 0);
+            let mut map: HashMap<TokenId, StateId> = HashMap::default();
+            for &(token, token_ids) in tokens() {
+                let Some((end, started)) = parts.walk(position, token) else {
+                    continue;
+                };
+                if let Some(next) = automaton.advance(from, 0, started, target(end)) {
+                    map.extend(token_ids.iter().map(|&token_id| (token_id, next)));
+                }
+            }
+            if matches!(position, Position::Suffix(s) if parts.suffix.is_match(s)) {
+                map.extend(
+                    automaton
+                        .terminators
+                        .iter()
+                        .map(|&token_id| (token_id, state)),
+                );
+            }
+            if !map.is_empty() {
+                usage.add_bytes(map_memory_usage(&map))?;
+                automaton.transitions.insert(state, map);
+            }
+            usage.check_time()?;
+        }
+
+        let body_positions = body_ids
+            .iter()
+            .map(|(&state, &i)| (Position::Body(state), i))
+            .chain(iter::once((Position::Boundary, 0)));
+        for (position, i) in body_positions {
+            let group = &mut automaton.groups[i as usize];
+            for &(token, token_ids) in tokens() {
+                let Some((end, started)) = parts.walk(position, token) else {
+                    continue;
+                };
+                let end = target(end);
+                for &token_id in token_ids {
+                    group.walks.push((token_id, started, end));
+                }
+            }
+            group
+                .walks
+                .sort_unstable_by_key(|&(token_id, _, _)| token_id);
+            let mut staying: Vec<(u32, TokenId)> = Vec::new();
+            for &(token_id, started, end) in &group.walks {
+                match end {
+                    Target::Suffix(_) => group.exits.push((token_id, started)),
+                    _ => staying.push((started, token_id)),
+                }
+            }
+            staying.sort_unstable();
+            (group.started, group.tokens) = staying.into_iter().unzip();
+            usage.add_bytes(group.memory_usage())?;
+            usage.check_time()?;
+        }
+        Ok(automaton.is_covered().then_some(automaton))
+    }
+
+    /// Checks that every state, which may be reached by tokens and isn't final, has allowed
+    /// tokens whatever the number of started repetitions.
+    fn is_covered(&self) -> bool {
+        let Some((initial, _)) = self.decode(self.initial_state) else {
+            return false;
+        };
+        let mut reachable = vec![initial];
+        let mut i = 0;
+        while let Some(&target) = reachable.get(i) {
+            let next: Vec<Target> = match target {
+                Target::Body(b) => self.groups[b as usize]
+                    .walks
+                    .iter()
+                    .map(|&(_, _, end)| end)
+                    .collect(),
+                _ => self
+                    .transitions
+                    .get(&self.state_id(target, 0))
+                    .into_iter()
+                    .flat_map(|map| map.values())
+                    .filter_map(|&state| Some(self.decode(state)?.0))
+                    .collect(),
+            };
+            for target in next {
+                if !reachable.contains(&target) {
+                    reachable.push(target);
+                }
+            }
+            i += 1;
+        }
+        reachable.into_iter().all(|target| {
+            let Target::Body(b) = target else {
+                // Final suffix states have the end-of-sequence token.
+                return self.transitions.contains_key(&self.state_id(target, 0));
+            };
+            let group = &self.groups[b as usize];
+            let staying = |started: u32| group.started.contains(&started);
+            let exiting = |started: u32| group.exits.iter().any(|&(_, s)| s == started);
+            if b == 0 {
+                // Before the minimum another repetition must be started, at the maximum
+                // the suffix must be entered, unless it's empty.
+                (self.min == 0 || staying(1)) && (self.suffix_nullable || exiting(0))
+            } else {
+                // The started repetition may be the last one.
+                staying(0)
+            }
+        })
+    }
+
+    pub(super) fn initial_state(&self) -> StateId {
+        self.initial_state
+    }
+
+    pub(super) fn final_states(&self) -> HashSet<StateId> {
+        let boundaries = (self.min..=self.max)
+            .filter(|_| self.suffix_nullable)
+            .map(|count| self.state_id(Target::Body(0), count));
+        let suffix = self
+            .transitions
+            .iter()
+            .filter(|(state, map)| map.get(&self.terminators[0]) == Some(state))
+            .map(|(&state, _)| state);
+        boundaries.chain(suffix).collect()
+    }
+
+    pub(super) fn contains_state(&self, state: StateId) -> bool {
+        self.decode(state).is_some()
+    }
+
+    /// Lists allowed tokens of the state, including the end-of-sequence and stop tokens.
+    pub(super) fn allowed_tokens(&self, state: StateId) -> Option<CountedTokens<'_>> {
+        let (target, count) = self.decode(state)?;
+        let Target::Body(i) = target else {
+            return Some(CountedTokens::Explicit(
+                self.transitions.get(&state)?.keys(),
+            ));
+        };
+        let group = &self.groups[i as usize];
+        let end = group
+            .started
+            .partition_point(|&started| count + started <= self.max);
+        let is_final = i == 0 && self.suffix_nullable && count >= self.min;
+        Some(CountedTokens::Body {
+            tokens: group.tokens[..end].iter(),
+            exits: group.exits.iter(),
+            bounds: (self.min.saturating_sub(count), self.max - count),
+            terminators: if is_final {
+                self.terminators.iter()
+            } else {
+                [].iter()
+            },
+        })
+    }
+
+    pub(super) fn next_state(&self, state: StateId, token_id: TokenId) -> Option<StateId> {
+        let (from, count) = self.decode(state)?;
+        let Target::Body(i) = from else {
+            return self.transitions.get(&state)?.get(&token_id).copied();
+        };
+        let walks = &self.groups[i as usize].walks;
+        let position = walks
+            .binary_search_by_key(&token_id, |&(id, _, _)| id)
+            .ok()?;
+        let (_, started, to) = walks[position];
+        self.advance(from, count, started, to)
+    }
+
+    /// Builds explicit transitions of the states reachable from the initial state.
+    pub(super) fn transitions(&self) -> HashMap<StateId, HashMap<TokenId, StateId>> {
+        let mut transitions: HashMap<StateId, HashMap<TokenId, StateId>> = HashMap::default();
+        self.for_each_transition(|state, token_id, next| {
+            transitions.entry(state).or_default().insert(token_id, next);
+        });
+        transitions
+    }
+
+    /// Counts the states with transitions and the transitions reachable from the initial
+    /// state, without building them.
+    pub(super) fn count_transitions(&self) -> (usize, usize) {
+        let (mut states, mut transitions) = (0, 0);
+        let mut last = None;
+        self.for_each_transition(|state, _, _| {
+            if last != Some(state) {
+                last = Some(state);
+                states += 1;
+            }
+            transitions += 1;
+        });
+        (states, transitions)
+    }
+
+    /// Visits the transitions of the states reachable from the initial state, state by state.
+    fn for_each_transition(&self, mut visit: impl FnMut(StateId, TokenId, StateId)) {
+        let mut seen: HashSet<StateId> = HashSet::from_iter([self.initial_state]);
+        let mut next_states = vec![self.initial_state];
+        while let Some(state) = next_states.pop() {
+            for &token_id in self.allowed_tokens(state).into_iter().flatten() {
+                if self.terminators.contains(&token_id) {
+                    visit(state, token_id, state);
+                } else if let Some(next) = self.next_state(state, token_id) {
+                    visit(state, token_id, next);
+                    if seen.insert(next) {
+                        next_states.push(next);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Returns an estimate of the heap memory held by the automaton, in bytes.
+    pub(super) fn memory_usage(&self) -> usize {
+        let transitions = self
+            .transitions
+            .values()
+            .map(map_memory_usage)
+            .sum::<usize>();
+        let groups = self
+            .groups
+            .iter()
+            .map(TokenGroup::memory_usage)
+            .sum::<usize>();
+        transitions + groups
+    }
+
+    /// Feeds the content of the automaton into a hash, in a deterministic order.
+    pub(super) fn hash_into(&self, write: &mut impl FnMut(u64)) {
+        for value in [
+            self.min,
+            self.max,
+            self.prefix_states,
+            self.suffix_states,
+            self.body_states,
+            self.initial_state.as_u32(),
+        ] {
+            write(value as u64);
+        }
+        let mut states: Vec<&StateId> = self.transitions.keys().collect();
+        states.sort_unstable();
+        for state in states {
+            let mut map: Vec<(&TokenId, &StateId)> = self.transitions[state].iter().collect();
+            map.sort_unstable();
+            write(state.as_u32() as u64);
+            write(map.len() as u64);
+            for (token_id, next_state) in map {
+                write(token_id.as_u32() as u64);
+                write(next_state.as_u32() as u64);
+            }
+        }
+        for group in &self.groups {
+            write(group.walks.len() as u64);
+            for &(token_id, started, end) in &group.walks {
+                let (part, i) = match end {
+                    Target::Prefix(i) => (0, i),
+                    Target::Body(i) => (1, i),
+                    Target::Suffix(i) => (2, i),
+                };
+                for value in [token_id.as_u32(), started, part, i] {
+                    write(value as u64);
+                }
+            }
+        }
+    }
+
+    fn state_id(&self, target: Target, count: u32) -> StateId {
+        StateId::new(match target {
+            Target::Prefix(i) => i,
+            Target::Suffix(i) => self.prefix_states + i,
+            Target::Body(i) => {
+                self.prefix_states + self.suffix_states + count * self.body_states + i
+            }
+        })
+    }
+
+    /// Splits the state into its target and the number of started repetitions.
+    fn decode(&self, state: StateId) -> Option<(Target, u32)> {
+        let state = state.as_u32();
+        if state < self.prefix_states {
+            return Some((Target::Prefix(state), 0));
+        }
+        let state = state - self.prefix_states;
+        if state < self.suffix_states {
+            return Some((Target::Suffix(state), 0));
+        }
+        let state = state - self.suffix_states;
+        let (count, i) = (state / self.body_states, state % self.body_states);
+        // Within a repetition, at least that one is started.
+        (count <= self.max && (i == 0 || count > 0)).then_some((Target::Body(i), count))
+    }
+
+    /// Returns the state reached by a token walk, if the number of repetitions stays within
+    /// the bounds.
+    fn advance(&self, from: Target, count: u32, started: u32, to: Target) -> Option<StateId> {
+        let count = count + started;
+        let valid = match (from, to) {
+            (_, Target::Body(_)) => count <= self.max,
+            (Target::Suffix(_), _) | (_, Target::Prefix(_)) => true,
+            // The walk has left the repetition.
+            (_, Target::Suffix(_)) => self.min <= count && count <= self.max,
+        };
+        valid.then(|| match to {
+            Target::Body(_) => self.state_id(to, count),
+            _ => self.state_id(to, 0),
+        })
+    }
+}
+
+impl<Context> Decode<Context> for CountedAutomaton {
+    fn decode<D: Decoder<Context = Context>>(decoder: &mut D) -> Result<Self, DecodeError> {
+        let automaton = Self {
+            min: Decode::decode(decoder)?,
+            max: Decode::decode(decoder)?,
+            terminators: Decode::decode(decoder)?,
+            prefix_states: Decode::decode(decoder)?,
+            suffix_states: Decode::decode(decoder)?,
+            body_states: Decode::decode(decoder)?,
+            initial_state: Decode::decode(decoder)?,
+            suffix_nullable: Decode::decode(decoder)?,
+            transitions: Decode::decode(decoder)?,
+            groups: Decode::decode(decoder)?,
+        };
+        automaton
+            .validate()
+            .map_err(|e| DecodeError::OtherString(format!("Malformed counted repetition: {e}")))?;
+        Ok(automaton)
+    }
+}
+
+bincode::impl_borrow_decode!(CountedAutomaton);
+
+impl CountedAutomaton {
+    /// Checks the invariants relied upon by the state arithmetic and the token groups.
+    fn validate(&self) -> Result<(), String> {
+        if self.min > self.max {
+            return Err(format!("bounds {{{},{}}}", self.min, self.max));
+        }
+        if self.terminators.is_empty() {
+            return Err("no end-of-sequence token".to_string());
+        }
+        if self.body_states == 0 || self.groups.len() != self.body_states as usize {
+            return Err(format!(
+                "{} token groups for {} body states",
+                self.groups.len(),
+                self.body_states
+            ));
+        }
+        // The last state of the last repetition must be numbered.
+        (self.max as u64 + 1)
+            .checked_mul(self.body_states as u64)
+            .and_then(|body| body.checked_add(self.prefix_states as u64))
+            .and_then(|states| states.checked_add(self.suffix_states as u64))
+            .filter(|&states| states <= u32::MAX as u64 + 1)
+            .ok_or("too many states")?;
+        if !self.contains_state(self.initial_state) {
+            return Err(format!("initial state {}", self.initial_state));
+        }
+        let in_range = |target: Target| match target {
+            Target::Prefix(i) => i < self.prefix_states,
+            Target::Body(i) => i < self.body_states,
+            Target::Suffix(i) => i < self.suffix_states,
+        };
+        let max_started = u32::MAX - self.max;
+        for (i, group) in self.groups.iter().enumerate() {
+            if group.tokens.len() != group.started.len()
+                || group.started.iter().any(|&started| started > max_started)
+                || group
+                    .exits
+                    .iter()
+                    .any(|&(_, started)| started > max_started)
+            {
+                return Err(format!("token group {i}"));
+            }
+            if !group.walks.windows(2).all(|pair| pair[0].0 < pair[1].0)
+                || group
+                    .walks
+                    .iter()
+                    .any(|&(_, started, to)| started > max_started || !in_range(to))
+            {
+                return Err(format!("walks of token group {i}"));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Iterator over the allowed tokens of a state of [`CountedAutomaton`].
+pub(super) enum CountedTokens<'a> {
+    Explicit(Keys<'a, TokenId, StateId>),
+    Body {
+        tokens: std::slice::Iter<'a, TokenId>,
+        exits: std::slice::Iter<'a, (TokenId, u32)>,
+        /// Bounds of the number of repetitions exiting tokens may start.
+        bounds: (u32, u32),
+        /// The end-of-sequence and stop tokens, in final states.
+        terminators: std::slice::Iter<'a, TokenId>,
+    },
+}
+
+impl<'a> Iterator for CountedTokens<'a> {
+    type Item = &'a TokenId;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            Self::Explicit(tokens) => tokens.next(),
+            Self::Body {
+                tokens,
+                exits,
+                bounds: (min, max),
+                terminators,
+            } => tokens
+                .next()
+                .or_else(|| {
+                    exits
+                        .find(|(_, started)| (*min..=*max).contains(started))
+                        .map(|(token_id, _)| token_id)
+                })
+                .or_else(|| terminators.next()),
+        }
+    }
+}
+
+/// Position of a walk within the DFAs of the parts.
+#[derive(Clone, Copy)]
+enum Position {
+    Prefix(AutomataStateId),
+    /// Between two repetitions, at the start of the body DFA.
+    Boundary,
+    /// Within a repetition, which may be at the start of the body DFA again.
+    Body(AutomataStateId),
+    Suffix(AutomataStateId),
+}
+
+struct Parts {
+    prefix: Option<Part>,
+    body: Part,
+    suffix: Part,
+}
+
+impl Parts {
+    fn is_unambiguous(&self) -> bool {
+        let prefix_free = |part: &Part| {
+            part.states.iter().all(|&state| {
+                !part.is_match(state) || (0..=255).all(|byte| part.next(state, byte).is_none())
+            })
+        };
+        let body = &self.body;
+        let suffix = &self.suffix;
+        self.prefix
+            .as_ref()
+            .is_none_or(|prefix| prefix_free(prefix) && !prefix.is_match(prefix.start))
+            && prefix_free(body)
+            && !body.is_match(body.start)
+            && (0..=255).all(|byte| {
+                body.next(body.start, byte).is_none() || suffix.next(suffix.start, byte).is_none()
+            })
+    }
+
+    /// Walks the bytes of a token, returns the reached position and the number of started
+    /// repetitions, or `None` if the token isn't valid from that position.
+    fn walk(&self, mut position: Position, token: &[u8]) -> Option<(Position, u32)> {
+        let body = &self.body;
+        let mut started = 0;
+        for &byte in token {
+            position = match position {
+                Position::Prefix(state) => {
+                    let prefix = self.prefix.as_ref()?;
+                    let next = prefix.next(state, byte)?;
+                    if prefix.is_match(next) {
+                        Position::Boundary
+                    } else {
+                        Position::Prefix(next)
+                    }
+                }
+                Position::Boundary => match body.next(body.start, byte) {
+                    Some(next) => {
+                        started += 1;
+                        body.position(next)
+                    }
+                    None => Position::Suffix(self.suffix.next(self.suffix.start, byte)?),
+                },
+                Position::Body(state) => body.position(body.next(state, byte)?),
+                Position::Suffix(state) => Position::Suffix(self.suffix.next(state, byte)?),
+            };
+        }
+        Some((position, started))
+    }
+}
+
+/// DFA of a part of the regular expression, restricted to the states leading to a match.
+struct Part {
+    dfa: DFA<Vec<u32>>,
+    start: AutomataStateId,
+    /// States leading to a match, in the order of discovery from `start`.
+    states: Vec<AutomataStateId>,
+    productive: HashSet<AutomataStateId>,
+}
+
+impl Part {
+    /// Builds the DFA within the limits of `usage`, or `None` if it can't be counted.
+    fn new(hir: &Hir, match_kind: MatchKind, usage: &ResourceUsage) -> Result<Option<Self>> {
+        // Automata exceeding the limits stop the construction, other failures fall back to the
+        // exploration of the DFA of the whole expression.
+        let exceeded = |error: Error| match error {
+            Error::ResourceLimitExceeded { .. } => Err(error),
+            _ => Ok(None),
+        };
+        let nfa = match Compiler::new()
+            .configure(usage.nfa_config())
+            .build_from_hir(hir)
+        {
+            Ok(nfa) => nfa,
+            Err(e) => return exceeded(usage.nfa_error(e)),
+        };
+        let dfa = match Builder::new()
+            .configure(usage.dfa_config().match_kind(match_kind))
+            .build_from_nfa(&nfa)
+        {
+            Ok(dfa) => dfa,
+            Err(e) => return exceeded(usage.dfa_error(e)),
+        };
+        let Some(start) = dfa.universal_start_state(Anchored::Yes) else {
+            return Ok(None);
+        };
+
+        let mut reachable = vec![start];
+        let mut reverse: HashMap<AutomataStateId, Vec<AutomataStateId>> = HashMap::default();
+        let mut i = 0;
+        while let Some(&state) = reachable.get(i) {
+            for byte in 0..=255u8 {
+                let next = dfa.next_state(state, byte);
+                if dfa.is_dead_state(next) || dfa.is_quit_state(next) {
+                    continue;
+                }
+                let previous = reverse.entry(next).or_default();
+                if previous.is_empty() && next != start {
+                    reachable.push(next);
+                }
+                previous.push(state);
+            }
+            i += 1;
+        }
+
+        let is_match = |state| dfa.is_match_state(dfa.next_eoi_state(state));
+        let mut productive: HashSet<AutomataStateId> =
+            reachable.iter().copied().filter(|&s| is_match(s)).collect();
+        let mut queue: Vec<AutomataStateId> = productive.iter().copied().collect();
+        while let Some(state) = queue.pop() {
+            for &previous in reverse.get(&state).into_iter().flatten() {
+                if productive.insert(previous) {
+                    queue.push(previous);
+                }
+            }
+        }
+        if !productive.contains(&start) {
+            return Ok(None);
+        }
+        let states = reachable
+            .into_iter()
+            .filter(|state| productive.contains(state))
+            .collect();
+        Ok(Some(Self {
+            dfa,
+            start,
+            states,
+            productive,
+        }))
+    }
+
+    fn next(&self, state: AutomataStateId, byte: u8) -> Option<AutomataStateId> {
+        let next = self.dfa.next_state(state, byte);
+        self.productive.contains(&next).then_some(next)
+    }
+
+    fn is_match(&self, state: AutomataStateId) -> bool {
+        self.dfa.is_match_state(self.dfa.next_eoi_state(state))
+    }
+
+    /// Returns the position of a body state, a match ends the repetition.
+    fn position(&self, state: AutomataStateId) -> Position {
+        if self.is_match(state) {
+            Position::Boundary
+        } else {
+            Position::Body(state)
+        }
+    }
+}
+
+/// Splits the regular expression around its repetition with the highest upper bound, if it's
+/// worth counting, into the prefix, the repeated body with its bounds and the suffix.
+fn split(regex: &str) -> Option<(Hir, Hir, u32, u32, Hir)> {
+    let hir = regex_syntax::parse(regex).ok()?;
+    // Assertions and lazy repetitions depend on the surroundings of the split.
+    if !hir.properties().look_set().is_empty() || !is_greedy(&hir) {
+        return None;
+    }
+    let hir = without_captures(&hir);
+    let items = match hir.kind() {
+        HirKind::Concat(items) => items.as_slice(),
+        _ => std::slice::from_ref(hir),
+    };
+    let (position, repetition) = items
+        .iter()
+        .enumerate()
+        .filter_map(|(i, item)| match without_captures(item).kind() {
+            HirKind::Repetition(repetition) => Some((i, repetition)),
+            _ => None,
+        })
+        .max_by_key(|(_, repetition)| repetition.max)?;
+    let max = repetition.max?;
+    let body = without_captures(&repetition.sub);
+    let any_char = *body == Hir::dot(Dot::AnyChar) || *body == Hir::dot(Dot::AnyCharExceptLF);
+    if max < COUNTED_REPETITION_THRESHOLD && !(any_char && items.len() == 1) {
+        return None;
+    }
+    Some((
+        Hir::concat(items[..position].to_vec()),
+        body.clone(),
+        repetition.min,
+        max,
+        Hir::concat(items[position + 1..].to_vec()),
+    ))
+}
+
+fn without_captures(mut hir: &Hir) -> &Hir {
+    while let HirKind::Capture(capture) = hir.kind() {
+        hir = &capture.sub;
+    }
+    hir
+}
+
+fn is_greedy(hir: &Hir) -> bool {
+    match hir.kind() {
+        HirKind::Repetition(repetition) => repetition.greedy && is_greedy(&repetition.sub),
+        HirKind::Capture(capture) => is_greedy(&capture.sub),
+        HirKind::Concat(items) | HirKind::Alternation(items) => items.iter().all(is_greedy),
+        _ => true,
+    }
+}
+
+/// Explicit transitions of a [`CountedAutomaton`], materialized on demand.
+///
+/// Data derived from an index on demand, e.g. explicit transitions of the automaton.
+///
+/// Not serialized, nor compared, since it's derived from the automaton.
+#[derive(Clone, Debug, Default)]
+pub(super) struct Materialized<T = HashMap<StateId, HashMap<TokenId, StateId>>>(
+    pub(super) OnceLock<T>,
+);
+
+impl<T> PartialEq for Materialized<T> {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl<T> Encode for Materialized<T> {
+    fn encode<E: Encoder>(&self, _: &mut E) -> Result<(), EncodeError> {
+        Ok(())
+    }
+}
+
+impl<Context, T> Decode<Context> for Materialized<T> {
+    fn decode<D: Decoder<Context = Context>>(_: &mut D) -> Result<Self, DecodeError> {
+        Ok(Self(OnceLock::new()))
+    }
+}
+
+bincode::impl_borrow_decode!(Materialized<T>, T);
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+
+    use super::*;
+    use crate::index::limits::ResourceUsage;
+    use crate::index::{Index, ResourceLimits};
+
+    #[test]
+    fn split_repetitions() {
+        for (regex, expected) in [
+            (".{1,5}", Some((0, 1, 5, 0))),
+            ("(.{0,300})", Some((0, 0, 300, 0))),
+            ("[^\\n]{2}", Some((0, 2, 2, 0))),
+            ("(?s:.){3,4}", Some((0, 3, 4, 0))),
+            (r#""[a-z]{0,64}""#, Some((1, 0, 64, 1))),
+            (r#"\[(?:[0-9],){2,100}[0-9]{1,3}\]"#, Some((1, 2, 100, 2))),
+            (".{1,}", None),
+            ("a{1,5}", None),
+            ("a.{1,5}", None),
+            ("[a-z]{0,100}?", None),
+            (r"\b[a-z]{0,100}", None),
+        ] {
+            let split = split(regex).map(|(prefix, _, min, max, suffix)| {
+                let len = |hir: &Hir| match hir.kind() {
+                    HirKind::Empty => 0,
+                    HirKind::Concat(items) => items.len(),
+                    _ => 1,
+                };
+                (len(&prefix), min, max, len(&suffix))
+            });
+            assert_eq!(split, expected, "{regex}");
+        }
+    }
+
+    fn vocabulary() -> Vocabulary {
+        let mut vocabulary = Vocabulary::new(0);
+        let tokens: [&[u8]; 19] = [
+            b"a",
+            b"bc",
+            b"def",
+            b"\n",
+            "é".as_bytes(),
+            &[0xC3],
+            &[0xA9],
+            &[0xE2, 0x82],
+            &[0xAC, b'a'],
+            &[0xAC],
+            b"\"",
+            b"a\"",
+            b"\"}",
+            b"{\"a\":\"",
+            b"\\",
+            b"\\\"",
+            b"1,",
+            b",",
+            b"2]",
+        ];
+        for (token_id, token) in tokens.into_iter().enumerate() {
+            vocabulary
+                .try_insert(token, token_id as u32 + 1)
+                .expect("Insert failed");
+        }
+        for (token, token_id) in [("1", 20), ("2", 21), ("[", 22), ("]", 23), ("}", 24)] {
+            vocabulary
+                .try_insert(token, token_id)
+                .expect("Insert failed");
+        }
+        vocabulary
+    }
+
+    #[test]
+    fn matches_dfa_index() {
+        let vocabulary = vocabulary();
+        let table = TokenTable::new(&vocabulary);
+        for regex in [
+            ".{1,5}",
+            ".{0,3}",
+            ".{4}",
+            "(?s:.){2,3}",
+            r#""[a-z]{0,70}""#,
+            r#"\{"a":"(?:[^"\\\x00-\x1F]|\\["\\]){1,80}"\}"#,
+            r#"\[[12](?:,[12]){2,70}\]"#,
+            r#"[12]{0,65}"#,
+        ] {
+            let counted = Index::new(regex, &vocabulary).expect("Index failed");
+            assert!(counted.counted.is_some(), "{regex}");
+            assert_matches_dfa_index(regex, &counted, &table);
+        }
+    }
+
+    /// Walks the index and the one of the explored DFA in lockstep.
+    fn assert_matches_dfa_index(regex: &str, counted: &Index, table: &TokenTable) {
+        let limits = ResourceLimits::default();
+        let mut usage = ResourceUsage::new(&limits);
+        let explored =
+            Index::explore_dfa(regex, table, &mut usage, |_, _| Ok(())).expect("Index failed");
+
+        // Walk both indexes in lockstep.
+        let mut queue = VecDeque::from([(counted.initial_state(), explored.initial_state())]);
+        let mut seen = HashSet::from_iter([counted.initial_state()]);
+        while let Some((a, b)) = queue.pop_front() {
+            assert_eq!(counted.is_final_state(&a), explored.is_final_state(&b));
+            let mut allowed = counted.allowed_tokens(&a).expect("Allowed tokens");
+            let mut expected = explored.allowed_tokens(&b).expect("Allowed tokens");
+            allowed.sort_unstable();
+            expected.sort_unstable();
+            assert_eq!(allowed, expected, "{regex} at {a}");
+            for token_id in allowed {
+                let next = counted.next_state(&a, &token_id);
+                let expected = explored.next_state(&b, &token_id);
+                assert_eq!(next.is_some(), expected.is_some());
+                if let (Some(next), Some(expected)) = (next, expected) {
+                    if seen.insert(next) {
+                        queue.push_back((next, expected));
+                    }
+                }
+            }
+        }
+
+        // Materialized transitions agree with the counter.
+        for (state, map) in counted.transitions() {
+            for (token_id, next) in map {
+                if *token_id != 0 {
+                    assert_eq!(counted.next_state(state, token_id), Some(*next));
+                }
+            }
+            assert_eq!(
+                map.len(),
+                counted.allowed_tokens(state).expect("Allowed tokens").len()
+            );
+        }
+        assert!(seen.iter().all(|s| counted.transitions().contains_key(s)));
+    }
+
+    #[test]
+    fn counts_bodies_with_inner_loops() {
+        let mut vocabulary = Vocabulary::new(0);
+        let tokens = [
+            "a", "b", "c", "1", "2", ",", "aa", "ab", "ba", "bc", "1,", "2,1",
+        ];
+        for (token_id, token) in tokens.into_iter().enumerate() {
+            vocabulary
+                .try_insert(token, token_id as u32 + 1)
+                .expect("Insert failed");
+        }
+        let table = TokenTable::new(&vocabulary);
+        // The body DFA loops back to its start within a repetition, e.g. after `a` of `a*b`.
+        for body in ["a*b", "(ab|a)", "[0-9]+,"] {
+            for max in [63, 64] {
+                for regex in [
+                    format!("({body}){{0,{max}}}c"),
+                    format!("({body}){{1,{max}}}"),
+                ] {
+                    let index = Index::new(&regex, &vocabulary).expect("Index failed");
+                    assert_matches_dfa_index(&regex, &index, &table);
+                }
+            }
+        }
+
+        let accepts = |index: &Index, text: &str| {
+            let mut state = index.initial_state();
+            for c in text.chars() {
+                let token_id = vocabulary.token_ids(c.to_string()).expect("No token")[0];
+                match index.next_state(&state, &token_id) {
+                    Some(next) => state = next,
+                    None => return false,
+                }
+            }
+            index.is_final_state(&state)
+        };
+        let index = Index::new("(a*b){0,64}c", &vocabulary).expect("Index failed");
+        assert!(index.counted.is_some());
+        for (text, expected) in [
+            ("ac", false),
+            ("aac", false),
+            ("abac", false),
+            ("abbc", true),
+        ] {
+            assert_eq!(accepts(&index, text), expected, "{text}");
+        }
+        let index = Index::new("(a*b){1,64}", &vocabulary).expect("Index failed");
+        assert!(index.counted.is_some());
+        assert!(accepts(&index, &format!("{}b", "a".repeat(100))));
+        assert!(!accepts(&index, &"a".repeat(100)));
+    }
+
+    #[test]
+    fn falls_back_when_ambiguous_or_uncovered() {
+        let counted = |regex: &str, table: &TokenTable| {
+            let limits = ResourceLimits::default();
+            CountedAutomaton::new(regex, table, &mut ResourceUsage::new(&limits))
+                .expect("Counting failed")
+        };
+        let vocabulary = vocabulary();
+        let table = TokenTable::new(&vocabulary);
+        // Digits may continue the repetition or start the suffix.
+        assert!(counted("[12]{0,100}[12]", &table).is_none());
+        // The prefix may continue after its match.
+        assert!(counted("a+[12]{0,100}", &table).is_none());
+
+        // The lone leading byte can't be completed.
+        let mut vocabulary = Vocabulary::new(0);
+        vocabulary.try_insert("a", 1).expect("Insert failed");
+        vocabulary.try_insert([0xC3], 2).expect("Insert failed");
+        let table = TokenTable::new(&vocabulary);
+        assert!(counted(".{1,3}", &table).is_none());
+        assert!(matches!(
+            Index::new(".{1,3}", &vocabulary),
+            Err(crate::Error::IncompatibleVocabulary { .. })
+        ));
+    }
+
+    #[test]
+    fn decode_validates_automaton() {
+        let config = bincode::config::standard();
+        let vocabulary = vocabulary();
+        let index = Index::new(".{1,80}", &vocabulary).expect("Index failed");
+        let encoded = bincode::encode_to_vec(&index, config).expect("Encoding failed");
+        let (decoded, _): (Index, usize) =
+            bincode::decode_from_slice(&encoded, config).expect("Decoding failed");
+        assert_eq!(decoded, index);
+
+        let valid = index.counted.clone().expect("Not counted");
+        let mut malformed = Vec::new();
+        let mut automaton = valid.clone();
+        automaton.groups.pop();
+        malformed.push(automaton);
+        let mut automaton = valid.clone();
+        automaton.groups[0].started.clear();
+        malformed.push(automaton);
+        let mut automaton = valid.clone();
+        automaton.groups[0].walks[0].2 = Target::Suffix(valid.suffix_states);
+        malformed.push(automaton);
+        let mut automaton = valid.clone();
+        automaton.body_states = 0;
+        automaton.groups.clear();
+        malformed.push(automaton);
+        let mut automaton = valid.clone();
+        automaton.max = u32::MAX;
+        malformed.push(automaton);
+        let mut automaton = valid;
+        automaton.terminators.clear();
+        malformed.push(automaton);
+
+        for automaton in malformed {
+            let mut index = index.clone();
+            index.counted = Some(automaton);
+            let encoded = bincode::encode_to_vec(&index, config).expect("Encoding failed");
+            let decoded: Result<(Index, usize), _> = bincode::decode_from_slice(&encoded, config);
+            assert!(
+                matches!(decoded, Err(DecodeError::OtherString(_))),
+                "{decoded:?}"
+            );
+        }
+    }
+}
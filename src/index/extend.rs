@@ -0,0 +1,230 @@
+//! Extension of an index with the tokens appended to its vocabulary, e.g. special tokens of a
+//! LoRA adapter, without exploring the automaton again for the whole vocabulary.
+//!
+//! Only the new tokens are walked from each state of the index. A new token reaching a state,
+//! which the index doesn't have, e.g. the middle of a constant spelled by other tokens, would
+//! need the whole vocabulary to be explored, so the index has to be rebuilt then.
+
+use regex_automata::dfa::Automaton;
+use regex_automata::util::primitives::StateID as AutomataStateId;
+use rustc_hash::FxHashMap as HashMap;
+
+use super::counted::Materialized;
+use super::limits::ResourceUsage;
+use super::{Index, ResourceLimits};
+use crate::prelude::*;
+use crate::vocabulary::VocabDelta;
+use crate::{Error, Result};
+
+impl Index {
+    /// Adds the transitions of the tokens of `delta` to the index, as if it was built with
+    /// the extended vocabulary.
+    ///
+    /// The index has to be built with the vocabulary `delta` extends, which its
+    /// [`Provenance`](super::Provenance) tells, or it fails with
+    /// [`Error::VocabularyMismatch`]. It fails with [`Error::IndexRebuildRequired`] if a new
+    /// token reaches a state, which the index doesn't have, or if the index counts a bounded
+    /// repetition. The index is left unchanged on failure.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use outlines_core::Error;
+    /// use outlines_core::prelude::*;
+    /// use outlines_core::vocabulary::VocabDelta;
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// let mut vocabulary = Vocabulary::new(0);
+    /// for (token, token_id) in [("<", 1), ("a", 2), ("b", 3), (">", 4)] {
+    ///     vocabulary.try_insert(token, token_id)?;
+    /// }
+    /// let mut index = Index::new("<[ab]+>", &vocabulary)?;
+    ///
+    /// let mut extended = vocabulary.clone();
+    /// extended.try_insert("ab", 5)?;
+    /// extended.try_insert("<tool>", 6)?;
+    /// index.extend_vocabulary(&VocabDelta::between(&vocabulary, &extended)?)?;
+    /// assert_eq!(index, Index::new("<[ab]+>", &extended)?);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn extend_vocabulary(&mut self, delta: &VocabDelta) -> Result<()> {
+        self.extend_vocabulary_with_limits(delta, &ResourceLimits::default())
+    }
+
+    /// Extends the index like [`extend_vocabulary`](Self::extend_vocabulary) within the limits
+    /// of resources, which bound the extended index, failing with
+    /// [`Error::ResourceLimitExceeded`] once one is exceeded. The index is left unchanged on
+    /// failure.
+    pub fn extend_vocabulary_with_limits(
+        &mut self,
+        delta: &VocabDelta,
+        limits: &ResourceLimits,
+    ) -> Result<()> {
+        let Some(provenance) = &self.provenance else {
+            return Err(Error::VocabularyMismatch(
+                "the vocabulary of the index is unknown".to_string(),
+            ));
+        };
+        if provenance.vocabulary_fingerprint != delta.base_fingerprint() {
+            return Err(Error::VocabularyMismatch(format!(
+                "fingerprint {} instead of {}",
+                delta.base_fingerprint(),
+                provenance.vocabulary_fingerprint
+            )));
+        }
+        if self.counted.is_some() {
+            return Err(Error::IndexRebuildRequired(
+                "the transitions of a bounded repetition are counted".to_string(),
+            ));
+        }
+
+        let mut usage = ResourceUsage::new(limits);
+        usage.check_states(self.transitions.len())?;
+        usage.add_tokens(self.transitions.values().map(HashMap::len).sum())?;
+        let dfa = usage.build_dfa(&provenance.regex)?;
+        // States walked to a match or to more bytes, like the ones kept by the exploration.
+        let is_useful = |state: AutomataStateId| {
+            dfa.is_match_state(dfa.next_eoi_state(state))
+                || dfa.byte_classes().representatives(..).any(|repr| {
+                    repr.as_u8().is_some_and(|byte| {
+                        let next = dfa.next_state(state, byte);
+                        !dfa.is_dead_state(next) && !dfa.is_quit_state(next)
+                    })
+                })
+        };
+        let mut added = Vec::new();
+        for &state in self.transitions.keys() {
+            let Ok(start) = AutomataStateId::new(state.as_usize()) else {
+                continue;
+            };
+            'token_loop: for (token, token_id) in delta.tokens() {
+                let mut next_state = start;
+                for &byte in token {
+                    next_state = dfa.next_state(next_state, byte);
+                    if dfa.is_dead_state(next_state) || dfa.is_quit_state(next_state) {
+                        continue 'token_loop;
+                    }
+                }
+                if !is_useful(next_state) {
+                    continue;
+                }
+                let next_state = StateId::new(next_state.as_u32());
+                if !self.transitions.contains_key(&next_state) {
+                    return Err(Error::IndexRebuildRequired(format!(
+                        "token {token_id} reaches the state {next_state}, which the index doesn't have"
+                    )));
+                }
+                added.push((state, *token_id, next_state));
+                usage.add_tokens(1)?;
+            }
+            usage.check_time()?;
+        }
+        usage.add_tokens(self.final_states.len() * delta.stop_token_ids().len())?;
+
+        for (state, token_id, next_state) in added {
+            self.transitions
+                .entry(state)
+                .or_default()
+                .insert(token_id, next_state);
+        }
+        for &final_state in &self.final_states {
+            let map = self.transitions.entry(final_state).or_default();
+            for &token_id in delta.stop_token_ids() {
+                map.insert(token_id, final_state);
+            }
+        }
+        self.stop_token_ids.extend(delta.stop_token_ids());
+        self.vocab_size = delta.vocab_size();
+        if let Some(provenance) = &mut self.provenance {
+            provenance.vocabulary_fingerprint = delta.fingerprint();
+        }
+        self.fingerprint = self.compute_fingerprint();
+        self.materialized = Materialized::default();
+        self.distances = Materialized::default();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vocabulary(tokens: &[(&str, u32)]) -> Vocabulary {
+        let mut vocabulary = Vocabulary::new(0);
+        for &(token, token_id) in tokens {
+            vocabulary
+                .try_insert(token, token_id)
+                .expect("Insert failed");
+        }
+        vocabulary
+    }
+
+    #[test]
+    fn extend_with_new_tokens() {
+        let regex = r#"\{"a":[0-9]+\}"#;
+        let base = vocabulary(&[("{", 1), ("\"a\":", 2), ("1", 3), ("2", 4), ("}", 5)]);
+        let mut index = Index::new(regex, &base).expect("Index failed");
+
+        let mut extended = base.clone();
+        extended.try_insert("12", 6).expect("Insert failed");
+        extended.try_insert("}", 7).expect("Insert failed");
+        extended.try_insert("<lora>", 8).expect("Insert failed");
+        extended.try_insert_stop_token(9).expect("Insert failed");
+        let delta = VocabDelta::between(&base, &extended).expect("Delta failed");
+        assert_eq!(delta.tokens().len(), 3);
+        assert_eq!(delta.stop_token_ids(), [TokenId::new(9)]);
+
+        let limits = ResourceLimits::default().with_max_masks_bytes(20);
+        assert!(matches!(
+            index.extend_vocabulary_with_limits(&delta, &limits),
+            Err(Error::ResourceLimitExceeded {
+                resource: "bytes of masks",
+                ..
+            })
+        ));
+        assert_eq!(index, Index::new(regex, &base).expect("Index failed"));
+
+        index.extend_vocabulary(&delta).expect("Extension failed");
+        let rebuilt = Index::new(regex, &extended).expect("Index failed");
+        assert_eq!(index, rebuilt);
+        assert!(index.verify_vocabulary(&extended).is_ok());
+        // The delta only applies to the vocabulary it extends.
+        assert!(matches!(
+            index.extend_vocabulary(&delta),
+            Err(Error::VocabularyMismatch(_))
+        ));
+
+        // `{"` only exists in the middle of the token `"a":` so far.
+        let mut split = base.clone();
+        split.try_insert("{\"", 10).expect("Insert failed");
+        let delta = VocabDelta::between(&base, &split).expect("Delta failed");
+        let mut index = Index::new(regex, &base).expect("Index failed");
+        assert!(matches!(
+            index.extend_vocabulary(&delta),
+            Err(Error::IndexRebuildRequired(_))
+        ));
+        assert_eq!(index, Index::new(regex, &base).expect("Index failed"));
+    }
+
+    #[test]
+    fn reject_shrunk_vocabulary() {
+        let base = vocabulary(&[("a", 1), ("b", 2)]);
+        let mut shrunk = base.clone();
+        shrunk.remove("b");
+        assert!(matches!(
+            VocabDelta::between(&base, &shrunk),
+            Err(Error::VocabularyNotExtended(reason)) if reason == "token 2 is removed"
+        ));
+        let mut grown = base.clone();
+        grown.try_insert("c", 3).expect("Insert failed");
+        assert!(VocabDelta::between(&base, &grown).is_ok());
+        let mut other_eos = Vocabulary::new(5);
+        other_eos.try_insert("a", 1).expect("Insert failed");
+        other_eos.try_insert("b", 2).expect("Insert failed");
+        assert!(matches!(
+            VocabDelta::between(&base, &other_eos),
+            Err(Error::VocabularyNotExtended(_))
+        ));
+    }
+}
@@ -0,0 +1,210 @@
+//! Quotas of an index construction, e.g. to compile untrusted schemas of many tenants with a
+//! bounded cost each.
+//!
+//! The limits are checked while the automaton is explored, after each state, so that a
+//! construction exceeding them stops early with [`Error::ResourceLimitExceeded`]. The automata
+//! of the regular expression are bounded as well, so that a blow-up of their determinization
+//! stops before the exploration.
+
+use std::error::Error as _;
+use std::time::Instant;
+
+use regex_automata::dfa::dense::{self, DFA};
+use regex_automata::nfa::thompson;
+use regex_automata::util::syntax;
+
+use crate::primitives::{StateId, TokenId};
+use crate::{Error, Result};
+
+/// Estimated bytes of an allowed token of a state, the entry of its transitions table with
+/// the control byte of its bucket.
+const MASK_ENTRY_BYTES: usize = std::mem::size_of::<(TokenId, StateId)>() + 1;
+
+/// Bytes of the automata of the regular expression allowed per state of the index, since the
+/// automata have more states, e.g. within the tokens, each one with transitions by at most
+/// 257 byte classes.
+const AUTOMATON_BYTES_PER_STATE: usize = 16 * 257 * std::mem::size_of::<u32>();
+
+/// Bounds of the resources of an index construction, unlimited by default.
+///
+/// The automata of the regular expression are limited to a multiple of `max_states`, so that
+/// a blow-up of their determinization, e.g. `(a|b)*a(a|b){20}`, is rejected before any state
+/// is explored. A bounded repetition, which is counted rather than explored, e.g.
+/// `[a-z]{0,4096}`, isn't limited by `max_states`, its states being implied by the counter.
+///
+/// # Example
+///
+/// ```rust
+/// # use outlines_core::Error;
+/// use outlines_core::index::ResourceLimits;
+/// use outlines_core::prelude::*;
+///
+/// # fn main() -> Result<(), Error> {
+/// let mut vocabulary = Vocabulary::new(3);
+/// for (token, token_id) in [("1", 1), ("2", 2)] {
+///     vocabulary.try_insert(token, token_id)?;
+/// }
+///
+/// let limits = ResourceLimits::default().with_max_states(4);
+/// assert!(Index::with_limits("[12][12][12]", &vocabulary, &limits).is_ok());
+/// assert!(matches!(
+///     Index::with_limits("[12][12][12][12]", &vocabulary, &limits),
+///     Err(Error::ResourceLimitExceeded { .. })
+/// ));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ResourceLimits {
+    /// Maximal number of states of the index.
+    pub max_states: Option<usize>,
+    /// Maximal estimate of the memory of the allowed tokens of all the states, in bytes, see
+    /// [`MemoryBreakdown::transitions`](super::MemoryBreakdown::transitions).
+    pub max_masks_bytes: Option<usize>,
+    /// Maximal duration of the construction, in milliseconds.
+    pub max_build_ms: Option<u64>,
+}
+
+impl ResourceLimits {
+    /// Sets the maximal number of states of the index.
+    pub fn with_max_states(self, max_states: usize) -> Self {
+        Self {
+            max_states: Some(max_states),
+            ..self
+        }
+    }
+
+    /// Sets the maximal estimate of the memory of the allowed tokens, in bytes.
+    pub fn with_max_masks_bytes(self, max_masks_bytes: usize) -> Self {
+        Self {
+            max_masks_bytes: Some(max_masks_bytes),
+            ..self
+        }
+    }
+
+    /// Sets the maximal duration of the construction, in milliseconds.
+    pub fn with_max_build_ms(self, max_build_ms: u64) -> Self {
+        Self {
+            max_build_ms: Some(max_build_ms),
+            ..self
+        }
+    }
+
+    /// Maximal size of each automaton of the regular expression, in bytes.
+    fn automaton_bytes(&self) -> Option<usize> {
+        self.max_states
+            .map(|states| states.saturating_mul(AUTOMATON_BYTES_PER_STATE))
+    }
+}
+
+/// Resources used by a running construction, checked against its limits.
+pub(super) struct ResourceUsage<'a> {
+    limits: &'a ResourceLimits,
+    start: Instant,
+    masks_bytes: usize,
+}
+
+impl<'a> ResourceUsage<'a> {
+    pub(super) fn new(limits: &'a ResourceLimits) -> Self {
+        Self {
+            limits,
+            start: Instant::now(),
+            masks_bytes: 0,
+        }
+    }
+
+    /// Accounts for `count` allowed tokens added to a state.
+    pub(super) fn add_tokens(&mut self, count: usize) -> Result<()> {
+        self.add_bytes(count * MASK_ENTRY_BYTES)
+    }
+
+    /// Accounts for `bytes` of allowed tokens, e.g. of counted transitions.
+    pub(super) fn add_bytes(&mut self, bytes: usize) -> Result<()> {
+        self.masks_bytes += bytes;
+        match self.limits.max_masks_bytes {
+            Some(limit) if self.masks_bytes > limit => Err(Error::ResourceLimitExceeded {
+                resource: "bytes of masks",
+                limit: limit as u64,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks the number of states found so far and the elapsed time.
+    pub(super) fn check_states(&self, states: usize) -> Result<()> {
+        if let Some(limit) = self.limits.max_states.filter(|&limit| states > limit) {
+            return Err(Error::ResourceLimitExceeded {
+                resource: "states",
+                limit: limit as u64,
+            });
+        }
+        self.check_time()
+    }
+
+    /// Configuration of the NFA of the regular expression within the limits.
+    pub(super) fn nfa_config(&self) -> thompson::Config {
+        thompson::Config::new().nfa_size_limit(self.limits.automaton_bytes())
+    }
+
+    /// Configuration of the DFA of the regular expression within the limits.
+    pub(super) fn dfa_config(&self) -> dense::Config {
+        let limit = self.limits.automaton_bytes();
+        dense::Config::new()
+            .dfa_size_limit(limit)
+            .determinize_size_limit(limit)
+    }
+
+    /// Builds the DFA of the regular expression, which may match bytes of invalid UTF-8,
+    /// e.g. `(?-u:\xFF)` for binary formats.
+    pub(super) fn build_dfa(&self, regex: &str) -> Result<DFA<Vec<u32>>> {
+        DFA::builder()
+            .syntax(syntax::Config::new().utf8(false))
+            .thompson(self.nfa_config().utf8(false))
+            .configure(self.dfa_config())
+            .build(regex)
+            .map_err(|e| self.dfa_error(e))
+    }
+
+    /// Reports a DFA exceeding its size limit as a construction exceeding the limits.
+    pub(super) fn dfa_error(&self, error: dense::BuildError) -> Error {
+        let nfa_exceeded = error
+            .source()
+            .and_then(|source| source.downcast_ref::<thompson::BuildError>())
+            .is_some_and(|source| source.size_limit().is_some());
+        match self.automaton_exceeded() {
+            Some(exceeded) if error.is_size_limit_exceeded() || nfa_exceeded => exceeded,
+            _ => Box::new(error).into(),
+        }
+    }
+
+    /// Reports an NFA exceeding its size limit as a construction exceeding the limits.
+    pub(super) fn nfa_error(&self, error: thompson::BuildError) -> Error {
+        match self.automaton_exceeded() {
+            Some(exceeded) if error.size_limit().is_some() => exceeded,
+            _ => Box::new(error).into(),
+        }
+    }
+
+    /// Error of an automaton exceeding its size limit, if it has one.
+    fn automaton_exceeded(&self) -> Option<Error> {
+        self.limits
+            .automaton_bytes()
+            .map(|limit| Error::ResourceLimitExceeded {
+                resource: "bytes of automata",
+                limit: limit as u64,
+            })
+    }
+
+    /// Checks the time elapsed since the start of the construction.
+    pub(super) fn check_time(&self) -> Result<()> {
+        match self.limits.max_build_ms {
+            Some(limit) if self.start.elapsed().as_millis() > limit as u128 => {
+                Err(Error::ResourceLimitExceeded {
+                    resource: "milliseconds",
+                    limit,
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+}
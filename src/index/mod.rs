@@ -0,0 +1,2434 @@
+//! Building an `Index` to efficiently map vocabulary tokens to state transitions.
+
+use std::collections::hash_map::Entry;
+use std::io::{Read, Write};
+use std::iter;
+use std::ops::ControlFlow;
+use std::time::{Duration, Instant};
+
+use bincode::{config, Decode, Encode};
+use regex_automata::dfa::dense::DFA;
+use regex_automata::dfa::Automaton;
+use regex_automata::util::primitives::StateID as AutomataStateId;
+use regex_automata::Anchored;
+use rustc_hash::{FxHashMap as HashMap, FxHashSet as HashSet};
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::*;
+use crate::primitives::SUMMARY_ENTRIES;
+use crate::vocabulary::Vocabulary;
+use crate::{Error, Result};
+
+mod classes;
+mod codegen;
+mod counted;
+mod extend;
+mod limits;
+mod prefilter;
+mod verify;
+
+pub use classes::ByteClasses;
+use counted::{CountedAutomaton, Materialized};
+pub use limits::ResourceLimits;
+use limits::ResourceUsage;
+use prefilter::LiteralPrefilter;
+pub use verify::Provenance;
+
+/// Version of the streaming format produced by [`Index::write_to`].
+const STREAM_FORMAT_VERSION: u8 = 5;
+
+/// Number of states serialized per chunk by [`Index::write_to`].
+const STREAM_CHUNK_STATES: usize = 1024;
+
+/// Bytes [`Index::read_from`] allocates at most for each decoded part of a stream, so that
+/// forged lengths fail instead of exhausting the memory.
+const STREAM_DECODE_LIMIT: usize = 1 << 30;
+
+/// States [`Index::read_from`] reserves at most before reading them, whatever the header
+/// claims.
+const STREAM_RESERVED_STATES: usize = 64 * STREAM_CHUNK_STATES;
+
+/// Number of explored states between two reports of [`IndexBuilder::on_progress`], by default.
+const PROGRESS_INTERVAL_STATES: usize = 1000;
+
+/// Callback receiving the progress of an index construction.
+type ProgressCallback<'a> = Box<dyn FnMut(BuildProgress) -> ControlFlow<()> + Send + 'a>;
+
+/// Progress of an index construction, reported by [`IndexBuilder::on_progress`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BuildProgress {
+    /// Number of automaton states explored so far.
+    pub states_explored: usize,
+    /// Number of states discovered, but not explored yet.
+    pub frontier_size: usize,
+    /// Time elapsed since the start of the construction.
+    pub elapsed: Duration,
+}
+
+/// Progress of a streaming (de)serialization, reported once per written or read chunk.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StreamProgress {
+    /// Number of states processed so far.
+    pub states_done: usize,
+    /// Total number of states with transitions in the index.
+    pub states_total: usize,
+    /// Number of bytes written or read so far.
+    pub bytes: usize,
+}
+
+/// Estimate of the memory held by an [`Index`], in bytes, see [`Index::footprint`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MemoryBreakdown {
+    /// The `Index` structure itself.
+    pub base: usize,
+    /// The set of final states.
+    pub final_states: usize,
+    /// The explicit transitions.
+    pub transitions: usize,
+    /// The counter-augmented transitions of a bounded repetition, if any.
+    pub counted: usize,
+    /// The stop tokens.
+    pub stop_tokens: usize,
+    /// Data built on demand, e.g. by [`Index::transitions`] or [`Index::distance_to_final`].
+    pub caches: usize,
+}
+
+impl MemoryBreakdown {
+    /// Returns the memory held by all the parts.
+    pub fn total(&self) -> usize {
+        self.base
+            + self.final_states
+            + self.transitions
+            + self.counted
+            + self.stop_tokens
+            + self.caches
+    }
+}
+
+/// Estimate of the heap memory of a hash table with `capacity` entries, each bucket of
+/// which also has a control byte.
+fn table_bytes<K, V>(capacity: usize) -> usize {
+    capacity * (std::mem::size_of::<(K, V)>() + 1)
+}
+
+/// Fixed part of the streaming format, written before the chunks of transitions.
+#[derive(Encode, Decode)]
+struct StreamHeader {
+    version: u8,
+    initial_state: StateId,
+    final_states: Vec<StateId>,
+    eos_token_id: TokenId,
+    vocab_size: usize,
+    states_total: usize,
+}
+
+/// Chunk of states of the streaming format.
+///
+/// Many states allow the same tokens, so each distinct set of allowed tokens, a mask, is
+/// written once, by the chunk of its first state, and is referred to by its number after.
+#[derive(Encode, Decode)]
+struct StreamChunk {
+    /// Masks used for the first time by the states of the chunk, in order of their numbers.
+    masks: Vec<Vec<TokenId>>,
+    /// States with the number of their mask and the next states of its tokens, in order.
+    /// A single next state is shared by all the tokens of the mask.
+    states: Vec<(StateId, u32, Vec<StateId>)>,
+}
+
+/// Vocabulary tokens prepared for the construction of indexes, independently of
+/// the regular expression.
+pub(crate) struct TokenTable<'a> {
+    /// Tokens grouped by their first byte, the last group holds empty tokens. Used to
+    /// skip tokens, which can't follow a forced literal, without walking the DFA.
+    by_first_byte: Vec<Vec<(&'a Token, &'a Vec<TokenId>)>>,
+    /// All tokens in the order of the vocabulary, which keeps the order of transitions.
+    tokens: Vec<(&'a Token, &'a Vec<TokenId>)>,
+    /// Non-empty tokens by their bytes. Used to look up the tokens spelling the prefixes of a
+    /// long forced literal, e.g. a constant of a JSON schema, without scanning the vocabulary.
+    by_bytes: HashMap<&'a [u8], (&'a Token, &'a Vec<TokenId>)>,
+    max_token_len: usize,
+    eos_token_id: TokenId,
+    stop_token_ids: Vec<TokenId>,
+    vocab_size: usize,
+    vocabulary_fingerprint: u64,
+}
+
+impl<'a> TokenTable<'a> {
+    pub(crate) fn new(vocabulary: &'a Vocabulary) -> Self {
+        let eos_token_id = vocabulary.eos_token_id();
+        let mut by_first_byte: Vec<Vec<(&Token, &Vec<TokenId>)>> = vec![Vec::new(); 257];
+        let mut tokens = Vec::with_capacity(vocabulary.tokens().len());
+        let mut by_bytes = HashMap::default();
+        let mut max_token_len = 0;
+        for (token, ids) in vocabulary.tokens().iter() {
+            if ids.contains(&eos_token_id) {
+                continue;
+            }
+            let group = token.first().map_or(256, |&byte| byte as usize);
+            by_first_byte[group].push((token, ids));
+            tokens.push((token, ids));
+            if !token.is_empty() {
+                by_bytes.insert(token.as_slice(), (token, ids));
+            }
+            max_token_len = max_token_len.max(token.len());
+        }
+        Self {
+            by_first_byte,
+            tokens,
+            by_bytes,
+            max_token_len,
+            eos_token_id,
+            stop_token_ids: vocabulary.stop_token_ids().to_vec(),
+            vocab_size: vocabulary.vocab_size(),
+            vocabulary_fingerprint: vocabulary.fingerprint(),
+        }
+    }
+
+    /// Returns the non-empty tokens starting with `byte`.
+    pub(crate) fn starting_with(&self, byte: u8) -> impl Iterator<Item = &'a Token> + '_ {
+        self.by_first_byte[byte as usize]
+            .iter()
+            .map(|&(token, _)| token)
+    }
+
+    /// Returns all the tokens, in the order of the vocabulary.
+    pub(crate) fn tokens(&self) -> impl Iterator<Item = &'a Token> + '_ {
+        self.tokens.iter().map(|&(token, _)| token)
+    }
+
+    /// Returns the tokens starting with a first byte of the literals of `prefilter`, and the
+    /// empty ones, in the order of the vocabulary like all the tokens.
+    fn prefiltered<'t>(
+        &'t self,
+        prefilter: &'t LiteralPrefilter,
+    ) -> impl Iterator<Item = &'t (&'a Token, &'a Vec<TokenId>)> {
+        self.tokens.iter().filter(|(token, _)| {
+            token
+                .first()
+                .is_none_or(|byte| prefilter.first_bytes().binary_search(byte).is_ok())
+        })
+    }
+}
+
+/// Builds an [`Index`] with options, which [`Index::new`] doesn't offer.
+///
+/// ## Example
+///
+/// ```rust
+/// # use outlines_core::Error;
+/// use std::ops::ControlFlow;
+///
+/// use outlines_core::index::IndexBuilder;
+/// use outlines_core::prelude::*;
+///
+/// # fn main() -> Result<(), Error> {
+/// let mut vocabulary = Vocabulary::new(3);
+/// for (token, token_id) in [("1", 1), ("2", 2)] {
+///     vocabulary.try_insert(token, token_id)?;
+/// }
+///
+/// let index = IndexBuilder::new()
+///     .with_progress_interval(1)
+///     .on_progress(|progress| {
+///         println!("{} states explored", progress.states_explored);
+///         ControlFlow::Continue(())
+///     })
+///     .build("[12]{3}", &vocabulary)?;
+/// assert_eq!(index.final_states().len(), 1);
+/// # Ok(())
+/// # }
+/// ```
+pub struct IndexBuilder<'a> {
+    progress: Option<ProgressCallback<'a>>,
+    progress_interval: usize,
+    limits: ResourceLimits,
+}
+
+impl Default for IndexBuilder<'_> {
+    fn default() -> Self {
+        Self {
+            progress: None,
+            progress_interval: PROGRESS_INTERVAL_STATES,
+            limits: ResourceLimits::default(),
+        }
+    }
+}
+
+impl std::fmt::Debug for IndexBuilder<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("IndexBuilder")
+            .field("progress", &self.progress.is_some())
+            .field("progress_interval", &self.progress_interval)
+            .field("limits", &self.limits)
+            .finish()
+    }
+}
+
+impl<'a> IndexBuilder<'a> {
+    /// Creates a builder, which builds the same index as [`Index::new`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the callback invoked every `progress_interval` explored states and once the
+    /// exploration is done.
+    ///
+    /// Returning [`ControlFlow::Break`] cancels the construction, which then fails with
+    /// [`Error::IndexBuildCancelled`].
+    pub fn on_progress(
+        self,
+        callback: impl FnMut(BuildProgress) -> ControlFlow<()> + Send + 'a,
+    ) -> Self {
+        Self {
+            progress: Some(Box::new(callback)),
+            ..self
+        }
+    }
+
+    /// Sets the number of explored states between two progress reports, at least one.
+    pub fn with_progress_interval(self, states: usize) -> Self {
+        Self {
+            progress_interval: states.max(1),
+            ..self
+        }
+    }
+
+    /// Sets the limits of the resources of the construction, which then fails with
+    /// [`Error::ResourceLimitExceeded`] once one is exceeded.
+    pub fn with_limits(self, limits: ResourceLimits) -> Self {
+        Self { limits, ..self }
+    }
+
+    /// Builds an `Index` from regular expression and vocabulary tokens.
+    pub fn build(self, regex: &str, vocabulary: &Vocabulary) -> Result<Index> {
+        let table = TokenTable::new(vocabulary);
+        let Some(mut callback) = self.progress else {
+            return Index::with_token_table(regex, &table, &self.limits);
+        };
+        let start = Instant::now();
+        Index::explore(
+            regex,
+            &table,
+            &self.limits,
+            |states_explored, frontier_size| {
+                if states_explored % self.progress_interval != 0 && frontier_size > 0 {
+                    return Ok(());
+                }
+                let progress = BuildProgress {
+                    states_explored,
+                    frontier_size,
+                    elapsed: start.elapsed(),
+                };
+                match callback(progress) {
+                    ControlFlow::Continue(()) => Ok(()),
+                    ControlFlow::Break(()) => Err(Error::IndexBuildCancelled { states_explored }),
+                }
+            },
+        )
+    }
+}
+
+/// `Index` efficiently maps vocabulary tokens to state transitions.
+///
+/// `Display` and `Debug` summarize the index, [`Index::dump_full`] lists all its transitions.
+#[derive(Clone, PartialEq, Encode, Decode)]
+pub struct Index {
+    /// The ID of the initial state in the automaton, processing begins from this state.
+    initial_state: StateId,
+    /// A collection of states considered as terminal states.
+    final_states: HashSet<StateId>,
+    /// A mapping of state transitions, defined by tokens ids and their corresponding state changes.
+    ///
+    /// ### Example
+    /// ```ignore
+    /// transitions = {
+    ///    1: {10: 2, 15: 3},
+    ///    2: {20: 4, 25: 3},
+    ///    3: {30: 4},
+    ///    4: {40: 4},
+    /// }
+    ///  +--------------------------------------+
+    ///  |               State 1                |
+    ///  |            Initial State             |
+    ///  +--------------------------------------+
+    ///              |                     |
+    ///              +                     |
+    ///         Token ID 10                |
+    ///  +-----------------------+         |
+    ///  |        State 2        |         |
+    ///  +-----------------------+         |
+    ///       |             |              |
+    ///       |             +              +
+    ///       |        Token ID 25    Token ID 15
+    ///       |        +------------------------+
+    ///       |        |        State 3         |
+    ///       |        +------------------------+
+    ///       |                            |
+    ///       +                            +
+    ///  Token ID 20                  Token ID 30
+    ///  +--------------------------------------+
+    ///  |               State 4                |
+    ///  |             Final state              |
+    ///  +--------------------------------------+
+    /// ```
+    transitions: HashMap<StateId, HashMap<TokenId, StateId>>,
+    /// The token ID reserved for the "end-of-sequence" token.
+    eos_token_id: TokenId,
+    /// Token IDs of the stop tokens of the vocabulary, allowed in final states like the
+    /// "end-of-sequence" token.
+    stop_token_ids: Vec<TokenId>,
+    /// The size of the vocabulary used to build the index.
+    vocab_size: usize,
+    /// Stable hash of the index content, see [`Index::fingerprint`].
+    fingerprint: u64,
+    /// What the index was built from, see [`Index::verify_vocabulary`].
+    provenance: Option<Provenance>,
+    /// Counter-augmented transitions of a bounded repetition, which replace `transitions`.
+    counted: Option<CountedAutomaton>,
+    /// Byte classes of the automaton of the regular expression.
+    byte_classes: ByteClasses,
+    /// Explicit transitions of `counted`, built on demand by [`Index::transitions`].
+    materialized: Materialized,
+    /// Distances of the states to final states, built on demand by [`Index::distance_to_final`].
+    distances: Materialized<HashMap<StateId, usize>>,
+}
+/// The `Index` structure is designed to efficiently map tokens from a given vocabulary
+/// to state transitions within a finite-state automaton.
+///
+/// ## Usage:
+/// The `Index` is typically constructed by combining a vocabulary and regular expressions.
+/// Once built, it can be used to efficiently evaluate token sequences or to validate input data.
+///
+/// ## Example:
+/// ```rust
+/// use outlines_core::prelude::*;
+///
+/// # fn run() -> Result<(), outlines_core::Error> {
+/// let regex = "0|[1-9][0-9]*";
+/// let vocabulary = Vocabulary::from_pretrained("openai-community/gpt2", None)?;
+/// let index = Index::new(regex, &vocabulary)?;
+///
+/// let initial_state = index.initial_state();
+/// println!("Initial state is {}", initial_state);
+/// println!("Is initial state a final state? {}", index.is_final_state(&initial_state));
+///
+/// let allowed_tokens = index.allowed_tokens(&initial_state).expect("Some allowed tokens");
+/// println!("Allowed tokens at initial state are {:?}", allowed_tokens);
+///
+/// let token_id = allowed_tokens.first().expect("First token");
+/// println!("Next state for the token_id {} is {:?}", token_id, index.next_state(&initial_state, token_id));
+///
+/// println!("Final states are {:?}", index.final_states());
+/// println!("Index has exactly {} transitions", index.transitions().len());
+/// # Ok(())
+/// # }
+///
+/// ```
+///
+/// ## Performance:
+/// - **Complexity**:
+///   The `Index` can accommodate large vocabularies and complex regular expressions.
+///   However, its size may grow significantly with the complexity of the input.
+/// - **Construction Cost**:
+///   Building the `Index` involves processing the vocabulary and regular expressions,
+///   which may require a considerable amount of time and computational resources.
+/// - **Bounded repetitions**:
+///   Repetitions with a large upper bound, e.g. `"[a-z]{0,4096}"` or `.{1,500}`, are served by
+///   a counter of started repetitions over the tokens walked from each state of the repeated
+///   pattern, instead of explicit transitions. Then [`Index::transitions`] builds them on the
+///   first call.
+/// - **Binary formats**:
+///   Patterns may match bytes, which aren't valid UTF-8, e.g. `(?-u:\xFF)`, to be walked by
+///   byte-fallback tokens, see [`cbor`](crate::cbor).
+impl Index {
+    /// Builds an `Index` from regular expression and vocabulary tokens.
+    pub fn new(regex: &str, vocabulary: &Vocabulary) -> Result<Self> {
+        Self::with_limits(regex, vocabulary, &ResourceLimits::default())
+    }
+
+    /// Builds an `Index` from regular expression and vocabulary tokens within the limits of
+    /// resources, failing with [`Error::ResourceLimitExceeded`] once one is exceeded.
+    pub fn with_limits(
+        regex: &str,
+        vocabulary: &Vocabulary,
+        limits: &ResourceLimits,
+    ) -> Result<Self> {
+        Self::with_token_table(regex, &TokenTable::new(vocabulary), limits)
+    }
+
+    /// Builds an `Index` from regular expression and vocabulary tokens prepared in advance,
+    /// which allows to share the preparation between many regular expressions.
+    pub(crate) fn with_token_table(
+        regex: &str,
+        table: &TokenTable,
+        limits: &ResourceLimits,
+    ) -> Result<Self> {
+        Self::explore(regex, table, limits, |_, _| Ok(()))
+    }
+
+    /// Builds an `Index` by exploring the automaton of the regular expression, unless it's
+    /// a bounded repetition, which is counted.
+    fn explore(
+        regex: &str,
+        table: &TokenTable,
+        limits: &ResourceLimits,
+        progress: impl FnMut(usize, usize) -> Result<()>,
+    ) -> Result<Self> {
+        #[cfg(feature = "metrics")]
+        let start = Instant::now();
+        let mut usage = ResourceUsage::new(limits);
+        let index = match CountedAutomaton::new(regex, table, &mut usage) {
+            Ok(Some(counted)) => ByteClasses::from_regex(regex)
+                .map(|classes| Self::from_counted(counted, classes, table)),
+            Ok(None) => Self::explore_dfa(regex, table, &mut usage, progress),
+            Err(e) => Err(e),
+        }
+        .map(|index| Self {
+            provenance: Some(Provenance {
+                regex: regex.to_string(),
+                vocabulary_fingerprint: table.vocabulary_fingerprint,
+            }),
+            ..index
+        });
+        #[cfg(feature = "metrics")]
+        crate::metrics::record_build(start, &index);
+        index
+    }
+
+    /// Explores the automaton of the regular expression to build an `Index`, `progress` is
+    /// invoked with the number of explored states and the frontier size after each state,
+    /// once the `usage` of resources is checked.
+    fn explore_dfa(
+        regex: &str,
+        table: &TokenTable,
+        usage: &mut ResourceUsage,
+        mut progress: impl FnMut(usize, usize) -> Result<()>,
+    ) -> Result<Self> {
+        let vocab_size = table.vocab_size;
+        let dfa = usage.build_dfa(regex)?;
+        let start_state = match dfa.universal_start_state(Anchored::Yes) {
+            Some(s) => s,
+            None => return Err(Error::DfaHasNoStartState),
+        };
+
+        let mut transitions: HashMap<StateId, HashMap<TokenId, StateId>> = HashMap::default();
+        let mut final_states: HashSet<StateId> = HashSet::default();
+
+        let mut seen: HashSet<AutomataStateId> = HashSet::from_iter([start_state]);
+        let mut next_states: Vec<AutomataStateId> = vec![start_state];
+        let mut is_useful_state_cache: HashMap<AutomataStateId, bool> = HashMap::default();
+
+        let mut forced_byte_cache: HashMap<AutomataStateId, Option<u8>> = HashMap::default();
+        let mut states_explored = 0;
+
+        while let Some(current_state) = next_states.pop() {
+            let mut has_valid_transitions = false;
+
+            if dfa.is_match_state(dfa.next_eoi_state(current_state)) {
+                final_states.insert(StateId::new(current_state.as_u32()));
+                has_valid_transitions = true;
+            }
+
+            // Maximal run of bytes forced from the current state, e.g. a constant key.
+            let literal = forced_literal(
+                &dfa,
+                current_state,
+                table.max_token_len,
+                &mut forced_byte_cache,
+            );
+            // Otherwise, the few literals walked from the state, if any, e.g. the next keys.
+            let prefilter = if literal.is_empty() {
+                LiteralPrefilter::new(&dfa, current_state, table.max_token_len)
+            } else {
+                None
+            };
+            // A literal as long as the longest token contains every token that may follow, so
+            // only the tokens spelling its prefixes are looked up, e.g. along a long constant.
+            let hoisted: Vec<(&Token, &Vec<TokenId>)> = if literal.len() >= table.max_token_len {
+                let bytes: Vec<u8> = literal.iter().map(|&(byte, _)| byte).collect();
+                (1..=bytes.len())
+                    .filter_map(|len| table.by_bytes.get(&bytes[..len]).copied())
+                    .collect()
+            } else {
+                Vec::new()
+            };
+            let candidates: Box<dyn Iterator<Item = &(&Token, &Vec<TokenId>)>> =
+                match (literal.first(), &prefilter) {
+                    (Some(_), _) if literal.len() >= table.max_token_len => {
+                        Box::new(hoisted.iter().chain(table.by_first_byte[256].iter()))
+                    }
+                    (Some(&(byte, _)), _) => Box::new(
+                        table.by_first_byte[byte as usize]
+                            .iter()
+                            .chain(table.by_first_byte[256].iter()),
+                    ),
+                    (None, Some(prefilter)) => Box::new(table.prefiltered(prefilter)),
+                    (None, None) => Box::new(table.tokens.iter()),
+                };
+
+            'token_loop: for &(token, ids) in candidates {
+                if prefilter
+                    .as_ref()
+                    .is_some_and(|prefilter| !prefilter.may_follow(token))
+                {
+                    continue 'token_loop;
+                }
+                // Walk along the forced literal by comparing bytes, then the DFA for the rest.
+                let mut next_state = current_state;
+                let walked = token
+                    .iter()
+                    .zip(literal.iter())
+                    .take_while(|(byte, (forced, _))| *byte == forced)
+                    .count();
+                if walked > 0 {
+                    if walked < token.len() && walked < literal.len() {
+                        continue 'token_loop;
+                    }
+                    next_state = literal[walked - 1].1;
+                }
+                for transition_byte in &token[walked..] {
+                    next_state = dfa.next_state(next_state, *transition_byte);
+                    if dfa.is_dead_state(next_state) || dfa.is_quit_state(next_state) {
+                        continue 'token_loop;
+                    }
+                }
+
+                // Determine if the `next_state` is a useful state to keep in the index.
+                // We use a cache to avoid re-evaluating the same state multiple times.
+                let is_useful_state =
+                    *is_useful_state_cache.entry(next_state).or_insert_with(|| {
+                        let check_is_intermediate_state = || {
+                            dfa.byte_classes().representatives(..).any(|repr| {
+                                if let Some(byte) = repr.as_u8() {
+                                    let s = dfa.next_state(next_state, byte);
+                                    !dfa.is_dead_state(s) && !dfa.is_quit_state(s)
+                                } else {
+                                    false
+                                }
+                            })
+                        };
+                        let is_full_match_state =
+                            dfa.is_match_state(dfa.next_eoi_state(next_state));
+
+                        // A state is useful if it is a match state OR it can transition further.
+                        // Performance: We use short-circuiting here. `check_is_intermediate_state()` is
+                        // computationally expensive but is ONLY executed if `is_full_match_state` is false.
+                        is_full_match_state || check_is_intermediate_state()
+                    });
+
+                if is_useful_state {
+                    has_valid_transitions = true;
+                    for token_id in ids {
+                        transitions
+                            .entry(StateId::new(current_state.as_u32()))
+                            .or_default()
+                            .insert(*token_id, StateId::new(next_state.as_u32()));
+                    }
+                    usage.add_tokens(ids.len())?;
+                    if !seen.contains(&next_state) {
+                        seen.insert(next_state);
+                        next_states.push(next_state);
+                    }
+                }
+            }
+
+            // If the current state has no valid transitions and is not a match state,
+            // it means the vocabulary is incompatible with the regex.
+            if !has_valid_transitions && !dfa.is_match_state(current_state) {
+                let mut valid_characters = Vec::new();
+                for byte in 0..=255u8 {
+                    let test_state = dfa.next_state(current_state, byte);
+                    if !dfa.is_dead_state(test_state) && !dfa.is_quit_state(test_state) {
+                        if byte.is_ascii() {
+                            valid_characters.push(char::from(byte).to_string());
+                        } else {
+                            valid_characters.push(format!("\\x{:02x}", byte));
+                        }
+                    }
+                }
+
+                return Err(Error::IncompatibleVocabulary {
+                    regex: regex.to_string(),
+                    error_state: StateId::new(current_state.as_u32()),
+                    missing_tokens: valid_characters,
+                });
+            }
+
+            states_explored += 1;
+            usage.check_states(seen.len())?;
+            progress(states_explored, next_states.len())?;
+        }
+
+        // Populate `transitions` with mappings from `final_states` to `eos_token_id`
+        // and to the stop tokens.
+        for &final_state in &final_states {
+            let map = transitions.entry(final_state).or_default();
+            for &token_id in iter::once(&table.eos_token_id).chain(&table.stop_token_ids) {
+                map.insert(token_id, final_state);
+            }
+        }
+
+        Ok(Self::from_parts(
+            StateId::new(start_state.as_u32()),
+            final_states,
+            transitions,
+            table.eos_token_id,
+            table.stop_token_ids.clone(),
+            vocab_size,
+            ByteClasses::from_automaton(dfa.byte_classes()),
+        ))
+    }
+
+    /// Assembles an index from its parts, computing its fingerprint.
+    pub(crate) fn from_parts(
+        initial_state: StateId,
+        final_states: HashSet<StateId>,
+        transitions: HashMap<StateId, HashMap<TokenId, StateId>>,
+        eos_token_id: TokenId,
+        stop_token_ids: Vec<TokenId>,
+        vocab_size: usize,
+        byte_classes: ByteClasses,
+    ) -> Self {
+        let mut index = Self {
+            initial_state,
+            final_states,
+            transitions,
+            eos_token_id,
+            stop_token_ids,
+            vocab_size,
+            fingerprint: 0,
+            provenance: None,
+            counted: None,
+            byte_classes,
+            materialized: Materialized::default(),
+            distances: Materialized::default(),
+        };
+        index.fingerprint = index.compute_fingerprint();
+        index
+    }
+
+    /// Assembles an index of a counted repetition, computing its fingerprint.
+    fn from_counted(
+        counted: CountedAutomaton,
+        byte_classes: ByteClasses,
+        table: &TokenTable,
+    ) -> Self {
+        let mut index = Self {
+            initial_state: counted.initial_state(),
+            final_states: counted.final_states(),
+            transitions: HashMap::default(),
+            eos_token_id: table.eos_token_id,
+            stop_token_ids: table.stop_token_ids.clone(),
+            vocab_size: table.vocab_size,
+            fingerprint: 0,
+            provenance: None,
+            counted: Some(counted),
+            byte_classes,
+            materialized: Materialized::default(),
+            distances: Materialized::default(),
+        };
+        index.fingerprint = index.compute_fingerprint();
+        index
+    }
+
+    /// Hashes the content of the index in a deterministic order with 64-bit FNV-1a,
+    /// so that the result is stable across processes and platforms.
+    fn compute_fingerprint(&self) -> u64 {
+        let mut hash: u64 = 0xcbf29ce484222325;
+        let mut write = |value: u64| {
+            for byte in value.to_le_bytes() {
+                hash ^= byte as u64;
+                hash = hash.wrapping_mul(0x100000001b3);
+            }
+        };
+        write(self.initial_state.as_u32() as u64);
+        write(self.eos_token_id.as_u32() as u64);
+        write(self.vocab_size as u64);
+        // Indexes without stop tokens keep the fingerprint of the previous versions.
+        for token_id in &self.stop_token_ids {
+            write(token_id.as_u32() as u64);
+        }
+
+        let mut final_states: Vec<&StateId> = self.final_states.iter().collect();
+        final_states.sort_unstable();
+        write(final_states.len() as u64);
+        for state in final_states {
+            write(state.as_u32() as u64);
+        }
+
+        if let Some(counted) = &self.counted {
+            counted.hash_into(&mut write);
+            return hash;
+        }
+
+        let mut states: Vec<&StateId> = self.transitions.keys().collect();
+        states.sort_unstable();
+        write(states.len() as u64);
+        for state in states {
+            let mut map: Vec<(&TokenId, &StateId)> = self.transitions[state].iter().collect();
+            map.sort_unstable();
+            write(state.as_u32() as u64);
+            write(map.len() as u64);
+            for (token_id, next_state) in map {
+                write(token_id.as_u32() as u64);
+                write(next_state.as_u32() as u64);
+            }
+        }
+        hash
+    }
+
+    /// Returns the ID of the initial state in the automaton.
+    pub fn initial_state(&self) -> StateId {
+        self.initial_state
+    }
+
+    /// Returns set of final states.
+    pub fn final_states(&self) -> &HashSet<StateId> {
+        &self.final_states
+    }
+
+    /// Returns state transitions map of tokens ids and their corresponding transition states.
+    ///
+    /// Transitions of counted repetitions are built on the first call.
+    pub fn transitions(&self) -> &HashMap<StateId, HashMap<TokenId, StateId>> {
+        match &self.counted {
+            Some(counted) => self.materialized.0.get_or_init(|| counted.transitions()),
+            None => &self.transitions,
+        }
+    }
+
+    /// Returns the number of states with transitions, without building the transitions of
+    /// counted repetitions.
+    pub fn num_states(&self) -> usize {
+        self.count_transitions().0
+    }
+
+    /// Returns the number of transitions, without building the transitions of counted
+    /// repetitions.
+    pub fn num_transitions(&self) -> usize {
+        self.count_transitions().1
+    }
+
+    /// Checks if state is in final states set or not.
+    pub fn is_final_state(&self, state: &StateId) -> bool {
+        self.final_states.contains(state)
+    }
+
+    /// Checks if the generation can finish in the state, i.e. the end-of-sequence token is
+    /// allowed. Same as [`Index::is_final_state`].
+    pub fn is_accepting(&self, state: &StateId) -> bool {
+        self.is_final_state(state)
+    }
+
+    /// Checks if the generation must finish in the state, i.e. the end-of-sequence token and
+    /// the stop tokens are the only allowed tokens.
+    pub fn is_dead_end_without_eos(&self, state: &StateId) -> bool {
+        self.is_accepting(state)
+            && self
+                .allowed_tokens_iter(state)
+                .is_some_and(|mut tokens| tokens.all(|token_id| self.is_stop_token(token_id)))
+    }
+
+    /// Returns the ID of the end-of-sequence token.
+    pub fn eos_token_id(&self) -> TokenId {
+        self.eos_token_id
+    }
+
+    /// Returns the IDs of the stop tokens, which are allowed in final states like the
+    /// end-of-sequence token.
+    pub fn stop_token_ids(&self) -> &[TokenId] {
+        &self.stop_token_ids
+    }
+
+    /// Checks if the token ends the generation, i.e. it's the end-of-sequence token or
+    /// a stop token.
+    pub fn is_stop_token(&self, token_id: &TokenId) -> bool {
+        token_id == &self.eos_token_id || self.stop_token_ids.contains(token_id)
+    }
+
+    /// Lists allowed tokens for a give state ID or `None` if it is not found in `Index`.
+    pub fn allowed_tokens(&self, state: &StateId) -> Option<Vec<TokenId>> {
+        self.allowed_tokens_iter(state)
+            .map(|tokens| tokens.cloned().collect())
+    }
+
+    pub fn allowed_tokens_iter(&self, state: &StateId) -> Option<impl Iterator<Item = &TokenId>> {
+        match &self.counted {
+            Some(counted) => counted.allowed_tokens(*state).map(AllowedTokens::Counted),
+            None => self
+                .transitions
+                .get(state)
+                .map(|map| AllowedTokens::Mapped(map.keys())),
+        }
+    }
+
+    /// Returns the bitmasks of the tokens allowed in `next_state` but not in `prev_state`, and
+    /// of the ones no longer allowed, of [`mask::mask_len`](crate::mask::mask_len) words, or
+    /// `None` if either state isn't found, see [`mask::delta`](crate::mask::delta).
+    pub fn mask_delta(
+        &self,
+        prev_state: &StateId,
+        next_state: &StateId,
+    ) -> Option<(Vec<u32>, Vec<u32>)> {
+        let mask = |state| {
+            let mut words = vec![0; crate::mask::mask_len(self.vocab_size)];
+            crate::mask::set_tokens(&mut words, self.allowed_tokens_iter(state)?);
+            Some(words)
+        };
+        let (prev, next) = (mask(prev_state)?, mask(next_state)?);
+        Some(crate::mask::delta(&prev, &next))
+    }
+
+    /// Returns transition state for a given state and token id or `None` otherwise.
+    pub fn next_state(&self, state: &StateId, token_id: &TokenId) -> Option<StateId> {
+        if self.is_stop_token(token_id) {
+            return None;
+        }
+        if let Some(counted) = &self.counted {
+            return counted.next_state(*state, *token_id);
+        }
+        Some(*self.transitions.get(state)?.get(token_id)?)
+    }
+
+    pub fn vocab_size(&self) -> usize {
+        self.vocab_size
+    }
+
+    /// Returns the byte classes of the automaton the index is built from.
+    ///
+    /// Indexes read with [`Index::read_from`] from the first version of the format put
+    /// every byte into its own class.
+    pub fn byte_classes(&self) -> &ByteClasses {
+        &self.byte_classes
+    }
+
+    /// Returns a stable hash of the index content.
+    ///
+    /// Indexes built from the same regular expression and vocabulary share the fingerprint,
+    /// which could be used to check the compatibility of the index with a persisted state.
+    pub fn fingerprint(&self) -> u64 {
+        self.fingerprint
+    }
+
+    /// Returns what the index was built from, unless it's assembled otherwise, e.g. by
+    /// [`Index::complement`] or read from a stream of a previous version.
+    pub fn provenance(&self) -> Option<&Provenance> {
+        self.provenance.as_ref()
+    }
+
+    /// Checks if the state belongs to the index.
+    pub fn contains_state(&self, state: &StateId) -> bool {
+        if let Some(counted) = &self.counted {
+            return counted.contains_state(*state);
+        }
+        *state == self.initial_state
+            || self.transitions.contains_key(state)
+            || self.final_states.contains(state)
+    }
+
+    /// Returns the minimal number of tokens leading from the state to a final state, without
+    /// the end-of-sequence token, or `None` if no final state can be reached.
+    ///
+    /// Distances of all states are computed on the first call.
+    pub fn distance_to_final(&self, state: &StateId) -> Option<usize> {
+        let distances = self.distances.0.get_or_init(|| {
+            // Successors of the states reachable from the initial state, or final ones, without
+            // repetitions.
+            let mut next_states: Vec<StateId> = iter::once(self.initial_state)
+                .chain(self.final_states.iter().copied())
+                .collect();
+            let mut edges: HashMap<StateId, Vec<((), StateId)>> = next_states
+                .iter()
+                .map(|&state| (state, Vec::new()))
+                .collect();
+            while let Some(state) = next_states.pop() {
+                let mut successors: HashSet<StateId> = HashSet::default();
+                for token_id in self.allowed_tokens_iter(&state).into_iter().flatten() {
+                    if let Some(next) = self.next_state(&state, token_id) {
+                        successors.insert(next);
+                    }
+                }
+                for &next in &successors {
+                    if let Entry::Vacant(entry) = edges.entry(next) {
+                        entry.insert(Vec::new());
+                        next_states.push(next);
+                    }
+                }
+                edges.insert(
+                    state,
+                    successors.into_iter().map(|next| ((), next)).collect(),
+                );
+            }
+            crate::regex::distances_to_match(&edges, |state| self.is_final_state(&state))
+        });
+        distances.get(state).copied()
+    }
+
+    /// Returns the state reached from the initial state by the text, e.g. a partially generated
+    /// JSON value of a previous call, to resume with
+    /// [`Guide::from_state`](crate::guide::Guide::from_state), or `None` if the text can't be
+    /// generated with the tokens of the vocabulary.
+    ///
+    /// All the ways to split the text into tokens of the vocabulary, which the index was built
+    /// with, are walked, since the text may be tokenized differently than it was generated.
+    pub fn state_for_text(
+        &self,
+        vocabulary: &Vocabulary,
+        text: impl AsRef<[u8]>,
+    ) -> Option<StateId> {
+        let text = text.as_ref();
+        let max_token_len = vocabulary.tokens().keys().map(Vec::len).max().unwrap_or(0);
+        // States reached by the tokens ending at each position of the text.
+        let mut reached: Vec<HashSet<StateId>> = vec![HashSet::default(); text.len() + 1];
+        reached[0].insert(self.initial_state);
+        for start in 0..text.len() {
+            if reached[start].is_empty() {
+                continue;
+            }
+            let states: Vec<StateId> = reached[start].iter().copied().collect();
+            for end in start + 1..=text.len().min(start + max_token_len) {
+                let Some(token_ids) = vocabulary.token_ids(&text[start..end]) else {
+                    continue;
+                };
+                for state in &states {
+                    for token_id in token_ids {
+                        if let Some(next) = self.next_state(state, token_id) {
+                            reached[end].insert(next);
+                        }
+                    }
+                }
+            }
+        }
+        // Splits of the same text lead to the same state of the automaton.
+        reached[text.len()].iter().min().copied()
+    }
+
+    /// Enumerates all token sequences accepted by the index, of at most `max_len` tokens.
+    ///
+    /// Sequences are produced lazily in depth-first order of ascending token ids, without
+    /// the end-of-sequence token. Since their number grows exponentially with `max_len`,
+    /// it's meant for small languages, e.g. in tests.
+    pub fn enumerate_strings(&self, max_len: usize) -> impl Iterator<Item = Vec<TokenId>> + '_ {
+        let edges: HashMap<StateId, Vec<(TokenId, StateId)>> = self
+            .transitions()
+            .iter()
+            .map(|(&state, map)| {
+                let out = map
+                    .keys()
+                    .filter_map(|token_id| Some((*token_id, self.next_state(&state, token_id)?)))
+                    .collect();
+                (state, out)
+            })
+            .collect();
+        let distances =
+            crate::regex::distances_to_match(&edges, |state| self.is_final_state(&state));
+        TokenSequences::new(self, edges, distances, max_len)
+    }
+
+    /// Builds an index of the token sequences of at most `max_tokens` tokens, whose text
+    /// isn't accepted by this index, e.g. anything except the strings of a pattern, to be
+    /// combined with other constraints in negative-constraint workflows.
+    ///
+    /// The alphabet is the tokens of `vocabulary`, which the index was built from, except the
+    /// end-of-sequence and stop tokens. Since splits of the same text lead to the same state,
+    /// a text is rejected whatever its tokenization. The complement copies the states of the
+    /// index once per length, and allows all the tokens once the text leaves the index, so
+    /// its size grows with `max_tokens` times the vocabulary size.
+    ///
+    /// Fails with [`Error::EmptyComplement`] if the index accepts every such sequence.
+    pub fn complement(&self, vocabulary: &Vocabulary, max_tokens: usize) -> Result<Index> {
+        self.complement_with_limits(vocabulary, max_tokens, &ResourceLimits::default())
+    }
+
+    /// Builds the [`complement`](Self::complement) of the index within the limits of
+    /// resources, failing with [`Error::ResourceLimitExceeded`] once one is exceeded.
+    pub fn complement_with_limits(
+        &self,
+        vocabulary: &Vocabulary,
+        max_tokens: usize,
+        limits: &ResourceLimits,
+    ) -> Result<Index> {
+        let mut usage = ResourceUsage::new(limits);
+        let mut token_ids: Vec<TokenId> = vocabulary
+            .tokens()
+            .values()
+            .flatten()
+            .filter(|&token_id| *token_id != self.eos_token_id && !self.is_stop_token(token_id))
+            .copied()
+            .collect();
+        token_ids.sort_unstable();
+
+        // States of the product with the counter of tokens, by number of tokens. The state of
+        // the index is `None` once the text left it, since no continuation brings it back.
+        let mut ids: HashMap<(Option<StateId>, usize), StateId> = HashMap::default();
+        ids.insert((Some(self.initial_state), 0), StateId::new(0));
+        let mut layers: Vec<Vec<Option<StateId>>> = vec![vec![Some(self.initial_state)]];
+        let mut transitions: HashMap<StateId, HashMap<TokenId, StateId>> = HashMap::default();
+        for len in 0..max_tokens {
+            let mut next_layer = Vec::new();
+            for &state in &layers[len] {
+                let map = transitions.entry(ids[&(state, len)]).or_default();
+                for token_id in &token_ids {
+                    let next = state.and_then(|state| self.next_state(&state, token_id));
+                    let new_id = StateId::new(ids.len() as u32);
+                    let next_id = *ids.entry((next, len + 1)).or_insert_with(|| {
+                        next_layer.push(next);
+                        new_id
+                    });
+                    map.insert(*token_id, next_id);
+                }
+                usage.add_tokens(token_ids.len())?;
+                usage.check_states(ids.len())?;
+            }
+            layers.push(next_layer);
+        }
+
+        // Only the states reaching a rejected text within the bound are kept, walking the
+        // layers backwards since each transition adds a token.
+        let is_rejected = |state: &Option<StateId>| !state.is_some_and(|s| self.is_final_state(&s));
+        let mut final_states: HashSet<StateId> = HashSet::default();
+        let mut useful: HashSet<StateId> = HashSet::default();
+        for (len, layer) in layers.iter().enumerate().rev() {
+            for state in layer {
+                let id = ids[&(*state, len)];
+                if is_rejected(state) {
+                    final_states.insert(id);
+                    useful.insert(id);
+                } else if transitions
+                    .get(&id)
+                    .is_some_and(|map| map.values().any(|next| useful.contains(next)))
+                {
+                    useful.insert(id);
+                }
+            }
+        }
+        if !useful.contains(&StateId::new(0)) {
+            return Err(Error::EmptyComplement { max_tokens });
+        }
+        transitions.retain(|state, map| {
+            map.retain(|_, next| useful.contains(next));
+            useful.contains(state) && !map.is_empty()
+        });
+        for &final_state in &final_states {
+            let map = transitions.entry(final_state).or_default();
+            for &token_id in iter::once(&self.eos_token_id).chain(&self.stop_token_ids) {
+                map.insert(token_id, final_state);
+            }
+        }
+
+        Ok(Self::from_parts(
+            StateId::new(0),
+            final_states,
+            transitions,
+            self.eos_token_id,
+            self.stop_token_ids.clone(),
+            self.vocab_size,
+            self.byte_classes.clone(),
+        ))
+    }
+
+    /// Formats all the transitions of the index, which may be huge, unlike `Display`.
+    pub fn dump_full(&self) -> String {
+        let mut dump = String::from("Index object with transitions:\n");
+        for (state_id, token_ids) in self.transitions().iter() {
+            dump.push_str(&format!("{:?} -> {:#?}\n", state_id, token_ids));
+        }
+        dump
+    }
+
+    /// Counts the states with transitions and the transitions, walking the counted
+    /// repetitions unless their transitions are materialized.
+    fn count_transitions(&self) -> (usize, usize) {
+        match self.explicit_transitions() {
+            Some(transitions) => (
+                transitions.len(),
+                transitions.values().map(HashMap::len).sum(),
+            ),
+            None => self
+                .counted
+                .as_ref()
+                .map_or((0, 0), CountedAutomaton::count_transitions),
+        }
+    }
+
+    /// Returns the explicit transitions, unless they're counted and not materialized yet.
+    fn explicit_transitions(&self) -> Option<&HashMap<StateId, HashMap<TokenId, StateId>>> {
+        match &self.counted {
+            Some(_) => self.materialized.0.get(),
+            None => Some(&self.transitions),
+        }
+    }
+
+    /// Returns an estimate of the memory held by the index, in bytes, by its parts.
+    pub fn footprint(&self) -> MemoryBreakdown {
+        let transitions =
+            table_bytes::<StateId, HashMap<TokenId, StateId>>(self.transitions.capacity())
+                + self
+                    .transitions
+                    .values()
+                    .map(|map| table_bytes::<TokenId, StateId>(map.capacity()))
+                    .sum::<usize>();
+        let materialized = self.materialized.0.get().map_or(0, |transitions| {
+            table_bytes::<StateId, HashMap<TokenId, StateId>>(transitions.capacity())
+                + transitions
+                    .values()
+                    .map(|map| table_bytes::<TokenId, StateId>(map.capacity()))
+                    .sum::<usize>()
+        });
+        let distances = self.distances.0.get().map_or(0, |distances| {
+            table_bytes::<StateId, usize>(distances.capacity())
+        });
+        MemoryBreakdown {
+            base: std::mem::size_of::<Self>(),
+            final_states: table_bytes::<StateId, ()>(self.final_states.capacity()),
+            transitions,
+            counted: self
+                .counted
+                .as_ref()
+                .map_or(0, CountedAutomaton::memory_usage),
+            stop_tokens: self.stop_token_ids.capacity() * std::mem::size_of::<TokenId>(),
+            caches: materialized + distances,
+        }
+    }
+
+    /// Serializes the index into `writer` chunk by chunk, without buffering the whole
+    /// encoded index in memory.
+    ///
+    /// `progress` is invoked after each chunk of states is written. Returns the total
+    /// number of bytes written.
+    pub fn write_to<W, F>(&self, mut writer: W, mut progress: F) -> Result<usize>
+    where
+        W: Write,
+        F: FnMut(StreamProgress),
+    {
+        let config = config::standard();
+        let header = StreamHeader {
+            version: STREAM_FORMAT_VERSION,
+            initial_state: self.initial_state,
+            final_states: self.final_states.iter().copied().collect(),
+            eos_token_id: self.eos_token_id,
+            vocab_size: self.vocab_size,
+            states_total: self.transitions().len(),
+        };
+        let mut bytes = bincode::encode_into_std_write(&header, &mut writer, config)?;
+        bytes += bincode::encode_into_std_write(&self.byte_classes, &mut writer, config)?;
+        bytes += bincode::encode_into_std_write(&self.stop_token_ids, &mut writer, config)?;
+        bytes += bincode::encode_into_std_write(&self.provenance, &mut writer, config)?;
+
+        let states: Vec<&StateId> = self.transitions().keys().collect();
+        let mut masks: HashMap<Vec<TokenId>, u32> = HashMap::default();
+        let mut states_done = 0;
+        for chunk in states.chunks(STREAM_CHUNK_STATES) {
+            let mut entries = StreamChunk {
+                masks: Vec::new(),
+                states: Vec::with_capacity(chunk.len()),
+            };
+            for &&state in chunk {
+                let mut transitions: Vec<(TokenId, StateId)> = self.transitions()[&state]
+                    .iter()
+                    .map(|(&t, &s)| (t, s))
+                    .collect();
+                transitions.sort_unstable();
+                let (mask, mut next_states): (Vec<TokenId>, Vec<StateId>) =
+                    transitions.into_iter().unzip();
+                if next_states.windows(2).all(|pair| pair[0] == pair[1]) {
+                    next_states.truncate(1);
+                }
+                let number = masks.len() as u32;
+                let number = *masks.entry(mask).or_insert_with_key(|mask| {
+                    entries.masks.push(mask.clone());
+                    number
+                });
+                entries.states.push((state, number, next_states));
+            }
+            bytes += bincode::encode_into_std_write(&entries, &mut writer, config)?;
+            states_done += chunk.len();
+            progress(StreamProgress {
+                states_done,
+                states_total: header.states_total,
+                bytes,
+            });
+        }
+        writer
+            .flush()
+            .map_err(|e| Error::IndexStreamError(e.to_string()))?;
+        Ok(bytes)
+    }
+
+    /// Deserializes an index previously written by [`Index::write_to`] from `reader`.
+    ///
+    /// `progress` is invoked after each chunk of states is read.
+    pub fn read_from<R, F>(mut reader: R, mut progress: F) -> Result<Self>
+    where
+        R: Read,
+        F: FnMut(StreamProgress),
+    {
+        let config = config::standard().with_limit::<STREAM_DECODE_LIMIT>();
+        let mut reader = CountingReader::new(&mut reader);
+        let header: StreamHeader = bincode::decode_from_std_read(&mut reader, config)?;
+        let (byte_classes, stop_token_ids, provenance) = match header.version {
+            STREAM_FORMAT_VERSION => (
+                bincode::decode_from_std_read(&mut reader, config)?,
+                bincode::decode_from_std_read(&mut reader, config)?,
+                bincode::decode_from_std_read(&mut reader, config)?,
+            ),
+            // The provenance was not written yet, and masks were not deduplicated in 3,
+            // see below.
+            4 | 3 => (
+                bincode::decode_from_std_read(&mut reader, config)?,
+                bincode::decode_from_std_read(&mut reader, config)?,
+                None,
+            ),
+            // Stop tokens were not written yet.
+            2 => (
+                bincode::decode_from_std_read(&mut reader, config)?,
+                Vec::new(),
+                None,
+            ),
+            // Byte classes were not written yet.
+            1 => (ByteClasses::singletons(), Vec::new(), None),
+            version => {
+                return Err(Error::IndexStreamError(format!(
+                    "unsupported format version {}, expected {}",
+                    version, STREAM_FORMAT_VERSION
+                )))
+            }
+        };
+
+        let mut transitions: HashMap<StateId, HashMap<TokenId, StateId>> = HashMap::default();
+        transitions.reserve(header.states_total.min(STREAM_RESERVED_STATES));
+        // Masks are deduplicated since the version 4.
+        let mut masks: Vec<Vec<TokenId>> = Vec::new();
+        while transitions.len() < header.states_total {
+            let entries: Vec<(StateId, Vec<(TokenId, StateId)>)> = if header.version < 4 {
+                bincode::decode_from_std_read(&mut reader, config)?
+            } else {
+                let chunk: StreamChunk = bincode::decode_from_std_read(&mut reader, config)?;
+                masks.extend(chunk.masks);
+                chunk
+                    .states
+                    .into_iter()
+                    .map(|(state, number, next_states)| {
+                        let mask = masks
+                            .get(number as usize)
+                            .filter(|mask| {
+                                mask.len() == next_states.len()
+                                    || (!mask.is_empty() && next_states.len() == 1)
+                            })
+                            .ok_or_else(|| {
+                                Error::IndexStreamError(format!(
+                                    "invalid mask {number} of state {state}"
+                                ))
+                            })?;
+                        let next_states = next_states.iter().copied().cycle();
+                        Ok((state, mask.iter().copied().zip(next_states).collect()))
+                    })
+                    .collect::<Result<_>>()?
+            };
+            if entries.is_empty() {
+                return Err(Error::IndexStreamError("empty chunk of states".to_string()));
+            }
+            for (state, map) in entries {
+                transitions.insert(state, map.into_iter().collect());
+            }
+            progress(StreamProgress {
+                states_done: transitions.len(),
+                states_total: header.states_total,
+                bytes: reader.bytes,
+            });
+        }
+
+        let index = Self::from_parts(
+            header.initial_state,
+            header.final_states.into_iter().collect(),
+            transitions,
+            header.eos_token_id,
+            stop_token_ids,
+            header.vocab_size,
+            byte_classes,
+        );
+        Ok(Self {
+            provenance,
+            ..index
+        })
+    }
+}
+
+/// Iterator over the allowed tokens of a state, see [`Index::allowed_tokens_iter`].
+enum AllowedTokens<'a, C> {
+    Mapped(std::collections::hash_map::Keys<'a, TokenId, StateId>),
+    Counted(C),
+}
+
+impl<'a, C: Iterator<Item = &'a TokenId>> Iterator for AllowedTokens<'a, C> {
+    type Item = &'a TokenId;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            Self::Mapped(tokens) => tokens.next(),
+            Self::Counted(tokens) => tokens.next(),
+        }
+    }
+}
+
+/// Depth-first iterator over the token sequences accepted by an index, see
+/// [`Index::enumerate_strings`].
+struct TokenSequences<'a> {
+    index: &'a Index,
+    edges: HashMap<StateId, Vec<(TokenId, StateId)>>,
+    /// Length of the shortest path to a final state, reachable states only.
+    distances: HashMap<StateId, usize>,
+    max_len: usize,
+    /// Transitions to explore at each depth of `path`, with the position of the next one.
+    stack: Vec<(Vec<(TokenId, StateId)>, usize)>,
+    path: Vec<TokenId>,
+    /// The empty sequence, if the initial state is final.
+    empty: Option<Vec<TokenId>>,
+}
+
+impl<'a> TokenSequences<'a> {
+    fn new(
+        index: &'a Index,
+        edges: HashMap<StateId, Vec<(TokenId, StateId)>>,
+        distances: HashMap<StateId, usize>,
+        max_len: usize,
+    ) -> Self {
+        let mut sequences = Self {
+            index,
+            edges,
+            distances,
+            max_len,
+            stack: Vec::new(),
+            path: Vec::new(),
+            empty: index.is_final_state(&index.initial_state()).then(Vec::new),
+        };
+        sequences.push(index.initial_state());
+        sequences
+    }
+
+    /// Pushes the transitions of `state`, which still lead to a final state within the bound.
+    fn push(&mut self, state: StateId) {
+        let remaining = self.max_len - self.path.len();
+        let mut options: Vec<(TokenId, StateId)> = self
+            .edges
+            .get(&state)
+            .into_iter()
+            .flatten()
+            .filter(|(_, next)| self.distances.get(next).is_some_and(|&d| d < remaining))
+            .copied()
+            .collect();
+        options.sort_unstable();
+        self.stack.push((options, 0));
+    }
+}
+
+impl Iterator for TokenSequences<'_> {
+    type Item = Vec<TokenId>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(empty) = self.empty.take() {
+            return Some(empty);
+        }
+        loop {
+            let (options, position) = self.stack.last_mut()?;
+            let Some(&(token_id, next)) = options.get(*position) else {
+                self.stack.pop();
+                self.path.pop();
+                continue;
+            };
+            *position += 1;
+            self.path.push(token_id);
+            self.push(next);
+            if self.index.is_final_state(&next) {
+                return Some(self.path.clone());
+            }
+        }
+    }
+}
+
+/// Collects the maximal run of bytes forced from `state`, i.e. each of the walked states has
+/// exactly one byte leading to a live state, together with the states reached after each byte.
+///
+/// The run is capped by `max_len`, since longer literals can't be covered by a single token.
+fn forced_literal(
+    dfa: &DFA<Vec<u32>>,
+    state: AutomataStateId,
+    max_len: usize,
+    cache: &mut HashMap<AutomataStateId, Option<u8>>,
+) -> Vec<(u8, AutomataStateId)> {
+    let mut literal = Vec::new();
+    let mut current = state;
+    while literal.len() < max_len {
+        let forced = *cache.entry(current).or_insert_with(|| {
+            let mut live_bytes = (0..=255u8).filter(|&byte| {
+                let next = dfa.next_state(current, byte);
+                !dfa.is_dead_state(next) && !dfa.is_quit_state(next)
+            });
+            match (live_bytes.next(), live_bytes.next()) {
+                (Some(byte), None) => Some(byte),
+                _ => None,
+            }
+        });
+        match forced {
+            Some(byte) => {
+                current = dfa.next_state(current, byte);
+                literal.push((byte, current));
+            }
+            None => break,
+        }
+    }
+    literal
+}
+
+/// Reader wrapper keeping track of the number of consumed bytes.
+struct CountingReader<R> {
+    inner: R,
+    bytes: usize,
+}
+
+impl<R> CountingReader<R> {
+    fn new(inner: R) -> Self {
+        Self { inner, bytes: 0 }
+    }
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.bytes += n;
+        Ok(n)
+    }
+}
+
+impl std::fmt::Display for Index {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Some(transitions) = self.explicit_transitions() else {
+            return writeln!(
+                f,
+                "Index object with counted transitions, {} final states and {} bytes",
+                self.final_states.len(),
+                self.footprint().total()
+            );
+        };
+        writeln!(
+            f,
+            "Index object with {} states, {} transitions, {} final states and {} bytes:",
+            transitions.len(),
+            transitions.values().map(HashMap::len).sum::<usize>(),
+            self.final_states.len(),
+            self.footprint().total()
+        )?;
+        let mut states: Vec<_> = transitions.keys().copied().collect();
+        states.sort_unstable();
+        for state_id in states.iter().take(SUMMARY_ENTRIES) {
+            let mut tokens: Vec<_> = transitions[state_id].iter().collect();
+            tokens.sort_unstable();
+            write!(f, "{state_id} -> {{")?;
+            for (i, (token_id, next_state)) in tokens.iter().take(SUMMARY_ENTRIES).enumerate() {
+                let separator = if i > 0 { ", " } else { "" };
+                write!(f, "{separator}{token_id}: {next_state}")?;
+            }
+            if tokens.len() > SUMMARY_ENTRIES {
+                write!(f, ", ... {} more", tokens.len() - SUMMARY_ENTRIES)?;
+            }
+            writeln!(f, "}}")?;
+        }
+        if states.len() > SUMMARY_ENTRIES {
+            writeln!(f, "... {} more states", states.len() - SUMMARY_ENTRIES)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Debug for Index {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Index")
+            .field("initial_state", &self.initial_state)
+            .field("final_states", &self.final_states.len())
+            .field("states", &self.explicit_transitions().map(HashMap::len))
+            .field("eos_token_id", &self.eos_token_id)
+            .field("stop_token_ids", &self.stop_token_ids)
+            .field("vocab_size", &self.vocab_size)
+            .field("counted", &self.counted.is_some())
+            .field("fingerprint", &self.fingerprint)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds the expected transitions of an index from raw identifiers.
+    fn raw_transitions<const N: usize>(
+        transitions: [(u32, Vec<(u32, u32)>); N],
+    ) -> HashMap<StateId, HashMap<TokenId, StateId>> {
+        transitions
+            .into_iter()
+            .map(|(state, map)| {
+                let map = map
+                    .into_iter()
+                    .map(|(token_id, next)| (TokenId::new(token_id), StateId::new(next)));
+                (StateId::new(state), map.collect())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn mask_delta_between_states() {
+        let mut vocabulary = Vocabulary::new(4);
+        for (token, token_id) in [("blah", 0), ("1a", 1), ("2", 2), ("0", 3)] {
+            vocabulary
+                .try_insert(token, token_id)
+                .expect("Insert failed");
+        }
+        let index = Index::new("0|[1-9][0-9]*", &vocabulary).expect("Index failed");
+        let initial_state = index.initial_state();
+        let digit = index
+            .next_state(&initial_state, &TokenId::new(2))
+            .expect("No next state");
+        let zero = index
+            .next_state(&initial_state, &TokenId::new(3))
+            .expect("No next state");
+
+        // The end-of-sequence token is allowed once a digit is generated.
+        assert_eq!(
+            index.mask_delta(&initial_state, &digit),
+            Some((vec![1 << 4], vec![0]))
+        );
+        assert_eq!(
+            index.mask_delta(&digit, &zero),
+            Some((vec![0], vec![1 << 2 | 1 << 3]))
+        );
+        assert_eq!(index.mask_delta(&zero, &zero), Some((vec![0], vec![0])));
+        assert_eq!(index.mask_delta(&zero, &StateId::new(u32::MAX)), None);
+    }
+
+    #[test]
+    fn index_from_regex() {
+        let regex = "0|[1-9][0-9]*";
+        let eos_token_id = TokenId::new(4);
+        let mut vocabulary = Vocabulary::new(eos_token_id);
+        for (token, token_id) in [("blah", 0), ("1a", 1), ("2", 2), ("0", 3)] {
+            vocabulary
+                .try_insert(token, token_id as u32)
+                .expect("Insert failed");
+        }
+        let index = Index::new(regex, &vocabulary).expect("Index failed");
+        let initial_state = index.initial_state();
+        assert_eq!(initial_state, 40);
+        assert_eq!(
+            index.final_states(),
+            &HashSet::from_iter([24, 48, 56].map(StateId::new))
+        );
+        assert!(!index.is_final_state(&initial_state));
+
+        let expected = raw_transitions([
+            (24, vec![(3, 24), (4, 24), (2, 24)]),
+            (48, vec![(4, 48)]),
+            (40, vec![(3, 48), (2, 56)]),
+            (56, vec![(3, 24), (4, 56), (2, 24)]),
+        ]);
+        assert_eq!(index.transitions(), &expected);
+
+        let allowed_tokens = index
+            .allowed_tokens(&initial_state)
+            .expect("No allowed tokens");
+        let token_id = allowed_tokens.first().expect("No first tokens");
+
+        let state = StateId::new(48);
+        assert_eq!(index.next_state(&initial_state, token_id), Some(state));
+        assert!(index.is_final_state(&state));
+
+        assert_eq!(index.next_state(&state, &eos_token_id), None);
+        assert_eq!(index.next_state(&state, token_id), None);
+
+        // After "0" the generation must finish, after "2" it only can.
+        assert!(
+            index.is_accepting(&StateId::new(48))
+                && index.is_dead_end_without_eos(&StateId::new(48))
+        );
+        assert!(
+            index.is_accepting(&StateId::new(56))
+                && !index.is_dead_end_without_eos(&StateId::new(56))
+        );
+        assert!(
+            !index.is_accepting(&StateId::new(40))
+                && !index.is_dead_end_without_eos(&StateId::new(40))
+        );
+    }
+
+    #[test]
+    fn build_with_progress() {
+        let mut vocabulary = Vocabulary::new(3);
+        for (token, token_id) in [("1", 1), ("2", 2)] {
+            vocabulary
+                .try_insert(token, token_id)
+                .expect("Insert failed");
+        }
+        let regex = "[12]{5}";
+
+        let mut reports = Vec::new();
+        let index = IndexBuilder::new()
+            .with_progress_interval(2)
+            .on_progress(|progress| {
+                reports.push(progress);
+                ControlFlow::Continue(())
+            })
+            .build(regex, &vocabulary)
+            .expect("Index failed");
+        assert_eq!(index, Index::new(regex, &vocabulary).expect("Index failed"));
+        let explored: Vec<usize> = reports.iter().map(|p| p.states_explored).collect();
+        assert_eq!(explored, vec![2, 4, 6]);
+        assert_eq!(reports.last().map(|p| p.frontier_size), Some(0));
+
+        let result = IndexBuilder::new()
+            .with_progress_interval(3)
+            .on_progress(|_| ControlFlow::Break(()))
+            .build(regex, &vocabulary);
+        assert!(matches!(
+            result,
+            Err(Error::IndexBuildCancelled { states_explored: 3 })
+        ));
+    }
+
+    #[test]
+    fn build_within_limits() {
+        let mut vocabulary = Vocabulary::new(3);
+        for (token, token_id) in [("1", 1), ("2", 2)] {
+            vocabulary
+                .try_insert(token, token_id)
+                .expect("Insert failed");
+        }
+        let regex = "[12]{5}";
+        let index = Index::new(regex, &vocabulary).expect("Index failed");
+
+        let limits = ResourceLimits::default()
+            .with_max_states(6)
+            .with_max_masks_bytes(index.footprint().transitions)
+            .with_max_build_ms(60_000);
+        let limited = Index::with_limits(regex, &vocabulary, &limits).expect("Index failed");
+        assert_eq!(limited, index);
+
+        for (limits, resource) in [
+            (limits.with_max_states(5), "states"),
+            (limits.with_max_masks_bytes(20), "bytes of masks"),
+            (limits.with_max_build_ms(0), "milliseconds"),
+        ] {
+            let result = IndexBuilder::new()
+                .with_limits(limits)
+                .on_progress(|_| {
+                    std::thread::sleep(Duration::from_millis(1));
+                    ControlFlow::Continue(())
+                })
+                .build(regex, &vocabulary);
+            match result {
+                Err(Error::ResourceLimitExceeded {
+                    resource: exceeded, ..
+                }) => {
+                    assert_eq!(exceeded, resource)
+                }
+                other => unreachable!("Unexpected result {other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn reject_determinization_blowup() {
+        let mut vocabulary = Vocabulary::new(3);
+        for (token, token_id) in [("a", 1), ("b", 2)] {
+            vocabulary
+                .try_insert(token, token_id)
+                .expect("Insert failed");
+        }
+        // The DFA has a state per suffix of 21 characters, the NFA only a few ones.
+        let limits = ResourceLimits::default().with_max_states(100);
+        assert!(matches!(
+            Index::with_limits("(a|b)*a(a|b){20}", &vocabulary, &limits),
+            Err(Error::ResourceLimitExceeded {
+                resource: "bytes of automata",
+                ..
+            })
+        ));
+        assert!(Index::with_limits("(a|b)*a(a|b){2}", &vocabulary, &limits).is_ok());
+    }
+
+    #[test]
+    fn state_for_text() {
+        let mut vocabulary = Vocabulary::new(10);
+        for (token, token_id) in [
+            ("{", 1),
+            ("\"", 2),
+            ("a", 3),
+            ("\":", 4),
+            ("1", 5),
+            ("}", 6),
+            (":", 7),
+        ] {
+            vocabulary
+                .try_insert(token, token_id)
+                .expect("Insert failed");
+        }
+        let index = Index::new(r#"\{"a+":1\}"#, &vocabulary).expect("Index failed");
+
+        let mut state = index.initial_state();
+        for token_id in [1, 2, 3, 3, 4].map(TokenId::new) {
+            state = index.next_state(&state, &token_id).expect("No next state");
+        }
+        // Whether the text is split into `":` or into `"` and `:`, the state is the same.
+        assert_eq!(index.state_for_text(&vocabulary, "{\"aa\":"), Some(state));
+        assert_eq!(
+            index.state_for_text(&vocabulary, ""),
+            Some(index.initial_state())
+        );
+        let finished = index
+            .state_for_text(&vocabulary, "{\"a\":1}")
+            .expect("No state");
+        assert!(index.is_final_state(&finished));
+
+        assert_eq!(index.state_for_text(&vocabulary, "{\"b"), None);
+        assert_eq!(index.state_for_text(&vocabulary, "{\"a\"1"), None);
+    }
+
+    #[test]
+    fn summarized_display() {
+        let mut vocabulary = Vocabulary::new(100);
+        for token_id in 0..20 {
+            vocabulary
+                .try_insert(format!("{token_id:02}"), token_id)
+                .expect("Insert failed");
+        }
+        let index = Index::new("[0-9]{2,40}", &vocabulary).expect("Index failed");
+
+        let summary = index.to_string();
+        assert!(summary.starts_with("Index object with "));
+        assert!(summary.lines().count() <= SUMMARY_ENTRIES + 2);
+        assert!(summary.lines().all(|line| line.len() < 200));
+        assert!(format!("{index:?}").starts_with("Index { initial_state: "));
+
+        let dump = index.dump_full();
+        assert!(dump.starts_with("Index object with transitions:\n"));
+        assert!(dump.len() > summary.len());
+        // Counted transitions, if any, are summarized once materialized by the dump.
+        assert!(index.to_string().contains(" states, "));
+    }
+
+    #[test]
+    fn index_from_regex_initital_in_allowed() {
+        let regex = "`\\n(\\.\\n)?`\\n";
+        let mut vocabulary = Vocabulary::new(104);
+        for (token, token_id) in [("\n", 103), (".", 102), ("`", 101)] {
+            vocabulary
+                .try_insert(token, token_id as u32)
+                .expect("Insert failed");
+        }
+
+        let index = Index::new(regex, &vocabulary).expect("Index failed");
+        let allowed = index
+            .allowed_tokens(&index.initial_state())
+            .expect("No allowed tokens");
+        assert!(allowed.contains(&TokenId::new(101)));
+    }
+
+    #[test]
+    fn index_from_regex_multibyte() {
+        let regex = "😇| [😈-😍][😇-😎]*";
+        let mut vocabulary = Vocabulary::new(8);
+        for (token, token_id) in [(" 😍", 5), ("blah", 0), ("😇", 2), ("😈a", 1), ("😍", 3)]
+        {
+            vocabulary
+                .try_insert(token, token_id as u32)
+                .expect("Insert failed");
+        }
+        for (token, token_id) in [
+            (vec![32, 240, 159, 152, 136], 7),
+            (vec![32, 240, 159, 152, 141], 6),
+            (vec![240, 159, 152, 141], 4),
+        ] {
+            vocabulary
+                .try_insert(token, token_id as u32)
+                .expect("Insert failed");
+        }
+
+        let index = Index::new(regex, &vocabulary).expect("Index failed");
+        assert_eq!(
+            index.final_states(),
+            &HashSet::from_iter([208, 128].map(StateId::new))
+        );
+
+        let expected = raw_transitions([
+            (208, vec![(3, 208), (8, 208), (4, 208), (2, 208)]),
+            (80, vec![(2, 128), (7, 208), (5, 208), (6, 208)]),
+            (128, vec![(8, 128)]),
+        ]);
+        assert_eq!(index.transitions(), &expected);
+    }
+
+    #[test]
+    fn index_incompatible_vocabulary_error() {
+        let regex = "0 1";
+        let mut vocabulary = Vocabulary::new(3);
+        for (token, token_id) in [("0", 0), ("0 ", 1), ("1", 2)] {
+            vocabulary
+                .try_insert(token, token_id as u32)
+                .expect("Insert failed");
+        }
+
+        let result = Index::new(regex, &vocabulary);
+        assert!(result.is_err());
+
+        if let Err(Error::IncompatibleVocabulary {
+            regex: _,
+            missing_tokens,
+            ..
+        }) = result
+        {
+            assert!(missing_tokens.contains(&" ".to_string()));
+        } else {
+            panic!("Expected IncompatibleVocabulary error");
+        }
+    }
+
+    #[test]
+    fn index_incompatible_vocabulary_error_non_ascii() {
+        let regex = "😈😍";
+        let mut vocabulary = Vocabulary::new(3);
+        for (token, token_id) in [("😈", 0), (" ", 1), ("b", 2)] {
+            vocabulary
+                .try_insert(token, token_id as u32)
+                .expect("Insert failed");
+        }
+
+        let result = Index::new(regex, &vocabulary);
+        assert!(result.is_err());
+
+        if let Err(Error::IncompatibleVocabulary {
+            regex: _,
+            missing_tokens,
+            ..
+        }) = result
+        {
+            assert!(missing_tokens.contains(&"\\xf0".to_string()));
+        } else {
+            panic!("Expected IncompatibleVocabulary error");
+        }
+    }
+
+    #[test]
+    fn index_from_regex_with_forced_literal() {
+        let regex = r#"\{"name":"[a-z]+"\}"#;
+        let mut vocabulary = Vocabulary::new(9);
+        for (token, token_id) in [
+            ("{", 0),
+            ("{\"", 1),
+            ("{\"name\":\"", 2),
+            ("name", 3),
+            ("\":\"", 4),
+            ("\"", 5),
+            ("ab", 6),
+            ("\"}", 7),
+            ("{\"nam", 8),
+            ("{\"other", 10),
+            ("", 11),
+        ] {
+            vocabulary
+                .try_insert(token, token_id as u32)
+                .expect("Insert failed");
+        }
+        let index = Index::new(regex, &vocabulary).expect("Index failed");
+
+        let mut allowed = index
+            .allowed_tokens(&index.initial_state())
+            .expect("No allowed tokens");
+        allowed.sort();
+        assert_eq!(allowed, vec![0, 1, 2, 8, 11]);
+
+        let mut state = index.initial_state();
+        for token_id in [1, 3, 4, 6, 6, 7].map(TokenId::new) {
+            state = index.next_state(&state, &token_id).expect("Transit failed");
+        }
+        assert!(index.is_final_state(&state));
+    }
+
+    #[test]
+    fn index_with_literal_prefilter() {
+        let regex =
+            r#"\{[ ]?"alpha":[ ]?[0-9]+,[ ]?"beta":[ ]?[0-9]+,[ ]?"gamma":[ ]?(true|false)[ ]?\}"#;
+        let mut vocabulary = Vocabulary::new(0);
+        let tokens = [
+            "{",
+            "{ ",
+            "{\"",
+            "{ \"al",
+            "\"",
+            " \"",
+            "alpha",
+            "al",
+            "beta",
+            "\":",
+            "\": ",
+            ":",
+            " ",
+            "1",
+            "12",
+            ",",
+            ", \"",
+            ",\"gamma\":",
+            "gam",
+            "ma",
+            "true",
+            "false",
+            "}",
+            " }",
+            "x",
+            "\"delta",
+            "",
+        ];
+        for (token_id, token) in tokens.iter().enumerate() {
+            vocabulary
+                .try_insert(*token, token_id as u32 + 1)
+                .expect("Insert failed");
+        }
+        let index = Index::new(regex, &vocabulary).expect("Index failed");
+
+        // Every token walking from a state of the index to another one is allowed.
+        let dfa = DFA::new(regex).expect("DFA failed");
+        let states: HashSet<StateId> = index
+            .transitions()
+            .keys()
+            .chain(index.final_states())
+            .copied()
+            .collect();
+        for &state in index.transitions().keys() {
+            for (token, ids) in vocabulary.tokens() {
+                let mut next = AutomataStateId::new(state.as_usize()).expect("Invalid state");
+                for &byte in token {
+                    next = dfa.next_state(next, byte);
+                }
+                let next = StateId::new(next.as_u32());
+                let expected = states.contains(&next).then_some(next);
+                assert_eq!(index.next_state(&state, &ids[0]), expected, "{token:?}");
+            }
+        }
+
+        // Prefiltered tokens keep the order of the vocabulary, like without the prefilter.
+        let table = TokenTable::new(&vocabulary);
+        let position = |token: &Token| table.tokens.iter().position(|(t, _)| *t == token);
+        let mut prefiltered = 0;
+        for &state in index.transitions().keys() {
+            let state = AutomataStateId::new(state.as_usize()).expect("Invalid state");
+            let Some(prefilter) = LiteralPrefilter::new(&dfa, state, table.max_token_len) else {
+                continue;
+            };
+            let positions: Vec<Option<usize>> = table
+                .prefiltered(&prefilter)
+                .map(|(token, _)| position(token))
+                .collect();
+            assert!(positions.windows(2).all(|pair| pair[0] < pair[1]));
+            prefiltered += 1;
+        }
+        assert!(prefiltered > 0);
+    }
+
+    #[test]
+    fn index_with_hoisted_constant() {
+        let regex = r#""Answer with yes or no\."(, [a-z]+)?"#;
+        let mut vocabulary = Vocabulary::new(0);
+        let tokens = [
+            "\"", "\"An", "Answer", "An", "swer", " with", " ", "with", "w", "yes", " or", "or",
+            " no", "no", ".", ".\"", "\",", ",", " maybe", "a", "z", "",
+        ];
+        for (token_id, token) in tokens.iter().enumerate() {
+            vocabulary
+                .try_insert(*token, token_id as u32 + 1)
+                .expect("Insert failed");
+        }
+        let index = Index::new(regex, &vocabulary).expect("Index failed");
+
+        // Transitions along the constant are the ones of walking the DFA byte by byte.
+        let dfa = DFA::new(regex).expect("DFA failed");
+        let states: HashSet<StateId> = index
+            .transitions()
+            .keys()
+            .chain(index.final_states())
+            .copied()
+            .collect();
+        for &state in index.transitions().keys() {
+            for (token, ids) in vocabulary.tokens() {
+                let mut next = AutomataStateId::new(state.as_usize()).expect("Invalid state");
+                for &byte in token {
+                    next = dfa.next_state(next, byte);
+                }
+                let next = StateId::new(next.as_u32());
+                let expected = states.contains(&next).then_some(next);
+                assert_eq!(index.next_state(&state, &ids[0]), expected, "{token:?}");
+            }
+        }
+
+        // Different tokenizations of the constant reach the final state.
+        for tokenization in [
+            &["\"An", "swer", " with", " ", "yes", " or", " no", ".\""][..],
+            &[
+                "\"", "An", "swer", " ", "with", " ", "yes", " ", "or", " no", ".", "\"",
+            ][..],
+        ] {
+            let mut state = index.initial_state();
+            for token in tokenization {
+                let ids = vocabulary.token_ids(token).expect("Missing token");
+                state = index.next_state(&state, &ids[0]).expect("Transit failed");
+            }
+            assert!(index.is_final_state(&state));
+        }
+    }
+
+    #[test]
+    fn index_stream_roundtrip() {
+        let regex = "0|[1-9][0-9]*";
+        let mut vocabulary = Vocabulary::new(4);
+        for (token, token_id) in [("blah", 0), ("1a", 1), ("2", 2), ("0", 3)] {
+            vocabulary
+                .try_insert(token, token_id as u32)
+                .expect("Insert failed");
+        }
+        let index = Index::new(regex, &vocabulary).expect("Index failed");
+
+        let mut buffer = Vec::new();
+        let mut written = Vec::new();
+        let bytes = index
+            .write_to(&mut buffer, |p| written.push(p))
+            .expect("Write failed");
+        assert_eq!(bytes, buffer.len());
+        let last = written.last().expect("No progress reported");
+        assert_eq!(last.states_done, index.transitions().len());
+        assert_eq!(last.bytes, bytes);
+
+        let mut read = Vec::new();
+        let restored = Index::read_from(buffer.as_slice(), |p| read.push(p)).expect("Read failed");
+        assert_eq!(restored, index);
+        assert_eq!(restored.fingerprint(), index.fingerprint());
+        assert_eq!(read.last().map(|p| p.bytes), Some(bytes));
+
+        let truncated = &buffer[..buffer.len() - 1];
+        assert!(Index::read_from(truncated, |_| {}).is_err());
+    }
+
+    #[test]
+    fn index_stream_forged_header() {
+        let config = config::standard();
+        let header = StreamHeader {
+            version: STREAM_FORMAT_VERSION,
+            initial_state: StateId::new(0),
+            final_states: Vec::new(),
+            eos_token_id: TokenId::new(0),
+            vocab_size: 1,
+            states_total: usize::MAX,
+        };
+
+        // Claims more states than the stream holds.
+        let forged = bincode::encode_to_vec(header, config).expect("Encode failed");
+        assert!(Index::read_from(forged.as_slice(), |_| {}).is_err());
+
+        // Claims a huge list of final states, without its content: the version, the initial
+        // state 0 and the length of the list, a varint of 8 bytes.
+        let mut forged = vec![STREAM_FORMAT_VERSION, 0, 253];
+        forged.extend((u64::MAX / 8).to_le_bytes());
+        assert!(matches!(
+            Index::read_from(forged.as_slice(), |_| {}),
+            Err(Error::IndexDecodeError(_))
+        ));
+    }
+
+    #[test]
+    fn index_stream_deduplicates_masks() {
+        let mut vocabulary = Vocabulary::new(10);
+        for digit in 0..10 {
+            vocabulary
+                .try_insert(digit.to_string(), digit)
+                .expect("Insert failed");
+        }
+        // All the states but the initial and the last ones allow the same digits.
+        let index = Index::new("[0-9]{60}", &vocabulary).expect("Index failed");
+        let config = config::standard();
+
+        let mut buffer = Vec::new();
+        index.write_to(&mut buffer, |_| {}).expect("Write failed");
+        let restored = Index::read_from(buffer.as_slice(), |_| {}).expect("Read failed");
+        assert_eq!(restored.transitions(), index.transitions());
+        assert_eq!(restored.fingerprint(), index.fingerprint());
+        assert_eq!(restored.provenance(), index.provenance());
+
+        // Previous version wrote all the transitions of each state.
+        let header = StreamHeader {
+            version: 3,
+            initial_state: index.initial_state(),
+            final_states: index.final_states().iter().copied().collect(),
+            eos_token_id: index.eos_token_id(),
+            vocab_size: index.vocab_size(),
+            states_total: index.transitions().len(),
+        };
+        let entries: Vec<(StateId, Vec<(TokenId, StateId)>)> = index
+            .transitions()
+            .iter()
+            .map(|(&state, map)| (state, map.iter().map(|(&t, &s)| (t, s)).collect()))
+            .collect();
+        let mut previous = bincode::encode_to_vec(&header, config).expect("Encode failed");
+        for part in [
+            bincode::encode_to_vec(index.byte_classes(), config),
+            bincode::encode_to_vec(index.stop_token_ids(), config),
+            bincode::encode_to_vec(&entries, config),
+        ] {
+            previous.extend(part.expect("Encode failed"));
+        }
+        let restored = Index::read_from(previous.as_slice(), |_| {}).expect("Read failed");
+        assert_eq!(restored.transitions(), index.transitions());
+        assert!(buffer.len() * 2 < previous.len());
+
+        // Masks are referred to by their numbers.
+        let (_, header_len): (StreamHeader, usize) =
+            bincode::decode_from_slice(&buffer, config).expect("Decode failed");
+        let mut corrupted = buffer[..header_len].to_vec();
+        corrupted
+            .extend(bincode::encode_to_vec(index.byte_classes(), config).expect("Encode failed"));
+        corrupted
+            .extend(bincode::encode_to_vec(index.stop_token_ids(), config).expect("Encode failed"));
+        corrupted
+            .extend(bincode::encode_to_vec(index.provenance(), config).expect("Encode failed"));
+        let chunk = StreamChunk {
+            masks: Vec::new(),
+            states: vec![(index.initial_state(), 0, vec![StateId::new(1)])],
+        };
+        corrupted.extend(bincode::encode_to_vec(&chunk, config).expect("Encode failed"));
+        assert!(matches!(
+            Index::read_from(corrupted.as_slice(), |_| {}),
+            Err(Error::IndexStreamError(_))
+        ));
+    }
+
+    #[test]
+    fn index_with_stop_tokens() {
+        let mut vocabulary = Vocabulary::new(4);
+        for (token, token_id) in [("1", 1), ("2", 2), ("0", 3)] {
+            vocabulary
+                .try_insert(token, token_id)
+                .expect("Insert failed");
+        }
+        vocabulary.try_insert_stop_token(5).expect("Insert failed");
+
+        // Explored DFA, then counted repetition.
+        for regex in ["0|[1-9][0-9]*", "[0-9]{1,3}"] {
+            let index = Index::new(regex, &vocabulary).expect("Index failed");
+            let initial_state = index.initial_state();
+            let allowed = index.allowed_tokens(&initial_state).expect("No state");
+            assert!(!allowed.contains(&TokenId::new(5)));
+            let state = index
+                .next_state(&initial_state, &TokenId::new(3))
+                .expect("No next state");
+            let allowed = index.allowed_tokens(&state).expect("No state");
+            assert!(allowed.contains(&TokenId::new(4)) && allowed.contains(&TokenId::new(5)));
+            assert_eq!(index.next_state(&state, &TokenId::new(5)), None);
+            assert!(
+                index.is_stop_token(&TokenId::new(5)) && !index.is_stop_token(&TokenId::new(3))
+            );
+
+            let mut buffer = Vec::new();
+            index.write_to(&mut buffer, |_| {}).expect("Write failed");
+            let restored = Index::read_from(buffer.as_slice(), |_| {}).expect("Read failed");
+            assert_eq!(restored.stop_token_ids(), &[5]);
+            assert_eq!(restored.fingerprint(), index.fingerprint());
+        }
+
+        // "0" must be followed by the end-of-sequence token or a stop token.
+        let index = Index::new("0|[1-9][0-9]*", &vocabulary).expect("Index failed");
+        let state = index
+            .next_state(&index.initial_state(), &TokenId::new(3))
+            .expect("No next state");
+        assert!(index.is_dead_end_without_eos(&state));
+    }
+
+    #[test]
+    fn index_with_duplicate_tokens() {
+        let mut vocabulary = Vocabulary::new(0);
+        for (token, token_id) in [("a", 1), ("b", 2), ("a", 3), ("ab", 4), ("ab", 5), ("a", 3)] {
+            vocabulary
+                .try_insert(token, token_id)
+                .expect("Insert failed");
+        }
+        assert_eq!(vocabulary.token_ids("a").expect("No token ids"), &[1, 3]);
+
+        // Explored DFA, with a forced literal, then counted repetition.
+        for regex in ["(ab|a)+", "abab?", ".{1,5}"] {
+            let index = Index::new(regex, &vocabulary).expect("Index failed");
+            assert_eq!(index.counted.is_some(), regex == ".{1,5}");
+            for (state, map) in index.transitions() {
+                let allowed = index.allowed_tokens(state).expect("No state");
+                assert_eq!(allowed.len(), map.len(), "{regex}: repeated ids");
+                for ids in [[1, 3], [4, 5]].map(|ids| ids.map(TokenId::new)) {
+                    assert_eq!(map.get(&ids[0]), map.get(&ids[1]), "{regex}: {state}");
+                    assert_eq!(
+                        index.next_state(state, &ids[0]),
+                        index.next_state(state, &ids[1])
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn distances_to_final_states() {
+        let mut vocabulary = Vocabulary::new(0);
+        for (token, token_id) in [("a", 1), ("b", 2), ("ab", 3)] {
+            vocabulary
+                .try_insert(token, token_id)
+                .expect("Insert failed");
+        }
+        // Explored DFA, then counted repetition.
+        for (regex, distance) in [("a(ba)*b{3}", 3), (".{4,5}", 2)] {
+            let index = Index::new(regex, &vocabulary).expect("Index failed");
+            let initial_state = index.initial_state();
+            assert_eq!(index.distance_to_final(&initial_state), Some(distance));
+            for state in index.final_states() {
+                assert_eq!(index.distance_to_final(state), Some(0));
+            }
+            let next = index
+                .next_state(&initial_state, &TokenId::new(3))
+                .expect("No next state");
+            assert_eq!(index.distance_to_final(&next), Some(distance - 1));
+        }
+    }
+
+    #[test]
+    fn index_footprint() {
+        let mut vocabulary = Vocabulary::new(0);
+        for (token, token_id) in [("a", 1), ("b", 2)] {
+            vocabulary
+                .try_insert(token, token_id)
+                .expect("Insert failed");
+        }
+        let index = Index::new("a[ab]*", &vocabulary).expect("Index failed");
+        let footprint = index.footprint();
+        assert!(footprint.transitions > 0);
+        assert_eq!(footprint.counted, 0);
+        assert_eq!(footprint.caches, 0);
+
+        index.distance_to_final(&index.initial_state());
+        let with_caches = index.footprint();
+        assert!(with_caches.caches > 0);
+        assert_eq!(with_caches.total(), footprint.total() + with_caches.caches);
+
+        let counted = Index::new(".{2,40}", &vocabulary).expect("Index failed");
+        let footprint = counted.footprint();
+        assert!(footprint.counted > 0);
+        assert_eq!(footprint.caches, 0);
+        counted.transitions();
+        assert!(counted.footprint().caches > 0);
+    }
+
+    #[test]
+    fn index_byte_classes() {
+        let mut vocabulary = Vocabulary::new(4);
+        for (token, token_id) in [("1", 1), ("2", 2), ("0", 3)] {
+            vocabulary
+                .try_insert(token, token_id)
+                .expect("Insert failed");
+        }
+        let index = Index::new("0|[1-9][0-9]*", &vocabulary).expect("Index failed");
+        let classes = index.byte_classes();
+        assert_eq!(classes.class_of(b'1'), classes.class_of(b'9'));
+        assert_ne!(classes.class_of(b'0'), classes.class_of(b'1'));
+        assert_ne!(classes.class_of(b'9'), classes.class_of(b':'));
+
+        // Counted repetitions don't explore the DFA, but have the same classes.
+        let index = Index::new(".{1,5}", &vocabulary).expect("Index failed");
+        assert!(index.counted.is_some());
+        let expected = DFA::new(".{1,5}").expect("DFA failed");
+        assert_eq!(
+            index.byte_classes(),
+            &ByteClasses::from_automaton(expected.byte_classes())
+        );
+    }
+
+    #[test]
+    fn enumerate_accepted_sequences() {
+        let mut vocabulary = Vocabulary::new(0);
+        for (token, token_id) in [("a", 1), ("b", 2), ("ab", 3), ("c", 4)] {
+            vocabulary
+                .try_insert(token, token_id)
+                .expect("Insert failed");
+        }
+        let index = Index::new("(ab|c)*", &vocabulary).expect("Index failed");
+
+        let sequences: Vec<Vec<TokenId>> = index.enumerate_strings(2).collect();
+        assert_eq!(
+            sequences,
+            vec![
+                vec![],
+                vec![1, 2],
+                vec![3],
+                vec![3, 3],
+                vec![3, 4],
+                vec![4],
+                vec![4, 3],
+                vec![4, 4],
+            ]
+        );
+        assert_eq!(index.enumerate_strings(0).count(), 1);
+        assert_eq!(index.enumerate_strings(6).take(100).count(), 100);
+    }
+
+    #[test]
+    fn complement_within_bound() {
+        let mut vocabulary = Vocabulary::new(0);
+        for (token, token_id) in [("a", 1), ("b", 2), ("ab", 3)] {
+            vocabulary
+                .try_insert(token, token_id)
+                .expect("Insert failed");
+        }
+        let index = Index::new("ab?", &vocabulary).expect("Index failed");
+        let complement = index.complement(&vocabulary, 2).expect("Complement failed");
+
+        // 13 sequences of at most 2 tokens, but for both splits of "ab" and "a".
+        let sequences: Vec<Vec<TokenId>> = complement.enumerate_strings(2).collect();
+        assert_eq!(sequences.len(), 10);
+        for rejected in [vec![1], vec![3], vec![1, 2]] {
+            assert!(!sequences.contains(&rejected.into_iter().map(TokenId::new).collect()));
+        }
+        assert!(complement.is_final_state(&complement.initial_state()));
+        assert_eq!(complement.enumerate_strings(3).count(), 10);
+        let limits = ResourceLimits::default().with_max_states(3);
+        assert!(matches!(
+            index.complement_with_limits(&vocabulary, 2, &limits),
+            Err(Error::ResourceLimitExceeded {
+                resource: "states",
+                ..
+            })
+        ));
+
+        // Every sequence of the vocabulary is accepted.
+        let index = Index::new("[ab]*", &vocabulary).expect("Index failed");
+        assert!(matches!(
+            index.complement(&vocabulary, 3),
+            Err(Error::EmptyComplement { max_tokens: 3 })
+        ));
+    }
+
+    #[test]
+    fn index_from_regex_completeness() {
+        let regex = "(ac|[^a])+";
+        let eos_token_id = 3;
+        let mut vocabulary = Vocabulary::new(eos_token_id);
+        for (token, token_id) in [("a", 0), ("b", 1), ("c", 2)] {
+            vocabulary
+                .try_insert(token, token_id as u32)
+                .expect("Insert failed");
+        }
+
+        let index = Index::new(regex, &vocabulary).expect("Index failed");
+        let mut state = index.initial_state();
+
+        // "acac" should be accepted
+        for token_id in [0, 2, 0, 2].map(TokenId::new) {
+            state = index.next_state(&state, &token_id).expect("Transit failed");
+        }
+        assert!(index.is_final_state(&state));
+    }
+}
@@ -0,0 +1,154 @@
+//! Pre-filter of the tokens, which may follow a state of the automaton.
+//!
+//! Objects with many required keys branch between a few literals, e.g. a whitespace or the
+//! quote of the next key, so that a state has no single forced literal, but the strings
+//! walked from it are still a small set. Those strings are matched as a prefix of tokens with
+//! an anchored Aho-Corasick automaton: a token is skipped, when it leaves all the strings
+//! before completing one of them, since its walk in the automaton of the regular expression
+//! would reach a dead state.
+
+use aho_corasick::automaton::Automaton;
+use aho_corasick::dfa::DFA as LiteralDFA;
+use aho_corasick::{Anchored, MatchKind, StartKind};
+use regex_automata::dfa::dense::DFA;
+use regex_automata::dfa::Automaton as _;
+use regex_automata::util::alphabet::Unit;
+use regex_automata::util::primitives::StateID as AutomataStateId;
+
+/// Maximal number of bytes leading to live states from a state walked by a literal, more
+/// bytes end the literal there.
+const MAX_BRANCHES: usize = 8;
+
+/// Maximal number of literals of a state.
+const MAX_LITERALS: usize = 64;
+
+/// Maximal length of a literal, longer tokens are checked by walking the automaton.
+const MAX_LITERAL_LEN: usize = 16;
+
+/// Literals walked from a state of the automaton of a regular expression, which every
+/// token of the state starts with or is a prefix of.
+pub(super) struct LiteralPrefilter {
+    /// First bytes of the literals, in ascending order.
+    first_bytes: Vec<u8>,
+    matcher: LiteralDFA,
+}
+
+impl LiteralPrefilter {
+    /// Collects the literals walked from `state` breadth first, up to `max_len` bytes.
+    ///
+    /// Returns `None` if the state branches too much to be worth filtering.
+    pub(super) fn new(dfa: &DFA<Vec<u32>>, state: AutomataStateId, max_len: usize) -> Option<Self> {
+        // Bytes of the same class lead to the same state.
+        let classes = dfa.byte_classes();
+        let live_bytes = |current: AutomataStateId| -> Vec<(u8, AutomataStateId)> {
+            classes
+                .representatives(..)
+                .filter_map(|unit| {
+                    let byte = unit.as_u8()?;
+                    let next = dfa.next_state(current, byte);
+                    (!dfa.is_dead_state(next) && !dfa.is_quit_state(next)).then_some((byte, next))
+                })
+                .flat_map(|(byte, next)| {
+                    classes
+                        .elements(Unit::u8(classes.get(byte)))
+                        .filter_map(move |element| Some((element.as_u8()?, next)))
+                })
+                .collect()
+        };
+
+        let mut literals: Vec<Vec<u8>> = Vec::new();
+        let mut frontier: Vec<(Vec<u8>, AutomataStateId)> = vec![(Vec::new(), state)];
+        for _ in 0..max_len.min(MAX_LITERAL_LEN) {
+            let mut next_frontier = Vec::new();
+            for (literal, current) in frontier {
+                let next = live_bytes(current);
+                if next.is_empty() || next.len() > MAX_BRANCHES {
+                    literals.push(literal);
+                    continue;
+                }
+                next_frontier.extend(next.into_iter().map(|(byte, next)| {
+                    let mut extended = literal.clone();
+                    extended.push(byte);
+                    (extended, next)
+                }));
+            }
+            frontier = next_frontier;
+            // Literals may end before they branch, longer tokens are checked by walking.
+            if frontier.is_empty() || literals.len() + frontier.len() > MAX_LITERALS {
+                break;
+            }
+        }
+        literals.extend(frontier.into_iter().map(|(literal, _)| literal));
+
+        // An empty literal is a prefix of every token.
+        if literals.iter().any(|literal| literal.is_empty()) {
+            return None;
+        }
+        let mut first_bytes: Vec<u8> = literals.iter().map(|literal| literal[0]).collect();
+        first_bytes.sort_unstable();
+        first_bytes.dedup();
+        let matcher = LiteralDFA::builder()
+            .match_kind(MatchKind::Standard)
+            .start_kind(StartKind::Anchored)
+            .build(&literals)
+            .ok()?;
+        Some(Self {
+            first_bytes,
+            matcher,
+        })
+    }
+
+    /// Returns the bytes, which non-empty tokens of the state start with.
+    pub(super) fn first_bytes(&self) -> &[u8] {
+        &self.first_bytes
+    }
+
+    /// Checks if the token may follow the state, i.e. it's a prefix of a literal or starts
+    /// with one.
+    pub(super) fn may_follow(&self, token: &[u8]) -> bool {
+        let Ok(mut state) = self.matcher.start_state(Anchored::Yes) else {
+            return true;
+        };
+        for &byte in token {
+            state = self.matcher.next_state(Anchored::Yes, state, byte);
+            if self.matcher.is_dead(state) {
+                return false;
+            }
+            if self.matcher.is_match(state) {
+                return true;
+            }
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use regex_automata::Anchored as RegexAnchored;
+
+    use super::*;
+
+    #[test]
+    fn filter_tokens() {
+        let dfa = DFA::new(r#"\{[ ]?"(name|age)":"#).expect("DFA failed");
+        let start = dfa
+            .universal_start_state(RegexAnchored::Yes)
+            .expect("No start state");
+        let prefilter = LiteralPrefilter::new(&dfa, start, 8).expect("No prefilter");
+        assert_eq!(prefilter.first_bytes(), b"{");
+
+        for token in ["{", "{ ", "{\"na", "{ \"age\":", "{\"name\":\"", ""] {
+            assert!(prefilter.may_follow(token.as_bytes()), "{token}");
+        }
+        for token in ["x", "{x", "{ \"n\"", "{\"ag:"] {
+            assert!(!prefilter.may_follow(token.as_bytes()), "{token}");
+        }
+
+        // Too many branches right away.
+        let dfa = DFA::new("[a-z]+").expect("DFA failed");
+        let start = dfa
+            .universal_start_state(RegexAnchored::Yes)
+            .expect("No start state");
+        assert!(LiteralPrefilter::new(&dfa, start, 8).is_none());
+    }
+}
@@ -0,0 +1,193 @@
+//! Check of an index against the vocabulary it's used with, e.g. when a cached index is loaded
+//! next to a tokenizer, which may not be the one the index was built with.
+//!
+//! Token ids of another vocabulary still walk the index, but spell other texts, so that the
+//! generated text silently violates the regular expression.
+
+use bincode::{Decode, Encode};
+use regex_automata::dfa::dense::DFA;
+use regex_automata::dfa::Automaton;
+use regex_automata::nfa::thompson;
+use regex_automata::util::syntax;
+use regex_automata::Anchored;
+
+use super::Index;
+use crate::prelude::*;
+use crate::{Error, Result};
+
+/// Number of sampled walks, which are decoded and matched against the regular expression.
+const VERIFIED_WALKS: usize = 16;
+
+/// Maximal number of tokens of a sampled walk.
+const MAX_WALK_TOKENS: usize = 32;
+
+/// What an index is built from, see [`Index::provenance`].
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode)]
+pub struct Provenance {
+    /// The regular expression of the index.
+    pub regex: String,
+    /// Fingerprint of the vocabulary, see [`Vocabulary::fingerprint`].
+    pub vocabulary_fingerprint: u64,
+}
+
+impl Index {
+    /// Checks that the index is used with the vocabulary it was built with.
+    ///
+    /// The end-of-sequence token, the size of the vocabulary and the tokens of transitions
+    /// are checked first. Then, if the index knows its [`Provenance`], the fingerprint of the
+    /// vocabulary is compared and a few random walks of the index are decoded with the
+    /// vocabulary, each text having to match the regular expression. Fails with
+    /// [`Error::VocabularyMismatch`] on the first difference.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use outlines_core::Error;
+    /// use outlines_core::prelude::*;
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// let vocabulary = Vocabulary::fixture();
+    /// let index = Index::new("[0-9]{2}-[a-z]+", &vocabulary)?;
+    /// index.verify_vocabulary(&vocabulary)?;
+    ///
+    /// let mut other = vocabulary.clone();
+    /// other.try_insert("x", 9999)?;
+    /// assert!(index.verify_vocabulary(&other).is_err());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn verify_vocabulary(&self, vocabulary: &Vocabulary) -> Result<()> {
+        let mismatch = |reason: String| Err(Error::VocabularyMismatch(reason));
+        if self.eos_token_id != vocabulary.eos_token_id() {
+            return mismatch(format!(
+                "end-of-sequence token {} instead of {}",
+                vocabulary.eos_token_id(),
+                self.eos_token_id
+            ));
+        }
+        if self.vocab_size != vocabulary.vocab_size() {
+            return mismatch(format!(
+                "{} tokens instead of {}",
+                vocabulary.vocab_size(),
+                self.vocab_size
+            ));
+        }
+        if let Some(provenance) = &self.provenance {
+            let fingerprint = vocabulary.fingerprint();
+            if fingerprint != provenance.vocabulary_fingerprint {
+                return mismatch(format!(
+                    "fingerprint {fingerprint} instead of {}",
+                    provenance.vocabulary_fingerprint
+                ));
+            }
+        }
+
+        let is_text = |token_id: &TokenId| {
+            *token_id != self.eos_token_id && !self.stop_token_ids.contains(token_id)
+        };
+        for map in self.transitions().values() {
+            if let Some(token_id) = map
+                .keys()
+                .find(|&token_id| is_text(token_id) && vocabulary.token(*token_id).is_none())
+            {
+                return mismatch(format!("token {token_id} is missing"));
+            }
+        }
+
+        let Some(provenance) = &self.provenance else {
+            return Ok(());
+        };
+        let dfa = DFA::builder()
+            .syntax(syntax::Config::new().utf8(false))
+            .thompson(thompson::Config::new().utf8(false))
+            .build(&provenance.regex)
+            .map_err(Box::new)?;
+        let Some(start) = dfa.universal_start_state(Anchored::Yes) else {
+            return Err(Error::DfaHasNoStartState);
+        };
+        let walks = crate::regex::sample_tokens(self, VERIFIED_WALKS, MAX_WALK_TOKENS, 0);
+        for walk in walks {
+            let text: Vec<u8> = walk
+                .iter()
+                .filter(|token_id| is_text(token_id))
+                .filter_map(|token_id| vocabulary.token(*token_id))
+                .flatten()
+                .copied()
+                .collect();
+            let state = text
+                .iter()
+                .fold(start, |state, &byte| dfa.next_state(state, byte));
+            if !dfa.is_match_state(dfa.next_eoi_state(state)) {
+                return mismatch(format!(
+                    "tokens {walk:?} spell {:?}, which doesn't match the regular expression",
+                    String::from_utf8_lossy(&text)
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verify_vocabulary() {
+        let mut vocabulary = Vocabulary::new(4);
+        for (token, token_id) in [("a", 0), ("b", 1), ("ab", 2), ("c", 3)] {
+            vocabulary
+                .try_insert(token, token_id)
+                .expect("Insert failed");
+        }
+        let index = Index::new("(ab)+c", &vocabulary).expect("Index failed");
+        assert!(index.verify_vocabulary(&vocabulary).is_ok());
+
+        // Same ids spelling other tokens: only the walks tell them apart, once the
+        // fingerprint is out of the way.
+        let mut swapped = Vocabulary::new(4);
+        for (token, token_id) in [("b", 0), ("a", 1), ("ba", 2), ("c", 3)] {
+            swapped.try_insert(token, token_id).expect("Insert failed");
+        }
+        assert!(matches!(
+            index.verify_vocabulary(&swapped),
+            Err(Error::VocabularyMismatch(reason)) if reason.starts_with("fingerprint")
+        ));
+        let mut stale = index.clone();
+        stale.provenance = Some(Provenance {
+            vocabulary_fingerprint: swapped.fingerprint(),
+            ..index.provenance().cloned().expect("No provenance")
+        });
+        assert!(matches!(
+            stale.verify_vocabulary(&swapped),
+            Err(Error::VocabularyMismatch(reason)) if reason.contains("doesn't match")
+        ));
+
+        let mut other_eos = Vocabulary::new(5);
+        for (token, token_id) in [("a", 0), ("b", 1), ("ab", 2), ("c", 3)] {
+            other_eos
+                .try_insert(token, token_id)
+                .expect("Insert failed");
+        }
+        assert!(matches!(
+            index.verify_vocabulary(&other_eos),
+            Err(Error::VocabularyMismatch(reason)) if reason.starts_with("end-of-sequence")
+        ));
+
+        // Without provenance, only the tokens are checked.
+        let mut read = Vec::new();
+        stale.provenance = None;
+        stale.write_to(&mut read, |_| {}).expect("Write failed");
+        let read = Index::read_from(read.as_slice(), |_| {}).expect("Read failed");
+        assert_eq!(read.provenance(), None);
+        assert!(read.verify_vocabulary(&swapped).is_ok());
+        let mut missing = Vocabulary::new(4);
+        for (token, token_id) in [("a", 0), ("b", 1), ("c", 3)] {
+            missing.try_insert(token, token_id).expect("Insert failed");
+        }
+        assert!(matches!(
+            read.verify_vocabulary(&missing),
+            Err(Error::VocabularyMismatch(reason)) if reason == "token 2 is missing"
+        ));
+    }
+}
@@ -0,0 +1,144 @@
+//! Structured generation with [candle](https://github.com/huggingface/candle) tensors.
+//!
+//! [`LogitsProcessor`] wraps a [`Guide`] and masks out logits of the tokens, which are not
+//! allowed in the current state of the guide, so that any sampling strategy applied afterwards
+//! picks only valid tokens.
+//!
+//! ```rust
+//! # use outlines_core::Error;
+//! use std::sync::Arc;
+//!
+//! use candle_core::{Device, Tensor};
+//! use outlines_core::integrations::candle::LogitsProcessor;
+//! use outlines_core::prelude::*;
+//!
+//! # fn main() -> Result<(), Error> {
+//! let mut vocabulary = Vocabulary::new(3);
+//! for (token, token_id) in [("a", 0), ("b", 1), ("c", 2)] {
+//!     vocabulary.try_insert(token, token_id)?;
+//! }
+//! let index = Index::new("ab", &vocabulary)?;
+//! let mut processor = LogitsProcessor::new(Guide::new(Arc::new(index), 0));
+//!
+//! let logits = Tensor::new(&[1.0f32, 2.0, 3.0, 4.0], &Device::Cpu)?;
+//! let masked = processor.apply(&logits)?;
+//! let token_id = masked.argmax(0)?.to_scalar::<u32>()?;
+//! assert_eq!(token_id, 0);
+//!
+//! processor.advance(TokenId::new(token_id))?;
+//! # Ok(())
+//! # }
+//! ```
+
+use candle_core::{DType, Tensor, D};
+
+use crate::guide::Guide;
+use crate::prelude::*;
+use crate::Result;
+
+/// Applies the constraints of a [`Guide`] to candle logits tensors.
+#[derive(Clone, Debug)]
+pub struct LogitsProcessor {
+    guide: Guide,
+}
+
+impl LogitsProcessor {
+    /// Creates a logits processor from the guide.
+    pub fn new(guide: Guide) -> Self {
+        Self { guide }
+    }
+
+    /// Returns the underlying guide.
+    pub fn guide(&self) -> &Guide {
+        &self.guide
+    }
+
+    /// Returns logits with `-inf` set for the tokens not allowed in the current state.
+    ///
+    /// The last dimension of `logits` is the vocabulary one, any leading dimensions
+    /// (e.g. batch of one) are masked the same way. Token ids beyond the size of the last
+    /// dimension are ignored.
+    pub fn apply(&self, logits: &Tensor) -> Result<Tensor> {
+        let width = logits.dim(D::Minus1)?;
+        let mut mask = vec![f32::NEG_INFINITY; width];
+        for token_id in self.guide.allowed_tokens()? {
+            if let Some(value) = mask.get_mut(token_id.as_usize()) {
+                *value = 0.0;
+            }
+        }
+        let mask = Tensor::from_vec(mask, width, logits.device())?;
+        let mask = match logits.dtype() {
+            DType::F32 => mask,
+            dtype => mask.to_dtype(dtype)?,
+        };
+        Ok(logits.broadcast_add(&mask)?)
+    }
+
+    /// Advances the guide with the sampled token.
+    pub fn advance(&mut self, token_id: TokenId) -> Result<()> {
+        self.guide.advance(token_id)?;
+        Ok(())
+    }
+
+    /// Checks if the guide has reached a final state.
+    pub fn is_finished(&self) -> bool {
+        self.guide.is_finished()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use candle_core::Device;
+
+    use super::*;
+    use crate::index::Index;
+
+    #[test]
+    fn mask_logits() {
+        let mut vocabulary = Vocabulary::new(3);
+        for (token, token_id) in [("a", 0), ("b", 1), ("c", 2)] {
+            vocabulary
+                .try_insert(token, token_id)
+                .expect("Insert failed");
+        }
+        let index = Index::new("[ab]c", &vocabulary).expect("Index failed");
+        let mut processor = LogitsProcessor::new(Guide::new(Arc::new(index), 0));
+
+        let logits =
+            Tensor::new(&[[1.0f32, 2.0, 3.0, 4.0, 5.0]], &Device::Cpu).expect("Tensor failed");
+        let masked = processor
+            .apply(&logits)
+            .expect("Apply failed")
+            .to_vec2::<f32>()
+            .expect("To vec failed");
+        assert_eq!(
+            masked,
+            vec![vec![
+                1.0,
+                2.0,
+                f32::NEG_INFINITY,
+                f32::NEG_INFINITY,
+                f32::NEG_INFINITY
+            ]]
+        );
+
+        processor.advance(TokenId::new(1)).expect("Advance failed");
+        processor.advance(TokenId::new(2)).expect("Advance failed");
+        assert!(processor.is_finished());
+
+        let logits = Tensor::new(&[1.0f32, 2.0, 3.0], &Device::Cpu)
+            .expect("Tensor failed")
+            .to_dtype(DType::F16)
+            .expect("To dtype failed");
+        let masked = processor
+            .apply(&logits)
+            .expect("Apply failed")
+            .to_dtype(DType::F32)
+            .and_then(|t| t.to_vec1::<f32>())
+            .expect("To vec failed");
+        // Only EOS is allowed, which is beyond the width of the logits.
+        assert!(masked.iter().all(|v| v.is_infinite()));
+    }
+}
@@ -0,0 +1,196 @@
+//! Structured generation with the [llama.cpp](https://github.com/ggerganov/llama.cpp) samplers.
+//!
+//! [`Sampler`] wraps a [`Guide`] and applies its constraints either to the raw logits array
+//! or to the candidates array passed to custom samplers.
+//!
+//! This module doesn't depend on any particular llama.cpp bindings: [`TokenData`] has the same
+//! layout as `llama_token_data`, so the candidates of the Rust bindings (e.g. `llama-cpp-2`'s
+//! `LlamaTokenDataArray::data`, a transparent wrapper over it) could be reinterpreted as
+//! a slice of [`TokenData`] without copying.
+//!
+//! ```rust
+//! # use outlines_core::Error;
+//! use std::sync::Arc;
+//!
+//! use outlines_core::integrations::llama_cpp::{Sampler, TokenData};
+//! use outlines_core::prelude::*;
+//!
+//! # fn main() -> Result<(), Error> {
+//! let mut vocabulary = Vocabulary::new(3);
+//! for (token, token_id) in [("a", 0), ("b", 1), ("c", 2)] {
+//!     vocabulary.try_insert(token, token_id)?;
+//! }
+//! let index = Index::new("b+", &vocabulary)?;
+//! let mut sampler = Sampler::new(Guide::new(Arc::new(index), 0));
+//!
+//! let mut candidates = vec![
+//!     TokenData { id: 0, logit: 3.0, p: 0.0 },
+//!     TokenData { id: 1, logit: 1.0, p: 0.0 },
+//! ];
+//! sampler.apply_to_candidates(&mut candidates)?;
+//! assert_eq!(candidates[0].logit, f32::NEG_INFINITY);
+//!
+//! sampler.accept(TokenId::new(1))?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::guide::Guide;
+use crate::prelude::*;
+use crate::Result;
+
+/// Token candidate with the same memory layout as `llama_token_data`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TokenData {
+    /// Token id.
+    pub id: i32,
+    /// Log-odds of the token.
+    pub logit: f32,
+    /// Probability of the token.
+    pub p: f32,
+}
+
+/// Applies the constraints of a [`Guide`] to llama.cpp logits and candidates.
+#[derive(Clone, Debug)]
+pub struct Sampler {
+    guide: Guide,
+    /// Scratch mask of allowed token ids, reused between steps.
+    allowed: Vec<bool>,
+}
+
+impl Sampler {
+    /// Creates a sampler from the guide.
+    pub fn new(guide: Guide) -> Self {
+        let allowed = vec![false; guide.index().vocab_size()];
+        Self { guide, allowed }
+    }
+
+    /// Returns the underlying guide.
+    pub fn guide(&self) -> &Guide {
+        &self.guide
+    }
+
+    /// Sets `-inf` logits for the tokens not allowed in the current state, where
+    /// the position in `logits` is the token id.
+    pub fn apply_to_logits(&mut self, logits: &mut [f32]) -> Result<()> {
+        self.fill_allowed()?;
+        for (token_id, logit) in logits.iter_mut().enumerate() {
+            if !self.allowed.get(token_id).copied().unwrap_or(false) {
+                *logit = f32::NEG_INFINITY;
+            }
+        }
+        Ok(())
+    }
+
+    /// Sets `-inf` logits and zero probabilities for the candidates not allowed in the
+    /// current state.
+    pub fn apply_to_candidates(&mut self, candidates: &mut [TokenData]) -> Result<()> {
+        self.fill_allowed()?;
+        for candidate in candidates.iter_mut() {
+            let allowed = usize::try_from(candidate.id)
+                .ok()
+                .and_then(|id| self.allowed.get(id).copied())
+                .unwrap_or(false);
+            if !allowed {
+                candidate.logit = f32::NEG_INFINITY;
+                candidate.p = 0.0;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns a callback applying the constraints to the candidates, suitable for custom
+    /// sampler hooks.
+    pub fn as_callback(&mut self) -> impl FnMut(&mut [TokenData]) -> Result<()> + '_ {
+        move |candidates| self.apply_to_candidates(candidates)
+    }
+
+    /// Advances the guide with the sampled token.
+    pub fn accept(&mut self, token_id: TokenId) -> Result<()> {
+        self.guide.advance(token_id)?;
+        Ok(())
+    }
+
+    /// Checks if the guide has reached a final state.
+    pub fn is_finished(&self) -> bool {
+        self.guide.is_finished()
+    }
+
+    fn fill_allowed(&mut self) -> Result<()> {
+        self.allowed.fill(false);
+        for token_id in self.guide.allowed_tokens()? {
+            let token_id = token_id.as_usize();
+            if token_id >= self.allowed.len() {
+                self.allowed.resize(token_id + 1, false);
+            }
+            self.allowed[token_id] = true;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::index::Index;
+
+    fn sampler() -> Sampler {
+        let mut vocabulary = Vocabulary::new(3);
+        for (token, token_id) in [("a", 0), ("b", 1), ("c", 2)] {
+            vocabulary
+                .try_insert(token, token_id)
+                .expect("Insert failed");
+        }
+        let index = Index::new("[ab]c", &vocabulary).expect("Index failed");
+        Sampler::new(Guide::new(Arc::new(index), 0))
+    }
+
+    #[test]
+    fn apply_to_logits() {
+        let mut sampler = sampler();
+        let mut logits = vec![1.0, 2.0, 3.0, 4.0, 5.0];
+        sampler.apply_to_logits(&mut logits).expect("Apply failed");
+        assert_eq!(
+            logits,
+            vec![
+                1.0,
+                2.0,
+                f32::NEG_INFINITY,
+                f32::NEG_INFINITY,
+                f32::NEG_INFINITY
+            ]
+        );
+
+        sampler.accept(TokenId::new(0)).expect("Accept failed");
+        sampler.accept(TokenId::new(2)).expect("Accept failed");
+        assert!(sampler.is_finished());
+        let mut logits = vec![1.0; 4];
+        sampler.apply_to_logits(&mut logits).expect("Apply failed");
+        assert_eq!(logits[3], 1.0);
+        assert!(logits[..3].iter().all(|l| l.is_infinite()));
+    }
+
+    #[test]
+    fn apply_to_candidates() {
+        let mut sampler = sampler();
+        let mut candidates: Vec<TokenData> = [-1, 2, 1, 7]
+            .into_iter()
+            .map(|id| TokenData {
+                id,
+                logit: 1.0,
+                p: 0.25,
+            })
+            .collect();
+        let mut callback = sampler.as_callback();
+        callback(&mut candidates).expect("Apply failed");
+        let logits: Vec<f32> = candidates.iter().map(|c| c.logit).collect();
+        assert_eq!(
+            logits,
+            vec![f32::NEG_INFINITY, f32::NEG_INFINITY, 1.0, f32::NEG_INFINITY]
+        );
+        assert_eq!(candidates[0].p, 0.0);
+    }
+}
@@ -0,0 +1,6 @@
+//! Optional integrations with inference frameworks, each one behind its own feature.
+
+#[cfg(feature = "candle")]
+pub mod candle;
+#[cfg(feature = "llama-cpp")]
+pub mod llama_cpp;
@@ -0,0 +1,254 @@
+//! Static analysis of a JSON schema against a given vocabulary.
+
+use serde_json::Value;
+
+use crate::index::TokenTable;
+use crate::prelude::*;
+use crate::vocabulary::Vocabulary;
+
+/// Literal of a JSON schema, which can't be composed from vocabulary tokens.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UntokenizableLiteral {
+    /// JSON pointer to the schema node declaring the literal.
+    pub path: String,
+    /// The literal exactly as it's expected in the generated JSON, e.g. with quotes for strings.
+    pub literal: String,
+}
+
+/// Finds `const`, `enum` and property name literals of the schema, which can't be produced
+/// by any sequence of vocabulary tokens.
+///
+/// Annotations, e.g. `default` and `examples`, don't constrain the generated JSON, so their
+/// values aren't checked.
+///
+/// Since such literals are forced by the generated regular expression, the `Index` construction
+/// would fail later on with a less specific error.
+///
+/// # Example
+///
+/// ```rust
+/// use outlines_core::prelude::*;
+/// use serde_json::json;
+///
+/// let mut vocabulary = Vocabulary::new(0);
+/// for (token, token_id) in [("\"", 1), ("a", 2), ("b", 3)] {
+///     vocabulary.try_insert(token, token_id).expect("Insert failed");
+/// }
+///
+/// let schema = json!({"enum": ["ab", "ba", "abc"]});
+/// let issues = json_schema::analyze_against_vocabulary(&schema, &vocabulary);
+/// assert_eq!(issues.len(), 1);
+/// assert_eq!(issues[0].path, "/enum/2");
+/// ```
+pub fn analyze_against_vocabulary(
+    json: &Value,
+    vocabulary: &Vocabulary,
+) -> Vec<UntokenizableLiteral> {
+    let tokens = LiteralTokens::new(vocabulary);
+    let mut issues = Vec::new();
+    collect_issues(json, String::new(), &tokens, &mut issues);
+    issues
+}
+
+/// Tokens of the vocabulary grouped by their first and their last bytes, so that a literal
+/// is only compared with the tokens, which may start or end at its bytes.
+struct LiteralTokens<'a> {
+    table: TokenTable<'a>,
+    by_last_byte: Vec<Vec<&'a Token>>,
+}
+
+impl<'a> LiteralTokens<'a> {
+    fn new(vocabulary: &'a Vocabulary) -> Self {
+        let table = TokenTable::new(vocabulary);
+        let mut by_last_byte = vec![Vec::new(); 256];
+        for token in table.tokens() {
+            if let Some(&byte) = token.last() {
+                by_last_byte[byte as usize].push(token);
+            }
+        }
+        Self {
+            table,
+            by_last_byte,
+        }
+    }
+}
+
+fn collect_issues(
+    json: &Value,
+    path: String,
+    tokens: &LiteralTokens,
+    issues: &mut Vec<UntokenizableLiteral>,
+) {
+    match json {
+        Value::Object(obj) => {
+            for (key, value) in obj {
+                let child_path = format!("{}/{}", path, escape_pointer(key));
+                match (key.as_str(), value) {
+                    // Annotations are free values, which aren't generated as such.
+                    ("default" | "examples", _) => {}
+                    ("const", value) => check_literal(value, child_path, tokens, issues),
+                    ("enum", Value::Array(values)) => {
+                        for (i, value) in values.iter().enumerate() {
+                            check_literal(value, format!("{child_path}/{i}"), tokens, issues);
+                        }
+                    }
+                    ("properties", Value::Object(properties)) => {
+                        for (name, schema) in properties {
+                            let property_path = format!("{}/{}", child_path, escape_pointer(name));
+                            let name = Value::String(name.clone());
+                            check_literal(&name, property_path.clone(), tokens, issues);
+                            collect_issues(schema, property_path, tokens, issues);
+                        }
+                    }
+                    _ => collect_issues(value, child_path, tokens, issues),
+                }
+            }
+        }
+        Value::Array(values) => {
+            for (i, value) in values.iter().enumerate() {
+                collect_issues(value, format!("{path}/{i}"), tokens, issues);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn check_literal(
+    value: &Value,
+    path: String,
+    tokens: &LiteralTokens,
+    issues: &mut Vec<UntokenizableLiteral>,
+) {
+    let literal = match value {
+        // Compound constants are separated by whitespace pattern, so only leaves are checked.
+        Value::Array(values) => {
+            for (i, value) in values.iter().enumerate() {
+                check_literal(value, format!("{path}/{i}"), tokens, issues);
+            }
+            return;
+        }
+        Value::Object(obj) => {
+            for (key, value) in obj {
+                check_literal(
+                    value,
+                    format!("{}/{}", path, escape_pointer(key)),
+                    tokens,
+                    issues,
+                );
+            }
+            return;
+        }
+        value => value.to_string(),
+    };
+    if !is_composable(literal.as_bytes(), tokens) {
+        issues.push(UntokenizableLiteral { path, literal });
+    }
+}
+
+/// Checks whether `literal` can be covered by a sequence of tokens, where the first token
+/// may start before the literal and the last one may continue after it.
+fn is_composable(literal: &[u8], tokens: &LiteralTokens) -> bool {
+    let n = literal.len();
+    if n == 0 {
+        return true;
+    }
+
+    // Positions of the literal reachable by a sequence of tokens.
+    let mut reachable = vec![false; n + 1];
+    reachable[0] = true;
+    for len in 1..=n {
+        // Token's suffix is a prefix of the literal, so the token ends with its last byte.
+        let prefix = &literal[..len];
+        if tokens.by_last_byte[prefix[len - 1] as usize]
+            .iter()
+            .any(|token| token.len() > len && token.ends_with(prefix))
+        {
+            reachable[len] = true;
+        }
+    }
+    for i in 0..n {
+        if !reachable[i] {
+            continue;
+        }
+        let rest = &literal[i..];
+        for token in tokens.table.starting_with(rest[0]) {
+            if rest.starts_with(token) {
+                reachable[i + token.len()] = true;
+            } else if token.starts_with(rest) {
+                reachable[n] = true;
+            }
+        }
+        if reachable[n] {
+            return true;
+        }
+    }
+    reachable[n]
+}
+
+pub(super) fn escape_pointer(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn vocabulary(tokens: &[&str]) -> Vocabulary {
+        let mut vocabulary = Vocabulary::new(0);
+        for (i, token) in tokens.iter().enumerate() {
+            vocabulary
+                .try_insert(*token, i as u32 + 1)
+                .expect("Insert failed");
+        }
+        vocabulary
+    }
+
+    #[test]
+    fn composable_literals() {
+        let vocabulary = vocabulary(&["\"", "na", "me", "\"x", "1"]);
+        for (literal, expected) in [
+            ("\"name\"", true),
+            ("\"na\"", true),
+            // The first token starts before the literal
+            ("x1", true),
+            // The last token continues after the literal
+            ("\"", true),
+            ("\"nam\"", false),
+            ("2", false),
+        ] {
+            assert_eq!(
+                is_composable(literal.as_bytes(), &LiteralTokens::new(&vocabulary)),
+                expected,
+                "{literal}"
+            );
+        }
+    }
+
+    #[test]
+    fn analyze_schema_literals() {
+        let vocabulary = vocabulary(&["\"", "a", "b", "1", "é"]);
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "ab": {"const": "ba"},
+                "c": {"enum": ["a", 1, 2]},
+                "b": {"anyOf": [{"const": ["a", "ü"]}, {"type": "null"}]},
+                "a": {"type": "string", "default": "ü", "examples": [{"const": "ü"}]}
+            },
+            "default": {"properties": {"ü": {}}}
+        });
+        let issues = analyze_against_vocabulary(&schema, &vocabulary);
+        let paths: Vec<&str> = issues.iter().map(|i| i.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            vec![
+                "/properties/c",
+                "/properties/c/enum/2",
+                "/properties/b/anyOf/0/const/1"
+            ]
+        );
+        assert_eq!(issues[0].literal, "\"c\"");
+    }
+}
@@ -0,0 +1,298 @@
+//! Canonical form of a JSON schema, so that equivalent schemas share a stable hash, e.g. to
+//! key a cache of compiled schemas.
+
+use rustc_hash::FxHashMap as HashMap;
+use serde_json::{Map, Value};
+
+use super::keywords::is_annotation;
+use super::parsing::resolve_ref;
+use super::report::{SCHEMA_MAPS, SUBSCHEMAS};
+
+/// Annotations, which are kept while references are left unresolved.
+const DEFINITIONS: &[&str] = &["$defs", "$id", "definitions"];
+
+/// Normalizes the JSON schema and hashes the normalized schema.
+///
+/// The keywords of each schema node are sorted and annotations, e.g. `title`, are dropped.
+/// References to a schema, which doesn't recurse into itself, are replaced by it, unless other
+/// keywords constrain them. Keywords set to their default value, e.g. `"minItems": 0` or
+/// `"required": []`, are dropped, `required` names and `type` lists are sorted and a single
+/// `type` is unwrapped. The names of `properties` keep their order, which is the order of the
+/// generated keys, as well as `enum` and `const` values.
+///
+/// Defaults are applied by dropping the keywords, which are set to them, rather than by
+/// spelling out omitted keywords: both make a schema, which states a default, equivalent to
+/// one omitting it, but the former keeps the canonical form as small as its schema and
+/// doesn't depend on the set of keywords known to the parser.
+///
+/// The hash is computed with 64-bit FNV-1a over the normalized schema, like
+/// [`Index::fingerprint`](crate::index::Index::fingerprint), so that it's stable across
+/// processes and platforms.
+///
+/// # Example
+///
+/// ```rust
+/// use outlines_core::json_schema;
+/// use serde_json::json;
+///
+/// let (first, hash) = json_schema::canonicalize(&json!({
+///     "$defs": {"name": {"type": "string"}},
+///     "properties": {"name": {"$ref": "#/$defs/name"}},
+///     "type": "object",
+/// }));
+/// let (second, other_hash) = json_schema::canonicalize(&json!({
+///     "type": "object",
+///     "title": "Person",
+///     "properties": {"name": {"type": ["string"]}},
+///     "required": [],
+/// }));
+/// assert_eq!(first, second);
+/// assert_eq!(hash, other_hash);
+/// ```
+pub fn canonicalize(schema: &Value) -> (Value, u64) {
+    let mut canonicalizer = Canonicalizer {
+        root: schema,
+        keep_definitions: false,
+        kept_refs: false,
+        expanding: Vec::new(),
+        finite: HashMap::default(),
+    };
+    let mut canonical = canonicalizer.canonical(schema);
+    if canonicalizer.kept_refs {
+        // Remaining references still need their targets.
+        canonicalizer.keep_definitions = true;
+        canonical = canonicalizer.canonical(schema);
+    }
+
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in canonical.to_string().bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    (canonical, hash)
+}
+
+struct Canonicalizer<'a> {
+    root: &'a Value,
+    /// Whether `$defs`, `definitions` and `$id` are kept for unresolved references.
+    keep_definitions: bool,
+    /// Whether some references are left unresolved.
+    kept_refs: bool,
+    /// References, which targets are being searched for recursion.
+    expanding: Vec<&'a str>,
+    /// Whether references are resolved to a finite schema, by their values.
+    finite: HashMap<&'a str, bool>,
+}
+
+impl<'a> Canonicalizer<'a> {
+    fn canonical(&mut self, node: &'a Value) -> Value {
+        let Value::Object(obj) = node else {
+            return node.clone();
+        };
+        if let Some(reference) = obj.get("$ref") {
+            let only_ref = obj.keys().all(|k| k == "$ref" || is_annotation(k));
+            if only_ref && self.is_finite(reference) {
+                if let Ok(target) = resolve_ref(self.root, reference) {
+                    return self.canonical(target);
+                }
+            }
+            self.kept_refs = true;
+        }
+
+        let mut keywords: Vec<(&String, &Value)> = obj
+            .iter()
+            .filter(|(keyword, value)| !self.is_dropped(keyword, value))
+            .collect();
+        keywords.sort_unstable_by_key(|(keyword, _)| *keyword);
+        let mut canonical = Map::with_capacity(keywords.len());
+        for (keyword, value) in keywords {
+            let value = match (keyword.as_str(), value) {
+                ("type", Value::Array(types)) => canonical_types(types),
+                ("required", Value::Array(names)) => {
+                    let mut names = names.clone();
+                    names.sort_unstable_by_key(Value::to_string);
+                    names.dedup();
+                    Value::Array(names)
+                }
+                (keyword, Value::Object(schemas)) if SCHEMA_MAPS.contains(&keyword) => {
+                    Value::Object(
+                        schemas
+                            .iter()
+                            .map(|(name, schema)| (name.clone(), self.canonical(schema)))
+                            .collect(),
+                    )
+                }
+                (keyword, Value::Array(schemas)) if SUBSCHEMAS.contains(&keyword) => Value::Array(
+                    schemas
+                        .iter()
+                        .map(|schema| self.canonical(schema))
+                        .collect(),
+                ),
+                (keyword, schema) if SUBSCHEMAS.contains(&keyword) => self.canonical(schema),
+                (_, value) => value.clone(),
+            };
+            canonical.insert(keyword.clone(), value);
+        }
+        Value::Object(canonical)
+    }
+
+    fn is_dropped(&self, keyword: &str, value: &Value) -> bool {
+        if is_annotation(keyword) {
+            return !(self.keep_definitions && DEFINITIONS.contains(&keyword));
+        }
+        match (keyword, value) {
+            ("minItems" | "minProperties", Value::Number(n)) => n.as_u64() == Some(0),
+            ("required", Value::Array(names)) => names.is_empty(),
+            _ => false,
+        }
+    }
+
+    /// Whether the schema referenced by the value of `$ref` can be resolved, without
+    /// recursing into itself.
+    fn is_finite(&mut self, reference: &'a Value) -> bool {
+        let Some(key) = reference.as_str() else {
+            return false;
+        };
+        if let Some(&finite) = self.finite.get(key) {
+            return finite;
+        }
+        if self.expanding.contains(&key) {
+            return false;
+        }
+        let Ok(target) = resolve_ref(self.root, reference) else {
+            return false;
+        };
+        let mut references = Vec::new();
+        collect_refs(target, &mut references);
+        self.expanding.push(key);
+        let finite = references
+            .into_iter()
+            .all(|reference| self.is_finite(reference));
+        self.expanding.pop();
+        self.finite.insert(key, finite);
+        finite
+    }
+}
+
+/// Collects the values of `$ref` of the schema node and of its subschemas.
+fn collect_refs<'a>(node: &'a Value, references: &mut Vec<&'a Value>) {
+    let Value::Object(obj) = node else {
+        return;
+    };
+    for (keyword, value) in obj {
+        match value {
+            reference if keyword == "$ref" => references.push(reference),
+            Value::Object(schemas) if SCHEMA_MAPS.contains(&keyword.as_str()) => {
+                for schema in schemas.values() {
+                    collect_refs(schema, references);
+                }
+            }
+            Value::Array(schemas) if SUBSCHEMAS.contains(&keyword.as_str()) => {
+                for schema in schemas {
+                    collect_refs(schema, references);
+                }
+            }
+            schema if SUBSCHEMAS.contains(&keyword.as_str()) => collect_refs(schema, references),
+            _ => {}
+        }
+    }
+}
+
+/// Sorts and deduplicates a list of types, a single type is unwrapped.
+fn canonical_types(types: &[Value]) -> Value {
+    let Some(mut names) = types.iter().map(Value::as_str).collect::<Option<Vec<_>>>() else {
+        return Value::Array(types.to_vec());
+    };
+    names.sort_unstable();
+    names.dedup();
+    match names.as_slice() {
+        [name] => Value::String(name.to_string()),
+        names => names.iter().map(|name| Value::from(*name)).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::json_schema::regex_from_value;
+
+    #[test]
+    fn canonicalize_equivalent_schemas() {
+        let expected = json!({
+            "items": {
+                "properties": {"id": {"type": "integer"}, "tags": {"type": ["null", "string"]}},
+                "required": ["id", "tags"],
+                "type": "object",
+            },
+            "type": "array",
+        });
+        let schemas = [
+            expected.clone(),
+            json!({
+                "type": "array",
+                "minItems": 0,
+                "items": {"$ref": "#/$defs/item", "description": "An item"},
+                "$defs": {
+                    "id": {"type": ["integer", "integer"]},
+                    "item": {
+                        "type": "object",
+                        "required": ["tags", "id", "tags"],
+                        "properties": {
+                            "id": {"$ref": "#/$defs/id"},
+                            "tags": {"type": ["string", "null"], "title": "Tags"},
+                        },
+                    },
+                },
+            }),
+        ];
+        let hash = canonicalize(&expected).1;
+        for schema in schemas {
+            let (canonical, canonical_hash) = canonicalize(&schema);
+            assert_eq!(canonical, expected);
+            assert_eq!(canonical_hash, hash);
+            assert_eq!(canonical.to_string(), expected.to_string());
+            assert!(regex_from_value(&canonical, None, None).is_ok());
+        }
+    }
+
+    #[test]
+    fn canonicalize_keeps_recursive_refs() {
+        let schema = json!({
+            "$defs": {"node": {
+                "properties": {"next": {"anyOf": [{"$ref": "#/$defs/node"}, {"type": "null"}]}},
+                "type": "object",
+                "title": "Node",
+            }},
+            "$ref": "#/$defs/node",
+        });
+        let (canonical, _) = canonicalize(&schema);
+        assert_eq!(
+            canonical,
+            json!({
+                "$defs": {"node": {
+                    "properties": {"next": {"anyOf": [{"$ref": "#/$defs/node"}, {"type": "null"}]}},
+                    "type": "object",
+                }},
+                "$ref": "#/$defs/node",
+            })
+        );
+        assert_eq!(
+            regex_from_value(&canonical, None, None).expect("To regex failed"),
+            regex_from_value(&schema, None, None).expect("To regex failed"),
+        );
+    }
+
+    #[test]
+    fn canonicalize_distinguishes_generation_order() {
+        let first = json!({"properties": {"a": {"type": "integer"}, "b": {"type": "integer"}}});
+        let second = json!({"properties": {"b": {"type": "integer"}, "a": {"type": "integer"}}});
+        assert_ne!(canonicalize(&first).1, canonicalize(&second).1);
+        assert_ne!(
+            canonicalize(&json!({"const": {"a": 1, "b": 2}})).1,
+            canonicalize(&json!({"const": {"b": 2, "a": 1}})).1,
+        );
+        assert_eq!(canonicalize(&first).1, canonicalize(&first).1);
+    }
+}
@@ -0,0 +1,299 @@
+//! Intersection of schema nodes, which apply to the same instance, e.g. the members of
+//! `allOf` and the keywords next to them.
+//!
+//! Keywords are combined by their meaning: types and listed values are intersected, the
+//! tightest bounds are kept, required properties and dependencies are united and subschemas
+//! of the same property are combined lazily with `allOf`, so that they are intersected once
+//! converted.
+
+use serde_json::{json, Map, Value};
+
+use super::keywords::is_annotation;
+use super::ranges::power_of_ten;
+
+/// Keywords bounding a value from below, the greatest bound is kept.
+const LOWER_BOUNDS: &[&str] = &[
+    "minLength",
+    "minItems",
+    "minProperties",
+    "minimum",
+    "exclusiveMinimum",
+    "minDigits",
+    "minDigitsInteger",
+    "minDigitsFraction",
+    "minDigitsExponent",
+];
+
+/// Keywords bounding a value from above, the least bound is kept.
+const UPPER_BOUNDS: &[&str] = &[
+    "maxLength",
+    "maxItems",
+    "maxProperties",
+    "maximum",
+    "exclusiveMaximum",
+    "maxDigits",
+    "maxDigitsInteger",
+    "maxDigitsFraction",
+    "maxDigitsExponent",
+];
+
+/// Intersects a schema node with the keywords of another one, other than `allOf` and `$ref`.
+///
+/// The first node is taken by value, so that the intersection of many nodes copies only the
+/// keywords of the other ones. Keywords declared by both nodes with values, which can't be
+/// intersected, e.g. two different patterns, keep the value of the first node and are pushed
+/// into `conflicts`. Fails with the keyword, which no value can satisfy, e.g. disjoint types.
+pub(crate) fn intersect(
+    mut node: Map<String, Value>,
+    second: &Map<String, Value>,
+    conflicts: &mut Vec<String>,
+) -> Result<Map<String, Value>, &'static str> {
+    let common = common_values(&node, second);
+    for (keyword, value) in second {
+        if keyword == "allOf" || keyword == "$ref" {
+            continue;
+        }
+        let Some(current) = node.get(keyword) else {
+            node.insert(keyword.clone(), value.clone());
+            continue;
+        };
+        if current == value || is_annotation(keyword) {
+            continue;
+        }
+        let combined = match keyword.as_str() {
+            "type" => intersect_types(current, value).ok_or("type")?,
+            "required" => unite(current, value),
+            "dependentRequired" => match (current, value) {
+                (Value::Object(current), Value::Object(value)) => {
+                    let mut dependencies = current.clone();
+                    for (name, required) in value {
+                        let united = match dependencies.get(name) {
+                            Some(existing) => unite(existing, required),
+                            None => required.clone(),
+                        };
+                        dependencies.insert(name.clone(), united);
+                    }
+                    Value::Object(dependencies)
+                }
+                _ => current.clone(),
+            },
+            "properties" | "dependentSchemas" => match (current, value) {
+                (Value::Object(current), Value::Object(value)) => {
+                    let mut properties = current.clone();
+                    for (name, schema) in value {
+                        let combined = match properties.get(name) {
+                            Some(existing) if existing != schema => both(existing, schema),
+                            _ => schema.clone(),
+                        };
+                        properties.insert(name.clone(), combined);
+                    }
+                    Value::Object(properties)
+                }
+                _ => current.clone(),
+            },
+            "items" if current.is_object() && value.is_object() => both(current, value),
+            "additionalProperties" | "unevaluatedProperties" => match (current, value) {
+                (Value::Bool(false), _) | (_, Value::Bool(false)) => Value::Bool(false),
+                (Value::Bool(true), other) | (other, Value::Bool(true)) => other.clone(),
+                _ => both(current, value),
+            },
+            // Each alternative of the first node is combined with the second one.
+            "anyOf" | "oneOf" => match current {
+                Value::Array(alternatives) => Value::Array(
+                    alternatives
+                        .iter()
+                        .map(|alternative| both(alternative, &json!({ keyword: value })))
+                        .collect(),
+                ),
+                _ => current.clone(),
+            },
+            // Listed values are intersected below.
+            "enum" | "const" => continue,
+            // Multiples of the greater power of ten are multiples of the lesser one.
+            "multipleOf"
+                if [current, value]
+                    .iter()
+                    .all(|multiple| multiple.as_f64().and_then(power_of_ten).is_some()) =>
+            {
+                tighter(current, value, f64::max)
+            }
+            keyword if LOWER_BOUNDS.contains(&keyword) => tighter(current, value, f64::max),
+            keyword if UPPER_BOUNDS.contains(&keyword) => tighter(current, value, f64::min),
+            _ => {
+                conflicts.push(keyword.clone());
+                continue;
+            }
+        };
+        node.insert(keyword.clone(), combined);
+    }
+    if let Some(common) = common {
+        if common.is_empty() {
+            return Err("enum");
+        }
+        node.remove("const");
+        node.insert("enum".to_string(), Value::Array(common));
+    }
+    Ok(node)
+}
+
+/// Combines two subschemas lazily, they are intersected once converted.
+fn both(first: &Value, second: &Value) -> Value {
+    json!({"allOf": [first, second]})
+}
+
+/// Lists the types allowed by the `type` keyword.
+fn types(value: &Value) -> Vec<&str> {
+    match value {
+        Value::String(instance_type) => vec![instance_type.as_str()],
+        Value::Array(types) => types.iter().filter_map(Value::as_str).collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn intersect_types(first: &Value, second: &Value) -> Option<Value> {
+    let second = types(second);
+    let mut common: Vec<&str> = Vec::new();
+    for instance_type in types(first) {
+        let allowed = match instance_type {
+            // Integers are numbers.
+            "number" if second.contains(&"integer") => Some("integer"),
+            "integer" if second.contains(&"number") => Some("integer"),
+            instance_type if second.contains(&instance_type) => Some(instance_type),
+            _ => None,
+        };
+        if let Some(allowed) = allowed.filter(|allowed| !common.contains(allowed)) {
+            common.push(allowed);
+        }
+    }
+    match common.as_slice() {
+        [] => None,
+        [instance_type] => Some(json!(instance_type)),
+        _ => Some(json!(common)),
+    }
+}
+
+fn unite(first: &Value, second: &Value) -> Value {
+    let mut united = first.as_array().cloned().unwrap_or_default();
+    for value in second.as_array().into_iter().flatten() {
+        if !united.contains(value) {
+            united.push(value.clone());
+        }
+    }
+    Value::Array(united)
+}
+
+fn tighter(first: &Value, second: &Value, pick: fn(f64, f64) -> f64) -> Value {
+    match (first.as_f64(), second.as_f64()) {
+        (Some(a), Some(b)) if pick(a, b) != a => second.clone(),
+        _ => first.clone(),
+    }
+}
+
+/// Lists the values allowed by `enum` or `const`, if any.
+fn listed(node: &Map<String, Value>) -> Option<Vec<&Value>> {
+    match (node.get("const"), node.get("enum")) {
+        (Some(value), _) => Some(vec![value]),
+        (None, Some(Value::Array(values))) => Some(values.iter().collect()),
+        _ => None,
+    }
+}
+
+/// Lists the values listed by both nodes, kept as `enum`, if both of them list values.
+fn common_values(first: &Map<String, Value>, second: &Map<String, Value>) -> Option<Vec<Value>> {
+    let (first, second) = (listed(first)?, listed(second)?);
+    Some(
+        first
+            .into_iter()
+            .filter(|value| second.contains(value))
+            .cloned()
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn combine(first: Value, second: Value) -> (Result<Value, &'static str>, Vec<String>) {
+        let mut conflicts = Vec::new();
+        let result = intersect(
+            first.as_object().cloned().expect("Object schema"),
+            second.as_object().expect("Object schema"),
+            &mut conflicts,
+        );
+        (result.map(Value::Object), conflicts)
+    }
+
+    #[test]
+    fn intersect_nodes() {
+        let (node, conflicts) = combine(
+            json!({
+                "type": ["number", "string"],
+                "minimum": 1,
+                "maxLength": 5,
+                "enum": [1, 2, "a"],
+                "required": ["a"],
+                "pattern": "a+",
+                "title": "First"
+            }),
+            json!({
+                "type": "integer",
+                "minimum": 3,
+                "maxLength": 7,
+                "const": 2,
+                "required": ["b", "a"],
+                "pattern": "b+",
+                "title": "Second"
+            }),
+        );
+        assert_eq!(
+            node,
+            Ok(json!({
+                "type": "integer",
+                "minimum": 3,
+                "maxLength": 5,
+                "enum": [2],
+                "required": ["a", "b"],
+                "pattern": "a+",
+                "title": "First"
+            }))
+        );
+        assert_eq!(conflicts, vec!["pattern"]);
+
+        let (node, _) = combine(
+            json!({"properties": {"a": {"type": "string"}}, "additionalProperties": true}),
+            json!({
+                "properties": {"a": {"maxLength": 2}, "b": {}},
+                "additionalProperties": {"type": "null"}
+            }),
+        );
+        assert_eq!(
+            node,
+            Ok(json!({
+                "properties": {
+                    "a": {"allOf": [{"type": "string"}, {"maxLength": 2}]},
+                    "b": {}
+                },
+                "additionalProperties": {"type": "null"}
+            }))
+        );
+
+        let (node, _) = combine(
+            json!({"anyOf": [{"type": "string"}, {"type": "null"}]}),
+            json!({"anyOf": [{"maxLength": 2}, {"type": "null"}]}),
+        );
+        let second = json!({"anyOf": [{"maxLength": 2}, {"type": "null"}]});
+        assert_eq!(
+            node,
+            Ok(json!({"anyOf": [
+                {"allOf": [{"type": "string"}, second]},
+                {"allOf": [{"type": "null"}, second]}
+            ]}))
+        );
+
+        let (node, _) = combine(json!({"type": "string"}), json!({"type": "number"}));
+        assert_eq!(node, Err("type"));
+        let (node, _) = combine(json!({"const": 1}), json!({"enum": [2, 3]}));
+        assert_eq!(node, Err("enum"));
+    }
+}
@@ -0,0 +1,1260 @@
+//! Description of the structure, which a regular expression generated from a JSON schema
+//! forces a value into, e.g. to be shown to the users of a serving UI.
+//!
+//! The regular expression is recognized by the shapes [`regex_from_value`](super::regex_from_value)
+//! generates: objects with their properties, arrays with their bounds, strings with their
+//! lengths or formats, integers and numbers with their bounds, the other JSON types and listed
+//! values. Parts, which aren't recognized, e.g. free-form objects, are described by their
+//! regular expression as is.
+//!
+//! Bounds of integers and numbers are the least and greatest values of their DFA, which are
+//! checked by generating their regular expression again, see [`ranges`](super::ranges).
+
+use std::fmt;
+
+use regex_automata::dfa::dense::{self, DFA};
+use regex_automata::dfa::Automaton;
+use regex_automata::nfa::thompson;
+use regex_automata::util::primitives::StateID;
+use regex_automata::{Anchored, MatchKind};
+use regex_syntax::hir::{Class, Hir, HirKind, Repetition};
+use regex_syntax::ParserBuilder;
+use rustc_hash::FxHashSet as HashSet;
+use serde::Serialize;
+
+use super::ranges::{self, DecimalBound};
+use super::types::*;
+use crate::{Error, Result};
+
+/// Structure of the values permitted by a regular expression.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum HumanReadableSpec {
+    Object {
+        fields: Vec<FieldSpec>,
+    },
+    Array {
+        /// Structure of the items, `None` if the array is always empty.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        items: Option<Box<HumanReadableSpec>>,
+        min_items: usize,
+        max_items: Option<usize>,
+    },
+    String {
+        min_length: usize,
+        max_length: Option<usize>,
+        /// Name of a predefined format, e.g. `date`.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        format: Option<String>,
+        /// Pattern of the content of the string, if it's neither free nor a predefined format.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pattern: Option<String>,
+    },
+    Integer {
+        /// Least integer, both bounds are included.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        minimum: Option<i64>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        maximum: Option<i64>,
+    },
+    /// Number with at most one bound of each side, like the keywords of JSON schemas.
+    Number {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        minimum: Option<f64>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        exclusive_minimum: Option<f64>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        maximum: Option<f64>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        exclusive_maximum: Option<f64>,
+    },
+    Boolean,
+    Null,
+    /// Single value, as JSON text.
+    Const {
+        value: String,
+    },
+    /// Listed values, as JSON text.
+    Enum {
+        values: Vec<String>,
+    },
+    AnyOf {
+        alternatives: Vec<HumanReadableSpec>,
+    },
+    /// Part of the regular expression, which isn't recognized.
+    Pattern {
+        regex: String,
+    },
+}
+
+/// Property of an object.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct FieldSpec {
+    pub name: String,
+    pub required: bool,
+    #[serde(flatten)]
+    pub spec: HumanReadableSpec,
+}
+
+/// Describes the structure of the values permitted by a regular expression.
+///
+/// # Example
+///
+/// ```rust
+/// # use outlines_core::Error;
+/// use outlines_core::json_schema::{self, HumanReadableSpec};
+///
+/// # fn main() -> Result<(), Error> {
+/// let schema = r#"{
+///     "type": "object",
+///     "properties": {"name": {"type": "string", "maxLength": 8}, "age": {"type": "integer"}},
+///     "required": ["name"]
+/// }"#;
+/// let regex = json_schema::regex_from_str(schema, None, None)?;
+///
+/// let spec = json_schema::describe_regex(&regex)?;
+/// assert_eq!(spec.to_string(), "{ name: string (at most 8 characters), age?: integer }");
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Fails only if the regular expression is invalid.
+pub fn describe_regex(regex: &str) -> Result<HumanReadableSpec> {
+    let describer = Describer::new();
+    let hir = describer.parse(regex)?;
+    let items = flatten(&hir);
+    Ok(match describer.value(&items, 0) {
+        Some((spec, end)) if end == items.len() => spec,
+        _ => HumanReadableSpec::Pattern {
+            regex: regex.to_string(),
+        },
+    })
+}
+
+/// Either a single byte of a literal or any other node of a concatenation.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Item<'h> {
+    Byte(u8),
+    Node(&'h Hir),
+}
+
+/// Concatenated items of a node, with literals split into bytes.
+fn flatten(hir: &Hir) -> Vec<Item<'_>> {
+    let mut items = Vec::new();
+    let mut stack = vec![hir];
+    while let Some(hir) = stack.pop() {
+        match hir.kind() {
+            HirKind::Concat(children) => stack.extend(children.iter().rev()),
+            HirKind::Literal(literal) => items.extend(literal.0.iter().copied().map(Item::Byte)),
+            _ => items.push(Item::Node(hir)),
+        }
+    }
+    items
+}
+
+/// Joins items back into a node.
+fn unflatten(items: &[Item]) -> Hir {
+    Hir::concat(
+        items
+            .iter()
+            .map(|item| match item {
+                Item::Byte(byte) => Hir::literal([*byte]),
+                Item::Node(hir) => (*hir).clone(),
+            })
+            .collect(),
+    )
+}
+
+/// Turns ASCII byte classes into Unicode ones, which are displayed without the `(?-u:)` flag.
+fn readable(hir: &Hir) -> Hir {
+    match hir.kind() {
+        HirKind::Class(Class::Bytes(class)) => match class.to_unicode_class() {
+            Some(class) => Hir::class(Class::Unicode(class)),
+            None => hir.clone(),
+        },
+        HirKind::Concat(children) => Hir::concat(children.iter().map(readable).collect()),
+        HirKind::Alternation(children) => Hir::alternation(children.iter().map(readable).collect()),
+        HirKind::Repetition(repetition) => Hir::repetition(Repetition {
+            sub: Box::new(readable(&repetition.sub)),
+            ..repetition.clone()
+        }),
+        _ => hir.clone(),
+    }
+}
+
+/// Removes capture groups, so that nodes are compared regardless of their numbering.
+fn without_captures(hir: &Hir) -> Hir {
+    match hir.kind() {
+        HirKind::Capture(capture) => without_captures(&capture.sub),
+        HirKind::Concat(children) => Hir::concat(children.iter().map(without_captures).collect()),
+        HirKind::Alternation(children) => {
+            Hir::alternation(children.iter().map(without_captures).collect())
+        }
+        HirKind::Repetition(repetition) => Hir::repetition(Repetition {
+            sub: Box::new(without_captures(&repetition.sub)),
+            ..repetition.clone()
+        }),
+        _ => hir.clone(),
+    }
+}
+
+fn is_whitespace(hir: &Hir) -> bool {
+    let is_space = |c: u32| matches!(c, 0x20 | 0x09 | 0x0A | 0x0D);
+    match hir.kind() {
+        HirKind::Empty => true,
+        HirKind::Literal(literal) => literal.0.iter().all(|&b| is_space(b.into())),
+        HirKind::Class(Class::Bytes(class)) => class
+            .ranges()
+            .iter()
+            .all(|range| (range.start()..=range.end()).all(|b| is_space(b.into()))),
+        HirKind::Class(Class::Unicode(class)) => class
+            .ranges()
+            .iter()
+            .all(|range| (range.start()..=range.end()).all(|c| is_space(c.into()))),
+        HirKind::Repetition(repetition) => is_whitespace(&repetition.sub),
+        HirKind::Concat(children) | HirKind::Alternation(children) => {
+            children.iter().all(is_whitespace)
+        }
+        _ => false,
+    }
+}
+
+/// Bounds of the repetition as `usize`.
+fn bounds(repetition: &Repetition) -> (usize, Option<usize>) {
+    (
+        repetition.min as usize,
+        repetition.max.map(|max| max as usize),
+    )
+}
+
+/// Optional node, e.g. an optional property.
+fn as_optional<'h>(item: Option<&Item<'h>>) -> Option<&'h Hir> {
+    match item {
+        Some(Item::Node(hir)) => match hir.kind() {
+            HirKind::Repetition(repetition) if repetition.min == 0 && repetition.max == Some(1) => {
+                Some(&repetition.sub)
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Bytes of the integers and numbers generated within bounds.
+const NUMERIC: &[u8] = b"-.0123456789";
+
+/// Whether the item only matches digits, signs and decimal points.
+fn is_numeric(item: &Item) -> bool {
+    fn is_numeric_hir(hir: &Hir) -> bool {
+        match hir.kind() {
+            HirKind::Empty => true,
+            HirKind::Literal(literal) => literal.0.iter().all(|b| NUMERIC.contains(b)),
+            HirKind::Class(Class::Bytes(class)) => class
+                .ranges()
+                .iter()
+                .all(|range| (range.start()..=range.end()).all(|b| NUMERIC.contains(&b))),
+            HirKind::Repetition(repetition) => is_numeric_hir(&repetition.sub),
+            HirKind::Concat(children) | HirKind::Alternation(children) => {
+                children.iter().all(is_numeric_hir)
+            }
+            _ => false,
+        }
+    }
+    match item {
+        Item::Byte(byte) => NUMERIC.contains(byte),
+        Item::Node(hir) => is_numeric_hir(hir),
+    }
+}
+
+/// Describes the value of a property, which isn't recognized, by its regular expression, up
+/// to the next property or the end of the object.
+fn unrecognized_field(items: &[Item], position: usize) -> (HumanReadableSpec, usize) {
+    let starts_property = |item: &Item| {
+        as_optional(Some(item)).is_some_and(|sub| {
+            let sub = flatten(sub);
+            sub.get(skip_whitespace(&sub, 0)) == Some(&Item::Byte(b','))
+        })
+    };
+    let (mut depth, mut quoted, mut escaped) = (0usize, false, false);
+    let mut end = position;
+    while let Some(item) = items.get(end) {
+        match item {
+            Item::Byte(_) if escaped => escaped = false,
+            Item::Byte(b'\\') if quoted => escaped = true,
+            Item::Byte(b'"') => quoted = !quoted,
+            Item::Byte(b'{' | b'[') if !quoted => depth += 1,
+            Item::Byte(b',' | b'}' | b']') if !quoted && depth == 0 => break,
+            Item::Byte(b'}' | b']') if !quoted => depth -= 1,
+            Item::Node(_) if !quoted && depth == 0 && starts_property(item) => break,
+            _ => {}
+        }
+        end += 1;
+    }
+    while end > position && skip_whitespace(items, end - 1) == end {
+        end -= 1;
+    }
+    let spec = HumanReadableSpec::Pattern {
+        regex: readable(&unflatten(&items[position..end])).to_string(),
+    };
+    (spec, end)
+}
+
+/// Magnitude of a decimal number, by its digits.
+enum Magnitude {
+    Unbounded,
+    Value { integer: Vec<u8>, fraction: Vec<u8> },
+}
+
+/// DFA of the decimal numbers of a regular expression, to find their least and greatest
+/// values.
+///
+/// The digits of an extreme value are the least or greatest ones, which lead to a match. A
+/// fraction ending with repeated nines is rounded up, e.g. `[0-2](\.[0-9]+)?` is bounded by
+/// `3`, whether the bound is excluded being left to the caller.
+struct Extremes {
+    dfa: DFA<Vec<u32>>,
+    start: StateID,
+    /// States reachable from the start, which lead to a match.
+    live: HashSet<StateID>,
+    /// States ending an integer part with `k` more digits, by `k`, up to the number of
+    /// states, after which integer parts are unbounded.
+    integer_ends: Vec<HashSet<StateID>>,
+}
+
+impl Extremes {
+    fn new(hir: &Hir) -> Option<Self> {
+        let nfa = thompson::Compiler::new().build_from_hir(hir).ok()?;
+        let dfa = dense::Builder::new()
+            .configure(dense::Config::new().match_kind(MatchKind::All))
+            .build_from_nfa(&nfa)
+            .ok()?;
+        let start = dfa.universal_start_state(Anchored::Yes)?;
+        let mut reachable = vec![start];
+        let mut i = 0;
+        while let Some(&state) = reachable.get(i) {
+            for &byte in NUMERIC {
+                let next = dfa.next_state(state, byte);
+                if !dfa.is_dead_state(next) && !reachable.contains(&next) {
+                    reachable.push(next);
+                }
+            }
+            i += 1;
+        }
+        let accepts = |state: StateID| dfa.is_match_state(dfa.next_eoi_state(state));
+        let mut live: HashSet<StateID> =
+            reachable.iter().copied().filter(|&s| accepts(s)).collect();
+        loop {
+            let before = live.len();
+            for &state in &reachable {
+                if NUMERIC
+                    .iter()
+                    .any(|&byte| live.contains(&dfa.next_state(state, byte)))
+                {
+                    live.insert(state);
+                }
+            }
+            if live.len() == before {
+                break;
+            }
+        }
+        let mut extremes = Self {
+            dfa,
+            start,
+            live,
+            integer_ends: Vec::new(),
+        };
+        let ends: HashSet<StateID> = reachable
+            .iter()
+            .copied()
+            .filter(|&state| extremes.accepts(state) || extremes.next(state, b'.').is_some())
+            .collect();
+        extremes.integer_ends.push(ends);
+        for _ in 0..reachable.len() {
+            let last = &extremes.integer_ends[extremes.integer_ends.len() - 1];
+            let ends = reachable
+                .iter()
+                .copied()
+                .filter(|&state| extremes.digits(state).any(|(_, next)| last.contains(&next)))
+                .collect();
+            extremes.integer_ends.push(ends);
+        }
+        Some(extremes)
+    }
+
+    fn next(&self, state: StateID, byte: u8) -> Option<StateID> {
+        let next = self.dfa.next_state(state, byte);
+        self.live.contains(&next).then_some(next)
+    }
+
+    fn accepts(&self, state: StateID) -> bool {
+        self.dfa.is_match_state(self.dfa.next_eoi_state(state))
+    }
+
+    /// Digits leading to a match from the state, in ascending order.
+    fn digits(&self, state: StateID) -> impl DoubleEndedIterator<Item = (u8, StateID)> + '_ {
+        (b'0'..=b'9').filter_map(move |digit| Some((digit, self.next(state, digit)?)))
+    }
+
+    /// Least and greatest values, `None` if unbounded, or `None` if they aren't recognized.
+    fn bounds(&self) -> Option<(Option<DecimalBound>, Option<DecimalBound>)> {
+        let negative = self.next(self.start, b'-');
+        let positive = self.digits(self.start).next().is_some();
+        let bound = |magnitude: Magnitude, negative: bool| match magnitude {
+            Magnitude::Unbounded => Some(None),
+            Magnitude::Value { integer, fraction } => {
+                let integer = std::str::from_utf8(&integer).ok()?.parse().ok()?;
+                let fraction = String::from_utf8(fraction).ok()?;
+                Some(Some(DecimalBound {
+                    negative: negative && (integer > 0 || !fraction.is_empty()),
+                    integer,
+                    fraction,
+                    exclusive: false,
+                }))
+            }
+        };
+        let min = match negative {
+            Some(negative) => bound(self.magnitude(negative, true)?, true)?,
+            None => bound(self.magnitude(self.start, false)?, false)?,
+        };
+        let max = match (positive, negative) {
+            (true, _) => bound(self.magnitude(self.start, true)?, false)?,
+            (false, Some(negative)) => bound(self.magnitude(negative, false)?, true)?,
+            (false, None) => return None,
+        };
+        Some((min, max))
+    }
+
+    /// Least or greatest magnitude of the numbers from the state, without sign.
+    fn magnitude(&self, mut state: StateID, greatest: bool) -> Option<Magnitude> {
+        let lengths =
+            (1..self.integer_ends.len()).filter(|&k| self.integer_ends[k].contains(&state));
+        let length = extreme(lengths, greatest)?;
+        if greatest && length + 1 == self.integer_ends.len() {
+            return Some(Magnitude::Unbounded);
+        }
+        let mut integer = Vec::with_capacity(length);
+        for remaining in (0..length).rev() {
+            let digits = self
+                .digits(state)
+                .filter(|(_, next)| self.integer_ends[remaining].contains(next));
+            let (digit, next) = extreme(digits, greatest)?;
+            integer.push(digit);
+            state = next;
+        }
+        let dot = self.next(state, b'.');
+        let (fraction, carry) = match dot {
+            Some(dot) if greatest || !self.accepts(state) => self.fraction(dot, greatest)?,
+            _ => (Vec::new(), false),
+        };
+        Some(if carry {
+            round_up(integer, fraction)
+        } else {
+            Magnitude::Value { integer, fraction }
+        })
+    }
+
+    /// Least or greatest fraction digits from the state after the decimal point, without
+    /// trailing zeros, and whether they are followed by repeated nines.
+    fn fraction(&self, mut state: StateID, greatest: bool) -> Option<(Vec<u8>, bool)> {
+        let mut fraction: Vec<u8> = Vec::new();
+        let mut seen: Vec<(StateID, usize)> = Vec::new();
+        loop {
+            if !greatest && self.accepts(state) {
+                break;
+            }
+            if let Some(&(_, start)) = seen.iter().find(|(seen, _)| *seen == state) {
+                let repeated = fraction.split_off(start);
+                if repeated.iter().all(|&digit| digit == b'0') {
+                    break;
+                }
+                if greatest && repeated.iter().all(|&digit| digit == b'9') {
+                    return Some((trimmed(fraction), true));
+                }
+                return None;
+            }
+            seen.push((state, fraction.len()));
+            match extreme(self.digits(state), greatest) {
+                Some((digit, next)) => {
+                    fraction.push(digit);
+                    state = next;
+                }
+                None => break,
+            }
+        }
+        Some((trimmed(fraction), false))
+    }
+}
+
+/// The last or the first item, of items in ascending order.
+fn extreme<T>(mut items: impl DoubleEndedIterator<Item = T>, greatest: bool) -> Option<T> {
+    if greatest {
+        items.next_back()
+    } else {
+        items.next()
+    }
+}
+
+fn trimmed(mut digits: Vec<u8>) -> Vec<u8> {
+    while digits.last() == Some(&b'0') {
+        digits.pop();
+    }
+    digits
+}
+
+/// Adds a unit of the last fraction digit, e.g. `1.7` for `1.6999…`.
+fn round_up(mut integer: Vec<u8>, mut fraction: Vec<u8>) -> Magnitude {
+    let digits = fraction.len();
+    integer.append(&mut fraction);
+    let mut carry = true;
+    for digit in integer.iter_mut().rev() {
+        if !carry {
+            break;
+        }
+        (*digit, carry) = if *digit == b'9' {
+            (b'0', true)
+        } else {
+            (*digit + 1, false)
+        };
+    }
+    if carry {
+        integer.insert(0, b'1');
+    }
+    let fraction = integer.split_off(integer.len() - digits);
+    Magnitude::Value {
+        integer,
+        fraction: trimmed(fraction),
+    }
+}
+
+/// Recognizer of the shapes of generated regular expressions.
+struct Describer {
+    /// Patterns of the JSON types and string formats with their descriptions, longer ones go
+    /// first, since some of them are prefixes of the others.
+    known: Vec<(Hir, HumanReadableSpec)>,
+    /// Pattern of a character of a string.
+    string_inner: Hir,
+}
+
+impl Describer {
+    fn new() -> Self {
+        let format = |name: &str| HumanReadableSpec::String {
+            min_length: 0,
+            max_length: None,
+            format: Some(name.to_string()),
+            pattern: None,
+        };
+        let known = [
+            (
+                NUMBER,
+                HumanReadableSpec::Number {
+                    minimum: None,
+                    exclusive_minimum: None,
+                    maximum: None,
+                    exclusive_maximum: None,
+                },
+            ),
+            (
+                INTEGER,
+                HumanReadableSpec::Integer {
+                    minimum: None,
+                    maximum: None,
+                },
+            ),
+            (BOOLEAN, HumanReadableSpec::Boolean),
+            (NULL, HumanReadableSpec::Null),
+            (DATE_TIME, format("date-time")),
+            (DATE, format("date")),
+            (TIME, format("time")),
+            (UUID, format("uuid")),
+            (URI, format("uri")),
+            (EMAIL, format("email")),
+        ]
+        .into_iter()
+        .map(|(regex, spec)| (Self::parse_known(regex), spec))
+        .collect();
+        Self {
+            known,
+            string_inner: Self::parse_known(STRING_INNER),
+        }
+    }
+
+    fn parse_known(regex: &str) -> Hir {
+        let parsed = Self::parser()
+            .parse(regex)
+            .expect("Known patterns are valid");
+        without_captures(&parsed)
+    }
+
+    /// Bytes oriented parser, so that classes like `\d` stay readable once described.
+    fn parser() -> regex_syntax::Parser {
+        ParserBuilder::new().unicode(false).utf8(false).build()
+    }
+
+    fn parse(&self, regex: &str) -> Result<Hir> {
+        let parsed = Self::parser()
+            .parse(regex)
+            .map_err(|e| Error::InvalidRegex(e.to_string().into()))?;
+        Ok(without_captures(&parsed))
+    }
+
+    /// Describes the value starting at `position`, returns its description and its end.
+    fn value(&self, items: &[Item], position: usize) -> Option<(HumanReadableSpec, usize)> {
+        for (hir, spec) in &self.known {
+            let known = flatten(hir);
+            if items[position..].starts_with(&known) {
+                return Some((spec.clone(), position + known.len()));
+            }
+        }
+        if let Some(number) = self.number(items, position) {
+            return Some(number);
+        }
+        match items.get(position)? {
+            Item::Byte(b'{') => self.object(items, position + 1),
+            Item::Byte(b'[') => self.array(items, position + 1),
+            Item::Byte(b'"') => self.string(items, position + 1),
+            Item::Byte(_) => {
+                let end = (position..items.len())
+                    .find(|&i| !matches!(items[i], Item::Byte(b) if !b",]} \t\n\r".contains(&b)))
+                    .unwrap_or(items.len());
+                Some((
+                    HumanReadableSpec::Const {
+                        value: literal(&items[position..end])?,
+                    },
+                    end,
+                ))
+            }
+            Item::Node(hir) => match hir.kind() {
+                HirKind::Alternation(alternatives) => {
+                    Some((self.alternatives(alternatives)?, position + 1))
+                }
+                _ => None,
+            },
+        }
+    }
+
+    /// Describes listed values or alternative structures.
+    fn alternatives(&self, alternatives: &[Hir]) -> Option<HumanReadableSpec> {
+        let mut values = Vec::new();
+        let mut specs = Vec::new();
+        for alternative in alternatives {
+            let items = flatten(alternative);
+            if let Some(value) = literal(&items) {
+                values.push(value.clone());
+                specs.push(HumanReadableSpec::Const { value });
+                continue;
+            }
+            match self.value(&items, 0)? {
+                (spec, end) if end == items.len() => specs.push(spec),
+                _ => return None,
+            }
+        }
+        Some(if values.len() == specs.len() {
+            HumanReadableSpec::Enum { values }
+        } else {
+            HumanReadableSpec::AnyOf {
+                alternatives: specs,
+            }
+        })
+    }
+
+    /// Describes the object after its opening brace.
+    fn object(&self, items: &[Item], mut position: usize) -> Option<(HumanReadableSpec, usize)> {
+        let mut fields = Vec::new();
+        self.fields(items, &mut position, true, &mut fields)?;
+        position = skip_whitespace(items, position);
+        (items.get(position)? == &Item::Byte(b'}'))
+            .then_some((HumanReadableSpec::Object { fields }, position + 1))
+    }
+
+    /// Collects the properties up to the closing brace, properties in optional groups or
+    /// alternatives are optional.
+    fn fields(
+        &self,
+        items: &[Item],
+        position: &mut usize,
+        required: bool,
+        fields: &mut Vec<FieldSpec>,
+    ) -> Option<()> {
+        loop {
+            *position = skip_whitespace(items, *position);
+            let group = match items.get(*position) {
+                Some(Item::Byte(b',')) => {
+                    *position += 1;
+                    continue;
+                }
+                Some(Item::Byte(b'"')) => {
+                    let (name, end) = key(items, *position + 1)?;
+                    let start = skip_whitespace(items, end);
+                    if items.get(start)? != &Item::Byte(b':') {
+                        return None;
+                    }
+                    let start = skip_whitespace(items, start + 1);
+                    let (spec, end) = self
+                        .value(items, start)
+                        .unwrap_or_else(|| unrecognized_field(items, start));
+                    if !fields.iter().any(|field| field.name == name) {
+                        fields.push(FieldSpec {
+                            name,
+                            required,
+                            spec,
+                        });
+                    }
+                    *position = end;
+                    continue;
+                }
+                Some(Item::Node(hir)) => match (as_optional(items.get(*position)), hir.kind()) {
+                    (Some(sub), _) => std::slice::from_ref(sub),
+                    (None, HirKind::Alternation(alternatives)) => alternatives.as_slice(),
+                    _ => return Some(()),
+                },
+                _ => return Some(()),
+            };
+            for hir in group {
+                let sub = flatten(hir);
+                let mut end = 0;
+                self.fields(&sub, &mut end, false, fields)?;
+                if end != sub.len() {
+                    return None;
+                }
+            }
+            *position += 1;
+        }
+    }
+
+    /// Describes the array after its opening bracket.
+    fn array(&self, items: &[Item], position: usize) -> Option<(HumanReadableSpec, usize)> {
+        let close = |spec: Option<HumanReadableSpec>, min_items, max_items, end| {
+            let end = skip_whitespace(items, end);
+            (items.get(end)? == &Item::Byte(b']')).then(|| {
+                let array = HumanReadableSpec::Array {
+                    items: spec.map(Box::new),
+                    min_items,
+                    max_items,
+                };
+                (array, end + 1)
+            })
+        };
+        let position = skip_whitespace(items, position);
+        if items.get(position)? == &Item::Byte(b']') {
+            return close(None, 0, Some(0), position);
+        }
+        // Items are optional altogether, unless the array has a minimum.
+        if let Some(sub) = as_optional(items.get(position)) {
+            let sub = flatten(sub);
+            if let Some(((spec, _, max_items), end)) = self.elements(&sub, 0) {
+                if let Some(array) = (end == sub.len())
+                    .then(|| close(Some(spec), 0, max_items, position + 1))
+                    .flatten()
+                {
+                    return Some(array);
+                }
+            }
+        }
+        let ((spec, min_items, max_items), end) = self.elements(items, position)?;
+        close(Some(spec), min_items, max_items, end)
+    }
+
+    /// Describes the first item and the repetition of the following ones.
+    #[allow(clippy::type_complexity)]
+    fn elements(
+        &self,
+        items: &[Item],
+        position: usize,
+    ) -> Option<((HumanReadableSpec, usize, Option<usize>), usize)> {
+        let (spec, mut end) = self.value(items, position)?;
+        // Following items, which are repeated exactly once, are inlined.
+        let mut count = 1;
+        loop {
+            let start = skip_whitespace(items, end);
+            if items.get(start) != Some(&Item::Byte(b',')) {
+                break;
+            }
+            match self.value(items, skip_whitespace(items, start + 1)) {
+                Some((next, next_end)) if next == spec => {
+                    count += 1;
+                    end = next_end;
+                }
+                _ => break,
+            }
+        }
+        if let Some(Item::Node(hir)) = items.get(end) {
+            if let HirKind::Repetition(repetition) = hir.kind() {
+                let sub = flatten(&repetition.sub);
+                let start = skip_whitespace(&sub, 0);
+                if sub.get(start) == Some(&Item::Byte(b',')) {
+                    if let Some((next, next_end)) =
+                        self.value(&sub, skip_whitespace(&sub, start + 1))
+                    {
+                        if next == spec && next_end == sub.len() {
+                            let (min, max) = bounds(repetition);
+                            return Some((
+                                (spec, min + count, max.map(|max| max + count)),
+                                end + 1,
+                            ));
+                        }
+                    }
+                }
+            }
+        }
+        Some(((spec, count, Some(count)), end))
+    }
+
+    /// Describes a bounded integer or number, the longest run of items of digits, signs and
+    /// decimal points, unless it's a single value.
+    fn number(&self, items: &[Item], position: usize) -> Option<(HumanReadableSpec, usize)> {
+        let end = (position..items.len())
+            .find(|&i| !is_numeric(&items[i]))
+            .unwrap_or(items.len());
+        let number = &items[position..end];
+        if number.iter().all(|item| matches!(item, Item::Byte(_))) {
+            return None;
+        }
+        let (min, max) = Extremes::new(&unflatten(number))?.bounds()?;
+        let generates = |regex: String| self.parse(&regex).is_ok_and(|hir| flatten(&hir) == number);
+
+        let is_integer = |bound: &Option<DecimalBound>| {
+            bound
+                .as_ref()
+                .is_none_or(|bound| bound.fraction.is_empty() && bound.integer <= i64::MAX as u64)
+        };
+        if is_integer(&min) && is_integer(&max) {
+            let (minimum, maximum) = (
+                min.as_ref().map(DecimalBound::ceil),
+                max.as_ref().map(DecimalBound::floor),
+            );
+            if generates(ranges::bounded_integer(minimum, maximum)) {
+                return Some((HumanReadableSpec::Integer { minimum, maximum }, end));
+            }
+        }
+        // Whether the bounds are excluded shows only in the generated regular expression.
+        for (min_exclusive, max_exclusive) in
+            [(false, false), (true, false), (false, true), (true, true)]
+        {
+            let min = min.clone().map(|min| DecimalBound {
+                exclusive: min_exclusive,
+                ..min
+            });
+            let max = max.clone().map(|max| DecimalBound {
+                exclusive: max_exclusive,
+                ..max
+            });
+            if ranges::bounded_decimal(min.as_ref(), max.as_ref(), None).is_some_and(generates) {
+                let value = |bound: &Option<DecimalBound>, exclusive: bool| {
+                    bound
+                        .as_ref()
+                        .filter(|bound| bound.exclusive == exclusive)
+                        .and_then(DecimalBound::to_f64)
+                };
+                let spec = HumanReadableSpec::Number {
+                    minimum: value(&min, false),
+                    exclusive_minimum: value(&min, true),
+                    maximum: value(&max, false),
+                    exclusive_maximum: value(&max, true),
+                };
+                return Some((spec, end));
+            }
+        }
+        None
+    }
+
+    /// Describes the string after its opening quote.
+    fn string(&self, items: &[Item], position: usize) -> Option<(HumanReadableSpec, usize)> {
+        if let (Some(Item::Node(hir)), Some(Item::Byte(b'"'))) =
+            (items.get(position), items.get(position + 1))
+        {
+            if let HirKind::Repetition(repetition) = hir.kind() {
+                if *repetition.sub == self.string_inner {
+                    let (min_length, max_length) = bounds(repetition);
+                    let spec = HumanReadableSpec::String {
+                        min_length,
+                        max_length,
+                        format: None,
+                        pattern: None,
+                    };
+                    return Some((spec, position + 2));
+                }
+            }
+        }
+        if let Some((value, end)) = key(items, position) {
+            let spec = HumanReadableSpec::Const {
+                value: serde_json::to_string(&value).ok()?,
+            };
+            return Some((spec, end));
+        }
+        // Closing quote of a string `pattern` is the first quote outside of its nodes.
+        let end = (position..items.len()).find(|&i| items[i] == Item::Byte(b'"'))?;
+        let spec = HumanReadableSpec::String {
+            min_length: 0,
+            max_length: None,
+            format: None,
+            pattern: Some(readable(&unflatten(&items[position..end])).to_string()),
+        };
+        Some((spec, end + 1))
+    }
+}
+
+fn skip_whitespace(items: &[Item], mut position: usize) -> usize {
+    while let Some(item) = items.get(position) {
+        let is_whitespace = match item {
+            Item::Byte(byte) => byte.is_ascii_whitespace(),
+            Item::Node(hir) => is_whitespace(hir),
+        };
+        if !is_whitespace {
+            break;
+        }
+        position += 1;
+    }
+    position
+}
+
+/// Text of the items, if all of them are literal bytes.
+fn literal(items: &[Item]) -> Option<String> {
+    let bytes = items
+        .iter()
+        .map(|item| match item {
+            Item::Byte(byte) => Some(*byte),
+            Item::Node(_) => None,
+        })
+        .collect::<Option<Vec<u8>>>()?;
+    (!bytes.is_empty())
+        .then(|| String::from_utf8(bytes).ok())
+        .flatten()
+}
+
+/// Decodes a literal JSON string after its opening quote, returns it and its end.
+fn key(items: &[Item], position: usize) -> Option<(String, usize)> {
+    let mut end = position;
+    loop {
+        match items.get(end)? {
+            Item::Byte(b'"') => break,
+            Item::Byte(b'\\') => end += 2,
+            Item::Byte(_) => end += 1,
+            Item::Node(_) => return None,
+        }
+    }
+    let text = format!("\"{}\"", literal(&items[position..end]).unwrap_or_default());
+    Some((serde_json::from_str(&text).ok()?, end + 1))
+}
+
+impl fmt::Display for HumanReadableSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Object { fields } if fields.is_empty() => write!(f, "{{}}"),
+            Self::Object { fields } => {
+                write!(f, "{{ ")?;
+                for (i, field) in fields.iter().enumerate() {
+                    let separator = if i == 0 { "" } else { ", " };
+                    let optional = if field.required { "" } else { "?" };
+                    write!(f, "{separator}{}{optional}: {}", field.name, field.spec)?;
+                }
+                write!(f, " }}")
+            }
+            Self::Array {
+                items,
+                min_items,
+                max_items,
+            } => {
+                write!(f, "array")?;
+                if let Some(items) = items {
+                    write!(f, " of {items}")?;
+                }
+                write_bounds(f, *min_items, *max_items, "items")
+            }
+            Self::String {
+                min_length,
+                max_length,
+                format,
+                pattern,
+            } => {
+                write!(f, "string")?;
+                if let Some(format) = format {
+                    write!(f, " ({format})")?;
+                }
+                if let Some(pattern) = pattern {
+                    write!(f, " matching /{pattern}/")?;
+                }
+                write_bounds(f, *min_length, *max_length, "characters")
+            }
+            Self::Integer { minimum, maximum } => {
+                write!(f, "integer")?;
+                write_range(f, (*minimum, false), (*maximum, false))
+            }
+            Self::Number {
+                minimum,
+                exclusive_minimum,
+                maximum,
+                exclusive_maximum,
+            } => {
+                write!(f, "number")?;
+                write_range(
+                    f,
+                    (minimum.or(*exclusive_minimum), exclusive_minimum.is_some()),
+                    (maximum.or(*exclusive_maximum), exclusive_maximum.is_some()),
+                )
+            }
+            Self::Boolean => write!(f, "boolean"),
+            Self::Null => write!(f, "null"),
+            Self::Const { value } => write!(f, "{value}"),
+            Self::Enum { values } => write!(f, "{}", values.join(" | ")),
+            Self::AnyOf { alternatives } => {
+                for (i, alternative) in alternatives.iter().enumerate() {
+                    let separator = if i == 0 { "" } else { " | " };
+                    write!(f, "{separator}{alternative}")?;
+                }
+                Ok(())
+            }
+            Self::Pattern { regex } => write!(f, "/{regex}/"),
+        }
+    }
+}
+
+fn write_bounds(
+    f: &mut fmt::Formatter<'_>,
+    min: usize,
+    max: Option<usize>,
+    unit: &str,
+) -> fmt::Result {
+    match (min, max) {
+        (0, None) => Ok(()),
+        (min, None) => write!(f, " (at least {min} {unit})"),
+        (0, Some(max)) => write!(f, " (at most {max} {unit})"),
+        (min, Some(max)) if min == max => write!(f, " (exactly {min} {unit})"),
+        (min, Some(max)) => write!(f, " ({min} to {max} {unit})"),
+    }
+}
+
+/// Writes the bounds of a value, each one with whether it's excluded.
+fn write_range<T: fmt::Display>(
+    f: &mut fmt::Formatter<'_>,
+    (min, min_exclusive): (Option<T>, bool),
+    (max, max_exclusive): (Option<T>, bool),
+) -> fmt::Result {
+    if let (Some(min), Some(max), false, false) = (&min, &max, min_exclusive, max_exclusive) {
+        return write!(f, " ({min} to {max})");
+    }
+    let min = min.map(|min| match min_exclusive {
+        true => format!("greater than {min}"),
+        false => format!("at least {min}"),
+    });
+    let max = max.map(|max| match max_exclusive {
+        true => format!("less than {max}"),
+        false => format!("at most {max}"),
+    });
+    match (min, max) {
+        (None, None) => Ok(()),
+        (Some(bound), None) | (None, Some(bound)) => write!(f, " ({bound})"),
+        (Some(min), Some(max)) => write!(f, " ({min}, {max})"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::json_schema::{regex_from_value, regex_from_value_with_options, SchemaOptions};
+
+    fn describe(schema: serde_json::Value) -> HumanReadableSpec {
+        let regex = regex_from_value(&schema, None, None).expect("To regex failed");
+        describe_regex(&regex).expect("Describe failed")
+    }
+
+    #[test]
+    fn describe_object() {
+        let spec = describe(json!({
+            "type": "object",
+            "properties": {
+                "name": {"type": "string", "maxLength": 5},
+                "age": {"type": "integer"},
+                "tags": {"type": "array", "items": {"type": "string"}, "maxItems": 3},
+                "kind": {"enum": ["a", "b"]},
+                "born": {"type": "string", "format": "date"},
+                "score": {"anyOf": [{"type": "null"}, {"type": "number"}]},
+                "point": {
+                    "type": "object",
+                    "properties": {"x": {"type": "boolean"}},
+                    "required": ["x"]
+                }
+            },
+            "required": ["name", "point"]
+        }));
+        assert_eq!(
+            spec.to_string(),
+            "{ name: string (at most 5 characters), age?: integer, \
+             tags?: array of string (at most 3 items), kind?: \"a\" | \"b\", \
+             born?: string (date), score?: null | number, point: { x: boolean } }"
+        );
+
+        let HumanReadableSpec::Object { fields } = &spec else {
+            panic!("Object expected, got {spec:?}");
+        };
+        assert_eq!(
+            fields[3],
+            FieldSpec {
+                name: "kind".to_string(),
+                required: false,
+                spec: HumanReadableSpec::Enum {
+                    values: vec!["\"a\"".to_string(), "\"b\"".to_string()]
+                },
+            }
+        );
+        assert_eq!(
+            serde_json::to_value(&fields[0]).expect("Serialization failed"),
+            json!({
+                "name": "name",
+                "required": true,
+                "type": "string",
+                "min_length": 0,
+                "max_length": 5
+            })
+        );
+    }
+
+    #[test]
+    fn describe_optional_properties() {
+        let spec = describe(json!({
+            "type": "object",
+            "properties": {
+                "a": {"type": "integer"},
+                "b": {"type": "string", "minLength": 2},
+                "c": {"type": "array", "items": {"type": "integer"}, "minItems": 2}
+            }
+        }));
+        assert_eq!(
+            spec.to_string(),
+            "{ a?: integer, b?: string (at least 2 characters), \
+             c?: array of integer (at least 2 items) }"
+        );
+    }
+
+    #[test]
+    fn describe_values() {
+        assert_eq!(describe(json!({"const": "x"})).to_string(), "\"x\"");
+        assert_eq!(
+            describe(json!({"enum": [1, "a", null]})),
+            HumanReadableSpec::Enum {
+                values: vec!["1".to_string(), "\"a\"".to_string(), "null".to_string()]
+            }
+        );
+        assert_eq!(
+            describe(json!({"type": "string", "pattern": "[a-z]+"})).to_string(),
+            "string matching /[a-z]+/"
+        );
+        assert_eq!(
+            describe(
+                json!({"type": "array", "items": {"type": "integer"}, "minItems": 2, "maxItems": 2})
+            )
+            .to_string(),
+            "array of integer (exactly 2 items)"
+        );
+
+        // Structure doesn't depend on whitespace.
+        let schema =
+            json!({"type": "object", "properties": {"a": {"type": "integer"}}, "required": ["a"]});
+        for options in [
+            SchemaOptions {
+                canonical: true,
+                ..Default::default()
+            },
+            SchemaOptions {
+                whitespace_pattern: Some(r"[\n\t ]*".to_string()),
+                ..Default::default()
+            },
+        ] {
+            let regex = regex_from_value_with_options(&schema, &options).expect("To regex failed");
+            let spec = describe_regex(&regex).expect("Describe failed");
+            assert_eq!(spec.to_string(), "{ a: integer }");
+        }
+    }
+
+    #[test]
+    fn describe_numbers() {
+        for (schema, expected) in [
+            (json!({"minimum": 0, "maximum": 120}), "integer (0 to 120)"),
+            (json!({"exclusiveMinimum": 5}), "integer (at least 6)"),
+            (
+                json!({"minimum": 100, "maximum": 199}),
+                "integer (100 to 199)",
+            ),
+            (json!({"maximum": -3}), "integer (at most -3)"),
+            (
+                json!({"minimum": -40, "maximum": 40}),
+                "integer (-40 to 40)",
+            ),
+        ] {
+            let mut schema = schema;
+            schema["type"] = json!("integer");
+            assert_eq!(describe(schema).to_string(), expected);
+        }
+        for (schema, expected) in [
+            (json!({"minimum": 1.5, "maximum": 3}), "number (1.5 to 3)"),
+            (
+                json!({"exclusiveMinimum": -2.25, "maximum": 10}),
+                "number (greater than -2.25, at most 10)",
+            ),
+            (json!({"minimum": 0}), "number (at least 0)"),
+            (json!({"exclusiveMaximum": 0}), "number (less than 0)"),
+            (json!({"maximum": 1.75}), "number (at most 1.75)"),
+            (
+                json!({"minimum": 1.75, "exclusiveMaximum": 2}),
+                "number (at least 1.75, less than 2)",
+            ),
+        ] {
+            let mut schema = schema;
+            schema["type"] = json!("number");
+            assert_eq!(describe(schema).to_string(), expected);
+        }
+        assert_eq!(
+            serde_json::to_value(describe(
+                json!({"type": "number", "exclusiveMinimum": 0.5, "maximum": 2})
+            ))
+            .expect("Serialization failed"),
+            json!({"type": "number", "exclusive_minimum": 0.5, "maximum": 2.0})
+        );
+    }
+
+    #[test]
+    fn describe_bounded_fields() {
+        let spec = describe(json!({
+            "type": "object",
+            "properties": {
+                "age": {"type": "integer", "minimum": 0, "maximum": 120},
+                "price": {"type": "number", "minimum": 0, "multipleOf": 0.01},
+                "name": {"type": "string"}
+            },
+            "required": ["age", "price"]
+        }));
+        // Numbers with limited fraction digits aren't recognized, unlike the other fields.
+        let HumanReadableSpec::Object { fields } = &spec else {
+            panic!("Object expected, got {spec:?}");
+        };
+        let names: Vec<&str> = fields.iter().map(|field| field.name.as_str()).collect();
+        assert_eq!(names, ["age", "price", "name"]);
+        assert_eq!(
+            fields[0].spec,
+            HumanReadableSpec::Integer {
+                minimum: Some(0),
+                maximum: Some(120)
+            }
+        );
+        let HumanReadableSpec::Pattern { regex } = &fields[1].spec else {
+            panic!("Pattern expected, got {:?}", fields[1].spec);
+        };
+        assert!(regex.contains(r"\.[0-9]{1,2}"), "{regex}");
+        assert_eq!(fields[2].spec.to_string(), "string");
+    }
+
+    #[test]
+    fn describe_unrecognized() {
+        assert_eq!(
+            describe_regex("[a-z]+@example").expect("Describe failed"),
+            HumanReadableSpec::Pattern {
+                regex: "[a-z]+@example".to_string()
+            }
+        );
+        assert!(matches!(
+            describe_regex("(unclosed"),
+            Err(Error::InvalidRegex(_))
+        ));
+    }
+}
@@ -0,0 +1,168 @@
+//! Validation of `enum` and `const` values against the sibling keywords of their node.
+//!
+//! Listed values take precedence over the other keywords of the node, which only exclude
+//! the values violating them, e.g. `{"type": "string", "format": "date", "enum": [...]}`
+//! generates the listed dates only.
+
+use regex::Regex;
+use rustc_hash::FxHashMap as HashMap;
+use serde_json::{Map, Value};
+
+use super::pattern::transpile_pattern;
+use super::ranges::format_range;
+use super::types::FormatType;
+
+/// Sibling keywords, which `enum` and `const` values are validated against.
+pub(crate) const VALIDATED_KEYWORDS: &[&str] = &[
+    "type",
+    "format",
+    "pattern",
+    "minLength",
+    "maxLength",
+    "minimum",
+    "maximum",
+    "exclusiveMinimum",
+    "exclusiveMaximum",
+    "multipleOf",
+];
+
+/// Returns the first sibling keyword of the node, which the value violates.
+pub(crate) fn violated_keyword(
+    obj: &Map<String, Value>,
+    value: &Value,
+    formats: Option<&HashMap<String, String>>,
+) -> Option<&'static str> {
+    VALIDATED_KEYWORDS.iter().copied().find(|keyword| {
+        obj.get(*keyword)
+            .is_some_and(|constraint| !satisfies(keyword, constraint, value, formats))
+    })
+}
+
+/// Checks the value against a keyword, constraints which can't be checked, e.g. an invalid
+/// pattern or an unknown format, are satisfied.
+fn satisfies(
+    keyword: &str,
+    constraint: &Value,
+    value: &Value,
+    formats: Option<&HashMap<String, String>>,
+) -> bool {
+    match (keyword, value) {
+        ("type", _) => match constraint {
+            Value::String(instance_type) => has_type(value, instance_type),
+            Value::Array(types) => types
+                .iter()
+                .filter_map(Value::as_str)
+                .any(|instance_type| has_type(value, instance_type)),
+            _ => true,
+        },
+        ("format", Value::String(string)) => {
+            let Some(format) = constraint.as_str() else {
+                return true;
+            };
+            // Custom formats match the content, built-in ones the quoted string.
+            if let Some(pattern) = formats.and_then(|formats| formats.get(format)) {
+                full_match(pattern, string)
+            } else if let Some(format_type) = FormatType::from_str(format) {
+                let quoted = serde_json::to_string(string).unwrap_or_default();
+                full_match(format_type.to_regex(), &quoted)
+            } else {
+                true
+            }
+        }
+        ("format", Value::Number(number)) => {
+            constraint
+                .as_str()
+                .and_then(format_range)
+                .is_none_or(|(min, max)| match number.as_i64() {
+                    Some(number) => (min..=max).contains(&number),
+                    // Beyond `i64`, unless it's a float.
+                    None => !number.is_u64(),
+                })
+        }
+        ("pattern", Value::String(string)) => constraint
+            .as_str()
+            .and_then(|pattern| Regex::new(&transpile_pattern(pattern).pattern).ok())
+            .is_none_or(|re| re.is_match(string)),
+        ("minLength", Value::String(string)) => constraint
+            .as_u64()
+            .is_none_or(|min| string.chars().count() as u64 >= min),
+        ("maxLength", Value::String(string)) => constraint
+            .as_u64()
+            .is_none_or(|max| string.chars().count() as u64 <= max),
+        ("minimum", Value::Number(_)) => compare(value, constraint, |v, bound| v >= bound),
+        ("maximum", Value::Number(_)) => compare(value, constraint, |v, bound| v <= bound),
+        ("exclusiveMinimum", Value::Number(_)) => compare(value, constraint, |v, bound| v > bound),
+        ("exclusiveMaximum", Value::Number(_)) => compare(value, constraint, |v, bound| v < bound),
+        ("multipleOf", Value::Number(_)) => compare(value, constraint, |v, multiple| {
+            let quotient = v / multiple;
+            multiple <= 0.0 || (quotient - quotient.round()).abs() < 1e-9
+        }),
+        _ => true,
+    }
+}
+
+fn has_type(value: &Value, instance_type: &str) -> bool {
+    match instance_type {
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        "integer" => {
+            value.is_i64() || value.is_u64() || value.as_f64().is_some_and(|v| v.fract() == 0.0)
+        }
+        "boolean" => value.is_boolean(),
+        "null" => value.is_null(),
+        "array" => value.is_array(),
+        "object" => value.is_object(),
+        _ => true,
+    }
+}
+
+fn full_match(pattern: &str, string: &str) -> bool {
+    Regex::new(&format!("^(?:{pattern})$")).map_or(true, |re| re.is_match(string))
+}
+
+fn compare(value: &Value, bound: &Value, check: impl Fn(f64, f64) -> bool) -> bool {
+    match (value.as_f64(), bound.as_f64()) {
+        (Some(value), Some(bound)) => check(value, bound),
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn validate_values() {
+        let schema = json!({
+            "type": ["string", "integer"],
+            "format": "date",
+            "maxLength": 10,
+            "minimum": 0
+        });
+        let obj = schema.as_object().expect("Object schema");
+        for (value, expected) in [
+            (json!("2024-01-31"), None),
+            (json!("tomorrow"), Some("format")),
+            (json!(3), None),
+            (json!(2.0), None),
+            (json!(-1), Some("minimum")),
+            (json!(2.5), Some("type")),
+            (json!(null), Some("type")),
+        ] {
+            assert_eq!(violated_keyword(obj, &value, None), expected, "{value}");
+        }
+
+        let schema = json!({"format": "sku", "pattern": "^[A-Z]", "minLength": 3});
+        let obj = schema.as_object().expect("Object schema");
+        let formats = HashMap::from_iter([("sku".to_string(), "[A-Z]+-[0-9]+".to_string())]);
+        assert_eq!(violated_keyword(obj, &json!("AB-1"), Some(&formats)), None);
+        assert_eq!(
+            violated_keyword(obj, &json!("AB1"), Some(&formats)),
+            Some("format")
+        );
+        assert_eq!(violated_keyword(obj, &json!("A1"), None), Some("minLength"));
+        assert_eq!(violated_keyword(obj, &json!("ab-1"), None), Some("pattern"));
+    }
+}
@@ -0,0 +1,167 @@
+//! Sampling hints of the fields of a schema, declared by `x-outlines-` keywords, e.g.
+//! `"x-outlines-temperature": 0.2` on a property, which engines apply as they see fit.
+
+use std::collections::BTreeMap;
+
+use serde_json::{Map, Value};
+
+use super::parsing::resolve_ref;
+use crate::Result;
+
+/// Prefix of the keywords declaring sampling hints of a field.
+pub const HINT_PREFIX: &str = "x-outlines-";
+
+/// Collects the sampling hints of the fields of the JSON schema, by field path.
+///
+/// Paths are named like [`FieldPaths`](crate::fields::FieldPaths) does: keys of the objects
+/// joined by dots, with `[]` for the items of arrays. Hints are keyed by their keyword
+/// without [`HINT_PREFIX`], e.g. `temperature`, and are inherited by the nested fields, which
+/// may override them. Subschemas of `allOf`, `anyOf`, `oneOf` and `$ref` declare hints of the
+/// same field, the first declaration of a hint wins.
+///
+/// # Example
+///
+/// ```rust
+/// # use outlines_core::Error;
+/// use outlines_core::json_schema;
+/// use serde_json::json;
+///
+/// # fn main() -> Result<(), Error> {
+/// let schema = json!({
+///     "type": "object",
+///     "properties": {
+///         "summary": {"type": "string", "x-outlines-temperature": 0.9},
+///         "id": {"type": "integer", "x-outlines-temperature": 0}
+///     }
+/// });
+/// let hints = json_schema::field_hints(&schema)?;
+/// assert_eq!(hints["summary"]["temperature"], json!(0.9));
+/// assert_eq!(hints["id"]["temperature"], json!(0));
+/// # Ok(())
+/// # }
+/// ```
+pub fn field_hints(schema: &Value) -> Result<BTreeMap<String, Map<String, Value>>> {
+    let mut hints = BTreeMap::new();
+    collect(schema, schema, "", &Map::new(), &mut Vec::new(), &mut hints)?;
+    Ok(hints)
+}
+
+/// Collects the hints of the node at the field `path` and of its nested fields.
+fn collect<'a>(
+    root: &'a Value,
+    node: &'a Value,
+    path: &str,
+    inherited: &Map<String, Value>,
+    references: &mut Vec<&'a Value>,
+    hints: &mut BTreeMap<String, Map<String, Value>>,
+) -> Result<()> {
+    let Value::Object(obj) = node else {
+        return Ok(());
+    };
+    let mut own = inherited.clone();
+    for (keyword, value) in obj {
+        if let Some(hint) = keyword.strip_prefix(HINT_PREFIX) {
+            own.insert(hint.to_string(), value.clone());
+        }
+    }
+    if !path.is_empty() && !own.is_empty() {
+        let field = hints.entry(path.to_string()).or_default();
+        for (hint, value) in &own {
+            field.entry(hint.clone()).or_insert_with(|| value.clone());
+        }
+    }
+
+    if let Some(reference) = obj.get("$ref") {
+        // Recursive definitions declare the same hints again.
+        if !references.contains(&reference) {
+            references.push(reference);
+            collect(
+                root,
+                resolve_ref(root, reference)?,
+                path,
+                &own,
+                references,
+                hints,
+            )?;
+            references.pop();
+        }
+    }
+    for keyword in ["allOf", "anyOf", "oneOf"] {
+        if let Some(Value::Array(subschemas)) = obj.get(keyword) {
+            for subschema in subschemas {
+                collect(root, subschema, path, &own, references, hints)?;
+            }
+        }
+    }
+    if let Some(Value::Object(properties)) = obj.get("properties") {
+        for (name, property) in properties {
+            let path = match path {
+                "" => name.clone(),
+                path => format!("{path}.{name}"),
+            };
+            collect(root, property, &path, &own, references, hints)?;
+        }
+    }
+    let items = obj.get("prefixItems").and_then(Value::as_array);
+    for item in items
+        .into_iter()
+        .flatten()
+        .chain(obj.get("items").filter(|items| items.is_object()))
+    {
+        collect(root, item, &format!("{path}[]"), &own, references, hints)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn collect_hints() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "reply": {
+                    "type": "object",
+                    "x-outlines-temperature": 0.7,
+                    "x-outlines-top_p": 0.9,
+                    "properties": {
+                        "text": {"type": "string"},
+                        "code": {"type": "string", "x-outlines-temperature": 0.1}
+                    }
+                },
+                "steps": {"type": "array", "items": {"$ref": "#/$defs/step"}},
+                "plain": {"type": "integer"}
+            },
+            "$defs": {
+                "step": {
+                    "x-outlines-temperature": 0.3,
+                    "anyOf": [
+                        {"type": "string", "x-outlines-temperature": 0.5},
+                        {"$ref": "#/$defs/step"}
+                    ]
+                }
+            }
+        });
+        let hints = field_hints(&schema).expect("Hints failed");
+        assert_eq!(
+            hints.keys().collect::<Vec<_>>(),
+            ["reply", "reply.code", "reply.text", "steps[]"]
+        );
+        assert_eq!(
+            hints["reply"],
+            *json!({"temperature": 0.7, "top_p": 0.9})
+                .as_object()
+                .expect("Object expected")
+        );
+        assert_eq!(hints["reply.code"]["temperature"], json!(0.1));
+        assert_eq!(hints["reply.code"]["top_p"], json!(0.9));
+        assert_eq!(hints["reply.text"]["temperature"], json!(0.7));
+        assert_eq!(hints["steps[]"]["temperature"], json!(0.3));
+
+        assert!(field_hints(&json!({"$ref": "#/$defs/missing"})).is_err());
+    }
+}
@@ -0,0 +1,349 @@
+//! Detection of schema keywords, which are ignored by the regular expression generation.
+
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use super::analysis::escape_pointer;
+use super::enums::VALIDATED_KEYWORDS;
+use super::ranges::format_range;
+
+/// Keywords, which only annotate a schema without constraining its instances.
+const ANNOTATIONS: &[&str] = &[
+    "$anchor",
+    "$comment",
+    "$defs",
+    "$id",
+    "$schema",
+    "contentEncoding",
+    "contentMediaType",
+    "default",
+    "definitions",
+    "deprecated",
+    "description",
+    "examples",
+    "readOnly",
+    "title",
+    "writeOnly",
+];
+
+/// Keywords bounding the digits of a number, which take precedence over its bounds.
+const NUMBER_DIGITS: &[&str] = &[
+    "minDigitsInteger",
+    "maxDigitsInteger",
+    "minDigitsFraction",
+    "maxDigitsFraction",
+    "minDigitsExponent",
+    "maxDigitsExponent",
+];
+
+/// Keywords bounding the value of an integer or a number, and restricting it to multiples.
+const NUMERIC_BOUNDS: &[&str] = &[
+    "minimum",
+    "exclusiveMinimum",
+    "maximum",
+    "exclusiveMaximum",
+    "multipleOf",
+];
+
+/// How to handle keywords, which constrain a schema but are ignored by the regular
+/// expression generation, so that the generated values may violate them.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum KeywordPolicy {
+    /// Fails with [`Error::UnsupportedKeyword`](crate::Error::UnsupportedKeyword).
+    Strict,
+    /// Reports ignored keywords into the collector and carries on.
+    Warn(WarningCollector),
+    /// Silently ignores them.
+    #[default]
+    Lenient,
+}
+
+/// Issue of the schema found by the regular expression generation.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SchemaWarning {
+    /// JSON pointer to the schema node declaring the keyword, or to the excluded value.
+    pub path: String,
+    /// The ignored keyword, or the keyword violated by the excluded value.
+    pub keyword: String,
+    /// What happened to the keyword.
+    pub kind: WarningKind,
+    /// The construct of a `pattern`, which is changed by the translation of the pattern.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+/// Kind of a [`SchemaWarning`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WarningKind {
+    /// The keyword is not supported and is ignored.
+    IgnoredKeyword,
+    /// The value of `enum` or `const` violates a sibling keyword and is never generated.
+    ExcludedEnumValue,
+    /// A construct of the pattern is replaced by an equivalent one, see
+    /// [`transpile_pattern`](super::transpile_pattern).
+    RewrittenPatternConstruct,
+    /// A construct of the pattern is not supported and is dropped.
+    DroppedPatternConstruct,
+}
+
+impl std::fmt::Display for SchemaWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.kind {
+            WarningKind::IgnoredKeyword => write!(
+                f,
+                "Keyword '{}' at '{}' is not supported and is ignored",
+                self.keyword, self.path
+            ),
+            WarningKind::ExcludedEnumValue => write!(
+                f,
+                "Value at '{}' violates keyword '{}' and is excluded",
+                self.path, self.keyword
+            ),
+            WarningKind::RewrittenPatternConstruct => write!(
+                f,
+                "Construct '{}' of the pattern at '{}' is rewritten",
+                self.detail.as_deref().unwrap_or_default(),
+                self.path
+            ),
+            WarningKind::DroppedPatternConstruct => write!(
+                f,
+                "Construct '{}' of the pattern at '{}' is not supported and is dropped",
+                self.detail.as_deref().unwrap_or_default(),
+                self.path
+            ),
+        }
+    }
+}
+
+/// Shared collector of [`SchemaWarning`], clones of the collector share the warnings.
+#[derive(Clone, Debug, Default)]
+pub struct WarningCollector(Arc<Mutex<Vec<SchemaWarning>>>);
+
+impl PartialEq for WarningCollector {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl WarningCollector {
+    /// Creates an empty collector.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns collected warnings, in the order they were reported.
+    pub fn warnings(&self) -> Vec<SchemaWarning> {
+        self.lock().clone()
+    }
+
+    /// Returns collected warnings and clears the collector.
+    pub fn take(&self) -> Vec<SchemaWarning> {
+        std::mem::take(&mut *self.lock())
+    }
+
+    /// Reports a warning, unless the same one was already reported.
+    pub(crate) fn report(&self, warning: SchemaWarning) {
+        let mut warnings = self.lock();
+        if !warnings.contains(&warning) {
+            warnings.push(warning);
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<SchemaWarning>> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Lists constraining keywords of the schema node, which are ignored when the node is
+/// converted to a regular expression. Mirrors the keyword dispatch of the parser.
+pub(crate) fn ignored_keywords(obj: &Map<String, Value>) -> Vec<&str> {
+    if ["allOf", "anyOf", "oneOf", "$ref"]
+        .into_iter()
+        .any(|k| obj.contains_key(k))
+    {
+        // Sibling keywords are combined with the subschemas, which are checked themselves.
+        return Vec::new();
+    }
+    let honored: Vec<&str> =
+        if let Some(keyword) = ["enum", "const"].into_iter().find(|k| obj.contains_key(*k)) {
+            // Listed values are validated against the sibling keywords.
+            let mut honored = vec![keyword];
+            honored.extend(VALIDATED_KEYWORDS);
+            honored
+        } else if obj.contains_key("properties") {
+            let mut honored = vec![
+                "properties",
+                "required",
+                "type",
+                "dependentRequired",
+                "dependentSchemas",
+                "propertyNames",
+            ];
+            // Properties not listed are never allowed.
+            for keyword in ["additionalProperties", "unevaluatedProperties"] {
+                if obj.get(keyword) == Some(&Value::Bool(false)) {
+                    honored.push(keyword);
+                }
+            }
+            honored
+        } else if obj.contains_key("prefixItems") {
+            if obj.contains_key("items") || obj.contains_key("unevaluatedItems") {
+                vec![
+                    "prefixItems",
+                    "type",
+                    "items",
+                    "unevaluatedItems",
+                    "minItems",
+                    "maxItems",
+                ]
+            } else {
+                vec!["prefixItems", "type"]
+            }
+        } else {
+            match obj.get("type") {
+                Some(Value::String(instance_type)) => honored_by_type(instance_type, obj),
+                Some(Value::Array(types)) => types
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .flat_map(|instance_type| honored_by_type(instance_type, obj))
+                    .collect(),
+                _ => Vec::new(),
+            }
+        };
+    obj.keys()
+        .map(String::as_str)
+        .filter(|key| !honored.contains(key) && !is_annotation(key))
+        .collect()
+}
+
+/// Whether the keyword only annotates the schema, including `x-` extensions.
+pub(crate) fn is_annotation(keyword: &str) -> bool {
+    ANNOTATIONS.contains(&keyword) || keyword.starts_with("x-")
+}
+
+fn honored_by_type(instance_type: &str, obj: &Map<String, Value>) -> Vec<&'static str> {
+    let mut honored = vec!["type"];
+    match instance_type {
+        "string" if obj.contains_key("minLength") || obj.contains_key("maxLength") => {
+            honored.extend(["minLength", "maxLength", "pattern"])
+        }
+        "string" if obj.contains_key("pattern") => honored.push("pattern"),
+        "string" => honored.push("format"),
+        "number" if NUMBER_DIGITS.iter().any(|key| obj.contains_key(*key)) => {
+            honored.extend(NUMBER_DIGITS)
+        }
+        "number" => honored.extend(NUMERIC_BOUNDS),
+        "integer" if obj.contains_key("minDigits") || obj.contains_key("maxDigits") => {
+            honored.extend(["minDigits", "maxDigits"])
+        }
+        "integer" => {
+            honored.extend(NUMERIC_BOUNDS);
+            let format = obj.get("format").and_then(Value::as_str);
+            if format.and_then(format_range).is_some() {
+                honored.push("format")
+            }
+        }
+        "array" => honored.extend(["items", "unevaluatedItems", "minItems", "maxItems", "depth"]),
+        "object" => honored.extend([
+            "minProperties",
+            "maxProperties",
+            "additionalProperties",
+            "unevaluatedProperties",
+            "propertyNames",
+            "depth",
+        ]),
+        _ => {}
+    }
+    honored
+}
+
+/// Finds the JSON pointer of `node` within `root` by identity, if it belongs to it.
+pub(crate) fn pointer_of(root: &Value, node: &Value) -> Option<String> {
+    if std::ptr::eq(root, node) {
+        return Some(String::new());
+    }
+    let children: Box<dyn Iterator<Item = (String, &Value)>> = match root {
+        Value::Object(obj) => Box::new(obj.iter().map(|(key, value)| (escape_pointer(key), value))),
+        Value::Array(values) => {
+            Box::new(values.iter().enumerate().map(|(i, v)| (i.to_string(), v)))
+        }
+        _ => return None,
+    };
+    for (key, child) in children {
+        if let Some(path) = pointer_of(child, node) {
+            return Some(format!("/{key}{path}"));
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn detect_ignored_keywords() {
+        for (schema, expected) in [
+            (json!({"type": "integer", "minimum": 0}), vec![]),
+            (
+                json!({"type": "integer", "maxDigits": 3, "maximum": 99}),
+                vec!["maximum"],
+            ),
+            (json!({"type": "number", "minimum": 0}), vec![]),
+            (
+                json!({"type": "number", "maxDigitsFraction": 2, "maximum": 1}),
+                vec!["maximum"],
+            ),
+            (json!({"type": "integer", "format": "int64"}), vec![]),
+            (
+                json!({"type": "integer", "format": "uint8"}),
+                vec!["format"],
+            ),
+            (
+                json!({"type": "integer", "format": "int32", "maxDigits": 3}),
+                vec!["format"],
+            ),
+            (
+                json!({"type": "string", "maxLength": 5, "format": "date", "title": "A"}),
+                vec!["format"],
+            ),
+            (
+                json!({"type": "string", "maxLength": 5, "pattern": "a+", "title": "A"}),
+                vec![],
+            ),
+            (
+                json!({"type": "object", "properties": {}, "minProperties": 1}),
+                vec!["minProperties"],
+            ),
+            (json!({"type": "object", "allOf": [], "x-order": 1}), vec![]),
+            (json!({"$ref": "#/$defs/a", "description": "A"}), vec![]),
+            (
+                json!({"type": ["string", "null"], "format": "date", "uniqueItems": true}),
+                vec!["uniqueItems"],
+            ),
+            (
+                json!({"type": "string", "format": "date", "enum": [], "properties": {}}),
+                vec!["properties"],
+            ),
+        ] {
+            let obj = schema.as_object().expect("Object schema");
+            assert_eq!(ignored_keywords(obj), expected, "{schema}");
+        }
+    }
+
+    #[test]
+    fn find_pointer() {
+        let root = json!({"properties": {"a/b": {"items": [{}, {"type": "null"}]}}});
+        let node = &root["properties"]["a/b"]["items"][1];
+        assert_eq!(
+            pointer_of(&root, node).as_deref(),
+            Some("/properties/a~1b/items/1")
+        );
+        assert_eq!(pointer_of(&root, &json!({"type": "null"})), None);
+    }
+}
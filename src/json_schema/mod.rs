@@ -27,16 +27,30 @@
 //!     - Lists the properties that must be present.
 //! - `additionalProperties`
 //!     - Specifies whether additional properties are allowed or defines their schema.
+//! - `unevaluatedProperties`
+//!     - Stands for `additionalProperties`, when it's missing, e.g. `false` closes an object,
+//!       which properties are merged from its `allOf` members.
 //! - `minProperties`
 //!     - Minimum number of properties required.
 //! - `maxProperties`
 //!     - Maximum number of properties allowed.
+//! - `propertyNames`
+//!     - Constrains the keys of objects without `properties`, e.g. with a `pattern`, an
+//!       `enum` or a `maxLength`. Keys may still be repeated. Listed `properties`, which
+//!       names violate it, are not generated.
+//! - `dependentRequired` and `dependentSchemas`
+//!     - Apply to objects with `properties`, which are generated as the alternatives of the
+//!       presence of the optional properties others depend on, so their number grows
+//!       exponentially with these properties.
 //!
 //! #### Array
 //! - `items`
 //!     - Defines the schema for array elements (single schema or a schema per index).
 //! - `prefixItems`
 //!     - Specifies schemas for the first few elements of an array (tuple validation).
+//!     - The tuple is closed, unless `items` allows more elements.
+//! - `unevaluatedItems`
+//!     - Stands for `items`, when it's missing, e.g. `false` closes a tuple.
 //! - `minItems`
 //!     - Minimum number of items required in the array.
 //! - `maxItems`
@@ -48,9 +62,13 @@
 //! - `maxLength`
 //!     - Maximum string length.
 //! - `pattern`
-//!     - Regular expression the string must match.
+//!     - Regular expression the string must match. PCRE-like constructs are translated, see
+//!       [`transpile_pattern`]. The whole string matches the pattern by default, see
+//!       [`SchemaOptions::pattern_anchoring`]. Next to `maxLength`, the strings of the pattern
+//!       are bounded by the product of their automata, as long as it stays reasonably small.
 //! - `format`
-//!     - Specifies a pre-defined format, these are supported [`FormatType`]
+//!     - Specifies a pre-defined format, these are supported [`FormatType`], more formats can
+//!       be registered with [`SchemaOptions::formats`]
 //!
 //! #### Number
 //! - `minDigitsInteger`
@@ -65,12 +83,30 @@
 //!     - Defines minimum number of digits in the exponent part of a scientific notation number.
 //! - `maxDigitsExponent`
 //!     - Defines maximum number of digits in the exponent part of a scientific notation number.
+//! - `minimum`, `maximum`, `exclusiveMinimum` and `exclusiveMaximum`
+//!     - Bound the number, e.g. `1.5` to `1.7` are generated as `1\.([56][0-9]*|70*)`-like
+//!       digit ranges. Bounded numbers lose the exponent notation, so that e.g. `1000` isn't
+//!       generated as `1e3`, and bounds are ignored if the number of digits is constrained.
+//! - `multipleOf`
+//!     - Only powers of ten are supported, other multiples are an error. `0.01` limits the
+//!       number to two fraction digits, `1` makes it an integer and `100` an integer ending
+//!       with two zeros, all without exponent, unless the number of digits is constrained.
 //!
 //! #### Integer
 //! - `minDigits`
 //!     - Defines the minimum number of digits.
 //! - `maxDigits`
 //!     - Defines the maximum number of digits.
+//! - `minimum`, `maximum`, `exclusiveMinimum` and `exclusiveMaximum`
+//!     - Bound the integer, e.g. `0` to `255` are generated as
+//!       `(25[0-5]|2[0-4][0-9]|1[0-9]{2}|[1-9][0-9]|[0-9])`-like digit ranges, unless the
+//!       number of digits is constrained.
+//! - `multipleOf`
+//!     - Only powers of ten are supported, other multiples are an error, e.g. `100` generates
+//!       the integers ending with two zeros, unless the number of digits is constrained.
+//! - `format`
+//!     - `int32`, `uint32` and `int64` bound the integer to the range of the type, unless the
+//!       number of digits is constrained.
 //!
 //! #### Logical
 //! - `allOf`
@@ -91,6 +127,9 @@
 //! exponentially in recursive case, which likely to introduce performance issues by consuming large
 //! amounts of time, resources and memory.
 //!
+//! Regular expressions longer than [`SchemaOptions::max_regex_len`], [`DEFAULT_MAX_REGEX_LEN`]
+//! by default, fail early with [`Error::RegexTooLarge`], which locates the node exceeding it.
+//!
 //! ### References
 //!
 //! Only local references are currently being supported.
@@ -98,14 +137,145 @@
 //! ### Unconstrained objects
 //!
 //! An empty object means unconstrained, allowing any JSON type.
+//!
+//! ### Canonical JSON
+//!
+//! With [`SchemaOptions::canonical`], properties are emitted in sorted order of their names and
+//! no optional whitespace is allowed, so that the same value is always generated in the same
+//! form, e.g. to be hashed or signed. Note, that keys of `additionalProperties` can't be ordered
+//! by a regular expression and keep being generated in any order.
+//!
+//! ### Default length bounds
+//!
+//! Strings, arrays and objects without an upper bound allow generations to run away, e.g. an
+//! endless string. [`SchemaOptions::default_max_string`], [`SchemaOptions::default_max_items`]
+//! and [`SchemaOptions::default_max_props`] impose a maximum length on them, unless the schema
+//! declares its own `maxLength`, `maxItems` or `maxProperties`. A default below the minimum of
+//! the schema is raised to it.
+//!
+//! ### Combined keywords
+//!
+//! All keywords of a node apply jointly, as in JSON Schema: `allOf` members and `$ref` targets
+//! are intersected with the other keywords of their node, e.g. types and listed values are
+//! intersected and the tightest bounds are kept, while `anyOf` and `oneOf` combine them with
+//! each of their alternatives, skipping the unsatisfiable ones. Keywords, which can't be
+//! intersected, e.g. two different `pattern`, keep the first value and are reported as ignored,
+//! see below. It is an error, when no value satisfies the combined keywords.
+//!
+//! Duplicated alternatives of `anyOf` and `oneOf`, e.g. emitted by schema generators for
+//! nullable fields, are emitted once, so that they don't inflate the regular expression.
+//!
+//! ### Listed values
+//!
+//! Once combined, `enum` and `const` take precedence over the other keywords of their node, so
+//! that only the listed values are generated. The sibling keywords `type`, `format`, `pattern`,
+//! `minLength`, `maxLength`, `minimum`, `maximum`, `exclusiveMinimum`, `exclusiveMaximum` and
+//! `multipleOf` exclude the values violating them, which are reported by
+//! [`KeywordPolicy::Warn`]. It is an error, when no value is left. Other sibling keywords are
+//! ignored, see below.
+//!
+//! ### Unsupported keywords
+//!
+//! Constraining keywords, which are not supported, e.g. `not`, or not applicable to the way
+//! a schema node is converted, e.g. `minProperties` next to `properties`, are ignored by default,
+//! which makes the generation less strict than the schema. [`SchemaOptions::keyword_policy`]
+//! allows to collect them as warnings or to fail instead, see [`KeywordPolicy`].
+//!
+//! ### Vocabulary analysis
+//!
+//! [`analyze_against_vocabulary`] reports schema literals (`const`, `enum` values and property
+//! names), which can't be produced by the tokens of a given vocabulary.
+//!
+//! ### Describing regular expressions
+//!
+//! [`describe_regex`] recognizes the structure, which a generated regular expression forces a
+//! value into, e.g. property names, types and bounds, to be shown to users.
+//!
+//! ### Compilation reports
+//!
+//! [`regex_from_value_with_report`] reports on the conversion of a schema: the keywords it
+//! uses, the ignored ones, the changes made to it, e.g. default bounds, the cost of the
+//! generated regular expression and the duration of the conversion. The report is
+//! serializable, e.g. to be logged.
+//!
+//! ### Validating generated values
+//!
+//! Some keywords are approximated by the generated regular expression, e.g. `uniqueItems` is
+//! ignored. With the `schema-validation` feature, `validate` checks a generated value against
+//! the complete schema, see also `Guide::finish_and_validate`.
+//!
+//! ### JSON lines
+//!
+//! [`regex_jsonl_from_str`] and [`regex_jsonl_from_value`] generate a regular expression for
+//! a bounded number of newline-delimited JSON values, each one matching the given schema.
+//!
+//! ### Unions of schemas
+//!
+//! [`regex_from_values`] generates a regular expression for a value of any of several complete
+//! schemas, optionally led by a discriminator naming the schema, see [`SchemaUnion`].
+//!
+//! ### Partial instances
+//!
+//! [`partial_instance`] fixes properties of a schema to known values, e.g. ids supplied by the
+//! caller, so that they are generated verbatim.
+//!
+//! ### Sampling hints
+//!
+//! [`field_hints`] collects the hints declared by `x-outlines-` keywords of the fields of a
+//! schema, e.g. `x-outlines-temperature`, which
+//! [`FieldPaths::sampling_hints`](crate::fields::FieldPaths::sampling_hints) maps to the
+//! states of an index, so that engines adjust their sampling per field.
+//!
+//! ### Repairing outputs
+//!
+//! [`repair`] completes a model output generated without guidance, which nearly matches a
+//! schema, into a value of the schema, keeping its longest valid prefix.
+//!
+//! ### Canonical schemas
+//!
+//! [`canonicalize`] normalizes a schema, e.g. sorts its keywords and resolves references to
+//! non-recursive schemas, and hashes it, so that equivalent schemas share a stable key of
+//! their compiled form.
 
+pub use analysis::{analyze_against_vocabulary, UntokenizableLiteral};
+pub use canonical::canonicalize;
+pub use describe::{describe_regex, FieldSpec, HumanReadableSpec};
+pub use hints::{field_hints, HINT_PREFIX};
+pub use keywords::{KeywordPolicy, SchemaWarning, WarningCollector, WarningKind};
+pub use partial::partial_instance;
+pub use pattern::{
+    normalize_anchors, transpile_pattern, PatternAnchoring, PatternChange, PatternChangeKind,
+    TranspiledPattern,
+};
+pub use repair::{repair, Repair};
+pub use report::{
+    regex_from_value_with_report, CompilationReport, Normalization, NormalizationKind,
+};
+use rustc_hash::FxHashMap as HashMap;
+use serde_json::value::RawValue;
 use serde_json::Value;
 pub use types::*;
+#[cfg(feature = "schema-validation")]
+pub use validation::{validate, SchemaMismatch};
 
+mod analysis;
+mod canonical;
+mod combine;
+mod describe;
+mod enums;
+mod hints;
+mod keywords;
 mod parsing;
+mod partial;
+mod pattern;
+pub(crate) mod ranges;
+mod repair;
+mod report;
 pub mod types;
+#[cfg(feature = "schema-validation")]
+mod validation;
 
-use crate::Result;
+use crate::{Error, Result};
 
 /// Generates a regular expression string from given JSON schema string.
 ///
@@ -187,19 +357,351 @@ pub fn regex_from_value(
     whitespace_pattern: Option<&str>,
     max_recursion_depth: Option<usize>,
 ) -> Result<String> {
+    let options = SchemaOptions {
+        whitespace_pattern: whitespace_pattern.map(str::to_string),
+        max_recursion_depth,
+        ..Default::default()
+    };
+    regex_from_value_with_options(json, &options)
+}
+
+/// Options of the regular expression generation from a JSON schema.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SchemaOptions {
+    /// Pattern of the optional whitespace, [`WHITESPACE`] if `None`.
+    pub whitespace_pattern: Option<String>,
+    /// Maximum recursion depth of references, 3 if `None`.
+    pub max_recursion_depth: Option<usize>,
+    /// Generates canonical JSON: object keys in sorted order and no optional whitespace,
+    /// `whitespace_pattern` is ignored.
+    pub canonical: bool,
+    /// How to handle constraining keywords, which are not supported, see [`KeywordPolicy`].
+    pub keyword_policy: KeywordPolicy,
+    /// Regular expressions of string `format`s by their names, matching the content of the
+    /// string without the quotes. Take precedence over the formats of [`FormatType`].
+    pub formats: HashMap<String, String>,
+    /// Maximum length of strings without `maxLength`, `pattern` or `format`, including keys
+    /// of free-form objects. Unbounded if `None`.
+    pub default_max_string: Option<u64>,
+    /// Maximum number of items of arrays without `maxItems`. Unbounded if `None`.
+    pub default_max_items: Option<u64>,
+    /// Maximum number of properties of free-form objects without `maxProperties`.
+    /// Unbounded if `None`.
+    pub default_max_props: Option<u64>,
+    /// How the anchors of `pattern`s are interpreted, see [`PatternAnchoring`].
+    pub pattern_anchoring: PatternAnchoring,
+    /// Maximum length in bytes of the generated regular expression, see
+    /// [`Error::RegexTooLarge`]. [`DEFAULT_MAX_REGEX_LEN`] if `None`.
+    pub max_regex_len: Option<usize>,
+}
+
+/// Default maximum length in bytes of a regular expression generated from a JSON schema,
+/// longer ones, e.g. of deep recursions, are rejected instead of failing later to compile.
+pub const DEFAULT_MAX_REGEX_LEN: usize = 1 << 20;
+
+/// Generates a regular expression string from given JSON schema string with options.
+///
+/// # Example
+///
+/// ```rust
+/// # use outlines_core::Error;
+/// use outlines_core::prelude::*;
+/// use outlines_core::json_schema::SchemaOptions;
+///
+/// # fn main() -> Result<(), Error> {
+///     let schema = r#"{
+///         "type": "object",
+///         "properties": {"name": {"type": "string"}, "age": {"type": "integer"}},
+///         "required": ["name", "age"]
+///     }"#;
+///
+///     // Only `{"age":42,"name":"Bob"}` form is allowed, which is stable for hashing
+///     let options = SchemaOptions { canonical: true, ..Default::default() };
+///     let regex = json_schema::regex_from_str_with_options(schema, &options)?;
+///     println!("Generated regex: {}", regex);
+/// #   Ok(())
+/// }
+/// ```
+pub fn regex_from_str_with_options(json: &str, options: &SchemaOptions) -> Result<String> {
+    let json_value: Value = serde_json::from_str(json)?;
+    regex_from_value_with_options(&json_value, options)
+}
+
+/// Generates a regular expression string from `serde_json::Value` type of JSON schema
+/// with options, see [`regex_from_str_with_options`].
+pub fn regex_from_value_with_options(json: &Value, options: &SchemaOptions) -> Result<String> {
+    parser(json, options).to_regex(json)
+}
+
+/// Generates a regular expression string from a JSON schema borrowed from a larger document
+/// with options, see [`regex_from_str_with_options`].
+///
+/// # Example
+///
+/// ```rust
+/// # use outlines_core::Error;
+/// use serde::Deserialize;
+/// use serde_json::value::RawValue;
+/// use outlines_core::json_schema::{self, SchemaOptions};
+///
+/// # fn main() -> Result<(), Error> {
+///     // Only the schema of the operation is parsed into a `Value`.
+///     #[derive(Deserialize)]
+///     struct Operation<'a> {
+///         #[serde(borrow)]
+///         schema: &'a RawValue,
+///     }
+///     let export = r#"{"schema": {"type": "integer"}, "examples": [1, 2, 3]}"#;
+///     let operation: Operation = serde_json::from_str(export)?;
+///
+///     let regex = json_schema::regex_from_raw_value_with_options(operation.schema, &SchemaOptions::default())?;
+///     assert_eq!(regex, json_schema::regex_from_str(r#"{"type": "integer"}"#, None, None)?);
+/// #   Ok(())
+/// }
+/// ```
+pub fn regex_from_raw_value_with_options(
+    json: &RawValue,
+    options: &SchemaOptions,
+) -> Result<String> {
+    regex_from_str_with_options(json.get(), options)
+}
+
+/// Creates the parser of the JSON schema with options.
+fn parser<'a>(json: &'a Value, options: &'a SchemaOptions) -> parsing::Parser<'a> {
     let mut parser = parsing::Parser::new(json);
-    if let Some(pattern) = whitespace_pattern {
+    if let Some(pattern) = &options.whitespace_pattern {
         parser = parser.with_whitespace_pattern(pattern)
     }
-    if let Some(depth) = max_recursion_depth {
+    if let Some(depth) = options.max_recursion_depth {
         parser = parser.with_max_recursion_depth(depth)
     }
-    parser.to_regex(json)
+    if options.canonical {
+        parser = parser.with_canonical()
+    }
+    parser = parser
+        .with_keyword_policy(options.keyword_policy.clone())
+        .with_formats(&options.formats);
+    if let Some(max_length) = options.default_max_string {
+        parser = parser.with_default_max_string(max_length)
+    }
+    if let Some(max_items) = options.default_max_items {
+        parser = parser.with_default_max_items(max_items)
+    }
+    if let Some(max_properties) = options.default_max_props {
+        parser = parser.with_default_max_props(max_properties)
+    }
+    if let Some(max_regex_len) = options.max_regex_len {
+        parser = parser.with_max_regex_len(max_regex_len)
+    }
+    parser.with_pattern_anchoring(options.pattern_anchoring)
+}
+
+/// How the schemas of [`regex_from_values`] are combined.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SchemaUnion {
+    /// A value of any of the schemas.
+    #[default]
+    Alternation,
+    /// An object `{"schema": name, "value": value}`, where the leading `name` identifies the
+    /// schema of the `value`: its `title`, or its position in the list if it has none.
+    Discriminator,
+}
+
+/// Generates a regular expression string matching the values of any of the JSON schemas, e.g.
+/// to let the model choose among tools in a single constrained generation.
+///
+/// # Example
+///
+/// ```rust
+/// # use outlines_core::Error;
+/// use serde_json::json;
+/// use outlines_core::prelude::*;
+/// use outlines_core::json_schema::SchemaUnion;
+///
+/// # fn main() -> Result<(), Error> {
+///     let schemas = [
+///         json!({"title": "weather", "type": "object", "properties": {"city": {"type": "string"}}}),
+///         json!({"title": "time", "type": "object", "properties": {"zone": {"type": "string"}}}),
+///     ];
+///
+///     // `{"schema": "weather", "value": {"city": "Paris"}}`
+///     let regex = json_schema::regex_from_values(&schemas, SchemaUnion::Discriminator, None, None)?;
+///     println!("Generated regex: {}", regex);
+/// #   Ok(())
+/// }
+/// ```
+pub fn regex_from_values(
+    jsons: &[Value],
+    union: SchemaUnion,
+    whitespace_pattern: Option<&str>,
+    max_recursion_depth: Option<usize>,
+) -> Result<String> {
+    let options = SchemaOptions {
+        whitespace_pattern: whitespace_pattern.map(str::to_string),
+        max_recursion_depth,
+        ..Default::default()
+    };
+    regex_from_values_with_options(jsons, union, &options)
+}
+
+/// Generates a regular expression string matching the values of any of the JSON schemas with
+/// options, see [`regex_from_values`].
+///
+/// Fails if there is no schema, or if the discriminators of two schemas are the same.
+pub fn regex_from_values_with_options(
+    jsons: &[Value],
+    union: SchemaUnion,
+    options: &SchemaOptions,
+) -> Result<String> {
+    if jsons.is_empty() {
+        return Err(Error::EmptySchemaUnion);
+    }
+    let whitespace = match (&options.whitespace_pattern, options.canonical) {
+        (_, true) => "",
+        (Some(pattern), false) => pattern.as_str(),
+        (None, false) => WHITESPACE,
+    };
+    let mut names: HashMap<String, usize> = HashMap::default();
+    let mut branches = Vec::with_capacity(jsons.len());
+    for (position, json) in jsons.iter().enumerate() {
+        let branch = match union {
+            SchemaUnion::Alternation => {
+                let value = regex_from_value_with_options(json, options)?;
+                format!("({value})")
+            }
+            SchemaUnion::Discriminator => {
+                let name = match json.get("title").and_then(Value::as_str) {
+                    Some(title) => title.to_string(),
+                    None => position.to_string(),
+                };
+                if let Some(&first) = names.get(&name) {
+                    return Err(Error::DuplicateSchemaName {
+                        name,
+                        first,
+                        second: position,
+                    });
+                }
+                let tag = regex::escape(&serde_json::to_string(&name)?);
+                names.insert(name, position);
+                let value = regex_from_value_with_options(json, options)?;
+                format!(
+                    r#"\{{{whitespace}"schema"{whitespace}:{whitespace}{tag}{whitespace},{whitespace}"value"{whitespace}:{whitespace}({value}){whitespace}\}}"#
+                )
+            }
+        };
+        branches.push(branch);
+    }
+    Ok(branches.join("|"))
+}
+
+/// Generates a regular expression string for JSON lines from given JSON schema string.
+///
+/// Each line must match the schema, lines are delimited by `\n` with an optional trailing
+/// newline. The number of lines is bounded by `min_lines` and, optionally, `max_lines`.
+///
+/// Note, that whitespace pattern shouldn't allow newlines, otherwise lines can't be told apart.
+///
+/// # Example
+///
+/// ```rust
+/// # use outlines_core::Error;
+/// use outlines_core::prelude::*;
+///
+/// # fn main() -> Result<(), Error> {
+///     let schema = r#"{"type": "object", "properties": {"id": {"type": "integer"}}, "required": ["id"]}"#;
+///
+///     // Between 2 and 5 objects, one per line
+///     let regex = json_schema::regex_jsonl_from_str(&schema, None, None, 2, Some(5))?;
+///     println!("Generated regex: {}", regex);
+/// #   Ok(())
+/// }
+/// ```
+pub fn regex_jsonl_from_str(
+    json: &str,
+    whitespace_pattern: Option<&str>,
+    max_recursion_depth: Option<usize>,
+    min_lines: usize,
+    max_lines: Option<usize>,
+) -> Result<String> {
+    let json_value: Value = serde_json::from_str(json)?;
+    regex_jsonl_from_value(
+        &json_value,
+        whitespace_pattern,
+        max_recursion_depth,
+        min_lines,
+        max_lines,
+    )
+}
+
+/// Generates a regular expression string for JSON lines from `serde_json::Value` type of
+/// JSON schema, see [`regex_jsonl_from_str`].
+pub fn regex_jsonl_from_value(
+    json: &Value,
+    whitespace_pattern: Option<&str>,
+    max_recursion_depth: Option<usize>,
+    min_lines: usize,
+    max_lines: Option<usize>,
+) -> Result<String> {
+    let options = SchemaOptions {
+        whitespace_pattern: whitespace_pattern.map(str::to_string),
+        max_recursion_depth,
+        ..Default::default()
+    };
+    regex_jsonl_from_value_with_options(json, &options, min_lines, max_lines)
+}
+
+/// Generates a regular expression string for JSON lines from `serde_json::Value` type of
+/// JSON schema with options, see [`regex_jsonl_from_str`] and [`SchemaOptions`].
+pub fn regex_jsonl_from_value_with_options(
+    json: &Value,
+    options: &SchemaOptions,
+    min_lines: usize,
+    max_lines: Option<usize>,
+) -> Result<String> {
+    let line = regex_from_value_with_options(json, options)?;
+    jsonl_regex(&line, min_lines, max_lines)
+}
+
+/// Wraps a regular expression of a single line into newline-delimited repetitions, which may
+/// end with a newline.
+fn jsonl_regex(line: &str, min_lines: usize, max_lines: Option<usize>) -> Result<String> {
+    if max_lines == Some(0) {
+        return lines_regex(line, min_lines, max_lines);
+    }
+    let lines = format!(r"{}(\n)?", lines_regex(line, min_lines.max(1), max_lines)?);
+    if min_lines == 0 {
+        Ok(format!("({lines})?"))
+    } else {
+        Ok(lines)
+    }
+}
+
+/// Repeats a line pattern a bounded number of times, lines are separated by `\n`.
+pub(crate) fn lines_regex(
+    line: &str,
+    min_lines: usize,
+    max_lines: Option<usize>,
+) -> Result<String> {
+    if matches!(max_lines, Some(max) if max < min_lines) {
+        return Err(Error::MaxBoundError);
+    }
+    let successors = match max_lines {
+        Some(0) => return Ok(String::new()),
+        Some(max) => format!("{{{},{}}}", min_lines.saturating_sub(1), max - 1),
+        None => format!("{{{},}}", min_lines.saturating_sub(1)),
+    };
+    let lines = format!(r"({line})(\n({line})){successors}");
+    if min_lines == 0 {
+        Ok(format!("({lines})?"))
+    } else {
+        Ok(lines)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use regex::Regex;
+    use serde_json::json;
 
     use super::*;
 
@@ -409,7 +911,7 @@ mod tests {
             // ==========================================================
             (
                 r#"{"title": "Foo", "type": "array", "items": {"type": "number"}}"#,
-                format!(r#"\[{WHITESPACE}(({NUMBER})(,{WHITESPACE}({NUMBER})){{0,}})?{WHITESPACE}\]"#).as_str(),
+                format!(r#"\[{WHITESPACE}(({NUMBER})({WHITESPACE},{WHITESPACE}({NUMBER})){{0,}})?{WHITESPACE}\]"#).as_str(),
                 vec!["[1e+9,1.3]", "[]"], vec!["[1", r#"["a"]"#],
             ),
             // Array with a set min length
@@ -420,7 +922,7 @@ mod tests {
                     "items": {"type": "integer"},
                     "minItems": 3
                 }"#,
-                format!(r#"\[{WHITESPACE}(({INTEGER})(,{WHITESPACE}({INTEGER})){{2,}}){WHITESPACE}\]"#).as_str(),
+                format!(r#"\[{WHITESPACE}(({INTEGER})({WHITESPACE},{WHITESPACE}({INTEGER})){{2,}}){WHITESPACE}\]"#).as_str(),
                 vec!["[1,2,3]", "[1,2,3,4]"], vec!["[1]", "[1,2]", "[]"],
             ),
             // Array with a set max length
@@ -431,7 +933,7 @@ mod tests {
                     "items": {"type": "integer"},
                     "maxItems": 3
                 }"#,
-                format!(r#"\[{WHITESPACE}(({INTEGER})(,{WHITESPACE}({INTEGER})){{0,2}})?{WHITESPACE}\]"#).as_str(),
+                format!(r#"\[{WHITESPACE}(({INTEGER})({WHITESPACE},{WHITESPACE}({INTEGER})){{0,2}})?{WHITESPACE}\]"#).as_str(),
                 vec!["[1,2,3]", "[1,2]", "[]"], vec!["[1,2,3,4]"],
             ),
             // Array with a set min/max length
@@ -443,7 +945,7 @@ mod tests {
                     "minItems": 1,
                     "maxItems": 1
                 }"#,
-                format!(r#"\[{WHITESPACE}(({INTEGER})(,{WHITESPACE}({INTEGER})){{0,0}}){WHITESPACE}\]"#).as_str(),
+                format!(r#"\[{WHITESPACE}(({INTEGER})({WHITESPACE},{WHITESPACE}({INTEGER})){{0,0}}){WHITESPACE}\]"#).as_str(),
                 vec!["[1]"], vec!["[1, 2]", r#"["a"]"#, "[]"],
             ),
             // Array with zero length
@@ -787,11 +1289,11 @@ mod tests {
             (
                 r#"{
                     "title": "Foo",
-                    "allOf": [{"type": "string"}, {"type": "integer"}]
+                    "allOf": [{"type": "string", "minLength": 1}, {"maxLength": 3}]
                 }"#,
-                format!(r#"({STRING}{INTEGER})"#).as_str(),
-                vec![r#""a"1"#],
-                vec![r#""a""#, r#""1""#],
+                format!(r#""{STRING_INNER}{{1,3}}""#).as_str(),
+                vec![r#""a""#, r#""abc""#],
+                vec![r#""""#, r#""abcd""#, "1"],
             ),
             // ==========================================================
             //                     Object
@@ -964,7 +1466,7 @@ mod tests {
                     "title": "Character",
                     "type": "object"
                 }"#,
-                format!(r#"\{{[ ]?"name"[ ]?:[ ]?{STRING}([ ]?,[ ]?"age"[ ]?:[ ]?({INTEGER}|null))?([ ]?,[ ]?"weapon"[ ]?:[ ]?({STRING}|null))?[ ]?\}}"#).as_str(),
+                format!(r#"\{{[ ]?"name"[ ]?:[ ]?{STRING}([ ]?,[ ]?"age"[ ]?:[ ]?(null|{INTEGER}))?([ ]?,[ ]?"weapon"[ ]?:[ ]?(null|{STRING}))?[ ]?\}}"#).as_str(),
                 vec![
                     r#"{ "name" : "Player" }"#,
                     r#"{ "name" : "Player", "weapon" : "sword" }"#,
@@ -987,7 +1489,7 @@ mod tests {
                     "title": "Character",
                     "type": "object"
                 }"#,
-                format!(r#"\{{[ ]?"name"[ ]?:[ ]?{STRING}[ ]?,([ ]?"age"[ ]?:[ ]?({INTEGER}|null)[ ]?,)?[ ]?"weapon"[ ]?:[ ]?{STRING}([ ]?,[ ]?"strength"[ ]?:[ ]?({INTEGER}|null))?[ ]?\}}"#).as_str(),
+                format!(r#"\{{[ ]?"name"[ ]?:[ ]?{STRING}[ ]?,([ ]?"age"[ ]?:[ ]?(null|{INTEGER})[ ]?,)?[ ]?"weapon"[ ]?:[ ]?{STRING}([ ]?,[ ]?"strength"[ ]?:[ ]?(null|{INTEGER}))?[ ]?\}}"#).as_str(),
                 vec![
                     r#"{ "name" : "Player" , "weapon" : "sword" }"#,
                     r#"{ "name" : "Player", "age" : 10, "weapon" : "sword" , "strength" : 10 }"#,
@@ -1011,7 +1513,7 @@ mod tests {
                     "title": "Character",
                     "type": "object"
                 }"#,
-                format!(r#"\{{([ ]?"name"[ ]?:[ ]?({STRING}|null)[ ]?,)?[ ]?"age"[ ]?:[ ]?{INTEGER}[ ]?,[ ]?"armor"[ ]?:[ ]?{STRING}[ ]?,([ ]?"strength"[ ]?:[ ]?({INTEGER}|null)[ ]?,)?[ ]?"weapon"[ ]?:[ ]?{STRING}[ ]?\}}"#).as_str(),
+                format!(r#"\{{([ ]?"name"[ ]?:[ ]?(null|{STRING})[ ]?,)?[ ]?"age"[ ]?:[ ]?{INTEGER}[ ]?,[ ]?"armor"[ ]?:[ ]?{STRING}[ ]?,([ ]?"strength"[ ]?:[ ]?(null|{INTEGER})[ ]?,)?[ ]?"weapon"[ ]?:[ ]?{STRING}[ ]?\}}"#).as_str(),
                 vec![
                     r#"{ "name" : "Player", "age" : 10, "armor" : "plate", "strength" : 11, "weapon" : "sword" }"#,
                     r#"{ "age" : 10, "armor" : "plate", "weapon" : "sword" }"#,
@@ -1031,7 +1533,7 @@ mod tests {
                     "title": "Character",
                     "type": "object"
                 }"#,
-                format!(r#"\{{([ ]?"name"[ ]?:[ ]?({STRING}|null)|([ ]?"name"[ ]?:[ ]?({STRING}|null)[ ]?,)?[ ]?"age"[ ]?:[ ]?({INTEGER}|null)|([ ]?"name"[ ]?:[ ]?({STRING}|null)[ ]?,)?([ ]?"age"[ ]?:[ ]?({INTEGER}|null)[ ]?,)?[ ]?"strength"[ ]?:[ ]?({INTEGER}|null))?[ ]?\}}"#).as_str(),
+                format!(r#"\{{([ ]?"name"[ ]?:[ ]?(null|{STRING})|([ ]?"name"[ ]?:[ ]?(null|{STRING})[ ]?,)?[ ]?"age"[ ]?:[ ]?(null|{INTEGER})|([ ]?"name"[ ]?:[ ]?(null|{STRING})[ ]?,)?([ ]?"age"[ ]?:[ ]?(null|{INTEGER})[ ]?,)?[ ]?"strength"[ ]?:[ ]?(null|{INTEGER}))?[ ]?\}}"#).as_str(),
                 vec![
                     r#"{ "name" : "Player" }"#,
                     r#"{ "name" : "Player", "age" : 10, "strength" : 10 }"#,
@@ -1057,7 +1559,7 @@ mod tests {
             // (huge regex, but important test to verify matching it explicitely)
             (
                 "{}",
-                "((true|false))|(null)|(((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?)|((-)?(0|[1-9][0-9]*))|(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\")|(\\[[ ]?(((true|false)|null|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(-)?(0|[1-9][0-9]*)|\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|\\{[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"[ ]?:[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(true|false)|null|\\{[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"[ ]?:[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(true|false)|null)([ ]?,[ ]?\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"[ ]?:[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(true|false)|null)){0,})?[ ]?\\}|\\[[ ]?(((true|false)|null|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(-)?(0|[1-9][0-9]*)|\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\")(,[ ]?((true|false)|null|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(-)?(0|[1-9][0-9]*)|\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\")){0,})?[ ]?\\])([ ]?,[ ]?\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"[ ]?:[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(true|false)|null|\\{[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"[ ]?:[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(true|false)|null)([ ]?,[ ]?\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"[ ]?:[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(true|false)|null)){0,})?[ ]?\\}|\\[[ ]?(((true|false)|null|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(-)?(0|[1-9][0-9]*)|\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\")(,[ ]?((true|false)|null|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(-)?(0|[1-9][0-9]*)|\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\")){0,})?[ ]?\\])){0,})?[ ]?\\}|\\[[ ]?(((true|false)|null|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(-)?(0|[1-9][0-9]*)|\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|\\{[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"[ ]?:[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(true|false)|null)([ ]?,[ ]?\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"[ ]?:[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(true|false)|null)){0,})?[ ]?\\}|\\[[ ]?(((true|false)|null|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(-)?(0|[1-9][0-9]*)|\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\")(,[ ]?((true|false)|null|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(-)?(0|[1-9][0-9]*)|\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\")){0,})?[ ]?\\])(,[ ]?((true|false)|null|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(-)?(0|[1-9][0-9]*)|\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|\\{[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"[ ]?:[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(true|false)|null)([ ]?,[ ]?\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"[ ]?:[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(true|false)|null)){0,})?[ ]?\\}|\\[[ ]?(((true|false)|null|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(-)?(0|[1-9][0-9]*)|\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\")(,[ ]?((true|false)|null|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(-)?(0|[1-9][0-9]*)|\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\")){0,})?[ ]?\\])){0,})?[ ]?\\])(,[ ]?((true|false)|null|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(-)?(0|[1-9][0-9]*)|\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|\\{[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"[ ]?:[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(true|false)|null|\\{[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"[ ]?:[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(true|false)|null)([ ]?,[ ]?\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"[ ]?:[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(true|false)|null)){0,})?[ ]?\\}|\\[[ ]?(((true|false)|null|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(-)?(0|[1-9][0-9]*)|\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\")(,[ ]?((true|false)|null|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(-)?(0|[1-9][0-9]*)|\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\")){0,})?[ ]?\\])([ ]?,[ ]?\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"[ ]?:[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(true|false)|null|\\{[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"[ ]?:[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(true|false)|null)([ ]?,[ ]?\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"[ ]?:[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(true|false)|null)){0,})?[ ]?\\}|\\[[ ]?(((true|false)|null|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(-)?(0|[1-9][0-9]*)|\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\")(,[ ]?((true|false)|null|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(-)?(0|[1-9][0-9]*)|\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\")){0,})?[ ]?\\])){0,})?[ ]?\\}|\\[[ ]?(((true|false)|null|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(-)?(0|[1-9][0-9]*)|\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|\\{[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"[ ]?:[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(true|false)|null)([ ]?,[ ]?\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"[ ]?:[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(true|false)|null)){0,})?[ ]?\\}|\\[[ ]?(((true|false)|null|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(-)?(0|[1-9][0-9]*)|\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\")(,[ ]?((true|false)|null|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(-)?(0|[1-9][0-9]*)|\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\")){0,})?[ ]?\\])(,[ ]?((true|false)|null|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(-)?(0|[1-9][0-9]*)|\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|\\{[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"[ ]?:[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(true|false)|null)([ ]?,[ ]?\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"[ ]?:[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(true|false)|null)){0,})?[ ]?\\}|\\[[ ]?(((true|false)|null|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(-)?(0|[1-9][0-9]*)|\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\")(,[ ]?((true|false)|null|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(-)?(0|[1-9][0-9]*)|\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\")){0,})?[ ]?\\])){0,})?[ ]?\\])){0,})?[ ]?\\])|(\\{[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"[ ]?:[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(true|false)|null|\\{[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"[ ]?:[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(true|false)|null|\\{[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"[ ]?:[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(true|false)|null)([ ]?,[ ]?\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"[ ]?:[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(true|false)|null)){0,})?[ ]?\\}|\\[[ ]?(((true|false)|null|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(-)?(0|[1-9][0-9]*)|\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\")(,[ ]?((true|false)|null|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(-)?(0|[1-9][0-9]*)|\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\")){0,})?[ ]?\\])([ ]?,[ ]?\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"[ ]?:[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(true|false)|null|\\{[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"[ ]?:[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(true|false)|null)([ ]?,[ ]?\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"[ ]?:[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(true|false)|null)){0,})?[ ]?\\}|\\[[ ]?(((true|false)|null|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(-)?(0|[1-9][0-9]*)|\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\")(,[ ]?((true|false)|null|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(-)?(0|[1-9][0-9]*)|\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\")){0,})?[ ]?\\])){0,})?[ ]?\\}|\\[[ ]?(((true|false)|null|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(-)?(0|[1-9][0-9]*)|\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|\\{[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"[ ]?:[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(true|false)|null)([ ]?,[ ]?\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"[ ]?:[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(true|false)|null)){0,})?[ ]?\\}|\\[[ ]?(((true|false)|null|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(-)?(0|[1-9][0-9]*)|\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\")(,[ ]?((true|false)|null|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(-)?(0|[1-9][0-9]*)|\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\")){0,})?[ ]?\\])(,[ ]?((true|false)|null|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(-)?(0|[1-9][0-9]*)|\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|\\{[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"[ ]?:[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(true|false)|null)([ ]?,[ ]?\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"[ ]?:[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(true|false)|null)){0,})?[ ]?\\}|\\[[ ]?(((true|false)|null|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(-)?(0|[1-9][0-9]*)|\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\")(,[ ]?((true|false)|null|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(-)?(0|[1-9][0-9]*)|\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\")){0,})?[ ]?\\])){0,})?[ ]?\\])([ ]?,[ ]?\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"[ ]?:[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(true|false)|null|\\{[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"[ ]?:[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(true|false)|null|\\{[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"[ ]?:[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(true|false)|null)([ ]?,[ ]?\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"[ ]?:[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(true|false)|null)){0,})?[ ]?\\}|\\[[ ]?(((true|false)|null|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(-)?(0|[1-9][0-9]*)|\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\")(,[ ]?((true|false)|null|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(-)?(0|[1-9][0-9]*)|\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\")){0,})?[ ]?\\])([ ]?,[ ]?\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"[ ]?:[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(true|false)|null|\\{[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"[ ]?:[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(true|false)|null)([ ]?,[ ]?\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"[ ]?:[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(true|false)|null)){0,})?[ ]?\\}|\\[[ ]?(((true|false)|null|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(-)?(0|[1-9][0-9]*)|\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\")(,[ ]?((true|false)|null|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(-)?(0|[1-9][0-9]*)|\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\")){0,})?[ ]?\\])){0,})?[ ]?\\}|\\[[ ]?(((true|false)|null|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(-)?(0|[1-9][0-9]*)|\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|\\{[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"[ ]?:[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(true|false)|null)([ ]?,[ ]?\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"[ ]?:[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(true|false)|null)){0,})?[ ]?\\}|\\[[ ]?(((true|false)|null|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(-)?(0|[1-9][0-9]*)|\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\")(,[ ]?((true|false)|null|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(-)?(0|[1-9][0-9]*)|\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\")){0,})?[ ]?\\])(,[ ]?((true|false)|null|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(-)?(0|[1-9][0-9]*)|\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|\\{[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"[ ]?:[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(true|false)|null)([ ]?,[ ]?\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"[ ]?:[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(true|false)|null)){0,})?[ ]?\\}|\\[[ ]?(((true|false)|null|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(-)?(0|[1-9][0-9]*)|\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\")(,[ ]?((true|false)|null|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(-)?(0|[1-9][0-9]*)|\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\")){0,})?[ ]?\\])){0,})?[ ]?\\])){0,})?[ ]?\\})",
+                "((true|false))|(null)|(((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?)|((-)?(0|[1-9][0-9]*))|(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\")|(\\[[ ]?(((true|false)|null|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(-)?(0|[1-9][0-9]*)|\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|\\{[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"[ ]?:[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(true|false)|null|\\{[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"[ ]?:[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(true|false)|null)([ ]?,[ ]?\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"[ ]?:[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(true|false)|null)){0,})?[ ]?\\}|\\[[ ]?(((true|false)|null|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(-)?(0|[1-9][0-9]*)|\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\")([ ]?,[ ]?((true|false)|null|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(-)?(0|[1-9][0-9]*)|\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\")){0,})?[ ]?\\])([ ]?,[ ]?\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"[ ]?:[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(true|false)|null|\\{[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"[ ]?:[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(true|false)|null)([ ]?,[ ]?\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"[ ]?:[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(true|false)|null)){0,})?[ ]?\\}|\\[[ ]?(((true|false)|null|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(-)?(0|[1-9][0-9]*)|\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\")([ ]?,[ ]?((true|false)|null|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(-)?(0|[1-9][0-9]*)|\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\")){0,})?[ ]?\\])){0,})?[ ]?\\}|\\[[ ]?(((true|false)|null|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(-)?(0|[1-9][0-9]*)|\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|\\{[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"[ ]?:[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(true|false)|null)([ ]?,[ ]?\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"[ ]?:[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(true|false)|null)){0,})?[ ]?\\}|\\[[ ]?(((true|false)|null|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(-)?(0|[1-9][0-9]*)|\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\")([ ]?,[ ]?((true|false)|null|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(-)?(0|[1-9][0-9]*)|\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\")){0,})?[ ]?\\])([ ]?,[ ]?((true|false)|null|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(-)?(0|[1-9][0-9]*)|\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|\\{[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"[ ]?:[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(true|false)|null)([ ]?,[ ]?\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"[ ]?:[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(true|false)|null)){0,})?[ ]?\\}|\\[[ ]?(((true|false)|null|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(-)?(0|[1-9][0-9]*)|\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\")([ ]?,[ ]?((true|false)|null|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(-)?(0|[1-9][0-9]*)|\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\")){0,})?[ ]?\\])){0,})?[ ]?\\])([ ]?,[ ]?((true|false)|null|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(-)?(0|[1-9][0-9]*)|\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|\\{[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"[ ]?:[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(true|false)|null|\\{[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"[ ]?:[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(true|false)|null)([ ]?,[ ]?\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"[ ]?:[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(true|false)|null)){0,})?[ ]?\\}|\\[[ ]?(((true|false)|null|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(-)?(0|[1-9][0-9]*)|\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\")([ ]?,[ ]?((true|false)|null|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(-)?(0|[1-9][0-9]*)|\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\")){0,})?[ ]?\\])([ ]?,[ ]?\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"[ ]?:[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(true|false)|null|\\{[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"[ ]?:[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(true|false)|null)([ ]?,[ ]?\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"[ ]?:[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(true|false)|null)){0,})?[ ]?\\}|\\[[ ]?(((true|false)|null|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(-)?(0|[1-9][0-9]*)|\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\")([ ]?,[ ]?((true|false)|null|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(-)?(0|[1-9][0-9]*)|\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\")){0,})?[ ]?\\])){0,})?[ ]?\\}|\\[[ ]?(((true|false)|null|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(-)?(0|[1-9][0-9]*)|\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|\\{[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"[ ]?:[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(true|false)|null)([ ]?,[ ]?\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"[ ]?:[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(true|false)|null)){0,})?[ ]?\\}|\\[[ ]?(((true|false)|null|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(-)?(0|[1-9][0-9]*)|\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\")([ ]?,[ ]?((true|false)|null|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(-)?(0|[1-9][0-9]*)|\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\")){0,})?[ ]?\\])([ ]?,[ ]?((true|false)|null|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(-)?(0|[1-9][0-9]*)|\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|\\{[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"[ ]?:[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(true|false)|null)([ ]?,[ ]?\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"[ ]?:[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(true|false)|null)){0,})?[ ]?\\}|\\[[ ]?(((true|false)|null|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(-)?(0|[1-9][0-9]*)|\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\")([ ]?,[ ]?((true|false)|null|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(-)?(0|[1-9][0-9]*)|\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\")){0,})?[ ]?\\])){0,})?[ ]?\\])){0,})?[ ]?\\])|(\\{[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"[ ]?:[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(true|false)|null|\\{[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"[ ]?:[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(true|false)|null|\\{[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"[ ]?:[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(true|false)|null)([ ]?,[ ]?\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"[ ]?:[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(true|false)|null)){0,})?[ ]?\\}|\\[[ ]?(((true|false)|null|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(-)?(0|[1-9][0-9]*)|\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\")([ ]?,[ ]?((true|false)|null|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(-)?(0|[1-9][0-9]*)|\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\")){0,})?[ ]?\\])([ ]?,[ ]?\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"[ ]?:[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(true|false)|null|\\{[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"[ ]?:[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(true|false)|null)([ ]?,[ ]?\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"[ ]?:[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(true|false)|null)){0,})?[ ]?\\}|\\[[ ]?(((true|false)|null|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(-)?(0|[1-9][0-9]*)|\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\")([ ]?,[ ]?((true|false)|null|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(-)?(0|[1-9][0-9]*)|\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\")){0,})?[ ]?\\])){0,})?[ ]?\\}|\\[[ ]?(((true|false)|null|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(-)?(0|[1-9][0-9]*)|\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|\\{[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"[ ]?:[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(true|false)|null)([ ]?,[ ]?\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"[ ]?:[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(true|false)|null)){0,})?[ ]?\\}|\\[[ ]?(((true|false)|null|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(-)?(0|[1-9][0-9]*)|\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\")([ ]?,[ ]?((true|false)|null|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(-)?(0|[1-9][0-9]*)|\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\")){0,})?[ ]?\\])([ ]?,[ ]?((true|false)|null|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(-)?(0|[1-9][0-9]*)|\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|\\{[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"[ ]?:[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(true|false)|null)([ ]?,[ ]?\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"[ ]?:[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(true|false)|null)){0,})?[ ]?\\}|\\[[ ]?(((true|false)|null|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(-)?(0|[1-9][0-9]*)|\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\")([ ]?,[ ]?((true|false)|null|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(-)?(0|[1-9][0-9]*)|\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\")){0,})?[ ]?\\])){0,})?[ ]?\\])([ ]?,[ ]?\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"[ ]?:[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(true|false)|null|\\{[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"[ ]?:[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(true|false)|null|\\{[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"[ ]?:[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(true|false)|null)([ ]?,[ ]?\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"[ ]?:[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(true|false)|null)){0,})?[ ]?\\}|\\[[ ]?(((true|false)|null|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(-)?(0|[1-9][0-9]*)|\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\")([ ]?,[ ]?((true|false)|null|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(-)?(0|[1-9][0-9]*)|\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\")){0,})?[ ]?\\])([ ]?,[ ]?\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"[ ]?:[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(true|false)|null|\\{[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"[ ]?:[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(true|false)|null)([ ]?,[ ]?\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"[ ]?:[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(true|false)|null)){0,})?[ ]?\\}|\\[[ ]?(((true|false)|null|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(-)?(0|[1-9][0-9]*)|\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\")([ ]?,[ ]?((true|false)|null|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(-)?(0|[1-9][0-9]*)|\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\")){0,})?[ ]?\\])){0,})?[ ]?\\}|\\[[ ]?(((true|false)|null|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(-)?(0|[1-9][0-9]*)|\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|\\{[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"[ ]?:[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(true|false)|null)([ ]?,[ ]?\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"[ ]?:[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(true|false)|null)){0,})?[ ]?\\}|\\[[ ]?(((true|false)|null|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(-)?(0|[1-9][0-9]*)|\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\")([ ]?,[ ]?((true|false)|null|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(-)?(0|[1-9][0-9]*)|\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\")){0,})?[ ]?\\])([ ]?,[ ]?((true|false)|null|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(-)?(0|[1-9][0-9]*)|\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|\\{[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"[ ]?:[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(true|false)|null)([ ]?,[ ]?\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"[ ]?:[ ]?(\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\"|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(true|false)|null)){0,})?[ ]?\\}|\\[[ ]?(((true|false)|null|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(-)?(0|[1-9][0-9]*)|\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\")([ ]?,[ ]?((true|false)|null|((-)?(0|[1-9][0-9]*))(\\.[0-9]+)?([eE][+-][0-9]+)?|(-)?(0|[1-9][0-9]*)|\"([^\"\\\\\\x00-\\x1F\\x7F-\\x9F]|\\\\[\"\\\\/bfnrt])*\")){0,})?[ ]?\\])){0,})?[ ]?\\])){0,})?[ ]?\\})",
                 vec![
                     r#""aaabbuecuh""#,
                     "5.554",
@@ -1255,113 +1757,1057 @@ mod tests {
     }
 
     #[test]
-    fn test_unconstrained_others() {
-        for (schema, a_match, not_a_match) in [
-            // Unconstrained Object
-            (
-                r#"{
-                    "title": "Foo",
-                    "type": "object"
-                }"#,
-                vec![
-                    "{}",
-                    r#"{"a": 1, "b": null}"#,
-                    r#"{"a": {"z": {"g": 4}}, "b": null}"#,
-                ],
-                vec![
-                    "1234",          // not an object
-                    r#"["a", "a"]"#, // not an array
-                ],
-            ),
-            // Unconstrained Array
-            (
-                r#"{"type": "array"}"#,
-                vec![
-                    r#"[1, {}, false]"#,
-                    r#"[{}]"#,
-                    r#"[{"a": {"z": "q"}, "b": null}]"#,
-                    r#"[{"a": [1, 2, true], "b": null}]"#,
-                    r#"[{"a": [1, 2, true], "b": {"a": "b"}}, 1, true, [1, [2]]]"#,
-                ],
-                vec![
-                    // too deep, default unconstrained depth limit = 2
-                    r#"[{"a": [1, 2, true], "b": {"a": "b"}}, 1, true, [1, [2, [3]]]]"#,
-                    r#"[{"a": {"z": {"g": 4}}, "b": null}]"#,
-                    r#"[[[[1]]]]"#,
-                    // not an array
-                    r#"{}"#,
-                    r#"{"a": 1, "b": null}"#,
-                    r#"{"a": {"z": {"g": 4}}, "b": null}"#,
-                    "1234",
-                    r#"{"a": "a"}"#,
-                ],
-            ),
+    fn test_canonical_regex() {
+        let schema = r#"{
+            "type": "object",
+            "properties": {
+                "name": {"type": "string"},
+                "age": {"type": "integer"},
+                "tags": {"const": {"b": [1, 2], "a": null}}
+            },
+            "required": ["name", "age"]
+        }"#;
+        let options = SchemaOptions {
+            whitespace_pattern: Some(r"[ ]*".to_string()),
+            canonical: true,
+            ..Default::default()
+        };
+        let regex = regex_from_str_with_options(schema, &options).expect("To regex failed");
+        let re = Regex::new(&regex).expect("Regex failed");
+        for m in [
+            r#"{"age":42,"name":"Bob"}"#,
+            r#"{"age":42,"name":"Bob","tags":{"a":null,"b":[1,2]}}"#,
         ] {
-            let regex = regex_from_str(schema, None, None).expect("To regex failed");
-            let re = Regex::new(&regex).expect("Regex failed");
-            for m in a_match {
-                should_match(&re, m);
-            }
-            for not_m in not_a_match {
-                should_not_match(&re, not_m);
-            }
+            should_match(&re, m);
+        }
+        for not_m in [
+            r#"{"name":"Bob","age":42}"#,
+            r#"{"age": 42,"name":"Bob"}"#,
+            r#"{"age":42,"name":"Bob","tags":{"b":[1,2],"a":null}}"#,
+        ] {
+            should_not_match(&re, not_m);
         }
     }
 
     #[test]
-    fn with_whitespace_patterns() {
-        let schema = r#"{
-            "title": "Foo",
+    fn test_keyword_policy() {
+        let schema = json!({
             "type": "object",
-            "properties": {"date": {"type": "string", "format": "date"}}
-        }"#;
+            "properties": {
+                "age": {"type": "integer", "not": {"const": 0}},
+                "name": {"type": "string", "description": "Name"}
+            }
+        });
 
-        for (whitespace_pattern, expected_regex, a_match) in [
-            // Default
-            (
-                None,
-                format!(
-                    r#"\{{({WHITESPACE}"date"{WHITESPACE}:{WHITESPACE}{DATE})?{WHITESPACE}\}}"#
-                ),
-                vec![
-                    r#"{"date": "2018-11-13"}"#,
-                    r#"{ "date": "2018-11-13"}"#,
-                    r#"{"date": "2018-11-13" }"#,
-                ],
-            ),
-            (
-                Some(r#"[\n ]*"#),
-                format!(
-                    r#"\{{({ws}"date"{ws}:{ws}{DATE})?{ws}\}}"#,
-                    ws = r#"[\n ]*"#
-                ),
-                vec![
-                    r#"{
-                        "date":  "2018-11-13"
-                    }"#,
-                    r#"{ "date":
+        let lenient = regex_from_value(&schema, None, None).expect("To regex failed");
+        let options = SchemaOptions::default();
+        assert_eq!(
+            regex_from_value_with_options(&schema, &options).expect("To regex failed"),
+            lenient
+        );
 
-                    "2018-11-13"     }"#,
-                ],
-            ),
-            (
-                Some("SPACE"),
-                format!(r#"\{{({ws}"date"{ws}:{ws}{DATE})?{ws}\}}"#, ws = "SPACE"),
-                vec![r#"{SPACE"date"SPACE:SPACE"2018-11-13"SPACE}"#],
-            ),
-        ] {
-            let regex = regex_from_str(schema, whitespace_pattern, None).expect("To regex failed");
-            assert_eq!(regex, expected_regex);
+        let collector = WarningCollector::new();
+        let options = SchemaOptions {
+            keyword_policy: KeywordPolicy::Warn(collector.clone()),
+            ..Default::default()
+        };
+        let regex = regex_from_value_with_options(&schema, &options).expect("To regex failed");
+        assert_eq!(regex, lenient);
+        assert_eq!(
+            collector.take(),
+            vec![SchemaWarning {
+                path: "/properties/age".to_string(),
+                keyword: "not".to_string(),
+                kind: WarningKind::IgnoredKeyword,
+                detail: None,
+            }]
+        );
+        assert!(collector.warnings().is_empty());
 
-            let re = Regex::new(&regex).expect("Regex failed");
-            for m in a_match {
-                should_match(&re, m);
+        let options = SchemaOptions {
+            keyword_policy: KeywordPolicy::Strict,
+            ..Default::default()
+        };
+        match regex_from_value_with_options(&schema, &options) {
+            Err(Error::UnsupportedKeyword { path, keyword }) => {
+                assert_eq!(path, "/properties/age");
+                assert_eq!(keyword, "not");
             }
+            other => unreachable!("Unexpected result {other:?}"),
         }
     }
 
     #[test]
-    fn direct_recursion_in_array_and_default_behaviour() {
+    fn test_max_regex_len() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "id": {"type": "integer"},
+                "tree": {"$ref": "#/$defs/node"}
+            },
+            "$defs": {
+                "node": {
+                    "type": "object",
+                    "properties": {"children": {"type": "array", "items": {"$ref": "#/$defs/node"}}}
+                }
+            }
+        });
+        let options = SchemaOptions {
+            max_recursion_depth: Some(6),
+            ..Default::default()
+        };
+        let regex = regex_from_value(&schema, None, Some(2)).expect("To regex failed");
+
+        let options = SchemaOptions {
+            max_regex_len: Some(regex.len()),
+            ..options
+        };
+        match regex_from_value_with_options(&schema, &options) {
+            Err(Error::RegexTooLarge {
+                len,
+                limit,
+                path_of_largest_node,
+            }) => {
+                assert!(len > limit);
+                assert_eq!(limit, regex.len());
+                assert_eq!(path_of_largest_node, "/$defs/node/properties/children");
+            }
+            other => unreachable!("Unexpected result {other:?}"),
+        }
+
+        let options = SchemaOptions {
+            max_regex_len: Some(regex.len()),
+            max_recursion_depth: Some(2),
+            ..Default::default()
+        };
+        assert_eq!(
+            regex_from_value_with_options(&schema, &options).ok(),
+            Some(regex)
+        );
+
+        // Nodes built by the parser are located at their closest node of the schema.
+        let schema =
+            json!({"type": "object", "properties": {"tags": {"type": "array", "items": {}}}});
+        let options = SchemaOptions {
+            max_regex_len: Some(100),
+            ..Default::default()
+        };
+        assert!(matches!(
+            regex_from_value_with_options(&schema, &options),
+            Err(Error::RegexTooLarge { path_of_largest_node, .. })
+                if path_of_largest_node == "/properties/tags/items"
+        ));
+    }
+
+    #[test]
+    fn test_references_converted_in_place() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "age": {"allOf": [{"$ref": "#/$defs/age"}], "description": "Age"},
+                "years": {"$ref": "#/$defs/age", "minimum": 1}
+            },
+            "required": ["age", "years"],
+            "$defs": {"age": {"type": "integer", "minimum": 0, "not": {"const": 0}}}
+        });
+        let collector = WarningCollector::new();
+        let options = SchemaOptions {
+            keyword_policy: KeywordPolicy::Warn(collector.clone()),
+            ..Default::default()
+        };
+        let regex = regex_from_value_with_options(&schema, &options).expect("To regex failed");
+        assert_eq!(
+            regex,
+            regex_from_value(&schema, None, None).expect("To regex failed")
+        );
+        // The single constraining member is converted where it's defined, while the
+        // intersection of several ones is built by the parser.
+        let paths: Vec<String> = collector
+            .take()
+            .into_iter()
+            .map(|warning| warning.path)
+            .collect();
+        assert_eq!(paths, vec!["/$defs/age", "/properties/years"]);
+    }
+
+    #[test]
+    fn test_enum_precedence() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "day": {
+                    "type": "string",
+                    "format": "date",
+                    "enum": ["2024-01-31", "tomorrow", 3]
+                },
+                "code": {"type": "integer", "minimum": 10, "const": 12}
+            },
+            "required": ["day", "code"]
+        });
+        let collector = WarningCollector::new();
+        let options = SchemaOptions {
+            keyword_policy: KeywordPolicy::Warn(collector.clone()),
+            ..Default::default()
+        };
+        let regex = regex_from_value_with_options(&schema, &options).expect("To regex failed");
+        let re = Regex::new(&format!("^{regex}$")).expect("Regex failed");
+        assert!(re.is_match(r#"{"day": "2024-01-31", "code": 12}"#));
+        assert!(!re.is_match(r#"{"day": "tomorrow", "code": 12}"#));
+        assert!(!re.is_match(r#"{"day": 3, "code": 12}"#));
+        assert!(!re.is_match(r#"{"day": "2024-02-01", "code": 12}"#));
+        assert_eq!(
+            collector.take(),
+            vec![
+                SchemaWarning {
+                    path: "/properties/day/enum/1".to_string(),
+                    keyword: "format".to_string(),
+                    kind: WarningKind::ExcludedEnumValue,
+                    detail: None,
+                },
+                SchemaWarning {
+                    path: "/properties/day/enum/2".to_string(),
+                    keyword: "type".to_string(),
+                    kind: WarningKind::ExcludedEnumValue,
+                    detail: None,
+                },
+            ]
+        );
+
+        // Listed values win over the other keywords of the node.
+        let schema = json!({"type": "object", "properties": {"a": {}}, "enum": [{"b": 1}]});
+        let regex = regex_from_value(&schema, None, None).expect("To regex failed");
+        assert!(Regex::new(&format!("^{regex}$"))
+            .expect("Regex failed")
+            .is_match(r#"{"b": 1}"#));
+
+        let schema =
+            json!({"properties": {"a": {"type": "string", "maxLength": 2, "enum": ["abc"]}}});
+        match regex_from_value(&schema, None, None) {
+            Err(Error::NoValidEnumValue { path, keyword }) => {
+                assert_eq!(path, "/properties/a/enum");
+                assert_eq!(keyword, "enum");
+            }
+            other => unreachable!("Unexpected result {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_combined_keywords() {
+        let schema = json!({
+            "$defs": {"Named": {"properties": {"name": {"type": "string"}}, "required": ["name"]}},
+            "type": "object",
+            "allOf": [
+                {"$ref": "#/$defs/Named"},
+                {"properties": {"name": {"maxLength": 3}, "age": {"type": "integer"}}}
+            ],
+            "anyOf": [{"required": ["age"]}, {"type": "string"}]
+        });
+        let regex = regex_from_value(&schema, Some(""), None).expect("To regex failed");
+        let re = Regex::new(&format!("^{regex}$")).expect("Regex failed");
+        assert!(re.is_match(r#"{"name":"abc","age":1}"#));
+        assert!(!re.is_match(r#"{"name":"abc"}"#));
+        assert!(!re.is_match(r#"{"name":"abcd","age":1}"#));
+        assert!(!re.is_match(r#""abc""#));
+
+        let schema =
+            json!({"type": "number", "$ref": "#/$defs/a", "$defs": {"a": {"not": {"const": 0}}}});
+        let collector = WarningCollector::new();
+        let options = SchemaOptions {
+            keyword_policy: KeywordPolicy::Warn(collector.clone()),
+            ..Default::default()
+        };
+        regex_from_value_with_options(&schema, &options).expect("To regex failed");
+        assert_eq!(
+            collector.take(),
+            vec![SchemaWarning {
+                path: String::new(),
+                keyword: "not".to_string(),
+                kind: WarningKind::IgnoredKeyword,
+                detail: None,
+            }]
+        );
+
+        let schema = json!({
+            "properties": {"a": {"allOf": [{"pattern": "a+"}, {"pattern": "b+"}], "type": "string"}}
+        });
+        let options = SchemaOptions {
+            keyword_policy: KeywordPolicy::Strict,
+            ..Default::default()
+        };
+        match regex_from_value_with_options(&schema, &options) {
+            Err(Error::UnsupportedKeyword { path, keyword }) => {
+                assert_eq!(path, "/properties/a");
+                assert_eq!(keyword, "pattern");
+            }
+            other => unreachable!("Unexpected result {other:?}"),
+        }
+
+        let schema = json!({"allOf": [{"type": "string"}, {"type": ["null", "integer"]}]});
+        match regex_from_value(&schema, None, None) {
+            Err(Error::UnsatisfiableSchema { keyword, .. }) => assert_eq!(keyword, "type"),
+            other => unreachable!("Unexpected result {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_custom_formats() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "sku": {"type": "string", "format": "sku"},
+                "day": {"type": "string", "format": "date"}
+            },
+            "required": ["sku", "day"]
+        });
+        let options = SchemaOptions {
+            canonical: true,
+            formats: HashMap::from_iter([
+                ("sku".to_string(), "[A-Z]{3}-[0-9]{4}".to_string()),
+                ("date".to_string(), "[0-9]{8}".to_string()),
+            ]),
+            ..Default::default()
+        };
+        let regex = regex_from_value_with_options(&schema, &options).expect("To regex failed");
+        let re = Regex::new(&regex).expect("Regex failed");
+        should_match(&re, r#"{"day":"20240131","sku":"ABC-1234"}"#);
+        should_not_match(&re, r#"{"day":"2024-01-31","sku":"ABC-1234"}"#);
+        should_not_match(&re, r#"{"day":"20240131","sku":"abc"}"#);
+
+        let regex = regex_jsonl_from_value_with_options(&schema, &options, 2, Some(2))
+            .expect("To regex failed");
+        let re = Regex::new(&regex).expect("Regex failed");
+        should_match(
+            &re,
+            "{\"day\":\"20240131\",\"sku\":\"ABC-1234\"}\n{\"day\":\"20240201\",\"sku\":\"XYZ-0001\"}",
+        );
+
+        let result = regex_from_value(&schema, None, None);
+        assert!(matches!(result, Err(Error::StringTypeUnsupportedFormat(_))));
+    }
+
+    #[test]
+    fn test_default_max_lengths() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "name": {"type": "string"},
+                "code": {"type": "string", "minLength": 4},
+                "tags": {"type": "array", "items": {"type": "integer"}},
+                "meta": {"type": "object"},
+                "id": {"type": "string", "maxLength": 6}
+            },
+            "required": ["name", "code", "tags", "meta", "id"]
+        });
+        let options = SchemaOptions {
+            whitespace_pattern: Some(String::new()),
+            default_max_string: Some(3),
+            default_max_items: Some(2),
+            default_max_props: Some(1),
+            ..Default::default()
+        };
+        let regex = regex_from_value_with_options(&schema, &options).expect("To regex failed");
+        let re = Regex::new(&regex).expect("Regex failed");
+        for m in [
+            r#"{"name":"abc","code":"abcd","tags":[1,2],"meta":{"k":1},"id":"abcdef"}"#,
+            r#"{"name":"","code":"abcd","tags":[],"meta":{},"id":""}"#,
+        ] {
+            should_match(&re, m);
+        }
+        for not_m in [
+            // The name is too long.
+            r#"{"name":"abcd","code":"abcd","tags":[],"meta":{},"id":""}"#,
+            // The default is raised to the minimum length.
+            r#"{"name":"","code":"abcde","tags":[],"meta":{},"id":""}"#,
+            r#"{"name":"","code":"abcd","tags":[1,2,3],"meta":{},"id":""}"#,
+            r#"{"name":"","code":"abcd","tags":[],"meta":{"k":1,"l":2},"id":""}"#,
+            // Keys of free-form objects are bounded as well.
+            r#"{"name":"","code":"abcd","tags":[],"meta":{"long":1},"id":""}"#,
+        ] {
+            should_not_match(&re, not_m);
+        }
+
+        let unbounded = regex_from_value(&schema, Some(""), None).expect("To regex failed");
+        let re = Regex::new(&unbounded).expect("Regex failed");
+        should_match(
+            &re,
+            r#"{"name":"abcd","code":"abcdef","tags":[1,2,3],"meta":{"k":1,"l":2},"id":""}"#,
+        );
+    }
+
+    #[test]
+    fn test_closed_tuples() {
+        let tuple = json!([{"type": "string"}, {"type": "integer"}]);
+        for (schema, a_match, not_a_match) in [
+            (
+                json!({"type": "array", "prefixItems": tuple, "items": false}),
+                vec![r#"["a",1]"#],
+                vec![r#"["a",1,2]"#, r#"["a"]"#, "[]"],
+            ),
+            (
+                json!({"type": "array", "prefixItems": tuple, "unevaluatedItems": false}),
+                vec![r#"["a",1]"#],
+                vec![r#"["a",1,2]"#],
+            ),
+            (
+                json!({
+                    "type": "array",
+                    "prefixItems": tuple,
+                    "items": {"type": "boolean"},
+                    "maxItems": 4
+                }),
+                vec![r#"["a",1]"#, r#"["a",1,true]"#, r#"["a",1,true,false]"#],
+                vec![r#"["a",1,true,false,true]"#, r#"["a",1,2]"#],
+            ),
+            (
+                json!({"prefixItems": tuple, "items": true, "minItems": 3}),
+                vec![r#"["a",1,null]"#, r#"["a",1,{"b":[2]},"c"]"#],
+                vec![r#"["a",1]"#],
+            ),
+            (
+                json!({"prefixItems": [], "unevaluatedItems": {"type": "integer"}}),
+                vec!["[]", "[1,2]"],
+                vec![r#"["a"]"#],
+            ),
+            (
+                json!({"type": "array", "items": false}),
+                vec!["[]"],
+                vec!["[1]"],
+            ),
+            (
+                json!({"type": "array", "unevaluatedItems": false}),
+                vec!["[]"],
+                vec!["[1]"],
+            ),
+            (
+                json!({"type": "array", "items": true, "maxItems": 1}),
+                vec!["[]", "[null]"],
+                vec!["[1,2]"],
+            ),
+        ] {
+            // Closing keywords are supported, not ignored.
+            let options = SchemaOptions {
+                whitespace_pattern: Some(String::new()),
+                keyword_policy: KeywordPolicy::Strict,
+                ..Default::default()
+            };
+            let regex = regex_from_value_with_options(&schema, &options).expect("To regex failed");
+            let re = Regex::new(&regex).expect("Regex failed");
+            for m in a_match {
+                should_match(&re, m);
+            }
+            for not_m in not_a_match {
+                should_not_match(&re, not_m);
+            }
+        }
+
+        for schema in [
+            json!({"prefixItems": tuple, "items": false, "minItems": 3}),
+            json!({"type": "array", "items": false, "minItems": 1}),
+        ] {
+            let result = regex_from_value(&schema, None, None);
+            assert!(
+                matches!(result, Err(Error::UnsatisfiableSchema { ref keyword, .. }) if keyword == "minItems"),
+                "{schema}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_uniform_whitespace() {
+        let values = [
+            (
+                json!({"type": "array", "items": {"type": "integer"}}),
+                "[ 1 , 2 ]",
+            ),
+            (json!({"type": "array"}), r#"[ 1 , "a" ]"#),
+            (
+                json!({"prefixItems": [{"type": "integer"}], "items": {"type": "integer"}}),
+                "[ 1 , 2 , 3 ]",
+            ),
+            (
+                json!({"type": "object", "additionalProperties": {"type": "integer"}}),
+                r#"{ "a" : 1 , "b" : 2 }"#,
+            ),
+            (
+                json!({"properties": {"a": {"type": "integer"}, "b": {"type": "integer"}}}),
+                r#"{ "a" : 1 , "b" : 2 }"#,
+            ),
+            (json!({"const": {"a": [1, 2]}}), r#"{ "a" : [ 1 , 2 ] }"#),
+        ];
+        for (schema, value) in values {
+            let regex = regex_from_value(&schema, Some(r"[ ]?"), None).expect("To regex failed");
+            let re = Regex::new(&regex).expect("Regex failed");
+            // Whitespace is allowed around every separator and inside every delimiter.
+            should_match(&re, value);
+            should_match(&re, &value.replace(' ', ""));
+            should_not_match(&re, &value.replace(' ', "  "));
+        }
+    }
+
+    #[test]
+    fn test_integer_formats() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "small": {"type": "integer", "format": "int32"},
+                "big": {"type": "integer", "format": "int64"},
+                "code": {"type": "integer", "format": "int32", "enum": [1, 4294967296_i64]}
+            },
+            "required": ["small", "big", "code"]
+        });
+        let options = SchemaOptions {
+            whitespace_pattern: Some(String::new()),
+            keyword_policy: KeywordPolicy::Strict,
+            ..Default::default()
+        };
+        let regex = regex_from_value_with_options(&schema, &options).expect("To regex failed");
+        let re = Regex::new(&regex).expect("Regex failed");
+        for m in [
+            r#"{"small":2147483647,"big":-9223372036854775808,"code":1}"#,
+            r#"{"small":-2147483648,"big":0,"code":1}"#,
+        ] {
+            should_match(&re, m);
+        }
+        for not_m in [
+            r#"{"small":2147483648,"big":0,"code":1}"#,
+            r#"{"small":0,"big":9223372036854775808,"code":1}"#,
+            r#"{"small":0,"big":0,"code":4294967296}"#,
+            r#"{"small":-0,"big":0,"code":1}"#,
+        ] {
+            should_not_match(&re, not_m);
+        }
+    }
+
+    #[test]
+    fn test_unevaluated_properties() {
+        for (schema, a_match, not_a_match) in [
+            (
+                json!({
+                    "allOf": [
+                        {"properties": {"a": {"type": "string"}}, "required": ["a"]},
+                        {"properties": {"b": {"type": "integer"}}}
+                    ],
+                    "unevaluatedProperties": false
+                }),
+                vec![r#"{"a":"x"}"#, r#"{"a":"x","b":1}"#],
+                vec![r#"{"a":"x","c":1}"#, r#"{"b":1}"#, r#"{"a":"x","b":"y"}"#],
+            ),
+            (
+                json!({
+                    "$defs": {"base": {"type": "object", "properties": {"a": {"type": "integer"}}}},
+                    "$ref": "#/$defs/base",
+                    "properties": {"b": {"type": "boolean"}},
+                    "unevaluatedProperties": false
+                }),
+                vec!["{}", r#"{"b":true,"a":1}"#],
+                vec![r#"{"c":1}"#],
+            ),
+            (
+                json!({"type": "object", "unevaluatedProperties": false}),
+                vec!["{}"],
+                vec![r#"{"a":1}"#],
+            ),
+            (
+                json!({"type": "object", "additionalProperties": false}),
+                vec!["{}"],
+                vec![r#"{"a":1}"#],
+            ),
+            (
+                json!({"type": "object", "unevaluatedProperties": {"type": "integer"}}),
+                vec!["{}", r#"{"a":1,"b":2}"#],
+                vec![r#"{"a":"x"}"#],
+            ),
+        ] {
+            let options = SchemaOptions {
+                whitespace_pattern: Some(String::new()),
+                keyword_policy: KeywordPolicy::Strict,
+                ..Default::default()
+            };
+            let regex = regex_from_value_with_options(&schema, &options).expect("To regex failed");
+            let re = Regex::new(&regex).expect("Regex failed");
+            for m in a_match {
+                should_match(&re, m);
+            }
+            for not_m in not_a_match {
+                should_not_match(&re, not_m);
+            }
+        }
+
+        let schema = json!({"type": "object", "unevaluatedProperties": false, "minProperties": 1});
+        let result = regex_from_value(&schema, None, None);
+        assert!(matches!(
+            result,
+            Err(Error::UnsatisfiableSchema { ref keyword, .. }) if keyword == "minProperties"
+        ));
+    }
+
+    #[test]
+    fn test_property_names() {
+        for (schema, a_match, not_a_match) in [
+            (
+                json!({
+                    "type": "object",
+                    "additionalProperties": {"type": "integer"},
+                    "propertyNames": {"pattern": "^[a-z]+_id$"},
+                    "maxProperties": 2
+                }),
+                vec!["{}", r#"{"user_id":1}"#, r#"{"user_id":1,"org_id":2}"#],
+                vec![
+                    r#"{"user":1}"#,
+                    r#"{"user_id":"x"}"#,
+                    r#"{"a_id":1,"b_id":2,"c_id":3}"#,
+                ],
+            ),
+            (
+                json!({
+                    "type": "object",
+                    "additionalProperties": {"type": "boolean"},
+                    "propertyNames": {"enum": ["read", "write", 1]},
+                    "minProperties": 1
+                }),
+                vec![r#"{"read":true}"#, r#"{"write":false,"read":true}"#],
+                vec!["{}", r#"{"exec":true}"#, r#"{1:true}"#],
+            ),
+            (
+                json!({"type": "object", "propertyNames": {"maxLength": 2}}),
+                vec![r#"{"ab":null}"#, r#"{"":1}"#],
+                vec![r#"{"abc":null}"#],
+            ),
+            (
+                json!({"type": "object", "propertyNames": false}),
+                vec!["{}"],
+                vec![r#"{"a":1}"#],
+            ),
+            (
+                json!({
+                    "type": "object",
+                    "properties": {
+                        "id": {"type": "integer"},
+                        "Name": {"type": "string"},
+                        "tag": {"type": "string"}
+                    },
+                    "required": ["id"],
+                    "propertyNames": {"pattern": "^[a-z]+$", "maxLength": 2}
+                }),
+                vec![r#"{"id":1}"#],
+                vec![r#"{"id":1,"Name":"a"}"#, r#"{"id":1,"tag":"a"}"#],
+            ),
+        ] {
+            let options = SchemaOptions {
+                whitespace_pattern: Some(String::new()),
+                keyword_policy: KeywordPolicy::Strict,
+                ..Default::default()
+            };
+            let regex = regex_from_value_with_options(&schema, &options).expect("To regex failed");
+            let re = Regex::new(&regex).expect("Regex failed");
+            for m in a_match {
+                should_match(&re, m);
+            }
+            for not_m in not_a_match {
+                should_not_match(&re, not_m);
+            }
+        }
+
+        let schema = json!({
+            "properties": {"id": {"type": "integer"}},
+            "required": ["id"],
+            "propertyNames": {"enum": ["name"]}
+        });
+        assert!(matches!(
+            regex_from_value(&schema, None, None),
+            Err(Error::UnsatisfiableSchema { keyword, .. }) if keyword == "propertyNames"
+        ));
+    }
+
+    #[test]
+    fn test_bounded_patterns() {
+        let schema = json!({"type": "string", "pattern": "^[a-z]+-[0-9]+$", "maxLength": 5});
+        let options = SchemaOptions {
+            keyword_policy: KeywordPolicy::Strict,
+            ..Default::default()
+        };
+        let regex = regex_from_value_with_options(&schema, &options).expect("To regex failed");
+        let re = Regex::new(&regex).expect("Regex failed");
+        for m in [r#""a-1""#, r#""ab-12""#] {
+            should_match(&re, m);
+        }
+        for not_m in [r#""abc-12""#, r#""abcde""#, r#""a-""#] {
+            should_not_match(&re, not_m);
+        }
+
+        let schema = json!({"type": "string", "pattern": "^[0-9]{4}$", "maxLength": 3});
+        let result = regex_from_value(&schema, None, None);
+        assert!(matches!(
+            result,
+            Err(Error::UnsatisfiableSchema { ref keyword, .. }) if keyword == "pattern"
+        ));
+
+        // Without a maximum, the pattern only passes, if it already satisfies the minimum.
+        let schema = json!({"type": "string", "pattern": "^a+$", "minLength": 2});
+        let collector = WarningCollector::new();
+        let options = SchemaOptions {
+            keyword_policy: KeywordPolicy::Warn(collector.clone()),
+            ..Default::default()
+        };
+        regex_from_value_with_options(&schema, &options).expect("To regex failed");
+        assert_eq!(collector.warnings()[0].keyword, "pattern");
+    }
+
+    #[test]
+    fn test_transpiled_patterns() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "code": {"type": "string", "pattern": r"^(?<area>\d{3})-(?!0)\d+$"},
+                "tag": {"type": "string", "pattern": r"[\w-]+", "enum": ["a-b", "١"]}
+            },
+            "required": ["code"]
+        });
+        let collector = WarningCollector::new();
+        let options = SchemaOptions {
+            whitespace_pattern: Some(String::new()),
+            keyword_policy: KeywordPolicy::Warn(collector.clone()),
+            ..Default::default()
+        };
+        let regex = regex_from_value_with_options(&schema, &options).expect("To regex failed");
+        let re = Regex::new(&regex).expect("Regex failed");
+        should_match(&re, r#"{"code":"123-45","tag":"a-b"}"#);
+        should_match(&re, r#"{"code":"123-05"}"#);
+        should_not_match(&re, r#"{"code":"١٢٣-45"}"#);
+        should_not_match(&re, r#"{"code":"123-45","tag":"١"}"#);
+
+        let warnings: Vec<_> = collector
+            .take()
+            .into_iter()
+            .map(|w| (w.path, w.kind, w.detail))
+            .collect();
+        let code = "/properties/code".to_string();
+        assert_eq!(
+            warnings,
+            vec![
+                (
+                    code.clone(),
+                    WarningKind::RewrittenPatternConstruct,
+                    Some("(?<area>".to_string())
+                ),
+                (
+                    code.clone(),
+                    WarningKind::RewrittenPatternConstruct,
+                    Some(r"\d".to_string())
+                ),
+                (
+                    code,
+                    WarningKind::DroppedPatternConstruct,
+                    Some("(?!0)".to_string())
+                ),
+                (
+                    "/properties/tag/enum/1".to_string(),
+                    WarningKind::ExcludedEnumValue,
+                    None
+                ),
+            ]
+        );
+
+        let options = SchemaOptions {
+            keyword_policy: KeywordPolicy::Strict,
+            ..Default::default()
+        };
+        match regex_from_value_with_options(&schema, &options) {
+            Err(Error::UnsupportedPatternConstruct { path, construct }) => {
+                assert_eq!(path, "/properties/code");
+                assert_eq!(construct, "(?!0)");
+            }
+            result => panic!("Unexpected result: {result:?}"),
+        }
+    }
+
+    #[test]
+    fn test_jsonl_regex() {
+        let schema = r#"{
+            "type": "object",
+            "properties": {"id": {"type": "integer"}},
+            "required": ["id"]
+        }"#;
+        for (min_lines, max_lines, a_match, not_a_match) in [
+            (
+                1,
+                None,
+                vec![r#"{"id": 1}"#, "{\"id\": 1}\n{\"id\": 2}\n"],
+                vec!["", "{\"id\": 1}\n\n{\"id\": 2}", r#"[{"id": 1}]"#],
+            ),
+            (
+                2,
+                Some(3),
+                vec![
+                    "{\"id\": 1}\n{\"id\": 2}",
+                    "{\"id\": 1}\n{\"id\": 2}\n{\"id\": 3}\n",
+                ],
+                vec![
+                    r#"{"id": 1}"#,
+                    "{\"id\": 1}\n{\"id\": 2}\n{\"id\": 3}\n{\"id\": 4}",
+                ],
+            ),
+            (
+                0,
+                Some(1),
+                vec!["", r#"{"id": 1}"#],
+                vec!["{\"id\": 1}\n{\"id\": 2}"],
+            ),
+        ] {
+            let regex = regex_jsonl_from_str(schema, None, None, min_lines, max_lines)
+                .expect("To regex failed");
+            let re = Regex::new(&regex).expect("Regex failed");
+            for m in a_match {
+                should_match(&re, m);
+            }
+            for not_m in not_a_match {
+                should_not_match(&re, not_m);
+            }
+        }
+
+        let result = regex_jsonl_from_str(schema, None, None, 3, Some(2));
+        assert!(matches!(result, Err(crate::Error::MaxBoundError)));
+    }
+
+    #[test]
+    fn test_nullable_alternatives() {
+        let value =
+            regex_from_value(&json!({"type": "integer"}), None, None).expect("To regex failed");
+        for schema in [
+            json!({"anyOf": [{"type": "integer"}, {"type": "null"}]}),
+            json!({"anyOf": [{"type": "null"}, {"type": "integer", "title": "Count"}]}),
+            json!({"oneOf": [{"type": "integer"}, {"type": "null"}]}),
+            json!({"type": ["integer", "null"], "anyOf": [{"minimum": 0}, {"type": "null"}]}),
+        ] {
+            let regex = regex_from_value(&schema, None, None).expect("To regex failed");
+            if schema.get("type").is_some() {
+                assert!(regex.starts_with("(null|"), "{schema}");
+                continue;
+            }
+            assert_eq!(regex, format!("(null|{value})"), "{schema}");
+        }
+
+        // Other alternations are kept as they are.
+        let regex = regex_from_value(
+            &json!({"oneOf": [{"type": "integer"}, {"type": "string"}]}),
+            None,
+            None,
+        )
+        .expect("To regex failed");
+        assert_eq!(regex, format!("((?:{value})|(?:{STRING}))"));
+    }
+
+    #[test]
+    fn test_deduplicated_branches() {
+        for (keyword, duplicated) in [
+            (
+                "anyOf",
+                json!([
+                    {"type": "string", "maxLength": 2},
+                    {"type": "null"},
+                    {"maxLength": 2, "type": "string"},
+                    {"type": "null", "title": "Nothing"},
+                ]),
+            ),
+            (
+                "oneOf",
+                json!([{"type": "string", "maxLength": 2}, {"type": "null"}, {"type": "null"}]),
+            ),
+        ] {
+            let expected = regex_from_value(
+                &json!({keyword: [{"type": "string", "maxLength": 2}, {"type": "null"}]}),
+                None,
+                None,
+            )
+            .expect("To regex failed");
+            let schema = json!({ keyword: duplicated });
+            let (regex, report) = regex_from_value_with_report(&schema, &SchemaOptions::default())
+                .expect("To regex failed");
+            assert_eq!(regex, expected, "{keyword}");
+            let removed = duplicated.as_array().map_or(0, Vec::len) - 2;
+            assert_eq!(
+                report.normalizations,
+                vec![Normalization {
+                    path: String::new(),
+                    keyword: keyword.to_string(),
+                    kind: NormalizationKind::DeduplicatedBranches { removed },
+                }]
+            );
+        }
+    }
+
+    #[test]
+    fn test_schema_unions() {
+        let schemas = [
+            json!({"title": "weather", "type": "object", "properties": {"city": {"type": "string"}}, "required": ["city"]}),
+            json!({"type": "integer"}),
+        ];
+        for (union, a_match, not_a_match) in [
+            (
+                SchemaUnion::Alternation,
+                vec![r#"{"city": "Paris"}"#, "42"],
+                vec![r#"{"schema": "1", "value": 42}"#, r#""Paris""#],
+            ),
+            (
+                SchemaUnion::Discriminator,
+                vec![
+                    r#"{"schema": "weather", "value": {"city": "Paris"}}"#,
+                    r#"{"schema":"1","value":42}"#,
+                ],
+                vec![
+                    r#"{"schema": "weather", "value": 42}"#,
+                    r#"{"value": 42, "schema": "1"}"#,
+                    "42",
+                ],
+            ),
+        ] {
+            let regex = regex_from_values(&schemas, union, None, None).expect("To regex failed");
+            let re = Regex::new(&format!("^(?:{regex})$")).expect("Regex failed");
+            for m in a_match {
+                should_match(&re, m);
+            }
+            for not_m in not_a_match {
+                should_not_match(&re, not_m);
+            }
+        }
+
+        let options = SchemaOptions {
+            canonical: true,
+            ..Default::default()
+        };
+        let regex = regex_from_values_with_options(&schemas, SchemaUnion::Discriminator, &options)
+            .expect("To regex failed");
+        let re = Regex::new(&format!("^(?:{regex})$")).expect("Regex failed");
+        should_match(&re, r#"{"schema":"1","value":42}"#);
+        should_not_match(&re, r#"{"schema": "1", "value": 42}"#);
+
+        let duplicates = [
+            json!({"type": "null", "title": "a"}),
+            json!({"type": "null", "title": "a"}),
+        ];
+        assert!(matches!(
+            regex_from_values(&duplicates, SchemaUnion::Discriminator, None, None),
+            Err(crate::Error::DuplicateSchemaName {
+                first: 0,
+                second: 1,
+                ..
+            })
+        ));
+        assert!(regex_from_values(&duplicates, SchemaUnion::Alternation, None, None).is_ok());
+        assert!(matches!(
+            regex_from_values(&[], SchemaUnion::Alternation, None, None),
+            Err(crate::Error::EmptySchemaUnion)
+        ));
+    }
+
+    #[test]
+    fn test_unconstrained_others() {
+        for (schema, a_match, not_a_match) in [
+            // Unconstrained Object
+            (
+                r#"{
+                    "title": "Foo",
+                    "type": "object"
+                }"#,
+                vec![
+                    "{}",
+                    r#"{"a": 1, "b": null}"#,
+                    r#"{"a": {"z": {"g": 4}}, "b": null}"#,
+                ],
+                vec![
+                    "1234",          // not an object
+                    r#"["a", "a"]"#, // not an array
+                ],
+            ),
+            // Unconstrained Array
+            (
+                r#"{"type": "array"}"#,
+                vec![
+                    r#"[1, {}, false]"#,
+                    r#"[{}]"#,
+                    r#"[{"a": {"z": "q"}, "b": null}]"#,
+                    r#"[{"a": [1, 2, true], "b": null}]"#,
+                    r#"[{"a": [1, 2, true], "b": {"a": "b"}}, 1, true, [1, [2]]]"#,
+                ],
+                vec![
+                    // too deep, default unconstrained depth limit = 2
+                    r#"[{"a": [1, 2, true], "b": {"a": "b"}}, 1, true, [1, [2, [3]]]]"#,
+                    r#"[{"a": {"z": {"g": 4}}, "b": null}]"#,
+                    r#"[[[[1]]]]"#,
+                    // not an array
+                    r#"{}"#,
+                    r#"{"a": 1, "b": null}"#,
+                    r#"{"a": {"z": {"g": 4}}, "b": null}"#,
+                    "1234",
+                    r#"{"a": "a"}"#,
+                ],
+            ),
+        ] {
+            let regex = regex_from_str(schema, None, None).expect("To regex failed");
+            let re = Regex::new(&regex).expect("Regex failed");
+            for m in a_match {
+                should_match(&re, m);
+            }
+            for not_m in not_a_match {
+                should_not_match(&re, not_m);
+            }
+        }
+    }
+
+    #[test]
+    fn with_whitespace_patterns() {
+        let schema = r#"{
+            "title": "Foo",
+            "type": "object",
+            "properties": {"date": {"type": "string", "format": "date"}}
+        }"#;
+
+        for (whitespace_pattern, expected_regex, a_match) in [
+            // Default
+            (
+                None,
+                format!(
+                    r#"\{{({WHITESPACE}"date"{WHITESPACE}:{WHITESPACE}{DATE})?{WHITESPACE}\}}"#
+                ),
+                vec![
+                    r#"{"date": "2018-11-13"}"#,
+                    r#"{ "date": "2018-11-13"}"#,
+                    r#"{"date": "2018-11-13" }"#,
+                ],
+            ),
+            (
+                Some(r#"[\n ]*"#),
+                format!(
+                    r#"\{{({ws}"date"{ws}:{ws}{DATE})?{ws}\}}"#,
+                    ws = r#"[\n ]*"#
+                ),
+                vec![
+                    r#"{
+                        "date":  "2018-11-13"
+                    }"#,
+                    r#"{ "date":
+
+                    "2018-11-13"     }"#,
+                ],
+            ),
+            (
+                Some("SPACE"),
+                format!(r#"\{{({ws}"date"{ws}:{ws}{DATE})?{ws}\}}"#, ws = "SPACE"),
+                vec![r#"{SPACE"date"SPACE:SPACE"2018-11-13"SPACE}"#],
+            ),
+        ] {
+            let regex = regex_from_str(schema, whitespace_pattern, None).expect("To regex failed");
+            assert_eq!(regex, expected_regex);
+
+            let re = Regex::new(&regex).expect("Regex failed");
+            for m in a_match {
+                should_match(&re, m);
+            }
+        }
+    }
+
+    #[test]
+    fn direct_recursion_in_array_and_default_behaviour() {
         let schema = r##"
         {
             "type": "object",
@@ -1626,4 +3072,331 @@ mod tests {
             "Regex should contain typeE when max_recursion_depth is specified"
         );
     }
+
+    #[test]
+    fn test_integer_bounds() {
+        for (schema, matches, not_matches) in [
+            (
+                json!({"type": "integer", "minimum": 0, "maximum": 255}),
+                vec!["0", "9", "99", "199", "255"],
+                vec!["-1", "256", "300", "1000", "007"],
+            ),
+            (
+                json!({"type": "integer", "exclusiveMinimum": -3, "exclusiveMaximum": 3}),
+                vec!["-2", "0", "2"],
+                vec!["-3", "3", "-0"],
+            ),
+            (
+                json!({"type": "integer", "minimum": 1.5, "maximum": 3.5}),
+                vec!["2", "3"],
+                vec!["1", "4"],
+            ),
+            (
+                json!({"type": "integer", "minimum": 100}),
+                vec!["100", "999", "123456789012345678901234567890"],
+                vec!["99", "-100", "0"],
+            ),
+            (
+                json!({"type": "integer", "exclusiveMaximum": 0}),
+                vec!["-1", "-123456789012345678901234567890"],
+                vec!["0", "1"],
+            ),
+            (
+                json!({"type": "integer", "format": "uint32", "maximum": 1e12}),
+                vec!["0", "4294967295"],
+                vec!["-1", "4294967296"],
+            ),
+            (
+                json!({"type": "integer", "maxDigits": 2, "minimum": 50}),
+                vec!["7", "-7"],
+                vec!["100"],
+            ),
+        ] {
+            let regex = regex_from_value(&schema, None, None).expect("To regex failed");
+            let re = Regex::new(&format!("^{regex}$")).expect("Regex failed");
+            for m in matches {
+                should_match(&re, m);
+            }
+            for not_m in not_matches {
+                should_not_match(&re, not_m);
+            }
+        }
+
+        let schema = json!({"type": "integer", "exclusiveMinimum": 2, "maximum": 2});
+        match regex_from_value(&schema, None, None) {
+            Err(Error::UnsatisfiableSchema { keyword, .. }) => {
+                assert_eq!(keyword, "exclusiveMinimum")
+            }
+            other => unreachable!("Unexpected result {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_number_bounds() {
+        for (schema, matches, not_matches) in [
+            (
+                json!({"type": "number", "minimum": 0, "maximum": 1}),
+                vec!["0", "0.25", "0.999", "1", "1.0", "1.00"],
+                vec!["-0.5", "1.01", "2", "1e0", "01"],
+            ),
+            (
+                json!({"type": "number", "exclusiveMinimum": 0}),
+                vec!["0.001", "1", "12345.6"],
+                vec!["0", "0.0", "-1"],
+            ),
+            (
+                json!({"type": "number", "minimum": -1.5, "exclusiveMaximum": 2}),
+                vec!["-1.5", "-1.50", "-1.1", "-1", "-0.5", "0", "1.99"],
+                vec!["-1.51", "-2", "2", "2.0"],
+            ),
+            (
+                json!({"type": "number", "minimum": 0.1, "maximum": 0.9}),
+                vec!["0.1", "0.15", "0.5", "0.9", "0.900"],
+                vec!["0", "0.09", "0.901", "1", "-0.5"],
+            ),
+            (
+                json!({"type": "number", "minimum": 1.5, "maximum": 1.7}),
+                vec!["1.5", "1.6", "1.69999", "1.7", "1.70"],
+                vec!["1", "1.4999", "1.7001", "2"],
+            ),
+            (
+                json!({"type": "number", "minimum": 0.001}),
+                vec!["0.001", "0.0011", "0.01", "1", "1000.5"],
+                vec!["0", "0.0009", "0.000", "-1"],
+            ),
+            (
+                json!({"type": "number", "exclusiveMinimum": -0.5, "exclusiveMaximum": 0.5}),
+                vec!["-0.49", "-0.1", "0", "0.0", "0.25", "0.4999"],
+                vec!["-0.5", "-0.50", "0.5", "-1", "1", "-0"],
+            ),
+            (
+                json!({"type": "number", "minimum": 1.5}),
+                vec!["1.5", "1.50", "1.51", "2", "100.25"],
+                vec!["1.49", "1", "-1.5"],
+            ),
+            (
+                json!({"type": "number", "maximum": -3}),
+                vec!["-3", "-3.0", "-3.5", "-1000"],
+                vec!["-2.9", "3", "0"],
+            ),
+            (
+                json!({"type": "number", "maxDigitsInteger": 2, "minimum": 500}),
+                vec!["12", "-12.5e+3"],
+                vec!["100"],
+            ),
+        ] {
+            let regex = regex_from_value(&schema, None, None).expect("To regex failed");
+            let re = Regex::new(&format!("^{regex}$")).expect("Regex failed");
+            for m in matches {
+                should_match(&re, m);
+            }
+            for not_m in not_matches {
+                should_not_match(&re, not_m);
+            }
+        }
+
+        let schema = json!({"type": "number", "minimum": 1.75, "exclusiveMaximum": 1.75});
+        match regex_from_value(&schema, None, None) {
+            Err(Error::UnsatisfiableSchema { keyword, .. }) => assert_eq!(keyword, "minimum"),
+            other => unreachable!("Unexpected result {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_dependencies() {
+        for (schema, matches, not_matches) in [
+            (
+                json!({
+                    "type": "object",
+                    "properties": {
+                        "name": {"type": "string"},
+                        "card": {"type": "integer"},
+                        "address": {"type": "string"}
+                    },
+                    "dependentRequired": {"card": ["address"]}
+                }),
+                vec![
+                    "{}",
+                    r#"{"name":"a"}"#,
+                    r#"{"address":"b"}"#,
+                    r#"{"card":1,"address":"b"}"#,
+                    r#"{"name":"a","card":1,"address":"b"}"#,
+                ],
+                vec![r#"{"card":1}"#, r#"{"name":"a","card":1}"#],
+            ),
+            (
+                json!({
+                    "type": "object",
+                    "properties": {"a": {"type": "integer"}, "b": {"type": "integer"}},
+                    "required": ["a"],
+                    "dependentRequired": {"a": ["b"]}
+                }),
+                vec![r#"{"a":1,"b":2}"#],
+                vec![r#"{"a":1}"#, r#"{"b":2}"#],
+            ),
+            (
+                json!({
+                    "type": "object",
+                    "properties": {
+                        "kind": {"type": "string"},
+                        "size": {"type": "integer"}
+                    },
+                    "dependentSchemas": {
+                        "kind": {"properties": {"size": {"maximum": 9}}, "required": ["size"]}
+                    }
+                }),
+                vec!["{}", r#"{"size":42}"#, r#"{"kind":"a","size":9}"#],
+                vec![r#"{"kind":"a"}"#, r#"{"kind":"a","size":42}"#],
+            ),
+            (
+                json!({
+                    "allOf": [
+                        {"properties": {"a": {"const": 1}, "b": {"const": 2}}},
+                        {"dependentRequired": {"b": ["a"]}}
+                    ]
+                }),
+                vec!["{}", r#"{"a":1}"#, r#"{"a":1,"b":2}"#],
+                vec![r#"{"b":2}"#],
+            ),
+        ] {
+            let regex = regex_from_value(&schema, None, None).expect("To regex failed");
+            let re = Regex::new(&format!("^{regex}$")).expect("Regex failed");
+            for m in matches {
+                should_match(&re, m);
+            }
+            for not_m in not_matches {
+                should_not_match(&re, not_m);
+            }
+            let options = SchemaOptions {
+                keyword_policy: KeywordPolicy::Strict,
+                ..Default::default()
+            };
+            assert!(regex_from_value_with_options(&schema, &options).is_ok());
+        }
+
+        let properties: serde_json::Map<String, Value> = (0..24)
+            .map(|i| (format!("p{i}"), json!({"type": "integer"})))
+            .collect();
+        let dependencies: serde_json::Map<String, Value> =
+            (0..24).map(|i| (format!("p{i}"), json!([]))).collect();
+        let schema = json!({"properties": properties, "dependentRequired": dependencies});
+        assert!(matches!(
+            regex_from_value(&schema, None, None),
+            Err(Error::RegexTooLarge { .. })
+        ));
+    }
+
+    #[test]
+    fn test_multiple_of() {
+        for (schema, matches, not_matches) in [
+            (
+                json!({"type": "integer", "multipleOf": 100}),
+                vec!["0", "100", "-2500", "1000000"],
+                vec!["10", "150", "000", "-0100"],
+            ),
+            (
+                json!({"type": "integer", "multipleOf": 10, "minimum": 15, "maximum": 50}),
+                vec!["20", "50"],
+                vec!["10", "15", "55", "60", "0"],
+            ),
+            (
+                json!({"type": "integer", "multipleOf": 0.5}),
+                vec![],
+                vec![],
+            ),
+            (
+                json!({"type": "integer", "multipleOf": 0.1}),
+                vec!["7", "-12"],
+                vec!["7.5"],
+            ),
+            (
+                json!({"type": "number", "multipleOf": 0.01}),
+                vec!["0", "1.5", "-3.25", "12.00"],
+                vec!["1.234", "1e2", "1."],
+            ),
+            (
+                json!({"type": "number", "multipleOf": 0.1, "exclusiveMinimum": 0, "maximum": 1}),
+                vec!["0.1", "0.5", "1", "1.0"],
+                vec!["0", "0.0", "0.05", "1.1"],
+            ),
+            (
+                json!({"type": "number", "multipleOf": 1000, "maximum": 2500.5}),
+                vec!["2000", "0", "-1000"],
+                vec!["2500", "3000", "2000.0"],
+            ),
+            (
+                json!({"type": "number", "multipleOf": 0.01, "maxDigitsFraction": 3}),
+                vec!["1.234"],
+                vec![],
+            ),
+        ] {
+            let regex = match regex_from_value(&schema, None, None) {
+                Ok(regex) => regex,
+                Err(Error::UnsupportedMultipleOf { path, multiple }) => {
+                    assert_eq!(path, "");
+                    assert_eq!(*multiple, json!(0.5));
+                    continue;
+                }
+                Err(e) => unreachable!("To regex failed: {e}"),
+            };
+            let re = Regex::new(&format!("^{regex}$")).expect("Regex failed");
+            for m in matches {
+                should_match(&re, m);
+            }
+            for not_m in not_matches {
+                should_not_match(&re, not_m);
+            }
+        }
+
+        for (schema, expected) in [
+            (json!({"type": "number", "multipleOf": 3}), "3"),
+            (json!({"type": "number", "multipleOf": 0}), "0"),
+            (json!({"type": "integer", "multipleOf": "10"}), r#""10""#),
+        ] {
+            match regex_from_value(&schema, None, None) {
+                Err(e @ Error::UnsupportedMultipleOf { .. }) => {
+                    assert!(e.to_string().ends_with(&format!("found {expected}")), "{e}")
+                }
+                other => unreachable!("Unexpected result {other:?}"),
+            }
+        }
+
+        let schema = json!({"type": "integer", "multipleOf": 100, "minimum": 1, "maximum": 99});
+        match regex_from_value(&schema, None, None) {
+            Err(Error::UnsatisfiableSchema { keyword, .. }) => assert_eq!(keyword, "multipleOf"),
+            other => unreachable!("Unexpected result {other:?}"),
+        }
+
+        // The greater power of ten is kept, and listed values are checked against it.
+        let schema = json!({"allOf": [
+            {"type": "integer", "multipleOf": 10},
+            {"multipleOf": 100, "enum": [50, 200, 300]},
+            {"enum": [200, 250, 300, 1000]}
+        ]});
+        let regex = regex_from_value(&schema, None, None).expect("To regex failed");
+        assert_eq!(regex, "(200|300)");
+    }
+
+    #[test]
+    fn test_primitives() {
+        assert_eq!(Primitive::String.as_str(), STRING);
+        assert_eq!(Primitive::Integer.to_string(), INTEGER);
+        assert_eq!(FormatType::Uuid.primitive().as_str(), UUID);
+        assert_eq!(JsonType::Null.to_regex(), Primitive::Null.as_str());
+
+        let integer =
+            Regex::new(&format!("^{}$", Primitive::Integer.quoted())).expect("Regex failed");
+        assert!(integer.is_match(r#""-12""#));
+        assert!(!integer.is_match("-12"));
+
+        let booleans = Regex::new(&format!("^{}$", Primitive::Boolean.repeated(2, None)))
+            .expect("Regex failed");
+        assert!(booleans.is_match("truefalsetrue"));
+        assert!(!booleans.is_match("true"));
+        assert_eq!(Primitive::Null.repeated(3, Some(3)), "(null){3}");
+
+        let date = Regex::new(&format!("^{}$", Primitive::Date.optional())).expect("Regex failed");
+        assert!(date.is_match(""));
+        assert!(date.is_match(r#""2024-02-29""#));
+    }
 }
@@ -1,11 +1,20 @@
 //! Parser generates a regular expression described by a JSON schema.
 
+use std::borrow::Cow;
+use std::cell::RefCell;
 use std::num::NonZeroU64;
 
 use regex::escape;
+use rustc_hash::FxHashMap as HashMap;
 use serde_json::{json, Value};
 
+use crate::compose::{self, Composition};
+use crate::json_schema::keywords::{self, KeywordPolicy, SchemaWarning, WarningKind};
+use crate::json_schema::pattern::{self, PatternAnchoring, PatternChange, PatternChangeKind};
+use crate::json_schema::ranges::DecimalBound;
+use crate::json_schema::report::{Normalization, NormalizationKind};
 use crate::json_schema::types;
+use crate::json_schema::{combine, enums, ranges};
 use crate::{Error, Result};
 
 pub(crate) struct Parser<'a> {
@@ -13,6 +22,26 @@ pub(crate) struct Parser<'a> {
     whitespace_pattern: &'a str,
     recursion_depth: usize,
     max_recursion_depth: usize,
+    /// Whether object keys are emitted in canonical (sorted) order.
+    sort_keys: bool,
+    keyword_policy: KeywordPolicy,
+    /// Regular expressions of custom string formats, by their names.
+    formats: Option<&'a HashMap<String, String>>,
+    /// Default maximum length of strings without `maxLength`, including object keys.
+    default_max_string: Option<u64>,
+    /// Default maximum number of array items without `maxItems`.
+    default_max_items: Option<u64>,
+    /// Default maximum number of object properties without `maxProperties`.
+    default_max_props: Option<u64>,
+    /// How the anchors of `pattern`s are interpreted.
+    pattern_anchoring: PatternAnchoring,
+    /// Maximum length of the regular expression of a node.
+    max_regex_len: usize,
+    /// JSON pointer to the closest node of the root schema being converted, which locates
+    /// issues of the nodes built by the parser itself. Not tracked by the lenient policy.
+    path: String,
+    /// Changes of the schema semantics made by the conversion, in the order they were made.
+    normalizations: RefCell<Vec<Normalization>>,
 }
 
 impl<'a> Parser<'a> {
@@ -29,6 +58,16 @@ impl<'a> Parser<'a> {
             whitespace_pattern: types::WHITESPACE,
             recursion_depth: 0,
             max_recursion_depth: 3,
+            sort_keys: false,
+            keyword_policy: KeywordPolicy::default(),
+            formats: None,
+            default_max_string: None,
+            default_max_items: None,
+            default_max_props: None,
+            pattern_anchoring: PatternAnchoring::default(),
+            max_regex_len: super::DEFAULT_MAX_REGEX_LEN,
+            path: String::new(),
+            normalizations: RefCell::default(),
         }
     }
 
@@ -46,18 +85,233 @@ impl<'a> Parser<'a> {
         }
     }
 
+    /// Emits object keys in sorted order without any optional whitespace.
+    pub fn with_canonical(self) -> Self {
+        Self {
+            whitespace_pattern: "",
+            sort_keys: true,
+            ..self
+        }
+    }
+
+    pub fn with_keyword_policy(self, keyword_policy: KeywordPolicy) -> Self {
+        Self {
+            keyword_policy,
+            ..self
+        }
+    }
+
+    /// Registers regular expressions of custom string formats, overriding the built-in ones.
+    pub fn with_formats(self, formats: &'a HashMap<String, String>) -> Self {
+        Self {
+            formats: Some(formats),
+            ..self
+        }
+    }
+
+    /// Bounds the length of strings without `maxLength`, unless they have a `pattern` or
+    /// a `format`.
+    pub fn with_default_max_string(self, max_length: u64) -> Self {
+        Self {
+            default_max_string: Some(max_length),
+            ..self
+        }
+    }
+
+    /// Bounds the number of items of arrays without `maxItems`.
+    pub fn with_default_max_items(self, max_items: u64) -> Self {
+        Self {
+            default_max_items: Some(max_items),
+            ..self
+        }
+    }
+
+    /// Bounds the number of properties of free-form objects without `maxProperties`.
+    pub fn with_default_max_props(self, max_properties: u64) -> Self {
+        Self {
+            default_max_props: Some(max_properties),
+            ..self
+        }
+    }
+
+    /// Fails with [`Error::RegexTooLarge`] as soon as the regular expression of a node is
+    /// longer than `max_regex_len` bytes.
+    pub fn with_max_regex_len(self, max_regex_len: usize) -> Self {
+        Self {
+            max_regex_len,
+            ..self
+        }
+    }
+
+    /// Interprets the anchors of `pattern`s, which match the whole string by default.
+    pub fn with_pattern_anchoring(self, pattern_anchoring: PatternAnchoring) -> Self {
+        Self {
+            pattern_anchoring,
+            ..self
+        }
+    }
+
+    /// Returns the changes of the schema semantics made by the conversions so far.
+    pub fn normalizations(&self) -> Vec<Normalization> {
+        self.normalizations.borrow().clone()
+    }
+
+    /// Records a change of the semantics of the current node, unless it's already recorded.
+    fn normalized(&self, keyword: &str, kind: NormalizationKind) {
+        let normalization = Normalization {
+            path: self.path.clone(),
+            keyword: keyword.to_string(),
+            kind,
+        };
+        let mut normalizations = self.normalizations.borrow_mut();
+        if !normalizations.contains(&normalization) {
+            normalizations.push(normalization);
+        }
+    }
+
+    /// Applies the keyword policy to the keywords of the node ignored by its conversion.
+    fn check_keywords(&self, json: &Value) -> Result<()> {
+        let Value::Object(obj) = json else {
+            return Ok(());
+        };
+        if self.keyword_policy == KeywordPolicy::Lenient {
+            return Ok(());
+        }
+        self.report_ignored(&keywords::ignored_keywords(obj))
+    }
+
+    /// Reports keywords of the current node, which are ignored, according to the policy.
+    fn report_ignored(&self, ignored: &[&str]) -> Result<()> {
+        let Some(first) = ignored.first() else {
+            return Ok(());
+        };
+        match &self.keyword_policy {
+            KeywordPolicy::Strict => Err(Error::UnsupportedKeyword {
+                path: self.path.clone(),
+                keyword: first.to_string(),
+            }),
+            KeywordPolicy::Warn(collector) => {
+                for keyword in ignored {
+                    collector.report(SchemaWarning {
+                        path: self.path.clone(),
+                        keyword: keyword.to_string(),
+                        kind: WarningKind::IgnoredKeyword,
+                        detail: None,
+                    });
+                }
+                Ok(())
+            }
+            KeywordPolicy::Lenient => Ok(()),
+        }
+    }
+
+    /// Reports the changes made to the `pattern` of the current node, according to the policy.
+    fn report_pattern_changes(&self, changes: &[PatternChange]) -> Result<()> {
+        match &self.keyword_policy {
+            KeywordPolicy::Strict => match changes
+                .iter()
+                .find(|change| change.kind == PatternChangeKind::Dropped)
+            {
+                Some(dropped) => Err(Error::UnsupportedPatternConstruct {
+                    path: self.path.clone(),
+                    construct: dropped.construct.clone(),
+                }),
+                None => Ok(()),
+            },
+            KeywordPolicy::Warn(collector) => {
+                for change in changes {
+                    collector.report(SchemaWarning {
+                        path: self.path.clone(),
+                        keyword: "pattern".to_string(),
+                        kind: match change.kind {
+                            PatternChangeKind::Rewritten => WarningKind::RewrittenPatternConstruct,
+                            PatternChangeKind::Dropped => WarningKind::DroppedPatternConstruct,
+                        },
+                        detail: Some(change.construct.clone()),
+                    });
+                }
+                Ok(())
+            }
+            KeywordPolicy::Lenient => Ok(()),
+        }
+    }
+
+    /// Orders object entries as they should be emitted.
+    fn ordered<'b>(&self, obj: &'b serde_json::Map<String, Value>) -> Vec<(&'b String, &'b Value)> {
+        let mut entries: Vec<_> = obj.iter().collect();
+        if self.sort_keys {
+            // Canonical JSON (RFC 8785) sorts keys by their UTF-16 code units.
+            entries.sort_by(|(a, _), (b, _)| a.encode_utf16().cmp(b.encode_utf16()));
+        }
+        entries
+    }
+
     #[allow(clippy::wrong_self_convention)]
     pub fn to_regex(&mut self, json: &Value) -> Result<String> {
+        // Nodes built by the parser itself don't belong to the root schema.
+        let outer = match self.keyword_policy {
+            KeywordPolicy::Lenient => None,
+            _ => keywords::pointer_of(self.root, json)
+                .map(|path| std::mem::replace(&mut self.path, path)),
+        };
+        let result = self
+            .check_keywords(json)
+            .and_then(|()| self.dispatch(json))
+            .and_then(|regex| self.check_len(regex))
+            .map_err(|e| self.locate(e, json));
+        if let Some(outer) = outer {
+            self.path = outer;
+        }
+        result
+    }
+
+    /// Fails if the regular expression of the current node is too long, before it's embedded
+    /// into the ones of its parents. Since nodes are checked from the innermost ones, the
+    /// path locates the largest node, which exceeds the limit by itself.
+    fn check_len(&self, regex: String) -> Result<String> {
+        if regex.len() > self.max_regex_len {
+            return Err(Error::RegexTooLarge {
+                len: regex.len(),
+                limit: self.max_regex_len,
+                path_of_largest_node: self.path.clone(),
+            });
+        }
+        Ok(regex)
+    }
+
+    /// Locates a too long regular expression at the closest node of the root schema, since
+    /// the path isn't tracked by the lenient policy.
+    fn locate(&self, error: Error, json: &Value) -> Error {
+        match error {
+            Error::RegexTooLarge {
+                len,
+                limit,
+                path_of_largest_node,
+            } if path_of_largest_node.is_empty() => Error::RegexTooLarge {
+                len,
+                limit,
+                path_of_largest_node: keywords::pointer_of(self.root, json).unwrap_or_default(),
+            },
+            error => error,
+        }
+    }
+
+    /// Converts the node by its keywords. Combinators and references come first, as they
+    /// combine the other keywords of the node with their subschemas.
+    fn dispatch(&mut self, json: &Value) -> Result<String> {
         match json {
             Value::Object(obj) if obj.is_empty() => self.parse_empty_object(),
-            Value::Object(obj) if obj.contains_key("properties") => self.parse_properties(obj),
-            Value::Object(obj) if obj.contains_key("allOf") => self.parse_all_of(obj),
+            Value::Object(obj) if obj.contains_key("allOf") => self.parse_all_of(json, obj),
             Value::Object(obj) if obj.contains_key("anyOf") => self.parse_any_of(obj),
             Value::Object(obj) if obj.contains_key("oneOf") => self.parse_one_of(obj),
-            Value::Object(obj) if obj.contains_key("prefixItems") => self.parse_prefix_items(obj),
+            Value::Object(obj) if obj.contains_key("$ref") => self.parse_ref(json, obj),
             Value::Object(obj) if obj.contains_key("enum") => self.parse_enum(obj),
             Value::Object(obj) if obj.contains_key("const") => self.parse_const(obj),
-            Value::Object(obj) if obj.contains_key("$ref") => self.parse_ref(obj),
+            Value::Object(obj) if obj.contains_key("properties") && has_dependencies(obj) => {
+                self.parse_dependencies(obj)
+            }
+            Value::Object(obj) if obj.contains_key("properties") => self.parse_properties(obj),
+            Value::Object(obj) if obj.contains_key("prefixItems") => self.parse_prefix_items(obj),
             Value::Object(obj) if obj.contains_key("type") => self.parse_type(obj),
             json => Err(Error::UnsupportedJsonSchema(Box::new(json.clone()))),
         }
@@ -93,6 +347,7 @@ impl<'a> Parser<'a> {
             .get("properties")
             .and_then(Value::as_object)
             .ok_or_else(|| Error::PropertiesNotFound)?;
+        let mut properties = self.ordered(properties);
 
         let required_properties = obj
             .get("required")
@@ -100,9 +355,25 @@ impl<'a> Parser<'a> {
             .map(|arr| arr.iter().filter_map(Value::as_str).collect::<Vec<_>>())
             .unwrap_or_default();
 
+        // Listed properties, which names violate `propertyNames`, are never generated.
+        let property_names = obj.get("propertyNames");
+        let (named, unnamed): (Vec<_>, Vec<_>) = properties
+            .into_iter()
+            .partition(|(name, _)| self.is_property_name(property_names, name));
+        if unnamed
+            .iter()
+            .any(|(name, _)| required_properties.contains(&name.as_str()))
+        {
+            return Err(Error::UnsatisfiableSchema {
+                path: self.path.clone(),
+                keyword: "propertyNames".to_string(),
+            });
+        }
+        properties = named;
+
         let is_required: Vec<bool> = properties
-            .keys()
-            .map(|item| required_properties.contains(&item.as_str()))
+            .iter()
+            .map(|(item, _)| required_properties.contains(&item.as_str()))
             .collect();
 
         if is_required.iter().any(|&x| x) {
@@ -114,11 +385,9 @@ impl<'a> Parser<'a> {
                 .max()
                 .unwrap();
 
-            for (i, (name, value)) in properties.iter().enumerate() {
-                let mut subregex =
-                    format!(r#"{0}"{1}"{0}:{0}"#, self.whitespace_pattern, escape(name));
-                subregex += &mut match self.to_regex(value) {
-                    Ok(regex) => regex,
+            for (i, &(name, value)) in properties.iter().enumerate() {
+                let mut subregex = match self.to_regex(value) {
+                    Ok(regex) => self.property(name, &regex),
                     Err(e) if e.is_recursion_limit() => continue,
                     Err(e) => return Err(e),
                 };
@@ -139,11 +408,9 @@ impl<'a> Parser<'a> {
             }
         } else {
             let mut property_subregexes = Vec::new();
-            for (name, value) in properties.iter() {
-                let mut subregex =
-                    format!(r#"{0}"{1}"{0}:{0}"#, self.whitespace_pattern, escape(name));
-                subregex += &mut match self.to_regex(value) {
-                    Ok(regex) => regex,
+            for &(name, value) in properties.iter() {
+                let subregex = match self.to_regex(value) {
+                    Ok(regex) => self.property(name, &regex),
                     Err(e) if e.is_recursion_limit() => continue,
                     Err(e) => return Err(e),
                 };
@@ -167,28 +434,261 @@ impl<'a> Parser<'a> {
         Ok(regex)
     }
 
-    fn parse_all_of(&mut self, obj: &serde_json::Map<String, Value>) -> Result<String> {
-        match obj.get("allOf") {
-            Some(Value::Array(all_of)) => {
-                let subregexes: Result<Vec<String>> =
-                    all_of.iter().map(|t| self.to_regex(t)).collect();
+    /// Whether the name of a listed property satisfies the `propertyNames` schema, as far as
+    /// its listed values and the keywords validating them can tell.
+    fn is_property_name(&self, property_names: Option<&Value>, name: &str) -> bool {
+        let name = Value::String(name.to_string());
+        match property_names {
+            Some(Value::Bool(false)) => false,
+            Some(Value::Object(names)) => {
+                names
+                    .get("enum")
+                    .and_then(Value::as_array)
+                    .is_none_or(|values| values.contains(&name))
+                    && names.get("const").is_none_or(|value| *value == name)
+                    && enums::violated_keyword(names, &name, self.formats).is_none()
+            }
+            _ => true,
+        }
+    }
+
+    /// Member of a listed property, with its leading whitespace, so that a missing optional
+    /// member doesn't leave the whitespace around it twice.
+    fn property(&self, name: &str, value_regex: &str) -> String {
+        let key = format!(r#""{}""#, escape(name));
+        format!(
+            "{}{}",
+            self.whitespace_pattern,
+            self.member(&key, value_regex)
+        )
+    }
+
+    /// Converts an object with `dependentRequired` or `dependentSchemas` into the alternatives
+    /// of the presence of its optional properties, which others depend on: a present one is
+    /// required with its dependencies, an absent one isn't listed. Alternatives contradicting
+    /// the dependencies are skipped, so that a property required by a present one is present.
+    fn parse_dependencies(&mut self, obj: &serde_json::Map<String, Value>) -> Result<String> {
+        let unsupported = || Error::UnsupportedJsonSchema(Box::new(Value::Object(obj.clone())));
+        let empty = serde_json::Map::new();
+        let dependent_required = match obj.get("dependentRequired") {
+            Some(Value::Object(dependent_required)) => dependent_required,
+            Some(_) => return Err(unsupported()),
+            None => &empty,
+        };
+        let dependent_schemas = match obj.get("dependentSchemas") {
+            Some(Value::Object(dependent_schemas)) => dependent_schemas,
+            Some(_) => return Err(unsupported()),
+            None => &empty,
+        };
+        let mut dependencies: HashMap<&str, Vec<&str>> = HashMap::default();
+        for (name, required) in dependent_required {
+            let Value::Array(required) = required else {
+                return Err(unsupported());
+            };
+            let required = required
+                .iter()
+                .map(|dependency| dependency.as_str().ok_or_else(unsupported))
+                .collect::<Result<_>>()?;
+            dependencies.insert(name.as_str(), required);
+        }
+        let properties = obj
+            .get("properties")
+            .and_then(Value::as_object)
+            .ok_or_else(|| Error::PropertiesNotFound)?;
+        let required: Vec<&str> = obj
+            .get("required")
+            .and_then(Value::as_array)
+            .map(|arr| arr.iter().filter_map(Value::as_str).collect())
+            .unwrap_or_default();
+        let is_dependency =
+            |name: &str| dependencies.contains_key(name) || dependent_schemas.contains_key(name);
+        // Unlisted properties are never generated, so their dependencies never apply.
+        let optional: Vec<&str> = self
+            .ordered(properties)
+            .into_iter()
+            .map(|(name, _)| name.as_str())
+            .filter(|name| is_dependency(name) && !required.contains(name))
+            .collect();
 
-                let subregexes = subregexes?;
-                let combined_regex = subregexes.join("");
+        let mut base = obj.clone();
+        base.remove("dependentRequired");
+        base.remove("dependentSchemas");
+        let mut subregexes: Vec<String> = Vec::new();
+        let mut unsatisfiable = None;
+        let mut len = 0;
+        'presence: for presence in 0..1u64.checked_shl(optional.len() as u32).unwrap_or(u64::MAX) {
+            let is_present = |i: usize| presence.checked_shr(i as u32).unwrap_or(0) & 1 == 1;
+            let absent: Vec<&str> = (0..optional.len())
+                .filter(|&i| !is_present(i))
+                .map(|i| optional[i])
+                .collect();
+            let mut present = required.clone();
+            present.extend(
+                (0..optional.len())
+                    .filter(|&i| is_present(i))
+                    .map(|i| optional[i]),
+            );
+            let mut all_required = present.clone();
+            let mut schemas = vec![];
+            for name in present {
+                for &dependency in dependencies.get(name).into_iter().flatten() {
+                    if absent.contains(&dependency) {
+                        continue 'presence;
+                    }
+                    if !all_required.contains(&dependency) {
+                        all_required.push(dependency);
+                    }
+                }
+                schemas.extend(dependent_schemas.get(name).cloned());
+            }
 
-                Ok(format!(r"({})", combined_regex))
+            let mut variant = base.clone();
+            let listed: serde_json::Map<String, Value> = properties
+                .iter()
+                .filter(|(name, _)| !absent.contains(&name.as_str()))
+                .map(|(name, schema)| (name.clone(), schema.clone()))
+                .collect();
+            variant.insert("properties".to_string(), Value::Object(listed));
+            variant.insert("required".to_string(), json!(all_required));
+            let variant = if schemas.is_empty() {
+                Value::Object(variant)
+            } else {
+                schemas.insert(0, Value::Object(variant));
+                json!({ "allOf": schemas })
+            };
+            match self.to_regex(&variant) {
+                Ok(regex) if subregexes.contains(&regex) => {}
+                Ok(regex) => {
+                    // The alternatives grow exponentially with the optional properties.
+                    len += regex.len() + 1;
+                    if len > self.max_regex_len {
+                        return Err(Error::RegexTooLarge {
+                            len,
+                            limit: self.max_regex_len,
+                            path_of_largest_node: self.path.clone(),
+                        });
+                    }
+                    subregexes.push(regex);
+                }
+                Err(e) if e.is_unsatisfiable() => {
+                    unsatisfiable.get_or_insert(e);
+                }
+                Err(e) => return Err(e),
             }
+        }
+        match unsatisfiable {
+            Some(e) if subregexes.is_empty() => Err(e),
+            _ => Ok(format!("({})", subregexes.join("|"))),
+        }
+    }
+
+    fn parse_all_of(
+        &mut self,
+        json: &Value,
+        obj: &serde_json::Map<String, Value>,
+    ) -> Result<String> {
+        match obj.get("allOf") {
+            Some(Value::Array(_)) => self.parse_combined(json),
             _ => Err(Error::AllOfMustBeAnArray),
         }
     }
 
+    /// Intersects the node with its `allOf` members and `$ref` targets, recursively, then
+    /// converts the intersection.
+    fn parse_combined(&mut self, json: &Value) -> Result<String> {
+        let keyword = if json.get("allOf").is_some() {
+            "allOf"
+        } else {
+            "$ref"
+        };
+        self.parse_intersection(keyword, &[json])
+    }
+
+    /// Intersects the nodes with their `allOf` members and `$ref` targets, recursively, then
+    /// converts the intersection, recorded as a normalization of `keyword`.
+    ///
+    /// Members are borrowed from the schema and copied only into the intersection of several
+    /// constraining ones, so that large referenced definitions aren't copied.
+    fn parse_intersection(&mut self, keyword: &str, nodes: &[&Value]) -> Result<String> {
+        let mut members = Vec::new();
+        let mut refs = 0;
+        for node in nodes {
+            self.flatten(node, &mut members, &mut refs)?;
+        }
+        self.normalized(keyword, NormalizationKind::Combined);
+
+        // A single constraining member, e.g. a referenced definition, is converted in place.
+        let mut constraining = members.iter().filter(|member| is_constraining(member));
+        let node = match (constraining.next(), constraining.next()) {
+            (Some(&member), None) if !is_combined(member) => Cow::Borrowed(member),
+            _ => {
+                let mut conflicts = Vec::new();
+                let mut node = serde_json::Map::new();
+                for member in members.iter().filter_map(|member| member.as_object()) {
+                    node = combine::intersect(node, member, &mut conflicts).map_err(|keyword| {
+                        Error::UnsatisfiableSchema {
+                            path: self.path.clone(),
+                            keyword: keyword.to_string(),
+                        }
+                    })?;
+                }
+                conflicts.dedup();
+                self.report_ignored(&conflicts.iter().map(String::as_str).collect::<Vec<_>>())?;
+                Cow::Owned(Value::Object(node))
+            }
+        };
+
+        // Resolved references count towards the recursion limit, until the intersection
+        // is converted.
+        self.recursion_depth += refs;
+        let result = self.to_regex(&node);
+        self.recursion_depth -= refs;
+        result
+    }
+
+    /// Collects the node and its `allOf` members and `$ref` targets, recursively, whose
+    /// keywords other than `allOf` and `$ref` are intersected.
+    fn flatten<'v>(
+        &self,
+        json: &'v Value,
+        members: &mut Vec<&'v Value>,
+        refs: &mut usize,
+    ) -> Result<()>
+    where
+        'a: 'v,
+    {
+        let obj = match json {
+            Value::Object(obj) => obj,
+            Value::Bool(true) => return Ok(()),
+            json => return Err(Error::UnsupportedJsonSchema(Box::new(json.clone()))),
+        };
+        members.push(json);
+        if let Some(reference) = obj.get("$ref") {
+            if self.recursion_depth + *refs > self.max_recursion_depth {
+                return Err(Error::RefRecursionLimitReached(self.max_recursion_depth));
+            }
+            *refs += 1;
+            self.flatten(self.resolve_ref(reference)?, members, refs)?;
+        }
+        match obj.get("allOf") {
+            Some(Value::Array(all_of)) => {
+                for subschema in all_of {
+                    self.flatten(subschema, members, refs)?;
+                }
+            }
+            Some(_) => return Err(Error::AllOfMustBeAnArray),
+            None => {}
+        }
+        Ok(())
+    }
+
     fn parse_any_of(&mut self, obj: &serde_json::Map<String, Value>) -> Result<String> {
         match obj.get("anyOf") {
             Some(Value::Array(any_of)) => {
-                let subregexes: Result<Vec<String>> =
-                    any_of.iter().map(|t| self.to_regex(t)).collect();
-
-                let subregexes = subregexes?;
+                let subregexes = self.parse_alternatives(obj, "anyOf", any_of)?;
+                if let Some(regex) = nullable(&subregexes) {
+                    return Ok(regex);
+                }
 
                 Ok(format!(r"({})", subregexes.join("|")))
             }
@@ -199,10 +699,10 @@ impl<'a> Parser<'a> {
     fn parse_one_of(&mut self, obj: &serde_json::Map<String, Value>) -> Result<String> {
         match obj.get("oneOf") {
             Some(Value::Array(one_of)) => {
-                let subregexes: Result<Vec<String>> =
-                    one_of.iter().map(|t| self.to_regex(t)).collect();
-
-                let subregexes = subregexes?;
+                let subregexes = self.parse_alternatives(obj, "oneOf", one_of)?;
+                if let Some(regex) = nullable(&subregexes) {
+                    return Ok(regex);
+                }
                 let xor_patterns: Vec<String> = subregexes
                     .into_iter()
                     .map(|subregex| format!(r"(?:{})", subregex))
@@ -214,46 +714,201 @@ impl<'a> Parser<'a> {
         }
     }
 
+    /// Converts the alternatives of `anyOf` or `oneOf`, each one combined with the other
+    /// keywords of the node. Unsatisfiable alternatives are skipped, unless all of them are.
+    ///
+    /// Duplicated alternatives, e.g. of nullable expansions, are emitted once: identical
+    /// ones are not converted again and the ones converted to the same regular expression
+    /// are dropped.
+    fn parse_alternatives(
+        &mut self,
+        obj: &serde_json::Map<String, Value>,
+        keyword: &str,
+        alternatives: &[Value],
+    ) -> Result<Vec<String>> {
+        let siblings: serde_json::Map<String, Value> = obj
+            .iter()
+            .filter(|(key, _)| *key != keyword && !keywords::is_annotation(key))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+        let siblings = (!siblings.is_empty()).then_some(Value::Object(siblings));
+        let mut subregexes: Vec<String> = Vec::with_capacity(alternatives.len());
+        let mut unsatisfiable = None;
+        let mut removed = 0;
+        for (i, alternative) in alternatives.iter().enumerate() {
+            if alternatives[..i].contains(alternative) {
+                removed += 1;
+                continue;
+            }
+            let result = match &siblings {
+                None => self.to_regex(alternative),
+                Some(siblings) => self.parse_intersection("allOf", &[siblings, alternative]),
+            };
+            match result {
+                Ok(regex) if subregexes.contains(&regex) => removed += 1,
+                Ok(regex) => subregexes.push(regex),
+                Err(e) if e.is_unsatisfiable() => {
+                    unsatisfiable.get_or_insert(e);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        if removed > 0 {
+            self.normalized(keyword, NormalizationKind::DeduplicatedBranches { removed });
+        }
+        match unsatisfiable {
+            Some(e) if subregexes.is_empty() => Err(e),
+            _ => Ok(subregexes),
+        }
+    }
+
+    /// Converts a tuple, which is closed unless `items`, or `unevaluatedItems`, allow items
+    /// after the prefix, within the bounds of `minItems` and `maxItems`.
     fn parse_prefix_items(&mut self, obj: &serde_json::Map<String, Value>) -> Result<String> {
-        match obj.get("prefixItems") {
-            Some(Value::Array(prefix_items)) => {
-                let element_patterns: Result<Vec<String>> =
-                    prefix_items.iter().map(|t| self.to_regex(t)).collect();
+        let Some(Value::Array(prefix_items)) = obj.get("prefixItems") else {
+            return Err(Error::PrefixItemsMustBeAnArray);
+        };
+        let additional = Self::array_items(obj);
+        if prefix_items.is_empty() && additional.is_some() {
+            // Only the items after the prefix are left.
+            let mut array = obj.clone();
+            array.remove("prefixItems");
+            return self.parse_array_type(&array);
+        }
+
+        let element_patterns: Result<Vec<String>> =
+            prefix_items.iter().map(|t| self.to_regex(t)).collect();
+        let element_patterns = element_patterns?;
+        let tuple_inner = element_patterns.join(&self.separator());
+
+        // The prefix is always generated in full.
+        let length = prefix_items.len() as u64;
+        let min_items = obj.get("minItems").and_then(Value::as_u64);
+        let max_items = obj.get("maxItems").and_then(Value::as_u64);
+        if additional.is_some() && max_items.is_some_and(|max| max < length) {
+            return Err(Error::UnsupportedJsonSchema(Box::new(Value::Object(
+                obj.clone(),
+            ))));
+        }
+        let additional_items = match additional {
+            None => String::new(),
+            Some(Value::Bool(false)) if min_items.is_some_and(|min| min > length) => {
+                return Err(Error::UnsatisfiableSchema {
+                    path: self.path.clone(),
+                    keyword: "minItems".to_string(),
+                })
+            }
+            Some(Value::Bool(false)) => String::new(),
+            Some(items) => {
+                let max_items = max_items
+                    .or_else(|| self.default_max("maxItems", self.default_max_items, min_items));
+                let min_additional = min_items.unwrap_or(0).saturating_sub(length);
+                let repeats = match max_items.map(|max| max - length) {
+                    Some(0) => "{0}".to_string(),
+                    Some(max_additional) => format!("{{{min_additional},{max_additional}}}"),
+                    None => format!("{{{min_additional},}}"),
+                };
+                let items_regex = self.items_regex(items)?;
+                format!("({}({items_regex})){repeats}", self.separator())
+            }
+        };
 
-                let element_patterns = element_patterns?;
+        Ok(self.delimited(r"\[", &(tuple_inner + &additional_items), r"\]"))
+    }
 
-                let comma_split_pattern = format!("{0},{0}", self.whitespace_pattern);
-                let tuple_inner = element_patterns.join(&comma_split_pattern);
+    /// Schema of the array items after `prefixItems`, given by `items` or, without it, by
+    /// `unevaluatedItems`, which applies to the same items then.
+    fn array_items(obj: &serde_json::Map<String, Value>) -> Option<&Value> {
+        obj.get("items").or_else(|| obj.get("unevaluatedItems"))
+    }
 
-                Ok(format!(r"\[{0}{tuple_inner}{0}\]", self.whitespace_pattern))
-            }
-            _ => Err(Error::PrefixItemsMustBeAnArray),
+    /// Regular expression of array items, `true` allows any value.
+    fn items_regex(&mut self, items: &Value) -> Result<String> {
+        match items {
+            Value::Bool(true) => self.to_regex(&json!({})),
+            items => self.to_regex(items),
         }
     }
 
+    /// Separator of array items and object members, whitespace is allowed on both sides.
+    fn separator(&self) -> String {
+        format!("{0},{0}", self.whitespace_pattern)
+    }
+
+    /// Object member of the key and value patterns.
+    fn member(&self, key: &str, value: &str) -> String {
+        format!("{key}{0}:{0}{value}", self.whitespace_pattern)
+    }
+
+    /// Array or object of the inner pattern, whitespace is allowed after the opening delimiter
+    /// and before the closing one.
+    fn delimited(&self, open: &str, inner: &str, close: &str) -> String {
+        format!("{open}{0}{inner}{0}{close}", self.whitespace_pattern)
+    }
+
     fn parse_enum(&mut self, obj: &serde_json::Map<String, Value>) -> Result<String> {
         match obj.get("enum") {
-            Some(Value::Array(enum_values)) => {
-                let choices: Result<Vec<String>> = enum_values
-                    .iter()
+            Some(Value::Array(enum_values)) if !enum_values.is_empty() => {
+                let choices: Result<Vec<String>> = self
+                    .valid_values(obj, "enum")?
+                    .into_iter()
                     .map(|choice| self.parse_const_value(choice))
                     .collect();
 
                 let choices = choices?;
                 Ok(format!(r"({})", choices.join("|")))
             }
+            Some(Value::Array(_)) => Ok("()".to_string()),
             _ => Err(Error::EnumMustBeAnArray),
         }
     }
 
     fn parse_const(&mut self, obj: &serde_json::Map<String, Value>) -> Result<String> {
-        if let Some(const_value) = obj.get("const") {
-            self.parse_const_value(const_value)
+        if obj.contains_key("const") {
+            let values = self.valid_values(obj, "const")?;
+            self.parse_const_value(values[0])
         } else {
             Err(Error::ConstKeyNotFound)
         }
     }
 
+    /// Keeps the values listed by `enum` or `const`, which satisfy the sibling keywords of
+    /// the node, reporting the excluded ones. Fails when no value is left.
+    fn valid_values<'b>(
+        &self,
+        obj: &'b serde_json::Map<String, Value>,
+        keyword: &str,
+    ) -> Result<Vec<&'b Value>> {
+        let listed = &obj[keyword];
+        let values = match listed {
+            Value::Array(values) if keyword == "enum" => values.as_slice(),
+            value => std::slice::from_ref(value),
+        };
+        let mut valid = Vec::with_capacity(values.len());
+        for value in values {
+            let Some(violated) = enums::violated_keyword(obj, value, self.formats) else {
+                valid.push(value);
+                continue;
+            };
+            if let KeywordPolicy::Warn(collector) = &self.keyword_policy {
+                collector.report(SchemaWarning {
+                    path: keywords::pointer_of(self.root, value)
+                        .unwrap_or_else(|| self.path.clone()),
+                    keyword: violated.to_string(),
+                    kind: WarningKind::ExcludedEnumValue,
+                    detail: None,
+                });
+            }
+        }
+        if valid.is_empty() {
+            return Err(Error::NoValidEnumValue {
+                path: keywords::pointer_of(self.root, listed).unwrap_or_else(|| self.path.clone()),
+                keyword: keyword.to_string(),
+            });
+        }
+        Ok(valid)
+    }
+
     fn parse_const_value(&self, value: &Value) -> Result<String> {
         match value {
             Value::Array(array_values) => {
@@ -261,23 +916,20 @@ impl<'a> Parser<'a> {
                     .iter()
                     .map(|value| self.parse_const_value(value))
                     .collect::<Result<Vec<String>>>()?
-                    .join(format!("{0},{0}", self.whitespace_pattern).as_str());
-                Ok(format!(r"\[{0}{inner_regex}{0}\]", self.whitespace_pattern))
+                    .join(&self.separator());
+                Ok(self.delimited(r"\[", &inner_regex, r"\]"))
             }
             Value::Object(obj) => {
-                let inner_regex = obj
-                    .iter()
+                let inner_regex = self
+                    .ordered(obj)
+                    .into_iter()
                     .map(|(key, value)| {
-                        self.parse_const_value(value).map(|value_regex| {
-                            format!(r#""{key}"{0}:{0}{value_regex}"#, self.whitespace_pattern)
-                        })
+                        self.parse_const_value(value)
+                            .map(|value_regex| self.member(&format!(r#""{key}""#), &value_regex))
                     })
                     .collect::<Result<Vec<String>>>()?
-                    .join(format!("{0},{0}", self.whitespace_pattern).as_str());
-                Ok(format!(
-                    r"\{{{0}{inner_regex}{0}\}}",
-                    self.whitespace_pattern
-                ))
+                    .join(&self.separator());
+                Ok(self.delimited(r"\{", &inner_regex, r"\}"))
             }
             _ => {
                 let json_string = serde_json::to_string(value)?;
@@ -286,41 +938,29 @@ impl<'a> Parser<'a> {
         }
     }
 
-    fn parse_ref(&mut self, obj: &serde_json::Map<String, Value>) -> Result<String> {
+    fn parse_ref(&mut self, json: &Value, obj: &serde_json::Map<String, Value>) -> Result<String> {
+        // Keywords next to the reference constrain the referenced schema.
+        if obj
+            .keys()
+            .any(|keyword| keyword != "$ref" && !keywords::is_annotation(keyword))
+        {
+            return self.parse_combined(json);
+        }
         if self.recursion_depth > self.max_recursion_depth {
             return Err(Error::RefRecursionLimitReached(self.max_recursion_depth));
         }
+        let referenced_schema = self.resolve_ref(&obj["$ref"])?;
         self.recursion_depth += 1;
-        let ref_path = obj["$ref"]
-            .as_str()
-            .ok_or_else(|| Error::RefMustBeAString)?;
-
-        let parts: Vec<&str> = ref_path.split('#').collect();
-
-        let result = match parts.as_slice() {
-            [fragment] | ["", fragment] => {
-                let path_parts: Vec<&str> =
-                    fragment.split('/').filter(|&s| !s.is_empty()).collect();
-                let referenced_schema = Self::resolve_local_ref(self.root, &path_parts)?;
-                self.to_regex(referenced_schema)
-            }
-            [base, fragment] => {
-                if let Some(id) = self.root["$id"].as_str() {
-                    if *base == id || base.is_empty() {
-                        let path_parts: Vec<&str> =
-                            fragment.split('/').filter(|&s| !s.is_empty()).collect();
-                        let referenced_schema = Self::resolve_local_ref(self.root, &path_parts)?;
-                        return self.to_regex(referenced_schema);
-                    }
-                }
-                Err(Error::ExternalReferencesNotSupported(Box::from(ref_path)))
-            }
-            _ => Err(Error::InvalidReferenceFormat(Box::from(ref_path))),
-        };
+        let result = self.to_regex(referenced_schema);
         self.recursion_depth -= 1;
         result
     }
 
+    /// Finds the schema node referenced by the value of `$ref`.
+    fn resolve_ref(&self, reference: &Value) -> Result<&'a Value> {
+        resolve_ref(self.root, reference)
+    }
+
     fn parse_type(&mut self, obj: &serde_json::Map<String, Value>) -> Result<String> {
         match obj.get("type") {
             Some(Value::String(instance_type)) => self.parse_type_string(instance_type, obj),
@@ -389,8 +1029,23 @@ impl<'a> Parser<'a> {
                 _ => {}
             }
 
+            if let Some(pattern) = obj.get("pattern").and_then(Value::as_str) {
+                let min_len = min_items.and_then(Value::as_u64).unwrap_or(0);
+                let max_len = max_items.and_then(Value::as_u64);
+                if let Some(regex) = self.bounded_pattern(pattern, min_len, max_len)? {
+                    return Ok(regex);
+                }
+            }
+
             let formatted_max = max_items
                 .and_then(Value::as_u64)
+                .or_else(|| {
+                    self.default_max(
+                        "maxLength",
+                        self.default_max_string,
+                        min_items.and_then(Value::as_u64),
+                    )
+                })
                 .map_or("".to_string(), |n| format!("{}", n));
             let formatted_min = min_items
                 .and_then(Value::as_u64)
@@ -403,18 +1058,55 @@ impl<'a> Parser<'a> {
                 formatted_max,
             ))
         } else if let Some(pattern) = obj.get("pattern").and_then(Value::as_str) {
-            if pattern.starts_with('^') && pattern.ends_with('$') {
-                Ok(format!(r#"("{}")"#, &pattern[1..pattern.len() - 1]))
-            } else {
-                Ok(format!(r#"("{}")"#, pattern))
-            }
+            let transpiled = pattern::transpile_pattern(pattern);
+            self.report_pattern_changes(&transpiled.changes)?;
+            let pattern = pattern::normalize_anchors(&transpiled.pattern, self.pattern_anchoring);
+            Ok(format!(r#"("{pattern}")"#))
         } else if let Some(format) = obj.get("format").and_then(Value::as_str) {
+            if let Some(pattern) = self.formats.and_then(|formats| formats.get(format)) {
+                return Ok(format!(r#""({pattern})""#));
+            }
             match types::FormatType::from_str(format) {
                 Some(format_type) => Ok(format_type.to_regex().to_string()),
                 None => Err(Error::StringTypeUnsupportedFormat(Box::from(format))),
             }
         } else {
-            Ok(types::JsonType::String.to_regex().to_string())
+            Ok(self.string_regex("maxLength"))
+        }
+    }
+
+    /// Regular expression of the strings matching `pattern` within the length bounds, built
+    /// from the product of their automata. If the product can't be expressed, the pattern is
+    /// reported as ignored and `None` is returned, so that only the bounds are kept.
+    fn bounded_pattern(
+        &self,
+        pattern: &str,
+        min_len: u64,
+        max_len: Option<u64>,
+    ) -> Result<Option<String>> {
+        let transpiled = pattern::transpile_pattern(pattern);
+        self.report_pattern_changes(&transpiled.changes)?;
+        let pattern = pattern::normalize_anchors(&transpiled.pattern, self.pattern_anchoring);
+        let len = |n: u64| usize::try_from(n).unwrap_or(usize::MAX);
+        match compose::bounded_length(&pattern, len(min_len), max_len.map(len))? {
+            Composition::Regex(regex) => Ok(Some(format!(r#"("{regex}")"#))),
+            Composition::Empty => Err(Error::UnsatisfiableSchema {
+                path: self.path.clone(),
+                keyword: "pattern".to_string(),
+            }),
+            Composition::Unsupported => {
+                self.report_ignored(&["pattern"])?;
+                Ok(None)
+            }
+        }
+    }
+
+    /// Regular expression of a string without constraints, bounded by `default_max_string`,
+    /// which is recorded as the default of `keyword`.
+    fn string_regex(&self, keyword: &str) -> String {
+        match self.default_max(keyword, self.default_max_string, None) {
+            Some(max_length) => format!(r#""{}{{0,{}}}""#, types::STRING_INNER, max_length),
+            None => types::JsonType::String.to_regex().to_string(),
         }
     }
 
@@ -475,8 +1167,36 @@ impl<'a> Parser<'a> {
                 integers_quantifier, fraction_quantifier, exponent_quantifier
             ))
         } else {
-            let format_type = types::JsonType::Number;
-            Ok(format_type.to_regex().to_string())
+            let (min, max) = Self::decimal_bounds(obj);
+            let unsatisfiable = || {
+                let keyword = if ranges::bounded_decimal(min.as_ref(), max.as_ref(), None).is_some()
+                {
+                    "multipleOf"
+                } else {
+                    ["minimum", "exclusiveMinimum"]
+                        .into_iter()
+                        .find(|keyword| obj.contains_key(*keyword))
+                        .unwrap_or("maximum")
+                };
+                Error::UnsatisfiableSchema {
+                    path: self.path.clone(),
+                    keyword: keyword.to_string(),
+                }
+            };
+            match (self.multiple_of_power(obj)?, &min, &max) {
+                (None, None, None) => Ok(types::JsonType::Number.to_regex().to_string()),
+                // Multiples of an integer are integers.
+                (Some(power @ 0..), _, _) => {
+                    let (min, max) = Self::integer_bounds(obj);
+                    ranges::bounded_multiple(min, max, power.unsigned_abs())
+                        .ok_or_else(unsatisfiable)
+                }
+                (power, min, max) => {
+                    let fraction = power.map(i32::unsigned_abs);
+                    ranges::bounded_decimal(min.as_ref(), max.as_ref(), fraction)
+                        .ok_or_else(unsatisfiable)
+                }
+            }
         }
     }
 
@@ -497,20 +1217,122 @@ impl<'a> Parser<'a> {
 
             Ok(format!(r"(-)?(0|[1-9][0-9]{})", quantifier))
         } else {
-            let format_type = types::JsonType::Integer;
-            Ok(format_type.to_regex().to_string())
+            let format_range = obj
+                .get("format")
+                .and_then(Value::as_str)
+                .and_then(ranges::format_range);
+            let (min, max) = Self::integer_bounds(obj);
+            let min = [min, format_range.map(|(min, _)| min)]
+                .into_iter()
+                .flatten()
+                .max();
+            let max = [max, format_range.map(|(_, max)| max)]
+                .into_iter()
+                .flatten()
+                .min();
+            // Every integer is a multiple of the fractional powers of ten.
+            let zeros = self
+                .multiple_of_power(obj)?
+                .map_or(0, |power| power.max(0).unsigned_abs());
+            match (min, max) {
+                (None, None) if zeros == 0 => Ok(types::JsonType::Integer.to_regex().to_string()),
+                (Some(min), Some(max)) if min > max => Err(Error::UnsatisfiableSchema {
+                    path: self.path.clone(),
+                    keyword: ["minimum", "exclusiveMinimum", "format"]
+                        .into_iter()
+                        .find(|keyword| obj.contains_key(*keyword))
+                        .unwrap_or("minimum")
+                        .to_string(),
+                }),
+                (min, max) => ranges::bounded_multiple(min, max, zeros).ok_or_else(|| {
+                    Error::UnsatisfiableSchema {
+                        path: self.path.clone(),
+                        keyword: "multipleOf".to_string(),
+                    }
+                }),
+            }
         }
     }
 
-    fn parse_object_type(&mut self, obj: &serde_json::Map<String, Value>) -> Result<String> {
-        let min_properties = obj.get("minProperties").and_then(|v| v.as_u64());
-        let max_properties = obj.get("maxProperties").and_then(|v| v.as_u64());
+    /// Power of ten of `multipleOf`, the only multiples supported, e.g. `2` for `100` and `-2`
+    /// for `0.01`.
+    fn multiple_of_power(&self, obj: &serde_json::Map<String, Value>) -> Result<Option<i32>> {
+        let Some(multiple) = obj.get("multipleOf") else {
+            return Ok(None);
+        };
+        match multiple.as_f64().and_then(ranges::power_of_ten) {
+            Some(power) => Ok(Some(power)),
+            None => Err(Error::UnsupportedMultipleOf {
+                path: self.path.clone(),
+                multiple: Box::new(multiple.clone()),
+            }),
+        }
+    }
 
-        let num_repeats = Self::get_num_items_pattern(min_properties, max_properties);
+    /// Inclusive bounds of an integer from `minimum`, `maximum`, and their exclusive
+    /// counterparts, see [`Self::decimal_bounds`]. A bound beyond the range of `i64` is
+    /// clamped to it, or dropped if it doesn't constrain the `i64` integers.
+    fn integer_bounds(obj: &serde_json::Map<String, Value>) -> (Option<i64>, Option<i64>) {
+        let (lower, upper) = Self::decimal_bounds(obj);
+        (
+            lower.map(|min| min.ceil()).filter(|&min| min > i64::MIN),
+            upper.map(|max| max.floor()).filter(|&max| max < i64::MAX),
+        )
+    }
 
-        if num_repeats.is_none() {
+    /// Bounds of a number from `minimum`, `maximum`, and their exclusive counterparts, the
+    /// tightest ones being kept. A bound beyond the range of `u64` saturates, or is dropped if
+    /// it doesn't constrain the numbers within that range.
+    fn decimal_bounds(
+        obj: &serde_json::Map<String, Value>,
+    ) -> (Option<DecimalBound>, Option<DecimalBound>) {
+        let bound = |keyword: &str, exclusive: bool| {
+            let value = obj.get(keyword)?;
+            match value.as_i64() {
+                Some(bound) => Some(DecimalBound::from_i64(bound, exclusive)),
+                None => DecimalBound::from_f64(value.as_f64()?, exclusive),
+            }
+        };
+        // The greatest lower bound, exclusive before inclusive at the same value.
+        let lower = [bound("minimum", false), bound("exclusiveMinimum", true)]
+            .into_iter()
+            .flatten()
+            .max_by(|a, b| a.cmp_value(b).then(a.exclusive.cmp(&b.exclusive)))
+            .filter(|min| !min.negative || min.integer < u64::MAX);
+        let upper = [bound("maximum", false), bound("exclusiveMaximum", true)]
+            .into_iter()
+            .flatten()
+            .min_by(|a, b| a.cmp_value(b).then(b.exclusive.cmp(&a.exclusive)))
+            .filter(|max| max.negative || max.integer < u64::MAX);
+        (lower, upper)
+    }
+
+    fn parse_object_type(&mut self, obj: &serde_json::Map<String, Value>) -> Result<String> {
+        let min_properties = obj.get("minProperties").and_then(|v| v.as_u64());
+        // Without `properties`, no property is evaluated by the node itself.
+        let additional_properties = obj
+            .get("additionalProperties")
+            .or_else(|| obj.get("unevaluatedProperties"));
+        let property_names = obj.get("propertyNames");
+        if additional_properties == Some(&Value::Bool(false))
+            || property_names == Some(&Value::Bool(false))
+        {
+            if min_properties.is_some_and(|min| min > 0) {
+                return Err(Error::UnsatisfiableSchema {
+                    path: self.path.clone(),
+                    keyword: "minProperties".to_string(),
+                });
+            }
             return Ok(format!(r"\{{{}\}}", self.whitespace_pattern));
         }
+        let max_properties = obj
+            .get("maxProperties")
+            .and_then(|v| v.as_u64())
+            .or_else(|| self.default_max("maxProperties", self.default_max_props, min_properties));
+
+        let Some(num_repeats) = Self::get_num_items_pattern(min_properties, max_properties) else {
+            return Ok(format!(r"\{{{}\}}", self.whitespace_pattern));
+        };
 
         let allow_empty = if min_properties.unwrap_or(0) == 0 {
             "?"
@@ -518,8 +1340,6 @@ impl<'a> Parser<'a> {
             ""
         };
 
-        let additional_properties = obj.get("additionalProperties");
-
         let value_pattern = match additional_properties {
             None | Some(&Value::Bool(true)) => {
                 let mut legal_types = vec![
@@ -541,28 +1361,44 @@ impl<'a> Parser<'a> {
             Some(props) => self.to_regex(props)?,
         };
 
-        let key_value_pattern = format!(
-            "{}{1}:{1}{value_pattern}",
-            types::STRING,
-            self.whitespace_pattern,
-        );
-        let key_value_successor_pattern =
-            format!("{0},{0}{key_value_pattern}", self.whitespace_pattern,);
-        let multiple_key_value_pattern =
-            format!("({key_value_pattern}({key_value_successor_pattern}){{0,}}){allow_empty}");
-
-        let res = format!(
-            r"\{{{0}{1}{0}\}}",
-            self.whitespace_pattern, multiple_key_value_pattern
+        let key_pattern = self.property_names_regex(property_names)?;
+        let key_value_pattern = self.member(&key_pattern, &value_pattern);
+        let key_value_successor_pattern = format!("{}{key_value_pattern}", self.separator());
+        let multiple_key_value_pattern = format!(
+            "({key_value_pattern}({key_value_successor_pattern}){num_repeats}){allow_empty}"
         );
 
-        Ok(res)
+        Ok(self.delimited(r"\{", &multiple_key_value_pattern, r"\}"))
+    }
+
+    /// Regular expression of the keys of a map, constrained by the `propertyNames` schema, which
+    /// applies to strings. Keys may still be repeated, which JSON parsers usually tolerate.
+    fn property_names_regex(&mut self, property_names: Option<&Value>) -> Result<String> {
+        match property_names {
+            Some(Value::Object(names)) if !names.is_empty() => {
+                self.to_regex(&json!({"allOf": [{"type": "string"}, names]}))
+            }
+            _ => Ok(self.string_regex("propertyNames")),
+        }
     }
 
     fn parse_array_type(&mut self, obj: &serde_json::Map<String, Value>) -> Result<String> {
+        let min_items = obj.get("minItems").and_then(Value::as_u64);
+        if Self::array_items(obj) == Some(&Value::Bool(false)) {
+            // No item is allowed.
+            if min_items.is_some_and(|min| min > 0) {
+                return Err(Error::UnsatisfiableSchema {
+                    path: self.path.clone(),
+                    keyword: "minItems".to_string(),
+                });
+            }
+            return Ok(format!(r"\[{0}\]", self.whitespace_pattern));
+        }
         let num_repeats = Self::get_num_items_pattern(
-            obj.get("minItems").and_then(Value::as_u64),
-            obj.get("maxItems").and_then(Value::as_u64),
+            min_items,
+            obj.get("maxItems")
+                .and_then(Value::as_u64)
+                .or_else(|| self.default_max("maxItems", self.default_max_items, min_items)),
         )
         .unwrap_or_else(|| String::from(""));
 
@@ -576,12 +1412,13 @@ impl<'a> Parser<'a> {
             ""
         };
 
-        if let Some(items) = obj.get("items") {
-            let items_regex = self.to_regex(items)?;
-            Ok(format!(
-                r"\[{0}(({1})(,{0}({1})){2}){3}{0}\]",
-                self.whitespace_pattern, items_regex, num_repeats, allow_empty
-            ))
+        if let Some(items) = Self::array_items(obj).filter(|items| **items != Value::Bool(true)) {
+            let items_regex = self.items_regex(items)?;
+            let inner = format!(
+                "(({items_regex})({}({items_regex})){num_repeats}){allow_empty}",
+                self.separator()
+            );
+            Ok(self.delimited(r"\[", &inner, r"\]"))
         } else {
             // parse unconstrained object case
             let mut legal_types = vec![
@@ -603,24 +1440,14 @@ impl<'a> Parser<'a> {
 
             let regexes = regexes?;
             let regexes_joined = regexes.join("|");
-
-            Ok(format!(
-                r"\[{0}(({1})(,{0}({1})){2}){3}{0}\]",
-                self.whitespace_pattern, regexes_joined, num_repeats, allow_empty
-            ))
+            let inner = format!(
+                "(({regexes_joined})({}({regexes_joined})){num_repeats}){allow_empty}",
+                self.separator()
+            );
+            Ok(self.delimited(r"\[", &inner, r"\]"))
         }
     }
 
-    fn resolve_local_ref<'b>(schema: &'b Value, path_parts: &[&str]) -> Result<&'b Value> {
-        let mut current = schema;
-        for &part in path_parts {
-            current = current
-                .get(part)
-                .ok_or_else(|| Error::InvalidRefecencePath(Box::from(part)))?;
-        }
-        Ok(current)
-    }
-
     fn validate_quantifiers(
         min_bound: Option<u64>,
         max_bound: Option<u64>,
@@ -638,6 +1465,14 @@ impl<'a> Parser<'a> {
         Ok((min_bound.flatten(), max_bound.flatten()))
     }
 
+    /// Default upper bound, raised to the lower bound if it's below it, which is recorded as
+    /// the value of `keyword`.
+    fn default_max(&self, keyword: &str, default: Option<u64>, min: Option<u64>) -> Option<u64> {
+        let max = default.map(|max| max.max(min.unwrap_or(0)))?;
+        self.normalized(keyword, NormalizationKind::DefaultBound { value: max });
+        Some(max)
+    }
+
     fn get_num_items_pattern(min_items: Option<u64>, max_items: Option<u64>) -> Option<String> {
         let min_items = min_items.unwrap_or(0);
 
@@ -657,3 +1492,60 @@ impl<'a> Parser<'a> {
         }
     }
 }
+
+/// Finds the schema node of `root` referenced by the value of `$ref`, only local references
+/// are supported.
+/// Emits a value or `null`, e.g. of an `Optional` field of Pydantic, as `(null|value)`,
+/// without wrapping the value once more, since the alternatives are exclusive.
+fn nullable(subregexes: &[String]) -> Option<String> {
+    let value = match subregexes {
+        [value, null] | [null, value] if null == types::NULL && value != types::NULL => value,
+        _ => return None,
+    };
+    Some(format!("({}|{value})", types::NULL))
+}
+
+/// Whether properties of the object node depend on others.
+fn has_dependencies(obj: &serde_json::Map<String, Value>) -> bool {
+    obj.contains_key("dependentRequired") || obj.contains_key("dependentSchemas")
+}
+
+/// Whether the node has keywords to intersect, other than annotations.
+fn is_constraining(json: &Value) -> bool {
+    json.as_object().is_some_and(|obj| {
+        obj.keys()
+            .any(|keyword| !is_combinator(keyword) && !keywords::is_annotation(keyword))
+    })
+}
+
+/// Whether the node has `allOf` members or a `$ref` target to intersect with.
+fn is_combined(json: &Value) -> bool {
+    json.as_object()
+        .is_some_and(|obj| obj.keys().any(|keyword| is_combinator(keyword)))
+}
+
+fn is_combinator(keyword: &str) -> bool {
+    keyword == "allOf" || keyword == "$ref"
+}
+
+pub(super) fn resolve_ref<'a>(root: &'a Value, reference: &Value) -> Result<&'a Value> {
+    let ref_path = reference.as_str().ok_or_else(|| Error::RefMustBeAString)?;
+
+    let parts: Vec<&str> = ref_path.split('#').collect();
+
+    let fragment = match parts.as_slice() {
+        [fragment] | ["", fragment] => fragment,
+        [base, fragment] => match root["$id"].as_str() {
+            Some(id) if *base == id || base.is_empty() => fragment,
+            _ => return Err(Error::ExternalReferencesNotSupported(Box::from(ref_path))),
+        },
+        _ => return Err(Error::InvalidReferenceFormat(Box::from(ref_path))),
+    };
+    let mut current = root;
+    for part in fragment.split('/').filter(|&s| !s.is_empty()) {
+        current = current
+            .get(part)
+            .ok_or_else(|| Error::InvalidRefecencePath(Box::from(part)))?;
+    }
+    Ok(current)
+}
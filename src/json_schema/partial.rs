@@ -0,0 +1,202 @@
+//! Partial evaluation of a schema against known values of its properties, e.g. ids supplied
+//! by the caller, which are then generated verbatim.
+
+use serde_json::{json, Map, Value};
+
+use super::analysis::escape_pointer;
+use super::parsing::resolve_ref;
+use crate::{Error, Result};
+
+/// Fixes properties of the JSON schema to known values.
+///
+/// Each known property is constrained to its value with `const` and made required, so that
+/// the regular expression generated from the returned schema writes the value verbatim in
+/// place of the property schema, which shrinks the automaton. A known object is partial
+/// itself when the property is an object schema with `properties`, only its listed
+/// properties being fixed, while any other value is fixed as a whole.
+///
+/// The constraints are added to the `allOf` of the root, so that they are intersected with
+/// the schema once converted: it's an error to convert the schema if a known value violates
+/// its property schema, see [`Error::NoValidEnumValue`]. Unlisted properties are added,
+/// unless the schema forbids them, which fails with [`Error::UnsatisfiableSchema`].
+///
+/// # Example
+///
+/// ```rust
+/// # use outlines_core::Error;
+/// use outlines_core::json_schema;
+/// use serde_json::json;
+///
+/// # fn main() -> Result<(), Error> {
+/// let schema = json!({
+///     "type": "object",
+///     "properties": {
+///         "id": {"type": "integer"},
+///         "user": {
+///             "type": "object",
+///             "properties": {"name": {"type": "string"}, "role": {"type": "string"}}
+///         }
+///     }
+/// });
+/// let known = json!({"id": 42, "user": {"role": "admin"}});
+/// let partial = json_schema::partial_instance(&schema, known.as_object().unwrap())?;
+/// let regex = json_schema::regex_from_value(&partial, Some(""), None)?;
+/// assert!(regex.starts_with(r#"\{"id":42,"user":\{("name":"#));
+/// # Ok(())
+/// # }
+/// ```
+pub fn partial_instance(schema: &Value, known_values: &Map<String, Value>) -> Result<Value> {
+    if known_values.is_empty() {
+        return Ok(schema.clone());
+    }
+    let constraint = fixed_properties(schema, schema, known_values, "")?;
+    match schema {
+        Value::Object(obj) => {
+            // Definitions stay at the root, so that references still resolve.
+            let mut partial = obj.clone();
+            match partial.get_mut("allOf") {
+                Some(Value::Array(members)) => members.push(constraint),
+                _ => {
+                    partial.insert("allOf".to_string(), json!([constraint]));
+                }
+            }
+            Ok(Value::Object(partial))
+        }
+        _ => Ok(json!({"allOf": [schema, constraint]})),
+    }
+}
+
+/// Builds the schema fixing the known properties of the object schema at `path`.
+fn fixed_properties(
+    root: &Value,
+    schema: &Value,
+    known_values: &Map<String, Value>,
+    path: &str,
+) -> Result<Value> {
+    let schema = dereferenced(root, schema)?;
+    let declared = schema.get("properties").and_then(Value::as_object);
+    let mut properties = Map::new();
+    for (name, value) in known_values {
+        let property = declared.and_then(|declared| declared.get(name));
+        if property.is_none() {
+            if let Some(keyword) = ["additionalProperties", "unevaluatedProperties"]
+                .into_iter()
+                .find(|keyword| schema.get(*keyword) == Some(&Value::Bool(false)))
+            {
+                return Err(Error::UnsatisfiableSchema {
+                    path: path.to_string(),
+                    keyword: keyword.to_string(),
+                });
+            }
+        }
+        let fixed = match (value, property) {
+            (Value::Object(known_values), Some(property))
+                if dereferenced(root, property)?.get("properties").is_some() =>
+            {
+                let path = format!("{path}/properties/{}", escape_pointer(name));
+                fixed_properties(root, property, known_values, &path)?
+            }
+            _ => json!({"const": value}),
+        };
+        properties.insert(name.clone(), fixed);
+    }
+    let required: Vec<&String> = known_values.keys().collect();
+    Ok(json!({"properties": properties, "required": required}))
+}
+
+/// Follows the references of a schema node, which only refers to another one.
+fn dereferenced<'a>(root: &'a Value, mut schema: &'a Value) -> Result<&'a Value> {
+    let mut visited = Vec::new();
+    while let Some(reference) = schema.get("$ref") {
+        if schema.get("properties").is_some() || visited.contains(&reference) {
+            break;
+        }
+        visited.push(reference);
+        schema = resolve_ref(root, reference)?;
+    }
+    Ok(schema)
+}
+
+#[cfg(test)]
+mod tests {
+    use regex::Regex;
+
+    use super::*;
+    use crate::json_schema::regex_from_value;
+
+    fn known(values: Value) -> Map<String, Value> {
+        values.as_object().expect("Object expected").clone()
+    }
+
+    #[test]
+    fn fix_known_properties() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "id": {"type": "string", "format": "uuid"},
+                "owner": {"$ref": "#/$defs/user"},
+                "tags": {"type": "array", "items": {"type": "string"}},
+                "note": {"type": "string"}
+            },
+            "required": ["id"],
+            "$defs": {
+                "user": {
+                    "type": "object",
+                    "properties": {"name": {"type": "string"}, "age": {"type": "integer"}},
+                    "required": ["name"]
+                }
+            }
+        });
+        let id = "123e4567-e89b-12d3-a456-426614174000";
+        let partial = partial_instance(
+            &schema,
+            &known(json!({"id": id, "owner": {"age": 30}, "tags": ["a", "b"]})),
+        )
+        .expect("Partial failed");
+        let regex = regex_from_value(&partial, Some(""), None).expect("To regex failed");
+        let re = Regex::new(&format!("^{regex}$")).expect("Regex failed");
+        let prefix = format!(r#"{{"id":"{id}","owner":{{"name":"Bo","age":30}},"tags":["a","b"]"#);
+        assert!(re.is_match(&format!("{prefix}}}")));
+        assert!(re.is_match(&format!(r#"{prefix},"note":"x"}}"#)));
+        assert!(!re.is_match(&format!(
+            r#"{{"id":"{id}","owner":{{"name":"Bo","age":31}},"tags":["a","b"]}}"#
+        )));
+        assert!(!re.is_match(&format!(
+            r#"{{"id":"{id}","owner":{{"name":"Bo","age":30}}}}"#
+        )));
+        let full = regex_from_value(&schema, Some(""), None).expect("To regex failed");
+        assert!(regex.len() < full.len());
+
+        assert_eq!(
+            partial_instance(&schema, &Map::new()).expect("Partial failed"),
+            schema
+        );
+    }
+
+    #[test]
+    fn reject_invalid_known_values() {
+        let schema = json!({
+            "type": "object",
+            "properties": {"id": {"type": "integer"}},
+            "additionalProperties": false
+        });
+        match partial_instance(&schema, &known(json!({"name": "a"}))) {
+            Err(Error::UnsatisfiableSchema { path, keyword }) => {
+                assert_eq!(path, "");
+                assert_eq!(keyword, "additionalProperties");
+            }
+            other => unreachable!("Unexpected result {other:?}"),
+        }
+
+        let partial =
+            partial_instance(&schema, &known(json!({"id": "a"}))).expect("Partial failed");
+        match regex_from_value(&partial, None, None) {
+            // The property is intersected at the root.
+            Err(Error::NoValidEnumValue { path, keyword }) => {
+                assert_eq!(path, "");
+                assert_eq!(keyword, "const");
+            }
+            other => unreachable!("Unexpected result {other:?}"),
+        }
+    }
+}
@@ -0,0 +1,574 @@
+//! Best-effort translation of PCRE-like `pattern`s into the syntax of the `regex` crate.
+//!
+//! Patterns of schemas are usually written for PCRE or ECMAScript engines, which support
+//! constructs the automata can't express, or express differently.
+
+use serde::{Deserialize, Serialize};
+
+use super::types::STRING_INNER;
+
+/// Pattern translated by [`transpile_pattern`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TranspiledPattern {
+    /// The pattern in the syntax of the `regex` crate.
+    pub pattern: String,
+    /// Changes made to the original pattern, in order of their offsets.
+    pub changes: Vec<PatternChange>,
+}
+
+/// Construct of a pattern, which is changed by [`transpile_pattern`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PatternChange {
+    /// Byte offset of the construct in the original pattern.
+    pub offset: usize,
+    /// The construct, as written in the original pattern.
+    pub construct: String,
+    /// What the construct is replaced with, empty when it's removed.
+    pub replacement: String,
+    /// Whether the pattern still matches the same strings.
+    pub kind: PatternChangeKind,
+}
+
+/// Kind of a [`PatternChange`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PatternChangeKind {
+    /// The construct is replaced by an equivalent one.
+    Rewritten,
+    /// The construct isn't supported and is removed or approximated.
+    Dropped,
+}
+
+/// How the anchors of a `pattern` are interpreted, see [`normalize_anchors`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PatternAnchoring {
+    /// The pattern matches the whole string, whether it's anchored or not.
+    #[default]
+    AnchorBoth,
+    /// The pattern matches anywhere in the string, unless it's anchored, as in JSON Schema.
+    RespectExisting,
+}
+
+/// Translates a PCRE-like pattern into the syntax of the `regex` crate, rewriting equivalent
+/// constructs and dropping the others, and reports every [`PatternChange`].
+///
+/// Rewritten constructs are:
+/// - `\d`, `\w`, `\s` and their negations, which are ASCII classes in PCRE but Unicode ones
+///   in the `regex` crate;
+/// - named groups, which become non-capturing, so that names can't clash between fields;
+/// - `\Q...\E` quotes, `(?#...)` comments and `(?|...)` branch resets;
+/// - literal `[` and doubled set operators in classes, and literal `{`.
+///
+/// Dropped constructs are lookarounds, the possessiveness of quantifiers and the atomicity
+/// of groups, so that generated values may not match the original pattern.
+///
+/// # Example
+///
+/// ```rust
+/// use outlines_core::json_schema::{transpile_pattern, PatternChangeKind};
+///
+/// let transpiled = transpile_pattern(r"(?<year>\d{4})-(?=[01])\d\d");
+/// assert_eq!(transpiled.pattern, "(?:[0-9]{4})-[0-9][0-9]");
+/// assert_eq!(transpiled.changes[2].construct, "(?=[01])");
+/// assert_eq!(transpiled.changes[2].kind, PatternChangeKind::Dropped);
+/// ```
+pub fn transpile_pattern(pattern: &str) -> TranspiledPattern {
+    let mut transpiler = Transpiler {
+        pattern,
+        offset: 0,
+        output: String::with_capacity(pattern.len()),
+        changes: Vec::new(),
+    };
+    transpiler.run();
+    TranspiledPattern {
+        pattern: transpiler.output,
+        changes: transpiler.changes,
+    }
+}
+
+struct Transpiler<'a> {
+    pattern: &'a str,
+    /// Byte offset of the next character of the pattern.
+    offset: usize,
+    output: String,
+    changes: Vec<PatternChange>,
+}
+
+impl Transpiler<'_> {
+    fn rest(&self) -> &str {
+        &self.pattern[self.offset..]
+    }
+
+    /// Copies the next `len` bytes of the pattern as they are.
+    fn copy(&mut self, len: usize) {
+        self.output
+            .push_str(&self.pattern[self.offset..self.offset + len]);
+        self.offset += len;
+    }
+
+    /// Replaces the next `len` bytes of the pattern.
+    fn change(&mut self, len: usize, replacement: &str, kind: PatternChangeKind) {
+        self.changes.push(PatternChange {
+            offset: self.offset,
+            construct: self.pattern[self.offset..self.offset + len].to_string(),
+            replacement: replacement.to_string(),
+            kind,
+        });
+        self.output.push_str(replacement);
+        self.offset += len;
+    }
+
+    fn run(&mut self) {
+        // Whether the last construct is a quantifier, which could be made possessive.
+        let mut after_quantifier = false;
+        while let Some(c) = self.rest().chars().next() {
+            let quantifier = match c {
+                '\\' => {
+                    self.escape(false);
+                    false
+                }
+                '[' => {
+                    self.class();
+                    false
+                }
+                '(' => {
+                    self.group();
+                    false
+                }
+                '+' if after_quantifier => {
+                    self.change(1, "", PatternChangeKind::Dropped);
+                    false
+                }
+                '*' | '+' | '?' => {
+                    self.copy(1);
+                    // A lazy quantifier can't be made possessive.
+                    if self.rest().starts_with('?') {
+                        self.copy(1);
+                        false
+                    } else {
+                        true
+                    }
+                }
+                '{' => match counted_repetition(self.rest()) {
+                    Some(len) => {
+                        self.copy(len);
+                        if self.rest().starts_with('?') {
+                            self.copy(1);
+                            false
+                        } else {
+                            true
+                        }
+                    }
+                    None => {
+                        self.change(1, r"\{", PatternChangeKind::Rewritten);
+                        false
+                    }
+                },
+                c => {
+                    self.copy(c.len_utf8());
+                    false
+                }
+            };
+            after_quantifier = quantifier;
+        }
+    }
+
+    /// Translates the escape sequence at the current offset.
+    fn escape(&mut self, in_class: bool) {
+        let Some(escaped) = self.rest()[1..].chars().next() else {
+            self.copy(1);
+            return;
+        };
+        let class = match (escaped, in_class) {
+            ('d', false) => Some("[0-9]"),
+            ('D', false) => Some("[^0-9]"),
+            ('w', false) => Some("[0-9A-Za-z_]"),
+            ('W', false) => Some("[^0-9A-Za-z_]"),
+            ('s', false) => Some(r"[\t\n\x0B\f\r ]"),
+            ('S', false) => Some(r"[^\t\n\x0B\f\r ]"),
+            ('d', true) => Some("[:digit:]"),
+            ('D', true) => Some("[:^digit:]"),
+            ('w', true) => Some("[:word:]"),
+            ('W', true) => Some("[:^word:]"),
+            ('s', true) => Some("[:space:]"),
+            ('S', true) => Some("[:^space:]"),
+            _ => None,
+        };
+        if let Some(class) = class {
+            self.change(2, class, PatternChangeKind::Rewritten);
+        } else if escaped == 'Q' && !in_class {
+            let quoted = &self.rest()[2..];
+            let (text, len) = match quoted.find(r"\E") {
+                Some(end) => (&quoted[..end], end + 4),
+                None => (quoted, quoted.len() + 2),
+            };
+            let escaped = regex::escape(text);
+            self.change(len, &escaped, PatternChangeKind::Rewritten);
+        } else {
+            self.copy(1 + escaped.len_utf8());
+        }
+    }
+
+    /// Translates the bracketed class at the current offset.
+    fn class(&mut self) {
+        self.copy(1);
+        if self.rest().starts_with('^') {
+            self.copy(1);
+        }
+        // A leading `]` is a literal.
+        if self.rest().starts_with(']') {
+            self.copy(1);
+        }
+        while let Some(c) = self.rest().chars().next() {
+            match c {
+                ']' => {
+                    self.copy(1);
+                    return;
+                }
+                '\\' => self.escape(true),
+                '[' => match posix_class(self.rest()) {
+                    Some(len) => self.copy(len),
+                    // Nested classes are literals in PCRE.
+                    None => self.change(1, r"\[", PatternChangeKind::Rewritten),
+                },
+                // Doubled operators are set operations in the `regex` crate.
+                '&' | '-' | '~' if self.rest()[1..].starts_with(c) => {
+                    self.copy(1);
+                    self.change(1, &format!(r"\{c}"), PatternChangeKind::Rewritten);
+                }
+                c => self.copy(c.len_utf8()),
+            }
+        }
+    }
+
+    /// Translates the opening of the group at the current offset.
+    fn group(&mut self) {
+        let rest = self.rest();
+        for lookaround in ["(?=", "(?!", "(?<=", "(?<!"] {
+            if rest.starts_with(lookaround) {
+                let len = group_len(rest).unwrap_or(rest.len());
+                self.change(len, "", PatternChangeKind::Dropped);
+                return;
+            }
+        }
+        if rest.starts_with("(?#") {
+            let len = rest.find(')').map_or(rest.len(), |end| end + 1);
+            self.change(len, "", PatternChangeKind::Rewritten);
+        } else if rest.starts_with("(?>") {
+            self.change(3, "(?:", PatternChangeKind::Dropped);
+        } else if rest.starts_with("(?|") {
+            self.change(3, "(?:", PatternChangeKind::Rewritten);
+        } else if let Some(len) = group_name_len(rest) {
+            self.change(len, "(?:", PatternChangeKind::Rewritten);
+        } else if rest.starts_with("(?") {
+            self.copy(2);
+        } else {
+            self.copy(1);
+        }
+    }
+}
+
+/// Length of the counted repetition at the start of the pattern, e.g. `{2,5}`.
+fn counted_repetition(pattern: &str) -> Option<usize> {
+    let end = pattern.find('}')?;
+    let bounds = &pattern[1..end];
+    let (min, max) = bounds.split_once(',').unwrap_or((bounds, "0"));
+    let is_number = |s: &str| s.bytes().all(|b| b.is_ascii_digit());
+    (!min.is_empty() && is_number(min) && is_number(max)).then_some(end + 1)
+}
+
+/// Length of the ASCII class at the start of a bracketed class, e.g. `[:alpha:]`.
+fn posix_class(pattern: &str) -> Option<usize> {
+    let name = pattern.strip_prefix("[:")?;
+    let end = name.find(":]")?;
+    let name = name[..end].strip_prefix('^').unwrap_or(&name[..end]);
+    (!name.is_empty() && name.bytes().all(|b| b.is_ascii_lowercase())).then_some(end + 4)
+}
+
+/// Length of the opening of a named group, e.g. `(?<name>`, `(?P<name>` or `(?'name'`.
+fn group_name_len(pattern: &str) -> Option<usize> {
+    let (prefix, close) = if pattern.starts_with("(?P<") {
+        ("(?P<", '>')
+    } else if pattern.starts_with("(?<") {
+        ("(?<", '>')
+    } else if pattern.starts_with("(?'") {
+        ("(?'", '\'')
+    } else {
+        return None;
+    };
+    let name = &pattern[prefix.len()..];
+    let end = name.find(close)?;
+    let is_name = name[..end]
+        .chars()
+        .all(|c| c == '_' || c.is_ascii_alphanumeric());
+    (end > 0 && is_name).then_some(prefix.len() + end + 1)
+}
+
+/// Length of the group at the start of the pattern, up to its closing parenthesis.
+fn group_len(pattern: &str) -> Option<usize> {
+    let mut depth = 0;
+    let mut in_class = false;
+    let mut chars = pattern.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '[' if !in_class => in_class = true,
+            ']' if in_class => in_class = false,
+            '(' if !in_class => depth += 1,
+            ')' if !in_class => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i + 1);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Rewrites a pattern into one, which matches the whole content of a JSON string.
+///
+/// The leading `^` or `\A` and trailing `$` or `\z` anchors of each top-level alternative are
+/// removed. With [`PatternAnchoring::RespectExisting`], a side without an anchor is extended
+/// with any characters instead. Line anchors of multiline patterns, e.g. `(?m)^a$`, are
+/// treated as anchors of the string, which may exclude some matching strings.
+///
+/// # Example
+///
+/// ```rust
+/// use outlines_core::json_schema::{normalize_anchors, PatternAnchoring};
+///
+/// assert_eq!(normalize_anchors("^a|b$", PatternAnchoring::AnchorBoth), "(?:a|b)");
+/// assert_eq!(normalize_anchors("^[0-9]+$", PatternAnchoring::RespectExisting), "[0-9]+");
+/// ```
+pub fn normalize_anchors(pattern: &str, anchoring: PatternAnchoring) -> String {
+    let (flags, pattern) = split_flags(pattern);
+    let any = format!("{STRING_INNER}*");
+    let branches: Vec<String> = top_level_branches(pattern)
+        .into_iter()
+        .map(|branch| {
+            let (starts, branch) = match branch.strip_prefix('^') {
+                Some(rest) => (true, rest),
+                None => branch
+                    .strip_prefix(r"\A")
+                    .map_or((false, branch), |rest| (true, rest)),
+            };
+            let (ends, branch) = match strip_end_anchor(branch) {
+                Some(rest) => (true, rest),
+                None => (false, branch),
+            };
+            match anchoring {
+                PatternAnchoring::AnchorBoth => branch.to_string(),
+                PatternAnchoring::RespectExisting => format!(
+                    "{}{branch}{}",
+                    if starts { "" } else { &any },
+                    if ends { "" } else { &any }
+                ),
+            }
+        })
+        .collect();
+    match branches.as_slice() {
+        [branch] => format!("{flags}{branch}"),
+        _ => format!("(?:{flags}{})", branches.join("|")),
+    }
+}
+
+/// Splits the leading flag groups of a pattern, e.g. `(?m)`, from the rest of it.
+fn split_flags(pattern: &str) -> (&str, &str) {
+    let mut end = 0;
+    while let Some(flags) = pattern[end..].strip_prefix("(?") {
+        match flags.find(')') {
+            Some(len)
+                if flags[..len]
+                    .chars()
+                    .all(|c| c == '-' || c.is_ascii_alphabetic()) =>
+            {
+                end += len + 3;
+            }
+            _ => break,
+        }
+    }
+    pattern.split_at(end)
+}
+
+/// Alternatives of the pattern, which aren't nested in a group or a class.
+fn top_level_branches(pattern: &str) -> Vec<&str> {
+    let mut branches = Vec::new();
+    let (mut start, mut depth, mut class_depth) = (0, 0, 0);
+    let mut chars = pattern.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '[' => {
+                class_depth += 1;
+                // A leading `]` of a class is a literal.
+                chars.next_if(|&(_, c)| c == '^');
+                chars.next_if(|&(_, c)| c == ']');
+            }
+            ']' if class_depth > 0 => class_depth -= 1,
+            '(' if class_depth == 0 => depth += 1,
+            ')' if class_depth == 0 => depth -= 1,
+            '|' if class_depth == 0 && depth == 0 => {
+                branches.push(&pattern[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    branches.push(&pattern[start..]);
+    branches
+}
+
+/// Removes the trailing `$` or `\z` anchor of the pattern, unless its backslash is escaped.
+fn strip_end_anchor(pattern: &str) -> Option<&str> {
+    let rest = pattern
+        .strip_suffix('$')
+        .or_else(|| pattern.strip_suffix(r"\z"))?;
+    // An odd number of backslashes escapes the anchor.
+    let escapes = rest.len() - rest.trim_end_matches('\\').len();
+    (escapes % 2 == 0).then_some(rest)
+}
+
+#[cfg(test)]
+mod tests {
+    use regex::Regex;
+
+    use super::*;
+
+    fn check(pattern: &str, expected: &str, changes: &[(usize, &str, &str, PatternChangeKind)]) {
+        let transpiled = transpile_pattern(pattern);
+        assert_eq!(transpiled.pattern, expected, "{pattern}");
+        let found: Vec<_> = transpiled
+            .changes
+            .iter()
+            .map(|c| {
+                assert_eq!(
+                    &pattern[c.offset..c.offset + c.construct.len()],
+                    c.construct
+                );
+                (
+                    c.offset,
+                    c.construct.as_str(),
+                    c.replacement.as_str(),
+                    c.kind,
+                )
+            })
+            .collect();
+        assert_eq!(found, changes, "{pattern}");
+    }
+
+    #[test]
+    fn rewritten_constructs() {
+        use PatternChangeKind::Rewritten;
+
+        check(r"^[a-z]+\.(com|org)$", r"^[a-z]+\.(com|org)$", &[]);
+        check(
+            r"\d+[\w.-]\S",
+            r"[0-9]+[[:word:].-][^\t\n\x0B\f\r ]",
+            &[
+                (0, r"\d", "[0-9]", Rewritten),
+                (4, r"\w", "[:word:]", Rewritten),
+                (9, r"\S", r"[^\t\n\x0B\f\r ]", Rewritten),
+            ],
+        );
+        check(
+            r"(?P<a>x)(?'b'y)(?<c>z)(?#note)(?|p|q)",
+            "(?:x)(?:y)(?:z)(?:p|q)",
+            &[
+                (0, "(?P<a>", "(?:", Rewritten),
+                (8, "(?'b'", "(?:", Rewritten),
+                (15, "(?<c>", "(?:", Rewritten),
+                (22, "(?#note)", "", Rewritten),
+                (30, "(?|", "(?:", Rewritten),
+            ],
+        );
+        check(
+            r"\Q1+1\E=[[a&&b[:alpha:]]{a}x{2,}",
+            r"1\+1=[\[a&\&b[:alpha:]]\{a}x{2,}",
+            &[
+                (0, r"\Q1+1\E", r"1\+1", Rewritten),
+                (9, "[", r"\[", Rewritten),
+                (12, "&", r"\&", Rewritten),
+                (24, "{", r"\{", Rewritten),
+            ],
+        );
+    }
+
+    #[test]
+    fn dropped_constructs() {
+        use PatternChangeKind::Dropped;
+
+        check(
+            r"(?!-)[a-z(]+(?<=(a|b))x*+y{2}+z?+(?>ab|a)c+?",
+            "[a-z(]+x*y{2}z?(?:ab|a)c+?",
+            &[
+                (0, "(?!-)", "", Dropped),
+                (12, "(?<=(a|b))", "", Dropped),
+                (24, "+", "", Dropped),
+                (29, "+", "", Dropped),
+                (32, "+", "", Dropped),
+                (33, "(?>", "(?:", Dropped),
+            ],
+        );
+    }
+
+    #[test]
+    fn transpiled_patterns_compile() {
+        for pattern in [
+            r"\d{3}-\d{4}",
+            r"[\d\s]+(?=x)",
+            r"(?<id>[A-Z]{2}\d+)",
+            r"a{,3}",
+            r"[]a]",
+            r"\Qa.b",
+            "(?=unclosed",
+        ] {
+            let transpiled = transpile_pattern(pattern);
+            assert!(Regex::new(&transpiled.pattern).is_ok(), "{pattern}");
+        }
+        let re = Regex::new(&transpile_pattern(r"^\d+$").pattern).expect("Regex failed");
+        assert!(re.is_match("123"));
+        // Unicode digits aren't digits in PCRE.
+        assert!(!re.is_match("١٢٣"));
+    }
+
+    #[test]
+    fn anchors() {
+        use PatternAnchoring::{AnchorBoth, RespectExisting};
+
+        let any = format!("{STRING_INNER}*");
+        for (pattern, both, existing) in [
+            ("a", "a".to_string(), format!("{any}a{any}")),
+            ("^a", "a".to_string(), format!("a{any}")),
+            ("a$", "a".to_string(), format!("{any}a")),
+            ("^a$", "a".to_string(), "a".to_string()),
+            (r"\Aa\z", "a".to_string(), "a".to_string()),
+            (r"^a\$", r"a\$".to_string(), format!(r"a\${any}")),
+            (r"^a\\$", r"a\\".to_string(), r"a\\".to_string()),
+            ("(?m)^a$", "(?m)a".to_string(), "(?m)a".to_string()),
+            (
+                "(?m)^a$\n^b$",
+                "(?m)a$\n^b".to_string(),
+                "(?m)a$\n^b".to_string(),
+            ),
+            (
+                "^a|(b|c)$",
+                "(?:a|(b|c))".to_string(),
+                format!("(?:a{any}|{any}(b|c))"),
+            ),
+            ("^[|$]$", "[|$]".to_string(), "[|$]".to_string()),
+        ] {
+            assert_eq!(normalize_anchors(pattern, AnchorBoth), both, "{pattern}");
+            assert_eq!(
+                normalize_anchors(pattern, RespectExisting),
+                existing,
+                "{pattern}"
+            );
+        }
+    }
+}
@@ -0,0 +1,844 @@
+//! Regular expressions of the integers and decimal numbers within bounds.
+//!
+//! The range is split by the number of digits, then each part is split by its leading digits
+//! into alternatives of digit classes, e.g. `[8, 213]` is
+//! `[89]|[1-9][0-9]|1[0-9]{2}|2(0[0-9]|1[0-3])`. Decimal numbers are split by their integer
+//! part, the fraction digits being split the same way at the integer parts of the bounds.
+//!
+//! Multiples of a power of ten are supported as well: the multiples of `10^k` are the integers
+//! ending with `k` zeros, the multiples of `10^-k` the decimal numbers with at most `k` fraction
+//! digits.
+
+use std::cmp::Ordering;
+
+/// Bound of a decimal number, which is excluded itself if `exclusive`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct DecimalBound {
+    /// Whether the bound is below zero.
+    pub(crate) negative: bool,
+    /// Integer part of the absolute value, saturated to `u64`.
+    pub(crate) integer: u64,
+    /// Fraction digits of the absolute value, without trailing zeros.
+    pub(crate) fraction: String,
+    pub(crate) exclusive: bool,
+}
+
+/// Absolute value of a decimal bound: its integer part, its fraction digits and whether it's
+/// excluded.
+type Magnitude<'a> = (u64, &'a [u8], bool);
+
+impl DecimalBound {
+    pub(crate) fn from_i64(value: i64, exclusive: bool) -> Self {
+        Self {
+            negative: value < 0,
+            integer: value.unsigned_abs(),
+            fraction: String::new(),
+            exclusive,
+        }
+    }
+
+    /// Bound of a finite float by its shortest decimal representation, e.g. `0.1` rather than
+    /// the binary fraction closest to it.
+    pub(crate) fn from_f64(value: f64, exclusive: bool) -> Option<Self> {
+        if !value.is_finite() {
+            return None;
+        }
+        // Floats are displayed without exponent.
+        let digits = value.abs().to_string();
+        let (integer, fraction) = digits.split_once('.').unwrap_or((&digits, ""));
+        let integer = integer.parse().unwrap_or(u64::MAX);
+        let fraction = fraction.trim_end_matches('0').to_string();
+        Some(Self {
+            negative: value < 0.0 && (integer > 0 || !fraction.is_empty()),
+            integer,
+            fraction,
+            exclusive,
+        })
+    }
+
+    /// Compares the values of the bounds, regardless of their exclusion.
+    pub(crate) fn cmp_value(&self, other: &Self) -> Ordering {
+        let magnitude = (self.integer, self.fraction.as_bytes());
+        let other_magnitude = (other.integer, other.fraction.as_bytes());
+        match (self.negative, other.negative) {
+            (false, false) => magnitude.cmp(&other_magnitude),
+            (true, true) => other_magnitude.cmp(&magnitude),
+            (true, false) => Ordering::Less,
+            (false, true) => Ordering::Greater,
+        }
+    }
+
+    /// The least integer within the bound as a lower one, saturated to `i64`.
+    pub(crate) fn ceil(&self) -> i64 {
+        let rounded = match (self.fraction.is_empty(), self.negative) {
+            (true, _) => self.truncated() + self.exclusive as i128,
+            (false, false) => self.truncated() + 1,
+            (false, true) => self.truncated(),
+        };
+        rounded.clamp(i64::MIN.into(), i64::MAX.into()) as i64
+    }
+
+    /// The greatest integer within the bound as an upper one, saturated to `i64`.
+    pub(crate) fn floor(&self) -> i64 {
+        let rounded = match (self.fraction.is_empty(), self.negative) {
+            (true, _) => self.truncated() - self.exclusive as i128,
+            (false, false) => self.truncated(),
+            (false, true) => self.truncated() - 1,
+        };
+        rounded.clamp(i64::MIN.into(), i64::MAX.into()) as i64
+    }
+
+    /// Value of the bound, up to the precision of `f64`.
+    pub(crate) fn to_f64(&self) -> Option<f64> {
+        let sign = if self.negative { "-" } else { "" };
+        format!("{sign}{}.{}0", self.integer, self.fraction)
+            .parse()
+            .ok()
+    }
+
+    fn truncated(&self) -> i128 {
+        if self.negative {
+            -i128::from(self.integer)
+        } else {
+            self.integer.into()
+        }
+    }
+
+    fn magnitude(&self) -> Magnitude<'_> {
+        (self.integer, self.fraction.as_bytes(), self.exclusive)
+    }
+}
+
+/// Range of the integers of an OpenAPI `format`, e.g. `int32`.
+pub(crate) fn format_range(format: &str) -> Option<(i64, i64)> {
+    match format {
+        "int32" => Some((i32::MIN.into(), i32::MAX.into())),
+        "uint32" => Some((0, u32::MAX.into())),
+        "int64" => Some((i64::MIN, i64::MAX)),
+        _ => None,
+    }
+}
+
+/// Generates the regular expression of the integers from `min` to `max`, both included.
+pub(crate) fn integer_range(min: i64, max: i64) -> String {
+    debug_assert!(min <= max, "Empty range [{min}, {max}]");
+    let mut alternatives = Vec::new();
+    if min < 0 {
+        let (lo, hi) = (max.min(-1).unsigned_abs(), min.unsigned_abs());
+        alternatives.push(format!("-({})", unsigned_range(lo, hi)));
+    }
+    if max >= 0 {
+        alternatives.push(unsigned_range(
+            min.max(0).unsigned_abs(),
+            max.unsigned_abs(),
+        ));
+    }
+    format!("({})", alternatives.join("|"))
+}
+
+/// Generates the regular expression of the integers within optional bounds, both included.
+pub(crate) fn bounded_integer(min: Option<i64>, max: Option<i64>) -> String {
+    let (min, max) = match (min, max) {
+        (Some(min), Some(max)) => return integer_range(min, max),
+        (None, None) => return format!("(-)?({})", unsigned_from(0)),
+        bounds => bounds,
+    };
+    let mut alternatives = Vec::new();
+    match (min, max) {
+        (Some(min), _) => {
+            if min < 0 {
+                alternatives.push(format!("-({})", unsigned_range(1, min.unsigned_abs())));
+            }
+            alternatives.push(unsigned_from(min.max(0).unsigned_abs()));
+        }
+        (_, Some(max)) => {
+            alternatives.push(format!("-({})", unsigned_from(max.min(-1).unsigned_abs())));
+            if max >= 0 {
+                alternatives.push(unsigned_range(0, max.unsigned_abs()));
+            }
+        }
+        _ => unreachable!("Bounds are handled above"),
+    }
+    format!("({})", alternatives.join("|"))
+}
+
+/// Returns `k` if `multiple` is `10^k`, e.g. `-2` for `0.01`, up to the precision of `f64`.
+pub(crate) fn power_of_ten(multiple: f64) -> Option<i32> {
+    if !multiple.is_finite() || multiple <= 0.0 {
+        return None;
+    }
+    let power = multiple.log10().round();
+    ((multiple / 10f64.powf(power) - 1.0).abs() < 1e-9).then_some(power as i32)
+}
+
+/// Generates the regular expression of the multiples of `10^zeros` within optional bounds,
+/// both included, or `None` if no multiple is within them.
+pub(crate) fn bounded_multiple(min: Option<i64>, max: Option<i64>, zeros: u32) -> Option<String> {
+    let within =
+        |value: i64| min.is_none_or(|min| min <= value) && max.is_none_or(|max| value <= max);
+    let Some(scale) = 10i64.checked_pow(zeros) else {
+        // Only zero is a multiple within `i64`.
+        return within(0).then(|| "(0)".to_string());
+    };
+    // Bounds of the multiplied integers, e.g. `[150, 420]` is `[2, 4]` for the multiples of 100.
+    let min = min.map(|min| min.div_euclid(scale) + (min.rem_euclid(scale) != 0) as i64);
+    let max = max.map(|max| max.div_euclid(scale));
+    if let (Some(min), Some(max)) = (min, max) {
+        if min > max {
+            return None;
+        }
+    }
+    if zeros == 0 {
+        return Some(bounded_integer(min, max));
+    }
+    // Zero has no trailing zeros, unlike the other multiples.
+    let mut alternatives = Vec::new();
+    if min.is_none_or(|min| min < 0) {
+        let max = max.map_or(-1, |max| max.min(-1));
+        alternatives.push(format!("{}0{{{zeros}}}", bounded_integer(min, Some(max))));
+    }
+    if min.is_none_or(|min| min <= 0) && max.is_none_or(|max| max >= 0) {
+        alternatives.push("0".to_string());
+    }
+    if max.is_none_or(|max| max > 0) {
+        let min = min.map_or(1, |min| min.max(1));
+        alternatives.push(format!("{}0{{{zeros}}}", bounded_integer(Some(min), max)));
+    }
+    Some(format!("({})", alternatives.join("|")))
+}
+
+/// Generates the regular expression of the decimal numbers without exponent within optional
+/// bounds, with at most `fraction` fraction digits if given, or `None` if no number is within
+/// them.
+pub(crate) fn bounded_decimal(
+    min: Option<&DecimalBound>,
+    max: Option<&DecimalBound>,
+    fraction: Option<u32>,
+) -> Option<String> {
+    let mut alternatives = Vec::new();
+    if max.is_none_or(|max| !max.negative) {
+        let lo = min
+            .filter(|min| !min.negative)
+            .map_or((0, &[][..], false), DecimalBound::magnitude);
+        let hi = max.map(DecimalBound::magnitude);
+        alternatives.extend(unsigned_decimal(lo, hi, fraction));
+    }
+    // Negative numbers are the opposites of positive ones within the opposite bounds.
+    if min.is_none_or(|min| min.negative) {
+        let lo = max
+            .filter(|max| max.negative)
+            .map_or((0, &[][..], true), DecimalBound::magnitude);
+        let hi = min.map(DecimalBound::magnitude);
+        alternatives
+            .extend(unsigned_decimal(lo, hi, fraction).map(|decimal| format!("-({decimal})")));
+    }
+    (!alternatives.is_empty()).then(|| format!("({})", alternatives.join("|")))
+}
+
+/// Alternatives of the non-negative decimal numbers from `lo` to `hi`, unbounded without
+/// `hi`, with at most `fraction` fraction digits.
+///
+/// The integer parts strictly between the bounds take any fraction digits, while the ones of
+/// the bounds take the fraction digits within them, e.g. `[1.5, 3]` is
+/// `1\.[5-9][0-9]*|(2)(\.[0-9]+)?|3(\.0+)?`.
+fn unsigned_decimal(lo: Magnitude, hi: Option<Magnitude>, fraction: Option<u32>) -> Option<String> {
+    let (lo, lo_fraction, lo_exclusive) = lo;
+    let lo_zero = lo_fraction.is_empty() && !lo_exclusive;
+    let mut alternatives = Vec::new();
+    match hi {
+        None => {
+            alternatives.extend(with_fraction(
+                lo.to_string(),
+                lo_zero,
+                fraction_from(lo_fraction, lo_exclusive, fraction, true),
+            ));
+            if let Some(next) = lo.checked_add(1) {
+                alternatives.extend(with_fraction(
+                    format!("({})", unsigned_from(next)),
+                    true,
+                    fraction_from(&[], false, fraction, true),
+                ));
+            }
+        }
+        Some((hi, hi_fraction, hi_exclusive)) => {
+            let hi_zero = !(hi_fraction.is_empty() && hi_exclusive);
+            match lo.cmp(&hi) {
+                Ordering::Greater => {}
+                Ordering::Equal => alternatives.extend(with_fraction(
+                    lo.to_string(),
+                    lo_zero && hi_zero,
+                    fraction_between(
+                        (lo_fraction, lo_exclusive),
+                        (hi_fraction, hi_exclusive),
+                        fraction,
+                        true,
+                    ),
+                )),
+                Ordering::Less => {
+                    alternatives.extend(with_fraction(
+                        lo.to_string(),
+                        lo_zero,
+                        fraction_from(lo_fraction, lo_exclusive, fraction, true),
+                    ));
+                    if hi - lo > 1 {
+                        alternatives.extend(with_fraction(
+                            format!("({})", unsigned_range(lo + 1, hi - 1)),
+                            true,
+                            fraction_from(&[], false, fraction, true),
+                        ));
+                    }
+                    alternatives.extend(with_fraction(
+                        hi.to_string(),
+                        hi_zero,
+                        fraction_to(hi_fraction, hi_exclusive, fraction, true),
+                    ));
+                }
+            }
+        }
+    }
+    (!alternatives.is_empty()).then(|| alternatives.join("|"))
+}
+
+/// Appends the fraction digits to the integer part, optionally if zero is a fraction within
+/// the bounds, or `None` if no fraction is.
+fn with_fraction(integer: String, zero: bool, digits: Option<String>) -> Option<String> {
+    match (zero, digits) {
+        (true, Some(digits)) => Some(format!(r"{integer}(\.{})?", grouped(digits))),
+        (true, None) => Some(integer),
+        (false, Some(digits)) => Some(format!(r"{integer}\.{}", grouped(digits))),
+        (false, None) => None,
+    }
+}
+
+/// Generates the regular expression of the fraction digits from `lo`, which is excluded if
+/// `exclusive`, with at most `max_digits` digits and at least one if `nonempty`, or `None` if
+/// no digits are within the bounds.
+fn fraction_from(
+    lo: &[u8],
+    exclusive: bool,
+    max_digits: Option<u32>,
+    nonempty: bool,
+) -> Option<String> {
+    let Some((&first, rest)) = lo.split_first() else {
+        return if exclusive {
+            nonzero_digits(max_digits)
+        } else {
+            repeat("[0-9]", nonempty as u32, max_digits)
+        };
+    };
+    // Without digits, the fraction is zero, which is below `lo`.
+    let rest_digits = fewer_digits(max_digits)?;
+    let any = repeat("[0-9]", 0, rest_digits)?;
+    if rest.is_empty() && !exclusive {
+        // The last digit of `lo` is followed by any digits, like the greater ones.
+        return Some(digit_class(first, b'9') + &any);
+    }
+    let mut alternatives = Vec::new();
+    if first < b'9' {
+        alternatives.push(digit_class(first + 1, b'9') + &any);
+    }
+    alternatives.extend(
+        fraction_from(rest, exclusive, rest_digits, false).map(|tail| followed(first, tail)),
+    );
+    alternation(alternatives)
+}
+
+/// Generates the regular expression of the fraction digits up to `hi`, like [`fraction_from`].
+fn fraction_to(
+    hi: &[u8],
+    exclusive: bool,
+    max_digits: Option<u32>,
+    nonempty: bool,
+) -> Option<String> {
+    let Some((&first, rest)) = hi.split_first() else {
+        return (!exclusive)
+            .then(|| repeat("0", nonempty as u32, max_digits))
+            .flatten();
+    };
+    let mut alternatives = Vec::new();
+    if let Some(rest_digits) = fewer_digits(max_digits) {
+        if first > b'0' {
+            alternatives.extend(
+                repeat("[0-9]", 0, rest_digits).map(|any| digit_class(b'0', first - 1) + &any),
+            );
+        }
+        alternatives.extend(
+            fraction_to(rest, exclusive, rest_digits, false).map(|tail| followed(first, tail)),
+        );
+    }
+    let digits = alternation(alternatives);
+    if nonempty {
+        return digits;
+    }
+    // Without digits, the fraction is zero, which is below `hi`.
+    Some(digits.map_or_else(String::new, |digits| format!("({digits})?")))
+}
+
+/// Generates the regular expression of the fraction digits from `lo` to `hi`, like
+/// [`fraction_from`] and [`fraction_to`].
+fn fraction_between(
+    (lo, lo_exclusive): (&[u8], bool),
+    (hi, hi_exclusive): (&[u8], bool),
+    max_digits: Option<u32>,
+    nonempty: bool,
+) -> Option<String> {
+    if lo.is_empty() && !lo_exclusive {
+        return fraction_to(hi, hi_exclusive, max_digits, nonempty);
+    }
+    // Zero is below `lo`, so that the fractions start with a digit and `hi` isn't zero.
+    let (Some((&hi_first, hi_rest)), Some(rest_digits)) =
+        (hi.split_first(), fewer_digits(max_digits))
+    else {
+        return None;
+    };
+    let (lo_first, lo_rest) = lo
+        .split_first()
+        .map_or((b'0', &[][..]), |(&first, rest)| (first, rest));
+    let mut alternatives = Vec::new();
+    match lo_first.cmp(&hi_first) {
+        Ordering::Greater => {}
+        Ordering::Equal => alternatives.extend(
+            fraction_between(
+                (lo_rest, lo_exclusive),
+                (hi_rest, hi_exclusive),
+                rest_digits,
+                false,
+            )
+            .map(|tail| followed(lo_first, tail)),
+        ),
+        Ordering::Less => {
+            // The last digit of `lo` is followed by any digits, like the greater ones.
+            let mut middle_lo = lo_first;
+            if !lo_rest.is_empty() || lo_exclusive {
+                alternatives.extend(
+                    fraction_from(lo_rest, lo_exclusive, rest_digits, false)
+                        .map(|tail| followed(lo_first, tail)),
+                );
+                middle_lo += 1;
+            }
+            if middle_lo < hi_first {
+                alternatives.extend(
+                    repeat("[0-9]", 0, rest_digits)
+                        .map(|any| digit_class(middle_lo, hi_first - 1) + &any),
+                );
+            }
+            alternatives.extend(
+                fraction_to(hi_rest, hi_exclusive, rest_digits, false)
+                    .map(|tail| followed(hi_first, tail)),
+            );
+        }
+    }
+    alternation(alternatives)
+}
+
+/// Generates the regular expression of the non-zero fraction digits, with at most
+/// `max_digits` digits.
+fn nonzero_digits(max_digits: Option<u32>) -> Option<String> {
+    let Some(max_digits) = max_digits else {
+        return Some("[0-9]*[1-9][0-9]*".to_string());
+    };
+    // By the position of the first non-zero digit.
+    alternation(
+        (0..max_digits)
+            .map(|zeros| {
+                format!(
+                    "{}[1-9]{}",
+                    "0".repeat(zeros as usize),
+                    repeat("[0-9]", 0, Some(max_digits - 1 - zeros)).unwrap_or_default()
+                )
+            })
+            .collect(),
+    )
+}
+
+/// Remaining digits after one, `None` if no digit is left.
+fn fewer_digits(max_digits: Option<u32>) -> Option<Option<u32>> {
+    match max_digits {
+        None => Some(None),
+        Some(digits) => digits.checked_sub(1).map(Some),
+    }
+}
+
+/// Repeats the pattern from `min` to `max` times, unbounded without `max`, or `None` if
+/// `max` is below `min`.
+fn repeat(pattern: &str, min: u32, max: Option<u32>) -> Option<String> {
+    let quantifier = match (min, max) {
+        (0, None) => "*".to_string(),
+        (1, None) => "+".to_string(),
+        (min, None) => format!("{{{min},}}"),
+        (min, Some(max)) if max < min => return None,
+        (_, Some(0)) => return Some(String::new()),
+        (1, Some(1)) => String::new(),
+        (min, Some(max)) if min == max => format!("{{{max}}}"),
+        (min, Some(max)) => format!("{{{min},{max}}}"),
+    };
+    Some(format!("{pattern}{quantifier}"))
+}
+
+fn alternation(alternatives: Vec<String>) -> Option<String> {
+    (!alternatives.is_empty()).then(|| alternatives.join("|"))
+}
+
+fn grouped(pattern: String) -> String {
+    if pattern.contains('|') {
+        format!("({pattern})")
+    } else {
+        pattern
+    }
+}
+
+fn followed(digit: u8, tail: String) -> String {
+    format!("{}{}", digit as char, grouped(tail))
+}
+
+/// Generates the regular expression of the non-negative integers from `min`, as alternatives
+/// without a group around them.
+fn unsigned_from(min: u64) -> String {
+    if min == 0 {
+        return "0|[1-9][0-9]*".to_string();
+    }
+    // The numbers with as many digits as `min`, then the longer ones.
+    let digits = min.to_string().len() as u32;
+    let hi = 10u64
+        .checked_pow(digits)
+        .map_or(u64::MAX, |power| power - 1);
+    format!("{}|[1-9][0-9]{{{digits},}}", unsigned_range(min, hi))
+}
+
+/// Generates the regular expression of the non-negative integers from `min` to `max`, both
+/// included, as alternatives without a group around them.
+pub(crate) fn unsigned_range(min: u64, max: u64) -> String {
+    let mut alternatives = Vec::new();
+    let mut lo = min;
+    loop {
+        let lo_digits = lo.to_string();
+        // The greatest number with as many digits as `lo`.
+        let hi = 10u64
+            .checked_pow(lo_digits.len() as u32)
+            .map_or(u64::MAX, |power| power - 1)
+            .min(max);
+        alternatives.extend(same_length(lo_digits.as_bytes(), hi.to_string().as_bytes()));
+        if hi == max {
+            break;
+        }
+        lo = hi + 1;
+    }
+    alternatives.join("|")
+}
+
+/// Alternatives of the numbers from `lo` to `hi`, which have the same number of digits.
+fn same_length(lo: &[u8], hi: &[u8]) -> Vec<String> {
+    let (first_lo, first_hi) = (lo[0], hi[0]);
+    let rest = lo.len() - 1;
+    if rest == 0 {
+        return vec![digit_class(first_lo, first_hi)];
+    }
+    if first_lo == first_hi {
+        return vec![prefixed(first_lo, same_length(&lo[1..], &hi[1..]))];
+    }
+
+    let mut alternatives = Vec::new();
+    // Leading digits, which are followed by any digits, go to a single class.
+    let mut middle_lo = first_lo;
+    if lo[1..].iter().any(|&digit| digit != b'0') {
+        let nines = vec![b'9'; rest];
+        alternatives.push(prefixed(first_lo, same_length(&lo[1..], &nines)));
+        middle_lo += 1;
+    }
+    let hi_is_full = hi[1..].iter().all(|&digit| digit == b'9');
+    let middle_hi = if hi_is_full { first_hi } else { first_hi - 1 };
+    if middle_lo <= middle_hi {
+        let any = if rest == 1 {
+            "[0-9]".to_string()
+        } else {
+            format!("[0-9]{{{rest}}}")
+        };
+        alternatives.push(digit_class(middle_lo, middle_hi) + &any);
+    }
+    if !hi_is_full {
+        let zeros = vec![b'0'; rest];
+        alternatives.push(prefixed(first_hi, same_length(&zeros, &hi[1..])));
+    }
+    alternatives
+}
+
+fn prefixed(digit: u8, tail: Vec<String>) -> String {
+    match tail.as_slice() {
+        [single] => format!("{}{single}", digit as char),
+        _ => format!("{}({})", digit as char, tail.join("|")),
+    }
+}
+
+fn digit_class(lo: u8, hi: u8) -> String {
+    match hi - lo {
+        0 => (lo as char).to_string(),
+        1 => format!("[{}{}]", lo as char, hi as char),
+        _ => format!("[{}-{}]", lo as char, hi as char),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use regex::Regex;
+
+    use super::*;
+
+    fn check_range(min: i64, max: i64, probes: impl IntoIterator<Item = i64>) {
+        let regex = integer_range(min, max);
+        let re = Regex::new(&format!("^{regex}$")).expect("Regex failed");
+        for value in probes {
+            assert_eq!(
+                re.is_match(&value.to_string()),
+                (min..=max).contains(&value),
+                "{value} in [{min}, {max}]: {regex}"
+            );
+        }
+        for not_canonical in ["-0", "00", "01", "+1", ""] {
+            assert!(!re.is_match(not_canonical), "{not_canonical}: {regex}");
+        }
+    }
+
+    #[test]
+    fn small_ranges() {
+        assert_eq!(
+            unsigned_range(8, 213),
+            "[89]|[1-9][0-9]|1[0-9]{2}|2(0[0-9]|1[0-3])"
+        );
+        assert_eq!(integer_range(0, 0), "(0)");
+        assert_eq!(integer_range(-5, -5), "(-(5))");
+
+        let bounds = [
+            -1000, -101, -100, -99, -10, -9, -1, 0, 1, 9, 10, 99, 100, 345, 1000,
+        ];
+        for &min in &bounds {
+            for &max in bounds.iter().filter(|&&max| max >= min) {
+                check_range(min, max, -1100..=1100);
+            }
+        }
+    }
+
+    #[test]
+    fn half_bounded_ranges() {
+        let bounds = [
+            -1000, -101, -100, -99, -10, -9, -1, 0, 1, 9, 10, 99, 100, 345,
+        ];
+        for bound in bounds {
+            for (min, max) in [(Some(bound), None), (None, Some(bound))] {
+                let regex = bounded_integer(min, max);
+                let re = Regex::new(&format!("^{regex}$")).expect("Regex failed");
+                for value in -20000..=20000 {
+                    let expected =
+                        min.is_none_or(|min| value >= min) && max.is_none_or(|max| value <= max);
+                    assert_eq!(
+                        re.is_match(&value.to_string()),
+                        expected,
+                        "{value} in [{min:?}, {max:?}]: {regex}"
+                    );
+                }
+                for not_canonical in ["-0", "00", "01", "+1", ""] {
+                    assert!(!re.is_match(not_canonical), "{not_canonical}: {regex}");
+                }
+            }
+        }
+        assert_eq!(
+            bounded_integer(Some(10), None),
+            "([1-9][0-9]|[1-9][0-9]{2,})"
+        );
+        assert_eq!(bounded_integer(None, None), "(-)?(0|[1-9][0-9]*)");
+
+        let re = Regex::new(&format!("^{}$", bounded_integer(Some(i64::MIN), None)))
+            .expect("Regex failed");
+        assert!(re.is_match("-9223372036854775808"));
+        assert!(!re.is_match("-9223372036854775809"));
+        assert!(re.is_match("123456789012345678901234567890"));
+    }
+
+    #[test]
+    fn decimal_ranges() {
+        let bounds = [
+            -12.0, -10.0, -1.5, -1.0, -0.05, 0.0, 0.5, 1.0, 1.25, 7.0, 10.0,
+        ];
+        let probes: Vec<String> = (-1300..=1300)
+            .flat_map(|hundredths: i64| {
+                let sign = if hundredths < 0 { "-" } else { "" };
+                let (whole, fraction) = (hundredths.abs() / 100, hundredths.abs() % 100);
+                let mut probes = vec![
+                    format!("{sign}{whole}.{fraction:02}"),
+                    format!("{sign}{whole}.{fraction:02}1"),
+                ];
+                if fraction % 10 == 0 {
+                    probes.push(format!("{sign}{whole}.{}", fraction / 10));
+                }
+                if fraction == 0 {
+                    probes.push(format!("{sign}{whole}"));
+                }
+                probes
+            })
+            // Negative zeros are not generated.
+            .filter(|probe| probe.parse::<f64>() != Ok(0.0) || !probe.starts_with('-'))
+            .collect();
+        let decimal_bounds = bounds.iter().flat_map(|&value| {
+            [false, true].map(|exclusive| DecimalBound::from_f64(value, exclusive))
+        });
+        let decimal_bounds: Vec<Option<DecimalBound>> = decimal_bounds.chain([None]).collect();
+        for fraction in [None, Some(1), Some(2)] {
+            for min in &decimal_bounds {
+                for max in &decimal_bounds {
+                    let within = |probe: &str| {
+                        let number: f64 = probe.parse().expect("Invalid number");
+                        let digits = probe.split_once('.').map_or(0, |(_, digits)| digits.len());
+                        let bound = |bound: &DecimalBound| {
+                            let value = format!(
+                                "{}{}.{}",
+                                if bound.negative { "-" } else { "" },
+                                bound.integer,
+                                bound.fraction
+                            );
+                            value.parse::<f64>().expect("Invalid bound")
+                        };
+                        fraction.is_none_or(|fraction| digits <= fraction as usize)
+                            && min.as_ref().is_none_or(|min| {
+                                number > bound(min) || (!min.exclusive && number == bound(min))
+                            })
+                            && max.as_ref().is_none_or(|max| {
+                                number < bound(max) || (!max.exclusive && number == bound(max))
+                            })
+                    };
+                    let Some(regex) = bounded_decimal(min.as_ref(), max.as_ref(), fraction) else {
+                        assert!(
+                            !probes.iter().any(|probe| within(probe)),
+                            "Empty [{min:?}, {max:?}] by {fraction:?}"
+                        );
+                        continue;
+                    };
+                    let re = Regex::new(&format!("^{regex}$")).expect("Regex failed");
+                    for probe in &probes {
+                        assert_eq!(
+                            re.is_match(probe),
+                            within(probe),
+                            "{probe} in [{min:?}, {max:?}] by {fraction:?}: {regex}"
+                        );
+                    }
+                    for not_canonical in ["00", "01.5", "+1", "", "1.", ".5", "1e2", "-0"] {
+                        assert!(!re.is_match(not_canonical), "{not_canonical}: {regex}");
+                    }
+                }
+            }
+        }
+
+        let bound = |value, exclusive| DecimalBound::from_f64(value, exclusive);
+        assert_eq!(
+            unsigned_decimal((1, b"5", false), Some((3, b"", false)), None).as_deref(),
+            Some(r"1\.[5-9][0-9]*|(2)(\.[0-9]+)?|3(\.0+)?")
+        );
+        assert_eq!(
+            unsigned_decimal((1, b"5", false), Some((1, b"7", false)), None).as_deref(),
+            Some(r"1\.([56][0-9]*|70*)")
+        );
+        assert_eq!(
+            bound(0.1, false),
+            Some(DecimalBound {
+                negative: false,
+                integer: 0,
+                fraction: "1".to_string(),
+                exclusive: false,
+            })
+        );
+        assert_eq!(bound(-0.0, true), Some(DecimalBound::from_i64(0, true)));
+        assert_eq!(bound(f64::NAN, false), None);
+        let huge = bound(-1e30, false).expect("Finite bound");
+        assert!(huge.negative && huge.integer == u64::MAX);
+        assert_eq!(huge.ceil(), i64::MIN);
+        for (value, ceil, floor) in [(1.5, 2, 1), (-1.5, -1, -2), (-0.5, 0, -1), (2.0, 2, 2)] {
+            let bound = bound(value, false).expect("Finite bound");
+            assert_eq!((bound.ceil(), bound.floor()), (ceil, floor), "{value}");
+        }
+        let exclusive = DecimalBound::from_i64(-2, true);
+        assert_eq!((exclusive.ceil(), exclusive.floor()), (-1, -3));
+    }
+
+    #[test]
+    fn multiples() {
+        assert_eq!(power_of_ten(100.0), Some(2));
+        assert_eq!(power_of_ten(1.0), Some(0));
+        assert_eq!(power_of_ten(0.01), Some(-2));
+        assert_eq!(power_of_ten(1e-7), Some(-7));
+        for multiple in [0.0, -10.0, 2.0, 0.5, 0.02, f64::NAN] {
+            assert_eq!(power_of_ten(multiple), None, "{multiple}");
+        }
+
+        let bounds = [-1200, -150, -100, -1, 0, 1, 100, 420, 1000];
+        let bounds: Vec<Option<i64>> = bounds.into_iter().map(Some).chain([None]).collect();
+        for zeros in [0, 1, 2] {
+            let scale = 10i64.pow(zeros);
+            for &min in &bounds {
+                for &max in &bounds {
+                    let within = |value: i64| {
+                        value % scale == 0
+                            && min.is_none_or(|min| min <= value)
+                            && max.is_none_or(|max| value <= max)
+                    };
+                    let Some(regex) = bounded_multiple(min, max, zeros) else {
+                        assert!(!(-1500..=1500).any(within), "Empty [{min:?}, {max:?}]");
+                        continue;
+                    };
+                    let re = Regex::new(&format!("^{regex}$")).expect("Regex failed");
+                    for value in -1500..=1500 {
+                        assert_eq!(
+                            re.is_match(&value.to_string()),
+                            within(value),
+                            "{value} in [{min:?}, {max:?}] by {scale}: {regex}"
+                        );
+                    }
+                    for not_canonical in ["00", "000", "-000", "0100", ""] {
+                        assert!(!re.is_match(not_canonical), "{not_canonical}: {regex}");
+                    }
+                }
+            }
+        }
+        assert_eq!(bounded_multiple(None, None, 19).as_deref(), Some("(0)"));
+        assert_eq!(bounded_multiple(Some(1), None, 19), None);
+
+        let min = DecimalBound::from_i64(1, true);
+        let max = DecimalBound::from_i64(3, false);
+        let regex = bounded_decimal(Some(&min), Some(&max), Some(2)).expect("Empty range");
+        let re = Regex::new(&format!("^{regex}$")).expect("Regex failed");
+        for number in ["1.01", "1.1", "1.10", "2", "2.5", "2.99", "3", "3.00"] {
+            assert!(re.is_match(number), "{number}: {regex}");
+        }
+        for number in ["1", "1.00", "1.001", "2.555", "3.01", "3.000", "-2"] {
+            assert!(!re.is_match(number), "{number}: {regex}");
+        }
+    }
+
+    #[test]
+    fn format_ranges() {
+        let (min, max) = format_range("int32").expect("No range");
+        let probes = [
+            0,
+            1,
+            -1,
+            max,
+            max - 1,
+            max + 1,
+            min,
+            min + 1,
+            min - 1,
+            10 * max,
+        ];
+        check_range(min, max, probes);
+
+        let (min, max) = format_range("int64").expect("No range");
+        check_range(min, max, [0, 7, -7, max, max - 1, min, min + 1]);
+        let re = Regex::new(&format!("^{}$", integer_range(min, max))).expect("Regex failed");
+        assert!(!re.is_match("9223372036854775808"));
+        assert!(re.is_match("-9223372036854775808"));
+        assert!(!re.is_match("-9223372036854775809"));
+        assert!(!re.is_match("10000000000000000000"));
+
+        let (min, max) = format_range("uint32").expect("No range");
+        check_range(min, max, [0, -1, max, max + 1]);
+
+        assert_eq!(format_range("uint8"), None);
+    }
+}
@@ -0,0 +1,154 @@
+//! Repair of model outputs, which nearly match a schema, e.g. cut off by a token limit or
+//! followed by chatter, with the automaton of the schema's regular expression.
+
+use regex_automata::dfa::dense::DFA;
+use regex_automata::dfa::Automaton;
+use regex_automata::util::primitives::StateID as AutomataStateId;
+use regex_automata::Anchored;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::regex_from_value;
+use crate::regex::{byte_classes, distances_to_match, live_edges};
+use crate::{Error, Result};
+
+/// Repaired output of [`repair`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Repair {
+    /// Length of the kept prefix of the text, in bytes.
+    pub valid_len: usize,
+    /// Text appended to the kept prefix, empty if the prefix is a valid value already.
+    pub completion: String,
+    /// The kept prefix followed by the completion, a value matching the schema.
+    pub repaired: String,
+}
+
+/// Repairs a text, which should be a value of the JSON schema, into one.
+///
+/// The longest prefix of the text, which some value of the schema starts with, is kept and
+/// the rest of the text is dropped. The prefix is then completed with the shortest text
+/// reaching a value of the schema, e.g. closing quotes and braces, or adding missing required
+/// properties with their shortest values, like `""` or `0`. Printable ASCII characters are
+/// preferred, so that the completion is deterministic.
+///
+/// The text is matched against the regular expression generated from the schema with the
+/// default options, so keywords approximated by the regular expression are not repaired.
+/// Fails with [`Error::NoValidInstance`] if no value matches the schema.
+///
+/// # Example
+///
+/// ```rust
+/// # use outlines_core::Error;
+/// use outlines_core::json_schema;
+/// use serde_json::json;
+///
+/// # fn main() -> Result<(), Error> {
+/// let schema = json!({
+///     "type": "object",
+///     "properties": {"name": {"type": "string"}, "age": {"type": "integer"}},
+///     "required": ["name", "age"]
+/// });
+/// let repair = json_schema::repair(r#"{"name": "Ada Lovel"#, &schema)?;
+/// assert_eq!(repair.completion, r#"","age":0}"#);
+/// assert_eq!(repair.repaired, r#"{"name": "Ada Lovel","age":0}"#);
+/// # Ok(())
+/// # }
+/// ```
+pub fn repair(text: &str, schema: &Value) -> Result<Repair> {
+    let regex = regex_from_value(schema, None, None)?;
+    let dfa = DFA::new(&regex).map_err(Box::new)?;
+    let start = dfa
+        .universal_start_state(Anchored::Yes)
+        .ok_or(Error::DfaHasNoStartState)?;
+    let classes = byte_classes(&dfa);
+    let edges = live_edges(&dfa, start, &classes);
+    let is_match = |state: AutomataStateId| dfa.is_match_state(dfa.next_eoi_state(state));
+    let distances = distances_to_match(&edges, is_match);
+    if !distances.contains_key(&start) {
+        return Err(Error::NoValidInstance);
+    }
+
+    // Prefixes are cut between characters, so that the repaired text is valid UTF-8.
+    let (mut valid_len, mut state) = (0, start);
+    let mut next_state = start;
+    for (i, c) in text.char_indices() {
+        for &byte in c.encode_utf8(&mut [0; 4]).as_bytes() {
+            next_state = dfa.next_state(next_state, byte);
+        }
+        // States out of the distances can't be completed, nor can the ones after them.
+        if !distances.contains_key(&next_state) {
+            break;
+        }
+        valid_len = i + c.len_utf8();
+        state = next_state;
+    }
+
+    let mut completion = Vec::new();
+    while let Some(&distance) = distances.get(&state).filter(|&&distance| distance > 0) {
+        let (byte, next_state) = edges[&state]
+            .iter()
+            .filter(|(_, next_state)| distances.get(next_state) == Some(&(distance - 1)))
+            .flat_map(|&(class, next_state)| {
+                classes[class].iter().map(move |&byte| (byte, next_state))
+            })
+            .min_by_key(|&(byte, _)| (!(byte.is_ascii_graphic() || byte == b' '), byte))
+            .expect("A state closer to a match has a next state");
+        completion.push(byte);
+        state = next_state;
+    }
+    // Matches of the regular expression are valid UTF-8, so is the completion.
+    let completion = String::from_utf8_lossy(&completion).into_owned();
+    let repaired = format!("{}{completion}", &text[..valid_len]);
+    Ok(Repair {
+        valid_len,
+        completion,
+        repaired,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn repair_outputs() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "name": {"type": "string"},
+                "tags": {"type": "array", "items": {"enum": ["a", "bé"]}},
+                "age": {"type": "integer"}
+            },
+            "required": ["name", "age"]
+        });
+        let regex = regex_from_value(&schema, None, None).expect("To regex failed");
+        let re = ::regex::Regex::new(&format!("^(?:{regex})$")).expect("Regex failed");
+        for (text, valid_len, completion) in [
+            (r#"{"name":"x","age":3}"#, 20, ""),
+            (r#"{"name":"x","age":3} Hope it helps!"#, 20, ""),
+            (r#"{"name":"x","age":3"#, 19, "}"),
+            (r#"{"name":"x","tags":["a","b"#, 26, r#"é"],"age":0}"#),
+            (r#"{"name":"x","tags":["a","c"]}"#, 25, r#"a"],"age":0}"#),
+            (r#"{"name": "x", "age": "3"}"#, 21, r#"0}"#),
+            ("Sure! Here it is", 0, r#"{"name":"","age":0}"#),
+            ("", 0, r#"{"name":"","age":0}"#),
+        ] {
+            let repair = repair(text, &schema).expect("Repair failed");
+            assert_eq!(repair.valid_len, valid_len, "{text}");
+            assert_eq!(repair.completion, completion, "{text}");
+            assert_eq!(
+                repair.repaired,
+                format!("{}{completion}", &text[..valid_len])
+            );
+            assert!(re.is_match(&repair.repaired), "{}", repair.repaired);
+        }
+
+        // The cut doesn't split a character.
+        let schema = json!({"properties": {"tags": schema["properties"]["tags"]}});
+        let repair = repair(r#"{"tags":["bè"#, &schema).expect("Repair failed");
+        assert_eq!(repair.valid_len, 11);
+        assert_eq!(repair.repaired, r#"{"tags":["bé"]}"#);
+    }
+}
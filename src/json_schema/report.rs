@@ -0,0 +1,305 @@
+//! Report of the conversion of a JSON schema into a regular expression, e.g. to be logged by
+//! platforms to learn which schema features their users rely on.
+
+use std::collections::BTreeMap;
+use std::time::Instant;
+
+use regex_automata::nfa::thompson::NFA;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::keywords::{KeywordPolicy, SchemaWarning, WarningCollector};
+use super::{parser, SchemaOptions};
+use crate::Result;
+
+/// Keywords, which values are maps of subschemas by names.
+pub(super) const SCHEMA_MAPS: &[&str] = &[
+    "$defs",
+    "definitions",
+    "dependentSchemas",
+    "patternProperties",
+    "properties",
+];
+
+/// Keywords, which values are a subschema or a list of subschemas.
+pub(super) const SUBSCHEMAS: &[&str] = &[
+    "additionalItems",
+    "additionalProperties",
+    "allOf",
+    "anyOf",
+    "contains",
+    "else",
+    "if",
+    "items",
+    "not",
+    "oneOf",
+    "prefixItems",
+    "propertyNames",
+    "then",
+    "unevaluatedItems",
+    "unevaluatedProperties",
+];
+
+/// Report of the conversion of a JSON schema, see [`regex_from_value_with_report`].
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct CompilationReport {
+    /// Number of occurrences of each keyword in the schema nodes, annotations included.
+    pub keywords: BTreeMap<String, usize>,
+    /// Ignored keywords and excluded listed values, whatever the keyword policy.
+    pub warnings: Vec<SchemaWarning>,
+    /// Changes of the schema semantics made by the conversion.
+    pub normalizations: Vec<Normalization>,
+    /// Length of the generated regular expression, in bytes.
+    pub regex_length: usize,
+    /// Number of states of the NFA of the regular expression, which bounds the cost of the
+    /// index construction, unless the NFA is too big to be built.
+    pub nfa_states: Option<usize>,
+    /// Duration of the regular expression generation, in seconds.
+    pub generation_seconds: f64,
+    /// Duration of the cost estimation, in seconds.
+    pub estimation_seconds: f64,
+}
+
+/// Change of the semantics of a schema node made by its conversion.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Normalization {
+    /// JSON pointer to the closest schema node of the change.
+    pub path: String,
+    /// The keyword, which the change applies to.
+    pub keyword: String,
+    /// What was changed.
+    pub kind: NormalizationKind,
+}
+
+/// Kind of a [`Normalization`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum NormalizationKind {
+    /// The missing bound is set by the default of [`SchemaOptions`].
+    DefaultBound { value: u64 },
+    /// The node is intersected with its `allOf` members or `$ref` target.
+    Combined,
+    /// Duplicated alternatives of `anyOf` or `oneOf` are removed.
+    DeduplicatedBranches { removed: usize },
+}
+
+/// Generates a regular expression from the JSON schema with options, like
+/// [`regex_from_value_with_options`](super::regex_from_value_with_options), and reports
+/// on the conversion.
+///
+/// Ignored keywords are reported even with [`KeywordPolicy::Lenient`], while
+/// [`KeywordPolicy::Strict`] still fails on them. A [`KeywordPolicy::Warn`] collector
+/// receives the warnings as well.
+///
+/// # Example
+///
+/// ```rust
+/// # use outlines_core::Error;
+/// use outlines_core::json_schema::{self, SchemaOptions};
+/// use serde_json::json;
+///
+/// # fn main() -> Result<(), Error> {
+/// let schema = json!({"type": "array", "items": {"type": "integer", "not": {"const": 0}}});
+/// let options = SchemaOptions { default_max_items: Some(8), ..Default::default() };
+/// let (_, report) = json_schema::regex_from_value_with_report(&schema, &options)?;
+/// assert_eq!(report.keywords["type"], 2);
+/// assert_eq!(report.warnings[0].keyword, "not");
+/// assert_eq!(report.normalizations[0].keyword, "maxItems");
+/// # Ok(())
+/// # }
+/// ```
+pub fn regex_from_value_with_report(
+    json: &Value,
+    options: &SchemaOptions,
+) -> Result<(String, CompilationReport)> {
+    let collector = WarningCollector::new();
+    let keyword_policy = match options.keyword_policy {
+        KeywordPolicy::Strict => KeywordPolicy::Strict,
+        _ => KeywordPolicy::Warn(collector.clone()),
+    };
+    let report_options = SchemaOptions {
+        keyword_policy,
+        ..options.clone()
+    };
+
+    let start = Instant::now();
+    let mut parser = parser(json, &report_options);
+    let regex = parser.to_regex(json)?;
+    let generation_seconds = start.elapsed().as_secs_f64();
+
+    let start = Instant::now();
+    let nfa_states = NFA::new(&regex).ok().map(|nfa| nfa.states().len());
+    let estimation_seconds = start.elapsed().as_secs_f64();
+
+    let warnings = collector.take();
+    if let KeywordPolicy::Warn(user_collector) = &options.keyword_policy {
+        for warning in &warnings {
+            user_collector.report(warning.clone());
+        }
+    }
+    let mut keywords = BTreeMap::new();
+    count_keywords(json, &mut keywords);
+    let report = CompilationReport {
+        keywords,
+        warnings,
+        normalizations: parser.normalizations(),
+        regex_length: regex.len(),
+        nfa_states,
+        generation_seconds,
+        estimation_seconds,
+    };
+    Ok((regex, report))
+}
+
+/// Counts the keywords of the schema node and of its subschemas.
+fn count_keywords(json: &Value, keywords: &mut BTreeMap<String, usize>) {
+    let Value::Object(obj) = json else {
+        return;
+    };
+    for (keyword, value) in obj {
+        *keywords.entry(keyword.clone()).or_default() += 1;
+        match value {
+            Value::Object(schemas) if SCHEMA_MAPS.contains(&keyword.as_str()) => {
+                for schema in schemas.values() {
+                    count_keywords(schema, keywords);
+                }
+            }
+            Value::Array(schemas) if SUBSCHEMAS.contains(&keyword.as_str()) => {
+                for schema in schemas {
+                    count_keywords(schema, keywords);
+                }
+            }
+            schema if SUBSCHEMAS.contains(&keyword.as_str()) => count_keywords(schema, keywords),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::json_schema::{regex_from_value_with_options, WarningKind};
+
+    #[test]
+    fn report() {
+        let schema = json!({
+            "$defs": {"name": {"type": "string", "title": "Name"}},
+            "type": "object",
+            "properties": {
+                "name": {"$ref": "#/$defs/name", "maxLength": 4},
+                "tags": {"type": "array", "items": {"type": "string"}, "uniqueItems": true},
+                "type": {"enum": [1, 2, "a"], "type": "integer"}
+            },
+            "required": ["name"]
+        });
+        let options = SchemaOptions {
+            default_max_string: Some(16),
+            default_max_items: Some(2),
+            ..Default::default()
+        };
+        let (regex, report) =
+            regex_from_value_with_report(&schema, &options).expect("Report failed");
+        assert_eq!(
+            regex,
+            regex_from_value_with_options(&schema, &options).expect("To regex failed")
+        );
+
+        let keywords: Vec<_> = report
+            .keywords
+            .iter()
+            .map(|(keyword, count)| (keyword.as_str(), *count))
+            .collect();
+        assert_eq!(
+            keywords,
+            vec![
+                ("$defs", 1),
+                ("$ref", 1),
+                ("enum", 1),
+                ("items", 1),
+                ("maxLength", 1),
+                ("properties", 1),
+                ("required", 1),
+                ("title", 1),
+                ("type", 5),
+                ("uniqueItems", 1),
+            ]
+        );
+
+        let warnings: Vec<_> = report
+            .warnings
+            .iter()
+            .map(|w| (w.path.as_str(), w.keyword.as_str(), w.kind))
+            .collect();
+        assert_eq!(
+            warnings,
+            vec![
+                (
+                    "/properties/tags",
+                    "uniqueItems",
+                    WarningKind::IgnoredKeyword
+                ),
+                (
+                    "/properties/type/enum/2",
+                    "type",
+                    WarningKind::ExcludedEnumValue
+                ),
+            ]
+        );
+
+        let normalizations: Vec<_> = report
+            .normalizations
+            .iter()
+            .map(|n| (n.path.as_str(), n.keyword.as_str(), n.kind))
+            .collect();
+        assert_eq!(
+            normalizations,
+            vec![
+                ("/properties/name", "$ref", NormalizationKind::Combined),
+                (
+                    "/properties/tags",
+                    "maxItems",
+                    NormalizationKind::DefaultBound { value: 2 }
+                ),
+                (
+                    "/properties/tags/items",
+                    "maxLength",
+                    NormalizationKind::DefaultBound { value: 16 }
+                ),
+            ]
+        );
+
+        assert_eq!(report.regex_length, regex.len());
+        assert!(report.nfa_states.is_some_and(|states| states > 0));
+
+        let value = serde_json::to_value(&report.normalizations[1]).expect("Serialization failed");
+        assert_eq!(
+            value,
+            json!({
+                "path": "/properties/tags",
+                "keyword": "maxItems",
+                "kind": {"type": "default_bound", "value": 2}
+            })
+        );
+    }
+
+    #[test]
+    fn report_with_policies() {
+        let schema = json!({"type": "integer", "not": {"const": 0}});
+        let collector = WarningCollector::new();
+        let options = SchemaOptions {
+            keyword_policy: KeywordPolicy::Warn(collector.clone()),
+            ..Default::default()
+        };
+        let (_, report) = regex_from_value_with_report(&schema, &options).expect("Report failed");
+        assert_eq!(report.warnings, collector.warnings());
+        assert_eq!(report.warnings.len(), 1);
+
+        let options = SchemaOptions {
+            keyword_policy: KeywordPolicy::Strict,
+            ..Default::default()
+        };
+        assert!(regex_from_value_with_report(&schema, &options).is_err());
+    }
+}
@@ -1,13 +1,99 @@
 //! Static collection of regular expressions for JSON and format types used
 //! in generating a regular expression string based on a given JSON schema.
 
+use std::fmt::{self, Display};
+
 // allow `\"`, `\\`, or any character which isn't a control sequence
 pub static STRING_INNER: &str = r#"([^"\\\x00-\x1F\x7F-\x9F]|\\["\\/bfnrt])"#;
-pub static STRING: &str = r#""([^"\\\x00-\x1F\x7F-\x9F]|\\["\\/bfnrt])*""#;
-pub static INTEGER: &str = r#"(-)?(0|[1-9][0-9]*)"#;
-pub static NUMBER: &str = r#"((-)?(0|[1-9][0-9]*))(\.[0-9]+)?([eE][+-][0-9]+)?"#;
-pub static BOOLEAN: &str = r#"(true|false)"#;
-pub static NULL: &str = r#"null"#;
+pub static STRING: &str = Primitive::String.as_str();
+pub static INTEGER: &str = Primitive::Integer.as_str();
+pub static NUMBER: &str = Primitive::Number.as_str();
+pub static BOOLEAN: &str = Primitive::Boolean.as_str();
+pub static NULL: &str = Primitive::Null.as_str();
+
+/// Regular expression of a JSON value of a primitive type or string format, displayed as
+/// the regular expression, to compose patterns of schemas by hand.
+///
+/// # Example
+///
+/// ```rust
+/// use outlines_core::json_schema::Primitive;
+///
+/// assert_eq!(Primitive::Boolean.to_string(), "(true|false)");
+/// assert_eq!(Primitive::Boolean.optional(), "((true|false))?");
+/// assert_eq!(Primitive::Null.repeated(1, Some(2)), "(null){1,2}");
+/// assert_eq!(Primitive::Integer.quoted(), r#""((-)?(0|[1-9][0-9]*))""#);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Primitive {
+    String,
+    Integer,
+    Number,
+    Boolean,
+    Null,
+    DateTime,
+    Date,
+    Time,
+    Uuid,
+    Uri,
+    Email,
+}
+
+impl Primitive {
+    /// Returns the regular expression of the primitive.
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Primitive::String => r#""([^"\\\x00-\x1F\x7F-\x9F]|\\["\\/bfnrt])*""#,
+            Primitive::Integer => r#"(-)?(0|[1-9][0-9]*)"#,
+            Primitive::Number => r#"((-)?(0|[1-9][0-9]*))(\.[0-9]+)?([eE][+-][0-9]+)?"#,
+            Primitive::Boolean => r#"(true|false)"#,
+            Primitive::Null => r#"null"#,
+            // https://www.iso.org/obp/ui/#iso:std:iso:8601:-1:ed-1:v1:en and https://stackoverflow.com/questions/3143070/regex-to-match-an-iso-8601-datetime-string
+            Primitive::DateTime => {
+                r#""(-?(?:[1-9][0-9]*)?[0-9]{4})-(1[0-2]|0[1-9])-(3[01]|0[1-9]|[12][0-9])T(2[0-3]|[01][0-9]):([0-5][0-9]):([0-5][0-9])(\.[0-9]{3})?(Z)?""#
+            }
+            Primitive::Date => r#""(?:\d{4})-(?:0[1-9]|1[0-2])-(?:0[1-9]|[1-2][0-9]|3[0-1])""#,
+            Primitive::Time => r#""(2[0-3]|[01][0-9]):([0-5][0-9]):([0-5][0-9])(\\.[0-9]+)?(Z)?""#,
+            // https://datatracker.ietf.org/doc/html/rfc9562 and https://stackoverflow.com/questions/136505/searching-for-uuids-in-text-with-regex
+            Primitive::Uuid => r#""[0-9a-f]{8}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{12}""#,
+            // https://datatracker.ietf.org/doc/html/rfc3986#appendix-B
+            Primitive::Uri => {
+                r#""(?:(https?|ftp):\/\/([^\s:@]+(:[^\s:@]*)?@)?([a-zA-Z\d.-]+\.[a-zA-Z]{2,}|localhost)(:\d+)?(\/[^\s?#]*)?(\?[^\s#]*)?(#[^\s]*)?|urn:[a-zA-Z\d][a-zA-Z\d\-]{0,31}:[^\s]+)""#
+            }
+            // https://www.rfc-editor.org/rfc/rfc5322 and https://stackoverflow.com/questions/13992403/regex-validation-of-email-addresses-according-to-rfc5321-rfc5322
+            Primitive::Email => {
+                r#""(?:[a-z0-9!#$%&'*+/=?^_`{|}~-]+(?:\.[a-z0-9!#$%&'*+/=?^_`{|}~-]+)*|"(?:[\x01-\x08\x0b\x0c\x0e-\x1f\x21\x23-\x5b\x5d-\x7f]|\\[\x01-\x09\x0b\x0c\x0e-\x7f])*")@(?:(?:[a-z0-9](?:[a-z0-9-]*[a-z0-9])?\.)+[a-z0-9](?:[a-z0-9-]*[a-z0-9])?|\[(?:(?:(2(5[0-5]|[0-4][0-9])|1[0-9][0-9]|[1-9]?[0-9]))\.){3}(?:(2(5[0-5]|[0-4][0-9])|1[0-9][0-9]|[1-9]?[0-9])|[a-z0-9-]*[a-z0-9]:(?:[\x01-\x08\x0b\x0c\x0e-\x1f\x21-\x5a\x53-\x7f]|\\[\x01-\x09\x0b\x0c\x0e-\x7f])+)\])""#
+            }
+        }
+    }
+
+    /// Regular expression of the primitive or of nothing.
+    pub fn optional(&self) -> String {
+        format!("({self})?")
+    }
+
+    /// Regular expression of `min` to `max` repetitions of the primitive, unbounded without
+    /// `max`.
+    pub fn repeated(&self, min: usize, max: Option<usize>) -> String {
+        match max {
+            Some(max) if max == min => format!("({self}){{{min}}}"),
+            Some(max) => format!("({self}){{{min},{max}}}"),
+            None => format!("({self}){{{min},}}"),
+        }
+    }
+
+    /// Regular expression of the primitive between double quotes, e.g. for numbers encoded
+    /// as strings.
+    pub fn quoted(&self) -> String {
+        format!("\"({self})\"")
+    }
+}
+
+impl Display for Primitive {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
 
 /// Default whitespace pattern used for generating a regular expression from JSON schema.
 ///
@@ -28,26 +114,27 @@ pub enum JsonType {
 
 impl JsonType {
     pub fn to_regex(&self) -> &'static str {
+        self.primitive().as_str()
+    }
+
+    /// Returns the primitive of the type.
+    pub fn primitive(&self) -> Primitive {
         match self {
-            JsonType::String => STRING,
-            JsonType::Integer => INTEGER,
-            JsonType::Number => NUMBER,
-            JsonType::Boolean => BOOLEAN,
-            JsonType::Null => NULL,
+            JsonType::String => Primitive::String,
+            JsonType::Integer => Primitive::Integer,
+            JsonType::Number => Primitive::Number,
+            JsonType::Boolean => Primitive::Boolean,
+            JsonType::Null => Primitive::Null,
         }
     }
 }
 
-// https://www.iso.org/obp/ui/#iso:std:iso:8601:-1:ed-1:v1:en and https://stackoverflow.com/questions/3143070/regex-to-match-an-iso-8601-datetime-string
-pub static DATE_TIME: &str = r#""(-?(?:[1-9][0-9]*)?[0-9]{4})-(1[0-2]|0[1-9])-(3[01]|0[1-9]|[12][0-9])T(2[0-3]|[01][0-9]):([0-5][0-9]):([0-5][0-9])(\.[0-9]{3})?(Z)?""#;
-pub static DATE: &str = r#""(?:\d{4})-(?:0[1-9]|1[0-2])-(?:0[1-9]|[1-2][0-9]|3[0-1])""#;
-pub static TIME: &str = r#""(2[0-3]|[01][0-9]):([0-5][0-9]):([0-5][0-9])(\\.[0-9]+)?(Z)?""#;
-// https://datatracker.ietf.org/doc/html/rfc9562 and https://stackoverflow.com/questions/136505/searching-for-uuids-in-text-with-regex
-pub static UUID: &str = r#""[0-9a-f]{8}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{12}""#;
-// https://datatracker.ietf.org/doc/html/rfc3986#appendix-B
-pub static URI: &str = r#""(?:(https?|ftp):\/\/([^\s:@]+(:[^\s:@]*)?@)?([a-zA-Z\d.-]+\.[a-zA-Z]{2,}|localhost)(:\d+)?(\/[^\s?#]*)?(\?[^\s#]*)?(#[^\s]*)?|urn:[a-zA-Z\d][a-zA-Z\d\-]{0,31}:[^\s]+)""#;
-// https://www.rfc-editor.org/rfc/rfc5322 and https://stackoverflow.com/questions/13992403/regex-validation-of-email-addresses-according-to-rfc5321-rfc5322
-pub static EMAIL: &str = r#""(?:[a-z0-9!#$%&'*+/=?^_`{|}~-]+(?:\.[a-z0-9!#$%&'*+/=?^_`{|}~-]+)*|"(?:[\x01-\x08\x0b\x0c\x0e-\x1f\x21\x23-\x5b\x5d-\x7f]|\\[\x01-\x09\x0b\x0c\x0e-\x7f])*")@(?:(?:[a-z0-9](?:[a-z0-9-]*[a-z0-9])?\.)+[a-z0-9](?:[a-z0-9-]*[a-z0-9])?|\[(?:(?:(2(5[0-5]|[0-4][0-9])|1[0-9][0-9]|[1-9]?[0-9]))\.){3}(?:(2(5[0-5]|[0-4][0-9])|1[0-9][0-9]|[1-9]?[0-9])|[a-z0-9-]*[a-z0-9]:(?:[\x01-\x08\x0b\x0c\x0e-\x1f\x21-\x5a\x53-\x7f]|\\[\x01-\x09\x0b\x0c\x0e-\x7f])+)\])""#;
+pub static DATE_TIME: &str = Primitive::DateTime.as_str();
+pub static DATE: &str = Primitive::Date.as_str();
+pub static TIME: &str = Primitive::Time.as_str();
+pub static UUID: &str = Primitive::Uuid.as_str();
+pub static URI: &str = Primitive::Uri.as_str();
+pub static EMAIL: &str = Primitive::Email.as_str();
 
 /// Supported format type of the `JsonType::String`.
 #[derive(Debug, PartialEq)]
@@ -62,13 +149,18 @@ pub enum FormatType {
 
 impl FormatType {
     pub fn to_regex(&self) -> &'static str {
+        self.primitive().as_str()
+    }
+
+    /// Returns the primitive of the format.
+    pub fn primitive(&self) -> Primitive {
         match self {
-            FormatType::DateTime => DATE_TIME,
-            FormatType::Date => DATE,
-            FormatType::Time => TIME,
-            FormatType::Uuid => UUID,
-            FormatType::Uri => URI,
-            FormatType::Email => EMAIL,
+            FormatType::DateTime => Primitive::DateTime,
+            FormatType::Date => Primitive::Date,
+            FormatType::Time => Primitive::Time,
+            FormatType::Uuid => Primitive::Uuid,
+            FormatType::Uri => Primitive::Uri,
+            FormatType::Email => Primitive::Email,
         }
     }
 
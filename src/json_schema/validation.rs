@@ -0,0 +1,89 @@
+//! Validation of generated values against their JSON Schema.
+//!
+//! Regular expressions approximate some keywords of a schema, e.g. `uniqueItems` or numeric
+//! bounds are not enforced, so that a value generated with the index of a schema may still
+//! violate it. [`validate`] checks a value with the complete semantics of JSON Schema.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{Error, Result};
+
+/// A part of a value, which doesn't satisfy a keyword of its schema.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SchemaMismatch {
+    /// JSON pointer of the mismatching part of the value, empty for the whole value.
+    pub instance_path: String,
+    /// JSON pointer of the unsatisfied keyword in the schema, empty if the value isn't JSON.
+    pub schema_path: String,
+    /// Description of the mismatch.
+    pub message: String,
+}
+
+/// Validates the JSON encoded value against the schema and returns all its mismatches.
+///
+/// A value, which is not valid JSON, has a single mismatch at its root. Fails if the schema
+/// itself is invalid.
+pub fn validate(schema: &str, value: &[u8]) -> Result<Vec<SchemaMismatch>> {
+    let schema: Value = serde_json::from_str(schema)?;
+    let validator =
+        jsonschema::validator_for(&schema).map_err(|e| Error::InvalidJsonSchema(e.to_string()))?;
+    let value: Value = match serde_json::from_slice(value) {
+        Ok(value) => value,
+        Err(e) => {
+            return Ok(vec![SchemaMismatch {
+                instance_path: String::new(),
+                schema_path: String::new(),
+                message: format!("Invalid JSON: {e}"),
+            }])
+        }
+    };
+    Ok(validator
+        .iter_errors(&value)
+        .map(|error| SchemaMismatch {
+            instance_path: error.instance_path().as_str().to_string(),
+            schema_path: error.schema_path().as_str().to_string(),
+            message: error.to_string(),
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validate_values() {
+        let schema = r#"{
+            "type": "object",
+            "properties": {
+                "tags": {"type": "array", "items": {"type": "string"}, "uniqueItems": true},
+                "count": {"type": "integer", "minimum": 1}
+            },
+            "required": ["count"]
+        }"#;
+        let mismatches = validate(schema, br#"{"tags": ["a", "b"], "count": 2}"#);
+        assert_eq!(mismatches.expect("Validation failed"), vec![]);
+
+        let mismatches =
+            validate(schema, br#"{"tags": ["a", "a"], "count": 0}"#).expect("Validation failed");
+        let paths: Vec<_> = mismatches
+            .iter()
+            .map(|m| (m.instance_path.as_str(), m.schema_path.as_str()))
+            .collect();
+        assert_eq!(
+            paths,
+            vec![
+                ("/tags", "/properties/tags/uniqueItems"),
+                ("/count", "/properties/count/minimum")
+            ]
+        );
+
+        let mismatches = validate(schema, br#"{"count": 1"#).expect("Validation failed");
+        assert_eq!(mismatches.len(), 1);
+        assert!(mismatches[0].message.starts_with("Invalid JSON"));
+
+        let result = validate(r#"{"type": 1}"#, b"1");
+        assert!(matches!(result, Err(Error::InvalidJsonSchema(_))));
+    }
+}
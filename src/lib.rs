@@ -18,6 +18,32 @@
 //!
 //! Note, that not all the features of JSON schema are supported for regex generation: [Supported Features](json_schema#supported-features)
 //!
+//! ## `markdown`
+//!
+//! [`markdown`] module provides builders of regular expressions for Markdown-structured outputs,
+//! such as bulleted lists and tables.
+//!
+//! ## `text`
+//!
+//! [`text`] module provides builders of regular expressions for free text with constraints on
+//! its content, such as containing at least one of the given substrings.
+//!
+//! ## `cbor`
+//!
+//! [`cbor`] module is an experimental builder of byte-level regular expressions for compact
+//! binary payloads in a subset of CBOR, to be generated with byte-fallback vocabularies.
+//!
+//! ## `regex`
+//!
+//! [`regex`] module helps to sanity-check constraints, e.g. by sampling example strings
+//! permitted by a regular expression.
+//!
+//! ## `primitives`
+//!
+//! [`primitives`] module defines the distinct [`TokenId`](primitives::TokenId) and
+//! [`StateId`](primitives::StateId) types, which are serialized as `u32`. Their former `u32`
+//! aliases are kept in `primitives::compat`, enabled by the `compat-ids` feature.
+//!
 //! ## `Index`
 //!
 //! Once [`index::Index`] is built, it can be used to evaluate or validate token sequences.
@@ -27,6 +53,36 @@
 //! `Index` can accommodate large vocabularies and complex regular expressions. However, its size **may** grow
 //! significantly with the complexity of the input, as well as time and computational resources.
 //!
+//! When many schemas are compiled against the same vocabulary, [`batch::BatchCompiler`] shares
+//! the preparation of vocabulary tokens between them and compiles them in parallel.
+//!
+//! ## `Guide`
+//!
+//! [`guide::Guide`] walks through the states of an `Index` token by token, supporting rollback
+//! and export of its state to resume generation elsewhere. [`fields`] module names the JSON
+//! field generated in each state, reported by the guide while it walks.
+//!
+//! ## Token masks
+//!
+//! [`mask`] module packs allowed tokens into bitmasks and iterates over the tokens of a
+//! bitmask without collecting them, while [`bias`] module attaches biases to transitions,
+//! exported besides the masks for soft guidance.
+//!
+//! ## `IndexRegistry`
+//!
+//! [`registry::IndexRegistry`] keeps named indexes of a serving process, compiling them in
+//! the background and evicting least recently used ones over its memory budget.
+//!
+//! ## Metrics
+//!
+//! `metrics` module reports counters and histograms, e.g. of index constructions and served
+//! masks, to a recorder of the serving system, enabled by the `metrics` feature.
+//!
+//! ## Integrations
+//!
+//! `integrations` module provides optional adapters for inference frameworks, each one
+//! enabled by its own feature, e.g. `candle` or `llama-cpp`.
+//!
 //! ## Python bindings
 //!
 //! Additionally, crate provides interfaces to integrate the crate's functionality with Python.
@@ -80,14 +136,37 @@
 //! }
 //! ```
 
+#[cfg(feature = "arrow")]
+pub mod arrow;
+pub mod batch;
+pub mod bias;
+pub mod capabilities;
+pub mod cbor;
 pub mod error;
+pub mod fields;
+pub mod guide;
 pub mod index;
+#[cfg(any(feature = "candle", feature = "llama-cpp"))]
+pub mod integrations;
 pub mod json_schema;
+pub mod markdown;
+pub mod mask;
+#[cfg(feature = "metrics")]
+pub mod metrics;
 pub mod prelude;
 pub mod primitives;
+#[cfg(feature = "protobuf")]
+pub mod protobuf;
+pub mod regex;
+pub mod registry;
+pub mod text;
+pub mod typescript;
 pub mod vocabulary;
 
+pub use capabilities::{capabilities, Capabilities};
 pub use error::{Error, Result};
 
+mod compose;
+
 #[cfg(feature = "python-bindings")]
 mod python_bindings;
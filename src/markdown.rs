@@ -0,0 +1,219 @@
+//! Builders of regular expressions for Markdown-structured outputs.
+//!
+//! Generated regular expressions are meant to be compiled into an [`Index`](crate::index::Index)
+//! as any other regex, for example:
+//!
+//! ```rust
+//! # use outlines_core::Error;
+//! use outlines_core::markdown::{BulletList, Table};
+//!
+//! # fn main() -> Result<(), Error> {
+//! // A bulleted list of 3 to 5 items, each of them is a capitalized word
+//! let list = BulletList::new(r"[A-Z][a-z]+").with_items(3, Some(5)).to_regex()?;
+//!
+//! // A table with two columns and at most 10 rows
+//! let table = Table::new()
+//!     .with_column("Name", r"[A-Za-z ]+")
+//!     .with_column("Age", r"[0-9]{1,3}")
+//!     .with_rows(1, Some(10))
+//!     .to_regex()?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Note, that item and cell patterns shouldn't match newlines, since lines delimit items and rows.
+
+use regex::escape;
+
+use crate::json_schema::lines_regex;
+use crate::{Error, Result};
+
+/// Default cell pattern: any text without pipes and newlines.
+pub static CELL: &str = r"[^|\n]+";
+
+/// Builder of a bulleted list, one item per line.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BulletList {
+    item_pattern: String,
+    marker: String,
+    min_items: usize,
+    max_items: Option<usize>,
+}
+
+impl BulletList {
+    /// Creates a list of at least one item, each of them matching `item_pattern`.
+    pub fn new(item_pattern: impl Into<String>) -> Self {
+        Self {
+            item_pattern: item_pattern.into(),
+            marker: "-".to_string(),
+            min_items: 1,
+            max_items: None,
+        }
+    }
+
+    /// Sets the bullet marker, `-` by default.
+    pub fn with_marker(self, marker: impl Into<String>) -> Self {
+        Self {
+            marker: marker.into(),
+            ..self
+        }
+    }
+
+    /// Bounds the number of items in the list.
+    pub fn with_items(self, min_items: usize, max_items: Option<usize>) -> Self {
+        Self {
+            min_items,
+            max_items,
+            ..self
+        }
+    }
+
+    /// Generates the regular expression of the list.
+    #[allow(clippy::wrong_self_convention)]
+    pub fn to_regex(&self) -> Result<String> {
+        let line = format!("{} ({})", escape(&self.marker), self.item_pattern);
+        lines_regex(&line, self.min_items, self.max_items)
+    }
+}
+
+/// Builder of a table with a header row, a delimiter row and a bounded number of rows.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Table {
+    columns: Vec<(String, String)>,
+    min_rows: usize,
+    max_rows: Option<usize>,
+}
+
+impl Table {
+    /// Creates a table without columns, allowing any number of rows.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends a column with a given header, each cell of which matches `cell_pattern`.
+    pub fn with_column(
+        mut self,
+        header: impl Into<String>,
+        cell_pattern: impl Into<String>,
+    ) -> Self {
+        self.columns.push((header.into(), cell_pattern.into()));
+        self
+    }
+
+    /// Bounds the number of rows in the table, excluding header and delimiter rows.
+    pub fn with_rows(self, min_rows: usize, max_rows: Option<usize>) -> Self {
+        Self {
+            min_rows,
+            max_rows,
+            ..self
+        }
+    }
+
+    /// Generates the regular expression of the table.
+    #[allow(clippy::wrong_self_convention)]
+    pub fn to_regex(&self) -> Result<String> {
+        if self.columns.is_empty() {
+            return Err(Error::MarkdownTableWithoutColumns);
+        }
+        let header = self
+            .columns
+            .iter()
+            .map(|(header, _)| escape(header))
+            .collect::<Vec<_>>()
+            .join(r" \| ");
+        let delimiter = vec!["---"; self.columns.len()].join(r" \| ");
+        let row = self
+            .columns
+            .iter()
+            .map(|(_, cell)| format!("({cell})"))
+            .collect::<Vec<_>>()
+            .join(r" \| ");
+
+        if matches!(self.max_rows, Some(max) if max < self.min_rows) {
+            return Err(Error::MaxBoundError);
+        }
+        let rows = match (self.min_rows, self.max_rows) {
+            (_, Some(0)) => String::new(),
+            // Rows are optional altogether, so is the newline separating them from the delimiter.
+            (0, max_rows) => format!(
+                r"(\n{})?",
+                lines_regex(&format!(r"\| {row} \|"), 1, max_rows)?
+            ),
+            (min_rows, max_rows) => format!(
+                r"\n{}",
+                lines_regex(&format!(r"\| {row} \|"), min_rows, max_rows)?
+            ),
+        };
+        Ok(format!(r"\| {header} \|\n\| {delimiter} \|{rows}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use regex::Regex;
+
+    use super::*;
+
+    fn full_match(regex: &str, value: &str) -> bool {
+        Regex::new(&format!("^(?:{regex})$"))
+            .expect("Regex failed")
+            .is_match(value)
+    }
+
+    #[test]
+    fn bullet_list() {
+        let regex = BulletList::new("[a-z]+")
+            .with_items(2, Some(3))
+            .to_regex()
+            .expect("To regex failed");
+        assert!(full_match(&regex, "- one\n- two"));
+        assert!(full_match(&regex, "- one\n- two\n- three"));
+        assert!(!full_match(&regex, "- one"));
+        assert!(!full_match(&regex, "- one\n- two\n- three\n- four"));
+        assert!(!full_match(&regex, "- One\n- two"));
+
+        let regex = BulletList::new("[a-z]+")
+            .with_marker("*")
+            .to_regex()
+            .expect("To regex failed");
+        assert!(full_match(&regex, "* one"));
+        assert!(!full_match(&regex, "- one"));
+
+        let result = BulletList::new("[a-z]+").with_items(3, Some(2)).to_regex();
+        assert!(matches!(result, Err(Error::MaxBoundError)));
+    }
+
+    #[test]
+    fn table() {
+        let regex = Table::new()
+            .with_column("Name", "[A-Za-z]+")
+            .with_column("Age", "[0-9]+")
+            .with_rows(1, Some(2))
+            .to_regex()
+            .expect("To regex failed");
+        assert!(full_match(
+            &regex,
+            "| Name | Age |\n| --- | --- |\n| Bob | 42 |"
+        ));
+        assert!(full_match(
+            &regex,
+            "| Name | Age |\n| --- | --- |\n| Bob | 42 |\n| Alice | 7 |"
+        ));
+        assert!(!full_match(&regex, "| Name | Age |\n| --- | --- |"));
+        assert!(!full_match(
+            &regex,
+            "| Name | Age |\n| --- | --- |\n| Bob | forty |"
+        ));
+
+        let regex = Table::new()
+            .with_column("Item", CELL)
+            .with_rows(0, None)
+            .to_regex()
+            .expect("To regex failed");
+        assert!(full_match(&regex, "| Item |\n| --- |"));
+        assert!(full_match(&regex, "| Item |\n| --- |\n| a b |\n| c |"));
+
+        let result = Table::new().to_regex();
+        assert!(matches!(result, Err(Error::MarkdownTableWithoutColumns)));
+    }
+}
@@ -0,0 +1,130 @@
+//! Bitmasks of tokens, as written by `Guide.write_mask_into` of the Python bindings and
+//! applied by the kernels of `outlines_core.kernels`.
+//!
+//! Token ids are packed into 32-bit words: the token `t` is the bit `t % 32` of the word
+//! `t / 32`. [`set_bits`] iterates over the tokens of a mask without collecting them:
+//!
+//! ```rust
+//! use outlines_core::mask;
+//! use outlines_core::primitives::TokenId;
+//!
+//! let mut words = vec![0; mask::mask_len(40)];
+//! mask::set_tokens(&mut words, &[3, 33, 1].map(TokenId::new));
+//! assert_eq!(mask::set_bits(words).collect::<Vec<_>>(), vec![1, 3, 33]);
+//! ```
+
+use std::iter::FusedIterator;
+
+use crate::primitives::TokenId;
+
+/// Number of words of the mask of a vocabulary.
+pub fn mask_len(vocab_size: usize) -> usize {
+    vocab_size.div_ceil(32)
+}
+
+/// Sets the bits of the tokens, token ids beyond the mask are ignored.
+pub fn set_tokens<'a>(mask: &mut [u32], tokens: impl IntoIterator<Item = &'a TokenId>) {
+    for &token_id in tokens {
+        if let Some(word) = mask.get_mut(token_id.as_usize() / 32) {
+            *word |= 1 << (token_id.as_u32() % 32);
+        }
+    }
+}
+
+/// Returns the bits set in `next` but not in `prev`, and the ones set in `prev` but not in
+/// `next`, e.g. to update a mask kept on a device with the differences only.
+///
+/// Masks of different lengths are compared as if the shorter one was padded with zeros.
+pub fn delta(prev: &[u32], next: &[u32]) -> (Vec<u32>, Vec<u32>) {
+    let len = prev.len().max(next.len());
+    let word = |mask: &[u32], i: usize| mask.get(i).copied().unwrap_or(0);
+    (0..len)
+        .map(|i| {
+            let (prev, next) = (word(prev, i), word(next, i));
+            (next & !prev, prev & !next)
+        })
+        .unzip()
+}
+
+/// Returns an iterator over the token ids of the bits set in the words, in increasing order.
+pub fn set_bits<I: IntoIterator<Item = u32>>(words: I) -> SetBits<I::IntoIter> {
+    SetBits {
+        words: words.into_iter(),
+        word: 0,
+        base: 0,
+        next_base: 0,
+    }
+}
+
+/// Iterator over the token ids of a mask, see [`set_bits`].
+#[derive(Clone, Debug)]
+pub struct SetBits<I> {
+    words: I,
+    /// Bits of the current word, which are not visited yet.
+    word: u32,
+    /// Token id of the first bit of the current word.
+    base: u32,
+    next_base: u32,
+}
+
+impl<I: Iterator<Item = u32>> Iterator for SetBits<I> {
+    type Item = TokenId;
+
+    fn next(&mut self) -> Option<TokenId> {
+        while self.word == 0 {
+            self.word = self.words.next()?;
+            self.base = self.next_base;
+            self.next_base = self.next_base.wrapping_add(32);
+        }
+        let bit = self.word.trailing_zeros();
+        // Clears the lowest set bit.
+        self.word &= self.word - 1;
+        Some(TokenId::new(self.base + bit))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (_, words) = self.words.size_hint();
+        let pending = self.word.count_ones() as usize;
+        (pending, words.map(|words| pending + words * 32))
+    }
+}
+
+impl<I: FusedIterator<Item = u32>> FusedIterator for SetBits<I> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_and_iterate_bits() {
+        assert_eq!(mask_len(0), 0);
+        assert_eq!(mask_len(32), 1);
+        assert_eq!(mask_len(33), 2);
+
+        let tokens = [0, 5, 31, 32, 63, 64, 95, 200].map(TokenId::new);
+        let mut mask = vec![0; mask_len(96)];
+        set_tokens(&mut mask, &tokens);
+        assert_eq!(mask, vec![1 | 1 << 5 | 1 << 31, 1 | 1 << 31, 1 | 1 << 31]);
+        assert_eq!(
+            set_bits(mask.iter().copied()).collect::<Vec<_>>(),
+            &tokens[..7]
+        );
+
+        let mut bits = set_bits([0, u32::MAX, 0]);
+        assert_eq!(bits.size_hint(), (0, Some(96)));
+        assert_eq!(bits.next(), Some(TokenId::new(32)));
+        assert_eq!(bits.size_hint(), (31, Some(31 + 32)));
+        assert_eq!(bits.by_ref().count(), 31);
+        assert_eq!(bits.next(), None);
+
+        assert_eq!(set_bits(Vec::new()).next(), None);
+    }
+
+    #[test]
+    fn delta_of_masks() {
+        let (added, removed) = delta(&[0b0110, 1], &[0b1100]);
+        assert_eq!(added, vec![0b1000, 0]);
+        assert_eq!(removed, vec![0b0010, 1]);
+        assert_eq!(delta(&[7], &[7]), (vec![0], vec![0]));
+    }
+}
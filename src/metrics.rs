@@ -0,0 +1,247 @@
+//! Metrics of structured generation, reported to a recorder of the serving system, `metrics`
+//! feature.
+//!
+//! The library reports counters and histograms to the recorder set by [`set_recorder`], if
+//! any, so that they could be wired into an observability stack, e.g. with an adapter to the
+//! metrics crate of the serving system. [`PrometheusRecorder`] keeps them in memory and
+//! renders them in the Prometheus text format:
+//!
+//! ```rust
+//! # use outlines_core::Error;
+//! use std::sync::Arc;
+//!
+//! use outlines_core::metrics::{self, PrometheusRecorder};
+//! use outlines_core::prelude::*;
+//!
+//! # fn main() -> Result<(), Error> {
+//! let recorder = Arc::new(PrometheusRecorder::new());
+//! metrics::set_recorder(recorder.clone());
+//!
+//! let mut vocabulary = Vocabulary::new(3);
+//! vocabulary.try_insert("1", 1)?;
+//! Index::new("1+", &vocabulary)?;
+//!
+//! assert!(recorder.counter(metrics::INDEXES_BUILT) >= 1);
+//! println!("{}", recorder.render());
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Instant;
+
+use crate::Result;
+
+/// Counter of the indexes built from a regular expression.
+pub const INDEXES_BUILT: &str = "outlines_core_indexes_built_total";
+/// Counter of the failed or cancelled index constructions.
+pub const INDEX_BUILD_ERRORS: &str = "outlines_core_index_build_errors_total";
+/// Histogram of the durations of index constructions, in seconds.
+pub const INDEX_BUILD_SECONDS: &str = "outlines_core_index_build_duration_seconds";
+/// Counter of the indexes found in an [`IndexRegistry`](crate::registry::IndexRegistry).
+pub const REGISTRY_HITS: &str = "outlines_core_registry_hits_total";
+/// Counter of the indexes missing from an [`IndexRegistry`](crate::registry::IndexRegistry).
+pub const REGISTRY_MISSES: &str = "outlines_core_registry_misses_total";
+/// Counter of the masks of allowed tokens served by guides.
+pub const MASKS_SERVED: &str = "outlines_core_masks_served_total";
+/// Counter of the tokens rejected by guides.
+pub const REJECTED_TOKENS: &str = "outlines_core_rejected_tokens_total";
+
+/// Upper bounds of the buckets of [`PrometheusRecorder`] histograms.
+const BUCKETS: [f64; 8] = [0.001, 0.005, 0.01, 0.05, 0.1, 0.5, 1.0, 10.0];
+
+/// Receives the metrics reported by the library.
+///
+/// Metrics are reported from any thread, often on hot paths, e.g. on each mask, so
+/// recording should be cheap.
+pub trait MetricsRecorder: Send + Sync {
+    /// Adds `value` to the counter.
+    fn increment_counter(&self, name: &'static str, value: u64);
+
+    /// Records an observation of the histogram.
+    fn record_histogram(&self, name: &'static str, value: f64);
+}
+
+static RECORDER: RwLock<Option<Arc<dyn MetricsRecorder>>> = RwLock::new(None);
+
+/// Sets the recorder of the metrics of the process, replacing the previous one.
+pub fn set_recorder(recorder: Arc<dyn MetricsRecorder>) {
+    *RECORDER.write().unwrap_or_else(|e| e.into_inner()) = Some(recorder);
+}
+
+/// Stops reporting metrics.
+pub fn clear_recorder() {
+    *RECORDER.write().unwrap_or_else(|e| e.into_inner()) = None;
+}
+
+fn with_recorder(f: impl FnOnce(&dyn MetricsRecorder)) {
+    let recorder = RECORDER.read().unwrap_or_else(|e| e.into_inner());
+    if let Some(recorder) = recorder.as_deref() {
+        f(recorder);
+    }
+}
+
+/// Increments the counter by one.
+pub(crate) fn increment(name: &'static str) {
+    with_recorder(|recorder| recorder.increment_counter(name, 1));
+}
+
+/// Records an observation of the histogram.
+pub(crate) fn observe(name: &'static str, value: f64) {
+    with_recorder(|recorder| recorder.record_histogram(name, value));
+}
+
+/// Records the outcome of an index construction started at `start`.
+pub(crate) fn record_build<T>(start: Instant, result: &Result<T>) {
+    if result.is_ok() {
+        increment(INDEXES_BUILT);
+        observe(INDEX_BUILD_SECONDS, start.elapsed().as_secs_f64());
+    } else {
+        increment(INDEX_BUILD_ERRORS);
+    }
+}
+
+/// Recorder keeping the metrics in memory, to be rendered in the Prometheus text format,
+/// e.g. by the metrics endpoint of a server.
+#[derive(Debug, Default)]
+pub struct PrometheusRecorder {
+    metrics: Mutex<Metrics>,
+}
+
+#[derive(Debug, Default)]
+struct Metrics {
+    counters: BTreeMap<&'static str, u64>,
+    histograms: BTreeMap<&'static str, Histogram>,
+}
+
+#[derive(Debug, Default)]
+struct Histogram {
+    /// Number of observations of each bucket, not cumulated.
+    buckets: [u64; BUCKETS.len()],
+    count: u64,
+    sum: f64,
+}
+
+impl PrometheusRecorder {
+    /// Creates a recorder without metrics.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the value of the counter, zero if it's never incremented.
+    pub fn counter(&self, name: &str) -> u64 {
+        self.lock().counters.get(name).copied().unwrap_or(0)
+    }
+
+    /// Returns the number of observations of the histogram.
+    pub fn histogram_count(&self, name: &str) -> u64 {
+        self.lock().histograms.get(name).map_or(0, |h| h.count)
+    }
+
+    /// Renders the metrics in the Prometheus text format.
+    pub fn render(&self) -> String {
+        let metrics = self.lock();
+        let mut text = String::new();
+        for (name, value) in &metrics.counters {
+            writeln!(text, "# TYPE {name} counter").unwrap();
+            writeln!(text, "{name} {value}").unwrap();
+        }
+        for (name, histogram) in &metrics.histograms {
+            writeln!(text, "# TYPE {name} histogram").unwrap();
+            let mut cumulated = 0;
+            for (bound, count) in BUCKETS.iter().zip(histogram.buckets) {
+                cumulated += count;
+                writeln!(text, "{name}_bucket{{le=\"{bound}\"}} {cumulated}").unwrap();
+            }
+            writeln!(text, "{name}_bucket{{le=\"+Inf\"}} {}", histogram.count).unwrap();
+            writeln!(text, "{name}_sum {}", histogram.sum).unwrap();
+            writeln!(text, "{name}_count {}", histogram.count).unwrap();
+        }
+        text
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Metrics> {
+        self.metrics.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl MetricsRecorder for PrometheusRecorder {
+    fn increment_counter(&self, name: &'static str, value: u64) {
+        *self.lock().counters.entry(name).or_default() += value;
+    }
+
+    fn record_histogram(&self, name: &'static str, value: f64) {
+        let mut metrics = self.lock();
+        let histogram = metrics.histograms.entry(name).or_default();
+        if let Some(bucket) = BUCKETS.iter().position(|bound| value <= *bound) {
+            histogram.buckets[bucket] += 1;
+        }
+        histogram.count += 1;
+        histogram.sum += value;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::guide::Guide;
+    use crate::index::Index;
+    use crate::primitives::TokenId;
+    use crate::registry::IndexRegistry;
+    use crate::vocabulary::Vocabulary;
+
+    #[test]
+    fn prometheus_recorder() {
+        let recorder = PrometheusRecorder::new();
+        recorder.increment_counter(MASKS_SERVED, 2);
+        recorder.record_histogram(INDEX_BUILD_SECONDS, 0.02);
+        recorder.record_histogram(INDEX_BUILD_SECONDS, 20.0);
+        assert_eq!(recorder.counter(MASKS_SERVED), 2);
+        assert_eq!(recorder.counter(REJECTED_TOKENS), 0);
+        assert_eq!(recorder.histogram_count(INDEX_BUILD_SECONDS), 2);
+
+        let text = recorder.render();
+        assert!(text.contains("# TYPE outlines_core_masks_served_total counter\n"));
+        assert!(text.contains("outlines_core_masks_served_total 2\n"));
+        assert!(text.contains("outlines_core_index_build_duration_seconds_bucket{le=\"0.01\"} 0\n"));
+        assert!(text.contains("outlines_core_index_build_duration_seconds_bucket{le=\"0.05\"} 1\n"));
+        assert!(text.contains("outlines_core_index_build_duration_seconds_bucket{le=\"+Inf\"} 2\n"));
+        assert!(text.contains("outlines_core_index_build_duration_seconds_count 2\n"));
+    }
+
+    #[test]
+    fn reported_metrics() {
+        let recorder = Arc::new(PrometheusRecorder::new());
+        set_recorder(recorder.clone());
+
+        let mut vocabulary = Vocabulary::new(3);
+        vocabulary.try_insert("1", 1).expect("Insert failed");
+        let index = Arc::new(Index::new("1+", &vocabulary).expect("Index failed"));
+        assert!(Index::new("(", &vocabulary).is_err());
+
+        let registry = IndexRegistry::new(usize::MAX);
+        registry.insert("ones", index.clone());
+        assert!(registry.get("ones").is_some());
+        assert!(registry.get("twos").is_none());
+
+        let mut guide = Guide::new(index, 1);
+        guide.allowed_tokens().expect("No allowed tokens");
+        assert!(guide.advance(TokenId::new(2)).is_err());
+
+        // Other tests may report metrics concurrently.
+        for name in [
+            INDEXES_BUILT,
+            INDEX_BUILD_ERRORS,
+            REGISTRY_HITS,
+            REGISTRY_MISSES,
+            MASKS_SERVED,
+            REJECTED_TOKENS,
+        ] {
+            assert!(recorder.counter(name) >= 1, "{name} isn't reported");
+        }
+        assert!(recorder.histogram_count(INDEX_BUILD_SECONDS) >= 1);
+        clear_recorder();
+    }
+}
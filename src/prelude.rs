@@ -3,6 +3,7 @@
 #[cfg(feature = "hugginface-hub")]
 pub use tokenizers::FromPretrainedParameters;
 
+pub use super::guide::Guide;
 pub use super::index::Index;
 pub use super::json_schema;
 pub use super::primitives::{StateId, Token, TokenId};
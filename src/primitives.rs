@@ -1,10 +1,109 @@
 //! Defines fundamental types used throughout the crate.
+//!
+//! [`TokenId`] and [`StateId`] are distinct types, so that a state can't be passed in place of
+//! a token by mistake. They're converted from and to `u32` explicitly, and are serialized as
+//! `u32`, so that serialized indexes and vocabularies are unchanged:
+//!
+//! ```rust
+//! use outlines_core::primitives::{StateId, TokenId};
+//!
+//! let token_id = TokenId::new(42);
+//! assert_eq!(token_id.as_u32(), 42);
+//! assert_eq!(u32::from(StateId::from(7)), 7);
+//! ```
+
+use bincode::{Decode, Encode};
+use serde::{Deserialize, Serialize};
 
 /// Token content.
 pub type Token = Vec<u8>;
 
-/// Token identifier.
-pub type TokenId = u32;
+macro_rules! id_type {
+    ($(#[$meta:meta])* $name:ident) => {
+        $(#[$meta])*
+        #[derive(
+            Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+            Encode, Decode,
+        )]
+        #[cfg_attr(
+            feature = "python-bindings",
+            derive(pyo3::FromPyObject, pyo3::IntoPyObject, pyo3::IntoPyObjectRef)
+        )]
+        #[serde(transparent)]
+        #[repr(transparent)]
+        pub struct $name(u32);
+
+        impl $name {
+            /// Wraps the raw identifier.
+            pub const fn new(id: u32) -> Self {
+                Self(id)
+            }
+
+            /// Returns the raw identifier.
+            pub const fn as_u32(self) -> u32 {
+                self.0
+            }
+
+            /// Returns the raw identifier as an index, e.g. of a mask.
+            pub const fn as_usize(self) -> usize {
+                self.0 as usize
+            }
+        }
+
+        impl From<u32> for $name {
+            fn from(id: u32) -> Self {
+                Self(id)
+            }
+        }
+
+        impl From<$name> for u32 {
+            fn from(id: $name) -> Self {
+                id.0
+            }
+        }
+
+        // Compared to raw identifiers, e.g. to the expected tokens of a test.
+        impl PartialEq<u32> for $name {
+            fn eq(&self, other: &u32) -> bool {
+                self.0 == *other
+            }
+        }
+
+        impl std::fmt::Display for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                self.0.fmt(f)
+            }
+        }
+
+        // Printed as the raw identifier, to keep the output of collections readable.
+        impl std::fmt::Debug for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                self.0.fmt(f)
+            }
+        }
+    };
+}
+
+id_type!(
+    /// Token identifier.
+    TokenId
+);
+
+id_type!(
+    /// State id.
+    StateId
+);
+
+/// Bare `u32` aliases of the identifiers, for code written before [`TokenId`] and [`StateId`]
+/// were distinct types, `compat-ids` feature.
+#[cfg(feature = "compat-ids")]
+pub mod compat {
+    /// Token identifier.
+    pub type TokenId = u32;
+
+    /// State id.
+    pub type StateId = u32;
+}
 
-/// State id.
-pub type StateId = u32;
+/// Number of entries listed by the summarized `Display` of large objects, e.g. of an index.
+pub(crate) const SUMMARY_ENTRIES: usize = 8;
@@ -0,0 +1,517 @@
+//! Conversion of protobuf messages into JSON schemas of their [proto3 JSON mapping].
+//!
+//! A compiled descriptor set, e.g. written by `protoc --include_imports --descriptor_set_out`,
+//! describes the messages, which are converted into a JSON schema with a `$defs` entry per
+//! message and then compiled as any other schema:
+//!
+//! ```rust,no_run
+//! # use outlines_core::Error;
+//! use outlines_core::json_schema::SchemaOptions;
+//! use outlines_core::protobuf;
+//!
+//! # fn main() -> Result<(), Error> {
+//! let descriptor_set = std::fs::read("shop.pb").expect("Read failed");
+//! let regex = protobuf::regex_from_descriptor_set(
+//!     &descriptor_set,
+//!     "shop.Order",
+//!     &SchemaOptions::default(),
+//! )?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The mapping follows the output of protobuf printers:
+//! - fields are named by their `json_name`, i.e. in lowerCamelCase by default;
+//! - enums are written by the names of their values;
+//! - 64-bit integers are decimal strings, bytes are base64 strings;
+//! - at most one field of a `oneof` is present, every variant becoming an `anyOf` branch;
+//! - maps are objects, well-known types have their dedicated representations, e.g.
+//!   `google.protobuf.Timestamp` is an RFC 3339 string.
+//!
+//! Note, that a message with several `oneof`s has as many variants as the product of their sizes.
+//!
+//! [proto3 JSON mapping]: https://protobuf.dev/programming-guides/json/
+
+use prost::Message;
+use prost_types::field_descriptor_proto::{Label, Type};
+use prost_types::{DescriptorProto, EnumDescriptorProto, FieldDescriptorProto};
+use rustc_hash::FxHashMap as HashMap;
+use serde_json::{json, Map, Value};
+
+pub use prost_types::FileDescriptorSet;
+
+use crate::json_schema::{self, ranges, SchemaOptions};
+use crate::{Error, Result};
+
+/// Pattern of base64 strings of `bytes` fields.
+static BYTES: &str = r"^((?:[A-Za-z0-9+/]{4})*(?:[A-Za-z0-9+/]{2}==|[A-Za-z0-9+/]{3}=)?)$";
+
+/// Pattern of `google.protobuf.Duration`, seconds with up to nanoseconds precision.
+static DURATION: &str = r"^(-?(0|[1-9][0-9]*)(\.[0-9]{1,9})?s)$";
+
+/// Converts the message of an encoded `FileDescriptorSet` into a JSON schema.
+pub fn schema_from_descriptor_set(descriptor_set: &[u8], message: &str) -> Result<Value> {
+    let descriptor_set = FileDescriptorSet::decode(descriptor_set)?;
+    schema_from_file_descriptor_set(&descriptor_set, message)
+}
+
+/// Converts the message of the descriptor set into a JSON schema, `message` being its fully
+/// qualified name, e.g. `shop.Order`.
+pub fn schema_from_file_descriptor_set(
+    descriptor_set: &FileDescriptorSet,
+    message: &str,
+) -> Result<Value> {
+    let mut converter = Converter::new(descriptor_set);
+    let root = converter.message_schema(message.trim_start_matches('.'))?;
+    if converter.defs.is_empty() {
+        return Ok(root);
+    }
+    let mut schema = Map::new();
+    schema.insert("$defs".to_string(), Value::Object(converter.defs));
+    if let Value::Object(root) = root {
+        schema.extend(root);
+    }
+    Ok(Value::Object(schema))
+}
+
+/// Generates a regular expression from the message of an encoded `FileDescriptorSet`.
+pub fn regex_from_descriptor_set(
+    descriptor_set: &[u8],
+    message: &str,
+    options: &SchemaOptions,
+) -> Result<String> {
+    let schema = schema_from_descriptor_set(descriptor_set, message)?;
+    json_schema::regex_from_value_with_options(&schema, options)
+}
+
+struct Converter<'a> {
+    messages: HashMap<String, &'a DescriptorProto>,
+    enums: HashMap<String, &'a EnumDescriptorProto>,
+    /// Schemas of the converted messages, by their fully qualified names.
+    defs: Map<String, Value>,
+}
+
+impl<'a> Converter<'a> {
+    fn new(descriptor_set: &'a FileDescriptorSet) -> Self {
+        let mut converter = Self {
+            messages: HashMap::default(),
+            enums: HashMap::default(),
+            defs: Map::new(),
+        };
+        for file in &descriptor_set.file {
+            let package = file.package();
+            for message in &file.message_type {
+                converter.register_message(package, message);
+            }
+            for enumeration in &file.enum_type {
+                let name = qualified(package, enumeration.name());
+                converter.enums.insert(name, enumeration);
+            }
+        }
+        converter
+    }
+
+    fn register_message(&mut self, scope: &str, message: &'a DescriptorProto) {
+        let name = qualified(scope, message.name());
+        for nested in &message.nested_type {
+            self.register_message(&name, nested);
+        }
+        for enumeration in &message.enum_type {
+            let enum_name = qualified(&name, enumeration.name());
+            self.enums.insert(enum_name, enumeration);
+        }
+        self.messages.insert(name, message);
+    }
+
+    /// Schema of a message field, a reference to its definition for non well-known types.
+    fn message_schema(&mut self, name: &str) -> Result<Value> {
+        if let Some(schema) = well_known_schema(name) {
+            return Ok(schema);
+        }
+        let reference = json!({"$ref": format!("#/$defs/{name}")});
+        if self.defs.contains_key(name) {
+            return Ok(reference);
+        }
+        let message = *self
+            .messages
+            .get(name)
+            .ok_or_else(|| Error::ProtobufTypeNotFound(name.to_string()))?;
+        // Recursive messages refer to the definition under construction.
+        self.defs.insert(name.to_string(), Value::Null);
+        let schema = self.object_schema(message)?;
+        self.defs.insert(name.to_string(), schema);
+        Ok(reference)
+    }
+
+    fn object_schema(&mut self, message: &DescriptorProto) -> Result<Value> {
+        let mut properties = Vec::with_capacity(message.field.len());
+        let mut oneofs: Vec<Vec<usize>> = vec![Vec::new(); message.oneof_decl.len()];
+        let mut variants = vec![Vec::new()];
+        for (position, field) in message.field.iter().enumerate() {
+            properties.push((json_name(field), self.field_schema(field)?));
+            // Optional fields of proto3 belong to synthetic oneofs of their own.
+            match field.oneof_index {
+                Some(index) if !field.proto3_optional() => match oneofs.get_mut(index as usize) {
+                    Some(oneof) => oneof.push(position),
+                    None => variants[0].push(position),
+                },
+                _ => variants[0].push(position),
+            }
+        }
+        // Each variant has at most one field of every `oneof`.
+        for oneof in oneofs.iter().filter(|oneof| !oneof.is_empty()) {
+            let mut with_oneof = Vec::with_capacity(variants.len() * (oneof.len() + 1));
+            for variant in variants {
+                for &position in oneof {
+                    let mut with_field = variant.clone();
+                    with_field.push(position);
+                    with_oneof.push(with_field);
+                }
+                with_oneof.push(variant);
+            }
+            variants = with_oneof;
+        }
+
+        let mut objects: Vec<Value> = variants
+            .into_iter()
+            .map(|mut variant| {
+                variant.sort_unstable();
+                let properties: Map<String, Value> = variant
+                    .into_iter()
+                    .map(|position| properties[position].clone())
+                    .collect();
+                json!({"type": "object", "properties": properties})
+            })
+            .collect();
+        Ok(match objects.len() {
+            1 => objects.remove(0),
+            _ => json!({"anyOf": objects}),
+        })
+    }
+
+    fn field_schema(&mut self, field: &FieldDescriptorProto) -> Result<Value> {
+        let type_name = field.type_name().trim_start_matches('.');
+        let value = match field.r#type() {
+            Type::Message | Type::Group => {
+                if let Some(entry) = self.map_entry(type_name) {
+                    return self.map_schema(entry);
+                }
+                self.message_schema(type_name)?
+            }
+            Type::Enum => self.enum_schema(type_name)?,
+            scalar => scalar_schema(scalar),
+        };
+        Ok(match field.label() {
+            Label::Repeated => json!({"type": "array", "items": value}),
+            _ => value,
+        })
+    }
+
+    fn map_entry(&self, name: &str) -> Option<&'a DescriptorProto> {
+        self.messages
+            .get(name)
+            .copied()
+            .filter(|message| message.options.as_ref().is_some_and(|o| o.map_entry()))
+    }
+
+    /// Schema of a map, which keys are always strings in JSON.
+    fn map_schema(&mut self, entry: &DescriptorProto) -> Result<Value> {
+        let value = match entry.field.iter().find(|field| field.number() == 2) {
+            Some(field) => self.field_schema(field)?,
+            None => json!({}),
+        };
+        Ok(json!({"type": "object", "additionalProperties": value}))
+    }
+
+    fn enum_schema(&self, name: &str) -> Result<Value> {
+        if name == "google.protobuf.NullValue" {
+            return Ok(json!({"type": "null"}));
+        }
+        let enumeration = self
+            .enums
+            .get(name)
+            .ok_or_else(|| Error::ProtobufTypeNotFound(name.to_string()))?;
+        let values: Vec<&str> = enumeration.value.iter().map(|value| value.name()).collect();
+        Ok(json!({"enum": values}))
+    }
+}
+
+fn qualified(scope: &str, name: &str) -> String {
+    if scope.is_empty() {
+        name.to_string()
+    } else {
+        format!("{scope}.{name}")
+    }
+}
+
+/// Name of the field in JSON, lowerCamelCase of its name, unless `json_name` is given.
+fn json_name(field: &FieldDescriptorProto) -> String {
+    if let Some(json_name) = &field.json_name {
+        return json_name.clone();
+    }
+    let mut name = String::with_capacity(field.name().len());
+    let mut capitalize = false;
+    for c in field.name().chars() {
+        match c {
+            '_' => capitalize = true,
+            c if capitalize => {
+                name.extend(c.to_uppercase());
+                capitalize = false;
+            }
+            c => name.push(c),
+        }
+    }
+    name
+}
+
+fn scalar_schema(scalar: Type) -> Value {
+    match scalar {
+        Type::Double | Type::Float => json!({"type": "number"}),
+        Type::Int32 | Type::Sint32 | Type::Sfixed32 => {
+            json!({"type": "integer", "format": "int32"})
+        }
+        Type::Uint32 | Type::Fixed32 => json!({"type": "integer", "format": "uint32"}),
+        Type::Int64 | Type::Sint64 | Type::Sfixed64 => {
+            let range = ranges::integer_range(i64::MIN, i64::MAX);
+            json!({"type": "string", "pattern": format!("^{range}$")})
+        }
+        Type::Uint64 | Type::Fixed64 => {
+            let range = ranges::unsigned_range(0, u64::MAX);
+            json!({"type": "string", "pattern": format!("^({range})$")})
+        }
+        Type::Bool => json!({"type": "boolean"}),
+        Type::Bytes => json!({"type": "string", "pattern": BYTES}),
+        Type::String | Type::Message | Type::Group | Type::Enum => json!({"type": "string"}),
+    }
+}
+
+/// Schemas of the well-known types, which have a dedicated JSON representation.
+fn well_known_schema(name: &str) -> Option<Value> {
+    let schema = match name.strip_prefix("google.protobuf.")? {
+        "Timestamp" => json!({"type": "string", "format": "date-time"}),
+        "Duration" => json!({"type": "string", "pattern": DURATION}),
+        "FieldMask" => json!({"type": "string"}),
+        "Struct" => json!({"type": "object"}),
+        "ListValue" => json!({"type": "array"}),
+        "Value" => json!({}),
+        "Empty" => json!({"type": "object", "additionalProperties": false}),
+        "DoubleValue" | "FloatValue" => scalar_schema(Type::Double),
+        "Int64Value" => scalar_schema(Type::Int64),
+        "UInt64Value" => scalar_schema(Type::Uint64),
+        "Int32Value" => scalar_schema(Type::Int32),
+        "UInt32Value" => scalar_schema(Type::Uint32),
+        "BoolValue" => scalar_schema(Type::Bool),
+        "StringValue" => scalar_schema(Type::String),
+        "BytesValue" => scalar_schema(Type::Bytes),
+        _ => return None,
+    };
+    Some(schema)
+}
+
+#[cfg(test)]
+mod tests {
+    use prost_types::{
+        EnumValueDescriptorProto, FileDescriptorProto, MessageOptions, OneofDescriptorProto,
+    };
+    use regex::Regex;
+
+    use super::*;
+
+    fn field(name: &str, number: i32, kind: Type, type_name: Option<&str>) -> FieldDescriptorProto {
+        FieldDescriptorProto {
+            name: Some(name.to_string()),
+            number: Some(number),
+            label: Some(Label::Optional as i32),
+            r#type: Some(kind as i32),
+            type_name: type_name.map(str::to_string),
+            ..Default::default()
+        }
+    }
+
+    fn repeated(field: FieldDescriptorProto) -> FieldDescriptorProto {
+        FieldDescriptorProto {
+            label: Some(Label::Repeated as i32),
+            ..field
+        }
+    }
+
+    fn in_oneof(field: FieldDescriptorProto, index: i32) -> FieldDescriptorProto {
+        FieldDescriptorProto {
+            oneof_index: Some(index),
+            ..field
+        }
+    }
+
+    fn message(name: &str, field: Vec<FieldDescriptorProto>) -> DescriptorProto {
+        DescriptorProto {
+            name: Some(name.to_string()),
+            field,
+            ..Default::default()
+        }
+    }
+
+    fn shop() -> FileDescriptorSet {
+        let counts_entry = DescriptorProto {
+            options: Some(MessageOptions {
+                map_entry: Some(true),
+                ..Default::default()
+            }),
+            ..message(
+                "CountsEntry",
+                vec![
+                    field("key", 1, Type::String, None),
+                    field("value", 2, Type::Int32, None),
+                ],
+            )
+        };
+        let order = DescriptorProto {
+            nested_type: vec![counts_entry],
+            oneof_decl: vec![OneofDescriptorProto {
+                name: Some("payment".to_string()),
+                ..Default::default()
+            }],
+            ..message(
+                "Order",
+                vec![
+                    field("order_id", 1, Type::String, None),
+                    field("total_cents", 2, Type::Int64, None),
+                    repeated(field("items", 3, Type::Message, Some(".shop.Item"))),
+                    repeated(field(
+                        "counts",
+                        4,
+                        Type::Message,
+                        Some(".shop.Order.CountsEntry"),
+                    )),
+                    field("status", 5, Type::Enum, Some(".shop.Status")),
+                    in_oneof(field("card", 6, Type::String, None), 0),
+                    in_oneof(field("voucher", 7, Type::String, None), 0),
+                    field(
+                        "created_at",
+                        8,
+                        Type::Message,
+                        Some(".google.protobuf.Timestamp"),
+                    ),
+                ],
+            )
+        };
+        let item = message(
+            "Item",
+            vec![
+                field("sku", 1, Type::String, None),
+                field("quantity", 2, Type::Uint32, None),
+                field("parent", 3, Type::Message, Some(".shop.Item")),
+            ],
+        );
+        let status = EnumDescriptorProto {
+            name: Some("Status".to_string()),
+            value: ["STATUS_UNSPECIFIED", "PAID"]
+                .into_iter()
+                .enumerate()
+                .map(|(number, name)| EnumValueDescriptorProto {
+                    name: Some(name.to_string()),
+                    number: Some(number as i32),
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        };
+        FileDescriptorSet {
+            file: vec![FileDescriptorProto {
+                name: Some("shop.proto".to_string()),
+                package: Some("shop".to_string()),
+                message_type: vec![order, item],
+                enum_type: vec![status],
+                syntax: Some("proto3".to_string()),
+                ..Default::default()
+            }],
+        }
+    }
+
+    #[test]
+    fn order_schema() {
+        let schema = schema_from_file_descriptor_set(&shop(), ".shop.Order").expect("No schema");
+        let defs = schema["$defs"].as_object().expect("No $defs");
+        assert_eq!(
+            defs.keys().collect::<Vec<_>>(),
+            vec!["shop.Order", "shop.Item"]
+        );
+        assert_eq!(schema["$ref"], "#/$defs/shop.Order");
+        assert_eq!(
+            defs["shop.Order"]["anyOf"].as_array().map(Vec::len),
+            Some(3)
+        );
+        assert_eq!(
+            defs["shop.Item"]["properties"]["parent"],
+            json!({"$ref": "#/$defs/shop.Item"})
+        );
+
+        let result = schema_from_file_descriptor_set(&shop(), "shop.Missing");
+        assert!(matches!(result, Err(Error::ProtobufTypeNotFound(name)) if name == "shop.Missing"));
+    }
+
+    #[test]
+    fn order_regex() {
+        let descriptor_set = shop().encode_to_vec();
+        let options = SchemaOptions {
+            whitespace_pattern: Some(String::new()),
+            ..Default::default()
+        };
+        let regex = regex_from_descriptor_set(&descriptor_set, "shop.Order", &options)
+            .expect("To regex failed");
+        let re = Regex::new(&format!("^({regex})$")).expect("Regex failed");
+        for value in [
+            r#"{}"#,
+            r#"{"orderId":"a1","totalCents":"-9223372036854775808","status":"PAID"}"#,
+            r#"{"items":[{"sku":"x","quantity":4294967295}],"counts":{"x":-1},"card":"c"}"#,
+            r#"{"voucher":"v","createdAt":"2024-05-01T10:00:00Z"}"#,
+        ] {
+            assert!(re.is_match(value), "{value}");
+        }
+        for value in [
+            r#"{"order_id":"a1"}"#,
+            r#"{"totalCents":12}"#,
+            r#"{"totalCents":"9223372036854775808"}"#,
+            r#"{"items":[{"quantity":4294967296}]}"#,
+            r#"{"status":1}"#,
+            r#"{"card":"c","voucher":"v"}"#,
+        ] {
+            assert!(!re.is_match(value), "{value}");
+        }
+
+        let result = schema_from_descriptor_set(b"\xff", "shop.Order");
+        assert!(matches!(result, Err(Error::ProtobufDecodeError(_))));
+    }
+
+    #[test]
+    fn scalars_and_well_known_types() {
+        assert_eq!(
+            json_name(&field("total_cents_2", 1, Type::Int64, None)),
+            "totalCents2"
+        );
+        let file = FileDescriptorSet::default();
+        let schema = schema_from_file_descriptor_set(&file, "google.protobuf.Int32Value")
+            .expect("No schema");
+        assert_eq!(schema, json!({"type": "integer", "format": "int32"}));
+
+        for (kind, a_match, not_a_match) in [
+            (Type::Bytes, r#""aGk=""#, r#""aGk""#),
+            (
+                Type::Uint64,
+                r#""18446744073709551615""#,
+                r#""18446744073709551616""#,
+            ),
+        ] {
+            let regex = json_schema::regex_from_value(&scalar_schema(kind), None, None)
+                .expect("To regex failed");
+            let re = Regex::new(&format!("^({regex})$")).expect("Regex failed");
+            assert!(re.is_match(a_match), "{a_match}");
+            assert!(!re.is_match(not_a_match), "{not_a_match}");
+        }
+
+        let duration = well_known_schema("google.protobuf.Duration").expect("No schema");
+        let regex = json_schema::regex_from_value(&duration, None, None).expect("To regex failed");
+        let re = Regex::new(&format!("^({regex})$")).expect("Regex failed");
+        assert!(re.is_match(r#""-1.5s""#));
+        assert!(!re.is_match(r#""1.5""#));
+    }
+}
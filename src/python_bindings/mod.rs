@@ -1,20 +1,31 @@
 //! Provides tools and interfaces to integrate the crate's functionality with Python.
 
-use std::collections::VecDeque;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::ops::ControlFlow;
 use std::sync::Arc;
 
+use bincode::error::{DecodeError, EncodeError};
 use bincode::{config, Decode, Encode};
 use pyo3::exceptions::PyValueError;
 use pyo3::prelude::*;
 use pyo3::types::{PyAny, PyDict};
 use pyo3::wrap_pyfunction;
 use rustc_hash::{FxHashMap as HashMap, FxHashSet as HashSet};
+use tokenizers::utils::parallelism;
 #[cfg(feature = "hugginface-hub")]
 use tokenizers::FromPretrainedParameters;
 
-use crate::index::Index;
+use crate::bias::TransitionBiases;
+use crate::fields::FieldPaths;
+use crate::guide::{FinishPolicy, Guide, GuideSnapshot, Proposal};
+use crate::index::{ByteClasses, Index, IndexBuilder, ResourceLimits};
 use crate::json_schema;
+use crate::mask;
 use crate::prelude::*;
+use crate::text::Contains;
+use crate::vocabulary::VocabDelta;
+#[cfg(feature = "hugginface-hub")]
+use crate::vocabulary::{PretrainedOptions, ProcessingRule, SpecialToken, TestVocabulary};
 
 macro_rules! type_name {
     ($obj:expr) => {
@@ -23,116 +34,325 @@ macro_rules! type_name {
     };
 }
 
-/// Guide object based on Index.
-#[pyclass(name = "Guide", module = "outlines_core")]
-#[derive(Clone, Debug, PartialEq, Encode, Decode)]
-pub struct PyGuide {
+/// Marker of the binary data of pickled objects, followed by the version of its format.
+///
+/// Objects pickled before the format was versioned start with a `bincode` varint instead,
+/// which never starts with this byte, and are decoded with their former layout.
+const PICKLE_MARKER: u8 = 0xFF;
+
+/// Version of the format of pickled objects.
+const PICKLE_FORMAT_VERSION: u8 = 1;
+
+/// Bytes allocated at most while unpickling an object, so that forged lengths fail instead of
+/// exhausting the memory.
+const PICKLE_DECODE_LIMIT: usize = 1 << 32;
+
+/// Encodes an object to be pickled, led by its format.
+fn to_pickle<T: Encode>(value: &T) -> Result<Vec<u8>, EncodeError> {
+    let mut binary_data = vec![PICKLE_MARKER, PICKLE_FORMAT_VERSION];
+    binary_data.extend(bincode::encode_to_vec(value, config::standard())?);
+    Ok(binary_data)
+}
+
+/// Decodes a pickled object, or its former layout `L` if it was pickled before the format was
+/// versioned.
+fn from_pickle<T, L>(
+    binary_data: &[u8],
+    legacy: impl FnOnce(L) -> Result<T, DecodeError>,
+) -> Result<T, DecodeError>
+where
+    T: Decode<()>,
+    L: Decode<()>,
+{
+    let config = config::standard().with_limit::<PICKLE_DECODE_LIMIT>();
+    match binary_data {
+        [PICKLE_MARKER, PICKLE_FORMAT_VERSION, encoded @ ..] => {
+            Ok(bincode::decode_from_slice(encoded, config)?.0)
+        }
+        [PICKLE_MARKER, version, ..] => Err(DecodeError::OtherString(format!(
+            "unsupported format version {version}, expected {PICKLE_FORMAT_VERSION}"
+        ))),
+        encoded => legacy(bincode::decode_from_slice(encoded, config)?.0),
+    }
+}
+
+/// Layout of the indexes pickled before the format was versioned.
+#[derive(Decode)]
+struct LegacyIndex {
+    initial_state: StateId,
+    final_states: HashSet<StateId>,
+    transitions: HashMap<StateId, HashMap<TokenId, StateId>>,
+    eos_token_id: TokenId,
+    vocab_size: usize,
+}
+
+impl From<LegacyIndex> for Index {
+    fn from(index: LegacyIndex) -> Self {
+        Index::from_parts(
+            index.initial_state,
+            index.final_states,
+            index.transitions,
+            index.eos_token_id,
+            Vec::new(),
+            index.vocab_size,
+            ByteClasses::singletons(),
+        )
+    }
+}
+
+/// Layout of the vocabularies pickled before the format was versioned.
+#[derive(Decode)]
+struct LegacyVocabulary {
+    eos_token_id: TokenId,
+    tokens: HashMap<Token, Vec<TokenId>>,
+}
+
+impl TryFrom<LegacyVocabulary> for Vocabulary {
+    type Error = DecodeError;
+
+    fn try_from(legacy: LegacyVocabulary) -> Result<Self, DecodeError> {
+        let mut vocabulary = Vocabulary::new(legacy.eos_token_id);
+        for (token, ids) in legacy.tokens {
+            for id in ids {
+                vocabulary
+                    .try_insert(token.clone(), id)
+                    .map_err(|e| DecodeError::OtherString(e.to_string()))?;
+            }
+        }
+        Ok(vocabulary)
+    }
+}
+
+/// Layout of the guides pickled before the format was versioned, whose rollback history isn't
+/// restored.
+#[derive(Decode)]
+struct LegacyGuide {
     state: StateId,
-    index: PyIndex,
+    index: LegacyIndex,
+    #[allow(dead_code)]
     state_cache: VecDeque<StateId>,
 }
 
+impl TryFrom<LegacyGuide> for Guide {
+    type Error = DecodeError;
+
+    fn try_from(legacy: LegacyGuide) -> Result<Self, DecodeError> {
+        Guide::from_state(Arc::new(legacy.index.into()), legacy.state, 32)
+            .map_err(|e| DecodeError::OtherString(e.to_string()))
+    }
+}
+
+/// Guide object based on Index.
+#[pyclass(name = "Guide", module = "outlines_core")]
+#[derive(Clone, Debug, PartialEq, Encode, Decode)]
+pub struct PyGuide(Guide);
+
 #[pymethods]
 impl PyGuide {
     /// Creates a Guide object based on Index.
+    ///
+    /// With `finish_on="accept"` the guide is finished as soon as the end-of-sequence token is
+    /// allowed, with `finish_on="eos"` only once it's consumed by `advance`.
+    ///
+    /// With `max_tokens`, only tokens which still allow to finish within that many tokens are
+    /// allowed, so that the generation is never truncated.
+    ///
+    /// With `max_violations`, up to that many tokens which aren't allowed are consumed by
+    /// `advance` without moving, for softly constrained decoding.
+    ///
+    /// `token_biases` maps token ids to biases, added in every state, and `shorter_bias` is
+    /// added to the tokens getting closer to a final state, see `get_biases`.
+    ///
+    /// With `record_events`, how each consumed token was picked is recorded, see
+    /// `get_token_events`.
+    ///
+    /// With `record_history`, the consumed tokens are recorded, see `get_text`.
+    ///
+    /// With `mask_cache`, the allowed tokens of up to that many recently visited states are
+    /// cached, for `get_tokens` and `write_mask_into`, unless `max_tokens` is set.
+    ///
+    /// With the `vocabulary` of the index, the JSON field generated in each state is named,
+    /// see `get_current_path`.
     #[new]
-    #[pyo3(signature = (index, max_rollback=32))]
-    fn __new__(index: PyIndex, max_rollback: usize) -> Self {
-        PyGuide {
-            state: index.get_initial_state(),
-            index,
-            state_cache: VecDeque::with_capacity(max_rollback),
-        }
+    #[pyo3(signature = (index, max_rollback=32, finish_on="accept", max_tokens=None, max_violations=0, token_biases=None, shorter_bias=0.0, record_events=false, record_history=false, mask_cache=0, vocabulary=None))]
+    #[allow(clippy::too_many_arguments)]
+    fn __new__(
+        py: Python<'_>,
+        index: PyIndex,
+        max_rollback: usize,
+        finish_on: &str,
+        max_tokens: Option<usize>,
+        max_violations: usize,
+        token_biases: Option<HashMap<TokenId, f32>>,
+        shorter_bias: f32,
+        record_events: bool,
+        record_history: bool,
+        mask_cache: usize,
+        vocabulary: Option<PyRef<'_, PyVocabulary>>,
+    ) -> PyResult<Self> {
+        let guide = Guide::new(index.0, max_rollback);
+        configure_guide(
+            py,
+            guide,
+            finish_on,
+            max_tokens,
+            max_violations,
+            token_biases,
+            shorter_bias,
+            record_events,
+            record_history,
+            mask_cache,
+            vocabulary.as_deref(),
+        )
+    }
+
+    /// Creates a Guide starting from a state of the Index, e.g. reached by previously
+    /// generated output, with the same options as `Guide(...)`.
+    ///
+    /// Tokens are consumed, and budgeted by `max_tokens`, from that state. Fails if the state
+    /// isn't in the Index.
+    #[staticmethod]
+    #[pyo3(signature = (index, state, max_rollback=32, finish_on="accept", max_tokens=None, max_violations=0, token_biases=None, shorter_bias=0.0, record_events=false, record_history=false, mask_cache=0, vocabulary=None))]
+    #[allow(clippy::too_many_arguments)]
+    fn from_state(
+        py: Python<'_>,
+        index: PyIndex,
+        state: StateId,
+        max_rollback: usize,
+        finish_on: &str,
+        max_tokens: Option<usize>,
+        max_violations: usize,
+        token_biases: Option<HashMap<TokenId, f32>>,
+        shorter_bias: f32,
+        record_events: bool,
+        record_history: bool,
+        mask_cache: usize,
+        vocabulary: Option<PyRef<'_, PyVocabulary>>,
+    ) -> PyResult<Self> {
+        let guide = Guide::from_state(index.0, state, max_rollback)?;
+        configure_guide(
+            py,
+            guide,
+            finish_on,
+            max_tokens,
+            max_violations,
+            token_biases,
+            shorter_bias,
+            record_events,
+            record_history,
+            mask_cache,
+            vocabulary.as_deref(),
+        )
     }
 
     /// Retrieves current state id of the Guide.
     fn get_state(&self) -> StateId {
-        self.state
-    }
-
-    /// Gets the list of allowed tokens for the current state.
-    fn get_tokens(&self) -> PyResult<Vec<TokenId>> {
-        self.index
-            .get_allowed_tokens(self.state)
-            // Since Guide advances only through the states offered by the Index, it means
-            // None here shouldn't happen and it's an issue at Index creation step
-            .ok_or(PyErr::new::<PyValueError, _>(format!(
-                "No allowed tokens available for the state {}",
-                self.state
-            )))
+        self.0.state()
+    }
+
+    /// Gets the list of allowed tokens for the current state, sorted.
+    fn get_tokens(&self, py: Python<'_>) -> PyResult<Vec<TokenId>> {
+        if self.0.has_mask_cache() {
+            return Ok(sorted(py.detach(|| self.0.state_mask())?.tokens().to_vec()));
+        }
+        Ok(sorted(py.detach(|| self.0.allowed_tokens())?))
+    }
+
+    /// Gets the number of tokens left in the budget, None without `max_tokens`.
+    fn get_remaining_tokens(&self) -> Option<usize> {
+        self.0.remaining_tokens()
+    }
+
+    /// Gets the biases of the allowed tokens for the current state, as a list of
+    /// `(token_id, bias)` sorted by token id, to be added to their logits besides the mask.
+    fn get_biases(&self) -> Vec<(TokenId, f32)> {
+        self.0.token_biases()
+    }
+
+    /// Gets the path of the JSON field being generated, e.g. `address.city`, None outside of
+    /// fields or without the `vocabulary` of the index.
+    fn get_current_path(&self) -> Option<String> {
+        self.0.current_path()
+    }
+
+    /// Gets the positions of the violating tokens consumed since the initial state.
+    fn get_violations(&self) -> Vec<usize> {
+        self.0.violations().to_vec()
+    }
+
+    /// Gets how the tokens consumed since the initial state were picked, as a list of dicts
+    /// with the `token_id` and its `kind`: `forced`, `chosen` among `options` allowed tokens,
+    /// `eos` or `violation`. None unless the guide records events.
+    fn get_token_events(&self, py: Python<'_>) -> PyResult<Option<Py<PyAny>>> {
+        self.0
+            .token_events()
+            .map(|events| Ok(serde_pyobject::to_pyobject(py, &events)?.unbind()))
+            .transpose()
+    }
+
+    /// Gets the number of violating tokens which are still tolerated.
+    fn get_remaining_violations(&self) -> usize {
+        self.0.remaining_violations()
+    }
+
+    /// Gets the minimal number of tokens needed to finish from the current state.
+    fn get_min_tokens_to_finish(&self) -> Option<usize> {
+        self.0.min_tokens_to_finish()
     }
 
     /// Get the number of rollback steps available.
     fn get_allowed_rollback(&self) -> usize {
-        self.state_cache.len()
+        self.0.allowed_rollback()
     }
 
-    /// Guide moves to the next state provided by the token id and returns a list of allowed tokens, unless return_tokens is False.
+    /// Guide moves to the next state provided by the token id and returns a sorted list of allowed tokens, unless return_tokens is False.
     #[pyo3(signature = (token_id, return_tokens=None))]
     fn advance(
         &mut self,
+        py: Python<'_>,
         token_id: TokenId,
         return_tokens: Option<bool>,
     ) -> PyResult<Option<Vec<TokenId>>> {
-        match self.index.get_next_state(self.state, token_id) {
-            Some(new_state) => {
-                // Free up space in state_cache if needed.
-                if self.state_cache.len() == self.state_cache.capacity() {
-                    self.state_cache.pop_front();
-                }
-                self.state_cache.push_back(self.state);
-                self.state = new_state;
-                if return_tokens.unwrap_or(true) {
-                    self.get_tokens().map(Some)
-                } else {
-                    Ok(None)
-                }
+        let guide = &mut self.0;
+        let tokens = py.detach(|| {
+            guide.advance(token_id)?;
+            if return_tokens.unwrap_or(true) {
+                guide.allowed_tokens().map(|tokens| Some(sorted(tokens)))
+            } else {
+                Ok(None)
             }
-            None => Err(PyErr::new::<PyValueError, _>(format!(
-                "No next state found for the current state: {} with token ID: {token_id}",
-                self.state
-            ))),
-        }
+        })?;
+        Ok(tokens)
+    }
+
+    /// Computes the state and the allowed tokens, which advancing with the token id would lead
+    /// to, without advancing. The returned proposal is applied by its `commit` method.
+    fn propose(&self, py: Python<'_>, token_id: TokenId) -> PyResult<PyProposal> {
+        Ok(py.detach(|| self.0.propose(token_id)).map(PyProposal)?)
     }
 
     /// Rollback the Guide state `n` tokens (states).
     /// Fails if `n` is greater than stored prior states.
     fn rollback_state(&mut self, n: usize) -> PyResult<()> {
-        if n == 0 {
-            return Ok(());
-        }
-        if n > self.get_allowed_rollback() {
-            return Err(PyValueError::new_err(format!(
-                "Cannot roll back {n} step(s): only {available} states stored (max_rollback = {cap}). \
-                 You must advance through at least {n} state(s) before rolling back {n} step(s).",
-                 cap = self.state_cache.capacity(),
-                 available = self.get_allowed_rollback(),
-            )));
-        }
-        let mut new_state: u32 = self.state;
-        for _ in 0..n {
-            // unwrap is safe because length is checked above
-            new_state = self.state_cache.pop_back().unwrap();
-        }
-        self.state = new_state;
-        Ok(())
+        Ok(self.0.rollback_state(n)?)
     }
 
     // Returns a boolean indicating if the sequence leads to a valid state in the DFA
-    fn accepts_tokens(&self, sequence: Vec<u32>) -> bool {
-        let mut state = self.state;
-        for t in sequence {
-            match self.index.get_next_state(state, t) {
-                Some(s) => state = s,
-                None => return false,
-            }
-        }
-        true
+    fn accepts_tokens(&self, py: Python<'_>, sequence: Vec<TokenId>) -> bool {
+        py.detach(|| self.0.accepts_tokens(&sequence))
+    }
+
+    /// Gets the text generated since the initial state, decoded from the consumed tokens with
+    /// the vocabulary of the index. Invalid UTF-8 sequences are replaced.
+    ///
+    /// Fails unless the guide was created with `record_history=True`.
+    fn get_text(&self, py: Python<'_>, vocabulary: &PyVocabulary) -> PyResult<String> {
+        Ok(py.detach(|| self.0.text(&vocabulary.0))?)
     }
 
-    /// Checks if the automaton is in a final state.
+    /// Checks if the guide is finished, according to its `finish_on` policy.
     fn is_finished(&self) -> bool {
-        self.index.is_final_state(self.state)
+        self.0.is_finished()
     }
 
     /// Write the mask of allowed tokens into the memory specified by data_ptr.
@@ -140,26 +360,23 @@ impl PyGuide {
     /// `element_size` must be 4.
     ///
     /// `data_ptr` should be the data ptr to a `torch.tensor`, or `np.ndarray`, `mx.array` or other
-    /// contiguous memory array.
-    fn write_mask_into(&self, data_ptr: usize, numel: usize, element_size: usize) -> PyResult<()> {
-        let expected_elements = self.index.0.vocab_size().div_ceil(32);
-        if element_size != 4 {
-            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
-                format!(
-                    "Invalid element size: got {} bytes per element, expected 4 bytes (32-bit integer).",
-                    element_size
-                ),
-            ));
-        } else if data_ptr == 0 {
-            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
-                "Invalid data pointer: received a null pointer.",
-            ));
-        } else if data_ptr % 4 != 0 {
-            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
-                "Invalid data pointer alignment: pointer address {} is not a multiple of 4.",
-                data_ptr
-            )));
-        } else if numel < expected_elements {
+    /// memory array. Its elements are `stride` elements apart, 1 for contiguous arrays.
+    ///
+    /// Elements past `(vocab_size + 31) // 32`, e.g. when the model's vocabulary is padded, are
+    /// zeroed unless `zero_tail` is False, in which case they are left untouched.
+    #[pyo3(signature = (data_ptr, numel, element_size, stride=1, zero_tail=true))]
+    fn write_mask_into(
+        &self,
+        py: Python<'_>,
+        data_ptr: usize,
+        numel: usize,
+        element_size: usize,
+        stride: usize,
+        zero_tail: bool,
+    ) -> PyResult<()> {
+        let expected_elements = mask::mask_len(self.0.index().vocab_size());
+        check_mask_buffer(data_ptr, element_size, stride)?;
+        if numel < expected_elements {
             return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
                 format!(
                     "Invalid buffer size: got {} elements ({} bytes), expected {} elements ({} bytes). \
@@ -171,30 +388,127 @@ impl PyGuide {
                 )
             ));
         }
-        unsafe {
-            std::ptr::write_bytes(data_ptr as *mut u8, 0, numel * 4);
-        }
-        if let Some(tokens) = self.index.0.allowed_tokens_iter(&self.state) {
-            let slice = unsafe { std::slice::from_raw_parts_mut(data_ptr as *mut u32, numel) };
-            for &token in tokens {
-                let bucket = (token as usize) / 32;
-                if bucket < slice.len() {
-                    slice[bucket] |= 1 << ((token as usize) % 32);
+
+        let written = if zero_tail { numel } else { expected_elements };
+        py.detach(|| {
+            let base = data_ptr as *mut u32;
+            // Safety: the caller guarantees that `numel` elements, `stride` elements apart,
+            // are valid for writes starting at `data_ptr`, which is non-null and aligned.
+            let element = |i: usize| unsafe { base.add(i * stride) };
+            if self.0.has_mask_cache() {
+                let mask = self.0.state_mask();
+                let words = mask.as_ref().map_or(&[][..], |mask| mask.words());
+                for i in 0..written {
+                    let word = words.get(i).copied().unwrap_or(0);
+                    unsafe { element(i).write(word) };
                 }
+                return;
             }
-        }
+            #[cfg(feature = "metrics")]
+            crate::metrics::increment(crate::metrics::MASKS_SERVED);
+            for i in 0..written {
+                unsafe { element(i).write(0) };
+            }
+            if let Some(tokens) = self.0.allowed_tokens_iter() {
+                for &token in tokens {
+                    let bucket = token.as_usize() / 32;
+                    if bucket < written {
+                        unsafe { *element(bucket) |= 1 << (token.as_usize() % 32) };
+                    }
+                }
+            }
+        });
         Ok(())
     }
 
     fn reset(&mut self) {
-        self.state = self.index.get_initial_state();
+        self.0.reset()
+    }
+
+    /// Exports the current state of the Guide as a dict, to be resumed later with `Guide.resume`.
+    fn export_state<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let snapshot = self.0.export_state();
+        let dict = PyDict::new(py);
+        dict.set_item("state", snapshot.state)?;
+        dict.set_item("consumed_tokens", snapshot.consumed_tokens)?;
+        dict.set_item("max_rollback", snapshot.max_rollback)?;
+        dict.set_item("index_fingerprint", snapshot.index_fingerprint)?;
+        let finish_on = match snapshot.finish_policy {
+            FinishPolicy::OnAccept => "accept",
+            FinishPolicy::OnEos => "eos",
+        };
+        dict.set_item("finish_on", finish_on)?;
+        dict.set_item("eos_consumed", snapshot.eos_consumed)?;
+        dict.set_item("max_tokens", snapshot.max_tokens)?;
+        if let Some(token_ids) = snapshot.token_ids {
+            dict.set_item("token_ids", token_ids)?;
+        }
+        dict.set_item("max_violations", snapshot.max_violations)?;
+        dict.set_item("violations", snapshot.violations)?;
+        if let Some(events) = &snapshot.events {
+            dict.set_item("token_events", serde_pyobject::to_pyobject(py, events)?)?;
+        }
+        Ok(dict)
+    }
+
+    /// Resumes a Guide from a state exported with `Guide.export_state` and the same Index.
+    #[staticmethod]
+    fn resume(index: PyIndex, snapshot: &Bound<'_, PyDict>) -> PyResult<Self> {
+        fn field<'py, T: FromPyObjectOwned<'py>>(
+            snapshot: &Bound<'py, PyDict>,
+            key: &str,
+        ) -> PyResult<T> {
+            match snapshot.get_item(key)? {
+                Some(value) => value.extract().map_err(Into::into),
+                None => Err(PyValueError::new_err(format!(
+                    "Guide state is missing the '{key}' field"
+                ))),
+            }
+        }
+        let snapshot = GuideSnapshot {
+            state: field(snapshot, "state")?,
+            consumed_tokens: field(snapshot, "consumed_tokens")?,
+            max_rollback: field(snapshot, "max_rollback")?,
+            index_fingerprint: field(snapshot, "index_fingerprint")?,
+            // Absent from states exported before finish policies.
+            finish_policy: match snapshot.get_item("finish_on")? {
+                Some(value) => finish_policy(&value.extract::<String>()?)?,
+                None => FinishPolicy::default(),
+            },
+            eos_consumed: match snapshot.get_item("eos_consumed")? {
+                Some(value) => value.extract()?,
+                None => false,
+            },
+            max_tokens: match snapshot.get_item("max_tokens")? {
+                Some(value) => value.extract()?,
+                None => None,
+            },
+            token_ids: match snapshot.get_item("token_ids")? {
+                Some(value) => Some(value.extract()?),
+                None => None,
+            },
+            max_violations: match snapshot.get_item("max_violations")? {
+                Some(value) => value.extract()?,
+                None => 0,
+            },
+            violations: match snapshot.get_item("violations")? {
+                Some(value) => value.extract()?,
+                None => Vec::new(),
+            },
+            events: match snapshot.get_item("token_events")? {
+                Some(value) => Some(serde_pyobject::from_pyobject(value)?),
+                None => None,
+            },
+        };
+        Ok(PyGuide(Guide::resume(index.0, &snapshot)?))
     }
 
     /// Gets the debug string representation of the guide.
     fn __repr__(&self) -> String {
         format!(
             "Guide object with the state={:#?} and {:#?}",
-            self.state, self.index
+            self.0.state(),
+            self.0.index()
         )
     }
 
@@ -202,7 +516,8 @@ impl PyGuide {
     fn __str__(&self) -> String {
         format!(
             "Guide object with the state={} and {}",
-            self.state, self.index.0
+            self.0.state(),
+            self.0.index()
         )
     }
 
@@ -211,27 +526,160 @@ impl PyGuide {
         self == other
     }
 
+    /// Guides are unhashable, since they are compared by their state, which changes as they
+    /// advance.
+    #[classattr]
+    const __hash__: Option<Py<PyAny>> = None;
+
+    /// Makes a shallow copy of the Guide: its state is copied, while the index is shared.
+    fn __copy__(&self) -> Self {
+        self.clone()
+    }
+
+    /// Makes a deep copy of the Guide, the index is immutable and still shared.
+    fn __deepcopy__(&self, _py: Python<'_>, _memo: Py<PyDict>) -> Self {
+        self.clone()
+    }
+
     fn __reduce__(&self) -> PyResult<(Py<PyAny>, (Vec<u8>,))> {
         Python::attach(|py| {
             let cls = PyModule::import(py, "outlines_core")?.getattr("Guide")?;
-            let binary_data: Vec<u8> =
-                bincode::encode_to_vec(self, config::standard()).map_err(|e| {
-                    PyErr::new::<PyValueError, _>(format!("Serialization of Guide failed: {}", e))
-                })?;
+            let binary_data: Vec<u8> = to_pickle(self).map_err(|e| {
+                PyErr::new::<PyValueError, _>(format!("Serialization of Guide failed: {}", e))
+            })?;
             Ok((cls.getattr("from_binary")?.unbind(), (binary_data,)))
         })
     }
 
     #[staticmethod]
-    fn from_binary(binary_data: Vec<u8>) -> PyResult<Self> {
-        let (guide, _): (PyGuide, usize) =
-            bincode::decode_from_slice(&binary_data[..], config::standard()).map_err(|e| {
+    fn from_binary(py: Python<'_>, binary_data: Vec<u8>) -> PyResult<Self> {
+        let guide: PyGuide = py
+            .detach(|| {
+                from_pickle(&binary_data, |legacy: LegacyGuide| {
+                    Ok(PyGuide(legacy.try_into()?))
+                })
+            })
+            .map_err(|e| {
                 PyErr::new::<PyValueError, _>(format!("Deserialization of Guide failed: {}", e))
             })?;
         Ok(guide)
     }
 }
 
+/// Parses the `finish_on` policy of a Guide.
+/// Consequences of advancing a Guide with a token, see `Guide.propose`.
+#[pyclass(name = "Proposal", module = "outlines_core", frozen)]
+#[derive(Clone, Debug)]
+pub struct PyProposal(Proposal);
+
+#[pymethods]
+impl PyProposal {
+    /// Gets the proposed token id.
+    fn get_token_id(&self) -> TokenId {
+        self.0.token_id()
+    }
+
+    /// Gets the state, which advancing with the token leads to.
+    fn get_next_state(&self) -> StateId {
+        self.0.next_state()
+    }
+
+    /// Gets the tokens allowed once the token is consumed, sorted.
+    fn get_allowed_tokens(&self) -> Vec<TokenId> {
+        sorted(self.0.allowed_tokens().to_vec())
+    }
+
+    /// Checks if the token would be consumed as a violation.
+    fn is_violation(&self) -> bool {
+        self.0.is_violation()
+    }
+
+    /// Checks if the end-of-sequence token is allowed once the token is consumed.
+    fn allows_eos(&self) -> bool {
+        self.0.allows_eos()
+    }
+
+    /// Advances the guide with the proposed token and returns the next state.
+    /// Fails if the guide moved since the proposal.
+    fn commit(&self, guide: &mut PyGuide) -> PyResult<StateId> {
+        Ok(self.0.clone().commit(&mut guide.0)?)
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Proposal(token_id={}, next_state={})",
+            self.0.token_id(),
+            self.0.next_state()
+        )
+    }
+}
+
+/// Applies the options of `Guide(...)` to the guide.
+#[allow(clippy::too_many_arguments)]
+fn configure_guide(
+    py: Python<'_>,
+    guide: Guide,
+    finish_on: &str,
+    max_tokens: Option<usize>,
+    max_violations: usize,
+    token_biases: Option<HashMap<TokenId, f32>>,
+    shorter_bias: f32,
+    record_events: bool,
+    record_history: bool,
+    mask_cache: usize,
+    vocabulary: Option<&PyVocabulary>,
+) -> PyResult<PyGuide> {
+    let finish_policy = finish_policy(finish_on)?;
+    let biases = (token_biases.is_some() || shorter_bias != 0.0).then(|| {
+        let token_biases: Vec<(TokenId, f32)> =
+            token_biases.unwrap_or_default().into_iter().collect();
+        let index = guide.index();
+        py.detach(|| {
+            let mut biases = TransitionBiases::for_tokens(index, &token_biases);
+            if shorter_bias != 0.0 {
+                biases.merge(&TransitionBiases::toward_final(index, shorter_bias));
+            }
+            biases
+        })
+    });
+    let mut guide = guide
+        .with_finish_policy(finish_policy)
+        .with_max_violations(max_violations)
+        .with_history(record_history)
+        .with_mask_cache(mask_cache);
+    if let Some(biases) = biases {
+        guide = guide.with_biases(Arc::new(biases));
+    }
+    if record_events {
+        guide = guide.with_token_events();
+    }
+    if let Some(vocabulary) = vocabulary {
+        let index = guide.index();
+        let field_paths = py.detach(|| FieldPaths::new(index, &vocabulary.0));
+        guide = guide.with_field_paths(Arc::new(field_paths));
+    }
+    Ok(PyGuide(match max_tokens {
+        Some(max_tokens) => guide.with_max_tokens(max_tokens),
+        None => guide,
+    }))
+}
+
+fn finish_policy(finish_on: &str) -> PyResult<FinishPolicy> {
+    match finish_on {
+        "accept" => Ok(FinishPolicy::OnAccept),
+        "eos" => Ok(FinishPolicy::OnEos),
+        other => Err(PyValueError::new_err(format!(
+            "Invalid finish policy '{other}', expected one of 'accept', 'eos'"
+        ))),
+    }
+}
+
+/// Sorts the token ids returned to Python, which are listed in the order of hash maps.
+fn sorted(mut token_ids: Vec<TokenId>) -> Vec<TokenId> {
+    token_ids.sort_unstable();
+    token_ids
+}
+
 /// Index object based on regex and vocabulary.
 #[pyclass(name = "Index", module = "outlines_core", frozen)]
 #[derive(Clone, Debug, PartialEq, Encode, Decode)]
@@ -240,18 +688,149 @@ pub struct PyIndex(Arc<Index>);
 #[pymethods]
 impl PyIndex {
     /// Creates an index from a regex and vocabulary.
+    ///
+    /// `on_progress` is called with the number of explored states, the frontier size and
+    /// the elapsed seconds every `progress_interval` states. Returning `False` cancels it.
+    ///
+    /// `max_states`, `max_masks_bytes` and `max_build_ms` bound the resources of the
+    /// construction, which raises ValueError once one is exceeded.
     #[new]
-    fn __new__(py: Python<'_>, regex: &str, vocabulary: &PyVocabulary) -> PyResult<Self> {
-        py.detach(|| {
-            Index::new(regex, &vocabulary.0)
-                .map(|x| PyIndex(Arc::new(x)))
-                .map_err(Into::into)
-        })
+    #[pyo3(signature = (
+        regex,
+        vocabulary,
+        on_progress=None,
+        progress_interval=1000,
+        max_states=None,
+        max_masks_bytes=None,
+        max_build_ms=None,
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    fn __new__(
+        py: Python<'_>,
+        regex: &str,
+        vocabulary: &PyVocabulary,
+        on_progress: Option<Py<PyAny>>,
+        progress_interval: usize,
+        max_states: Option<usize>,
+        max_masks_bytes: Option<usize>,
+        max_build_ms: Option<u64>,
+    ) -> PyResult<Self> {
+        let limits = ResourceLimits {
+            max_states,
+            max_masks_bytes,
+            max_build_ms,
+        };
+        let Some(callback) = on_progress else {
+            return py.detach(|| {
+                Index::with_limits(regex, &vocabulary.0, &limits)
+                    .map(|x| PyIndex(Arc::new(x)))
+                    .map_err(Into::into)
+            });
+        };
+        let mut callback_error = None;
+        let builder = IndexBuilder::new()
+            .with_limits(limits)
+            .with_progress_interval(progress_interval)
+            .on_progress(|progress| {
+                Python::attach(|py| {
+                    let args = (
+                        progress.states_explored,
+                        progress.frontier_size,
+                        progress.elapsed.as_secs_f64(),
+                    );
+                    match callback.call1(py, args) {
+                        Ok(result) if matches!(result.extract::<bool>(py), Ok(false)) => {
+                            ControlFlow::Break(())
+                        }
+                        Ok(_) => ControlFlow::Continue(()),
+                        Err(e) => {
+                            callback_error = Some(e);
+                            ControlFlow::Break(())
+                        }
+                    }
+                })
+            });
+        let index = py.detach(|| builder.build(regex, &vocabulary.0));
+        if let Some(e) = callback_error {
+            return Err(e);
+        }
+        Ok(PyIndex(Arc::new(index?)))
     }
 
-    /// Returns allowed tokens in this state.
+    /// Returns allowed tokens in this state, sorted.
     fn get_allowed_tokens(&self, state: StateId) -> Option<Vec<TokenId>> {
-        self.0.allowed_tokens(&state)
+        self.0.allowed_tokens(&state).map(sorted)
+    }
+
+    /// Writes the tokens allowed in the state as one flag per token into `out`, a contiguous
+    /// 1-D NumPy array of uint8 or bool, allocated with `vocab_size` flags if None, and
+    /// returns it, or None if the state isn't in the index.
+    ///
+    /// `out` may be longer than the vocabulary, e.g. as wide as padded logits, the flags
+    /// past the vocabulary are cleared.
+    #[pyo3(signature = (state, out=None))]
+    fn get_allowed_bool_mask<'py>(
+        &self,
+        py: Python<'py>,
+        state: StateId,
+        out: Option<Bound<'py, PyAny>>,
+    ) -> PyResult<Option<Bound<'py, PyAny>>> {
+        if self.0.allowed_tokens_iter(&state).is_none() {
+            return Ok(None);
+        }
+        let vocab_size = self.0.vocab_size();
+        let out = match out {
+            Some(out) => out,
+            None => PyModule::import(py, "numpy")?
+                .getattr("zeros")?
+                .call1((vocab_size, "bool"))?,
+        };
+        let interface = out.getattr("__array_interface__")?;
+        let typestr: String = interface.get_item("typestr")?.extract()?;
+        let shape: Vec<usize> = interface.get_item("shape")?.extract()?;
+        let strides: Option<Vec<isize>> = interface.get_item("strides")?.extract()?;
+        let (data_ptr, readonly): (usize, bool) = interface.get_item("data")?.extract()?;
+        if typestr != "|u1" && typestr != "|b1" {
+            return Err(PyValueError::new_err(format!(
+                "Invalid mask dtype: got '{typestr}', expected uint8 or bool."
+            )));
+        }
+        let width = match shape.as_slice() {
+            [width] if *width >= vocab_size => *width,
+            _ => {
+                return Err(PyValueError::new_err(format!(
+                    "Invalid mask shape: got {shape:?}, expected a 1-D array of at least {vocab_size} elements."
+                )))
+            }
+        };
+        if strides.is_some_and(|strides| strides != [1]) || readonly || data_ptr == 0 {
+            return Err(PyValueError::new_err(
+                "Invalid mask memory: expected a contiguous and writable array.",
+            ));
+        }
+
+        py.detach(|| {
+            // Safety: the array interface guarantees that `width` contiguous bytes are valid
+            // for writes starting at `data_ptr`, which is non-null.
+            let flags = unsafe { std::slice::from_raw_parts_mut(data_ptr as *mut u8, width) };
+            flags.fill(0);
+            for token_id in self.0.allowed_tokens_iter(&state).into_iter().flatten() {
+                if let Some(flag) = flags.get_mut(token_id.as_usize()) {
+                    *flag = 1;
+                }
+            }
+        });
+        Ok(Some(out))
+    }
+
+    /// Gets the bitmasks of the tokens allowed in `next_state` but not in `prev_state`, and of
+    /// the ones no longer allowed, as lists of 32-bit words like `Guide.write_mask_into`.
+    fn get_mask_delta(
+        &self,
+        prev_state: StateId,
+        next_state: StateId,
+    ) -> Option<(Vec<u32>, Vec<u32>)> {
+        self.0.mask_delta(&prev_state, &next_state)
     }
 
     /// Updates the state.
@@ -264,14 +843,28 @@ impl PyIndex {
         self.0.is_final_state(&state)
     }
 
-    /// Get all final states.
-    fn get_final_states(&self) -> HashSet<StateId> {
-        self.0.final_states().clone()
+    /// Determines whether the generation can finish in the state, i.e. EOS is allowed.
+    fn is_accepting(&self, state: StateId) -> bool {
+        self.0.is_accepting(&state)
+    }
+
+    /// Determines whether the generation must finish in the state, i.e. only EOS is allowed.
+    fn is_dead_end_without_eos(&self, state: StateId) -> bool {
+        self.0.is_dead_end_without_eos(&state)
+    }
+
+    /// Get all final states, inserted in increasing order.
+    fn get_final_states(&self) -> BTreeSet<StateId> {
+        self.0.final_states().iter().copied().collect()
     }
 
-    /// Returns the Index as a Python Dict object.
-    fn get_transitions(&self) -> HashMap<StateId, HashMap<TokenId, StateId>> {
-        self.0.transitions().clone()
+    /// Returns the Index as a Python Dict object, with states and tokens in increasing order.
+    fn get_transitions(&self) -> BTreeMap<StateId, BTreeMap<TokenId, StateId>> {
+        self.0
+            .transitions()
+            .iter()
+            .map(|(&state, map)| (state, map.iter().map(|(&t, &s)| (t, s)).collect()))
+            .collect()
     }
 
     /// Returns the ID of the initial state of the index.
@@ -279,6 +872,31 @@ impl PyIndex {
         self.0.initial_state()
     }
 
+    /// Returns the byte class of each byte value, as 256 bytes of class ids.
+    fn get_byte_classes(&self) -> Vec<u8> {
+        self.0.byte_classes().as_slice().to_vec()
+    }
+
+    /// Returns the representative byte of each byte class, i.e. its smallest one, as bytes.
+    fn get_class_representatives(&self) -> Vec<u8> {
+        self.0.byte_classes().representatives()
+    }
+
+    /// Returns an estimate of the memory held by the index, in bytes, as a dict of its parts
+    /// with their `total`.
+    fn get_footprint<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let footprint = self.0.footprint();
+        let dict = PyDict::new(py);
+        dict.set_item("base", footprint.base)?;
+        dict.set_item("final_states", footprint.final_states)?;
+        dict.set_item("transitions", footprint.transitions)?;
+        dict.set_item("counted", footprint.counted)?;
+        dict.set_item("stop_tokens", footprint.stop_tokens)?;
+        dict.set_item("caches", footprint.caches)?;
+        dict.set_item("total", footprint.total())?;
+        Ok(dict)
+    }
+
     /// Gets the debug string representation of the index.
     fn __repr__(&self) -> String {
         format!("{:#?}", self.0)
@@ -289,11 +907,91 @@ impl PyIndex {
         format!("{}", self.0)
     }
 
+    /// Gets the state reached from the initial state by the text, e.g. previously generated
+    /// output to resume with `Guide.from_state`, or None if the vocabulary can't produce it.
+    fn state_for_text(
+        &self,
+        py: Python<'_>,
+        vocabulary: &PyVocabulary,
+        text: &str,
+    ) -> Option<StateId> {
+        py.detach(|| self.0.state_for_text(&vocabulary.0, text))
+    }
+
+    /// Builds an index of the token sequences of at most `max_tokens` tokens of the vocabulary,
+    /// whose text isn't accepted by this index, e.g. anything except the strings of a pattern.
+    fn complement(
+        &self,
+        py: Python<'_>,
+        vocabulary: &PyVocabulary,
+        max_tokens: usize,
+    ) -> PyResult<PyIndex> {
+        let index = py.detach(|| self.0.complement(&vocabulary.0, max_tokens))?;
+        Ok(PyIndex(Arc::new(index)))
+    }
+
+    /// Maps the states of the fields of the JSON schema, which declare sampling hints with
+    /// `x-outlines-` keywords, e.g. `x-outlines-temperature`, to their paths.
+    ///
+    /// Returns a dict with the field path of each hinted state in `states` and the hints of
+    /// each path, without the prefix, in `hints`.
+    fn get_sampling_hints(
+        &self,
+        py: Python<'_>,
+        vocabulary: &PyVocabulary,
+        json_schema: &str,
+    ) -> PyResult<Py<PyAny>> {
+        let schema = parse_json_schema(json_schema)?;
+        let hints =
+            py.detach(|| FieldPaths::new(&self.0, &vocabulary.0).sampling_hints(&schema))?;
+        Ok(serde_pyobject::to_pyobject(py, &hints)?.unbind())
+    }
+
+    /// Checks that the index is used with the vocabulary it was built with, e.g. after loading
+    /// a cached index, raising ValueError otherwise.
+    fn verify_vocabulary(&self, py: Python<'_>, vocabulary: &PyVocabulary) -> PyResult<()> {
+        py.detach(|| self.0.verify_vocabulary(&vocabulary.0))?;
+        Ok(())
+    }
+
+    /// Builds the index of the vocabulary `after`, which extends `before` the index was built
+    /// with, by adding the transitions of the new tokens, e.g. special tokens of a LoRA
+    /// adapter, raising ValueError if the index must be rebuilt instead.
+    fn extend_vocabulary(
+        &self,
+        py: Python<'_>,
+        before: &PyVocabulary,
+        after: &PyVocabulary,
+    ) -> PyResult<PyIndex> {
+        let index = py.detach(|| {
+            let delta = VocabDelta::between(&before.0, &after.0)?;
+            let mut index = (*self.0).clone();
+            index.extend_vocabulary(&delta)?;
+            Ok::<_, crate::Error>(index)
+        })?;
+        Ok(PyIndex(Arc::new(index)))
+    }
+
+    /// Gets all the transitions of the index, which may be huge, unlike `str(index)`.
+    fn dump_full(&self, py: Python<'_>) -> String {
+        py.detach(|| self.0.dump_full())
+    }
+
     /// Compares whether two indexes are the same.
     fn __eq__(&self, other: &PyIndex) -> bool {
         *self.0 == *other.0
     }
 
+    /// Hashes the index by its fingerprint.
+    fn __hash__(&self) -> u64 {
+        self.0.fingerprint()
+    }
+
+    /// Makes a shallow copy of the Index, which shares the same immutable index.
+    fn __copy__(&self) -> Self {
+        self.clone()
+    }
+
     /// Makes a deep copy of the Index.
     fn __deepcopy__(&self, _py: Python<'_>, _memo: Py<PyDict>) -> Self {
         PyIndex(Arc::new((*self.0).clone()))
@@ -302,18 +1000,18 @@ impl PyIndex {
     fn __reduce__(&self) -> PyResult<(Py<PyAny>, (Vec<u8>,))> {
         Python::attach(|py| {
             let cls = PyModule::import(py, "outlines_core")?.getattr("Index")?;
-            let binary_data: Vec<u8> = bincode::encode_to_vec(&self.0, config::standard())
-                .map_err(|e| {
-                    PyErr::new::<PyValueError, _>(format!("Serialization of Index failed: {}", e))
-                })?;
+            let binary_data: Vec<u8> = py.detach(|| to_pickle(&self.0)).map_err(|e| {
+                PyErr::new::<PyValueError, _>(format!("Serialization of Index failed: {}", e))
+            })?;
             Ok((cls.getattr("from_binary")?.unbind(), (binary_data,)))
         })
     }
 
     #[staticmethod]
-    fn from_binary(binary_data: Vec<u8>) -> PyResult<Self> {
-        let (index, _): (Index, usize) =
-            bincode::decode_from_slice(&binary_data[..], config::standard()).map_err(|e| {
+    fn from_binary(py: Python<'_>, binary_data: Vec<u8>) -> PyResult<Self> {
+        let index: Index = py
+            .detach(|| from_pickle(&binary_data, |legacy: LegacyIndex| Ok(legacy.into())))
+            .map_err(|e| {
                 PyErr::new::<PyValueError, _>(format!("Deserialization of Index failed: {}", e))
             })?;
         Ok(PyIndex(Arc::new(index)))
@@ -351,13 +1049,20 @@ impl PyVocabulary {
     }
 
     /// Creates the vocabulary of a pre-trained model.
+    ///
+    /// `on_progress` is called with the number of processed tokens and their total, as
+    /// tokens are processed. `stop_tokens` lists special tokens, by content or id, which are
+    /// allowed in final states like the end of sentence token, e.g. `["<|im_end|>"]`.
     #[staticmethod]
-    #[pyo3(signature = (model, revision=None, token=None))]
+    #[pyo3(signature = (model, revision=None, token=None, on_progress=None, stop_tokens=None))]
     #[cfg(feature = "hugginface-hub")]
     fn from_pretrained(
+        py: Python<'_>,
         model: String,
         revision: Option<String>,
         token: Option<String>,
+        on_progress: Option<Py<PyAny>>,
+        stop_tokens: Option<Vec<Bound<'_, PyAny>>>,
     ) -> PyResult<PyVocabulary> {
         let mut params = FromPretrainedParameters::default();
         if let Some(r) = revision {
@@ -366,8 +1071,62 @@ impl PyVocabulary {
         if token.is_some() {
             params.token = token
         }
-        let v = Vocabulary::from_pretrained(model.as_str(), Some(params))?;
-        Ok(PyVocabulary(v))
+        let stop_tokens = stop_tokens
+            .unwrap_or_default()
+            .iter()
+            .map(|stop_token| {
+                if let Ok(id) = stop_token.extract::<TokenId>() {
+                    return Ok(SpecialToken::Id(id));
+                }
+                if let Ok(name) = stop_token.extract::<String>() {
+                    return Ok(SpecialToken::Name(name));
+                }
+                Err(PyErr::new::<pyo3::exceptions::PyTypeError, _>(format!(
+                    "Expected a stop token of type str or int, got {:?}",
+                    type_name!(stop_token)
+                )))
+            })
+            .collect::<PyResult<Vec<_>>>()?;
+        let callback_error = std::sync::Mutex::new(None);
+        let options = PretrainedOptions::default()
+            .with_parameters(params)
+            .with_stop_tokens(stop_tokens)
+            .with_progress(|done, total| {
+                let Some(callback) = &on_progress else {
+                    return;
+                };
+                Python::attach(|py| {
+                    if let Err(e) = callback.call1(py, (done, total)) {
+                        let mut error = callback_error.lock().unwrap_or_else(|e| e.into_inner());
+                        error.get_or_insert(e);
+                    }
+                })
+            });
+        let v = py.detach(|| Vocabulary::from_pretrained_with_options(model.as_str(), options));
+        if let Some(e) = callback_error
+            .into_inner()
+            .unwrap_or_else(|e| e.into_inner())
+        {
+            return Err(e);
+        }
+        Ok(PyVocabulary(v?))
+    }
+
+    /// Creates a small vocabulary bundled with the library, to write tests without
+    /// downloading tokenizers: "ascii-json", "gpt2-mini" or "llama-mini".
+    #[staticmethod]
+    fn test_vocab(kind: &str) -> PyResult<PyVocabulary> {
+        let kind = match kind {
+            "ascii-json" => TestVocabulary::AsciiJson,
+            "gpt2-mini" => TestVocabulary::Gpt2Mini,
+            "llama-mini" => TestVocabulary::LlamaMini,
+            other => {
+                return Err(PyValueError::new_err(format!(
+                    "Invalid test vocabulary '{other}', expected one of 'ascii-json', 'gpt2-mini', 'llama-mini'"
+                )))
+            }
+        };
+        Ok(PyVocabulary(Vocabulary::test_vocab(kind)))
     }
 
     /// Inserts new token with token_id or extends list of token_ids if token already present.
@@ -419,6 +1178,76 @@ impl PyVocabulary {
         self.0.eos_token_id()
     }
 
+    /// Gets the ids of the stop tokens, allowed in final states like the end of sentence token.
+    fn get_stop_token_ids(&self) -> Vec<TokenId> {
+        self.0.stop_token_ids().to_vec()
+    }
+
+    /// Inserts a special token id, which ends the generation like the end of sentence token.
+    fn insert_stop_token(&mut self, token_id: TokenId) -> PyResult<()> {
+        Ok(self.0.try_insert_stop_token(token_id)?)
+    }
+
+    /// Explains how a token, as listed by the tokenizer (e.g. "Ġal" or "<0x20>"), is turned
+    /// into bytes: returns a dict with the `token`, its `bytes` (None if it can't be processed),
+    /// their `token_ids` and the `steps` as (input, output bytes or None, rule) tuples.
+    #[cfg(feature = "hugginface-hub")]
+    fn explain<'py>(&self, py: Python<'py>, token: &str) -> PyResult<Bound<'py, PyDict>> {
+        let explanation = self.0.explain(token);
+        let steps: Vec<_> = explanation
+            .steps
+            .into_iter()
+            .map(|step| {
+                let rule = match step.rule {
+                    ProcessingRule::ByteLevel => "byte_level",
+                    ProcessingRule::ByteFallback => "byte_fallback",
+                    ProcessingRule::SpaceReplacement => "space_replacement",
+                    ProcessingRule::Utf8 => "utf8",
+                };
+                (step.input, step.output, rule)
+            })
+            .collect();
+        let dict = PyDict::new(py);
+        dict.set_item("token", explanation.token)?;
+        dict.set_item("bytes", explanation.bytes)?;
+        dict.set_item("token_ids", explanation.token_ids)?;
+        dict.set_item("steps", steps)?;
+        Ok(dict)
+    }
+
+    /// Gets the token of a given token id, None for the end of sentence token.
+    fn get_token(&self, token_id: TokenId) -> Option<Token> {
+        self.0.token(token_id).cloned()
+    }
+
+    /// Returns the tokens, as bytes, with their token ids, ordered by their smallest token id.
+    fn items(&self) -> Vec<(Token, Vec<TokenId>)> {
+        self.0
+            .tokens_by_id()
+            .into_iter()
+            .map(|(token, token_ids)| (token.clone(), token_ids.clone()))
+            .collect()
+    }
+
+    /// Returns the tokens, as bytes, ordered by their smallest token id.
+    fn keys(&self) -> Vec<Token> {
+        self.0
+            .tokens_by_id()
+            .into_iter()
+            .map(|(token, _)| token.clone())
+            .collect()
+    }
+
+    /// Exports the vocabulary as a dict of token ids by token, as bytes, ordered by their
+    /// smallest token id.
+    fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let dict = PyDict::new(py);
+        for (token, token_ids) in self.0.tokens_by_id() {
+            dict.set_item(token.as_slice(), token_ids)?;
+        }
+        Ok(dict)
+    }
+
     /// Gets the debug string representation of the vocabulary.
     fn __repr__(&self) -> String {
         format!("{:#?}", self.0)
@@ -429,6 +1258,11 @@ impl PyVocabulary {
         format!("{}", self.0)
     }
 
+    /// Gets all the tokens of the vocabulary, which may be huge, unlike `str(vocabulary)`.
+    fn dump_full(&self) -> String {
+        self.0.dump_full()
+    }
+
     /// Compares whether two vocabularies are the same.
     fn __eq__(&self, other: &PyVocabulary) -> bool {
         self.0 == other.0
@@ -439,6 +1273,16 @@ impl PyVocabulary {
         self.0.len()
     }
 
+    /// Vocabularies are unhashable, since they are compared by their tokens, which are
+    /// mutable.
+    #[classattr]
+    const __hash__: Option<Py<PyAny>> = None;
+
+    /// Makes a copy of the Vocabulary, which doesn't share its tokens, since they are mutable.
+    fn __copy__(&self) -> Self {
+        self.clone()
+    }
+
     /// Makes a deep copy of the Vocabulary.
     fn __deepcopy__(&self, _py: Python<'_>, _memo: Py<PyDict>) -> Self {
         PyVocabulary(self.0.clone())
@@ -447,43 +1291,421 @@ impl PyVocabulary {
     fn __reduce__(&self) -> PyResult<(Py<PyAny>, (Vec<u8>,))> {
         Python::attach(|py| {
             let cls = PyModule::import(py, "outlines_core")?.getattr("Vocabulary")?;
-            let binary_data: Vec<u8> =
-                bincode::encode_to_vec(self, config::standard()).map_err(|e| {
-                    PyErr::new::<PyValueError, _>(format!(
-                        "Serialization of Vocabulary failed: {}",
-                        e
-                    ))
-                })?;
+            let binary_data: Vec<u8> = py.detach(|| to_pickle(self)).map_err(|e| {
+                PyErr::new::<PyValueError, _>(format!("Serialization of Vocabulary failed: {}", e))
+            })?;
             Ok((cls.getattr("from_binary")?.unbind(), (binary_data,)))
         })
     }
 
     #[staticmethod]
-    fn from_binary(binary_data: Vec<u8>) -> PyResult<Self> {
-        let (guide, _): (PyVocabulary, usize) =
-            bincode::decode_from_slice(&binary_data[..], config::standard()).map_err(|e| {
+    fn from_binary(py: Python<'_>, binary_data: Vec<u8>) -> PyResult<Self> {
+        let vocabulary: PyVocabulary = py
+            .detach(|| {
+                from_pickle(&binary_data, |legacy: LegacyVocabulary| {
+                    Ok(PyVocabulary(legacy.try_into()?))
+                })
+            })
+            .map_err(|e| {
                 PyErr::new::<PyValueError, _>(format!(
                     "Deserialization of Vocabulary failed: {}",
                     e
                 ))
             })?;
-        Ok(guide)
+        Ok(vocabulary)
+    }
+}
+
+/// Options of the regular expression generation, shared by all JSON schema functions.
+struct PySchemaOptions {
+    options: json_schema::SchemaOptions,
+    warnings: json_schema::WarningCollector,
+}
+
+impl PySchemaOptions {
+    /// Collects options from the arguments and the remaining keyword arguments:
+    ///
+    /// - `canonical`: sorted object keys without optional whitespace, `False` by default.
+    /// - `keyword_policy`: how unsupported constraining keywords are handled, "lenient" ignores
+    ///   them, "warn" emits a `UserWarning` for each of them and "strict" raises `ValueError`.
+    /// - `formats`: dict of regular expressions of custom string formats by their names.
+    /// - `default_max_string`, `default_max_items`, `default_max_props`: maximum lengths of
+    ///   strings, arrays and objects, which don't declare their own.
+    /// - `max_regex_len`: maximum length of the generated regular expression, 1 MiB by default.
+    /// - `pattern_anchoring`: "anchor_both" matches `pattern`s against the whole string, by
+    ///   default, while "respect_existing" only anchors the sides they declare with `^` or `$`.
+    fn extract(
+        whitespace_pattern: Option<&str>,
+        max_recursion_depth: usize,
+        kwargs: Option<&Bound<'_, PyDict>>,
+    ) -> PyResult<Self> {
+        let warnings = json_schema::WarningCollector::new();
+        let mut options = json_schema::SchemaOptions {
+            whitespace_pattern: whitespace_pattern.map(str::to_string),
+            max_recursion_depth: Some(max_recursion_depth),
+            ..Default::default()
+        };
+        for (key, value) in kwargs.into_iter().flatten() {
+            match key.extract::<String>()?.as_str() {
+                "canonical" => options.canonical = value.extract()?,
+                "keyword_policy" => {
+                    options.keyword_policy = match value.extract::<String>()?.as_str() {
+                        "lenient" => json_schema::KeywordPolicy::Lenient,
+                        "warn" => json_schema::KeywordPolicy::Warn(warnings.clone()),
+                        "strict" => json_schema::KeywordPolicy::Strict,
+                        other => {
+                            return Err(PyValueError::new_err(format!(
+                                "Invalid keyword policy '{other}', expected one of 'lenient', 'warn', 'strict'"
+                            )))
+                        }
+                    }
+                }
+                "formats" => {
+                    options.formats = value
+                        .extract::<std::collections::HashMap<String, String>>()?
+                        .into_iter()
+                        .collect()
+                }
+                "default_max_string" => options.default_max_string = value.extract()?,
+                "default_max_items" => options.default_max_items = value.extract()?,
+                "default_max_props" => options.default_max_props = value.extract()?,
+                "max_regex_len" => options.max_regex_len = value.extract()?,
+                "pattern_anchoring" => {
+                    options.pattern_anchoring = match value.extract::<String>()?.as_str() {
+                        "anchor_both" => json_schema::PatternAnchoring::AnchorBoth,
+                        "respect_existing" => json_schema::PatternAnchoring::RespectExisting,
+                        other => {
+                            return Err(PyValueError::new_err(format!(
+                                "Invalid pattern anchoring '{other}', expected one of 'anchor_both', 'respect_existing'"
+                            )))
+                        }
+                    }
+                }
+                other => {
+                    return Err(PyErr::new::<pyo3::exceptions::PyTypeError, _>(format!(
+                        "Unexpected schema option '{other}'"
+                    )))
+                }
+            }
+        }
+        Ok(Self { options, warnings })
+    }
+
+    /// Generates the regular expression of the JSON schema string with the GIL released,
+    /// then emits collected warnings.
+    fn generate<T, F>(&self, py: Python<'_>, json_schema: &str, generate: F) -> PyResult<T>
+    where
+        T: Send,
+        F: FnOnce(&serde_json::Value, &json_schema::SchemaOptions) -> crate::Result<T> + Send,
+    {
+        let value = parse_json_schema(json_schema)?;
+        self.run(py, |options| generate(&value, options))
+    }
+
+    /// Runs the generation with the GIL released, then emits collected warnings.
+    fn run<T, F>(&self, py: Python<'_>, generate: F) -> PyResult<T>
+    where
+        T: Send,
+        F: FnOnce(&json_schema::SchemaOptions) -> crate::Result<T> + Send,
+    {
+        let regex = py
+            .detach(|| generate(&self.options))
+            .map_err(|e| PyValueError::new_err(e.to_string()))?;
+        for warning in self.warnings.take() {
+            let message = std::ffi::CString::new(warning.to_string())?;
+            PyErr::warn(
+                py,
+                &py.get_type::<pyo3::exceptions::PyUserWarning>(),
+                &message,
+                1,
+            )?;
+        }
+        Ok(regex)
     }
 }
 
 /// Creates regex string from JSON schema with optional whitespace pattern.
+///
+/// Accepts the schema options `canonical`, `keyword_policy`, `formats`, `default_max_string`,
+/// `default_max_items`, `default_max_props`, `max_regex_len` and `pattern_anchoring` as
+/// keyword arguments.
 #[pyfunction(name = "build_regex_from_schema")]
-#[pyo3(signature = (json_schema, whitespace_pattern=None, max_recursion_depth=3))]
+#[pyo3(signature = (json_schema, whitespace_pattern=None, max_recursion_depth=3, **options))]
 pub fn build_regex_from_schema_py(
-    json_schema: String,
+    py: Python<'_>,
+    json_schema: &str,
     whitespace_pattern: Option<&str>,
     max_recursion_depth: usize,
+    options: Option<&Bound<'_, PyDict>>,
 ) -> PyResult<String> {
-    let value = serde_json::from_str(&json_schema).map_err(|_| {
+    PySchemaOptions::extract(whitespace_pattern, max_recursion_depth, options)?.generate(
+        py,
+        json_schema,
+        json_schema::regex_from_value_with_options,
+    )
+}
+
+/// Parses a JSON schema string.
+fn parse_json_schema(json_schema: &str) -> PyResult<serde_json::Value> {
+    serde_json::from_str(json_schema).map_err(|_| {
         PyErr::new::<pyo3::exceptions::PyTypeError, _>("Expected a valid JSON string.")
-    })?;
-    json_schema::regex_from_value(&value, whitespace_pattern, Some(max_recursion_depth))
-        .map_err(|e| PyValueError::new_err(e.to_string()))
+    })
+}
+
+/// Creates regex string matching a value of any of the JSON schemas.
+///
+/// With `union="discriminator"`, the value is wrapped into `{"schema": name, "value": value}`,
+/// where `name` is the title of the schema, or its position in the list. Accepts the same
+/// schema options as `build_regex_from_schema`.
+#[pyfunction(name = "build_regex_from_schemas")]
+#[pyo3(signature = (json_schemas, union="alternation", whitespace_pattern=None, max_recursion_depth=3, **options))]
+pub fn build_regex_from_schemas_py(
+    py: Python<'_>,
+    json_schemas: Vec<String>,
+    union: &str,
+    whitespace_pattern: Option<&str>,
+    max_recursion_depth: usize,
+    options: Option<&Bound<'_, PyDict>>,
+) -> PyResult<String> {
+    let union = match union {
+        "alternation" => json_schema::SchemaUnion::Alternation,
+        "discriminator" => json_schema::SchemaUnion::Discriminator,
+        other => {
+            return Err(PyValueError::new_err(format!(
+                "Invalid union '{other}', expected one of 'alternation', 'discriminator'"
+            )))
+        }
+    };
+    let values = json_schemas
+        .iter()
+        .map(|json_schema| parse_json_schema(json_schema))
+        .collect::<PyResult<Vec<_>>>()?;
+    PySchemaOptions::extract(whitespace_pattern, max_recursion_depth, options)?.run(py, |options| {
+        json_schema::regex_from_values_with_options(&values, union, options)
+    })
+}
+
+/// Creates regex string for JSON lines from JSON schema, with a bounded number of lines.
+///
+/// Accepts the same schema options as `build_regex_from_schema`.
+#[pyfunction(name = "build_jsonl_regex_from_schema")]
+#[pyo3(signature = (json_schema, min_lines=1, max_lines=None, whitespace_pattern=None, max_recursion_depth=3, **options))]
+pub fn build_jsonl_regex_from_schema_py(
+    py: Python<'_>,
+    json_schema: &str,
+    min_lines: usize,
+    max_lines: Option<usize>,
+    whitespace_pattern: Option<&str>,
+    max_recursion_depth: usize,
+    options: Option<&Bound<'_, PyDict>>,
+) -> PyResult<String> {
+    PySchemaOptions::extract(whitespace_pattern, max_recursion_depth, options)?.generate(
+        py,
+        json_schema,
+        |value, options| {
+            json_schema::regex_jsonl_from_value_with_options(value, options, min_lines, max_lines)
+        },
+    )
+}
+
+/// Creates regex string from JSON schema like `build_regex_from_schema`, and returns it with
+/// the report of the conversion.
+///
+/// The report is a dict of the `keywords` used by the schema with their numbers of occurrences,
+/// the `warnings` about ignored keywords and excluded values, the `normalizations` made, e.g.
+/// default bounds, the cost estimates `regex_length` and `nfa_states`, and the durations
+/// `generation_seconds` and `estimation_seconds`.
+#[pyfunction(name = "build_regex_from_schema_with_report")]
+#[pyo3(signature = (json_schema, whitespace_pattern=None, max_recursion_depth=3, **options))]
+pub fn build_regex_from_schema_with_report_py(
+    py: Python<'_>,
+    json_schema: &str,
+    whitespace_pattern: Option<&str>,
+    max_recursion_depth: usize,
+    options: Option<&Bound<'_, PyDict>>,
+) -> PyResult<(String, Py<PyAny>)> {
+    let (regex, report) = PySchemaOptions::extract(
+        whitespace_pattern,
+        max_recursion_depth,
+        options,
+    )?
+    .generate(py, json_schema, json_schema::regex_from_value_with_report)?;
+    Ok((regex, serde_pyobject::to_pyobject(py, &report)?.unbind()))
+}
+
+/// Fixes properties of the JSON schema to the known values of a JSON object, so that the
+/// regex generated from the returned JSON schema string writes them verbatim.
+#[pyfunction(name = "partial_instance")]
+pub fn partial_instance_py(json_schema: &str, known_values: &str) -> PyResult<String> {
+    let schema = parse_json_schema(json_schema)?;
+    let serde_json::Value::Object(known_values) = parse_json_schema(known_values)? else {
+        return Err(PyErr::new::<pyo3::exceptions::PyTypeError, _>(
+            "Expected a JSON object of known values.",
+        ));
+    };
+    let partial = json_schema::partial_instance(&schema, &known_values)?;
+    Ok(partial.to_string())
+}
+
+/// Repairs a text, which should be a value of the JSON schema, by keeping its longest valid
+/// prefix and completing it with the shortest text reaching a value of the schema.
+///
+/// Returns a dict with the `repaired` text, the `completion` appended to the kept prefix and
+/// the `valid_len` of the prefix, in UTF-8 bytes.
+#[pyfunction(name = "repair")]
+pub fn repair_py(py: Python<'_>, text: &str, json_schema: &str) -> PyResult<Py<PyAny>> {
+    let schema = parse_json_schema(json_schema)?;
+    let repair = py.detach(|| json_schema::repair(text, &schema))?;
+    Ok(serde_pyobject::to_pyobject(py, &repair)?.unbind())
+}
+
+/// Describes the structure, which a regex generated from a JSON schema forces a value into.
+///
+/// Returns a dict with the `type` of the value and its details, e.g. `fields` of an object.
+#[pyfunction(name = "describe_regex")]
+pub fn describe_regex_py(py: Python<'_>, regex: &str) -> PyResult<Py<PyAny>> {
+    let spec = json_schema::describe_regex(regex)?;
+    Ok(serde_pyobject::to_pyobject(py, &spec)?.unbind())
+}
+
+/// Samples strings matching the regex, or token sequences accepted by the index.
+#[pyfunction(name = "sample")]
+#[pyo3(signature = (regex_or_index, n=10, max_len=64, seed=None))]
+pub fn sample_py(
+    py: Python<'_>,
+    regex_or_index: &Bound<'_, PyAny>,
+    n: usize,
+    max_len: usize,
+    seed: Option<u64>,
+) -> PyResult<Py<PyAny>> {
+    let seed = seed.unwrap_or_else(|| {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos() as u64)
+    });
+    if let Ok(index) = regex_or_index.cast::<PyIndex>() {
+        let index = index.get().0.clone();
+        let samples = py.detach(|| crate::regex::sample_tokens(&index, n, max_len, seed));
+        return Ok(samples.into_pyobject(py)?.into_any().unbind());
+    }
+    if let Ok(regex) = regex_or_index.extract::<String>() {
+        let samples = py.detach(|| crate::regex::sample(&regex, n, max_len, seed))?;
+        return Ok(samples.into_pyobject(py)?.into_any().unbind());
+    }
+    Err(PyErr::new::<pyo3::exceptions::PyTypeError, _>(format!(
+        "Expected a regex string or an Index, got {}",
+        type_name!(regex_or_index).to_string_lossy()
+    )))
+}
+
+/// Checks the memory of a mask of 32-bit integers, `stride` elements apart.
+fn check_mask_buffer(data_ptr: usize, element_size: usize, stride: usize) -> PyResult<()> {
+    if element_size != 4 {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+            "Invalid element size: got {} bytes per element, expected 4 bytes (32-bit integer).",
+            element_size
+        )));
+    } else if data_ptr == 0 {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            "Invalid data pointer: received a null pointer.",
+        ));
+    } else if data_ptr % 4 != 0 {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+            "Invalid data pointer alignment: pointer address {} is not a multiple of 4.",
+            data_ptr
+        )));
+    } else if stride == 0 {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            "Invalid stride: elements must be at least 1 element apart.",
+        ));
+    }
+    Ok(())
+}
+
+/// Lists the token ids of the bits set in the mask specified by data_ptr, as written by
+/// `Guide.write_mask_into`, in increasing order.
+///
+/// `numel` elements of 4 bytes are read, `stride` elements apart.
+#[pyfunction(name = "mask_to_list")]
+#[pyo3(signature = (data_ptr, numel, element_size=4, stride=1))]
+pub fn mask_to_list_py(
+    py: Python<'_>,
+    data_ptr: usize,
+    numel: usize,
+    element_size: usize,
+    stride: usize,
+) -> PyResult<Vec<TokenId>> {
+    check_mask_buffer(data_ptr, element_size, stride)?;
+    Ok(py.detach(|| {
+        let base = data_ptr as *const u32;
+        // Safety: the caller guarantees that `numel` elements, `stride` elements apart,
+        // are valid for reads starting at `data_ptr`, which is non-null and aligned.
+        let words = (0..numel).map(|i| unsafe { base.add(i * stride).read() });
+        mask::set_bits(words).collect()
+    }))
+}
+
+/// Creates regex string of any text, which contains at least one of the substrings.
+#[pyfunction(name = "build_contains_regex")]
+#[pyo3(signature = (substrings, alphabet=None, case_insensitive=false))]
+pub fn build_contains_regex_py(
+    substrings: Vec<String>,
+    alphabet: Option<String>,
+    case_insensitive: bool,
+) -> PyResult<String> {
+    let mut contains = Contains::new(substrings).with_case_insensitive(case_insensitive);
+    if let Some(alphabet) = alphabet {
+        contains = contains.with_alphabet(alphabet);
+    }
+    Ok(contains.to_regex()?)
+}
+
+/// Reports the optional features, which the library was compiled with.
+///
+/// Returns a dict of flags by feature name: `hub`, `parallel`, `schema_validation`, `arrow`,
+/// `candle`, `llama_cpp`, `protobuf` and `python_bindings`.
+#[pyfunction(name = "capabilities")]
+pub fn capabilities_py(py: Python<'_>) -> PyResult<Py<PyAny>> {
+    Ok(serde_pyobject::to_pyobject(py, &crate::capabilities())?.unbind())
+}
+
+fn register_regex_module(parent_module: &Bound<'_, PyModule>) -> PyResult<()> {
+    let m = PyModule::new(parent_module.py(), "regex")?;
+    parent_module.add_submodule(&m)?;
+    m.add_function(wrap_pyfunction!(sample_py, &m)?)?;
+    m.add_function(wrap_pyfunction!(build_contains_regex_py, &m)?)?;
+
+    let sys = PyModule::import(m.py(), "sys")?;
+    let sys_modules_bind = (sys.as_ref() as &Bound<PyAny>).getattr("modules")?;
+    let sys_modules = sys_modules_bind.cast::<PyDict>()?;
+    sys_modules.set_item("outlines_core.regex", &m)?;
+
+    Ok(())
+}
+
+/// Disables the thread pool of the parallel iterators in a forked child, if the parent
+/// started it: its threads don't survive the fork, so that the child would hang on it.
+#[pyfunction(name = "_after_fork_in_child")]
+fn after_fork_in_child_py() {
+    if parallelism::has_parallelism_been_used() {
+        parallelism::set_parallelism(false);
+    }
+}
+
+/// Registers `after_fork_in_child_py` to run in the children of `os.fork`, which is missing
+/// on platforms without `fork`.
+fn register_fork_handler(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    let os = PyModule::import(m.py(), "os")?;
+    if !os.hasattr("register_at_fork")? {
+        return Ok(());
+    }
+    let kwargs = PyDict::new(m.py());
+    kwargs.set_item(
+        "after_in_child",
+        wrap_pyfunction!(after_fork_in_child_py, m)?,
+    )?;
+    os.getattr("register_at_fork")?.call((), Some(&kwargs))?;
+    Ok(())
 }
 
 fn register_child_module(parent_module: &Bound<'_, PyModule>) -> PyResult<()> {
@@ -504,6 +1726,15 @@ fn register_child_module(parent_module: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add("EMAIL", json_schema::EMAIL)?;
     m.add("URI", json_schema::URI)?;
     m.add_function(wrap_pyfunction!(build_regex_from_schema_py, &m)?)?;
+    m.add_function(wrap_pyfunction!(build_jsonl_regex_from_schema_py, &m)?)?;
+    m.add_function(wrap_pyfunction!(build_regex_from_schemas_py, &m)?)?;
+    m.add_function(wrap_pyfunction!(
+        build_regex_from_schema_with_report_py,
+        &m
+    )?)?;
+    m.add_function(wrap_pyfunction!(describe_regex_py, &m)?)?;
+    m.add_function(wrap_pyfunction!(partial_instance_py, &m)?)?;
+    m.add_function(wrap_pyfunction!(repair_py, &m)?)?;
 
     let sys = PyModule::import(m.py(), "sys")?;
     let sys_modules_bind = (sys.as_ref() as &Bound<PyAny>).getattr("modules")?;
@@ -518,6 +1749,10 @@ fn register_child_module(parent_module: &Bound<'_, PyModule>) -> PyResult<()> {
 /// - build regular expressions from JSON schemas
 ///
 /// - construct an Index object by combining a Vocabulary and regular expression to efficiently map tokens from a given Vocabulary to state transitions in a finite-state automation
+///
+/// The module may be imported by a single interpreter of the process, imports from
+/// sub-interpreters raise `ImportError`. Forking after the import is supported: the children
+/// of a process, which loaded a vocabulary in parallel, load vocabularies sequentially.
 #[pymodule(gil_used = false)]
 fn outlines_core(m: &Bound<'_, PyModule>) -> PyResult<()> {
     let version = env!("CARGO_PKG_VERSION");
@@ -526,7 +1761,12 @@ fn outlines_core(m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_class::<PyIndex>()?;
     m.add_class::<PyVocabulary>()?;
     m.add_class::<PyGuide>()?;
+    m.add_class::<PyProposal>()?;
+    m.add_function(wrap_pyfunction!(mask_to_list_py, m)?)?;
+    m.add_function(wrap_pyfunction!(capabilities_py, m)?)?;
     register_child_module(m)?;
+    register_regex_module(m)?;
+    register_fork_handler(m)?;
 
     Ok(())
 }
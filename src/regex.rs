@@ -0,0 +1,287 @@
+//! Utilities to inspect what a regular expression or an `Index` actually permits.
+//!
+//! [`sample`] random-walks the DFA of a regular expression to produce example strings,
+//! while [`sample_tokens`] does the same over the token-level automaton of an [`Index`]:
+//!
+//! ```rust
+//! # use outlines_core::Error;
+//! use outlines_core::prelude::*;
+//!
+//! # fn main() -> Result<(), Error> {
+//! let schema = r#"{"type": "object", "properties": {"age": {"type": "integer"}}}"#;
+//! let regex = json_schema::regex_from_str(schema, None, None)?;
+//!
+//! for example in outlines_core::regex::sample(&regex, 5, 32, 42)? {
+//!     println!("{example}");
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Walks are random, but reproducible for the same `seed`.
+
+use std::collections::hash_map::Entry;
+use std::collections::VecDeque;
+
+use regex_automata::dfa::dense::DFA;
+use regex_automata::dfa::Automaton;
+use regex_automata::util::primitives::StateID as AutomataStateId;
+use regex_automata::Anchored;
+use rustc_hash::FxHashMap as HashMap;
+
+use crate::index::Index;
+use crate::prelude::*;
+use crate::{Error, Result};
+
+/// Samples up to `n` strings matching the regular expression, each of at most `max_len` bytes.
+///
+/// At every step, the walk picks uniformly between stopping, if the current state is a match,
+/// and continuing with one of the byte classes, which still lead to a match within the length
+/// bound. Printable ASCII bytes are preferred within a class. Returns an empty vector, if no
+/// string of at most `max_len` bytes matches.
+pub fn sample(regex: &str, n: usize, max_len: usize, seed: u64) -> Result<Vec<String>> {
+    let dfa = DFA::new(regex).map_err(Box::new)?;
+    let start = dfa
+        .universal_start_state(Anchored::Yes)
+        .ok_or(Error::DfaHasNoStartState)?;
+    let classes = byte_classes(&dfa);
+    let edges = live_edges(&dfa, start, &classes);
+
+    let is_match = |state: AutomataStateId| dfa.is_match_state(dfa.next_eoi_state(state));
+    let distances = distances_to_match(&edges, is_match);
+
+    let mut rng = SplitMix64(seed);
+    let mut samples = Vec::with_capacity(n);
+    if distances.get(&start).is_none_or(|&d| d > max_len) {
+        return Ok(samples);
+    }
+    for _ in 0..n {
+        let mut state = start;
+        let mut bytes = Vec::new();
+        loop {
+            let remaining = max_len - bytes.len();
+            let options: Vec<&(usize, AutomataStateId)> = edges[&state]
+                .iter()
+                .filter(|(_, next)| distances.get(next).is_some_and(|&d| d < remaining))
+                .collect();
+            let stop = is_match(state) as usize;
+            let choice = rng.below(options.len() + stop);
+            if choice == options.len() {
+                break;
+            }
+            let &(class, next) = options[choice];
+            let printable: Vec<u8> = classes[class]
+                .iter()
+                .copied()
+                .filter(|b| b.is_ascii_graphic() || *b == b' ')
+                .collect();
+            let candidates = if printable.is_empty() {
+                &classes[class]
+            } else {
+                &printable
+            };
+            bytes.push(candidates[rng.below(candidates.len())]);
+            state = next;
+        }
+        samples.push(String::from_utf8_lossy(&bytes).into_owned());
+    }
+    Ok(samples)
+}
+
+/// Samples up to `n` token sequences accepted by the index, each of at most `max_len` tokens.
+///
+/// The walk follows the same rules as [`sample`], with tokens instead of byte classes.
+/// The end-of-sequence token is not included into sequences.
+pub fn sample_tokens(index: &Index, n: usize, max_len: usize, seed: u64) -> Vec<Vec<TokenId>> {
+    let mut edges: HashMap<StateId, Vec<(TokenId, StateId)>> = HashMap::default();
+    for (&state, map) in index.transitions() {
+        let mut out: Vec<(TokenId, StateId)> = map
+            .keys()
+            .filter_map(|token_id| Some((*token_id, index.next_state(&state, token_id)?)))
+            .collect();
+        // Transitions are stored in hash maps, sorting makes walks reproducible.
+        out.sort_unstable();
+        edges.insert(state, out);
+    }
+    for state in index.final_states() {
+        edges.entry(*state).or_default();
+    }
+    edges.entry(index.initial_state()).or_default();
+
+    let is_match = |state: StateId| index.is_final_state(&state);
+    let distances = distances_to_match(&edges, is_match);
+
+    let start = index.initial_state();
+    let mut rng = SplitMix64(seed);
+    let mut samples = Vec::with_capacity(n);
+    if distances.get(&start).is_none_or(|&d| d > max_len) {
+        return samples;
+    }
+    for _ in 0..n {
+        let mut state = start;
+        let mut tokens = Vec::new();
+        loop {
+            let remaining = max_len - tokens.len();
+            let options: Vec<&(TokenId, StateId)> = edges[&state]
+                .iter()
+                .filter(|(_, next)| distances.get(next).is_some_and(|&d| d < remaining))
+                .collect();
+            let stop = is_match(state) as usize;
+            let choice = rng.below(options.len() + stop);
+            if choice == options.len() {
+                break;
+            }
+            let &(token_id, next) = options[choice];
+            tokens.push(token_id);
+            state = next;
+        }
+        samples.push(tokens);
+    }
+    samples
+}
+
+/// Groups the bytes by the equivalence classes of the DFA.
+pub(crate) fn byte_classes(dfa: &DFA<Vec<u32>>) -> Vec<Vec<u8>> {
+    let mut classes: Vec<Vec<u8>> = vec![Vec::new(); dfa.byte_classes().alphabet_len()];
+    for byte in 0..=255u8 {
+        classes[dfa.byte_classes().get(byte) as usize].push(byte);
+    }
+    classes
+}
+
+/// Collects the forward edges of the states reachable from `start`, one per live byte class.
+pub(crate) fn live_edges(
+    dfa: &DFA<Vec<u32>>,
+    start: AutomataStateId,
+    classes: &[Vec<u8>],
+) -> HashMap<AutomataStateId, Vec<(usize, AutomataStateId)>> {
+    let mut edges: HashMap<AutomataStateId, Vec<(usize, AutomataStateId)>> = HashMap::default();
+    let mut queue = VecDeque::from([start]);
+    edges.insert(start, Vec::new());
+    while let Some(state) = queue.pop_front() {
+        let mut out = Vec::new();
+        for (class, bytes) in classes.iter().enumerate() {
+            let Some(&byte) = bytes.first() else {
+                continue;
+            };
+            let next = dfa.next_state(state, byte);
+            if dfa.is_dead_state(next) || dfa.is_quit_state(next) {
+                continue;
+            }
+            out.push((class, next));
+            if let Entry::Vacant(entry) = edges.entry(next) {
+                entry.insert(Vec::new());
+                queue.push_back(next);
+            }
+        }
+        edges.insert(state, out);
+    }
+    edges
+}
+
+/// Computes the length of the shortest path from each state to a match state,
+/// states without such a path are omitted.
+pub(crate) fn distances_to_match<S, L>(
+    edges: &HashMap<S, Vec<(L, S)>>,
+    is_match: impl Fn(S) -> bool,
+) -> HashMap<S, usize>
+where
+    S: Copy + Eq + std::hash::Hash,
+{
+    let mut reverse: HashMap<S, Vec<S>> = HashMap::default();
+    for (&state, out) in edges {
+        for (_, next) in out {
+            reverse.entry(*next).or_default().push(state);
+        }
+    }
+    let mut distances: HashMap<S, usize> = HashMap::default();
+    let mut queue = VecDeque::new();
+    for &state in edges.keys() {
+        if is_match(state) {
+            distances.insert(state, 0);
+            queue.push_back(state);
+        }
+    }
+    while let Some(state) = queue.pop_front() {
+        let distance = distances[&state] + 1;
+        for &previous in reverse.get(&state).into_iter().flatten() {
+            if let Entry::Vacant(entry) = distances.entry(previous) {
+                entry.insert(distance);
+                queue.push_back(previous);
+            }
+        }
+    }
+    distances
+}
+
+/// Small deterministic pseudo-random generator, good enough to pick walk steps.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a number in `0..n`, `n` must be positive.
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn full_match(regex: &str, value: &str) -> bool {
+        ::regex::Regex::new(&format!("^(?:{regex})$"))
+            .expect("Regex failed")
+            .is_match(value)
+    }
+
+    #[test]
+    fn sample_regex() {
+        let regex = r#"\{"id": [1-9][0-9]{0,3}, "tag": "[a-z]+"\}"#;
+        let samples = sample(regex, 20, 40, 7).expect("Sample failed");
+        assert_eq!(samples.len(), 20);
+        for s in &samples {
+            assert!(full_match(regex, s), "{s}");
+            assert!(s.len() <= 40);
+        }
+        assert_eq!(samples, sample(regex, 20, 40, 7).expect("Sample failed"));
+
+        let samples = sample("[^\"]{3}", 10, 12, 1).expect("Sample failed");
+        for s in &samples {
+            assert!(full_match("[^\"]{3}", s), "{s}");
+        }
+
+        // No match within the length bound.
+        assert!(sample("a{5}", 3, 4, 0).expect("Sample failed").is_empty());
+    }
+
+    #[test]
+    fn sample_index_tokens() {
+        let mut vocabulary = Vocabulary::new(4);
+        for (token, token_id) in [("a", 1), ("b", 2), ("ab", 3)] {
+            vocabulary
+                .try_insert(token, token_id)
+                .expect("Insert failed");
+        }
+        let index = Index::new("(ab)+", &vocabulary).expect("Index failed");
+
+        let samples = sample_tokens(&index, 10, 4, 3);
+        assert_eq!(samples.len(), 10);
+        for tokens in samples {
+            assert!(!tokens.is_empty() && tokens.len() <= 4);
+            let mut state = index.initial_state();
+            for token_id in &tokens {
+                state = index.next_state(&state, token_id).expect("Valid token");
+            }
+            assert!(index.is_final_state(&state));
+        }
+        assert!(sample_tokens(&index, 2, 0, 3).is_empty());
+    }
+}
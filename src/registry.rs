@@ -0,0 +1,447 @@
+//! Registry of named indexes, shared by the threads of a serving process.
+//!
+//! [`IndexRegistry`] maps names to compiled indexes, (re)compiles them in background threads
+//! and swaps a new index in atomically once its compilation is done, so that readers
+//! always get either the previous or the new index, never a partially built one.
+//!
+//! Indexes of JSON schemas are keyed by the hash of their canonical form, see
+//! [`canonicalize`](crate::json_schema::canonicalize), and by the fingerprint of the
+//! vocabulary, so that equivalent schemas share an index per vocabulary.
+//!
+//! Indexes are evicted in least recently used order, once their total estimated memory
+//! usage exceeds the budget of the registry.
+//!
+//! ```rust
+//! # use outlines_core::Error;
+//! use std::sync::Arc;
+//!
+//! use outlines_core::prelude::*;
+//! use outlines_core::registry::IndexRegistry;
+//!
+//! # fn main() -> Result<(), Error> {
+//! let mut vocabulary = Vocabulary::new(3);
+//! for (token, token_id) in [("1", 1), ("2", 2)] {
+//!     vocabulary.try_insert(token, token_id)?;
+//! }
+//! let vocabulary = Arc::new(vocabulary);
+//!
+//! let registry = IndexRegistry::new(64 * 1024 * 1024);
+//! let handle = registry.compile("digits", "[12]+", vocabulary.clone());
+//! handle.join().expect("Compilation panicked")?;
+//!
+//! let index = registry.get("digits").expect("Index is registered");
+//! assert!(!index.final_states().is_empty());
+//! # Ok(())
+//! # }
+//! ```
+
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread::JoinHandle;
+
+use rustc_hash::FxHashMap as HashMap;
+use serde_json::Value;
+
+use crate::index::Index;
+use crate::json_schema;
+use crate::vocabulary::Vocabulary;
+use crate::Result;
+
+/// Thread-safe registry of named indexes with a memory budget.
+///
+/// Cloning the registry is cheap, clones share the same entries.
+#[derive(Clone, Debug)]
+pub struct IndexRegistry {
+    inner: Arc<Mutex<Registry>>,
+}
+
+#[derive(Debug)]
+struct Registry {
+    entries: HashMap<String, Entry>,
+    /// Latest generation requested for each name, by an insertion or a compilation.
+    generations: HashMap<String, u64>,
+    memory_budget: usize,
+    memory_usage: usize,
+    /// Logical clock ordering the accesses to entries.
+    clock: u64,
+    next_generation: u64,
+}
+
+#[derive(Debug)]
+struct Entry {
+    index: Arc<Index>,
+    size: usize,
+    last_used: u64,
+}
+
+impl IndexRegistry {
+    /// Creates an empty registry, which keeps indexes within `memory_budget` bytes.
+    ///
+    /// The most recently inserted index is never evicted, even if it exceeds the budget alone.
+    pub fn new(memory_budget: usize) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(Registry {
+                entries: HashMap::default(),
+                generations: HashMap::default(),
+                memory_budget,
+                memory_usage: 0,
+                clock: 0,
+                next_generation: 0,
+            })),
+        }
+    }
+
+    /// Returns the index registered under `name`, marking it as recently used.
+    pub fn get(&self, name: &str) -> Option<Arc<Index>> {
+        let mut registry = self.lock();
+        registry.clock += 1;
+        let clock = registry.clock;
+        let Some(entry) = registry.entries.get_mut(name) else {
+            #[cfg(feature = "metrics")]
+            crate::metrics::increment(crate::metrics::REGISTRY_MISSES);
+            return None;
+        };
+        #[cfg(feature = "metrics")]
+        crate::metrics::increment(crate::metrics::REGISTRY_HITS);
+        entry.last_used = clock;
+        Some(entry.index.clone())
+    }
+
+    /// Registers the index under `name` and returns the replaced one, if any.
+    ///
+    /// Pending compilations of the same name won't replace this index when they complete.
+    pub fn insert(&self, name: impl Into<String>, index: Arc<Index>) -> Option<Arc<Index>> {
+        let mut registry = self.lock();
+        let generation = registry.new_generation(name.into());
+        registry.swap(generation, index)
+    }
+
+    /// Compiles the regular expression into an index in a background thread and registers
+    /// it under `name` on success.
+    ///
+    /// Until then, the previous index of that name, if any, stays available. If the name is
+    /// compiled or inserted again in the meantime, the result of this compilation is discarded.
+    pub fn compile(
+        &self,
+        name: impl Into<String>,
+        regex: impl Into<String>,
+        vocabulary: Arc<Vocabulary>,
+    ) -> JoinHandle<Result<Arc<Index>>> {
+        let generation = self.lock().new_generation(name.into());
+        let regex = regex.into();
+        let registry = self.clone();
+        std::thread::spawn(move || registry.complete(generation, Index::new(&regex, &vocabulary)))
+    }
+
+    /// Returns the name, which the index of the JSON schema with the vocabulary is registered
+    /// under.
+    ///
+    /// Equivalent schemas, e.g. differing by the order of their keywords or by annotations,
+    /// share the name, which is the hash of their canonical form with the
+    /// [`fingerprint`](Vocabulary::fingerprint) of the vocabulary, since the token ids of
+    /// an index are the ones of its vocabulary.
+    pub fn schema_name(schema: &Value, vocabulary: &Vocabulary) -> String {
+        schema_name(json_schema::canonicalize(schema).1, vocabulary)
+    }
+
+    /// Returns the index of the JSON schema or of an equivalent one with the vocabulary,
+    /// marking it as recently used.
+    pub fn get_json_schema(&self, schema: &Value, vocabulary: &Vocabulary) -> Option<Arc<Index>> {
+        self.get(&Self::schema_name(schema, vocabulary))
+    }
+
+    /// Compiles the canonical form of the JSON schema into an index in a background thread
+    /// and registers it under [`schema_name`](Self::schema_name) on success.
+    ///
+    /// If an equivalent schema is already registered with the same vocabulary, its index is
+    /// returned without compilation.
+    pub fn compile_json_schema(
+        &self,
+        schema: &Value,
+        vocabulary: Arc<Vocabulary>,
+    ) -> JoinHandle<Result<Arc<Index>>> {
+        let (canonical, hash) = json_schema::canonicalize(schema);
+        let name = schema_name(hash, &vocabulary);
+        if let Some(index) = self.get(&name) {
+            return std::thread::spawn(move || Ok(index));
+        }
+        let generation = self.lock().new_generation(name);
+        let registry = self.clone();
+        std::thread::spawn(move || {
+            let result = json_schema::regex_from_value(&canonical, None, None)
+                .and_then(|regex| Index::new(&regex, &vocabulary));
+            registry.complete(generation, result)
+        })
+    }
+
+    /// Removes the index registered under `name`.
+    pub fn remove(&self, name: &str) -> Option<Arc<Index>> {
+        let mut registry = self.lock();
+        let entry = registry.entries.remove(name)?;
+        registry.memory_usage -= entry.size;
+        Some(entry.index)
+    }
+
+    /// Checks if an index is registered under `name`.
+    pub fn contains(&self, name: &str) -> bool {
+        self.lock().entries.contains_key(name)
+    }
+
+    /// Returns the names of registered indexes, in arbitrary order.
+    pub fn names(&self) -> Vec<String> {
+        self.lock().entries.keys().cloned().collect()
+    }
+
+    /// Returns the number of registered indexes.
+    pub fn len(&self) -> usize {
+        self.lock().entries.len()
+    }
+
+    /// Checks if the registry is empty.
+    pub fn is_empty(&self) -> bool {
+        self.lock().entries.is_empty()
+    }
+
+    /// Returns the estimated memory usage of registered indexes, in bytes.
+    pub fn memory_usage(&self) -> usize {
+        self.lock().memory_usage
+    }
+
+    /// Returns the memory budget of the registry, in bytes.
+    pub fn memory_budget(&self) -> usize {
+        self.lock().memory_budget
+    }
+
+    /// Registers the result of a compilation on success, or discards its generation.
+    fn complete(&self, generation: (String, u64), result: Result<Index>) -> Result<Arc<Index>> {
+        match result {
+            Ok(index) => {
+                let index = Arc::new(index);
+                self.lock().swap(generation, index.clone());
+                Ok(index)
+            }
+            Err(e) => {
+                self.lock().discard(generation);
+                Err(e)
+            }
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Registry> {
+        // Entries stay consistent even if a thread panicked while holding the lock,
+        // since they are updated only after indexes are built.
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Registry {
+    fn new_generation(&mut self, name: String) -> (String, u64) {
+        self.next_generation += 1;
+        self.generations.insert(name.clone(), self.next_generation);
+        (name, self.next_generation)
+    }
+
+    /// Forgets the generation of a failed compilation, if it's still the latest one.
+    fn discard(&mut self, (name, generation): (String, u64)) {
+        if self.generations.get(&name) == Some(&generation) {
+            self.generations.remove(&name);
+        }
+    }
+
+    /// Registers the index if its generation is still the latest one for the name.
+    fn swap(&mut self, (name, generation): (String, u64), index: Arc<Index>) -> Option<Arc<Index>> {
+        if self.generations.get(&name) != Some(&generation) {
+            return None;
+        }
+        self.generations.remove(&name);
+        self.clock += 1;
+        let entry = Entry {
+            size: index.footprint().total(),
+            index,
+            last_used: self.clock,
+        };
+        self.memory_usage += entry.size;
+        let previous = self.entries.insert(name.clone(), entry).map(|previous| {
+            self.memory_usage -= previous.size;
+            previous.index
+        });
+        self.evict(&name);
+        previous
+    }
+
+    /// Evicts least recently used entries, except `keep`, until the budget is met.
+    fn evict(&mut self, keep: &str) {
+        while self.memory_usage > self.memory_budget {
+            let lru = self
+                .entries
+                .iter()
+                .filter(|(name, _)| name.as_str() != keep)
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(name, _)| name.clone());
+            let Some(name) = lru else {
+                break;
+            };
+            if let Some(entry) = self.entries.remove(&name) {
+                self.memory_usage -= entry.size;
+            }
+        }
+    }
+}
+
+/// Name of the index of a JSON schema, by the hash of its canonical form and the fingerprint
+/// of the vocabulary.
+fn schema_name(hash: u64, vocabulary: &Vocabulary) -> String {
+    format!("schema-{hash:016x}-{:016x}", vocabulary.fingerprint())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::primitives::TokenId;
+
+    use super::*;
+
+    fn vocabulary() -> Arc<Vocabulary> {
+        vocabulary_with_ids(1, 2)
+    }
+
+    fn vocabulary_with_ids(one: u32, two: u32) -> Arc<Vocabulary> {
+        let mut vocabulary = Vocabulary::new(3);
+        for (token, token_id) in [("1", one), ("2", two)] {
+            vocabulary
+                .try_insert(token, token_id)
+                .expect("Insert failed");
+        }
+        Arc::new(vocabulary)
+    }
+
+    fn index(regex: &str) -> Arc<Index> {
+        Arc::new(Index::new(regex, &vocabulary()).expect("Index failed"))
+    }
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let size = index("1").footprint().total();
+        let registry = IndexRegistry::new(2 * size);
+
+        assert!(registry.insert("a", index("1")).is_none());
+        assert!(registry.insert("b", index("1")).is_none());
+        assert_eq!(registry.memory_usage(), 2 * size);
+
+        // `a` becomes the most recently used entry.
+        assert!(registry.get("a").is_some());
+        registry.insert("c", index("1"));
+        assert!(registry.contains("a"));
+        assert!(!registry.contains("b"));
+        assert!(registry.contains("c"));
+
+        let replaced = registry.insert("a", index("2")).expect("Index is replaced");
+        assert_eq!(replaced.fingerprint(), index("1").fingerprint());
+        assert_eq!(registry.len(), 2);
+
+        assert!(registry.remove("a").is_some());
+        assert_eq!(registry.memory_usage(), size);
+    }
+
+    #[test]
+    fn compiles_in_background() {
+        let registry = IndexRegistry::new(usize::MAX);
+        let compiled = registry
+            .compile("digits", "[12]+", vocabulary())
+            .join()
+            .expect("Compilation panicked")
+            .expect("Compilation failed");
+        let registered = registry.get("digits").expect("Index is registered");
+        assert!(Arc::ptr_eq(&compiled, &registered));
+
+        // Failed compilation keeps the previous index.
+        let result = registry.compile("digits", "3", vocabulary()).join();
+        assert!(result.expect("Compilation panicked").is_err());
+        assert!(Arc::ptr_eq(
+            &registry.get("digits").expect("Index is registered"),
+            &registered
+        ));
+        assert!(registry.lock().generations.is_empty());
+
+        // Outdated compilation doesn't replace the newer index.
+        let handle = registry.compile("digits", "1+", vocabulary());
+        let inserted = index("2+");
+        registry.insert("digits", inserted.clone());
+        handle
+            .join()
+            .expect("Compilation panicked")
+            .expect("Compilation failed");
+        assert!(Arc::ptr_eq(
+            &registry.get("digits").expect("Index is registered"),
+            &inserted
+        ));
+    }
+
+    #[test]
+    fn compiles_equivalent_schemas_once() {
+        let registry = IndexRegistry::new(usize::MAX);
+        let schema = json!({"type": "integer", "title": "Count"});
+        let equivalent = json!({"type": ["integer"], "description": "A count"});
+        assert_eq!(
+            IndexRegistry::schema_name(&schema, &vocabulary()),
+            IndexRegistry::schema_name(&equivalent, &vocabulary())
+        );
+
+        let compiled = registry
+            .compile_json_schema(&schema, vocabulary())
+            .join()
+            .expect("Compilation panicked")
+            .expect("Compilation failed");
+        let cached = registry
+            .compile_json_schema(&equivalent, vocabulary())
+            .join()
+            .expect("Compilation panicked")
+            .expect("Compilation failed");
+        assert!(Arc::ptr_eq(&compiled, &cached));
+        assert!(Arc::ptr_eq(
+            &registry
+                .get_json_schema(&equivalent, &vocabulary())
+                .expect("Index is registered"),
+            &compiled
+        ));
+        assert_eq!(
+            registry.names(),
+            [IndexRegistry::schema_name(&schema, &vocabulary())]
+        );
+
+        assert!(registry
+            .get_json_schema(&json!({"type": "integer", "minimum": 1}), &vocabulary())
+            .is_none());
+    }
+
+    #[test]
+    fn compiles_schemas_per_vocabulary() {
+        let registry = IndexRegistry::new(usize::MAX);
+        let schema = json!({"type": "integer"});
+        let other = vocabulary_with_ids(5, 6);
+        assert_ne!(
+            IndexRegistry::schema_name(&schema, &vocabulary()),
+            IndexRegistry::schema_name(&schema, &other)
+        );
+
+        let compiled = registry
+            .compile_json_schema(&schema, vocabulary())
+            .join()
+            .expect("Compilation panicked")
+            .expect("Compilation failed");
+        assert!(registry.get_json_schema(&schema, &other).is_none());
+        let recompiled = registry
+            .compile_json_schema(&schema, other.clone())
+            .join()
+            .expect("Compilation panicked")
+            .expect("Compilation failed");
+        assert!(!Arc::ptr_eq(&compiled, &recompiled));
+        assert!(recompiled.verify_vocabulary(&other).is_ok());
+        let mut allowed = recompiled
+            .allowed_tokens(&recompiled.initial_state())
+            .expect("No allowed tokens");
+        allowed.sort_unstable();
+        assert_eq!(allowed, [TokenId::new(5), TokenId::new(6)]);
+        assert_eq!(registry.len(), 2);
+    }
+}
@@ -0,0 +1,153 @@
+//! Builders of regular expressions for free text with constraints on its content.
+//!
+//! [`Contains`] permits any text, which contains at least one of the given substrings, e.g.
+//! a citation marker or a required keyword:
+//!
+//! ```rust
+//! # use outlines_core::Error;
+//! use outlines_core::text::Contains;
+//!
+//! # fn main() -> Result<(), Error> {
+//! // A single line, which cites at least one of two sources
+//! let regex = Contains::new(["[1]", "[2]"])
+//!     .with_alphabet(r"[^\n]")
+//!     .to_regex()?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The DFA of the generated regular expression is the product of the free text automaton and
+//! the Aho-Corasick automaton of the substrings: its states before the first match track the
+//! longest suffix of the text, which is a prefix of some substring, while the states after it
+//! are final and accept the rest of the text.
+
+use regex::escape;
+
+use crate::{Error, Result};
+
+/// Default alphabet of the free text: any character, including newlines.
+pub static ANY_CHAR: &str = r"(?s:.)";
+
+/// Builder of free text, which must contain at least one of the substrings.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Contains {
+    substrings: Vec<String>,
+    alphabet: String,
+    case_insensitive: bool,
+}
+
+impl Contains {
+    /// Creates a constraint of any text, which contains at least one of `substrings`.
+    pub fn new<S: Into<String>>(substrings: impl IntoIterator<Item = S>) -> Self {
+        Self {
+            substrings: substrings.into_iter().map(Into::into).collect(),
+            alphabet: ANY_CHAR.to_string(),
+            case_insensitive: false,
+        }
+    }
+
+    /// Sets the pattern of a single character of the text, [`ANY_CHAR`] by default.
+    ///
+    /// Substrings are matched regardless of the alphabet, e.g. a substring with a newline is
+    /// still permitted in a text of `[^\n]` characters.
+    pub fn with_alphabet(self, alphabet: impl Into<String>) -> Self {
+        Self {
+            alphabet: alphabet.into(),
+            ..self
+        }
+    }
+
+    /// Matches substrings regardless of their case.
+    pub fn with_case_insensitive(self, case_insensitive: bool) -> Self {
+        Self {
+            case_insensitive,
+            ..self
+        }
+    }
+
+    /// Generates the regular expression of the text.
+    #[allow(clippy::wrong_self_convention)]
+    pub fn to_regex(&self) -> Result<String> {
+        if self.substrings.is_empty() {
+            return Err(Error::ContainsWithoutSubstrings);
+        }
+        let substrings: Vec<String> = self.substrings.iter().map(|s| escape(s)).collect();
+        let flags = if self.case_insensitive { "i" } else { "" };
+        let text = format!("(?:{})*", self.alphabet);
+        Ok(format!("{text}(?{flags}:{}){text}", substrings.join("|")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use regex::Regex;
+
+    use super::*;
+    use crate::index::Index;
+    use crate::primitives::TokenId;
+    use crate::vocabulary::Vocabulary;
+
+    fn full_match(regex: &str, value: &str) -> bool {
+        Regex::new(&format!("^(?:{regex})$"))
+            .expect("Regex failed")
+            .is_match(value)
+    }
+
+    #[test]
+    fn contains() {
+        let regex = Contains::new(["cat", "dog"])
+            .to_regex()
+            .expect("To regex failed");
+        assert!(full_match(&regex, "cat"));
+        assert!(full_match(&regex, "a hot dog\nand a cat"));
+        assert!(full_match(&regex, "concatenate"));
+        assert!(!full_match(&regex, "a cow"));
+        assert!(!full_match(&regex, "ca t"));
+        assert!(!full_match(&regex, "Dog"));
+
+        let regex = Contains::new(["a.b"])
+            .with_alphabet(r"[^\n]")
+            .with_case_insensitive(true)
+            .to_regex()
+            .expect("To regex failed");
+        assert!(full_match(&regex, "see A.B here"));
+        assert!(!full_match(&regex, "see axb here"));
+        assert!(!full_match(&regex, "a.b\n"));
+
+        let result = Contains::new(Vec::<String>::new()).to_regex();
+        assert!(matches!(result, Err(Error::ContainsWithoutSubstrings)));
+    }
+
+    #[test]
+    fn contains_index() {
+        let regex = Contains::new(["ab", "ba"])
+            .with_alphabet("[ab]")
+            .to_regex()
+            .expect("To regex failed");
+        let mut vocabulary = Vocabulary::new(3);
+        for (token, token_id) in [("a", 0), ("b", 1)] {
+            vocabulary
+                .try_insert(token, token_id)
+                .expect("Insert failed");
+        }
+        let index = Index::new(&regex, &vocabulary).expect("Index failed");
+
+        let walk = |tokens: &[u32]| {
+            tokens
+                .iter()
+                .try_fold(index.initial_state(), |state, &token| {
+                    index.next_state(&state, &TokenId::new(token))
+                })
+        };
+        // Once a substring is found, any continuation of the text is accepted.
+        for tokens in [&[0, 0, 1][..], &[1, 0, 0, 0], &[0, 1, 1, 1, 0]] {
+            let state = walk(tokens).expect("Walk failed");
+            assert!(index.is_final_state(&state));
+            assert_eq!(index.distance_to_final(&state), Some(0));
+        }
+
+        let state = walk(&[0, 0, 0]).expect("Walk failed");
+        assert!(!index.is_final_state(&state));
+        assert_eq!(index.distance_to_final(&state), Some(1));
+    }
+}
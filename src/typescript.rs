@@ -0,0 +1,395 @@
+//! Conversion of TypeScript type literals into JSON schemas, for output shapes written as
+//! TypeScript types rather than JSON schemas:
+//!
+//! ```rust
+//! # use outlines_core::Error;
+//! use outlines_core::json_schema::SchemaOptions;
+//! use outlines_core::typescript;
+//!
+//! # fn main() -> Result<(), Error> {
+//! let source = "{ name: string; age?: number; tags: string[]; role: 'admin' | 'user' }";
+//! let schema = typescript::schema_from_type(source)?;
+//! assert_eq!(schema["required"], serde_json::json!(["name", "tags", "role"]));
+//!
+//! let regex = typescript::regex_from_type(source, &SchemaOptions::default())?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Supported types are:
+//! - `string`, `number`, `boolean`, `null`, as well as `any` and `unknown` for any value;
+//! - string, number and boolean literals;
+//! - object literals, which members are separated by `;` or `,` and marked optional with `?`;
+//! - arrays `T[]` or `Array<T>`, tuples `[A, B]` and `Record<string, T>`;
+//! - unions `A | B` and parenthesized types.
+//!
+//! Comments are ignored, `readonly` modifiers are accepted and have no effect.
+
+use serde_json::{json, Map, Value};
+
+use crate::json_schema::{self, SchemaOptions};
+use crate::{Error, Result};
+
+/// Converts a TypeScript type literal into a JSON schema.
+pub fn schema_from_type(source: &str) -> Result<Value> {
+    let mut parser = Parser { source, offset: 0 };
+    let schema = parser.parse_type()?;
+    parser.skip_trivia();
+    if parser.offset < source.len() {
+        return Err(parser.error("expected the end of the type"));
+    }
+    Ok(schema)
+}
+
+/// Generates a regular expression from a TypeScript type literal.
+pub fn regex_from_type(source: &str, options: &SchemaOptions) -> Result<String> {
+    let schema = schema_from_type(source)?;
+    json_schema::regex_from_value_with_options(&schema, options)
+}
+
+struct Parser<'a> {
+    source: &'a str,
+    /// Byte offset of the next character.
+    offset: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn error(&self, message: &str) -> Error {
+        Error::TypeScriptSyntaxError {
+            offset: self.offset,
+            message: message.to_string(),
+        }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.source[self.offset..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    /// Skips whitespace and comments.
+    fn skip_trivia(&mut self) {
+        loop {
+            let rest = self.rest();
+            let trimmed = rest.trim_start();
+            self.offset += rest.len() - trimmed.len();
+            if trimmed.starts_with("//") {
+                self.offset += trimmed.find('\n').unwrap_or(trimmed.len());
+            } else if let Some(comment) = trimmed.strip_prefix("/*") {
+                self.offset += comment.find("*/").map_or(trimmed.len(), |end| end + 4);
+            } else {
+                return;
+            }
+        }
+    }
+
+    /// Consumes the punctuation, if it comes next.
+    fn eat(&mut self, punctuation: char) -> bool {
+        self.skip_trivia();
+        if self.peek() == Some(punctuation) {
+            self.offset += punctuation.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, punctuation: char) -> Result<()> {
+        if self.eat(punctuation) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected '{punctuation}'")))
+        }
+    }
+
+    fn identifier(&mut self) -> Option<&'a str> {
+        self.skip_trivia();
+        let rest = self.rest();
+        let end = rest
+            .char_indices()
+            .find(|&(i, c)| {
+                !(c == '_' || c == '$' || c.is_alphanumeric()) || (i == 0 && c.is_numeric())
+            })
+            .map_or(rest.len(), |(i, _)| i);
+        if end == 0 {
+            return None;
+        }
+        self.offset += end;
+        Some(&rest[..end])
+    }
+
+    fn parse_type(&mut self) -> Result<Value> {
+        // A leading `|` is allowed, e.g. before the first member of a multiline union.
+        self.eat('|');
+        let mut members = vec![self.parse_postfix()?];
+        while self.eat('|') {
+            members.push(self.parse_postfix()?);
+        }
+        if members.len() == 1 {
+            return Ok(members.remove(0));
+        }
+        // Unions of literals are enums.
+        let values: Option<Vec<Value>> = members
+            .iter()
+            .map(|member| member.get("const").cloned())
+            .collect();
+        Ok(match values {
+            Some(values) => json!({"enum": values}),
+            None => json!({"anyOf": members}),
+        })
+    }
+
+    fn parse_postfix(&mut self) -> Result<Value> {
+        let mut schema = self.parse_primary()?;
+        loop {
+            self.skip_trivia();
+            if !self.rest().starts_with('[') {
+                return Ok(schema);
+            }
+            self.offset += 1;
+            self.expect(']')?;
+            schema = json!({"type": "array", "items": schema});
+        }
+    }
+
+    fn parse_primary(&mut self) -> Result<Value> {
+        self.skip_trivia();
+        match self.peek() {
+            Some('{') => self.parse_object(),
+            Some('[') => self.parse_tuple(),
+            Some('(') => {
+                self.offset += 1;
+                let schema = self.parse_type()?;
+                self.expect(')')?;
+                Ok(schema)
+            }
+            Some(quote @ ('"' | '\'')) => Ok(json!({"const": self.parse_string(quote)?})),
+            Some(c) if c == '-' || c.is_ascii_digit() => Ok(json!({"const": self.parse_number()?})),
+            _ => {
+                let start = self.offset;
+                let schema = match self.identifier() {
+                    Some("string") => json!({"type": "string"}),
+                    Some("number") => json!({"type": "number"}),
+                    Some("boolean") => json!({"type": "boolean"}),
+                    Some("null") => json!({"type": "null"}),
+                    Some("true") => json!({"const": true}),
+                    Some("false") => json!({"const": false}),
+                    Some("any" | "unknown") => json!({}),
+                    Some("readonly") => return self.parse_postfix(),
+                    Some("Array") => {
+                        self.expect('<')?;
+                        let items = self.parse_type()?;
+                        self.expect('>')?;
+                        json!({"type": "array", "items": items})
+                    }
+                    Some("Record") => {
+                        self.expect('<')?;
+                        self.skip_trivia();
+                        let keys = self.offset;
+                        if self.identifier() != Some("string") {
+                            self.offset = keys;
+                            return Err(self.error("expected 'string' keys of a Record"));
+                        }
+                        self.expect(',')?;
+                        let values = self.parse_type()?;
+                        self.expect('>')?;
+                        json!({"type": "object", "additionalProperties": values})
+                    }
+                    _ => {
+                        self.offset = start;
+                        return Err(self.error("expected a type"));
+                    }
+                };
+                Ok(schema)
+            }
+        }
+    }
+
+    fn parse_object(&mut self) -> Result<Value> {
+        self.expect('{')?;
+        let mut properties = Map::new();
+        let mut required = Vec::new();
+        while !self.eat('}') {
+            self.skip_trivia();
+            let start = self.offset;
+            let mut name = self.parse_member_name()?;
+            // `readonly` is a modifier, unless it's the name of the member itself.
+            if name == "readonly" && !matches!(self.next_punctuation(), Some('?' | ':')) {
+                name = self.parse_member_name()?;
+            }
+            let optional = self.eat('?');
+            self.expect(':')?;
+            let schema = self.parse_type()?;
+            if properties.insert(name.clone(), schema).is_some() {
+                self.offset = start;
+                return Err(self.error(&format!("duplicate member '{name}'")));
+            }
+            if !optional {
+                required.push(name);
+            }
+            if !self.eat(';') && !self.eat(',') {
+                self.expect('}')?;
+                break;
+            }
+        }
+        let mut schema = json!({"type": "object", "properties": properties});
+        if !required.is_empty() {
+            schema["required"] = json!(required);
+        }
+        Ok(schema)
+    }
+
+    fn next_punctuation(&mut self) -> Option<char> {
+        self.skip_trivia();
+        self.peek()
+    }
+
+    fn parse_member_name(&mut self) -> Result<String> {
+        match self.next_punctuation() {
+            Some(quote @ ('"' | '\'')) => self.parse_string(quote),
+            _ => self
+                .identifier()
+                .map(str::to_string)
+                .ok_or_else(|| self.error("expected a member name")),
+        }
+    }
+
+    fn parse_tuple(&mut self) -> Result<Value> {
+        self.expect('[')?;
+        let mut items = Vec::new();
+        while !self.eat(']') {
+            items.push(self.parse_type()?);
+            if !self.eat(',') {
+                self.expect(']')?;
+                break;
+            }
+        }
+        Ok(json!({"type": "array", "prefixItems": items, "items": false}))
+    }
+
+    fn parse_string(&mut self, quote: char) -> Result<String> {
+        let start = self.offset;
+        self.offset += 1;
+        let mut value = String::new();
+        let mut chars = self.rest().char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                c if c == quote => {
+                    self.offset += i + 1;
+                    return Ok(value);
+                }
+                '\\' => match chars.next() {
+                    Some((_, 'n')) => value.push('\n'),
+                    Some((_, 't')) => value.push('\t'),
+                    Some((_, 'r')) => value.push('\r'),
+                    Some((_, escaped)) => value.push(escaped),
+                    None => break,
+                },
+                c => value.push(c),
+            }
+        }
+        self.offset = start;
+        Err(self.error("unterminated string literal"))
+    }
+
+    fn parse_number(&mut self) -> Result<Value> {
+        let rest = self.rest();
+        let end = rest
+            .char_indices()
+            .find(|&(i, c)| !(c.is_ascii_digit() || c == '.' || (i == 0 && c == '-')))
+            .map_or(rest.len(), |(i, _)| i);
+        let number = serde_json::from_str::<serde_json::Number>(&rest[..end])
+            .map_err(|_| self.error("invalid number literal"))?;
+        self.offset += end;
+        Ok(Value::Number(number))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use regex::Regex;
+
+    use super::*;
+
+    #[test]
+    fn type_literals() {
+        let source = r#"{
+            // The name is required
+            name: string;
+            age?: number,
+            readonly tags: readonly string[];
+            "kind": 'a' | "b" | 1 | true;
+            scores: Array<number | null>[],
+            pair: [string, boolean];
+            extra: Record<string, any>;
+            readonly: /* a member */ { nested: unknown };
+        }"#;
+        let schema = schema_from_type(source).expect("Parsing failed");
+        assert_eq!(
+            schema,
+            json!({
+                "type": "object",
+                "properties": {
+                    "name": {"type": "string"},
+                    "age": {"type": "number"},
+                    "tags": {"type": "array", "items": {"type": "string"}},
+                    "kind": {"enum": ["a", "b", 1, true]},
+                    "scores": {
+                        "type": "array",
+                        "items": {
+                            "type": "array",
+                            "items": {"anyOf": [{"type": "number"}, {"type": "null"}]}
+                        }
+                    },
+                    "pair": {
+                        "type": "array",
+                        "prefixItems": [{"type": "string"}, {"type": "boolean"}],
+                        "items": false
+                    },
+                    "extra": {"type": "object", "additionalProperties": {}},
+                    "readonly": {"type": "object", "properties": {"nested": {}}, "required": ["nested"]}
+                },
+                "required": ["name", "tags", "kind", "scores", "pair", "extra", "readonly"]
+            })
+        );
+    }
+
+    #[test]
+    fn syntax_errors() {
+        for (source, offset) in [
+            ("", 0),
+            ("{ name string }", 7),
+            ("{ a: string; a: number }", 13),
+            ("string | ", 9),
+            ("Record<number, string>", 7),
+            ("{ a: 'open }", 5),
+            ("string string", 7),
+        ] {
+            match schema_from_type(source) {
+                Err(Error::TypeScriptSyntaxError { offset: found, .. }) => {
+                    assert_eq!(found, offset, "{source}")
+                }
+                result => panic!("{source}: {result:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn type_regex() {
+        let options = SchemaOptions {
+            whitespace_pattern: Some(String::new()),
+            ..Default::default()
+        };
+        let regex = regex_from_type("{ id: number; labels?: ('x' | 'y')[] }", &options)
+            .expect("To regex failed");
+        let re = Regex::new(&format!("^({regex})$")).expect("Regex failed");
+        for value in [r#"{"id":1}"#, r#"{"id":2.5,"labels":["x","y"]}"#] {
+            assert!(re.is_match(value), "{value}");
+        }
+        for value in [r#"{"labels":["x"]}"#, r#"{"id":1,"labels":["z"]}"#] {
+            assert!(!re.is_match(value), "{value}");
+        }
+    }
+}
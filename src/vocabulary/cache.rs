@@ -0,0 +1,118 @@
+//! On-disk cache of the vocabularies of pretrained models.
+//!
+//! A cached vocabulary is stored in a versioned binary format: magic bytes, the version of
+//! the format, then the vocabulary encoded with `bincode`.
+
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+
+use bincode::config;
+use memmap2::Mmap;
+
+use super::{SpecialToken, Vocabulary};
+use crate::{Error, Result};
+
+/// Magic bytes, which start every cached vocabulary.
+const CACHE_MAGIC: &[u8; 4] = b"OCVB";
+
+/// Version of the format of cached vocabularies.
+const CACHE_FORMAT_VERSION: u8 = 2;
+
+/// Returns the path of the cached vocabulary of the model at the revision, with the stop
+/// tokens.
+///
+/// Stop tokens, which may contain any character, are keyed by their 64-bit FNV-1a hash.
+pub(super) fn cache_path(
+    cache_dir: &Path,
+    model: &str,
+    revision: &str,
+    stop_tokens: &[SpecialToken],
+) -> PathBuf {
+    let mut name = format!(
+        "{}--{}",
+        model.replace('/', "--"),
+        revision.replace('/', "--")
+    );
+    if !stop_tokens.is_empty() {
+        let mut hash: u64 = 0xcbf29ce484222325;
+        for stop_token in stop_tokens {
+            for byte in stop_token.to_string().bytes().chain([0]) {
+                hash ^= byte as u64;
+                hash = hash.wrapping_mul(0x100000001b3);
+            }
+        }
+        name.push_str(&format!("--{hash:016x}"));
+    }
+    cache_dir.join(format!("{name}.v{CACHE_FORMAT_VERSION}.bin"))
+}
+
+/// Loads the cached vocabulary, unless it's missing, corrupted or in another format.
+pub(super) fn load(path: &Path) -> Option<Vocabulary> {
+    let file = File::open(path).ok()?;
+    // SAFETY: caches are replaced by `store` with a rename, never modified in place, so
+    // the mapped file doesn't change while it's decoded.
+    let mmap = unsafe { Mmap::map(&file) }.ok()?;
+    let encoded = mmap
+        .strip_prefix(CACHE_MAGIC.as_slice())?
+        .strip_prefix(&[CACHE_FORMAT_VERSION])?;
+    let (vocabulary, _) = bincode::decode_from_slice(encoded, config::standard()).ok()?;
+    Some(vocabulary)
+}
+
+/// Stores the vocabulary into the cache, replacing the previous one atomically.
+pub(super) fn store(path: &Path, vocabulary: &Vocabulary) -> Result<()> {
+    let cache_error =
+        |e: &dyn std::fmt::Display| Error::VocabularyCacheError(format!("{}: {e}", path.display()));
+    let mut bytes = CACHE_MAGIC.to_vec();
+    bytes.push(CACHE_FORMAT_VERSION);
+    bytes.extend(
+        bincode::encode_to_vec(vocabulary, config::standard()).map_err(|e| cache_error(&e))?,
+    );
+
+    if let Some(cache_dir) = path.parent() {
+        fs::create_dir_all(cache_dir).map_err(|e| cache_error(&e))?;
+    }
+    // Concurrent writers don't share the temporary file.
+    let temporary = path.with_extension(format!("{}.tmp", std::process::id()));
+    fs::write(&temporary, bytes).map_err(|e| cache_error(&e))?;
+    fs::rename(&temporary, path).map_err(|e| cache_error(&e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn store_and_load() {
+        let cache_dir =
+            std::env::temp_dir().join(format!("outlines-core-store-{}", std::process::id()));
+        let path = cache_path(&cache_dir, "org/model", "refs/pr/1", &[]);
+        assert_eq!(
+            path.file_name().and_then(|name| name.to_str()),
+            Some("org--model--refs--pr--1.v2.bin")
+        );
+        let stop_tokens = ["<|im_end|>".into(), 2.into()];
+        assert_ne!(
+            cache_path(&cache_dir, "org/model", "refs/pr/1", &stop_tokens),
+            cache_path(&cache_dir, "org/model", "refs/pr/1", &stop_tokens[..1])
+        );
+        assert_eq!(load(&path), None);
+
+        let mut vocabulary = Vocabulary::new(3);
+        for (token, token_id) in [("a", 0), ("b", 1), ("a", 2)] {
+            vocabulary
+                .try_insert(token, token_id)
+                .expect("Insert failed");
+        }
+        store(&path, &vocabulary).expect("Store failed");
+        assert_eq!(load(&path), Some(vocabulary));
+
+        // Caches in another format are ignored.
+        let mut bytes = fs::read(&path).expect("Read failed");
+        bytes[CACHE_MAGIC.len()] += 1;
+        fs::write(&path, bytes).expect("Write failed");
+        assert_eq!(load(&path), None);
+
+        fs::remove_dir_all(cache_dir).expect("Removal failed");
+    }
+}
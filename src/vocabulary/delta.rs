@@ -0,0 +1,125 @@
+//! Tokens appended to a vocabulary, e.g. special tokens of a LoRA adapter, which existing
+//! indexes take in without a rebuild, see [`Index::extend_vocabulary`].
+
+use super::Vocabulary;
+use crate::prelude::*;
+use crate::{Error, Result};
+
+/// Difference between a vocabulary and its extension with new tokens.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VocabDelta {
+    /// Added tokens with each of their new ids, in the order of the ids.
+    tokens: Vec<(Token, TokenId)>,
+    /// Added stop tokens.
+    stop_token_ids: Vec<TokenId>,
+    /// Fingerprint of the vocabulary before the extension.
+    base_fingerprint: u64,
+    /// Fingerprint of the extended vocabulary.
+    fingerprint: u64,
+    /// Size of the extended vocabulary.
+    vocab_size: usize,
+}
+
+impl VocabDelta {
+    /// Computes the tokens added to `before` by `after`.
+    ///
+    /// Fails with [`Error::VocabularyNotExtended`] if `after` removes a token or an id of
+    /// `before`, or changes its end-of-sequence token.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use outlines_core::Error;
+    /// use outlines_core::prelude::*;
+    /// use outlines_core::vocabulary::VocabDelta;
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// let before = Vocabulary::fixture();
+    /// let mut after = before.clone();
+    /// after.try_insert("<tool>", 5000)?;
+    ///
+    /// let delta = VocabDelta::between(&before, &after)?;
+    /// assert_eq!(delta.tokens(), [(b"<tool>".to_vec(), TokenId::new(5000))]);
+    /// assert!(VocabDelta::between(&after, &before).is_err());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn between(before: &Vocabulary, after: &Vocabulary) -> Result<Self> {
+        let not_extended = |reason: String| Err(Error::VocabularyNotExtended(reason));
+        if before.eos_token_id() != after.eos_token_id() {
+            return not_extended(format!(
+                "end-of-sequence token {} instead of {}",
+                after.eos_token_id(),
+                before.eos_token_id()
+            ));
+        }
+        for (token, ids) in before.tokens() {
+            let kept = after.token_ids(token);
+            if let Some(id) = ids
+                .iter()
+                .find(|id| kept.is_none_or(|kept| !kept.contains(id)))
+            {
+                return not_extended(format!("token {id} is removed"));
+            }
+        }
+        if let Some(id) = before
+            .stop_token_ids()
+            .iter()
+            .find(|&&id| !after.is_stop_token(id))
+        {
+            return not_extended(format!("stop token {id} is removed"));
+        }
+
+        let mut tokens: Vec<(Token, TokenId)> = after
+            .tokens()
+            .iter()
+            .flat_map(|(token, ids)| ids.iter().map(move |&id| (token, id)))
+            .filter(|(token, id)| before.token_ids(token).is_none_or(|ids| !ids.contains(id)))
+            .map(|(token, id)| (token.clone(), id))
+            .collect();
+        tokens.sort_unstable_by_key(|&(_, id)| id);
+        let stop_token_ids = after
+            .stop_token_ids()
+            .iter()
+            .copied()
+            .filter(|&id| !before.is_stop_token(id))
+            .collect();
+        Ok(Self {
+            tokens,
+            stop_token_ids,
+            base_fingerprint: before.fingerprint(),
+            fingerprint: after.fingerprint(),
+            vocab_size: after.vocab_size(),
+        })
+    }
+
+    /// Returns the added tokens with each of their new ids, in the order of the ids.
+    pub fn tokens(&self) -> &[(Token, TokenId)] {
+        &self.tokens
+    }
+
+    /// Returns the added stop tokens.
+    pub fn stop_token_ids(&self) -> &[TokenId] {
+        &self.stop_token_ids
+    }
+
+    /// Whether no token is added.
+    pub fn is_empty(&self) -> bool {
+        self.tokens.is_empty() && self.stop_token_ids.is_empty()
+    }
+
+    /// Returns the fingerprint of the vocabulary before the extension.
+    pub fn base_fingerprint(&self) -> u64 {
+        self.base_fingerprint
+    }
+
+    /// Returns the fingerprint of the extended vocabulary.
+    pub fn fingerprint(&self) -> u64 {
+        self.fingerprint
+    }
+
+    /// Returns the size of the extended vocabulary.
+    pub fn vocab_size(&self) -> usize {
+        self.vocab_size
+    }
+}
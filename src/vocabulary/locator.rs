@@ -134,15 +134,17 @@ impl EosTokenLocation {
         match self.location {
             EosTokenField::Id => {
                 let config: Id = serde_json::from_reader(file).ok()?;
-                u32::try_from(config.eos_token_id).ok()
+                u32::try_from(config.eos_token_id).ok().map(TokenId::new)
             }
             EosTokenField::Value => {
                 let config: Value = serde_json::from_reader(file).ok()?;
-                tokenizer.token_to_id(&config.eos_token)
+                tokenizer.token_to_id(&config.eos_token).map(TokenId::new)
             }
             EosTokenField::Object => {
                 let config: Object = serde_json::from_reader(file).ok()?;
-                tokenizer.token_to_id(&config.eos_token.content)
+                tokenizer
+                    .token_to_id(&config.eos_token.content)
+                    .map(TokenId::new)
             }
         }
     }
@@ -211,7 +213,9 @@ mod tests {
 
             assert_eq!(located, *expected_token_id);
             assert_eq!(
-                tokenizer.id_to_token(located).expect("Token is not found"),
+                tokenizer
+                    .id_to_token(located.as_u32())
+                    .expect("Token is not found"),
                 expected_token.to_string()
             );
         }
@@ -1,26 +1,49 @@
 //! Creates `Vocabulary` manually or from pretrained large language model.
 
+#[cfg(feature = "hugginface-hub")]
+use std::path::PathBuf;
+#[cfg(feature = "hugginface-hub")]
+use std::sync::atomic::{AtomicUsize, Ordering};
+
 use bincode::{Decode, Encode};
 #[cfg(feature = "hugginface-hub")]
 use locator::{HFLocator, Locator};
 #[cfg(feature = "hugginface-hub")]
 use processor::TokenProcessor;
-use rustc_hash::FxHashMap as HashMap;
+use rustc_hash::{FxHashMap as HashMap, FxHashSet as HashSet};
 #[cfg(feature = "hugginface-hub")]
 use tokenizers::normalizers::Sequence;
 #[cfg(feature = "hugginface-hub")]
+use tokenizers::utils::parallelism::MaybeParallelIterator;
+#[cfg(feature = "hugginface-hub")]
 use tokenizers::{NormalizerWrapper, Tokenizer};
 
 use crate::prelude::*;
+use crate::primitives::SUMMARY_ENTRIES;
 use crate::{Error, Result};
 
+#[cfg(feature = "hugginface-hub")]
+mod cache;
+mod delta;
 #[cfg(feature = "hugginface-hub")]
 mod locator;
+mod presets;
 #[cfg(feature = "hugginface-hub")]
 mod processor;
 
+pub use delta::VocabDelta;
+pub use presets::TestVocabulary;
+
+/// Number of tokens processed between two reports of the progress.
+#[cfg(feature = "hugginface-hub")]
+const PROGRESS_CHUNK: usize = 4096;
+
 /// `Vocabulary` of large language model.
 ///
+/// Several token ids may share the same bytes, e.g. `<0x20>` and `▁` of byte fallback
+/// tokenizers. They are equivalent: indexes allow either all of them or none and move to the
+/// same state with any of them, so that masks never depend on the id picked for the bytes.
+///
 /// ## Examples
 ///
 #[cfg_attr(
@@ -62,28 +85,245 @@ mod processor;
 /// ```
 "##
 )]
-#[derive(Clone, Debug, Default, PartialEq, Encode, Decode)]
+#[derive(Clone, Default, Encode, Decode)]
 pub struct Vocabulary {
     eos_token_id: TokenId,
     tokens: HashMap<Token, Vec<TokenId>>,
+    /// Special tokens, which end the generation like the end-of-sequence token. They are
+    /// not matched as text, see [`Vocabulary::try_insert_stop_token`].
+    stop_token_ids: Vec<TokenId>,
+    /// How tokens of the tokenizer were turned into bytes, if the vocabulary comes from one.
+    normalization: Option<TokenNormalization>,
+}
+
+/// Special token of a tokenizer, selected by its content or its id.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SpecialToken {
+    /// Content of the token, e.g. `<|im_end|>`.
+    Name(String),
+    /// Id of the token.
+    Id(TokenId),
+}
+
+impl From<&str> for SpecialToken {
+    fn from(name: &str) -> Self {
+        Self::Name(name.to_string())
+    }
+}
+
+impl From<TokenId> for SpecialToken {
+    fn from(id: TokenId) -> Self {
+        Self::Id(id)
+    }
+}
+
+impl From<u32> for SpecialToken {
+    fn from(id: u32) -> Self {
+        Self::Id(TokenId::new(id))
+    }
+}
+
+impl std::fmt::Display for SpecialToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Name(name) => write!(f, "{name:?}"),
+            Self::Id(id) => write!(f, "{id}"),
+        }
+    }
+}
+
+/// Options of [`Vocabulary::from_pretrained_with_options`].
+///
+/// ```rust,no_run
+/// use outlines_core::prelude::*;
+/// use outlines_core::vocabulary::PretrainedOptions;
+///
+/// let options = PretrainedOptions::default()
+///     .with_stop_tokens(["<|im_end|>"])
+///     .with_cache_dir("vocabularies")
+///     .with_progress(|done, total| println!("{done}/{total} tokens"));
+/// let vocabulary = Vocabulary::from_pretrained_with_options("Qwen/Qwen2.5-0.5B", options);
+/// ```
+#[cfg(feature = "hugginface-hub")]
+#[derive(Default)]
+pub struct PretrainedOptions<'a> {
+    parameters: Option<FromPretrainedParameters>,
+    stop_tokens: Vec<SpecialToken>,
+    cache_dir: Option<PathBuf>,
+    progress: Option<Box<dyn Fn(usize, usize) + Send + Sync + 'a>>,
+}
+
+#[cfg(feature = "hugginface-hub")]
+impl<'a> PretrainedOptions<'a> {
+    /// Sets the parameters of the download, e.g. the revision of the model.
+    pub fn with_parameters(mut self, parameters: FromPretrainedParameters) -> Self {
+        self.parameters = Some(parameters);
+        self
+    }
+
+    /// Includes the selected special tokens, e.g. `<|im_end|>`, as stop tokens.
+    ///
+    /// Other special tokens are skipped.
+    pub fn with_stop_tokens(
+        mut self,
+        stop_tokens: impl IntoIterator<Item = impl Into<SpecialToken>>,
+    ) -> Self {
+        self.stop_tokens = stop_tokens.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Caches the vocabulary in `cache_dir` by model, revision and stop tokens, so that later
+    /// calls neither download nor parse the tokenizer.
+    ///
+    /// Note, that a branch revision, e.g. the default `main`, stays cached as it was when
+    /// the vocabulary was first created.
+    pub fn with_cache_dir(mut self, cache_dir: impl Into<PathBuf>) -> Self {
+        self.cache_dir = Some(cache_dir.into());
+        self
+    }
+
+    /// Reports the number of processed tokens out of the total to `progress`.
+    ///
+    /// Tokens are processed in parallel, so `progress` may be called from several threads.
+    pub fn with_progress(mut self, progress: impl Fn(usize, usize) + Send + Sync + 'a) -> Self {
+        self.progress = Some(Box::new(progress));
+        self
+    }
+}
+
+/// How the tokens listed by a tokenizer, e.g. `Ġal` or `<0x20>`, are turned into bytes.
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode)]
+pub enum TokenNormalization {
+    /// Byte level tokenizers (e.g., gpt2) map each byte to a printable character.
+    ByteLevel,
+    /// Byte fallback tokenizers (e.g., llama) have `<0x__>` tokens for single bytes and
+    /// replace spaces with a character, e.g. `▁`.
+    ByteFallback {
+        /// Character replacing spaces.
+        space: String,
+    },
+}
+
+/// Explanation of how a token listed by a tokenizer was turned into bytes, see
+/// [`Vocabulary::explain`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TokenExplanation {
+    /// Token as listed by the tokenizer.
+    pub token: String,
+    /// Processing steps, in the order of the parts of the token.
+    pub steps: Vec<ProcessingStep>,
+    /// Bytes of the token, unless one of its parts couldn't be processed.
+    pub bytes: Option<Token>,
+    /// Ids of the bytes in the vocabulary, empty when they are not in it.
+    pub token_ids: Vec<TokenId>,
+}
+
+/// Processing of a part of a token.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProcessingStep {
+    /// Part of the token processed by the step.
+    pub input: String,
+    /// Bytes of the part, none when it couldn't be processed.
+    pub output: Option<Token>,
+    /// Rule turning the part into bytes.
+    pub rule: ProcessingRule,
+}
+
+/// Rule of a [`ProcessingStep`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProcessingRule {
+    /// Character of a byte level tokenizer mapped back to its byte.
+    ByteLevel,
+    /// `<0x__>` token of a byte fallback tokenizer turned into its byte.
+    ByteFallback,
+    /// Character replacing spaces turned back into a space.
+    SpaceReplacement,
+    /// Text kept as is, encoded in UTF-8.
+    Utf8,
+}
+
+/// Vocabularies are equal when they have the same tokens, however they were normalized.
+impl PartialEq for Vocabulary {
+    fn eq(&self, other: &Self) -> bool {
+        self.eos_token_id == other.eos_token_id
+            && self.tokens == other.tokens
+            && self.stop_token_ids == other.stop_token_ids
+    }
 }
 
 impl Vocabulary {
     /// Creates an empty vocabulary.
-    pub fn new(eos_token_id: TokenId) -> Self {
+    pub fn new(eos_token_id: impl Into<TokenId>) -> Self {
         Self {
-            eos_token_id,
+            eos_token_id: eos_token_id.into(),
             tokens: HashMap::default(),
+            stop_token_ids: Vec::new(),
+            normalization: None,
         }
     }
 
+    /// Creates the small vocabulary of unit tests and examples, which is the same on every
+    /// call, i.e. the preset [`TestVocabulary::AsciiJson`].
+    ///
+    /// Tests depending on the tokens of a model, e.g. spaces of byte level tokenizers or
+    /// byte fallback, use the other presets of [`Vocabulary::test_vocab`].
+    pub fn fixture() -> Self {
+        Self::test_vocab(TestVocabulary::AsciiJson)
+    }
+
     /// Creates the vocabulary of pre-trained model from Hugging Face Hub.
+    ///
+    /// Same as [`Vocabulary::from_pretrained_with_options`] with only the `parameters` set.
     #[cfg(feature = "hugginface-hub")]
     pub fn from_pretrained(
         model: &str,
         parameters: Option<FromPretrainedParameters>,
     ) -> Result<Self> {
-        Self::from_pretrained_with_locator::<HFLocator>(model, parameters)
+        let options = match parameters {
+            Some(parameters) => PretrainedOptions::default().with_parameters(parameters),
+            None => PretrainedOptions::default(),
+        };
+        Self::from_pretrained_with_options(model, options)
+    }
+
+    /// Creates the vocabulary of pre-trained model from Hugging Face Hub, as set by the
+    /// `options`, e.g. with stop tokens or cached on disk.
+    ///
+    /// Cached vocabularies are loaded without reporting any progress.
+    #[cfg(feature = "hugginface-hub")]
+    pub fn from_pretrained_with_options(model: &str, options: PretrainedOptions) -> Result<Self> {
+        let PretrainedOptions {
+            parameters,
+            stop_tokens,
+            cache_dir,
+            progress,
+        } = options;
+        let progress = |done, total| {
+            if let Some(progress) = &progress {
+                progress(done, total)
+            }
+        };
+        let Some(cache_dir) = cache_dir else {
+            return Self::from_pretrained_with_locator::<HFLocator>(
+                model,
+                parameters,
+                &stop_tokens,
+                progress,
+            );
+        };
+        let revision = parameters.clone().unwrap_or_default().revision;
+        let path = cache::cache_path(&cache_dir, model, &revision, &stop_tokens);
+        if let Some(vocabulary) = cache::load(&path) {
+            return Ok(vocabulary);
+        }
+        let vocabulary = Self::from_pretrained_with_locator::<HFLocator>(
+            model,
+            parameters,
+            &stop_tokens,
+            progress,
+        )?;
+        cache::store(&path, &vocabulary)?;
+        Ok(vocabulary)
     }
 
     #[doc(hidden)]
@@ -92,6 +332,8 @@ impl Vocabulary {
     fn from_pretrained_with_locator<L: Locator>(
         model: &str,
         parameters: Option<FromPretrainedParameters>,
+        stop_tokens: &[SpecialToken],
+        progress: impl Fn(usize, usize) + Sync,
     ) -> Result<Self> {
         let mut tokenizer = Tokenizer::from_pretrained(model, parameters.clone())?;
         Self::filter_prepend_normalizers(&mut tokenizer);
@@ -104,28 +346,87 @@ impl Vocabulary {
                 reason: "EOS token id".to_string(),
             });
         };
+        Self::from_tokenizer(model, &tokenizer, eos_token_id, stop_tokens, progress)
+    }
 
+    /// Builds the vocabulary of the tokenizer of `model`, once its end-of-sequence token
+    /// is located.
+    #[cfg(feature = "hugginface-hub")]
+    fn from_tokenizer(
+        model: &str,
+        tokenizer: &Tokenizer,
+        eos_token_id: TokenId,
+        stop_tokens: &[SpecialToken],
+        progress: impl Fn(usize, usize) + Sync,
+    ) -> Result<Self> {
         // Start building the vocabulary from eos_token_id and added tokens.
         let mut vocabulary = Vocabulary::new(eos_token_id);
-        for (id, added_token) in tokenizer.get_added_tokens_decoder().iter() {
-            if !added_token.special && id != &eos_token_id {
-                vocabulary.try_insert(added_token.content.clone(), *id)?
+        let added_tokens = tokenizer.get_added_tokens_decoder();
+        for (&id, added_token) in added_tokens.iter() {
+            let id = TokenId::new(id);
+            if !added_token.special && id != eos_token_id {
+                vocabulary.try_insert(added_token.content.clone(), id)?
+            }
+        }
+        for stop_token in stop_tokens {
+            let found = added_tokens.iter().find(|(id, added_token)| {
+                added_token.special
+                    && match stop_token {
+                        SpecialToken::Name(name) => &added_token.content == name,
+                        SpecialToken::Id(token_id) => token_id == *id,
+                    }
+            });
+            match found {
+                Some((id, _)) if eos_token_id == *id => {}
+                Some((id, _)) => vocabulary.try_insert_stop_token(*id)?,
+                None => {
+                    return Err(Error::SpecialTokenNotFound {
+                        model: model.to_string(),
+                        token: stop_token.to_string(),
+                    })
+                }
             }
         }
 
         // Process each vocabulary token according to the tokenizer's level.
-        let Ok(processor) = TokenProcessor::new(&tokenizer) else {
+        let Ok(processor) = TokenProcessor::new(tokenizer) else {
             return Err(Error::UnsupportedTokenizer {
                 model: model.to_string(),
                 reason: "Token processor".to_string(),
             });
         };
-        for (token, token_id) in tokenizer.get_vocab(false) {
-            if token_id != eos_token_id {
-                let processed_token = processor.process(&token)?;
-                vocabulary.try_insert(processed_token, token_id)?;
-            }
+        // Ordered by id, so that ids of the same token are always listed in the same order.
+        let mut tokens: Vec<(String, TokenId)> = tokenizer
+            .get_vocab(false)
+            .into_iter()
+            .map(|(token, token_id)| (token, TokenId::new(token_id)))
+            .filter(|(_, token_id)| !vocabulary.is_stop_token(*token_id))
+            .collect();
+        tokens.sort_unstable_by_key(|(_, token_id)| *token_id);
+
+        let total = tokens.len();
+        let processed = AtomicUsize::new(0);
+        // Shares the switch of `tokenizers`, so that `TOKENIZERS_PARALLELISM` also applies here
+        // and the thread pool is known to be started, when the process forks.
+        let chunks: Vec<&[(String, TokenId)]> = tokens.chunks(PROGRESS_CHUNK).collect();
+        let chunks = chunks
+            .into_maybe_par_iter()
+            .map(|chunk| {
+                let chunk = chunk
+                    .iter()
+                    .map(|(token, token_id)| Ok((processor.process(token)?, *token_id)))
+                    .collect::<Result<Vec<_>>>()?;
+                progress(
+                    processed.fetch_add(chunk.len(), Ordering::Relaxed) + chunk.len(),
+                    total,
+                );
+                Ok(chunk)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        for (processed_token, token_id) in chunks.into_iter().flatten() {
+            vocabulary.try_insert(processed_token, token_id)?;
         }
+        vocabulary.normalization = Some(processor.normalization());
 
         Ok(vocabulary)
     }
@@ -135,6 +436,77 @@ impl Vocabulary {
         &self.tokens
     }
 
+    /// Formats all the tokens of the vocabulary with their token ids, which may be huge,
+    /// unlike `Display`.
+    pub fn dump_full(&self) -> String {
+        let mut dump = format!(
+            "Vocabulary object with eos_token_id={:?} and the following tokens to token_ids:\n",
+            self.eos_token_id
+        );
+        for (token, token_ids) in self.tokens.iter() {
+            let bytes: Vec<_> = token.iter().map(|b| format!("0x{:02X}", b)).collect();
+            dump.push_str(&format!("{:?} -> {:?}\n", bytes, token_ids));
+        }
+        dump
+    }
+
+    /// Returns all tokens with their token ids, ordered by their smallest token id.
+    pub fn tokens_by_id(&self) -> Vec<(&Token, &Vec<TokenId>)> {
+        let mut tokens: Vec<_> = self.tokens.iter().collect();
+        tokens.sort_unstable_by(|(a, a_ids), (b, b_ids)| {
+            (a_ids.iter().min(), a).cmp(&(b_ids.iter().min(), b))
+        });
+        tokens
+    }
+
+    /// Returns the token of the token id, unless it's not in the vocabulary, e.g. the
+    /// end of sentence token.
+    pub fn token(&self, token_id: TokenId) -> Option<&Token> {
+        self.tokens
+            .iter()
+            .find(|(_, token_ids)| token_ids.contains(&token_id))
+            .map(|(token, _)| token)
+    }
+
+    /// Returns how tokens of the tokenizer were turned into bytes, unless the vocabulary
+    /// was created manually.
+    pub fn normalization(&self) -> Option<&TokenNormalization> {
+        self.normalization.as_ref()
+    }
+
+    /// Explains how a token, as listed by the tokenizer (e.g., `Ġal`, `▁O` or `<0x20>`),
+    /// is turned into bytes and which ids they have in the vocabulary, e.g. to find out
+    /// why an expected token id is missing from a mask.
+    ///
+    /// Tokens of manually created vocabularies are kept as is.
+    #[cfg(feature = "hugginface-hub")]
+    pub fn explain(&self, token: &str) -> TokenExplanation {
+        let steps = match &self.normalization {
+            Some(normalization) => TokenProcessor::from(normalization).explain(token),
+            None => vec![ProcessingStep {
+                input: token.to_string(),
+                output: Some(token.as_bytes().to_vec()),
+                rule: ProcessingRule::Utf8,
+            }],
+        };
+        let bytes: Option<Token> = steps
+            .iter()
+            .map(|step| step.output.as_deref())
+            .collect::<Option<Vec<_>>>()
+            .map(|parts| parts.concat());
+        let token_ids = bytes
+            .as_ref()
+            .and_then(|bytes| self.token_ids(bytes))
+            .cloned()
+            .unwrap_or_default();
+        TokenExplanation {
+            token: token.to_string(),
+            steps,
+            bytes,
+            token_ids,
+        }
+    }
+
     /// Returns all token ids per provided token if available in the vocabulary.
     pub fn token_ids(&self, token: impl AsRef<[u8]>) -> Option<&Vec<TokenId>> {
         self.tokens.get(token.as_ref())
@@ -145,13 +517,50 @@ impl Vocabulary {
         self.eos_token_id
     }
 
+    /// Gets the identifiers of the special tokens, which end the generation like the end of
+    /// the sentence token.
+    pub fn stop_token_ids(&self) -> &[TokenId] {
+        &self.stop_token_ids
+    }
+
+    /// Checks if the identifier is the one of the end of the sentence token or of a stop token.
+    pub fn is_stop_token(&self, id: TokenId) -> bool {
+        id == self.eos_token_id || self.stop_token_ids.contains(&id)
+    }
+
+    /// Inserts a special token, which ends the generation like the end of the sentence token.
+    ///
+    /// Stop tokens are allowed in final states only and are never matched as text.
+    pub fn try_insert_stop_token(&mut self, id: impl Into<TokenId>) -> Result<(), Error> {
+        let id = id.into();
+        if id == self.eos_token_id {
+            return Err(Error::EOSTokenDisallowed);
+        }
+        if self.tokens.values().any(|ids| ids.contains(&id)) {
+            return Err(Error::StopTokenIsText(id));
+        }
+        if !self.stop_token_ids.contains(&id) {
+            self.stop_token_ids.push(id);
+        }
+        Ok(())
+    }
+
     /// Inserts a token to the vocabulary with the specified identifier.
-    pub fn try_insert(&mut self, token: impl Into<Token>, id: TokenId) -> Result<(), Error> {
+    ///
+    /// The identifier is added to the ones of the token, unless it's already listed.
+    pub fn try_insert(
+        &mut self,
+        token: impl Into<Token>,
+        id: impl Into<TokenId>,
+    ) -> Result<(), Error> {
+        let id = id.into();
         if id == self.eos_token_id {
             return Err(Error::EOSTokenDisallowed);
         }
-        let token = token.into();
-        self.tokens.entry(token).or_default().push(id);
+        let ids = self.tokens.entry(token.into()).or_default();
+        if !ids.contains(&id) {
+            ids.push(id);
+        }
         Ok(())
     }
 
@@ -162,14 +571,53 @@ impl Vocabulary {
     }
 
     pub fn len(&self) -> usize {
-        // +1 for eos_token_id which is not in self.tokens map.
-        self.tokens.values().map(|ids| ids.len()).sum::<usize>() + 1
+        // +1 for eos_token_id which is not in self.tokens map, as stop tokens.
+        self.tokens.values().map(|ids| ids.len()).sum::<usize>() + self.stop_token_ids.len() + 1
+    }
+
+    /// Returns the number of token ids, which masks of the vocabulary cover, the greatest
+    /// token id plus one if it exceeds [`len`](Self::len), e.g. once the ids of skipped
+    /// special tokens are reserved.
+    pub fn vocab_size(&self) -> usize {
+        let ids = self.tokens.values().flatten().chain(&self.stop_token_ids);
+        let max_id = ids.fold(self.eos_token_id, |max, &id| max.max(id));
+        self.len().max(max_id.as_usize() + 1)
     }
 
     pub fn is_empty(&self) -> bool {
         self.tokens.is_empty()
     }
 
+    /// Returns a stable hash of the vocabulary content.
+    ///
+    /// Equal vocabularies share the fingerprint, however their tokens were normalized. It's
+    /// computed on each call, with 64-bit FNV-1a like [`Index::fingerprint`].
+    pub fn fingerprint(&self) -> u64 {
+        let mut hash: u64 = 0xcbf29ce484222325;
+        let mut write = |bytes: &[u8]| {
+            for byte in bytes {
+                hash ^= *byte as u64;
+                hash = hash.wrapping_mul(0x100000001b3);
+            }
+        };
+        write(&self.eos_token_id.as_u32().to_le_bytes());
+        let mut tokens: Vec<(&Token, &Vec<TokenId>)> = self.tokens.iter().collect();
+        tokens.sort_unstable();
+        write(&(tokens.len() as u64).to_le_bytes());
+        for (token, ids) in tokens {
+            write(&(token.len() as u64).to_le_bytes());
+            write(token);
+            write(&(ids.len() as u64).to_le_bytes());
+            for id in ids {
+                write(&id.as_u32().to_le_bytes());
+            }
+        }
+        for id in &self.stop_token_ids {
+            write(&id.as_u32().to_le_bytes());
+        }
+        hash
+    }
+
     /// Filters out `Prepend` kind of tokenizer's normalizers.
     #[cfg(feature = "hugginface-hub")]
     fn filter_prepend_normalizers(tokenizer: &mut Tokenizer) {
@@ -206,35 +654,49 @@ impl std::fmt::Display for Vocabulary {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         writeln!(
             f,
-            "Vocabulary object with eos_token_id={:?} and the following tokens to token_ids:",
-            self.eos_token_id
+            "Vocabulary object with eos_token_id={:?} and {} tokens:",
+            self.eos_token_id,
+            self.tokens.len()
         )?;
-        for (token, token_ids) in self.tokens.iter() {
-            writeln!(
-                f,
-                "{:?} -> {:?}",
-                token
-                    .iter()
-                    .map(|b| format!("0x{:02X}", b))
-                    .collect::<Vec<_>>(),
-                token_ids
-            )?;
+        for (token, token_ids) in self.tokens_by_id().into_iter().take(SUMMARY_ENTRIES) {
+            writeln!(f, "{:?} -> {:?}", String::from_utf8_lossy(token), token_ids)?;
+        }
+        if self.tokens.len() > SUMMARY_ENTRIES {
+            writeln!(f, "... {} more tokens", self.tokens.len() - SUMMARY_ENTRIES)?;
         }
         Ok(())
     }
 }
 
+impl std::fmt::Debug for Vocabulary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Vocabulary")
+            .field("eos_token_id", &self.eos_token_id)
+            .field("tokens", &self.tokens.len())
+            .field("stop_token_ids", &self.stop_token_ids)
+            .field("normalization", &self.normalization)
+            .finish()
+    }
+}
+
 impl TryFrom<(TokenId, HashMap<Token, Vec<TokenId>>)> for Vocabulary {
     type Error = Error;
 
     fn try_from(values: (TokenId, HashMap<Token, Vec<TokenId>>)) -> Result<Self, Self::Error> {
-        let (eos_token_id, tokens) = values;
+        let (eos_token_id, mut tokens) = values;
         if tokens.iter().any(|(_, ids)| ids.contains(&eos_token_id)) {
             return Err(Error::EOSTokenDisallowed);
         }
+        // Repeated ids of a token are listed once, as by `try_insert`.
+        for ids in tokens.values_mut() {
+            let mut seen = HashSet::default();
+            ids.retain(|id| seen.insert(*id));
+        }
         Ok(Vocabulary {
             eos_token_id,
             tokens,
+            stop_token_ids: Vec::new(),
+            normalization: None,
         })
     }
 }
@@ -244,19 +706,11 @@ impl TryFrom<(TokenId, HashMap<String, Vec<TokenId>>)> for Vocabulary {
 
     fn try_from(values: (TokenId, HashMap<String, Vec<TokenId>>)) -> Result<Self, Self::Error> {
         let (eos_token_id, tokens) = values;
-        Ok(Vocabulary {
-            eos_token_id,
-            tokens: tokens
-                .into_iter()
-                .map(|(k, v)| {
-                    if v.contains(&eos_token_id) {
-                        Err(Error::EOSTokenDisallowed)
-                    } else {
-                        Ok((k.as_bytes().to_vec(), v))
-                    }
-                })
-                .collect::<Result<HashMap<Token, Vec<TokenId>>, _>>()?,
-        })
+        let tokens: HashMap<Token, Vec<TokenId>> = tokens
+            .into_iter()
+            .map(|(k, v)| (k.into_bytes(), v))
+            .collect();
+        Vocabulary::try_from((eos_token_id, tokens))
     }
 }
 
@@ -280,24 +734,24 @@ mod tests {
 
         for (token, id) in [("zero", 0), ("one", 1), ("two", 2)] {
             vocabulary.try_insert(token, id).expect("Insert failed");
-            assert_eq!(vocabulary.token_ids(token), Some(&vec![id]));
+            assert_eq!(vocabulary.token_ids(token).expect("No token ids"), &[id]);
         }
         assert_eq!(vocabulary.tokens.len(), 3);
         assert_eq!(vocabulary.tokens().len(), 3);
 
         // Confirm different types.
         vocabulary.try_insert(b"four", 4).expect("Insert failed");
-        assert_eq!(vocabulary.token_ids("four"), Some(&vec![4]));
+        assert_eq!(vocabulary.token_ids("four").expect("No token ids"), &[4]);
 
         vocabulary
             .try_insert(b"five".to_vec(), 5)
             .expect("Insert failed");
-        assert_eq!(vocabulary.token_ids("five"), Some(&vec![5]));
+        assert_eq!(vocabulary.token_ids("five").expect("No token ids"), &[5]);
 
         vocabulary
             .try_insert("six".to_string(), 6)
             .expect("Insert failed");
-        assert_eq!(vocabulary.token_ids("six"), Some(&vec![6]));
+        assert_eq!(vocabulary.token_ids("six").expect("No token ids"), &[6]);
 
         vocabulary.remove(b"four");
         assert_eq!(vocabulary.token_ids("four"), None);
@@ -309,10 +763,170 @@ mod tests {
         assert_eq!(vocabulary.token_ids("six"), None);
     }
 
+    #[test]
+    fn repeated_token_ids() {
+        let mut vocabulary = Vocabulary::new(3);
+        for (token, token_id) in [("a", 0), ("a", 1), ("a", 0)] {
+            vocabulary
+                .try_insert(token, token_id)
+                .expect("Insert failed");
+        }
+        assert_eq!(vocabulary.token_ids("a").expect("No token ids"), &[0, 1]);
+        assert_eq!(vocabulary.len(), 3);
+
+        let map = HashMap::from_iter([("a".to_string(), [1, 0, 1].map(TokenId::new).to_vec())]);
+        let vocabulary = Vocabulary::try_from((TokenId::new(3), map)).expect("Vocabulary failed");
+        assert_eq!(vocabulary.token_ids("a").expect("No token ids"), &[1, 0]);
+    }
+
+    #[test]
+    fn fingerprint() {
+        let mut vocabulary = Vocabulary::new(3);
+        let mut other = Vocabulary::new(3);
+        for (token, token_id) in [("a", 0), ("b", 1), ("ab", 2)] {
+            vocabulary
+                .try_insert(token, token_id)
+                .expect("Insert failed");
+        }
+        for (token, token_id) in [("ab", 2), ("b", 1), ("a", 0)] {
+            other.try_insert(token, token_id).expect("Insert failed");
+        }
+        assert_eq!(vocabulary.fingerprint(), other.fingerprint());
+
+        other.try_insert_stop_token(4).expect("Insert failed");
+        assert_ne!(vocabulary.fingerprint(), other.fingerprint());
+        other = vocabulary.clone();
+        other.remove("ab");
+        other.try_insert("a", 2).expect("Insert failed");
+        assert_ne!(vocabulary.fingerprint(), other.fingerprint());
+    }
+
+    #[test]
+    fn stop_tokens() {
+        let mut vocabulary = Vocabulary::new(3);
+        vocabulary.try_insert("a", 0).expect("Insert failed");
+        vocabulary.try_insert_stop_token(4).expect("Insert failed");
+        vocabulary.try_insert_stop_token(4).expect("Insert failed");
+
+        assert_eq!(vocabulary.stop_token_ids(), &[4]);
+        assert!(
+            vocabulary.is_stop_token(TokenId::new(3)) && vocabulary.is_stop_token(TokenId::new(4))
+        );
+        assert!(!vocabulary.is_stop_token(TokenId::new(0)));
+        assert_eq!(vocabulary.token(TokenId::new(4)), None);
+        assert_eq!(vocabulary.len(), 3);
+
+        assert!(matches!(
+            vocabulary.try_insert_stop_token(3),
+            Err(Error::EOSTokenDisallowed)
+        ));
+        assert!(matches!(
+            vocabulary.try_insert_stop_token(0),
+            Err(Error::StopTokenIsText(token_id)) if token_id == 0
+        ));
+    }
+
+    #[test]
+    fn tokens_by_id() {
+        let mut vocabulary = Vocabulary::new(0);
+        for (token, token_id) in [("c", 4), ("b", 2), ("a", 3), ("c", 1)] {
+            vocabulary
+                .try_insert(token, token_id)
+                .expect("Insert failed");
+        }
+        let tokens: Vec<_> = vocabulary
+            .tokens_by_id()
+            .into_iter()
+            .map(|(token, token_ids)| (token.as_slice(), token_ids.to_vec()))
+            .collect();
+        assert_eq!(
+            tokens,
+            vec![
+                (&b"c"[..], vec![TokenId::new(4), TokenId::new(1)]),
+                (b"b", vec![TokenId::new(2)]),
+                (b"a", vec![TokenId::new(3)])
+            ]
+        );
+        assert_eq!(vocabulary.token(TokenId::new(4)), Some(&b"c".to_vec()));
+        assert_eq!(vocabulary.token(TokenId::new(0)), None);
+    }
+
+    #[test]
+    fn summarized_display() {
+        let mut vocabulary = Vocabulary::new(100);
+        for token_id in 1..20 {
+            vocabulary
+                .try_insert(format!("t{token_id}"), token_id)
+                .expect("Insert failed");
+        }
+        let summary = vocabulary.to_string();
+        let lines: Vec<_> = summary.lines().collect();
+        assert_eq!(
+            lines[0],
+            "Vocabulary object with eos_token_id=100 and 19 tokens:"
+        );
+        assert_eq!(lines[1], "\"t1\" -> [1]");
+        assert_eq!(lines.len(), SUMMARY_ENTRIES + 2);
+        assert_eq!(lines[SUMMARY_ENTRIES + 1], "... 11 more tokens");
+        assert!(format!("{vocabulary:?}").contains("tokens: 19"));
+        assert_eq!(vocabulary.dump_full().lines().count(), 20);
+    }
+
+    #[cfg(feature = "hugginface-hub")]
+    #[test]
+    fn explain_tokens() {
+        let mut vocabulary = Vocabulary::new(0);
+        vocabulary.try_insert(" al", 1).expect("Insert failed");
+        vocabulary.try_insert("Ġal", 2).expect("Insert failed");
+
+        let explanation = vocabulary.explain("Ġal");
+        assert_eq!(explanation.steps.len(), 1);
+        assert_eq!(explanation.bytes.as_deref(), Some("Ġal".as_bytes()));
+        assert_eq!(explanation.token_ids, vec![2]);
+
+        vocabulary.normalization = Some(TokenNormalization::ByteLevel);
+        let explanation = vocabulary.explain("Ġal");
+        assert_eq!(explanation.steps.len(), 3);
+        assert_eq!(explanation.bytes.as_deref(), Some(&b" al"[..]));
+        assert_eq!(explanation.token_ids, vec![1]);
+
+        let explanation = vocabulary.explain("▁al");
+        assert_eq!(explanation.bytes, None);
+        assert!(explanation.token_ids.is_empty());
+        assert_eq!(vocabulary, {
+            let mut manual = vocabulary.clone();
+            manual.normalization = None;
+            manual
+        });
+    }
+
+    #[test]
+    fn fixture() {
+        let vocabulary = Vocabulary::fixture();
+        assert_eq!(vocabulary, Vocabulary::fixture());
+        assert_eq!(
+            vocabulary.fingerprint(),
+            Vocabulary::fixture().fingerprint()
+        );
+        assert_eq!(vocabulary.len(), 112);
+        assert_eq!(vocabulary.eos_token_id(), 111);
+        assert_eq!(vocabulary.token_ids("\n").expect("No token ids"), &[0]);
+        assert_eq!(vocabulary.token_ids("~").expect("No token ids"), &[95]);
+        assert_eq!(vocabulary.token_ids("{\"").expect("No token ids"), &[96]);
+
+        let index = Index::new(r#"\{"[a-z]+":(true|false)\}"#, &vocabulary).expect("Index failed");
+        let mut state = index.initial_state();
+        for token in [r#"{""#, "a", "b", r#"":"#, "true", "}"] {
+            let token_id = vocabulary.token_ids(token).expect("No token")[0];
+            state = index.next_state(&state, &token_id).expect("No next state");
+        }
+        assert!(index.is_final_state(&state));
+    }
+
     #[test]
     fn new_empty_vocabulary_from_hashmap() {
         let map: HashMap<Token, Vec<TokenId>> = HashMap::default();
-        let vocabulary = Vocabulary::try_from((1_u32, map)).expect("Vocabulary failed");
+        let vocabulary = Vocabulary::try_from((TokenId::new(1), map)).expect("Vocabulary failed");
         assert_eq!(vocabulary.eos_token_id, 1);
         assert!(vocabulary.tokens.is_empty());
     }
@@ -356,7 +970,9 @@ mod tests {
         assert_eq!(v_eos, vocabulary.eos_token_id());
         assert_eq!(v_eos, 50256);
         assert_eq!(
-            tokenizer.id_to_token(v_eos).expect("Token not found"),
+            tokenizer
+                .id_to_token(v_eos.as_u32())
+                .expect("Token not found"),
             "<|endoftext|>"
         );
 
@@ -370,13 +986,113 @@ mod tests {
             assert!(v_ids.is_some());
             for v_id in v_ids.unwrap() {
                 let t_token = tokenizer
-                    .id_to_token(*v_id)
+                    .id_to_token(v_id.as_u32())
                     .expect("Token id not found in tokenizer");
                 assert_eq!(&t_token, t_token_expected);
             }
         }
     }
 
+    /// Byte level tokenizer like the one of GPT-2, with a few words and special tokens, so
+    /// that vocabularies are built from a tokenizer without downloading one.
+    #[cfg(feature = "hugginface-hub")]
+    fn byte_level_tokenizer() -> Tokenizer {
+        let special = |id: u32, content: &str| {
+            serde_json::json!({
+                "id": id, "content": content, "single_word": false, "lstrip": false,
+                "rstrip": false, "normalized": false, "special": true
+            })
+        };
+        let tokenizer = serde_json::json!({
+            "version": "1.0",
+            "truncation": null,
+            "padding": null,
+            "added_tokens": [special(0, "<s>"), special(1, "</s>"), special(2, "<|im_end|>")],
+            "normalizer": null,
+            "pre_tokenizer": null,
+            "post_processor": null,
+            "decoder": {
+                "type": "ByteLevel", "add_prefix_space": false, "trim_offsets": true,
+                "use_regex": true
+            },
+            "model": {
+                "type": "WordLevel",
+                "vocab": {
+                    "<s>": 0, "</s>": 1, "<|im_end|>": 2, "a": 3, "Ġb": 4, "ĠÃ©": 5
+                },
+                "unk_token": "<s>"
+            }
+        });
+        tokenizer.to_string().parse().expect("Tokenizer failed")
+    }
+
+    #[cfg(feature = "hugginface-hub")]
+    #[test]
+    fn pretrained_cached() {
+        // The cached vocabulary is loaded without reaching the hub.
+        let model = "org/model";
+        let cache_dir =
+            std::env::temp_dir().join(format!("outlines-core-pretrained-{}", std::process::id()));
+        let vocabulary = Vocabulary::test_vocab(TestVocabulary::Gpt2Mini);
+        cache::store(
+            &cache::cache_path(&cache_dir, model, "main", &[]),
+            &vocabulary,
+        )
+        .expect("Store failed");
+
+        let options = PretrainedOptions::default().with_cache_dir(&cache_dir);
+        let cached =
+            Vocabulary::from_pretrained_with_options(model, options).expect("Vocabulary failed");
+        assert_eq!(cached, vocabulary);
+        std::fs::remove_dir_all(cache_dir).expect("Removal failed");
+    }
+
+    #[cfg(feature = "hugginface-hub")]
+    #[test]
+    fn pretrained_with_progress() {
+        let tokenizer = byte_level_tokenizer();
+        let reports = std::sync::Mutex::new(Vec::new());
+        let vocabulary =
+            Vocabulary::from_tokenizer("model", &tokenizer, TokenId::new(1), &[], |done, total| {
+                reports.lock().expect("Lock failed").push((done, total))
+            })
+            .expect("Vocabulary failed");
+        assert_eq!(vocabulary.token_ids(" b"), Some(&vec![TokenId::new(4)]));
+        assert_eq!(vocabulary.token_ids(" é"), Some(&vec![TokenId::new(5)]));
+
+        // Every token of the tokenizer but the end-of-sequence one is reported, in chunks.
+        let reports = reports.into_inner().expect("Lock failed");
+        let total = reports[0].1;
+        assert_eq!(total, 5);
+        assert_eq!(reports.len(), total.div_ceil(PROGRESS_CHUNK));
+        assert!(reports.iter().all(|&(done, t)| t == total && done <= total));
+        assert_eq!(reports.iter().map(|&(done, _)| done).max(), Some(total));
+    }
+
+    #[cfg(feature = "hugginface-hub")]
+    #[test]
+    fn pretrained_with_stop_tokens() {
+        let tokenizer = byte_level_tokenizer();
+        let eos_token_id = TokenId::new(1);
+        let stop_tokens = [
+            SpecialToken::from("<|im_end|>"),
+            SpecialToken::Id(eos_token_id),
+        ];
+        let vocabulary =
+            Vocabulary::from_tokenizer("model", &tokenizer, eos_token_id, &stop_tokens, |_, _| {})
+                .expect("Vocabulary failed");
+        // The end-of-sequence token `</s>` is already a terminator.
+        assert_eq!(vocabulary.stop_token_ids(), &[2]);
+        assert_eq!(vocabulary.token(TokenId::new(2)), None);
+
+        let stop_tokens = ["<|unknown|>".into()];
+        match Vocabulary::from_tokenizer("model", &tokenizer, eos_token_id, &stop_tokens, |_, _| {})
+        {
+            Err(Error::SpecialTokenNotFound { token, .. }) => assert_eq!(token, "\"<|unknown|>\""),
+            _ => unreachable!(),
+        }
+    }
+
     #[cfg(feature = "hugginface-hub")]
     #[test]
     fn pretrained_from_llama() {
@@ -390,7 +1106,9 @@ mod tests {
         assert_eq!(v_eos, vocabulary.eos_token_id());
         assert_eq!(v_eos, 2);
         assert_eq!(
-            tokenizer.id_to_token(v_eos).expect("Token not found"),
+            tokenizer
+                .id_to_token(v_eos.as_u32())
+                .expect("Token not found"),
             "</s>"
         );
 
@@ -413,7 +1131,7 @@ mod tests {
                 .iter()
                 .map(|v_id| {
                     tokenizer
-                        .id_to_token(*v_id)
+                        .id_to_token(v_id.as_u32())
                         .expect("Token id not found in tokenizer")
                 })
                 .collect::<HashSet<String>>();
@@ -466,7 +1184,8 @@ mod tests {
     #[test]
     fn unable_to_locate_eos_token_id_error() {
         let model = "hf-internal-testing/tiny-random-XLMRobertaXLForCausalLM";
-        let vocabulary = Vocabulary::from_pretrained_with_locator::<NoneLocator>(model, None);
+        let vocabulary =
+            Vocabulary::from_pretrained_with_locator::<NoneLocator>(model, None, &[], |_, _| {});
 
         match vocabulary {
             Err(Error::UnsupportedTokenizer { model, reason }) => {
@@ -0,0 +1,160 @@
+//! Small vocabularies bundled with the crate, see [`Vocabulary::test_vocab`].
+
+use super::{TokenNormalization, Vocabulary};
+use crate::prelude::*;
+
+/// Multi-character tokens of `ascii-json` after its characters.
+const ASCII_JSON: &[&str] = &[
+    "{\"", "\":", "\",", "\"}", "\":\"", "[\"", "\"]", ", ", "true", "false", "null", "  ", "ing",
+    "the", "er",
+];
+
+/// Tokens of `gpt2-mini` after its byte tokens, in the notation of its tokenizer.
+static GPT2_MINI: &str = include_str!("presets/gpt2-mini.txt");
+
+/// Tokens of `llama-mini` after its byte tokens, in the notation of its tokenizer.
+static LLAMA_MINI: &str = include_str!("presets/llama-mini.txt");
+
+/// Preset of [`Vocabulary::test_vocab`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TestVocabulary {
+    /// The printable ASCII characters and newline, with token ids from 0 in byte order,
+    /// followed by a few multi-character tokens of JSON, like `{"` or `true`, then the
+    /// end-of-sequence token, see [`Vocabulary::fixture`].
+    AsciiJson,
+    /// Byte level vocabulary like the one of GPT-2: a token for each byte, in the order of
+    /// GPT-2, common words and pieces of JSON, then the end-of-sequence token.
+    Gpt2Mini,
+    /// Byte fallback vocabulary like the one of Llama: end-of-sequence token 2, a `<0x__>`
+    /// token for each byte, then words, which start with `▁` for spaces.
+    ///
+    /// Like the vocabularies of [`Vocabulary::from_pretrained`], it skips the special tokens
+    /// `<unk>` and `<s>`, their token ids 0 and 1 being reserved, so that no text spells them.
+    LlamaMini,
+}
+
+impl Vocabulary {
+    /// Creates a small vocabulary of the preset, which is the same on every call, so that
+    /// indexes can be tested without downloading tokenizers from the Hugging Face Hub.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use outlines_core::prelude::*;
+    /// use outlines_core::vocabulary::TestVocabulary;
+    ///
+    /// let vocabulary = Vocabulary::test_vocab(TestVocabulary::Gpt2Mini);
+    /// assert!(vocabulary.token_ids(" the").is_some());
+    /// let index = Index::new(r#"\{"name": "[a-z]+"\}"#, &vocabulary).expect("Index failed");
+    /// ```
+    pub fn test_vocab(kind: TestVocabulary) -> Self {
+        match kind {
+            TestVocabulary::AsciiJson => {
+                let bytes = std::iter::once(b'\n').chain(b' '..=b'~');
+                let tokens: Vec<Token> = bytes
+                    .map(|byte| vec![byte])
+                    .chain(ASCII_JSON.iter().map(|word| word.as_bytes().to_vec()))
+                    .collect();
+                let mut vocabulary = Self::new(tokens.len() as u32);
+                vocabulary.insert_in_order(tokens, 0);
+                vocabulary
+            }
+            TestVocabulary::Gpt2Mini => {
+                // GPT-2 lists the bytes, which it maps to themselves, first.
+                let printable = |byte: u8| matches!(byte, b'!'..=b'~' | 0xA1..=0xAC | 0xAE..=0xFF);
+                let bytes = (0..=255u8)
+                    .filter(|&byte| printable(byte))
+                    .chain((0..=255u8).filter(|&byte| !printable(byte)))
+                    .map(|byte| vec![byte]);
+                let words = GPT2_MINI
+                    .lines()
+                    .map(|word| word.replace('Ġ', " ").replace('Ċ', "\n").into_bytes());
+                let tokens: Vec<Token> = bytes.chain(words).collect();
+                let mut vocabulary = Self::new(tokens.len() as u32);
+                vocabulary.insert_in_order(tokens, 0);
+                vocabulary.normalization = Some(TokenNormalization::ByteLevel);
+                vocabulary
+            }
+            TestVocabulary::LlamaMini => {
+                let mut vocabulary = Self::new(2);
+                let bytes = (0..=255u8).map(|byte| vec![byte]);
+                let words = LLAMA_MINI
+                    .lines()
+                    .map(|word| word.replace('▁', " ").into_bytes());
+                vocabulary.insert_in_order(bytes.chain(words), 3);
+                vocabulary.normalization = Some(TokenNormalization::ByteFallback {
+                    space: "▁".to_string(),
+                });
+                vocabulary
+            }
+        }
+    }
+
+    /// Inserts the tokens with consecutive token ids from `first_id`.
+    fn insert_in_order(&mut self, tokens: impl IntoIterator<Item = Token>, first_id: u32) {
+        for (token_id, token) in (first_id..).zip(tokens) {
+            let ids = self.tokens.entry(token).or_default();
+            ids.push(TokenId::new(token_id));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presets() {
+        for (kind, len, vocab_size, eos_token_id) in [
+            (TestVocabulary::AsciiJson, 112, 112, 111),
+            (TestVocabulary::Gpt2Mini, 402, 402, 401),
+            (TestVocabulary::LlamaMini, 393, 395, 2),
+        ] {
+            let vocabulary = Vocabulary::test_vocab(kind);
+            assert_eq!(vocabulary, Vocabulary::test_vocab(kind), "{kind:?}");
+            assert_eq!(vocabulary.len(), len, "{kind:?}");
+            assert_eq!(vocabulary.vocab_size(), vocab_size, "{kind:?}");
+            assert_eq!(vocabulary.eos_token_id(), eos_token_id, "{kind:?}");
+            // Token ids are consecutive after the reserved ones, so that masks cover all of them.
+            let mut token_ids: Vec<TokenId> =
+                vocabulary.tokens().values().flatten().copied().collect();
+            token_ids.push(TokenId::new(eos_token_id));
+            token_ids.sort_unstable();
+            let reserved = (vocab_size - len) as u32;
+            assert_eq!(
+                token_ids,
+                (reserved..vocab_size as u32).collect::<Vec<_>>(),
+                "{kind:?}"
+            );
+
+            let index = Index::new(r#"\{"name": "[a-z ]+"\}"#, &vocabulary).expect("Index failed");
+            assert!(!index.final_states().is_empty());
+        }
+        assert_eq!(
+            Vocabulary::fixture(),
+            Vocabulary::test_vocab(TestVocabulary::AsciiJson)
+        );
+
+        let gpt2 = Vocabulary::test_vocab(TestVocabulary::Gpt2Mini);
+        assert_eq!(gpt2.token_ids("!").expect("No token ids"), &[0]);
+        assert_eq!(gpt2.token_ids(" the").expect("No token ids"), &[256]);
+        assert_eq!(gpt2.token_ids("\n\n").map(Vec::len), Some(1));
+
+        // Spaces have a byte token and `▁`.
+        let llama = Vocabulary::test_vocab(TestVocabulary::LlamaMini);
+        assert_eq!(llama.token_ids(" ").expect("No token ids"), &[35, 381]);
+        // Special tokens aren't text, but their ids are covered by masks.
+        assert_eq!(llama.token_ids("<s>"), None);
+        let index = Index::new("<[a-z]>", &llama).expect("Index failed");
+        let mut state = index.initial_state();
+        for token in ["<", "s", ">"] {
+            let token_id = llama.token_ids(token).expect("No token ids")[0];
+            state = index.next_state(&state, &token_id).expect("No next state");
+        }
+        assert!(index.is_final_state(&state));
+        assert!(!index.transitions().values().any(|map| map
+            .keys()
+            .any(|token_id| *token_id == TokenId::new(0) || *token_id == TokenId::new(1))));
+        assert_eq!(index.vocab_size(), 395);
+    }
+}
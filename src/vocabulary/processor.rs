@@ -8,6 +8,7 @@ use serde::Deserialize;
 use tokenizers::normalizers::Replace;
 use tokenizers::{DecoderWrapper, Tokenizer};
 
+use super::{ProcessingRule, ProcessingStep, TokenNormalization};
 use crate::{Error, Result};
 
 /// Map to reverse certain UTF-8 characters back to bytes.
@@ -203,7 +204,7 @@ impl TokenProcessor {
                 .collect(),
             TokenProcessorLevel::ByteFallback(mods) => {
                 // If the token is of form `<0x__>`:
-                if token.len() == 6 && token.starts_with("<0x") && token.ends_with('>') {
+                if Self::is_byte_token(token) {
                     // Get to a single byte specified in the __ part and parse it in base 16 to a byte.
                     match u8::from_str_radix(&token[3..5], 16) {
                         Ok(byte) => Ok([byte].to_vec()),
@@ -216,6 +217,66 @@ impl TokenProcessor {
         }
     }
 
+    /// Whether the token is of form `<0x__>`.
+    fn is_byte_token(token: &str) -> bool {
+        token.len() == 6 && token.starts_with("<0x") && token.ends_with('>')
+    }
+
+    /// Returns how the processor turns tokens into bytes.
+    pub(crate) fn normalization(&self) -> TokenNormalization {
+        match &self.level {
+            TokenProcessorLevel::Byte => TokenNormalization::ByteLevel,
+            TokenProcessorLevel::ByteFallback(mods) => TokenNormalization::ByteFallback {
+                space: mods.spacechar.clone(),
+            },
+        }
+    }
+
+    /// Lists the steps of the processing of the token, their outputs make up the result of
+    /// [`TokenProcessor::process`].
+    pub(crate) fn explain(&self, token: &str) -> Vec<ProcessingStep> {
+        let step = |input: &str, output: Option<Vec<u8>>, rule| ProcessingStep {
+            input: input.to_string(),
+            output,
+            rule,
+        };
+        match &self.level {
+            TokenProcessorLevel::Byte => token
+                .chars()
+                .map(|char| {
+                    let byte = CHAR_MAP.get(&char).map(|&byte| vec![byte]);
+                    step(
+                        char.encode_utf8(&mut [0; 4]),
+                        byte,
+                        ProcessingRule::ByteLevel,
+                    )
+                })
+                .collect(),
+            TokenProcessorLevel::ByteFallback(_) if Self::is_byte_token(token) => {
+                let byte = self.process(token).ok();
+                vec![step(token, byte, ProcessingRule::ByteFallback)]
+            }
+            TokenProcessorLevel::ByteFallback(mods) => {
+                let mut steps = Vec::new();
+                for (i, part) in token.split(mods.spacechar.as_str()).enumerate() {
+                    if i > 0 {
+                        let space = Some(b" ".to_vec());
+                        steps.push(step(
+                            &mods.spacechar,
+                            space,
+                            ProcessingRule::SpaceReplacement,
+                        ));
+                    }
+                    if !part.is_empty() {
+                        let bytes = Some(part.as_bytes().to_vec());
+                        steps.push(step(part, bytes, ProcessingRule::Utf8));
+                    }
+                }
+                steps
+            }
+        }
+    }
+
     /// Since all fields of HF's `Replace` are private with no getters, it needs to be unpacked
     /// into local `ReplaceDecoder` structure.
     #[cfg(not(tarpaulin_include))]
@@ -230,6 +291,18 @@ impl TokenProcessor {
     }
 }
 
+impl From<&TokenNormalization> for TokenProcessor {
+    fn from(normalization: &TokenNormalization) -> Self {
+        let level = match normalization {
+            TokenNormalization::ByteLevel => TokenProcessorLevel::Byte,
+            TokenNormalization::ByteFallback { space } => TokenProcessorLevel::ByteFallback(Mods {
+                spacechar: space.clone(),
+            }),
+        };
+        Self { level }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -311,6 +384,64 @@ mod tests {
         }
     }
 
+    #[test]
+    fn explain_processing() {
+        let byte_level = TokenProcessor::from(&TokenNormalization::ByteLevel);
+        let steps = byte_level.explain("Ġa");
+        assert_eq!(
+            steps,
+            vec![
+                ProcessingStep {
+                    input: "Ġ".to_string(),
+                    output: Some(vec![0x20]),
+                    rule: ProcessingRule::ByteLevel,
+                },
+                ProcessingStep {
+                    input: "a".to_string(),
+                    output: Some(vec![0x61]),
+                    rule: ProcessingRule::ByteLevel,
+                },
+            ]
+        );
+        assert_eq!(byte_level.explain("▁")[0].output, None);
+
+        let byte_fallback = TokenProcessor::from(&TokenNormalization::ByteFallback {
+            space: "▁".to_string(),
+        });
+        let rules = |token| -> Vec<ProcessingRule> {
+            byte_fallback
+                .explain(token)
+                .into_iter()
+                .map(|step| step.rule)
+                .collect()
+        };
+        assert_eq!(rules("<0x20>"), vec![ProcessingRule::ByteFallback]);
+        assert_eq!(
+            rules("▁▁ab▁"),
+            vec![
+                ProcessingRule::SpaceReplacement,
+                ProcessingRule::SpaceReplacement,
+                ProcessingRule::Utf8,
+                ProcessingRule::SpaceReplacement,
+            ]
+        );
+        assert_eq!(rules("<0x61>a"), vec![ProcessingRule::Utf8]);
+
+        for (processor, token) in [
+            (&byte_level, "ĠalĊ"),
+            (&byte_fallback, "<0xE2>"),
+            (&byte_fallback, "▁O▁▁"),
+            (&byte_fallback, "<0x61>a"),
+        ] {
+            let bytes: Vec<u8> = processor
+                .explain(token)
+                .into_iter()
+                .flat_map(|step| step.output.expect("Not processed"))
+                .collect();
+            assert_eq!(bytes, processor.process(token).expect("Not processed"));
+        }
+    }
+
     #[test]
     fn unsupported_tokenizer_error() {
         let model = "hf-internal-testing/tiny-random-XLMRobertaXLForCausalLM";
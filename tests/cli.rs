@@ -0,0 +1,46 @@
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+fn run(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_outlines-core"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Spawn failed");
+    child
+        .stdin
+        .take()
+        .expect("No stdin")
+        .write_all(stdin.as_bytes())
+        .expect("Write failed");
+    child.wait_with_output().expect("Wait failed")
+}
+
+#[test]
+fn validate_from_stdin() {
+    let schema =
+        r#"{"type": "object", "properties": {"a": {"type": "integer"}}, "required": ["a"]}"#;
+
+    let output = run(&["validate"], &format!("{schema}\n{{\"a\":1}}\n"));
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "Valid");
+
+    let output = run(&["validate"], &format!("{schema}\n{{\"a\":\"1\"}}\n"));
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("doesn't match"));
+
+    // Only the schema is given, so the input is missing rather than read twice.
+    let output = run(&["validate"], schema);
+    assert!(!output.status.success());
+
+    let path = std::env::temp_dir().join(format!("outlines-core-cli-{}.json", std::process::id()));
+    std::fs::write(&path, schema).expect("Write failed");
+    let output = run(
+        &["validate", "--schema", path.to_str().expect("Path")],
+        "{\"a\":2}",
+    );
+    std::fs::remove_file(&path).expect("Remove failed");
+    assert!(output.status.success(), "{output:?}");
+}
@@ -4,7 +4,7 @@ from typing import Dict, List, Union
 
 import pytest
 
-from outlines_core import Guide, Index, Vocabulary
+from outlines_core import Guide, Index, Vocabulary, mask_to_list
 
 
 @pytest.fixture(scope="session")
@@ -136,6 +136,15 @@ def test_pickling_from_pretrained_with_revision(model, revision):
     assert sorted(deserialized.get_tokens()) == sorted(guide.get_tokens())
 
 
+def test_unpickling_unversioned_data():
+    # Guide pickled before the format was versioned, in the initial state of its index.
+    guide = Guide.from_binary(b"\x00" + b"\x00\x01\x01\x02\x00\x01\x01\x01\x01\x01\x02\x01\x02\x03" + b"\x00")
+    assert guide.get_state() == 0
+    assert guide.get_tokens() == [1]
+    guide.advance(1)
+    assert guide.is_finished()
+
+
 def test_equality(index):
     guide1 = Guide(index)
     guide2 = Guide(index)
@@ -168,6 +177,33 @@ def test_write_mask_into(index):
     ), f"mask and expected mask do not match. Mask: {mask[0]}, Expected: {expected_mask}"
 
 
+def test_write_mask_into_strided(index):
+    import torch
+
+    guide = Guide(index)
+    expected_mask = 0
+    for token in guide.get_tokens():
+        expected_mask |= 1 << (token % 32)
+
+    mask = torch.full((1, 4), -1, dtype=torch.int32)
+    view = mask[:, ::2]
+    guide.write_mask_into(
+        view.data_ptr(), view.numel(), view.element_size(), stride=view.stride(1)
+    )
+    assert mask.tolist() == [[expected_mask, -1, 0, -1]]
+
+    mask = torch.full((1, 4), -1, dtype=torch.int32)
+    view = mask[:, ::2]
+    guide.write_mask_into(
+        view.data_ptr(),
+        view.numel(),
+        view.element_size(),
+        stride=view.stride(1),
+        zero_tail=False,
+    )
+    assert mask.tolist() == [[expected_mask, -1, -1, -1]]
+
+
 def test_write_mask_into_interface(index):
     import torch
 
@@ -182,6 +218,43 @@ def test_write_mask_into_interface(index):
         guide.write_mask_into(0, mask.numel(), mask.element_size())
     with pytest.raises(ValueError, match="Invalid data pointer alignment"):
         guide.write_mask_into(5, mask.numel(), mask.element_size())
+    with pytest.raises(ValueError, match="Invalid stride"):
+        guide.write_mask_into(mask.data_ptr(), mask.numel(), mask.element_size(), 0)
+
+
+def test_hash_and_copy(index):
+    guide = Guide(index)
+    guide2 = copy.copy(guide)
+    assert guide2 == guide
+    with pytest.raises(TypeError, match="unhashable"):
+        hash(guide)
+
+    # Copies advance independently.
+    guide2.advance(guide2.get_tokens()[0])
+    assert guide2 != guide
+
+    guide3 = copy.deepcopy(guide2)
+    assert guide3 == guide2
+
+
+def test_mask_to_list(index):
+    from array import array
+
+    guide = Guide(index)
+    mask = array("i", [0, -1, 1 << 3])
+    mask_ptr, numel = mask.buffer_info()
+    assert mask_to_list(mask_ptr, numel) == list(range(32, 64)) + [67]
+    assert mask_to_list(mask_ptr, 2, stride=2) == [35]
+
+    mask = array("i", [-1] * 2)
+    mask_ptr, numel = mask.buffer_info()
+    guide.write_mask_into(mask_ptr, numel, mask.itemsize)
+    assert mask_to_list(mask_ptr, numel) == sorted(guide.get_tokens())
+
+    with pytest.raises(ValueError, match="Invalid element size"):
+        mask_to_list(mask_ptr, numel, 8)
+    with pytest.raises(ValueError, match="null pointer"):
+        mask_to_list(0, numel)
 
 
 def test_rollback(index):
@@ -217,3 +290,261 @@ def test_rollback_interface(index):
 def test_accepts_tokens_correctness(index, seq, expected):
     guide = Guide(index)
     assert guide.accepts_tokens(seq) is expected
+
+
+def test_export_state_and_resume(index):
+    guide = Guide(index)
+    guide.advance(1)
+
+    snapshot = guide.export_state()
+    assert snapshot["state"] == guide.get_state()
+    assert snapshot["consumed_tokens"] == 1
+
+    resumed = Guide.resume(index, snapshot)
+    assert resumed.get_state() == guide.get_state()
+    assert resumed.is_finished()
+
+    other_index = Index(r"[1-2]{2}", Vocabulary(3, {"1": [1], "2": [2]}))
+    with pytest.raises(ValueError, match="Index fingerprint mismatch"):
+        Guide.resume(other_index, snapshot)
+
+
+def test_finish_on_eos(index):
+    guide = Guide(index, finish_on="eos")
+    guide.advance(1)
+    assert not guide.is_finished()
+    assert index.is_accepting(guide.get_state())
+    assert index.is_dead_end_without_eos(guide.get_state())
+    assert not index.is_accepting(index.get_initial_state())
+
+    assert guide.advance(3) == [3]
+    assert guide.is_finished()
+    with pytest.raises(ValueError, match="No next state found"):
+        guide.advance(2)
+
+    snapshot = guide.export_state()
+    assert snapshot["finish_on"] == "eos"
+    assert Guide.resume(index, snapshot).is_finished()
+
+    guide.rollback_state(1)
+    assert not guide.is_finished()
+
+    with pytest.raises(ValueError, match="Invalid finish policy"):
+        Guide(index, finish_on="never")
+
+
+def test_duplicate_tokens_allowed_symmetrically():
+    vocabulary = Vocabulary(3, {"1": [1, 4, 1], "2": [2], "12": [5, 6]})
+    assert vocabulary.get("1") == [1, 4]
+    index = Index(r"[12]{2}", vocabulary)
+
+    guide = Guide(index)
+    assert sorted(guide.get_tokens()) == [1, 2, 4, 5, 6]
+    for token_ids in ([1, 4], [5, 6]):
+        states = {index.get_next_state(guide.get_state(), t) for t in token_ids}
+        assert len(states) == 1
+    assert sorted(guide.advance(4)) == [1, 2, 4]
+
+
+def test_stop_tokens():
+    vocabulary = Vocabulary(3, {"1": [1], "2": [2]})
+    vocabulary.insert_stop_token(4)
+    index = Index(r"[1-9]", vocabulary)
+
+    guide = Guide(index, finish_on="eos")
+    assert 4 not in guide.get_tokens()
+    assert sorted(guide.advance(1)) == [3, 4]
+    assert guide.advance(4) == [3]
+    assert guide.is_finished()
+
+
+def test_max_tokens():
+    vocabulary = Vocabulary(4, {'"': [1], "a": [2], 'a"': [3]})
+    index = Index(r'"a*"', vocabulary)
+
+    guide = Guide(index, max_tokens=3)
+    assert guide.get_remaining_tokens() == 3
+    assert guide.get_min_tokens_to_finish() == 2
+    guide.advance(1)
+    # The last token must close the string.
+    assert sorted(guide.advance(2)) == [1, 3]
+    assert guide.get_remaining_tokens() == 1
+
+    snapshot = guide.export_state()
+    assert snapshot["max_tokens"] == 3
+    assert Guide.resume(index, snapshot).get_remaining_tokens() == 1
+
+    assert Guide(index).get_remaining_tokens() is None
+    with pytest.raises(ValueError, match="budget of 1"):
+        Guide(index, max_tokens=1).get_tokens()
+
+
+def test_max_violations():
+    vocabulary = Vocabulary(4, {'"': [1], "a": [2], 'a"': [3]})
+    index = Index(r'"a*"', vocabulary)
+
+    guide = Guide(index, max_violations=1, record_history=True)
+    assert guide.get_remaining_violations() == 1
+    # The opening quote is missing, the guide goes on from the initial state.
+    assert guide.advance(2) == [1]
+    assert guide.get_violations() == [0]
+    with pytest.raises(ValueError, match="No next state found"):
+        guide.advance(2)
+
+    guide.advance(1)
+    guide.advance(3)
+    assert guide.is_finished()
+    assert guide.get_text(vocabulary) == 'a"a"'
+
+    snapshot = guide.export_state()
+    assert snapshot["violations"] == [0]
+    assert Guide.resume(index, snapshot).get_remaining_violations() == 0
+
+    assert not Guide(index).accepts_tokens([2, 1, 3])
+
+
+def test_token_events():
+    vocabulary = Vocabulary(4, {'"': [1], "a": [2], 'a"': [3]})
+    index = Index(r'"a*"', vocabulary)
+
+    guide = Guide(index, finish_on="eos", record_events=True)
+    for token_id in [1, 3, 4]:
+        guide.advance(token_id, return_tokens=False)
+    events = [
+        {"token_id": 1, "kind": "forced"},
+        {"token_id": 3, "kind": "chosen", "options": 3},
+        {"token_id": 4, "kind": "eos"},
+    ]
+    assert guide.get_token_events() == events
+
+    snapshot = guide.export_state()
+    assert Guide.resume(index, snapshot).get_token_events() == events
+    guide.rollback_state(1)
+    assert guide.get_token_events() == events[:2]
+    assert Guide(index).get_token_events() is None
+
+
+def test_mask_cache():
+    import torch
+
+    vocabulary = Vocabulary(4, {'"': [1], "a": [2], 'a"': [3]})
+    index = Index(r'"a*"', vocabulary)
+    guide = Guide(index, mask_cache=4)
+    uncached = Guide(index)
+
+    for token_id in [1, 2, 2]:
+        guide.advance(token_id, return_tokens=False)
+        uncached.advance(token_id, return_tokens=False)
+        assert sorted(guide.get_tokens()) == sorted(uncached.get_tokens())
+
+        mask = torch.full((1, 2), -1, dtype=torch.int32)
+        expected = torch.full((1, 2), -1, dtype=torch.int32)
+        guide.write_mask_into(mask.data_ptr(), mask.numel(), mask.element_size())
+        uncached.write_mask_into(
+            expected.data_ptr(), expected.numel(), expected.element_size()
+        )
+        assert mask.tolist() == expected.tolist()
+
+
+def test_from_state():
+    vocabulary = Vocabulary(4, {'"': [1], "a": [2], 'a"': [3]})
+    index = Index(r'"a*"', vocabulary)
+    guide = Guide(index)
+    guide.advance(1, return_tokens=False)
+    guide.advance(2, return_tokens=False)
+
+    resumed = Guide.from_state(index, guide.get_state(), max_tokens=1)
+    assert resumed.get_state() == guide.get_state()
+    assert sorted(resumed.get_tokens()) == [1, 3]
+    resumed.advance(3, return_tokens=False)
+    assert resumed.is_finished()
+
+    with pytest.raises(ValueError, match="not found"):
+        Guide.from_state(index, 1000)
+
+
+def test_propose_and_commit():
+    vocabulary = Vocabulary(4, {'"': [1], "a": [2], 'a"': [3]})
+    index = Index(r'"a*"', vocabulary)
+    guide = Guide(index)
+
+    proposal = guide.propose(1)
+    assert guide.get_state() == index.get_initial_state()
+    assert proposal.get_token_id() == 1
+    assert sorted(proposal.get_allowed_tokens()) == [1, 2, 3]
+    assert not proposal.allows_eos()
+    assert not proposal.is_violation()
+
+    assert proposal.commit(guide) == proposal.get_next_state()
+    assert guide.get_state() == proposal.get_next_state()
+    with pytest.raises(ValueError, match="outdated"):
+        proposal.commit(guide)
+
+    assert guide.propose(3).allows_eos()
+    with pytest.raises(ValueError):
+        Guide(index).propose(2)
+
+
+def test_biases():
+    vocabulary = Vocabulary(4, {'"': [1], "a": [2], 'a"': [3], " ": [5]})
+    index = Index(r'"[a ]*"', vocabulary)
+
+    guide = Guide(index, token_biases={5: -1.0}, shorter_bias=0.5)
+    assert guide.get_biases() == [(1, 0.5)]
+    guide.advance(1)
+    # Closing the string gets closer to the final state, unlike its content.
+    assert guide.get_biases() == [(1, 0.5), (3, 0.5), (5, -1.0)]
+    guide.advance(3)
+    assert guide.get_biases() == []
+
+    assert Guide(index).get_biases() == []
+
+
+def test_text():
+    vocabulary = Vocabulary(4, {'"': [1], "a": [2], 'a"': [3]})
+    index = Index(r'"a*"', vocabulary)
+
+    with pytest.raises(ValueError, match="doesn't record the consumed tokens"):
+        Guide(index).get_text(vocabulary)
+
+    guide = Guide(index, record_history=True)
+    assert guide.get_text(vocabulary) == ""
+    guide.advance(1)
+    guide.advance(2)
+    guide.advance(3)
+    assert guide.get_text(vocabulary) == '"aa"'
+
+    guide.rollback_state(1)
+    assert guide.get_text(vocabulary) == '"a'
+    assert Guide.resume(index, guide.export_state()).get_text(vocabulary) == '"a'
+
+
+def test_current_path():
+    vocabulary = Vocabulary(6, {"{": [1], '"a"': [2], ":": [3], "1": [4], "}": [5]})
+    index = Index(r'\{"a":[0-9]\}', vocabulary)
+
+    guide = Guide(index, vocabulary=vocabulary)
+    assert guide.get_current_path() is None
+    for token_id in [1, 2, 3]:
+        guide.advance(token_id)
+    assert guide.get_current_path() == "a"
+    guide.advance(4)
+    guide.advance(5)
+    assert guide.get_current_path() is None
+
+    assert Guide(index).get_current_path() is None
+
+
+def test_guides_in_threads(index):
+    from concurrent.futures import ThreadPoolExecutor
+
+    def generate(_):
+        guide = Guide(index)
+        tokens = guide.advance(guide.get_tokens()[0])
+        return guide.is_finished(), tokens
+
+    with ThreadPoolExecutor(max_workers=8) as executor:
+        results = list(executor.map(generate, range(64)))
+
+    assert all(finished for finished, _ in results)
+    assert all(tokens == results[0][1] for _, tokens in results)
@@ -1,5 +1,10 @@
 """Tests for package imports to catch import/module registration issues."""
 
+import os
+import sys
+
+import pytest
+
 
 def test_import_outlines_core():
     import outlines_core
@@ -10,6 +15,15 @@ def test_import_outlines_core():
     assert hasattr(outlines_core, "json_schema")
 
 
+def test_capabilities():
+    from outlines_core import capabilities
+
+    features = capabilities()
+    assert features["python_bindings"] is True
+    assert all(isinstance(value, bool) for value in features.values())
+    assert {"hub", "parallel", "schema_validation"} <= features.keys()
+
+
 def test_import_json_schema_module():
     from outlines_core import json_schema
 
@@ -39,6 +53,12 @@ def test_import_from_json_schema():
     assert callable(build_regex_from_schema)
 
 
+def test_import_regex_module():
+    from outlines_core.regex import sample
+
+    assert callable(sample)
+
+
 def test_import_main_classes():
     from outlines_core import Guide, Index, Vocabulary
 
@@ -75,3 +95,31 @@ def test_import_json_schema_from_rust_extension():
 
     assert hasattr(json_schema, "BOOLEAN")
     assert hasattr(json_schema, "build_regex_from_schema")
+
+
+def test_import_in_subinterpreter_fails():
+    interpreters = pytest.importorskip("_xxsubinterpreters")
+    import outlines_core  # noqa: F401
+
+    interpreter = interpreters.create()
+    try:
+        with pytest.raises(interpreters.RunFailedError, match="ImportError"):
+            interpreters.run_string(
+                interpreter,
+                f"import sys; sys.path = {sys.path!r}; import outlines_core",
+            )
+    finally:
+        interpreters.destroy(interpreter)
+
+
+@pytest.mark.skipif(not hasattr(os, "fork"), reason="Requires os.fork")
+def test_fork_after_import():
+    from outlines_core import Index, Vocabulary
+
+    vocabulary = Vocabulary(3, {"1": [1], "2": [2]})
+    pid = os.fork()
+    if pid == 0:
+        index = Index("[12]+", vocabulary)
+        os._exit(0 if index.get_allowed_tokens(index.get_initial_state()) else 1)
+    _, status = os.waitpid(pid, 0)
+    assert os.waitstatus_to_exitcode(status) == 0
@@ -1,10 +1,12 @@
 import copy
 import gc
+import json
 import pickle
 
 import pytest
 
 from outlines_core import Index, Vocabulary
+from outlines_core.json_schema import build_regex_from_schema
 
 
 @pytest.fixture(scope="session")
@@ -42,12 +44,168 @@ def test_basic_interface(index):
     assert index.get_transitions() == expected_transitions
 
 
+def test_deterministic_order():
+    vocabulary = Vocabulary(9, {str(digit): [digit] for digit in range(8, 0, -1)})
+    index = Index(r"[1-8]{1,3}", vocabulary)
+
+    state = index.get_initial_state()
+    assert index.get_allowed_tokens(state) == list(range(1, 9))
+    transitions = index.get_transitions()
+    assert list(transitions) == sorted(transitions)
+    for map in transitions.values():
+        assert list(map) == sorted(map)
+
+
+def test_mask_delta(index):
+    state = index.get_initial_state()
+    next_state = index.get_next_state(state, 1)
+    # Tokens 1 and 2 are replaced with the end-of-sequence token 3.
+    assert index.get_mask_delta(state, next_state) == ([1 << 3], [1 << 1 | 1 << 2])
+    assert index.get_mask_delta(next_state, next_state) == ([0], [0])
+    assert index.get_mask_delta(state, 1000) is None
+
+
+def test_state_for_text():
+    vocabulary = Vocabulary(4, {'"': [1], "a": [2], 'a"': [3]})
+    index = Index(r'"a*"', vocabulary)
+
+    state = index.get_next_state(index.get_initial_state(), 1)
+    state = index.get_next_state(state, 2)
+    assert index.state_for_text(vocabulary, '"aa') == state
+    assert index.is_final_state(index.state_for_text(vocabulary, '"a"'))
+    assert index.state_for_text(vocabulary, "b") is None
+
+
+def test_complement():
+    vocabulary = Vocabulary(4, {"a": [1], "b": [2], "ab": [3]})
+    index = Index("ab?", vocabulary)
+
+    complement = index.complement(vocabulary, 2)
+    state = complement.get_initial_state()
+    assert complement.is_final_state(state)
+    assert complement.get_next_state(state, 3) is not None
+    assert not complement.is_final_state(complement.get_next_state(state, 3))
+    assert complement.is_final_state(complement.get_next_state(state, 2))
+
+    with pytest.raises(ValueError, match="complement is empty"):
+        Index("[ab]*", vocabulary).complement(vocabulary, 2)
+
+
+def test_verify_vocabulary(index):
+    index.verify_vocabulary(Vocabulary(3, {"1": [1], "2": [2]}))
+    pickle.loads(pickle.dumps(index)).verify_vocabulary(
+        Vocabulary(3, {"1": [1], "2": [2]})
+    )
+
+    with pytest.raises(ValueError, match="end-of-sequence token 4 instead of 3"):
+        index.verify_vocabulary(Vocabulary(4, {"1": [1], "2": [2]}))
+    with pytest.raises(ValueError, match="fingerprint"):
+        index.verify_vocabulary(Vocabulary(3, {"2": [1], "1": [2]}))
+
+
+def test_extend_vocabulary(index):
+    before = Vocabulary(3, {"1": [1], "2": [2]})
+    after = Vocabulary(3, {"1": [1], "2": [2], "7": [5], "<lora>": [6]})
+    extended = index.extend_vocabulary(before, after)
+    assert extended == Index(r"[1-9]", after)
+    assert extended.get_allowed_tokens(extended.get_initial_state()) == [1, 2, 5]
+    assert index.get_allowed_tokens(index.get_initial_state()) == [1, 2]
+
+    with pytest.raises(ValueError, match="doesn't extend"):
+        index.extend_vocabulary(before, Vocabulary(3, {"1": [1]}))
+    with pytest.raises(ValueError, match="fingerprint"):
+        extended.extend_vocabulary(before, after)
+
+
+def test_allowed_bool_mask(index):
+    np = pytest.importorskip("numpy")
+    init_state = index.get_initial_state()
+    mask = index.get_allowed_bool_mask(init_state)
+    assert mask.dtype == np.bool_
+    assert mask.tolist() == [False, True, True]
+
+    # Logits padded past the vocabulary.
+    out = np.ones(8, dtype=np.uint8)
+    assert index.get_allowed_bool_mask(init_state, out=out) is out
+    assert out.tolist() == [0, 1, 1, 0, 0, 0, 0, 0]
+    assert index.get_allowed_bool_mask(1000) is None
+
+    with pytest.raises(ValueError, match="dtype"):
+        index.get_allowed_bool_mask(init_state, out=np.zeros(4, dtype=np.int32))
+    with pytest.raises(ValueError, match="shape"):
+        index.get_allowed_bool_mask(init_state, out=np.zeros(2, dtype=np.uint8))
+    with pytest.raises(ValueError, match="contiguous"):
+        index.get_allowed_bool_mask(init_state, out=np.zeros(8, dtype=np.uint8)[::2])
+
+
+def test_sampling_hints():
+    schema = json.dumps(
+        {
+            "type": "object",
+            "properties": {
+                "id": {"type": "integer"},
+                "note": {"type": "string", "x-outlines-temperature": 0.8},
+            },
+            "required": ["id", "note"],
+        }
+    )
+    vocabulary = Vocabulary(
+        3, {c: [i + 4] for i, c in enumerate('{}":,0123456789abnoteid')}
+    )
+    index = Index(build_regex_from_schema(schema, whitespace_pattern=""), vocabulary)
+    hints = index.get_sampling_hints(vocabulary, schema)
+    assert hints["hints"] == {"note": {"temperature": 0.8}}
+    assert set(hints["states"].values()) == {"note"}
+
+
+def test_summarized_repr(index):
+    assert str(index).startswith("Index object with 2 states, 3 transitions")
+    assert repr(index).startswith("Index { initial_state: 12")
+    assert index.dump_full().startswith("Index object with transitions:")
+
+    vocabulary = Vocabulary(3, {str(i): [i + 4] for i in range(100)})
+    assert len(str(vocabulary).splitlines()) == 10
+    assert len(vocabulary.dump_full().splitlines()) == 101
+
+
+def test_byte_classes(index):
+    classes = index.get_byte_classes()
+    assert len(classes) == 256
+    assert classes[ord("1")] == classes[ord("9")]
+    assert classes[ord("0")] != classes[ord("1")]
+
+    representatives = index.get_class_representatives()
+    assert len(representatives) == max(classes) + 1
+    for class_id, byte in enumerate(representatives):
+        assert classes[byte] == class_id
+    assert ord("1") in representatives
+
+
+def test_footprint(index):
+    footprint = index.get_footprint()
+    assert footprint["transitions"] > 0
+    parts = [value for key, value in footprint.items() if key != "total"]
+    assert footprint["total"] == sum(parts)
+
+
 def test_pickling(index):
     serialized = pickle.dumps(index)
     deserialized = pickle.loads(serialized)
     assert deserialized == index
 
 
+def test_unpickling_unversioned_data():
+    # Index pickled before the format was versioned, with a transition and the EOS token.
+    index = Index.from_binary(b"\x00\x01\x01\x02\x00\x01\x01\x01\x01\x01\x02\x01\x02\x03")
+    assert index.get_initial_state() == 0
+    assert index.get_final_states() == {1}
+    assert index.get_transitions() == {0: {1: 1}, 1: {2: 1}}
+    assert pickle.loads(pickle.dumps(index)) == index
+
+    with pytest.raises(ValueError, match="unsupported format version"):
+        Index.from_binary(b"\xff\x02")
+
+
 def test_deepcopy(index):
     index2 = copy.deepcopy(index)
     assert index2 == index
@@ -62,3 +220,49 @@ def test_deepcopy(index):
     assert is_deleted
 
     assert copy_index2 == index
+
+
+def test_hash_and_copy(index):
+    index2 = copy.copy(index)
+    assert index2 == index
+    assert hash(index2) == hash(index)
+    assert {index: "cached"}[copy.deepcopy(index)] == "cached"
+
+    other = Index("[1-9]{2}", Vocabulary(3, {"1": [1], "2": [2]}))
+    assert other != index
+    assert hash(other) != hash(index)
+
+
+def test_index_progress_callback():
+    vocabulary = Vocabulary(3, {"1": [1], "2": [2]})
+    reports = []
+
+    def on_progress(states_explored, frontier_size, elapsed):
+        reports.append((states_explored, frontier_size))
+        assert elapsed >= 0
+
+    index = Index("[12]{5}", vocabulary, on_progress=on_progress, progress_interval=2)
+    assert index == Index("[12]{5}", vocabulary)
+    assert [explored for explored, _ in reports] == [2, 4, 6]
+    assert reports[-1][1] == 0
+
+    with pytest.raises(ValueError, match="cancelled"):
+        Index("[12]{5}", vocabulary, on_progress=lambda *_: False)
+
+    def failing(*_):
+        raise RuntimeError("Stop")
+
+    with pytest.raises(RuntimeError, match="Stop"):
+        Index("[12]{5}", vocabulary, on_progress=failing, progress_interval=1)
+
+
+def test_index_resource_limits():
+    vocabulary = Vocabulary(3, {"1": [1], "2": [2]})
+    assert Index("[12][12][12]", vocabulary, max_states=4, max_build_ms=60_000) == Index(
+        "[12][12][12]", vocabulary
+    )
+
+    with pytest.raises(ValueError, match="limit of 4 states"):
+        Index("[12][12][12][12]", vocabulary, max_states=4)
+    with pytest.raises(ValueError, match="bytes of masks"):
+        Index("[12][12][12]", vocabulary, max_masks_bytes=8, on_progress=lambda *_: True)
@@ -4,7 +4,15 @@ import re
 import pytest
 from pydantic import BaseModel
 
-from outlines_core.json_schema import build_regex_from_schema
+from outlines_core.json_schema import (
+    build_jsonl_regex_from_schema,
+    build_regex_from_schema,
+    build_regex_from_schema_with_report,
+    build_regex_from_schemas,
+    describe_regex,
+    partial_instance,
+    repair,
+)
 
 
 def test_build_regex_from_json_schema():
@@ -24,6 +32,347 @@ def test_build_regex_from_json_schema():
     assert re.fullmatch(regex, expected)
 
 
+def test_build_jsonl_regex_from_json_schema():
+    class Foo(BaseModel):
+        foo: int
+
+    schema = json.dumps(Foo.model_json_schema())
+
+    regex = build_jsonl_regex_from_schema(schema, min_lines=2, max_lines=3)
+    assert re.fullmatch(regex, '{"foo": 1}\n{"foo": 2}\n')
+    assert not re.fullmatch(regex, '{"foo": 1}')
+    assert not re.fullmatch(regex, '{"foo": 1}\n{"foo": 2}\n{"foo": 3}\n{"foo": 4}')
+
+    with pytest.raises(ValueError):
+        build_jsonl_regex_from_schema(schema, min_lines=3, max_lines=2)
+
+
+def test_build_regex_from_schemas():
+    schemas = [
+        json.dumps(
+            {
+                "title": "weather",
+                "type": "object",
+                "properties": {"city": {"type": "string"}},
+            }
+        ),
+        json.dumps({"type": "integer"}),
+    ]
+
+    regex = build_regex_from_schemas(schemas)
+    assert re.fullmatch(regex, '{"city": "Paris"}')
+    assert re.fullmatch(regex, "42")
+
+    regex = build_regex_from_schemas(schemas, union="discriminator", canonical=True)
+    assert re.fullmatch(regex, '{"schema":"weather","value":{"city":"Paris"}}')
+    assert re.fullmatch(regex, '{"schema":"1","value":42}')
+    assert not re.fullmatch(regex, "42")
+
+    with pytest.raises(ValueError, match="share the discriminator 'weather'"):
+        build_regex_from_schemas([schemas[0], schemas[0]], union="discriminator")
+    with pytest.raises(ValueError, match="Invalid union"):
+        build_regex_from_schemas(schemas, union="tagged")
+
+
+def test_build_canonical_regex_from_json_schema():
+    schema = json.dumps(
+        {
+            "type": "object",
+            "properties": {"name": {"type": "string"}, "age": {"type": "integer"}},
+            "required": ["name", "age"],
+        }
+    )
+
+    regex = build_regex_from_schema(schema, canonical=True)
+    assert re.fullmatch(regex, '{"age":42,"name":"Bob"}')
+    assert not re.fullmatch(regex, '{"name":"Bob","age":42}')
+    assert not re.fullmatch(regex, '{"age": 42, "name": "Bob"}')
+
+
+def test_build_regex_with_keyword_policy():
+    schema = json.dumps(
+        {
+            "type": "object",
+            "properties": {"age": {"type": "integer", "not": {"const": 0}}},
+        }
+    )
+
+    lenient = build_regex_from_schema(schema)
+
+    with pytest.warns(UserWarning, match="'not'"):
+        assert build_regex_from_schema(schema, keyword_policy="warn") == lenient
+
+    with pytest.raises(ValueError, match="'not'"):
+        build_regex_from_schema(schema, keyword_policy="strict")
+
+    with pytest.raises(ValueError, match="Invalid keyword policy"):
+        build_regex_from_schema(schema, keyword_policy="unknown")
+
+
+def test_build_regex_with_multiple_of():
+    regex = build_regex_from_schema(json.dumps({"type": "number", "multipleOf": 0.01}))
+    assert re.fullmatch(regex, "-3.25")
+    assert not re.fullmatch(regex, "3.125")
+
+    regex = build_regex_from_schema(json.dumps({"type": "integer", "multipleOf": 100}))
+    assert re.fullmatch(regex, "1200")
+    assert not re.fullmatch(regex, "1250")
+
+    with pytest.raises(ValueError, match="only supports powers of ten"):
+        build_regex_from_schema(json.dumps({"type": "integer", "multipleOf": 2}))
+
+
+def test_build_regex_with_dependencies():
+    schema = json.dumps(
+        {
+            "type": "object",
+            "properties": {"card": {"type": "integer"}, "address": {"type": "string"}},
+            "dependentRequired": {"card": ["address"]},
+        }
+    )
+    regex = build_regex_from_schema(schema, whitespace_pattern="", keyword_policy="strict")
+    assert re.fullmatch(regex, '{"address":"a"}')
+    assert re.fullmatch(regex, '{"card":1,"address":"a"}')
+    assert not re.fullmatch(regex, '{"card":1}')
+
+
+def test_build_regex_with_property_names():
+    schema = json.dumps(
+        {
+            "type": "object",
+            "additionalProperties": {"type": "integer"},
+            "propertyNames": {"pattern": "^[a-z]+_id$"},
+        }
+    )
+    regex = build_regex_from_schema(schema, whitespace_pattern="", keyword_policy="strict")
+    assert re.fullmatch(regex, '{"user_id":1,"org_id":2}')
+    assert not re.fullmatch(regex, '{"user":1}')
+
+    schema = json.dumps(
+        {
+            "type": "object",
+            "properties": {"id": {"type": "integer"}, "Name": {"type": "string"}},
+            "propertyNames": {"pattern": "^[a-z]+$"},
+        }
+    )
+    regex = build_regex_from_schema(schema, whitespace_pattern="", keyword_policy="strict")
+    assert re.fullmatch(regex, '{"id":1}')
+    assert not re.fullmatch(regex, '{"id":1,"Name":"a"}')
+
+
+def test_schema_options_are_shared():
+    schema = json.dumps(
+        {
+            "type": "object",
+            "properties": {"sku": {"type": "string", "format": "sku"}},
+            "required": ["sku"],
+        }
+    )
+    options = {"canonical": True, "formats": {"sku": "[A-Z]{3}-[0-9]{4}"}}
+
+    regex = build_regex_from_schema(schema, **options)
+    assert re.fullmatch(regex, '{"sku":"ABC-1234"}')
+    assert not re.fullmatch(regex, '{"sku": "ABC-1234"}')
+
+    regex = build_jsonl_regex_from_schema(schema, min_lines=2, **options)
+    assert re.fullmatch(regex, '{"sku":"ABC-1234"}\n{"sku":"XYZ-0001"}\n')
+
+    with pytest.raises(ValueError, match="sku"):
+        build_jsonl_regex_from_schema(schema)
+
+    with pytest.raises(TypeError, match="Unexpected schema option"):
+        build_regex_from_schema(schema, unknown=True)
+
+
+def test_default_max_lengths():
+    schema = json.dumps(
+        {
+            "type": "object",
+            "properties": {
+                "name": {"type": "string"},
+                "tags": {"type": "array", "items": {"type": "integer"}},
+                "meta": {"type": "object"},
+            },
+            "required": ["name", "tags", "meta"],
+        }
+    )
+    options = {"default_max_string": 3, "default_max_items": 2, "default_max_props": 1}
+
+    regex = build_regex_from_schema(schema, whitespace_pattern="", **options)
+    assert re.fullmatch(regex, '{"name":"abc","tags":[1,2],"meta":{"k":1}}')
+    assert not re.fullmatch(regex, '{"name":"abcd","tags":[],"meta":{}}')
+    assert not re.fullmatch(regex, '{"name":"","tags":[1,2,3],"meta":{}}')
+    assert not re.fullmatch(regex, '{"name":"","tags":[],"meta":{"k":1,"l":2}}')
+
+    regex = build_regex_from_schema(schema, whitespace_pattern="")
+    assert re.fullmatch(regex, '{"name":"abcd","tags":[1,2,3],"meta":{"k":1,"l":2}}')
+
+
+def test_max_regex_len():
+    schema = json.dumps(
+        {"type": "object", "properties": {"tags": {"type": "array", "items": {}}}}
+    )
+
+    regex = build_regex_from_schema(schema)
+    assert build_regex_from_schema(schema, max_regex_len=len(regex)) == regex
+    message = "exceeds the limit of 100 bytes at '/properties/tags/items'"
+    with pytest.raises(ValueError, match=message):
+        build_regex_from_schema(schema, max_regex_len=100)
+
+
+def test_pattern_anchoring():
+    schema = json.dumps({"type": "string", "pattern": "^ab"})
+
+    regex = build_regex_from_schema(schema)
+    assert re.fullmatch(regex, '"ab"')
+    assert not re.fullmatch(regex, '"abc"')
+
+    regex = build_regex_from_schema(schema, pattern_anchoring="respect_existing")
+    assert re.fullmatch(regex, '"abc"')
+    assert not re.fullmatch(regex, '"cab"')
+
+    with pytest.raises(ValueError, match="Invalid pattern anchoring"):
+        build_regex_from_schema(schema, pattern_anchoring="none")
+
+
+def test_enum_precedence():
+    schema = json.dumps(
+        {"type": "string", "format": "date", "enum": ["2024-01-31", "tomorrow"]}
+    )
+    with pytest.warns(UserWarning, match="'/enum/1' violates keyword 'format'"):
+        regex = build_regex_from_schema(schema, keyword_policy="warn")
+    assert re.fullmatch(regex, '"2024-01-31"')
+    assert not re.fullmatch(regex, '"tomorrow"')
+
+    schema = json.dumps({"type": "string", "format": "date", "enum": ["tomorrow"]})
+    with pytest.raises(ValueError, match="No value of 'enum'"):
+        build_regex_from_schema(schema)
+
+
+def test_describe_regex():
+    schema = json.dumps(
+        {
+            "type": "object",
+            "properties": {
+                "name": {"type": "string", "maxLength": 8},
+                "tags": {"type": "array", "items": {"type": "integer"}},
+            },
+            "required": ["name"],
+        }
+    )
+    spec = describe_regex(build_regex_from_schema(schema))
+    assert spec["type"] == "object"
+    assert spec["fields"] == [
+        {
+            "name": "name",
+            "required": True,
+            "type": "string",
+            "min_length": 0,
+            "max_length": 8,
+        },
+        {
+            "name": "tags",
+            "required": False,
+            "type": "array",
+            "items": {"type": "integer"},
+            "min_items": 0,
+            "max_items": None,
+        },
+    ]
+
+    bounded = json.dumps({"type": "integer", "minimum": 0, "maximum": 120})
+    assert describe_regex(build_regex_from_schema(bounded)) == {
+        "type": "integer",
+        "minimum": 0,
+        "maximum": 120,
+    }
+
+    assert describe_regex("[a-z]+") == {"type": "pattern", "regex": "[a-z]+"}
+
+    with pytest.raises(ValueError, match="Invalid regular expression"):
+        describe_regex("(")
+
+
+def test_partial_instance():
+    schema = json.dumps(
+        {
+            "type": "object",
+            "properties": {"id": {"type": "integer"}, "name": {"type": "string"}},
+            "additionalProperties": False,
+        }
+    )
+    partial = partial_instance(schema, json.dumps({"id": 42}))
+    regex = build_regex_from_schema(partial, whitespace_pattern="")
+    assert re.fullmatch(regex, '{"id":42,"name":"Bob"}')
+    assert not re.fullmatch(regex, '{"id":41,"name":"Bob"}')
+    assert not re.fullmatch(regex, '{"name":"Bob"}')
+
+    with pytest.raises(ValueError, match="additionalProperties"):
+        partial_instance(schema, json.dumps({"age": 3}))
+    with pytest.raises(TypeError, match="JSON object"):
+        partial_instance(schema, "[42]")
+
+
+def test_repair():
+    schema = json.dumps(
+        {
+            "type": "object",
+            "properties": {"name": {"type": "string"}, "age": {"type": "integer"}},
+            "required": ["name", "age"],
+        }
+    )
+    repaired = repair('{"name": "Ada', schema)
+    assert repaired == {
+        "valid_len": 13,
+        "completion": '","age":0}',
+        "repaired": '{"name": "Ada","age":0}',
+    }
+    assert json.loads(repair('{"name":"Ada","age":36} Done!', schema)["repaired"]) == {
+        "name": "Ada",
+        "age": 36,
+    }
+
+
+def test_build_regex_from_schema_with_report():
+    schema = json.dumps(
+        {
+            "type": "object",
+            "properties": {
+                "tags": {"type": "array", "items": {"type": "integer", "not": {"const": 0}}},
+            },
+        }
+    )
+    regex, report = build_regex_from_schema_with_report(schema, default_max_items=4)
+    assert regex == build_regex_from_schema(schema, default_max_items=4)
+    assert report["keywords"] == {
+        "const": 1,
+        "items": 1,
+        "not": 1,
+        "properties": 1,
+        "type": 3,
+    }
+    assert report["warnings"] == [
+        {
+            "path": "/properties/tags/items",
+            "keyword": "not",
+            "kind": "ignored_keyword",
+        }
+    ]
+    assert report["normalizations"] == [
+        {
+            "path": "/properties/tags",
+            "keyword": "maxItems",
+            "kind": {"type": "default_bound", "value": 4},
+        }
+    ]
+    assert report["regex_length"] == len(regex)
+    assert report["nfa_states"] > 0
+    assert report["generation_seconds"] >= 0
+
+    with pytest.warns(UserWarning, match="Keyword 'not'"):
+        build_regex_from_schema_with_report(schema, keyword_policy="warn")
+
+
 def test_invalid_json():
     with pytest.raises(
         TypeError,
@@ -74,6 +74,10 @@ def test_interface_torch():
     ):
         fill_next_token_bitmask(None, mask_batch2)
 
+    strided_mask = torch.full((1, 2 * mask.shape[1]), -1, dtype=torch.int32)[:, ::2]
+    with pytest.raises(ValueError, match="Mask array must be contiguous"):
+        fill_next_token_bitmask(None, strided_mask)
+
 
 @pytest.mark.no_cover
 def test_interface_numpy():
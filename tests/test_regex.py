@@ -0,0 +1,46 @@
+import re
+
+import pytest
+
+from outlines_core import Index, Vocabulary
+from outlines_core.regex import build_contains_regex, sample
+
+
+def test_sample_regex():
+    regex = r"[a-z]{2,4}@example\.com"
+    samples = sample(regex, 10, max_len=32, seed=42)
+    assert len(samples) == 10
+    assert all(re.fullmatch(regex, s) for s in samples)
+    assert samples == sample(regex, 10, max_len=32, seed=42)
+
+
+def test_sample_regex_without_match_within_bound():
+    assert sample("a{5}", 3, max_len=4, seed=0) == []
+
+
+def test_sample_index():
+    vocabulary = Vocabulary(3, {"1": [1], "2": [2]})
+    index = Index("[12]{1,3}", vocabulary)
+    for tokens in sample(index, 5, max_len=3, seed=1):
+        state = index.get_initial_state()
+        for token_id in tokens:
+            state = index.get_next_state(state, token_id)
+        assert index.is_final_state(state)
+
+
+def test_sample_wrong_type():
+    with pytest.raises(TypeError, match="Expected a regex string or an Index"):
+        sample(1)
+
+
+def test_build_contains_regex():
+    regex = build_contains_regex(["[1]", "[2]"], alphabet=r"[^\n]")
+    assert re.fullmatch(regex, "As shown in [2], it works.")
+    assert not re.fullmatch(regex, "As shown in [3], it works.")
+    assert not re.fullmatch(regex, "See [1]\n")
+
+    regex = build_contains_regex(["Paris"], case_insensitive=True)
+    assert re.fullmatch(regex, "The capital is PARIS.")
+
+    with pytest.raises(ValueError, match="substrings"):
+        build_contains_regex([])
@@ -3,7 +3,7 @@ import pickle
 
 import pytest
 
-from outlines_core import Vocabulary
+from outlines_core import Index, Vocabulary
 
 
 @pytest.fixture(scope="session")
@@ -95,17 +95,103 @@ def test_insert_eos_token(vocabulary):
         vocabulary.insert("eos-token", 3)
 
 
+def test_items_and_dict_export():
+    vocabulary = Vocabulary(0, {"c": [4, 1], "b": [2], "a": [3]})
+    assert vocabulary.items() == [(b"c", [4, 1]), (b"b", [2]), (b"a", [3])]
+    assert vocabulary.keys() == [b"c", b"b", b"a"]
+    assert list(vocabulary.to_dict().items()) == vocabulary.items()
+    assert Vocabulary(0, vocabulary.to_dict()) == vocabulary
+
+    assert vocabulary.get_token(4) == b"c"
+    assert vocabulary.get_token(0) is None
+
+
+def test_explain():
+    vocabulary = Vocabulary(0, {"Ġal": [1]})
+    assert vocabulary.explain("Ġal") == {
+        "token": "Ġal",
+        "bytes": "Ġal".encode(),
+        "token_ids": [1],
+        "steps": [("Ġal", "Ġal".encode(), "utf8")],
+    }
+    assert vocabulary.explain("al")["token_ids"] == []
+
+
+def test_test_vocab():
+    vocabulary = Vocabulary.test_vocab("gpt2-mini")
+    assert vocabulary == Vocabulary.test_vocab("gpt2-mini")
+    assert vocabulary.explain("Ġthe")["bytes"] == b" the"
+    assert Vocabulary.test_vocab("llama-mini").get_eos_token_id() == 2
+
+    # Letters and the words "ing", "the", "er", "true", "false" and "null".
+    index = Index(r"[a-z]+", Vocabulary.test_vocab("ascii-json"))
+    assert len(index.get_allowed_tokens(index.get_initial_state())) == 32
+
+    with pytest.raises(ValueError, match="Invalid test vocabulary"):
+        Vocabulary.test_vocab("gpt2")
+
+
 def test_from_pretrained():
     vocabulary = Vocabulary.from_pretrained("gpt2")
     assert vocabulary.get_eos_token_id() == 50256
 
 
+def test_from_pretrained_with_progress():
+    reports = []
+    vocabulary = Vocabulary.from_pretrained(
+        "gpt2", on_progress=lambda done, total: reports.append((done, total))
+    )
+    assert vocabulary == Vocabulary.from_pretrained("gpt2")
+    total = reports[0][1]
+    assert all(t == total for _, t in reports)
+    assert max(done for done, _ in reports) == total
+
+
+def test_from_pretrained_with_stop_tokens():
+    model = "hf-internal-testing/llama-tokenizer"
+    vocabulary = Vocabulary.from_pretrained(model, stop_tokens=["<s>", 2])
+    assert vocabulary.get_stop_token_ids() == [1]
+    assert vocabulary.get_token(1) is None
+
+    with pytest.raises(ValueError, match="not found"):
+        Vocabulary.from_pretrained(model, stop_tokens=["<|im_end|>"])
+
+
+def test_stop_tokens(vocabulary):
+    vocabulary = copy.deepcopy(vocabulary)
+    vocabulary.insert_stop_token(7)
+    assert vocabulary.get_stop_token_ids() == [7]
+    assert pickle.loads(pickle.dumps(vocabulary)).get_stop_token_ids() == [7]
+
+    with pytest.raises(ValueError, match="EOS token should not be inserted"):
+        vocabulary.insert_stop_token(vocabulary.get_eos_token_id())
+
+
 def test_pickling(vocabulary):
     serialized = pickle.dumps(vocabulary)
     deserialized = pickle.loads(serialized)
     assert deserialized == vocabulary
 
 
+def test_unpickling_unversioned_data():
+    # Vocabulary pickled before the format was versioned, with the EOS token 2 and `a`.
+    vocabulary = Vocabulary.from_binary(b"\x02\x01\x01a\x01\x01")
+    assert vocabulary.get_eos_token_id() == 2
+    assert vocabulary.get("a") == [1]
+    assert pickle.loads(pickle.dumps(vocabulary)) == vocabulary
+
+
+def test_hash_and_copy(vocabulary):
+    vocabulary2 = copy.copy(vocabulary)
+    assert vocabulary2 == vocabulary
+    with pytest.raises(TypeError, match="unhashable"):
+        hash(vocabulary)
+
+    # Copies don't share tokens.
+    vocabulary2.insert("new", 4)
+    assert vocabulary2 != vocabulary
+
+
 def test_deepcopy(vocabulary):
     vocabulary2 = copy.deepcopy(vocabulary)
     assert vocabulary2 == vocabulary